@@ -0,0 +1,433 @@
+use std::fmt;
+
+use rustls::crypto::ring;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::PrivateKeyDer;
+use serde_yaml::Value;
+use x509_parser::parse_x509_certificate;
+use x509_parser::pem::Pem;
+
+use crate::http::{Request, Url};
+use crate::remote::ConnectOptions;
+use crate::report::Report;
+use crate::FileProcessor;
+
+/// Where certbot keeps the current certificate of each lineage.
+const CERTBOT_LIVE: &str = "/etc/letsencrypt/live";
+
+/// The files an ACME client keeps a certificate in.
+#[derive(Debug, PartialEq)]
+pub struct Files {
+    pub client: &'static str,
+    pub dir: String,
+    pub cert: String,
+    pub chain: Option<String>,
+    pub fullchain: Option<String>,
+    pub key: Option<String>,
+}
+
+impl Files {
+    /// The files of a certbot live directory, an acme.sh certificate directory,
+    /// or lego's files for a domain in `dir`, if `dir` has them.
+    fn find(processor: &impl FileProcessor, dir: &str, name: &str) -> Option<Files> {
+        let path = |file: &str| format!("{}/{}", dir.trim_end_matches('/'), file);
+        let existing = |file: String| Some(file).filter(|file| processor.is_file(file));
+        if processor.is_file(&path("cert.pem")) {
+            return Some(Files {
+                client: "certbot",
+                dir: String::from(dir),
+                cert: path("cert.pem"),
+                chain: existing(path("chain.pem")),
+                fullchain: existing(path("fullchain.pem")),
+                key: existing(path("privkey.pem")),
+            });
+        }
+        // acme.sh names its files after the domain, keeping ECDSA certificates in a `_ecc` directory
+        let domain = name.trim_end_matches("_ecc");
+        if processor.is_file(&path(&format!("{}.cer", domain))) {
+            return Some(Files {
+                client: "acme.sh",
+                dir: String::from(dir),
+                cert: path(&format!("{}.cer", domain)),
+                chain: existing(path("ca.cer")),
+                fullchain: existing(path("fullchain.cer")),
+                key: existing(path(&format!("{}.key", domain))),
+            });
+        }
+        // lego keeps every domain's files side by side, its certificate including the issuer
+        if processor.is_file(&path(&format!("{}.issuer.crt", domain))) {
+            return Some(Files {
+                client: "lego",
+                dir: String::from(dir),
+                cert: path(&format!("{}.crt", domain)),
+                chain: existing(path(&format!("{}.issuer.crt", domain))),
+                fullchain: None,
+                key: existing(path(&format!("{}.key", domain))),
+            });
+        }
+        None
+    }
+
+    /// Finds the files for a directory, or for a domain in the places certbot,
+    /// acme.sh and lego keep them by default.
+    pub fn locate(processor: &impl FileProcessor, target: &str) -> Option<Files> {
+        if processor.is_dir(target) {
+            let name = target.trim_end_matches('/').rsplit('/').next()?;
+            return Files::find(processor, target, name);
+        }
+        let mut dirs = vec![format!("{}/{}", CERTBOT_LIVE, target)];
+        if let Some(home) = processor.var("HOME") {
+            dirs.push(format!("{}/.acme.sh/{}_ecc", home, target));
+            dirs.push(format!("{}/.acme.sh/{}", home, target));
+        }
+        dirs.into_iter()
+            .filter(|dir| processor.is_dir(dir))
+            .find_map(|dir| Files::find(processor, &dir, target))
+            .or_else(|| Files::find(processor, ".lego/certificates", target))
+    }
+}
+
+/// What was found about an ACME certificate, before its report.
+pub enum Found {
+    /// The files of an ACME client, and how they fit together.
+    Files {
+        files: Files,
+        chain: usize,
+        /// Whether the full chain is the certificate followed by the chain.
+        fullchain: Option<bool>,
+        /// Whether the private key belongs to the certificate, or why that can't be told.
+        key: Option<Result<bool, String>>,
+    },
+    /// An order on an ACME server, and where its certificate is.
+    Order {
+        url: String,
+        status: String,
+        error: Option<String>,
+        identifiers: Vec<String>,
+        expires: Option<String>,
+        certificate: Option<String>,
+    },
+}
+
+/// An ACME certificate and its chain, and what was found along the way.
+pub struct Acme {
+    pub found: Found,
+    pub report: Report,
+}
+
+/// Decodes the certificate of an ACME order URL, an ACME client's directory,
+/// or a domain an ACME client keeps a certificate for.
+pub fn inspect(
+    processor: &impl FileProcessor,
+    target: &str,
+    connect: &ConnectOptions,
+) -> Result<Acme, Box<dyn std::error::Error>> {
+    if target.starts_with("https://") || target.starts_with("http://") {
+        return order(processor, target, connect);
+    }
+    let files = Files::locate(processor, target).ok_or_else(|| {
+        format!(
+            "Error: could not find an ACME certificate for {}, give a certbot live directory, an acme.sh directory or an ACME order URL.",
+            target
+        )
+    })?;
+
+    let cert = certificates(&processor.read(&files.cert)?);
+    let leaf = cert
+        .first()
+        .cloned()
+        .ok_or_else(|| format!("Error: {} does not contain a certificate.", files.cert))?;
+    let chain = match &files.chain {
+        Some(chain) => certificates(&processor.read(chain)?),
+        None => cert[1..].to_vec(),
+    };
+    let fullchain = match &files.fullchain {
+        Some(fullchain) => {
+            let expected: Vec<&Vec<u8>> = std::iter::once(&leaf).chain(&chain).collect();
+            let actual = certificates(&processor.read(fullchain)?);
+            Some(actual.iter().collect::<Vec<_>>() == expected)
+        }
+        None => None,
+    };
+    let key = match &files.key {
+        Some(key) => Some(key_matches(&processor.read(key)?, &leaf)),
+        None => None,
+    };
+
+    let mut items = vec![(String::from("certificate"), leaf)];
+    let count = chain.len();
+    items.extend(
+        chain
+            .into_iter()
+            .enumerate()
+            .map(|(i, der)| (format!("chain [{}/{}]", i + 1, count), der)),
+    );
+    let mut report = Report::default();
+    report.add_named(&files.dir, Ok(items));
+    Ok(Acme {
+        found: Found::Files {
+            files,
+            chain: count,
+            fullchain,
+            key,
+        },
+        report,
+    })
+}
+
+/// Fetches an order, then its certificate once it has been issued. Let's
+/// Encrypt answers plain GETs for both, so no account key is needed.
+fn order(
+    processor: &impl FileProcessor,
+    target: &str,
+    connect: &ConnectOptions,
+) -> Result<Acme, Box<dyn std::error::Error>> {
+    let url = Url::parse(target)?;
+    let response = processor.http(&Request::get(url.clone()), connect)?;
+    let order: Value = serde_yaml::from_slice(&response.success(&url)?.body)
+        .map_err(|_| format!("Error: {} did not answer with an ACME order.", url))?;
+    let status = order["status"]
+        .as_str()
+        .ok_or_else(|| format!("Error: {} did not answer with an ACME order.", url))?;
+    let text = |value: &Value| value.as_str().map(String::from);
+    let identifiers = order["identifiers"]
+        .as_sequence()
+        .map(|identifiers| {
+            identifiers
+                .iter()
+                .filter_map(|id| text(&id["value"]))
+                .collect()
+        })
+        .unwrap_or_default();
+    let certificate = text(&order["certificate"]);
+
+    let mut report = Report::default();
+    if let Some(certificate) = &certificate {
+        let url = Url::parse(certificate)?;
+        let result = processor
+            .http(&Request::get(url.clone()), connect)
+            .and_then(|response| response.success(&url))
+            .map(|response| {
+                certificates(&response.body)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, der)| match i {
+                        0 => (String::from("certificate"), der),
+                        i => (format!("chain [{}]", i), der),
+                    })
+                    .collect()
+            });
+        report.add_named(certificate, result);
+    }
+    Ok(Acme {
+        found: Found::Order {
+            url: String::from(target),
+            status: String::from(status),
+            error: text(&order["error"]["detail"]),
+            identifiers,
+            expires: text(&order["expires"]),
+            certificate,
+        },
+        report,
+    })
+}
+
+/// The DER certificates in a PEM file.
+fn certificates(pem: &[u8]) -> Vec<Vec<u8>> {
+    Pem::iter_from_buffer(pem)
+        .filter_map(Result::ok)
+        .filter(|pem| pem.label == "CERTIFICATE")
+        .map(|pem| pem.contents)
+        .collect()
+}
+
+/// Whether a PEM private key is the one for a certificate's public key.
+fn key_matches(key_pem: &[u8], cert_der: &[u8]) -> Result<bool, String> {
+    let key = PrivateKeyDer::from_pem_slice(key_pem)
+        .map_err(|error| format!("could not be read: {}", error))?;
+    let key = ring::default_provider()
+        .key_provider
+        .load_private_key(key)
+        .map_err(|error| format!("could not be read: {}", error))?;
+    let public = key
+        .public_key()
+        .ok_or_else(|| String::from("has a public key that could not be worked out"))?;
+    let (_, cert) = parse_x509_certificate(cert_der)
+        .map_err(|error| format!("could not be compared: {}", error))?;
+    Ok(cert.public_key().raw == public.as_ref())
+}
+
+impl fmt::Display for Acme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.found {
+            Found::Files {
+                files,
+                chain,
+                fullchain,
+                key,
+            } => {
+                writeln!(f, "ACME client: {}", files.client)?;
+                writeln!(f, "Directory: {}", files.dir)?;
+                writeln!(f, "Certificate: {}", files.cert)?;
+                match &files.chain {
+                    Some(path) => writeln!(f, "Chain: {}, {} certificate(s)", path, chain)?,
+                    None => writeln!(f, "Chain: not found")?,
+                }
+                if let (Some(path), Some(fullchain)) = (&files.fullchain, fullchain) {
+                    match fullchain {
+                        true => writeln!(f, "Full chain: {}, the certificate then the chain", path)?,
+                        false => writeln!(
+                            f,
+                            "Full chain: {}, does not match the certificate and chain, servers using it send something else",
+                            path
+                        )?,
+                    }
+                }
+                match (&files.key, key) {
+                    (Some(path), Some(Ok(true))) => {
+                        writeln!(f, "Private key: {}, matches the certificate", path)?
+                    }
+                    (Some(path), Some(Ok(false))) => {
+                        writeln!(f, "Private key: {}, does not match the certificate", path)?
+                    }
+                    (Some(path), Some(Err(reason))) => {
+                        writeln!(f, "Private key: {}, {}", path, reason)?
+                    }
+                    _ => writeln!(f, "Private key: not found")?,
+                }
+            }
+            Found::Order {
+                url,
+                status,
+                error,
+                identifiers,
+                expires,
+                certificate,
+            } => {
+                writeln!(f, "ACME order: {}", url)?;
+                let meaning = match status.as_str() {
+                    "pending" => "its authorizations have not all been completed",
+                    "ready" => "waiting for the client to finalize it with a CSR",
+                    "processing" => "the CA is issuing the certificate",
+                    "valid" => "the certificate has been issued",
+                    "invalid" => "an authorization failed or the order expired",
+                    _ => "not a status RFC 8555 defines",
+                };
+                writeln!(f, "Status: {}, {}", status, meaning)?;
+                if let Some(error) = error {
+                    writeln!(f, "Error: {}", error)?;
+                }
+                writeln!(f, "Identifiers: {}", identifiers.join(", "))?;
+                if let Some(expires) = expires {
+                    writeln!(f, "Expires: {}", expires)?;
+                }
+                if let Some(certificate) = certificate {
+                    writeln!(f, "Certificate: {}", certificate)?;
+                }
+            }
+        }
+        write!(f, "{}", self.report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{inspect, Files};
+    use crate::http::Response;
+    use crate::pem;
+    use crate::remote::ConnectOptions;
+    use crate::test::{google_der, FakeProcessor};
+
+    const CERT: &str = include_str!("../resources/self-signed.crt");
+    const KEY: &str = include_str!("../resources/self-signed.key");
+
+    #[test]
+    fn should_find_a_certbot_lineage_by_its_domain() {
+        let mut processor = FakeProcessor::default();
+        processor.add_dir("/etc/letsencrypt/live/example.com", &[]);
+        processor.add_file("/etc/letsencrypt/live/example.com/cert.pem", CERT);
+        processor.add_file("/etc/letsencrypt/live/example.com/chain.pem", "");
+        processor.add_file("/etc/letsencrypt/live/example.com/fullchain.pem", CERT);
+        processor.add_file("/etc/letsencrypt/live/example.com/privkey.pem", KEY);
+
+        let acme = inspect(&processor, "example.com", &ConnectOptions::default()).unwrap();
+
+        assert!(acme.to_string().starts_with(
+            "ACME client: certbot\n\
+             Directory: /etc/letsencrypt/live/example.com\n\
+             Certificate: /etc/letsencrypt/live/example.com/cert.pem\n\
+             Chain: /etc/letsencrypt/live/example.com/chain.pem, 0 certificate(s)\n\
+             Full chain: /etc/letsencrypt/live/example.com/fullchain.pem, the certificate then the chain\n\
+             Private key: /etc/letsencrypt/live/example.com/privkey.pem, matches the certificate\n\
+             Critical extensions: basicConstraints\n"
+        ));
+    }
+
+    #[test]
+    fn should_say_when_an_acme_sh_key_does_not_match() {
+        let mut processor = FakeProcessor::default();
+        processor.add_dir("/home/me/.acme.sh/example.com_ecc", &[]);
+        processor.add_file(
+            "/home/me/.acme.sh/example.com_ecc/example.com.cer",
+            &pem::encode("CERTIFICATE", &google_der()),
+        );
+        processor.add_file("/home/me/.acme.sh/example.com_ecc/example.com.key", KEY);
+
+        let files = Files::locate(&processor, "/home/me/.acme.sh/example.com_ecc").unwrap();
+        assert_eq!(files.client, "acme.sh");
+        assert_eq!(files.chain, None);
+
+        let acme = inspect(
+            &processor,
+            "/home/me/.acme.sh/example.com_ecc",
+            &ConnectOptions::default(),
+        )
+        .unwrap();
+        assert!(acme.to_string().contains(
+            "Chain: not found\n\
+             Private key: /home/me/.acme.sh/example.com_ecc/example.com.key, does not match the certificate\n"
+        ));
+    }
+
+    #[test]
+    fn should_fetch_the_certificate_of_an_order() {
+        let mut processor = FakeProcessor::default();
+        let order = r#"{"status": "valid", "expires": "2024-01-08T00:00:00Z",
+            "identifiers": [{"type": "dns", "value": "example.com"}],
+            "certificate": "https://acme.example.net/cert/1"}"#;
+        processor.responses.insert(
+            String::from("https://acme.example.net/order/1/2"),
+            Response {
+                status: 200,
+                headers: Vec::new(),
+                body: order.as_bytes().to_vec(),
+            },
+        );
+        processor.responses.insert(
+            String::from("https://acme.example.net/cert/1"),
+            Response {
+                status: 200,
+                headers: Vec::new(),
+                body: CERT.as_bytes().to_vec(),
+            },
+        );
+
+        let acme = inspect(
+            &processor,
+            "https://acme.example.net/order/1/2",
+            &ConnectOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(acme.report.entries.len(), 1);
+        assert!(acme.to_string().starts_with(
+            "ACME order: https://acme.example.net/order/1/2\n\
+             Status: valid, the certificate has been issued\n\
+             Identifiers: example.com\n\
+             Expires: 2024-01-08T00:00:00Z\n\
+             Certificate: https://acme.example.net/cert/1\n\
+             Critical extensions: basicConstraints\n"
+        ));
+    }
+}
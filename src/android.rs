@@ -0,0 +1,287 @@
+use x509_parser::asn1_rs::Any;
+use x509_parser::time::ASN1Time;
+
+use crate::cms::hex;
+use crate::extensions::{context_tag, elements, text_or_hex, unsigned, Field};
+
+/// The extension Android keystore adds to the leaf of an attestation chain.
+pub const KEY_ATTESTATION: &str = "1.3.6.1.4.1.11129.2.1.17";
+
+const SECURITY_LEVELS: &[&str] = &["Software", "TrustedEnvironment", "StrongBox"];
+
+const VERIFIED_BOOT_STATES: &[&str] = &["Verified", "SelfSigned", "Unverified", "Failed"];
+
+const PURPOSES: &[(u64, &str)] = &[
+    (0, "encrypt"),
+    (1, "decrypt"),
+    (2, "sign"),
+    (3, "verify"),
+    (5, "wrap key"),
+    (6, "agree key"),
+    (7, "attest key"),
+];
+
+const ALGORITHMS: &[(u64, &str)] = &[
+    (1, "RSA"),
+    (3, "EC"),
+    (32, "AES"),
+    (33, "3DES"),
+    (128, "HMAC"),
+];
+
+const DIGESTS: &[(u64, &str)] = &[
+    (0, "none"),
+    (1, "MD5"),
+    (2, "SHA-1"),
+    (3, "SHA-2-224"),
+    (4, "SHA-2-256"),
+    (5, "SHA-2-384"),
+    (6, "SHA-2-512"),
+];
+
+const PADDINGS: &[(u64, &str)] = &[
+    (1, "none"),
+    (2, "RSA OAEP"),
+    (3, "RSA PSS"),
+    (4, "RSA PKCS#1 v1.5 encrypt"),
+    (5, "RSA PKCS#1 v1.5 sign"),
+    (64, "PKCS#7"),
+];
+
+const CURVES: &[(u64, &str)] = &[
+    (0, "P-224"),
+    (1, "P-256"),
+    (2, "P-384"),
+    (3, "P-521"),
+    (4, "Curve25519"),
+];
+
+const ORIGINS: &[(u64, &str)] = &[
+    (0, "generated"),
+    (1, "derived"),
+    (2, "imported"),
+    (3, "reserved"),
+    (4, "securely imported"),
+];
+
+/// How each authorization list value is shown.
+enum Kind {
+    Integer,
+    Named(&'static [(u64, &'static str)]),
+    Set(&'static [(u64, &'static str)]),
+    /// Present or not, as NULL.
+    Flag,
+    /// Milliseconds since the epoch.
+    Time,
+    /// A year and month, or a year, month and day, as digits.
+    PatchLevel,
+    Bytes,
+    RootOfTrust,
+    ApplicationId,
+}
+
+/// The authorization list tags, as keymaster and KeyMint number them.
+const AUTHORIZATIONS: &[(u32, &str, Kind)] = &[
+    (1, "Purpose", Kind::Set(PURPOSES)),
+    (2, "Algorithm", Kind::Named(ALGORITHMS)),
+    (3, "Key size", Kind::Integer),
+    (5, "Digest", Kind::Set(DIGESTS)),
+    (6, "Padding", Kind::Set(PADDINGS)),
+    (10, "EC curve", Kind::Named(CURVES)),
+    (200, "RSA public exponent", Kind::Integer),
+    (203, "MGF digest", Kind::Set(DIGESTS)),
+    (303, "Rollback resistance", Kind::Flag),
+    (305, "Early boot only", Kind::Flag),
+    (400, "Active from", Kind::Time),
+    (401, "Origination expires", Kind::Time),
+    (402, "Usage expires", Kind::Time),
+    (405, "Usage count limit", Kind::Integer),
+    (503, "No authentication required", Kind::Flag),
+    (504, "User authentication type", Kind::Integer),
+    (505, "Authentication timeout", Kind::Integer),
+    (506, "Allow while on body", Kind::Flag),
+    (507, "Trusted user presence required", Kind::Flag),
+    (508, "Trusted confirmation required", Kind::Flag),
+    (509, "Unlocked device required", Kind::Flag),
+    (600, "All applications", Kind::Flag),
+    (701, "Creation time", Kind::Time),
+    (702, "Origin", Kind::Named(ORIGINS)),
+    (704, "Root of trust", Kind::RootOfTrust),
+    (705, "OS version", Kind::Integer),
+    (706, "OS patch level", Kind::PatchLevel),
+    (709, "Attestation application ID", Kind::ApplicationId),
+    (710, "Brand", Kind::Bytes),
+    (711, "Device", Kind::Bytes),
+    (712, "Product", Kind::Bytes),
+    (713, "Serial number", Kind::Bytes),
+    (714, "IMEI", Kind::Bytes),
+    (715, "MEID", Kind::Bytes),
+    (716, "Manufacturer", Kind::Bytes),
+    (717, "Model", Kind::Bytes),
+    (718, "Vendor patch level", Kind::PatchLevel),
+    (719, "Boot patch level", Kind::PatchLevel),
+    (720, "Device unique attestation", Kind::Flag),
+    (723, "Second IMEI", Kind::Bytes),
+    (724, "Module hash", Kind::Bytes),
+];
+
+/// Decodes a KeyDescription, the value of the key attestation extension.
+pub fn key_description(value: &[u8]) -> Option<Vec<Field>> {
+    let description = elements(value)?;
+    let fields = elements(description.first()?.data)?;
+    let version = unsigned(fields.first()?)?;
+    // KeyMint took over from keymaster at attestation version 100
+    let implementation = match version {
+        0..=99 => "Keymaster",
+        _ => "KeyMint",
+    };
+    let level = |field: &Any| {
+        let level = unsigned(field)? as usize;
+        SECURITY_LEVELS.get(level).map(|level| level.to_string())
+    };
+
+    Some(vec![
+        Field::new("Attestation version", version.to_string()),
+        Field::new("Attestation security level", level(fields.get(1)?)?),
+        Field::new(
+            &format!("{} version", implementation),
+            unsigned(fields.get(2)?)?.to_string(),
+        ),
+        Field::new(
+            &format!("{} security level", implementation),
+            level(fields.get(3)?)?,
+        ),
+        Field::new("Attestation challenge", text_or_hex(fields.get(4)?.data)),
+        // Only system apps can ask for a unique ID
+        Field::new(
+            "Unique ID",
+            match fields.get(5)?.data {
+                [] => String::from("none"),
+                id => hex(id),
+            },
+        ),
+        Field::group("Software enforced", authorizations(fields.get(6)?)?),
+        Field::group("Hardware enforced", authorizations(fields.get(7)?)?),
+    ])
+}
+
+fn authorizations(list: &Any) -> Option<Vec<Field>> {
+    let mut fields = Vec::new();
+    for authorization in elements(list.data)? {
+        let tag = context_tag(&authorization)?;
+        // Each value is explicitly tagged
+        let value = elements(authorization.data)?.into_iter().next()?;
+        let field = match AUTHORIZATIONS.iter().find(|(known, _, _)| *known == tag) {
+            Some((_, name, kind)) => Field::new(name, authorization_value(kind, &value)?),
+            None => Field::new(&format!("Tag {}", tag), hex(value.data)),
+        };
+        fields.push(field);
+    }
+    Some(fields)
+}
+
+fn authorization_value(kind: &Kind, value: &Any) -> Option<String> {
+    let named = |names: &[(u64, &str)], value: u64| match names.iter().find(|(n, _)| *n == value) {
+        Some((_, name)) => name.to_string(),
+        None => value.to_string(),
+    };
+    Some(match kind {
+        Kind::Integer => unsigned(value)?.to_string(),
+        Kind::Named(names) => named(names, unsigned(value)?),
+        Kind::Set(names) => elements(value.data)?
+            .iter()
+            .map(|value| Some(named(names, unsigned(value)?)))
+            .collect::<Option<Vec<_>>>()?
+            .join(", "),
+        Kind::Flag => String::from("yes"),
+        Kind::Time => {
+            let millis = unsigned(value)?;
+            ASN1Time::from_timestamp((millis / 1000) as i64)
+                .ok()?
+                .to_string()
+        }
+        Kind::PatchLevel => {
+            let level = unsigned(value)?.to_string();
+            match level.len() {
+                6 => format!("{}-{}", &level[..4], &level[4..]),
+                8 => format!("{}-{}-{}", &level[..4], &level[4..6], &level[6..]),
+                _ => level,
+            }
+        }
+        Kind::Bytes => text_or_hex(value.data),
+        Kind::RootOfTrust => root_of_trust(value)?,
+        Kind::ApplicationId => application_id(value.data)?,
+    })
+}
+
+/// The verified boot state, whether the bootloader is locked and the key it verified with.
+fn root_of_trust(value: &Any) -> Option<String> {
+    let fields = elements(value.data)?;
+    let state = unsigned(fields.get(2)?)? as usize;
+    let locked = match fields.get(1)?.data {
+        [0] => "unlocked",
+        _ => "locked",
+    };
+    Some(format!(
+        "{}, {}, verified boot key {}",
+        VERIFIED_BOOT_STATES.get(state)?,
+        locked,
+        hex(fields.first()?.data)
+    ))
+}
+
+/// The packages the key was made for and the digests of their signing certificates.
+fn application_id(der: &[u8]) -> Option<String> {
+    let application = elements(der)?;
+    let fields = elements(application.first()?.data)?;
+    let mut parts = Vec::new();
+    for package in elements(fields.first()?.data)? {
+        let package = elements(package.data)?;
+        parts.push(format!(
+            "{} version {}",
+            text_or_hex(package.first()?.data),
+            unsigned(package.get(1)?)?
+        ));
+    }
+    for digest in elements(fields.get(1)?.data)? {
+        parts.push(format!("signing certificate digest {}", hex(digest.data)));
+    }
+    Some(parts.join(", "))
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+
+    use super::{key_description, KEY_ATTESTATION};
+
+    const CERT: &[u8] = include_bytes!("../resources/android-attestation.crt");
+
+    fn extension_value() -> Vec<u8> {
+        let (_, pem) = x509_parser::pem::parse_x509_pem(CERT).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+        let extension = cert
+            .extensions()
+            .iter()
+            .find(|extension| extension.oid.to_id_string() == KEY_ATTESTATION)
+            .unwrap();
+        extension.value.to_vec()
+    }
+
+    #[test]
+    fn should_name_the_implementation_by_attestation_version() {
+        let fields = key_description(&extension_value()).unwrap();
+
+        assert_eq!(fields[2].name, "KeyMint version");
+        assert_eq!(fields[6].name, "Software enforced");
+        assert_eq!(fields[7].fields.len(), 12);
+    }
+
+    #[test]
+    fn should_not_decode_a_truncated_description() {
+        let value = extension_value();
+
+        assert!(key_description(&value[..value.len() - 10]).is_none());
+    }
+}
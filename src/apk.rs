@@ -0,0 +1,295 @@
+use std::convert::TryInto;
+use std::io::{Cursor, Read};
+
+/// The magic that ends an APK Signing Block, just before the central directory.
+const SIGNING_BLOCK_MAGIC: &[u8] = b"APK Sig Block 42";
+
+/// The IDs of the signature scheme blocks within an APK Signing Block.
+const SCHEMES: &[(u32, &str)] = &[
+    (0x7109_871a, "APK v2"),
+    (0xf053_68c0, "APK v3"),
+    (0x1b93_ad61, "APK v3.1"),
+];
+
+/// The v3 signed data attribute that holds the key rotation lineage.
+const PROOF_OF_ROTATION: u32 = 0x3ba0_6f8c;
+
+/// What a past signing certificate is still trusted with, by flag.
+const CAPABILITIES: &[(u32, &str)] = &[
+    (0x01, "installed data"),
+    (0x02, "shared user ID"),
+    (0x04, "permission"),
+    (0x08, "rollback"),
+    (0x10, "auth"),
+];
+
+/// Only the signature files of a JAR are read, so anything bigger is skipped.
+const MAX_ENTRY_SIZE: u64 = 16 * 1024 * 1024;
+
+/// The signatures of a JAR or APK.
+pub struct Signatures {
+    /// The PKCS#7 signature files under `META-INF`, with the `.SF` file they sign.
+    pub jar: Vec<JarSigner>,
+    /// The signers of the v2 and later APK signature schemes.
+    pub apk: Vec<ApkSigner>,
+}
+
+pub struct JarSigner {
+    pub name: String,
+    pub signature: Vec<u8>,
+    pub signed: Option<Vec<u8>>,
+}
+
+pub struct ApkSigner {
+    pub scheme: &'static str,
+    pub certificates: Vec<Vec<u8>>,
+    /// The platform versions a v3 signer applies to, as the maximum is usually left open.
+    pub sdk: Option<(u32, u32)>,
+    /// The signing certificates a v3 signer rotated through, oldest first.
+    pub lineage: Vec<Rotation>,
+}
+
+/// A certificate in the key rotation lineage of an APK.
+pub struct Rotation {
+    pub der: Vec<u8>,
+    pub capabilities: Vec<&'static str>,
+}
+
+/// Whether the bytes are a zip that is a JAR or APK, telling them from
+/// other archives by their manifest or APK Signing Block.
+pub fn is_package(bytes: &[u8]) -> bool {
+    if !bytes.starts_with(b"PK\x03\x04") {
+        return false;
+    }
+    signing_block(bytes).is_some()
+        || zip::ZipArchive::new(Cursor::new(bytes))
+            .is_ok_and(|mut zip| zip.by_name("META-INF/MANIFEST.MF").is_ok())
+}
+
+/// Reads the JAR signature files and APK Signing Block of a package.
+pub fn signatures(source: &str, bytes: &[u8]) -> Result<Signatures, Box<dyn std::error::Error>> {
+    let invalid = |error: &dyn std::fmt::Display| {
+        format!("Error: could not read the package {}: {}", source, error)
+    };
+    let mut zip = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| invalid(&e))?;
+    let mut files = Vec::new();
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i).map_err(|e| invalid(&e))?;
+        let name = String::from(file.name().map_err(|e| invalid(&e))?);
+        if !is_signature_file(&name) && !name.ends_with(".SF") || file.size() > MAX_ENTRY_SIZE {
+            continue;
+        }
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).map_err(|e| invalid(&e))?;
+        files.push((name, contents));
+    }
+
+    // Each signature file signs the .SF file with the same base name
+    let mut signatures = Signatures {
+        jar: Vec::new(),
+        apk: Vec::new(),
+    };
+    for (name, signature) in &files {
+        if !is_signature_file(name) {
+            continue;
+        }
+        let (base, _) = name
+            .rsplit_once('.')
+            .expect("signature files have an extension");
+        let signed = files
+            .iter()
+            .find(|(other, _)| *other == format!("{}.SF", base))
+            .map(|(_, signed)| signed.clone());
+        signatures.jar.push(JarSigner {
+            name: name.clone(),
+            signature: signature.clone(),
+            signed,
+        });
+    }
+
+    if let Some(block) = signing_block(bytes) {
+        signatures.apk = apk_signers(block)
+            .ok_or_else(|| format!("Error: the APK Signing Block of {} is not valid.", source))?;
+    }
+    if signatures.jar.is_empty() && signatures.apk.is_empty() {
+        return Err(format!("Error: {} is not signed.", source).into());
+    }
+    Ok(signatures)
+}
+
+fn is_signature_file(name: &str) -> bool {
+    let name = name.to_uppercase();
+    name.starts_with("META-INF/")
+        && !name["META-INF/".len()..].contains('/')
+        && [".RSA", ".DSA", ".EC"]
+            .iter()
+            .any(|extension| name.ends_with(extension))
+}
+
+/// Finds the APK Signing Block, which sits between the entries and the central directory.
+fn signing_block(bytes: &[u8]) -> Option<&[u8]> {
+    // The end of central directory record is at least 22 bytes, and its comment at most 64KiB
+    let search = bytes.len().saturating_sub(22 + 0xffff);
+    let eocd = search + find_last(&bytes[search..], b"PK\x05\x06")?;
+    let directory = u32_at(bytes, eocd + 16)? as usize;
+
+    let footer = bytes.get(directory.checked_sub(24)?..directory)?;
+    if &footer[8..] != SIGNING_BLOCK_MAGIC {
+        return None;
+    }
+    // The size is given at both ends, counting everything but the first
+    let size = u64_at(footer, 0)? as usize;
+    let start = directory.checked_sub(size.checked_add(8)?)?;
+    if u64_at(bytes, start)? as usize != size {
+        return None;
+    }
+    bytes.get(start + 8..directory - 24)
+}
+
+/// Reads the signers of each signature scheme in the block.
+fn apk_signers(block: &[u8]) -> Option<Vec<ApkSigner>> {
+    let mut signers = Vec::new();
+    let mut pairs = block;
+    while !pairs.is_empty() {
+        let length = u64_at(pairs, 0)? as usize;
+        let pair = pairs.get(8..length.checked_add(8)?)?;
+        pairs = &pairs[8 + length..];
+        let id = u32_at(pair, 0)?;
+        let scheme = match SCHEMES.iter().find(|(scheme, _)| *scheme == id) {
+            Some((_, scheme)) => *scheme,
+            None => continue, // padding, and the blocks of tools like Play's frosting
+        };
+
+        let scheme_signers = prefixed(&pair[4..])?;
+        for signer in LengthPrefixed(scheme_signers) {
+            let mut signed = LengthPrefixed(prefixed(signer)?);
+            signed.next()?; // digests
+            let certificates = LengthPrefixed(signed.next()?).map(<[u8]>::to_vec).collect();
+            let mut signer = ApkSigner {
+                scheme,
+                certificates,
+                sdk: None,
+                lineage: Vec::new(),
+            };
+            if scheme != "APK v2" {
+                let rest = signed.0;
+                signer.sdk = Some((u32_at(rest, 0)?, u32_at(rest, 4)?));
+                signed.0 = rest.get(8..)?;
+                for attribute in LengthPrefixed(signed.next()?) {
+                    if u32_at(attribute, 0)? == PROOF_OF_ROTATION {
+                        signer.lineage = lineage(&attribute[4..])?;
+                    }
+                }
+            }
+            signers.push(signer);
+        }
+    }
+    Some(signers)
+}
+
+/// Reads a SigningCertificateLineage: a version, then nodes each holding a
+/// certificate signed by the key of the node before.
+fn lineage(bytes: &[u8]) -> Option<Vec<Rotation>> {
+    let nodes = LengthPrefixed(bytes.get(4..)?);
+    let mut lineage = Vec::new();
+    for node in nodes {
+        let mut signed = LengthPrefixed(prefixed(node)?);
+        let flags = u32_at(node, 4 + u32_at(node, 0)? as usize)?;
+        lineage.push(Rotation {
+            der: signed.next()?.to_vec(),
+            capabilities: CAPABILITIES
+                .iter()
+                .filter(|(flag, _)| flags & flag != 0)
+                .map(|(_, name)| *name)
+                .collect(),
+        });
+    }
+    Some(lineage)
+}
+
+/// The element a little endian 32-bit length prefixes.
+fn prefixed(bytes: &[u8]) -> Option<&[u8]> {
+    let length = u32_at(bytes, 0)? as usize;
+    bytes.get(4..length.checked_add(4)?)
+}
+
+/// Iterates over length prefixed elements, stopping at the first that doesn't fit.
+struct LengthPrefixed<'a>(&'a [u8]);
+
+impl<'a> Iterator for LengthPrefixed<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let element = prefixed(self.0)?;
+        self.0 = &self.0[4 + element.len()..];
+        Some(element)
+    }
+}
+
+fn find_last(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Option<u64> {
+    let bytes = bytes.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{is_package, signatures};
+
+    const APK: &[u8] = include_bytes!("../resources/signed.apk");
+    const JAR: &[u8] = include_bytes!("../resources/signed.jar");
+
+    #[test]
+    fn should_read_the_signature_files_of_a_jar() {
+        let signatures = signatures("signed.jar", JAR).unwrap();
+
+        assert!(is_package(JAR));
+        assert!(signatures.apk.is_empty());
+        assert_eq!(signatures.jar.len(), 1);
+        assert_eq!(signatures.jar[0].name, "META-INF/RELEASE.RSA");
+        assert!(signatures.jar[0]
+            .signed
+            .as_ref()
+            .unwrap()
+            .starts_with(b"Signature-Version: 1.0"));
+    }
+
+    #[test]
+    fn should_read_the_signers_and_lineage_of_an_apk() {
+        let signatures = signatures("signed.apk", APK).unwrap();
+        let apk = &signatures.apk;
+
+        assert_eq!(apk.len(), 2);
+        assert_eq!(apk[0].scheme, "APK v2");
+        assert_eq!(apk[0].sdk, None);
+        assert_eq!(apk[1].scheme, "APK v3");
+        assert_eq!(apk[1].sdk, Some((28, 0x7fff_ffff)));
+        assert_eq!(apk[1].lineage.len(), 2);
+        assert_eq!(
+            apk[1].lineage[0].capabilities,
+            vec!["installed data", "shared user ID", "rollback", "auth"]
+        );
+        assert_eq!(apk[1].lineage[1].der, apk[1].certificates[0]);
+    }
+
+    #[test]
+    fn should_not_mistake_other_archives_for_packages() {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip.start_file("server.pem", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+
+        assert!(!is_package(&bytes));
+    }
+}
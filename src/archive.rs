@@ -0,0 +1,172 @@
+use std::io::{Cursor, Read};
+
+use crate::compression;
+
+/// Entries bigger than this are skipped, certificates are tiny and a
+/// crafted archive shouldn't be able to exhaust memory.
+const MAX_ENTRY_SIZE: u64 = 16 * 1024 * 1024;
+
+/// File extensions that suggest a certificate, chain or keystore
+/// even when the contents aren't PEM.
+const CERTIFICATE_EXTENSIONS: &[&str] = &[
+    "crt",
+    "cer",
+    "cert",
+    "der",
+    "pem",
+    "p7b",
+    "p7c",
+    "p7s",
+    "tsr",
+    "p12",
+    "pfx",
+    "jks",
+    "keystore",
+    "mobileprovision",
+    "provisionprofile",
+];
+
+/// The files in an archive, with anything skipped along the way.
+pub struct Entries {
+    pub files: Vec<(String, Vec<u8>)>,
+    pub warnings: Vec<String>,
+}
+
+/// Whether the bytes are a zip or tar archive. A compressed tar is
+/// only recognised as one once it has been decompressed.
+pub fn is_archive(bytes: &[u8]) -> bool {
+    is_zip(bytes) || is_tar(bytes)
+}
+
+/// Whether an archive entry is named like a certificate or is an archive
+/// itself, as vendor deliverables like to nest them.
+pub fn is_candidate(name: &str, bytes: &[u8]) -> bool {
+    let extension = name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase());
+    extension.is_some_and(|extension| CERTIFICATE_EXTENSIONS.contains(&extension.as_str()))
+        || is_archive(bytes)
+        || compression::is_compressed(bytes)
+}
+
+/// Lists the regular files of an archive with their contents.
+pub fn entries(source: &str, bytes: &[u8]) -> Result<Entries, Box<dyn std::error::Error>> {
+    let invalid = |error: &dyn std::fmt::Display| {
+        format!("Error: could not read the archive {}: {}", source, error)
+    };
+    let mut entries = Entries {
+        files: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let mut skip = |name: &str, size: u64| {
+        entries.warnings.push(format!(
+            "Warning: skipped {} in {}, at {} bytes it is too big to be a certificate.",
+            name, source, size
+        ))
+    };
+    let mut files = Vec::new();
+
+    if is_zip(bytes) {
+        let mut zip = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| invalid(&e))?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i).map_err(|e| invalid(&e))?;
+            if !file.is_file() {
+                continue;
+            }
+            let name = String::from(file.name().map_err(|e| invalid(&e))?);
+            if file.size() > MAX_ENTRY_SIZE {
+                skip(&name, file.size());
+                continue;
+            }
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).map_err(|e| invalid(&e))?;
+            files.push((name, contents));
+        }
+    } else {
+        let mut archive = tar::Archive::new(bytes);
+        for file in archive.entries().map_err(|e| invalid(&e))? {
+            let mut file = file.map_err(|e| invalid(&e))?;
+            if !file.header().entry_type().is_file() {
+                continue;
+            }
+            let name = file.path().map_err(|e| invalid(&e))?.display().to_string();
+            let size = file.size();
+            if size > MAX_ENTRY_SIZE {
+                skip(&name, size);
+                continue;
+            }
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).map_err(|e| invalid(&e))?;
+            files.push((name, contents));
+        }
+    }
+
+    entries.files = files;
+    Ok(entries)
+}
+
+fn is_zip(bytes: &[u8]) -> bool {
+    // A local file header, or the end of central directory of an empty archive
+    bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06")
+}
+
+fn is_tar(bytes: &[u8]) -> bool {
+    bytes.get(257..262) == Some(b"ustar")
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Write;
+
+    use super::{entries, is_archive, is_candidate};
+
+    const CERT: &[u8] = include_bytes!("../resources/google.com.crt");
+
+    fn tar() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(CERT.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "certs/google.crt", CERT)
+            .unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn should_list_the_files_of_a_zip() {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_directory("release/", options).unwrap();
+        zip.start_file("release/server.pem", options).unwrap();
+        zip.write_all(CERT).unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let entries = entries("release.zip", &bytes).unwrap();
+
+        assert!(is_archive(&bytes));
+        assert_eq!(
+            entries.files,
+            vec![(String::from("release/server.pem"), CERT.to_vec())]
+        );
+    }
+
+    #[test]
+    fn should_list_the_files_of_a_tar() {
+        let entries = entries("backup.tar", &tar()).unwrap();
+
+        assert!(is_archive(&tar()));
+        assert_eq!(entries.files[0].0, "certs/google.crt");
+        assert!(!is_archive(CERT));
+    }
+
+    #[test]
+    fn should_only_decode_entries_that_look_like_certificates() {
+        assert!(is_candidate("certs/ROOT.CER", b"\x30\x82"));
+        assert!(is_candidate("nested", &tar()));
+        assert!(is_candidate("nested", &[0x1f, 0x8b, 0x08]));
+        assert!(!is_candidate("bin/server", b"\x7fELF"));
+    }
+}
@@ -0,0 +1,332 @@
+use x509_parser::extensions::GeneralName;
+use x509_parser::pem::Pem;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+
+use crate::cms::{self, element, Tlv};
+use crate::oid;
+
+/// An RFC 5755 attribute certificate: attributes bound to a holder rather than a key.
+pub struct AttributeCertificate {
+    pub location: String,
+    /// Who the attributes belong to, by name or by the certificate they were given with.
+    pub holder: Vec<String>,
+    pub issuer: Vec<String>,
+    pub serial: String,
+    pub not_before: String,
+    pub not_after: String,
+    pub signature_algorithm: String,
+    pub attributes: Vec<(String, String)>,
+    /// Each extension by name, with whether it is critical.
+    pub extensions: Vec<String>,
+}
+
+/// The attributes RFC 5755 defines, and the VOMS one grid computing uses.
+const ATTRIBUTES: &[(&str, &str)] = &[
+    ("2.5.4.72", "Role"),
+    ("2.5.4.55", "Clearance"),
+    ("2.5.1.5.55", "Clearance"),
+    ("1.3.6.1.5.5.7.10.1", "Authentication info"),
+    ("1.3.6.1.5.5.7.10.2", "Access identity"),
+    ("1.3.6.1.5.5.7.10.3", "Charging identity"),
+    ("1.3.6.1.5.5.7.10.4", "Group"),
+    ("1.3.6.1.4.1.8005.100.100.4", "VOMS FQANs"),
+];
+
+/// The classifications of a clearance, by bit.
+const CLASSES: &[&str] = &[
+    "unmarked",
+    "unclassified",
+    "restricted",
+    "confidential",
+    "secret",
+    "top secret",
+];
+
+/// The extensions RFC 5755 adds for attribute certificates.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("1.3.6.1.5.5.7.1.4", "auditIdentity"),
+    ("2.5.29.55", "targetInformation"),
+    ("2.5.29.56", "noRevAvail"),
+    ("1.3.6.1.5.5.7.1.6", "acProxying"),
+];
+
+/// The DER of the attribute certificates in a file, whether it is one in binary
+/// or a series of `ATTRIBUTE CERTIFICATE` PEM blocks.
+pub fn find(bytes: &[u8]) -> Vec<Vec<u8>> {
+    if is_attribute_certificate(bytes) {
+        return vec![bytes.to_vec()];
+    }
+    Pem::iter_from_buffer(bytes)
+        .filter_map(Result::ok)
+        .filter(|pem| pem.label == "ATTRIBUTE CERTIFICATE")
+        .map(|pem| pem.contents)
+        .filter(|der| is_attribute_certificate(der))
+        .collect()
+}
+
+/// Tells an attribute certificate from a public key one by its validity, which
+/// comes a field later and is always in GeneralizedTime.
+fn is_attribute_certificate(bytes: &[u8]) -> bool {
+    let sniff = || {
+        let (certificate, _) = element(bytes)?;
+        let info = certificate.children()?.into_iter().next()?.children()?;
+        let validity = info.get(5)?.children()?;
+        Some(
+            info.first()?.tag == 0x02
+                && info.get(4)?.tag == 0x02
+                && validity.len() == 2
+                && validity.iter().all(|time| time.tag == 0x18),
+        )
+    };
+    sniff().unwrap_or(false)
+}
+
+pub fn read(source: &str, der: &[u8]) -> Result<AttributeCertificate, Box<dyn std::error::Error>> {
+    attribute_certificate(source, der)
+        .ok_or_else(|| format!("Error: {} is not a valid attribute certificate.", source).into())
+}
+
+fn attribute_certificate(source: &str, der: &[u8]) -> Option<AttributeCertificate> {
+    let (certificate, _) = element(der)?;
+    let certificate = certificate.children()?;
+    let info = certificate.first()?.children()?;
+    let validity = info.get(5)?.children()?;
+    let time = |time: &Tlv| Some(ASN1Time::from_der(time.raw).ok()?.1.to_string());
+
+    let mut extensions = Vec::new();
+    for field in info.iter().skip(7) {
+        // The issuerUniqueID, which RFC 5755 says not to use, is the other optional field
+        if field.tag != 0x30 {
+            continue;
+        }
+        for extension in field.children()? {
+            let extension = extension.children()?;
+            let id = extension.first()?.oid()?;
+            let name = match EXTENSIONS
+                .iter()
+                .find(|(known, _)| *known == id.to_id_string())
+            {
+                Some((_, name)) => String::from(*name),
+                None => oid::name(&id),
+            };
+            let critical = extension.len() == 3 && extension.get(1)?.contents != [0];
+            extensions.push(match critical {
+                true => format!("{} (critical)", name),
+                false => name,
+            });
+        }
+    }
+
+    Some(AttributeCertificate {
+        location: String::from(source),
+        holder: holder(info.get(1)?)?,
+        issuer: issuer(info.get(2)?)?,
+        signature_algorithm: cms::algorithm(certificate.get(1)?)?,
+        serial: cms::hex(info.get(4)?.contents),
+        not_before: time(validity.first()?)?,
+        not_after: time(validity.get(1)?)?,
+        attributes: info
+            .get(6)?
+            .children()?
+            .iter()
+            .map(attribute)
+            .collect::<Option<_>>()?,
+        extensions,
+    })
+}
+
+/// The holder, named directly or through the issuer and serial number of a
+/// public key certificate the attributes go with.
+fn holder(holder: &Tlv) -> Option<Vec<String>> {
+    let mut names = Vec::new();
+    for field in holder.children()? {
+        match field.tag {
+            0xa0 => names.push(issuer_serial(&field)?),
+            0xa1 => names.extend(general_names(&field)?),
+            0xa2 => names.push(String::from("an object digest")),
+            _ => return None,
+        }
+    }
+    Some(names)
+}
+
+/// The issuer, as GeneralNames in the old form or in the `[0]` form RFC 5755 requires.
+fn issuer(issuer: &Tlv) -> Option<Vec<String>> {
+    match issuer.tag {
+        0x30 => general_names(issuer),
+        0xa0 => {
+            let mut names = Vec::new();
+            for field in issuer.children()? {
+                match field.tag {
+                    0x30 => names.extend(general_names(&field)?),
+                    0xa0 => names.push(issuer_serial(&field)?),
+                    _ => names.push(String::from("an object digest")),
+                }
+            }
+            Some(names)
+        }
+        _ => None,
+    }
+}
+
+fn issuer_serial(issuer_serial: &Tlv) -> Option<String> {
+    let fields = issuer_serial.children()?;
+    Some(format!(
+        "the certificate {} issued by {}",
+        cms::hex(fields.get(1)?.contents),
+        general_names(fields.first()?)?.join(", ")
+    ))
+}
+
+fn general_names(names: &Tlv) -> Option<Vec<String>> {
+    names.children()?.iter().map(general_name).collect()
+}
+
+fn general_name(name: &Tlv) -> Option<String> {
+    match GeneralName::from_der(name.raw).ok()?.1 {
+        GeneralName::DirectoryName(name) => Some(name.to_string()),
+        name => Some(name.to_string()),
+    }
+}
+
+/// An attribute and its values, in a form for the kinds RFC 5755 defines.
+fn attribute(attribute: &Tlv) -> Option<(String, String)> {
+    let fields = attribute.children()?;
+    let id = fields.first()?.oid()?;
+    let known = ATTRIBUTES
+        .iter()
+        .find(|(known, _)| *known == id.to_id_string());
+    let values = fields.get(1)?.children()?;
+
+    let values: Vec<String> = match known.map(|(oid, _)| *oid) {
+        Some("2.5.4.72") => values.iter().map(role).collect::<Option<_>>()?,
+        Some("2.5.4.55") | Some("2.5.1.5.55") => {
+            values.iter().map(clearance).collect::<Option<_>>()?
+        }
+        Some("1.3.6.1.5.5.7.10.1") | Some("1.3.6.1.5.5.7.10.2") => values
+            .iter()
+            .map(service_auth_info)
+            .collect::<Option<_>>()?,
+        Some(_) => values.iter().map(ietf_attribute).collect::<Option<_>>()?,
+        None => values
+            .iter()
+            .map(|value| format!("{} bytes", value.raw.len()))
+            .collect(),
+    };
+    let name = match known {
+        Some((_, name)) => String::from(*name),
+        None => oid::name(&id),
+    };
+    Some((name, values.join(", ")))
+}
+
+/// The name of a role, and the authority that defines it if one is given.
+fn role(role: &Tlv) -> Option<String> {
+    let mut authority = None;
+    let mut name = None;
+    for field in role.children()? {
+        match field.tag {
+            0xa0 => authority = Some(general_names(&field)?.join(", ")),
+            0xa1 => name = Some(general_name(field.children()?.first()?)?),
+            _ => return None,
+        }
+    }
+    match authority {
+        Some(authority) => Some(format!("{} defined by {}", name?, authority)),
+        None => name,
+    }
+}
+
+/// A security policy and the classifications granted under it.
+fn clearance(clearance: &Tlv) -> Option<String> {
+    let fields = clearance.children()?;
+    let policy = oid::name(&fields.first()?.oid()?);
+    let classes = match fields.get(1).filter(|field| field.tag == 0x03) {
+        Some(list) => {
+            let bits = list.contents.get(1..)?;
+            CLASSES
+                .iter()
+                .enumerate()
+                .filter(|(bit, _)| {
+                    bits.get(bit / 8)
+                        .is_some_and(|byte| byte & (0x80 >> (bit % 8)) != 0)
+                })
+                .map(|(_, class)| *class)
+                .collect::<Vec<_>>()
+                .join(" and ")
+        }
+        // The class list defaults to unclassified
+        None => String::from(CLASSES[1]),
+    };
+    Some(format!("{} under {}", classes, policy))
+}
+
+/// A service and the identity the holder has with it.
+fn service_auth_info(info: &Tlv) -> Option<String> {
+    let fields = info.children()?;
+    Some(format!(
+        "{} at {}",
+        general_name(fields.get(1)?)?,
+        general_name(fields.first()?)?
+    ))
+}
+
+/// The values of an IetfAttrSyntax, which can each be bytes, an OID or a string.
+fn ietf_attribute(attribute: &Tlv) -> Option<String> {
+    let fields = attribute.children()?;
+    let values = fields.iter().find(|field| field.tag == 0x30)?;
+    let values: Vec<String> = values
+        .children()?
+        .iter()
+        .map(|value| match value.tag {
+            0x06 => value.oid().map(|oid| oid::name(&oid)),
+            0x04 | 0x0c => Some(String::from_utf8_lossy(value.contents).into_owned()),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    Some(values.join(", "))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{find, read};
+
+    const AC: &[u8] = include_bytes!("../resources/alice.ac.pem");
+
+    #[test]
+    fn should_decode_the_holder_issuer_and_attributes() {
+        let der = find(AC);
+        let certificate = read("alice.ac.pem", &der[0]).unwrap();
+
+        assert_eq!(der.len(), 1);
+        assert_eq!(certificate.holder, vec!["O=Example, CN=Alice"]);
+        assert_eq!(certificate.issuer, vec!["CN=self-signed.example"]);
+        assert_eq!(certificate.serial, "2a");
+        assert_eq!(
+            certificate.attributes,
+            vec![
+                (
+                    String::from("Role"),
+                    String::from("URI(urn:example:role:admin)")
+                ),
+                (String::from("Group"), String::from("engineering, ops")),
+                (
+                    String::from("Clearance"),
+                    String::from("secret under 1.3.6.1.4.1.32473.2")
+                ),
+            ]
+        );
+        assert_eq!(
+            certificate.extensions,
+            vec!["noRevAvail", "targetInformation (critical)"]
+        );
+    }
+
+    #[test]
+    fn should_not_mistake_a_certificate_for_an_attribute_certificate() {
+        assert!(find(include_bytes!("../resources/google.com.crt")).is_empty());
+        assert!(find(&crate::test::google_der()).is_empty());
+    }
+}
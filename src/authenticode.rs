@@ -0,0 +1,232 @@
+use std::convert::TryInto;
+
+/// The magic number of an OLE compound file, which is what an MSI is.
+const CFB_MAGIC: &[u8] = &[0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1];
+
+/// The stream of an MSI that holds its signature.
+const MSI_SIGNATURE: &str = "\u{5}DigitalSignature";
+
+/// WIN_CERTIFICATE type for a PKCS#7 SignedData.
+const WIN_CERT_TYPE_PKCS_SIGNED_DATA: u16 = 2;
+
+/// Sector chains end with this, and anything above it is not a sector number.
+const END_OF_CHAIN: u32 = 0xffff_fffe;
+
+/// Whether the bytes are a Windows PE image, such as an `.exe` or `.dll`.
+pub fn is_pe(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"MZ") && pe_header(bytes).is_some()
+}
+
+/// Whether the bytes are a compound file, as an `.msi` installer is.
+pub fn is_msi(bytes: &[u8]) -> bool {
+    bytes.starts_with(CFB_MAGIC)
+}
+
+/// The Authenticode signatures of a PE image or MSI, each a PKCS#7 SignedData.
+pub fn signatures(source: &str, bytes: &[u8]) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+    let signatures = match is_msi(bytes) {
+        true => msi_signature(bytes)
+            .ok_or_else(|| format!("Error: {} is not a valid MSI.", source))?
+            .into_iter()
+            .collect(),
+        false => pe_signatures(bytes)
+            .ok_or_else(|| format!("Error: {} is not a valid PE image.", source))?,
+    };
+    if signatures.is_empty() {
+        return Err(format!("Error: {} is not signed.", source).into());
+    }
+    Ok(signatures)
+}
+
+/// The offset of the PE signature, from the DOS header.
+fn pe_header(bytes: &[u8]) -> Option<usize> {
+    let offset = u32_at(bytes, 0x3c)? as usize;
+    match bytes.get(offset..offset.checked_add(4)?)? {
+        b"PE\0\0" => Some(offset),
+        _ => None,
+    }
+}
+
+/// Reads the certificate table the security data directory points at.
+fn pe_signatures(bytes: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let optional_header = pe_header(bytes)? + 24;
+    // The data directories follow fields that are wider in PE32+
+    let directories = match u16_at(bytes, optional_header)? {
+        0x10b => optional_header + 96,
+        0x20b => optional_header + 112,
+        _ => return None,
+    };
+    if u32_at(bytes, directories - 4)? < 5 {
+        return Some(Vec::new());
+    }
+    // Unlike the other directories, the security one holds a file offset
+    let start = u32_at(bytes, directories + 32)? as usize;
+    let size = u32_at(bytes, directories + 36)? as usize;
+    let table = bytes.get(start..start.checked_add(size)?)?;
+
+    let mut signatures = Vec::new();
+    let mut offset = 0;
+    while offset + 8 <= table.len() {
+        let length = u32_at(table, offset)? as usize;
+        if length < 8 {
+            return None;
+        }
+        if u16_at(table, offset + 6)? == WIN_CERT_TYPE_PKCS_SIGNED_DATA {
+            signatures.push(table.get(offset + 8..offset.checked_add(length)?)?.to_vec());
+        }
+        // Entries are padded to eight bytes
+        offset += (length + 7) & !7;
+    }
+    Some(signatures)
+}
+
+/// Reads the signature stream of an MSI, if it is signed.
+fn msi_signature(bytes: &[u8]) -> Option<Option<Vec<u8>>> {
+    let file = Cfb::new(bytes)?;
+    let entries = file.stream(u32_at(bytes, 0x30)?)?;
+    let root = entries.get(..128)?;
+    for entry in entries.chunks_exact(128) {
+        let name_len = (u16_at(entry, 64)? as usize).saturating_sub(2).min(64);
+        let name: Vec<u16> = entry[..name_len]
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        if entry[66] != 2 || String::from_utf16_lossy(&name) != MSI_SIGNATURE {
+            continue;
+        }
+        let start = u32_at(entry, 116)?;
+        let size = u32_at(entry, 120)? as usize;
+        let stream = match size < file.mini_cutoff {
+            // Small streams live in the mini stream, which the root entry holds
+            true => {
+                let mini_stream = file.stream(u32_at(root, 116)?)?;
+                let mini_fat = file.stream(u32_at(bytes, 0x3c)?)?;
+                chain(&mini_stream, &mini_fat, start, 64)?
+            }
+            false => file.stream(start)?,
+        };
+        return Some(Some(stream.get(..size)?.to_vec()));
+    }
+    Some(None)
+}
+
+/// Just enough of an OLE compound file reader to get at a stream.
+struct Cfb<'a> {
+    bytes: &'a [u8],
+    sector_size: usize,
+    mini_cutoff: usize,
+    fat: Vec<u8>,
+}
+
+impl<'a> Cfb<'a> {
+    fn new(bytes: &'a [u8]) -> Option<Cfb<'a>> {
+        let shift = u16_at(bytes, 0x1e)?;
+        if shift != 9 && shift != 12 {
+            return None;
+        }
+        let mut file = Cfb {
+            bytes,
+            sector_size: 1 << shift,
+            mini_cutoff: u32_at(bytes, 0x38)? as usize,
+            fat: Vec::new(),
+        };
+
+        // The first 109 FAT sectors are listed in the header, the rest in a chain of DIFAT sectors
+        let mut fat_sectors: Vec<u32> = (0..109)
+            .map(|i| u32_at(bytes, 0x4c + i * 4))
+            .collect::<Option<_>>()?;
+        let mut difat = u32_at(bytes, 0x44)?;
+        for _ in 0..u32_at(bytes, 0x48)? {
+            let sector = file.sector(difat)?;
+            let count = file.sector_size / 4 - 1;
+            fat_sectors.extend((0..count).map(|i| u32_at(sector, i * 4).unwrap_or(!0)));
+            difat = u32_at(sector, count * 4)?;
+        }
+        let count = u32_at(bytes, 0x2c)? as usize;
+        for sector in fat_sectors.into_iter().take(count) {
+            file.fat.extend_from_slice(file.sector(sector)?);
+        }
+        Some(file)
+    }
+
+    fn sector(&self, sector: u32) -> Option<&'a [u8]> {
+        let start = (sector as usize + 1).checked_mul(self.sector_size)?;
+        self.bytes.get(start..start.checked_add(self.sector_size)?)
+    }
+
+    /// Follows a chain of regular sectors through the FAT.
+    fn stream(&self, start: u32) -> Option<Vec<u8>> {
+        // Sector numbers count from after the header
+        let sectors = self.bytes.get(self.sector_size..)?;
+        chain(sectors, &self.fat, start, self.sector_size)
+    }
+}
+
+/// Follows a chain of `size` byte sectors of `data` through an allocation table.
+fn chain(data: &[u8], table: &[u8], start: u32, size: usize) -> Option<Vec<u8>> {
+    let mut stream = Vec::new();
+    let mut sector = start;
+    while sector < END_OF_CHAIN {
+        // A chain can't be longer than the table, so a loop in it is caught
+        if stream.len() > table.len() / 4 * size {
+            return None;
+        }
+        let offset = (sector as usize).checked_mul(size)?;
+        stream.extend_from_slice(data.get(offset..offset.checked_add(size)?)?);
+        sector = u32_at(table, sector as usize * 4)?;
+    }
+    Some(stream)
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{is_msi, is_pe, signatures};
+    use crate::cms;
+
+    const EXE: &[u8] = include_bytes!("../resources/signed.exe");
+    const MSI: &[u8] = include_bytes!("../resources/signed.msi");
+
+    #[test]
+    fn should_find_the_signature_of_a_pe_image() {
+        let signatures = signatures("signed.exe", EXE).unwrap();
+
+        assert!(is_pe(EXE));
+        assert!(!is_msi(EXE));
+        assert_eq!(signatures.len(), 1);
+        let signed = cms::read("signed.exe", &signatures[0]).unwrap();
+        assert_eq!(signed.signers[0].timestamps.len(), 1);
+    }
+
+    #[test]
+    fn should_find_the_signature_stream_of_an_msi() {
+        let signatures = signatures("signed.msi", MSI).unwrap();
+
+        assert!(is_msi(MSI));
+        assert_eq!(signatures, self::signatures("signed.exe", EXE).unwrap());
+    }
+
+    #[test]
+    fn should_error_when_an_image_is_not_signed() {
+        // Clearing the security directory and dropping the certificate table after it
+        let mut unsigned = EXE.to_vec();
+        unsigned[0xe8..0xf0].copy_from_slice(&[0; 8]);
+        unsigned.truncate(0x40 + 4 + 20 + 240);
+        let result = signatures("unsigned.exe", &unsigned);
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: unsigned.exe is not signed."
+        );
+    }
+}
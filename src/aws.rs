@@ -0,0 +1,687 @@
+use std::fmt;
+
+#[cfg(feature = "aws")]
+pub use api::read;
+
+/// Which AWS service to read certificates from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AwsService {
+    /// Certificate Manager, whose certificates are regional.
+    Acm,
+    /// IAM server certificates, which older load balancers and CloudFront use.
+    Iam,
+}
+
+/// What `cert-decoder aws acm` or `aws iam` should read, with `--region`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AwsOptions {
+    pub service: AwsService,
+    pub region: Option<String>,
+}
+
+impl fmt::Display for AwsOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.service, &self.region) {
+            (AwsService::Acm, Some(region)) => write!(f, "AWS ACM in {}", region),
+            (AwsService::Acm, None) => write!(f, "AWS ACM"),
+            (AwsService::Iam, _) => write!(f, "AWS IAM server certificates"),
+        }
+    }
+}
+
+#[cfg(not(feature = "aws"))]
+pub fn read(
+    _processor: &impl crate::FileProcessor,
+    options: &AwsOptions,
+    _connect: &crate::remote::ConnectOptions,
+) -> Result<Vec<crate::store::NamedCertificate>, Box<dyn std::error::Error>> {
+    Err(format!(
+        "Error: reading {} needs AWS support, rebuild cert-decoder with --features aws.",
+        options
+    )
+    .into())
+}
+
+/// Signs requests to ACM and IAM and finds credentials the way the AWS CLI
+/// does. Built with the `aws` feature.
+#[cfg(feature = "aws")]
+mod api {
+
+    use ring::{digest, hmac};
+    use serde_yaml::Value;
+    use x509_parser::pem::Pem;
+    use x509_parser::time::ASN1Time;
+
+    use super::{AwsOptions, AwsService};
+    use crate::cms;
+    use crate::http::{percent_encode, Request, Url};
+    use crate::remote::ConnectOptions;
+    use crate::store::NamedCertificate;
+    use crate::FileProcessor;
+
+    /// IAM is global, its one endpoint signs requests for us-east-1.
+    const IAM_ENDPOINT: &str = "https://iam.amazonaws.com/";
+    const IAM_REGION: &str = "us-east-1";
+
+    /// ListCertificates leaves out everything but 1024 and 2048-bit RSA unless asked.
+    const KEY_TYPES: &str = r#"["RSA_1024","RSA_2048","RSA_3072","RSA_4096","EC_prime256v1","EC_secp384r1","EC_secp521r1"]"#;
+
+    /// ACM certificates in these states have no certificate to fetch.
+    const UNISSUED: &[&str] = &["PENDING_VALIDATION", "VALIDATION_TIMED_OUT", "FAILED"];
+
+    /// An access key, as the AWS CLI finds it.
+    #[derive(Debug, PartialEq)]
+    struct Credentials {
+        access_key: String,
+        secret_key: String,
+        session_token: Option<String>,
+    }
+
+    /// Finds credentials as the SDKs do: in the environment, in the shared
+    /// credentials file for AWS_PROFILE, or else from the AWS CLI, which goes
+    /// on down the chain to roles, SSO, web identity and instance profiles.
+    fn credentials(
+        processor: &impl FileProcessor,
+    ) -> Result<Credentials, Box<dyn std::error::Error>> {
+        if let (Some(access_key), Some(secret_key)) = (
+            processor.var("AWS_ACCESS_KEY_ID"),
+            processor.var("AWS_SECRET_ACCESS_KEY"),
+        ) {
+            return Ok(Credentials {
+                access_key,
+                secret_key,
+                session_token: processor.var("AWS_SESSION_TOKEN"),
+            });
+        }
+        if let Some(credentials) = shared_credentials(processor)? {
+            return Ok(credentials);
+        }
+
+        let args: Vec<String> = ["configure", "export-credentials", "--format", "process"]
+            .iter()
+            .map(|arg| String::from(*arg))
+            .collect();
+        let output = processor.run("aws", &args, &[]).map_err(|error| {
+            format!(
+                "Error: no AWS credentials found, set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, add them to ~/.aws/credentials or install the AWS CLI for roles, SSO and instance profiles: {}",
+                error
+            )
+        })?;
+        let exported: Value = serde_yaml::from_slice(&output)?;
+        match (
+            exported["AccessKeyId"].as_str(),
+            exported["SecretAccessKey"].as_str(),
+        ) {
+            (Some(access_key), Some(secret_key)) => Ok(Credentials {
+                access_key: String::from(access_key),
+                secret_key: String::from(secret_key),
+                session_token: exported["SessionToken"].as_str().map(String::from),
+            }),
+            _ => Err(String::from(
+                "Error: aws configure export-credentials did not print an access key.",
+            )
+            .into()),
+        }
+    }
+
+    /// The access key for AWS_PROFILE in the shared credentials file, if it has
+    /// one. Profiles that assume a role or use SSO are left to the AWS CLI.
+    fn shared_credentials(
+        processor: &impl FileProcessor,
+    ) -> Result<Option<Credentials>, Box<dyn std::error::Error>> {
+        let path = match (
+            processor.var("AWS_SHARED_CREDENTIALS_FILE"),
+            processor.var("HOME"),
+        ) {
+            (Some(path), _) => path,
+            (None, Some(home)) => format!("{}/.aws/credentials", home),
+            (None, None) => return Ok(None),
+        };
+        if !processor.is_file(&path) {
+            return Ok(None);
+        }
+        let profile = processor
+            .var("AWS_PROFILE")
+            .unwrap_or_else(|| String::from("default"));
+        let contents = processor.read_to_string(&path)?;
+        let mut section = None;
+        let mut values = Vec::new();
+        for line in contents.lines().map(str::trim) {
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                section = Some(name.trim());
+            } else if let (Some((key, value)), true) =
+                (line.split_once('='), section == Some(&profile))
+            {
+                values.push((key.trim(), value.trim()));
+            }
+        }
+        let value = |key: &str| {
+            values
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| String::from(*value))
+        };
+        Ok(
+            match (value("aws_access_key_id"), value("aws_secret_access_key")) {
+                (Some(access_key), Some(secret_key)) => Some(Credentials {
+                    access_key,
+                    secret_key,
+                    session_token: value("aws_session_token"),
+                }),
+                _ => None,
+            },
+        )
+    }
+
+    /// Signs a request with Signature Version 4, adding the date, session token
+    /// and Authorization headers. Every header already on the request is signed.
+    fn sign(
+        request: Request,
+        credentials: &Credentials,
+        region: &str,
+        service: &str,
+        now: i64,
+    ) -> Result<Request, Box<dyn std::error::Error>> {
+        // Hashes and the signature are in plain lowercase hex
+        let hex = |bytes: &[u8]| cms::hex(bytes).replace(':', "");
+        let time = ASN1Time::from_timestamp(now)?.to_datetime();
+        let date = format!(
+            "{:04}{:02}{:02}",
+            time.year(),
+            u8::from(time.month()),
+            time.day()
+        );
+        let timestamp = format!(
+            "{}T{:02}{:02}{:02}Z",
+            date,
+            time.hour(),
+            time.minute(),
+            time.second()
+        );
+        let mut request = request.header("X-Amz-Date", &timestamp);
+        if let Some(token) = &credentials.session_token {
+            request = request.header("X-Amz-Security-Token", token);
+        }
+
+        let mut headers: Vec<(String, String)> = request
+            .headers
+            .iter()
+            .map(|(name, value)| (name.to_lowercase(), String::from(value.trim())))
+            .collect();
+        headers.push((String::from("host"), request.url.authority()));
+        headers.sort();
+        let signed_headers: Vec<&str> = headers.iter().map(|(name, _)| name.as_str()).collect();
+        let signed_headers = signed_headers.join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+        let (path, query) = request
+            .url
+            .path
+            .split_once('?')
+            .unwrap_or((&request.url.path, ""));
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            request.method,
+            path,
+            query,
+            canonical_headers,
+            signed_headers,
+            hex(digest::digest(&digest::SHA256, &request.body).as_ref())
+        );
+
+        let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref())
+        );
+        let key = [date.as_str(), region, service, "aws4_request"]
+            .iter()
+            .fold(
+                format!("AWS4{}", credentials.secret_key).into_bytes(),
+                |key, part| {
+                    let key = hmac::Key::new(hmac::HMAC_SHA256, &key);
+                    hmac::sign(&key, part.as_bytes()).as_ref().to_vec()
+                },
+            );
+        let signature = hmac::sign(
+            &hmac::Key::new(hmac::HMAC_SHA256, &key),
+            string_to_sign.as_bytes(),
+        );
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key,
+            scope,
+            signed_headers,
+            hex(signature.as_ref())
+        );
+        Ok(request.header("Authorization", &authorization))
+    }
+
+    /// Makes a signed request, turning AWS's error answers into their message.
+    fn call(
+        processor: &impl FileProcessor,
+        connect: &ConnectOptions,
+        request: Request,
+        region: &str,
+        service: &str,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let credentials = credentials(processor)?;
+        let url = request.url.clone();
+        let request = sign(request, &credentials, region, service, processor.now())?;
+        let response = processor.http(&request, connect)?;
+        if (200..300).contains(&response.status) {
+            return Ok(response.body);
+        }
+        // ACM answers errors in JSON and IAM in XML, both say what went wrong
+        let body = String::from_utf8_lossy(&response.body);
+        let message = serde_yaml::from_str::<Value>(&body)
+            .ok()
+            .and_then(|error| {
+                error
+                    .get("message")
+                    .or_else(|| error.get("Message"))
+                    .and_then(Value::as_str)
+                    .map(String::from)
+            })
+            .or_else(|| texts(&body, "Message").into_iter().next());
+        match message {
+            Some(message) => Err(format!(
+                "Error: {} answered with HTTP status {}: {}",
+                url, response.status, message
+            )
+            .into()),
+            None => Err(format!(
+                "Error: {} answered with HTTP status {}",
+                url, response.status
+            )
+            .into()),
+        }
+    }
+
+    /// The text of every element called `name`, for the XML IAM answers with.
+    fn texts(xml: &str, name: &str) -> Vec<String> {
+        let (open, close) = (format!("<{}>", name), format!("</{}>", name));
+        let mut found = Vec::new();
+        let mut rest = xml;
+        while let Some(start) = rest.find(&open) {
+            rest = &rest[start + open.len()..];
+            let end = match rest.find(&close) {
+                Some(end) => end,
+                None => break,
+            };
+            found.push(
+                rest[..end]
+                    .replace("&#xD;", "\r")
+                    .replace("&#xA;", "\n")
+                    .replace("&lt;", "<")
+                    .replace("&gt;", ">")
+                    .replace("&quot;", "\"")
+                    .replace("&apos;", "'")
+                    .replace("&amp;", "&"),
+            );
+            rest = &rest[end + close.len()..];
+        }
+        found
+    }
+
+    /// The first certificate in PEM text.
+    fn certificate(pem: &str) -> Option<Vec<u8>> {
+        Pem::iter_from_buffer(pem.as_bytes())
+            .filter_map(Result::ok)
+            .find(|block| block.label == "CERTIFICATE")
+            .map(|block| block.contents)
+    }
+
+    /// Reads every certificate in ACM for a region, or every IAM server
+    /// certificate, named by domain and ARN, or by name.
+    pub fn read(
+        processor: &impl FileProcessor,
+        options: &AwsOptions,
+        connect: &ConnectOptions,
+    ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        match options.service {
+            AwsService::Acm => {
+                let region = options
+                    .region
+                    .clone()
+                    .or_else(|| processor.var("AWS_REGION"))
+                    .or_else(|| processor.var("AWS_DEFAULT_REGION"))
+                    .ok_or(
+                        "Error: ACM certificates are regional, give --region or set AWS_REGION.",
+                    )?;
+                acm(processor, &region, connect)
+            }
+            AwsService::Iam => iam(processor, connect),
+        }
+    }
+
+    fn acm(
+        processor: &impl FileProcessor,
+        region: &str,
+        connect: &ConnectOptions,
+    ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        let url = Url::parse(&format!("https://acm.{}.amazonaws.com/", region))?;
+        let action = |target: &str, body: String| -> Result<Value, Box<dyn std::error::Error>> {
+            let request = Request::post(url.clone(), body.into_bytes())
+                .header("Content-Type", "application/x-amz-json-1.1")
+                .header("X-Amz-Target", &format!("CertificateManager.{}", target));
+            let body = call(processor, connect, request, region, "acm")?;
+            serde_yaml::from_slice::<Value>(&body).map_err(|error| {
+                format!(
+                    "Error: {} did not answer {} with JSON: {}",
+                    url, target, error
+                )
+                .into()
+            })
+        };
+
+        let mut summaries = Vec::new();
+        let mut next_token: Option<String> = None;
+        loop {
+            let token = next_token
+                .as_deref()
+                .map(|token| format!(r#","NextToken":{}"#, crate::json::string(token)))
+                .unwrap_or_default();
+            let body = format!(
+                r#"{{"MaxItems":1000,"Includes":{{"keyTypes":{}}}{}}}"#,
+                KEY_TYPES, token
+            );
+            let page = action("ListCertificates", body)?;
+            if let Some(list) = page["CertificateSummaryList"].as_sequence() {
+                summaries.extend(list.iter().cloned());
+            }
+            next_token = page["NextToken"].as_str().map(String::from);
+            if next_token.is_none() {
+                break;
+            }
+        }
+
+        let mut certificates = Vec::new();
+        for summary in summaries {
+            let arn = summary["CertificateArn"].as_str().unwrap_or_default();
+            let status = summary["Status"].as_str().unwrap_or_default();
+            if arn.is_empty() || UNISSUED.contains(&status) {
+                continue;
+            }
+            let body = format!(r#"{{"CertificateArn":{}}}"#, crate::json::string(arn));
+            let fetched = action("GetCertificate", body)?;
+            let der = fetched["Certificate"]
+                .as_str()
+                .and_then(certificate)
+                .ok_or_else(|| {
+                    format!("Error: ACM did not answer with the certificate {}.", arn)
+                })?;
+            let domain = summary["DomainName"].as_str().unwrap_or("(no domain)");
+            let id = arn.rsplit(':').next().unwrap_or(arn);
+            certificates.push((format!("{} ({})", domain, id), der));
+        }
+        Ok(certificates)
+    }
+
+    fn iam(
+        processor: &impl FileProcessor,
+        connect: &ConnectOptions,
+    ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        let url = Url::parse(IAM_ENDPOINT)?;
+        let action = |parameters: String| {
+            let body = format!("Version=2010-05-08&{}", parameters);
+            let request = Request::post(url.clone(), body.into_bytes()).header(
+                "Content-Type",
+                "application/x-www-form-urlencoded; charset=utf-8",
+            );
+            let body = call(processor, connect, request, IAM_REGION, "iam")?;
+            Ok::<String, Box<dyn std::error::Error>>(String::from_utf8_lossy(&body).into_owned())
+        };
+
+        let mut names = Vec::new();
+        let mut marker: Option<String> = None;
+        loop {
+            let page = match &marker {
+                Some(marker) => format!(
+                    "Action=ListServerCertificates&Marker={}",
+                    percent_encode(marker)
+                ),
+                None => String::from("Action=ListServerCertificates"),
+            };
+            let page = action(page)?;
+            names.extend(texts(&page, "ServerCertificateName"));
+            marker = match texts(&page, "IsTruncated").first().map(String::as_str) {
+                Some("true") => texts(&page, "Marker").into_iter().next(),
+                _ => None,
+            };
+            if marker.is_none() {
+                break;
+            }
+        }
+
+        let mut certificates = Vec::new();
+        for name in names {
+            let fetched = action(format!(
+                "Action=GetServerCertificate&ServerCertificateName={}",
+                percent_encode(&name)
+            ))?;
+            let der = texts(&fetched, "CertificateBody")
+                .first()
+                .and_then(|pem| certificate(pem))
+                .ok_or_else(|| {
+                    format!(
+                        "Error: IAM did not answer with the server certificate {}.",
+                        name
+                    )
+                })?;
+            certificates.push((name, der));
+        }
+        Ok(certificates)
+    }
+
+    #[cfg(test)]
+    mod test {
+
+        use std::collections::HashMap;
+
+        use super::{credentials, read, sign, texts, Credentials};
+        use crate::aws::{AwsOptions, AwsService};
+        use crate::http::{Request, Response, Url};
+        use crate::remote::ConnectOptions;
+        use crate::test::FakeProcessor;
+
+        fn ok(body: &str) -> Response {
+            Response {
+                status: 200,
+                headers: Vec::new(),
+                body: body.as_bytes().to_vec(),
+            }
+        }
+
+        fn processor() -> FakeProcessor {
+            FakeProcessor {
+                vars: HashMap::from([
+                    (
+                        String::from("AWS_ACCESS_KEY_ID"),
+                        String::from("AKIDEXAMPLE"),
+                    ),
+                    (
+                        String::from("AWS_SECRET_ACCESS_KEY"),
+                        String::from("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY"),
+                    ),
+                ]),
+                now: 1791936000,
+                ..FakeProcessor::default()
+            }
+        }
+
+        fn pem() -> String {
+            String::from_utf8(include_bytes!("../resources/self-signed.crt").to_vec()).unwrap()
+        }
+
+        #[test]
+        fn should_sign_requests_as_the_signature_v4_test_suite_does() {
+            let credentials = Credentials {
+                access_key: String::from("AKIDEXAMPLE"),
+                secret_key: String::from("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY"),
+                session_token: None,
+            };
+            let url = Url::parse("https://example.amazonaws.com/").unwrap();
+            // get-vanilla, at 20150830T123600Z
+            let signed = sign(
+                Request::get(url),
+                &credentials,
+                "us-east-1",
+                "service",
+                1440938160,
+            )
+            .unwrap();
+            assert_eq!(
+                signed.headers,
+                vec![
+                    (String::from("X-Amz-Date"), String::from("20150830T123600Z")),
+                    (
+                        String::from("Authorization"),
+                        String::from("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31")
+                    ),
+                ]
+            );
+        }
+
+        #[test]
+        fn should_read_credentials_from_the_shared_file_or_the_cli() {
+            let mut processor = FakeProcessor::default();
+            processor
+                .vars
+                .insert(String::from("HOME"), String::from("/home/user"));
+            processor
+                .vars
+                .insert(String::from("AWS_PROFILE"), String::from("audit"));
+            processor.files.insert(
+                String::from("/home/user/.aws/credentials"),
+                String::from("[default]\naws_access_key_id = AKIDDEFAULT\naws_secret_access_key = default\n\n[audit]\naws_access_key_id = AKIDAUDIT\naws_secret_access_key = secret\naws_session_token = token\n"),
+            );
+            assert_eq!(
+                credentials(&processor).unwrap(),
+                Credentials {
+                    access_key: String::from("AKIDAUDIT"),
+                    secret_key: String::from("secret"),
+                    session_token: Some(String::from("token")),
+                }
+            );
+
+            // A profile without an access key, such as one that assumes a role, is the CLI's to resolve
+            processor
+                .vars
+                .insert(String::from("AWS_PROFILE"), String::from("deploy"));
+            assert!(credentials(&processor)
+                .err()
+                .unwrap()
+                .to_string()
+                .starts_with("Error: no AWS credentials found"));
+            processor.commands.insert(
+                String::from("aws configure export-credentials --format process"),
+                String::from(r#"{"Version":1,"AccessKeyId":"ASIAROLE","SecretAccessKey":"role","SessionToken":"session","Expiration":"2026-10-15T12:00:00Z"}"#),
+            );
+            assert_eq!(
+                credentials(&processor).unwrap(),
+                Credentials {
+                    access_key: String::from("ASIAROLE"),
+                    secret_key: String::from("role"),
+                    session_token: Some(String::from("session")),
+                }
+            );
+        }
+
+        #[test]
+        fn should_read_every_issued_acm_certificate() {
+            let mut processor = processor();
+            let url = "https://acm.eu-west-1.amazonaws.com/";
+            processor.responses.insert(
+                format!("{} {}", url, r#"{"MaxItems":1000,"Includes":{"keyTypes":["RSA_1024","RSA_2048","RSA_3072","RSA_4096","EC_prime256v1","EC_secp384r1","EC_secp521r1"]}}"#),
+                ok(r#"{"CertificateSummaryList":[{"CertificateArn":"arn:aws:acm:eu-west-1:123456789012:certificate/1234","DomainName":"self-signed.example","Status":"ISSUED"},{"CertificateArn":"arn:aws:acm:eu-west-1:123456789012:certificate/5678","DomainName":"pending.example","Status":"PENDING_VALIDATION"}]}"#),
+            );
+            processor.responses.insert(
+                format!(
+                    "{} {}",
+                    url,
+                    r#"{"CertificateArn":"arn:aws:acm:eu-west-1:123456789012:certificate/1234"}"#
+                ),
+                ok(&format!(
+                    r#"{{"Certificate":{}}}"#,
+                    crate::json::string(&pem())
+                )),
+            );
+
+            let options = AwsOptions {
+                service: AwsService::Acm,
+                region: Some(String::from("eu-west-1")),
+            };
+            let certificates = read(&processor, &options, &ConnectOptions::default()).unwrap();
+            assert_eq!(certificates.len(), 1);
+            assert_eq!(certificates[0].0, "self-signed.example (certificate/1234)");
+            assert_eq!(options.to_string(), "AWS ACM in eu-west-1");
+        }
+
+        #[test]
+        fn should_read_iam_server_certificates_page_by_page() {
+            let mut processor = processor();
+            let url = "https://iam.amazonaws.com/";
+            let member = |name: &str| {
+                format!(
+                    "<member><ServerCertificateName>{}</ServerCertificateName></member>",
+                    name
+                )
+            };
+            processor.responses.insert(
+                format!("{} Version=2010-05-08&Action=ListServerCertificates", url),
+                ok(&format!("<ListServerCertificatesResponse><ListServerCertificatesResult><IsTruncated>true</IsTruncated><Marker>page 2</Marker><ServerCertificateMetadataList>{}</ServerCertificateMetadataList></ListServerCertificatesResult></ListServerCertificatesResponse>", member("legacy-elb"))),
+            );
+            processor.responses.insert(
+                format!("{} Version=2010-05-08&Action=ListServerCertificates&Marker=page%202", url),
+                ok(&format!("<ListServerCertificatesResponse><ListServerCertificatesResult><IsTruncated>false</IsTruncated><ServerCertificateMetadataList>{}</ServerCertificateMetadataList></ListServerCertificatesResult></ListServerCertificatesResponse>", member("cloudfront"))),
+            );
+            for name in ["legacy-elb", "cloudfront"] {
+                processor.responses.insert(
+                    format!("{} Version=2010-05-08&Action=GetServerCertificate&ServerCertificateName={}", url, name),
+                    ok(&format!("<GetServerCertificateResponse><GetServerCertificateResult><ServerCertificate><CertificateBody>{}</CertificateBody></ServerCertificate></GetServerCertificateResult></GetServerCertificateResponse>", pem().replace('\n', "&#xA;"))),
+                );
+            }
+
+            let options = AwsOptions {
+                service: AwsService::Iam,
+                region: None,
+            };
+            let certificates = read(&processor, &options, &ConnectOptions::default()).unwrap();
+            let names: Vec<&str> = certificates.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(names, vec!["legacy-elb", "cloudfront"]);
+        }
+
+        #[test]
+        fn should_say_why_aws_refused() {
+            let mut processor = processor();
+            processor.responses.insert(
+                String::from("https://iam.amazonaws.com/ Version=2010-05-08&Action=ListServerCertificates"),
+                Response {
+                    status: 403,
+                    headers: Vec::new(),
+                    body: b"<ErrorResponse><Error><Code>AccessDenied</Code><Message>User is not authorized to perform: iam:ListServerCertificates</Message></Error></ErrorResponse>".to_vec(),
+                },
+            );
+            let options = AwsOptions {
+                service: AwsService::Iam,
+                region: None,
+            };
+            assert_eq!(
+                read(&processor, &options, &ConnectOptions::default())
+                    .err()
+                    .unwrap()
+                    .to_string(),
+                "Error: https://iam.amazonaws.com/ answered with HTTP status 403: User is not authorized to perform: iam:ListServerCertificates"
+            );
+            assert_eq!(texts("<a>1</a><a>&lt;2&gt;</a>", "a"), vec!["1", "<2>"]);
+        }
+    }
+}
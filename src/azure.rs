@@ -0,0 +1,214 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_yaml::Value;
+
+use crate::http::{percent_encode, Request, Url};
+use crate::remote::ConnectOptions;
+use crate::store::NamedCertificate;
+use crate::FileProcessor;
+
+const API_VERSION: &str = "7.4";
+
+/// The vault's URL, for a bare name or a host name in another Azure cloud.
+fn base(vault: &str) -> Result<Url, Box<dyn std::error::Error>> {
+    let vault = vault.trim_start_matches("https://").trim_end_matches('/');
+    let host = if vault.contains('.') {
+        String::from(vault)
+    } else {
+        format!("{}.vault.azure.net", vault)
+    };
+    Url::parse(&format!("https://{}/", host))
+}
+
+/// Gets a token for Key Vault as the service principal in AZURE_TENANT_ID,
+/// AZURE_CLIENT_ID and AZURE_CLIENT_SECRET, or else from the Azure CLI.
+/// The resource is the vault's domain, which differs in sovereign clouds.
+fn token(
+    processor: &impl FileProcessor,
+    resource: &str,
+    connect: &ConnectOptions,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if let (Some(tenant), Some(client), Some(secret)) = (
+        processor.var("AZURE_TENANT_ID"),
+        processor.var("AZURE_CLIENT_ID"),
+        processor.var("AZURE_CLIENT_SECRET"),
+    ) {
+        let authority = processor
+            .var("AZURE_AUTHORITY_HOST")
+            .unwrap_or_else(|| String::from("https://login.microsoftonline.com"));
+        let url = Url::parse(&format!(
+            "{}/{}/oauth2/v2.0/token",
+            authority.trim_end_matches('/'),
+            tenant
+        ))?;
+        let body = format!(
+            "grant_type=client_credentials&client_id={}&client_secret={}&scope={}",
+            percent_encode(&client),
+            percent_encode(&secret),
+            percent_encode(&format!("{}/.default", resource))
+        );
+        let request = Request::post(url.clone(), body.into_bytes())
+            .header("Content-Type", "application/x-www-form-urlencoded");
+        let response = processor.http(&request, connect)?.success(&url)?;
+        let answer: Value = serde_yaml::from_slice(&response.body)?;
+        return answer["access_token"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| format!("Error: {} did not answer with an access token.", url).into());
+    }
+
+    let args: Vec<String> = [
+        "account",
+        "get-access-token",
+        "--resource",
+        resource,
+        "--query",
+        "accessToken",
+        "--output",
+        "tsv",
+    ]
+    .iter()
+    .map(|arg| String::from(*arg))
+    .collect();
+    let output = processor.run("az", &args, &[]).map_err(|error| {
+        format!(
+            "Error: could not get an Azure access token, set AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_CLIENT_SECRET or run az login: {}",
+            error
+        )
+    })?;
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+/// Reads the current version of every certificate in a Key Vault.
+pub fn read(
+    processor: &impl FileProcessor,
+    vault: &str,
+    connect: &ConnectOptions,
+) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    let base = base(vault)?;
+    let domain = base.host.split_once('.').map_or("", |(_, domain)| domain);
+    let token = token(processor, &format!("https://{}", domain), connect)?;
+    let get = |url: &str| -> Result<Value, Box<dyn std::error::Error>> {
+        let url = Url::parse(url)?;
+        let request =
+            Request::get(url.clone()).header("Authorization", &format!("Bearer {}", token));
+        let response = processor.http(&request, connect)?.success(&url)?;
+        Ok(serde_yaml::from_slice(&response.body)?)
+    };
+
+    let mut ids = Vec::new();
+    let mut next = Some(format!("{}certificates?api-version={}", base, API_VERSION));
+    while let Some(url) = next {
+        let page = get(&url)?;
+        if let Some(list) = page["value"].as_sequence() {
+            ids.extend(
+                list.iter()
+                    .filter_map(|item| item["id"].as_str())
+                    .map(String::from),
+            );
+        }
+        next = page["nextLink"].as_str().map(String::from);
+    }
+
+    let mut certificates = Vec::new();
+    for id in ids {
+        let fetched = get(&format!("{}?api-version={}", id, API_VERSION))?;
+        let der = fetched["cer"]
+            .as_str()
+            .and_then(|cer| STANDARD.decode(cer).ok())
+            .ok_or_else(|| format!("Error: {} has no certificate.", id))?;
+        let name = id.rsplit('/').next().unwrap_or(&id);
+        certificates.push((String::from(name), der));
+    }
+    Ok(certificates)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::collections::HashMap;
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    use super::read;
+    use crate::http::Response;
+    use crate::remote::ConnectOptions;
+    use crate::test::{self_signed_der, FakeProcessor};
+
+    fn ok(body: &str) -> Response {
+        Response {
+            status: 200,
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn should_read_every_certificate_in_a_key_vault() {
+        let mut processor = FakeProcessor {
+            vars: HashMap::from([
+                (String::from("AZURE_TENANT_ID"), String::from("contoso")),
+                (String::from("AZURE_CLIENT_ID"), String::from("auditor")),
+                (String::from("AZURE_CLIENT_SECRET"), String::from("s3cret&")),
+            ]),
+            ..FakeProcessor::default()
+        };
+        processor.responses.insert(
+            String::from("https://login.microsoftonline.com/contoso/oauth2/v2.0/token"),
+            ok(r#"{"token_type":"Bearer","access_token":"eyJ0"}"#),
+        );
+        let vault = "https://contoso-prod.vault.azure.net/certificates";
+        processor.responses.insert(
+            format!("{}?api-version=7.4", vault),
+            ok(&format!(
+                r#"{{"value":[{{"id":"{0}/www"}}],"nextLink":"{0}?api-version=7.4&$skiptoken=2"}}"#,
+                vault
+            )),
+        );
+        processor.responses.insert(
+            format!("{}?api-version=7.4&$skiptoken=2", vault),
+            ok(&format!(r#"{{"value":[{{"id":"{}/api"}}]}}"#, vault)),
+        );
+        let cer = STANDARD.encode(self_signed_der());
+        for name in ["www", "api"] {
+            processor.responses.insert(
+                format!("{}/{}?api-version=7.4", vault, name),
+                ok(&format!(r#"{{"cer":"{}"}}"#, cer)),
+            );
+        }
+
+        let certificates = read(&processor, "contoso-prod", &ConnectOptions::default()).unwrap();
+        let names: Vec<&str> = certificates.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["www", "api"]);
+        assert_eq!(certificates[0].1, self_signed_der());
+        let sent = processor.sent.lock().unwrap();
+        let body = &sent["https://login.microsoftonline.com/contoso/oauth2/v2.0/token"];
+        assert!(String::from_utf8_lossy(body).contains("client_secret=s3cret%26"));
+    }
+
+    #[test]
+    fn should_fall_back_to_the_azure_cli() {
+        let mut processor = FakeProcessor::default();
+        assert!(read(&processor, "contoso-prod", &ConnectOptions::default())
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("Error: could not get an Azure access token"));
+
+        processor
+            .commands
+            .insert(String::from("az"), String::from("eyJ0\n"));
+        processor.responses.insert(
+            String::from("https://contoso.vault.azure.cn/certificates?api-version=7.4"),
+            ok(r#"{"value":[]}"#),
+        );
+        let certificates = read(
+            &processor,
+            "contoso.vault.azure.cn",
+            &ConnectOptions::default(),
+        )
+        .unwrap();
+        assert!(certificates.is_empty());
+    }
+}
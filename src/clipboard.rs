@@ -0,0 +1,170 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+
+use crate::pem;
+use crate::report::Entry;
+use crate::trust_path::fingerprint;
+
+/// What to copy to the clipboard once certificates are decoded, from `--copy`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Copyable {
+    /// The SHA-256 fingerprint of the first certificate, the leaf of a chain.
+    Fingerprint,
+    /// The base64 SHA-256 of the first certificate's public key, as HPKP and
+    /// curl's `--pinnedpubkey` take it.
+    Pin,
+    /// Every certificate as clean PEM, without whatever it was pasted with.
+    Pem,
+}
+
+impl Copyable {
+    pub fn parse(value: &str) -> Result<Copyable, Box<dyn std::error::Error>> {
+        match value.to_lowercase().as_str() {
+            "fingerprint" => Ok(Copyable::Fingerprint),
+            "pin" => Ok(Copyable::Pin),
+            "pem" => Ok(Copyable::Pem),
+            _ => Err(format!(
+                "Error: --copy expects fingerprint, pin or pem, not {}.",
+                value
+            )
+            .into()),
+        }
+    }
+
+    /// What to copy of the decoded certificates, and what to call it.
+    pub fn text(&self, entries: &[Entry]) -> Option<(&'static str, String)> {
+        let leaf = entries.first()?;
+        Some(match self {
+            Copyable::Fingerprint => ("SHA-256 fingerprint", fingerprint(&leaf.der)),
+            Copyable::Pin => {
+                let cert = leaf.certificate();
+                let pin = STANDARD.encode(digest(&SHA256, cert.public_key().raw));
+                ("SPKI pin", pin)
+            }
+            Copyable::Pem => {
+                let pems: Vec<String> = entries
+                    .iter()
+                    .map(|entry| pem::encode("CERTIFICATE", &entry.der))
+                    .collect();
+                ("PEM", pems.concat())
+            }
+        })
+    }
+}
+
+/// The programs that read and write the clipboard on each platform, tried in
+/// order. Linux has one for Wayland and two for X11.
+#[cfg(target_os = "macos")]
+const PROGRAMS: &[(&str, &[&str], &[&str])] = &[("pbpaste", &[], &[])];
+#[cfg(windows)]
+const PROGRAMS: &[(&str, &[&str], &[&str])] = &[(
+    "powershell",
+    &["-NoProfile", "-Command", "Get-Clipboard -Raw"],
+    &["-NoProfile", "-Command", "$input | Set-Clipboard"],
+)];
+#[cfg(not(any(target_os = "macos", windows)))]
+const PROGRAMS: &[(&str, &[&str], &[&str])] = &[
+    ("wl-paste", &["--no-newline"], &[]),
+    (
+        "xclip",
+        &["-selection", "clipboard", "-out"],
+        &["-selection", "clipboard", "-in"],
+    ),
+    (
+        "xsel",
+        &["--clipboard", "--output"],
+        &["--clipboard", "--input"],
+    ),
+];
+
+/// The program that writes the clipboard, where it isn't the one that reads it.
+fn writer(reader: &str) -> &str {
+    match reader {
+        "pbpaste" => "pbcopy",
+        "wl-paste" => "wl-copy",
+        program => program,
+    }
+}
+
+/// Reads the system clipboard with the first of its programs that works, as
+/// wl-paste is often installed on X11 desktops where it can't.
+pub fn read() -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut failed = None;
+    for (program, read_args, _) in PROGRAMS {
+        let output = match Command::new(program)
+            .args(*read_args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+        {
+            Ok(output) => output,
+            Err(_) => continue,
+        };
+        if !output.status.success() {
+            failed = Some(format!(
+                "Error: could not read the clipboard, {} failed.",
+                program
+            ));
+            continue;
+        }
+        return Ok(output.stdout);
+    }
+    Err(failed.unwrap_or_else(unavailable).into())
+}
+
+/// Writes text to the system clipboard with the first of its programs that works.
+pub fn write(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut failed = None;
+    for (program, _, write_args) in PROGRAMS {
+        let program = writer(program);
+        let mut child = match Command::new(program)
+            .args(*write_args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(_) => continue,
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if !child.wait()?.success() {
+            failed = Some(format!(
+                "Error: could not copy to the clipboard, {} failed.",
+                program
+            ));
+            continue;
+        }
+        return Ok(());
+    }
+    Err(failed.unwrap_or_else(unavailable).into())
+}
+
+fn unavailable() -> String {
+    let programs: Vec<&str> = PROGRAMS.iter().map(|(program, _, _)| *program).collect();
+    format!(
+        "Error: could not find a program to use the clipboard with, install {}.",
+        programs.join(" or ")
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Copyable;
+
+    #[test]
+    fn should_parse_what_to_copy() {
+        assert_eq!(Copyable::parse("Pin").unwrap(), Copyable::Pin);
+        assert_eq!(
+            format!("{}", Copyable::parse("serial").err().unwrap()),
+            "Error: --copy expects fingerprint, pin or pem, not serial."
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use crate::aws::{self, AwsOptions, AwsService};
+use crate::azure;
+use crate::gcp;
+use crate::remote::ConnectOptions;
+use crate::store::NamedCertificate;
+use crate::FileProcessor;
+
+/// Where in a cloud to read certificates from, from the `aws` commands or `--cloud`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cloud {
+    Aws(AwsOptions),
+    /// An Azure Key Vault, by name or host name.
+    Azure(String),
+    /// A Google Cloud project's Certificate Manager, in a location.
+    Gcp {
+        project: String,
+        location: String,
+    },
+}
+
+impl Cloud {
+    /// Parses `--cloud aws-acm[:REGION]`, `aws-iam`, `azure:VAULT` or
+    /// `gcp:PROJECT[:LOCATION]`.
+    pub fn parse(s: &str) -> Result<Cloud, String> {
+        let err_msg = "Error: --cloud expects aws-acm[:REGION], aws-iam, azure:VAULT or gcp:PROJECT[:LOCATION].";
+        let (service, rest) = match s.split_once(':') {
+            Some((service, rest)) if !rest.is_empty() => (service, Some(rest)),
+            _ => (s, None),
+        };
+        match (service, rest) {
+            ("aws-acm", region) => Ok(Cloud::Aws(AwsOptions {
+                service: AwsService::Acm,
+                region: region.map(String::from),
+            })),
+            ("aws-iam", None) => Ok(Cloud::Aws(AwsOptions {
+                service: AwsService::Iam,
+                region: None,
+            })),
+            ("azure", Some(vault)) => Ok(Cloud::Azure(String::from(vault))),
+            ("gcp", Some(rest)) => {
+                let (project, location) = rest.split_once(':').unwrap_or((rest, "global"));
+                Ok(Cloud::Gcp {
+                    project: String::from(project),
+                    location: String::from(location),
+                })
+            }
+            _ => Err(String::from(err_msg)),
+        }
+    }
+}
+
+impl fmt::Display for Cloud {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cloud::Aws(aws) => write!(f, "{}", aws),
+            Cloud::Azure(vault) => write!(f, "Azure Key Vault {}", vault),
+            Cloud::Gcp { project, location } => {
+                write!(f, "GCP Certificate Manager in {} ({})", project, location)
+            }
+        }
+    }
+}
+
+/// Reads every certificate the cloud holds, named as the cloud names them.
+pub fn read(
+    processor: &impl FileProcessor,
+    cloud: &Cloud,
+    connect: &ConnectOptions,
+) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    match cloud {
+        Cloud::Aws(options) => aws::read(processor, options, connect),
+        Cloud::Azure(vault) => azure::read(processor, vault, connect),
+        Cloud::Gcp { project, location } => gcp::read(processor, project, location, connect),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Cloud;
+    use crate::aws::{AwsOptions, AwsService};
+
+    #[test]
+    fn should_parse_cloud_sources() {
+        assert_eq!(
+            Cloud::parse("aws-acm:eu-west-1"),
+            Ok(Cloud::Aws(AwsOptions {
+                service: AwsService::Acm,
+                region: Some(String::from("eu-west-1")),
+            }))
+        );
+        assert_eq!(
+            Cloud::parse("azure:contoso-prod").unwrap().to_string(),
+            "Azure Key Vault contoso-prod"
+        );
+        assert_eq!(
+            Cloud::parse("gcp:my-project"),
+            Ok(Cloud::Gcp {
+                project: String::from("my-project"),
+                location: String::from("global"),
+            })
+        );
+        assert_eq!(
+            Cloud::parse("gcp:my-project:europe-west1")
+                .unwrap()
+                .to_string(),
+            "GCP Certificate Manager in my-project (europe-west1)"
+        );
+        assert!(Cloud::parse("azure").is_err());
+        assert!(Cloud::parse("aws-iam:us-east-1").is_err());
+        assert!(Cloud::parse("oracle:tenancy").is_err());
+    }
+}
@@ -0,0 +1,468 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rustls::sign::CertifiedKey;
+use serde_yaml::Value;
+use x509_parser::pem::Pem;
+
+use crate::http::{percent_encode, Request, Trust, Url};
+use crate::k8s;
+use crate::keystore;
+use crate::kubeconfig::{Kubeconfig, Material, User};
+use crate::remote::{self, ConnectOptions, Server};
+use crate::report::Report;
+use crate::store::NamedCertificate;
+use crate::FileProcessor;
+
+/// How many objects to ask the API server for at a time.
+const PAGE_SIZE: usize = 500;
+
+/// The kinds of admission webhook configuration, whose webhooks carry a CA bundle.
+const WEBHOOK_CONFIGURATIONS: &[(&str, &str)] = &[
+    ("validating", "validatingwebhookconfigurations"),
+    ("mutating", "mutatingwebhookconfigurations"),
+];
+
+/// Whether to scan the cluster itself or only the kubeconfig for it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClusterCommand {
+    Scan,
+    Kubeconfig,
+}
+
+/// What `cert-decoder k8s scan` or `cert-decoder k8s kubeconfig` should look at.
+#[derive(Debug, PartialEq)]
+pub struct ClusterOptions {
+    pub command: ClusterCommand,
+    /// The kubeconfig to use instead of the default one, from `--kubeconfig`.
+    pub kubeconfig: Option<String>,
+    /// The context to use instead of the current one, from `--context`.
+    /// When inspecting a kubeconfig, every context is reported on without it.
+    pub context: Option<String>,
+    /// Only scan this namespace rather than all of them, from `--namespace`.
+    pub namespace: Option<String>,
+    /// Also check the CA bundles of admission webhooks, from `--webhooks`.
+    pub webhooks: bool,
+    /// Also check the chain the API server presents, from `--apiserver`.
+    pub apiserver: bool,
+    /// Certificates expiring within this long are called out, from `--expiring-within`.
+    pub expiring_within: Duration,
+}
+
+impl Default for ClusterOptions {
+    fn default() -> ClusterOptions {
+        ClusterOptions {
+            command: ClusterCommand::Scan,
+            kubeconfig: None,
+            context: None,
+            namespace: None,
+            webhooks: false,
+            apiserver: false,
+            expiring_within: Duration::from_secs(30 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// The certificates found in a cluster, and which cluster it was.
+pub struct Scan {
+    pub cluster: String,
+    pub report: Report,
+}
+
+/// Collects the certificates of every TLS secret in the cluster of the
+/// kubeconfig context, plus webhook and API server certificates if asked to.
+pub fn scan(
+    processor: &impl FileProcessor,
+    options: &ClusterOptions,
+    connect: &ConnectOptions,
+) -> Result<Scan, Box<dyn std::error::Error>> {
+    let config = Kubeconfig::load(processor, options.kubeconfig.as_deref())?;
+    let context = config.context(options.context.as_deref())?;
+    let cluster = config.cluster(&context.cluster)?;
+    let base = Url::parse(&cluster.server)?;
+    let credentials = credentials(processor, &config, config.user(&context.user)?)?;
+
+    let trust = if cluster.insecure_skip_tls_verify {
+        Trust::Insecure
+    } else if let Some(ca) = &cluster.certificate_authority {
+        Trust::Roots(pem_certificates(&ca.load(processor, &config.path)?))
+    } else {
+        Trust::System
+    };
+    let api = Api {
+        processor,
+        connect,
+        base: base.clone(),
+        trust,
+        credentials,
+    };
+
+    let mut report = Report::default();
+    let secrets = match &options.namespace {
+        Some(namespace) => format!("TLS secrets in {}", namespace),
+        None => String::from("TLS secrets"),
+    };
+    add(
+        &mut report,
+        &secrets,
+        api.secrets(options.namespace.as_deref()),
+    );
+    if options.webhooks {
+        add(&mut report, "webhooks", api.webhook_bundles());
+    }
+    if options.apiserver {
+        let server = Server {
+            host: base.host.clone(),
+            port: base.port,
+            address: None,
+        };
+        let chain = processor.handshake(&server, connect).map(|handshake| {
+            keystore::chain_entries(&server.to_string(), handshake.chain.into_iter())
+        });
+        add(&mut report, "apiserver", chain);
+    }
+
+    Ok(Scan {
+        cluster: format!("{} ({})", context.name, cluster.server),
+        report,
+    })
+}
+
+/// Adds what was found in one part of the cluster. Finding nothing isn't a
+/// failure, plenty of clusters have no webhooks, but it is worth mentioning.
+fn add(
+    report: &mut Report,
+    source: &str,
+    result: Result<Vec<NamedCertificate>, Box<dyn std::error::Error>>,
+) {
+    match result {
+        Ok(items) if items.is_empty() => report
+            .warnings
+            .push(format!("Warning: no certificates found in the {}.", source)),
+        result => report.add_named(source, result),
+    }
+}
+
+/// How to authenticate to the API server.
+struct Credentials {
+    identity: Option<Arc<CertifiedKey>>,
+    token: Option<String>,
+}
+
+/// The client certificate and bearer token to authenticate as `user` with.
+fn credentials(
+    processor: &impl FileProcessor,
+    config: &Kubeconfig,
+    user: &User,
+) -> Result<Credentials, Box<dyn std::error::Error>> {
+    let load = |material: &Option<Material>| match material {
+        Some(material) => material.load(processor, &config.path).map(Some),
+        None => Ok(None),
+    };
+    let mut cert = load(&user.client_certificate)?;
+    let mut key = load(&user.client_key)?;
+    let mut token = match (&user.token, &user.token_file) {
+        (Some(token), _) => Some(token.clone()),
+        (None, Some(file)) => Some(processor.read_to_string(file)?.trim().to_string()),
+        (None, None) => None,
+    };
+
+    // Cloud providers hand out short lived credentials from a plugin like kubectl does
+    if let Some(exec) = &user.exec {
+        let info = format!(
+            r#"{{"apiVersion":"{}","kind":"ExecCredential","spec":{{"interactive":false}}}}"#,
+            exec.api_version
+        );
+        let mut env = exec.env.clone();
+        env.push((String::from("KUBERNETES_EXEC_INFO"), info));
+        let output = processor.run(&exec.command, &exec.args, &env)?;
+        let credential: Value = serde_yaml::from_slice(&output).map_err(|error| {
+            format!(
+                "Error: {} did not print an ExecCredential: {}",
+                exec.command, error
+            )
+        })?;
+        let status = &credential["status"];
+        let string = |key: &str| status[key].as_str().map(String::from);
+        token = string("token").or(token);
+        if let (Some(exec_cert), Some(exec_key)) =
+            (string("clientCertificateData"), string("clientKeyData"))
+        {
+            cert = Some(exec_cert.into_bytes());
+            key = Some(exec_key.into_bytes());
+        }
+    } else if let (Some(provider), None) = (&user.auth_provider, &token) {
+        return Err(format!(
+            "Error: user {} uses the {} auth-provider, which Kubernetes no longer supports, switch to its exec plugin.",
+            user.name, provider
+        )
+        .into());
+    }
+
+    let identity = match (cert, key) {
+        (Some(cert), Some(key)) => Some(remote::load_identity(&cert, &key)?),
+        _ => None,
+    };
+    Ok(Credentials { identity, token })
+}
+
+/// Makes authenticated requests to the API server.
+struct Api<'a, P: FileProcessor> {
+    processor: &'a P,
+    connect: &'a ConnectOptions,
+    base: Url,
+    trust: Trust,
+    credentials: Credentials,
+}
+
+impl<P: FileProcessor> Api<'_, P> {
+    /// Fetches every object of a list, a page at a time.
+    fn list(&self, path: &str) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        let mut next = String::new();
+        loop {
+            let mut page = format!("{}{}limit={}", path, separator, PAGE_SIZE);
+            if !next.is_empty() {
+                page.push_str(&format!("&continue={}", percent_encode(&next)));
+            }
+            let list = self.get(&page)?;
+            items.extend(list["items"].as_sequence().cloned().unwrap_or_default());
+            match list["metadata"]["continue"].as_str() {
+                Some(token) if !token.is_empty() => next = String::from(token),
+                _ => return Ok(items),
+            }
+        }
+    }
+
+    fn get(&self, path: &str) -> Result<Value, Box<dyn std::error::Error>> {
+        // Servers behind a proxy such as Rancher's have a path of their own
+        let mut url = self.base.clone();
+        url.path = format!("{}{}", self.base.path.trim_end_matches('/'), path);
+
+        let mut request = Request::get(url.clone()).header("Accept", "application/json");
+        if let Some(token) = &self.credentials.token {
+            request = request.header("Authorization", &format!("Bearer {}", token));
+        }
+        request.trust = self.trust.clone();
+        request.identity = self.credentials.identity.clone();
+
+        let response = self.processor.http(&request, self.connect)?.success(&url)?;
+        serde_yaml::from_slice(&response.body)
+            .map_err(|error| format!("Error: {} did not answer with JSON: {}", url, error).into())
+    }
+
+    fn secrets(
+        &self,
+        namespace: Option<&str>,
+    ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        let path = match namespace {
+            Some(namespace) => format!("/api/v1/namespaces/{}/secrets", percent_encode(namespace)),
+            None => String::from("/api/v1/secrets"),
+        };
+        let path = format!("{}?fieldSelector=type%3Dkubernetes.io%2Ftls", path);
+
+        let mut found = Vec::new();
+        for secret in self.list(&path)? {
+            k8s::collect(&secret, Some("Secret"), &mut found)?;
+        }
+        Ok(found)
+    }
+
+    /// The CA bundles admission webhooks are called with, as injected by cert-manager.
+    fn webhook_bundles(&self) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        let mut found = Vec::new();
+        for (kind, resource) in WEBHOOK_CONFIGURATIONS {
+            let path = format!("/apis/admissionregistration.k8s.io/v1/{}", resource);
+            for configuration in self.list(&path)? {
+                let name = configuration["metadata"]["name"]
+                    .as_str()
+                    .unwrap_or("(unnamed)");
+                for webhook in configuration["webhooks"]
+                    .as_sequence()
+                    .into_iter()
+                    .flatten()
+                {
+                    let bundle = match webhook["clientConfig"]["caBundle"].as_str() {
+                        Some(bundle) => bundle,
+                        None => continue,
+                    };
+                    let label = format!(
+                        "{} {}/{} caBundle",
+                        kind,
+                        name,
+                        webhook["name"].as_str().unwrap_or("(unnamed)")
+                    );
+                    let pem = STANDARD.decode(bundle.trim()).map_err(|error| {
+                        format!("Error: {} is not valid base64: {}", label, error)
+                    })?;
+                    found.extend(k8s::pem_chain(&label, &pem)?);
+                }
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// The DER of every certificate in a PEM bundle.
+fn pem_certificates(pem: &[u8]) -> Vec<Vec<u8>> {
+    Pem::iter_from_buffer(pem)
+        .filter_map(Result::ok)
+        .filter(|block| block.label == "CERTIFICATE")
+        .map(|block| block.contents)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    use super::{scan, ClusterOptions};
+    use crate::http::Response;
+    use crate::remote::ConnectOptions;
+    use crate::test::{self_signed_der, FakeProcessor};
+
+    const CERT: &str = include_str!("../resources/self-signed.crt");
+    const SECRETS: &str =
+        "https://192.0.2.10:6443/api/v1/secrets?fieldSelector=type%3Dkubernetes.io%2Ftls&limit=500";
+
+    fn kubeconfig(user: &str) -> String {
+        format!(
+            "current-context: dev\ncontexts:\n- name: dev\n  context: {{cluster: dev, user: dev}}\nclusters:\n- name: dev\n  cluster:\n    server: https://192.0.2.10:6443\n    certificate-authority-data: {}\nusers:\n- name: dev\n  user:\n{}",
+            STANDARD.encode(CERT),
+            user
+        )
+    }
+
+    fn ok(body: &str) -> Response {
+        Response {
+            status: 200,
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn secret(name: &str) -> String {
+        format!(
+            r#"{{"metadata": {{"name": "{}", "namespace": "prod"}}, "type": "kubernetes.io/tls", "data": {{"tls.crt": "{}"}}}}"#,
+            name,
+            STANDARD.encode(CERT)
+        )
+    }
+
+    #[test]
+    fn should_collect_tls_secrets_across_pages() {
+        let mut processor = FakeProcessor::default();
+        processor
+            .vars
+            .insert(String::from("HOME"), String::from("/home/me"));
+        processor.add_file("/home/me/.kube/config", &kubeconfig("    token: abc\n"));
+        let first = format!(
+            r#"{{"kind": "SecretList", "metadata": {{"continue": "page=2"}}, "items": [{}]}}"#,
+            secret("web")
+        );
+        let second = format!(
+            r#"{{"kind": "SecretList", "metadata": {{}}, "items": [{}]}}"#,
+            secret("api")
+        );
+        processor
+            .responses
+            .insert(String::from(SECRETS), ok(&first));
+        processor
+            .responses
+            .insert(format!("{}&continue=page%3D2", SECRETS), ok(&second));
+
+        let scan = scan(
+            &processor,
+            &ClusterOptions::default(),
+            &ConnectOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(scan.cluster, "dev (https://192.0.2.10:6443)");
+        let locations: Vec<_> = scan
+            .report
+            .entries
+            .iter()
+            .map(|entry| entry.location.as_str())
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                "TLS secrets: prod/web tls.crt",
+                "TLS secrets: prod/api tls.crt"
+            ]
+        );
+        assert_eq!(scan.report.entries[0].der, self_signed_der());
+    }
+
+    #[test]
+    fn should_authenticate_with_an_exec_plugin_and_check_webhooks() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file(
+            "config",
+            &kubeconfig("    exec:\n      apiVersion: client.authentication.k8s.io/v1\n      command: aws\n"),
+        );
+        processor.commands.insert(
+            String::from("aws"),
+            String::from(r#"{"kind": "ExecCredential", "status": {"token": "k8s-aws-v1.abc"}}"#),
+        );
+        processor.responses.insert(
+            String::from(SECRETS),
+            ok(r#"{"kind": "SecretList", "metadata": {}, "items": []}"#),
+        );
+        let webhooks = format!(
+            r#"{{"items": [{{"metadata": {{"name": "cert-manager-webhook"}}, "webhooks": [{{"name": "webhook.cert-manager.io", "clientConfig": {{"caBundle": "{}"}}}}]}}]}}"#,
+            STANDARD.encode(CERT)
+        );
+        let api = "https://192.0.2.10:6443/apis/admissionregistration.k8s.io/v1";
+        processor.responses.insert(
+            format!("{}/validatingwebhookconfigurations?limit=500", api),
+            ok(&webhooks),
+        );
+        processor.responses.insert(
+            format!("{}/mutatingwebhookconfigurations?limit=500", api),
+            ok(r#"{"items": []}"#),
+        );
+        let options = ClusterOptions {
+            kubeconfig: Some(String::from("config")),
+            webhooks: true,
+            ..ClusterOptions::default()
+        };
+
+        let scan = scan(&processor, &options, &ConnectOptions::default()).unwrap();
+
+        assert_eq!(
+            scan.report.entries[0].location,
+            "webhooks: validating cert-manager-webhook/webhook.cert-manager.io caBundle"
+        );
+        assert_eq!(
+            scan.report.warnings,
+            vec!["Warning: no certificates found in the TLS secrets."]
+        );
+    }
+
+    #[test]
+    fn should_reject_removed_auth_providers() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file(
+            "config",
+            &kubeconfig("    auth-provider:\n      name: gcp\n"),
+        );
+        let options = ClusterOptions {
+            kubeconfig: Some(String::from("config")),
+            ..ClusterOptions::default()
+        };
+
+        let result = scan(&processor, &options, &ConnectOptions::default());
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: user dev uses the gcp auth-provider, which Kubernetes no longer supports, switch to its exec plugin."
+        );
+    }
+}
@@ -0,0 +1,600 @@
+use x509_parser::asn1_rs::BitString;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::certification_request::X509CertificationRequest;
+use x509_parser::extensions::GeneralName;
+use x509_parser::parse_x509_certificate;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+use x509_parser::verify::verify_signature;
+use x509_parser::x509::{AlgorithmIdentifier, X509Name};
+
+use crate::cms::encode;
+use crate::cms::{self, element, Tlv};
+use crate::dn;
+use crate::store::NamedCertificate;
+
+/// The bodies RFC 4210 and RFC 9810 define, by their tag number.
+const BODIES: &[(&str, &str)] = &[
+    ("ir", "initialization request"),
+    ("ip", "initialization response"),
+    ("cr", "certification request"),
+    ("cp", "certification response"),
+    ("p10cr", "PKCS#10 certification request"),
+    ("popdecc", "proof-of-possession challenge"),
+    ("popdecr", "proof-of-possession response"),
+    ("kur", "key update request"),
+    ("kup", "key update response"),
+    ("krr", "key recovery request"),
+    ("krp", "key recovery response"),
+    ("rr", "revocation request"),
+    ("rp", "revocation response"),
+    ("ccr", "cross-certification request"),
+    ("ccp", "cross-certification response"),
+    ("ckuann", "CA key update announcement"),
+    ("cann", "certificate announcement"),
+    ("rann", "revocation announcement"),
+    ("crlann", "CRL announcement"),
+    ("pkiconf", "confirmation"),
+    ("nested", "nested message"),
+    ("genm", "general message"),
+    ("genp", "general response"),
+    ("error", "error"),
+    ("certConf", "certificate confirmation"),
+    ("pollReq", "polling request"),
+    ("pollRep", "polling response"),
+];
+
+/// The statuses of a PKIStatusInfo, by their number.
+const STATUSES: &[&str] = &[
+    "accepted",
+    "grantedWithMods",
+    "rejection",
+    "waiting",
+    "revocationWarning",
+    "revocationNotification",
+    "keyUpdateWarning",
+];
+
+/// The reasons of a PKIFailureInfo, by their bit.
+const FAILURES: &[&str] = &[
+    "badAlg",
+    "badMessageCheck",
+    "badRequest",
+    "badTime",
+    "badCertId",
+    "badDataFormat",
+    "wrongAuthority",
+    "incorrectData",
+    "missingTimeStamp",
+    "badPOP",
+    "certRevoked",
+    "certConfirmed",
+    "wrongIntegrity",
+    "badRecipientNonce",
+    "timeNotAvailable",
+    "unacceptedPolicy",
+    "unacceptedExtension",
+    "addInfoNotAvailable",
+    "badSenderNonce",
+    "badCertTemplate",
+    "signerNotTrusted",
+    "transactionIdInUse",
+    "unsupportedVersion",
+    "notAuthorized",
+    "systemUnavail",
+    "systemFailure",
+    "duplicateCertReq",
+];
+
+/// The MACs a message can be protected with instead of a signature.
+const MACS: &[(&str, &str)] = &[
+    ("1.2.840.113533.7.66.13", "password-based MAC"),
+    ("1.2.840.113533.7.66.30", "Diffie-Hellman based MAC"),
+    ("1.2.840.113549.1.5.14", "PBMAC1"),
+    ("1.3.6.1.5.5.7.6.26", "KEM-based MAC"),
+];
+
+/// The InfoTypeAndValue types of the id-it arc, by their last number.
+const INFO_TYPES: &[(u8, &str)] = &[
+    (1, "caProtEncCert"),
+    (2, "signKeyPairTypes"),
+    (3, "encKeyPairTypes"),
+    (4, "preferredSymmAlg"),
+    (5, "caKeyUpdateInfo"),
+    (6, "currentCRL"),
+    (7, "unsupportedOIDs"),
+    (10, "keyPairParamReq"),
+    (11, "keyPairParamRep"),
+    (12, "revPassphrase"),
+    (13, "implicitConfirm"),
+    (14, "confirmWaitTime"),
+    (15, "origPKIMessage"),
+    (16, "suppLangTags"),
+    (17, "caCerts"),
+    (18, "rootCaKeyUpdate"),
+    (19, "certReqTemplate"),
+    (20, "rootCaCert"),
+    (21, "certProfile"),
+    (22, "crlStatusList"),
+    (23, "crls"),
+];
+
+/// The id-it arc, 1.3.6.1.5.5.7.4.
+const ID_IT: &str = "1.3.6.1.5.5.7.4.";
+
+/// An RFC 4210 PKIMessage, as CMP clients and CAs exchange.
+pub struct PkiMessage {
+    pub location: String,
+    pub version: u64,
+    /// The short name of the body, such as `ir`, and what it is.
+    pub body: (&'static str, &'static str),
+    pub sender: String,
+    pub recipient: String,
+    pub message_time: Option<String>,
+    pub transaction_id: Option<String>,
+    pub sender_kid: Option<String>,
+    /// How the message is protected, and whether that was checked.
+    pub protection: Option<String>,
+    /// The general info of the header, by name.
+    pub general_info: Vec<String>,
+    /// What the body holds, a line each.
+    pub details: Vec<String>,
+    /// The extra certificates, then those in the body.
+    pub certificates: Vec<NamedCertificate>,
+}
+
+/// The DER of a PKIMessage, telling it from certificates and other
+/// structures by its header starting with a version and a GeneralName.
+pub fn find(bytes: &[u8]) -> Option<Vec<u8>> {
+    let (message, rest) = element(bytes)?;
+    if message.tag != 0x30 || !rest.is_empty() {
+        return None;
+    }
+    let fields = message.children()?;
+    let header = fields
+        .first()
+        .filter(|header| header.tag == 0x30)?
+        .children()?;
+    let body = fields.get(1)?;
+    match header.as_slice() {
+        [version, sender, recipient, ..]
+            if version.tag == 0x02
+                && sender.tag & 0x80 != 0
+                && recipient.tag & 0x80 != 0
+                && (0xa0..0xa0 + BODIES.len() as u8).contains(&body.tag) =>
+        {
+            Some(bytes.to_vec())
+        }
+        _ => None,
+    }
+}
+
+pub fn read(source: &str, der: &[u8]) -> Result<PkiMessage, Box<dyn std::error::Error>> {
+    message(source, der)
+        .ok_or_else(|| format!("Error: {} is not a valid CMP message.", source).into())
+}
+
+fn message(source: &str, der: &[u8]) -> Option<PkiMessage> {
+    let (message, _) = element(der)?;
+    let fields = message.children()?;
+    let (header, body) = (fields.first()?, fields.get(1)?);
+    let header_fields = header.children()?;
+    let tag = (body.tag - 0xa0) as usize;
+
+    let mut cmp = PkiMessage {
+        location: String::from(source),
+        version: unsigned(header_fields.first()?.contents)?,
+        body: *BODIES.get(tag)?,
+        sender: general_name(header_fields.get(1)?)?,
+        recipient: general_name(header_fields.get(2)?)?,
+        message_time: None,
+        transaction_id: None,
+        sender_kid: None,
+        protection: None,
+        general_info: Vec::new(),
+        details: Vec::new(),
+        certificates: Vec::new(),
+    };
+    let mut protection_algorithm = None;
+    for field in &header_fields[3..] {
+        let inner = field.children()?.into_iter().next()?;
+        match field.tag {
+            0xa0 => {
+                cmp.message_time = Some(ASN1Time::from_der(inner.raw).ok()?.1.to_string());
+            }
+            0xa1 => protection_algorithm = Some(inner),
+            0xa2 => cmp.sender_kid = Some(key_id(inner.contents)),
+            0xa4 => cmp.transaction_id = Some(cms::hex(inner.contents)),
+            0xa8 => {
+                for info in inner.children()? {
+                    cmp.general_info.push(info_type(info.children()?.first()?)?);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The body's tag can be the same as those of the optional fields after it
+    let optional = fields.get(2..).unwrap_or_default();
+    let extra_certs = match optional.iter().find(|field| field.tag == 0xa1) {
+        Some(extra) => extra.children()?.first()?.children()?,
+        None => Vec::new(),
+    };
+    for (i, cert) in extra_certs.iter().enumerate() {
+        cmp.certificates
+            .push((format!("extraCerts [{}]", i + 1), cert.raw.to_vec()));
+    }
+    let protection = optional.iter().find(|field| field.tag == 0xa0);
+    cmp.protection = match (protection_algorithm, protection) {
+        (Some(algorithm), Some(protection)) => {
+            let signed = encode(0x30, &[header.raw, body.raw].concat());
+            let signature = protection.children()?;
+            let signer = extra_certs.first().map(|cert| cert.raw);
+            Some(describe_protection(
+                &algorithm,
+                signature.first()?,
+                &signed,
+                signer,
+            )?)
+        }
+        _ => None,
+    };
+
+    let content = body.children()?;
+    let content = content.first()?;
+    cmp.details = match cmp.body.0 {
+        "ir" | "cr" | "kur" | "ccr" => certificate_requests(content)?,
+        "ip" | "cp" | "kup" | "ccp" => certificate_responses(content, &mut cmp.certificates)?,
+        "p10cr" => {
+            let (_, request) = X509CertificationRequest::from_der(content.raw).ok()?;
+            vec![format!(
+                "Requested subject: {}",
+                dn::to_string(&request.certification_request_info.subject)
+            )]
+        }
+        "rr" => revocation_requests(content)?,
+        "rp" => {
+            let statuses = content.children()?.first()?.children()?;
+            statuses
+                .iter()
+                .map(|status| Some(format!("Status: {}", status_info(status)?)))
+                .collect::<Option<_>>()?
+        }
+        "genm" | "genp" => content
+            .children()?
+            .iter()
+            .map(|info| Some(format!("Info: {}", info_type(info.children()?.first()?)?)))
+            .collect::<Option<_>>()?,
+        "error" => {
+            let parts = content.children()?;
+            let mut lines = vec![format!("Status: {}", status_info(parts.first()?)?)];
+            for part in &parts[1..] {
+                match part.tag {
+                    0x02 => lines.push(format!("Error code: {}", unsigned(part.contents)?)),
+                    _ => lines.push(format!("Error details: {}", free_text(part)?)),
+                }
+            }
+            lines
+        }
+        "certConf" => content
+            .children()?
+            .iter()
+            .map(|status| {
+                let parts = status.children()?;
+                let id = unsigned(parts.get(1)?.contents)?;
+                let status = match parts.get(2) {
+                    Some(info) if info.tag == 0x30 => status_info(info)?,
+                    _ => String::from("accepted"),
+                };
+                Some(format!("Confirms request {}: {}", id, status))
+            })
+            .collect::<Option<_>>()?,
+        _ => Vec::new(),
+    };
+    Some(cmp)
+}
+
+impl PkiMessage {
+    /// What is worth pointing out about the message.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        // RFC 4210 lets an error be unprotected when the sender can't protect it
+        if self.protection.is_none() && self.body.0 != "error" {
+            warnings.push(format!(
+                "Warning: {} is an unprotected CMP message, so nothing shows who sent it or that it was not changed.",
+                self.location
+            ));
+        }
+        warnings
+    }
+}
+
+/// How a message is protected, checking a signature against the first extra
+/// certificate, which RFC 4210 has be the one to verify it with.
+fn describe_protection(
+    algorithm: &Tlv,
+    protection: &Tlv,
+    signed: &[u8],
+    signer: Option<&[u8]>,
+) -> Option<String> {
+    let oid = algorithm.children()?.first()?.oid()?.to_id_string();
+    if let Some((_, mac)) = MACS.iter().find(|(known, _)| *known == oid) {
+        return Some(match pbm_iterations(algorithm) {
+            Some(iterations) => format!("{} with {} iterations", mac, iterations),
+            None => String::from(*mac),
+        });
+    }
+    let name = cms::algorithm(algorithm)?;
+    let signer = match signer.and_then(|der| parse_x509_certificate(der).ok()) {
+        Some((_, signer)) => signer,
+        None => {
+            return Some(format!(
+                "signed with {}, not checked without extra certificates",
+                name
+            ))
+        }
+    };
+    let signer_name = dn::to_string(signer.subject());
+    Some(match is_signed_by(algorithm, protection, signed, &signer) {
+        true => format!("signed with {} by {}, verified", name, signer_name),
+        false => format!(
+            "signed with {}, but not by the first extra certificate, {}",
+            name, signer_name
+        ),
+    })
+}
+
+fn is_signed_by(algorithm: &Tlv, protection: &Tlv, signed: &[u8], cert: &X509Certificate) -> bool {
+    let algorithm = AlgorithmIdentifier::from_der(algorithm.raw);
+    let signature = BitString::from_der(protection.raw);
+    match (algorithm, signature) {
+        (Ok((_, algorithm)), Ok((_, signature))) => {
+            verify_signature(cert.public_key(), &algorithm, &signature, signed).is_ok()
+        }
+        _ => false,
+    }
+}
+
+/// The iteration count of a PBMParameter.
+fn pbm_iterations(algorithm: &Tlv) -> Option<u64> {
+    let parameters = algorithm.children()?.get(1)?.children()?;
+    unsigned(
+        parameters
+            .get(2)
+            .filter(|count| count.tag == 0x02)?
+            .contents,
+    )
+}
+
+/// The request IDs of CertReqMessages, with the subject of their templates.
+fn certificate_requests(requests: &Tlv) -> Option<Vec<String>> {
+    let mut lines = Vec::new();
+    for request in requests.children()? {
+        let request = request.children()?;
+        let fields = request.first()?.children()?;
+        let id = unsigned(fields.first()?.contents)?;
+        let template = fields.get(1)?.children()?;
+        let subject = template
+            .iter()
+            .find(|field| field.tag == 0xa5)
+            .and_then(|subject| X509Name::from_der(subject.contents).ok())
+            .map(|(_, subject)| dn::to_string(&subject));
+        lines.push(match subject {
+            Some(subject) => format!("Request {}: subject {}", id, subject),
+            None => format!("Request {}", id),
+        });
+    }
+    Some(lines)
+}
+
+/// The outcome of each request in a CertRepMessage, adding the CA
+/// certificates and the issued ones to those of the message.
+fn certificate_responses(
+    responses: &Tlv,
+    certificates: &mut Vec<NamedCertificate>,
+) -> Option<Vec<String>> {
+    let mut lines = Vec::new();
+    let fields = responses.children()?;
+    if let Some(ca_pubs) = fields.iter().find(|field| field.tag == 0xa1) {
+        for (i, cert) in ca_pubs.children()?.first()?.children()?.iter().enumerate() {
+            certificates.push((format!("caPubs [{}]", i + 1), cert.raw.to_vec()));
+        }
+    }
+    for response in fields.last()?.children()? {
+        let parts = response.children()?;
+        let id = unsigned(parts.first()?.contents)?;
+        let mut line = format!("Request {}: {}", id, status_info(parts.get(1)?)?);
+        if let Some(pair) = parts.get(2).filter(|pair| pair.tag == 0x30) {
+            let cert = pair.children()?;
+            let cert = cert.first()?;
+            match cert.tag {
+                0xa0 => {
+                    let der = cert.children()?.first()?.raw.to_vec();
+                    certificates.push((format!("certificate for request {}", id), der));
+                    line.push_str(", certificate issued");
+                }
+                _ => line.push_str(", certificate issued encrypted"),
+            }
+        }
+        lines.push(line);
+    }
+    Some(lines)
+}
+
+/// The certificates a RevReqContent asks to revoke, by serial and issuer.
+fn revocation_requests(requests: &Tlv) -> Option<Vec<String>> {
+    let mut lines = Vec::new();
+    for details in requests.children()? {
+        let template = details.children()?.first()?.children()?;
+        let serial = template
+            .iter()
+            .find(|field| field.tag == 0x81)
+            .map(|serial| cms::hex(serial.contents));
+        let issuer = template
+            .iter()
+            .find(|field| field.tag == 0xa3)
+            .and_then(|issuer| X509Name::from_der(issuer.contents).ok())
+            .map(|(_, issuer)| dn::to_string(&issuer));
+        lines.push(format!(
+            "Revoke: serial {}, issued by {}",
+            serial.as_deref().unwrap_or("not given"),
+            issuer.as_deref().unwrap_or("not given")
+        ));
+    }
+    Some(lines)
+}
+
+/// A PKIStatusInfo, as its status, text and the failures it names.
+fn status_info(info: &Tlv) -> Option<String> {
+    let parts = info.children()?;
+    let status = unsigned(parts.first()?.contents)?;
+    let mut status = STATUSES
+        .get(status as usize)
+        .map_or_else(|| format!("status {}", status), |name| String::from(*name));
+    for part in &parts[1..] {
+        match part.tag {
+            0x30 => status.push_str(&format!(", {}", free_text(part)?)),
+            0x03 => {
+                let failures = failures(part.contents);
+                if !failures.is_empty() {
+                    status.push_str(&format!(" ({})", failures.join(", ")));
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(status)
+}
+
+/// The names of the bits set in a PKIFailureInfo.
+fn failures(contents: &[u8]) -> Vec<&'static str> {
+    let bits = contents.get(1..).unwrap_or_default();
+    FAILURES
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| {
+            bits.get(bit / 8)
+                .is_some_and(|byte| byte & (0x80 >> (bit % 8)) != 0)
+        })
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// A PKIFreeText, its strings joined.
+fn free_text(text: &Tlv) -> Option<String> {
+    let strings: Vec<String> = text
+        .children()?
+        .iter()
+        .map(|string| String::from_utf8_lossy(string.contents).into_owned())
+        .collect();
+    Some(strings.join("; "))
+}
+
+/// The name of an InfoTypeAndValue's type.
+fn info_type(oid: &Tlv) -> Option<String> {
+    let oid = oid.oid()?.to_id_string();
+    let name = oid
+        .strip_prefix(ID_IT)
+        .and_then(|number| number.parse::<u8>().ok())
+        .and_then(|number| INFO_TYPES.iter().find(|(known, _)| *known == number));
+    Some(name.map_or(oid, |(_, name)| String::from(*name)))
+}
+
+/// The sender or recipient, which is an empty directory name when the
+/// other side knows who it is from the key ID.
+fn general_name(name: &Tlv) -> Option<String> {
+    match GeneralName::from_der(name.raw).ok()?.1 {
+        GeneralName::DirectoryName(name) if name.iter().next().is_none() => {
+            Some(String::from("none"))
+        }
+        GeneralName::DirectoryName(name) => Some(dn::to_string(&name)),
+        name => Some(name.to_string()),
+    }
+}
+
+/// A key ID, which with a password-based MAC is often a reference number in text.
+fn key_id(id: &[u8]) -> String {
+    match std::str::from_utf8(id) {
+        Ok(text) if !text.is_empty() && text.chars().all(|c| c.is_ascii_graphic()) => {
+            String::from(text)
+        }
+        _ => cms::hex(id),
+    }
+}
+
+fn unsigned(bytes: &[u8]) -> Option<u64> {
+    if bytes.is_empty() || bytes.len() > 8 {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .fold(0, |value, byte| value << 8 | *byte as u64),
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{find, read};
+
+    const REQUEST: &[u8] = include_bytes!("../resources/cmp-ir.der");
+    const RESPONSE: &[u8] = include_bytes!("../resources/cmp-ip.der");
+
+    #[test]
+    fn should_decode_the_header_and_requests() {
+        let der = find(REQUEST).unwrap();
+        let message = read("ir.der", &der).unwrap();
+
+        assert_eq!(message.version, 2);
+        assert_eq!(message.body, ("ir", "initialization request"));
+        assert_eq!(message.sender, "CN=device.example");
+        assert_eq!(message.recipient, "CN=self-signed.example");
+        assert_eq!(message.sender_kid.as_deref(), Some("1234"));
+        assert_eq!(
+            message.protection.as_deref(),
+            Some("password-based MAC with 500 iterations")
+        );
+        assert_eq!(
+            message.details,
+            vec!["Request 0: subject CN=device.example"]
+        );
+        assert!(message.certificates.is_empty());
+        assert!(message.warnings().is_empty());
+    }
+
+    #[test]
+    fn should_decode_the_certificates_of_a_response_and_check_its_signature() {
+        let message = read("ip.der", RESPONSE).unwrap();
+
+        assert_eq!(message.body, ("ip", "initialization response"));
+        assert_eq!(
+            message.protection.as_deref(),
+            Some("signed with ecdsa-with-SHA256 by CN=self-signed.example, verified")
+        );
+        assert_eq!(
+            message.details,
+            vec!["Request 0: accepted, certificate issued"]
+        );
+        let names: Vec<&str> = message
+            .certificates
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["extraCerts [1]", "caPubs [1]", "certificate for request 0"]
+        );
+    }
+
+    #[test]
+    fn should_not_take_a_certificate_for_a_message() {
+        let pem = include_str!("../resources/self-signed.crt");
+        let der = x509_parser::pem::parse_x509_pem(pem.as_bytes())
+            .unwrap()
+            .1
+            .contents;
+        assert_eq!(find(&der), None);
+        assert_eq!(find(include_bytes!("../resources/ocsp-request.der")), None);
+    }
+}
@@ -0,0 +1,722 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use ring::digest::{digest, Algorithm, SHA1_FOR_LEGACY_USE_ONLY, SHA256, SHA384, SHA512};
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+use x509_parser::asn1_rs::Any;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::oid::Oid;
+use x509_parser::extensions::ParsedExtension;
+use x509_parser::oid_registry::{
+    OID_EC_P256, OID_HASH_SHA1, OID_NIST_EC_P384, OID_NIST_HASH_SHA256, OID_NIST_HASH_SHA384,
+    OID_NIST_HASH_SHA512, OID_PKCS1_RSASSAPSS, OID_PKCS7_ID_SIGNED_DATA,
+    OID_PKCS9_ID_MESSAGE_DIGEST, OID_PKCS9_SIGNING_TIME, OID_SIG_ED25519,
+};
+use x509_parser::pem::Pem;
+use x509_parser::prelude::FromDer;
+use x509_parser::public_key::PublicKey;
+use x509_parser::signature_algorithm::RsaSsaPssParams;
+use x509_parser::time::ASN1Time;
+
+use crate::oid;
+use crate::pss;
+use crate::timestamp::{self, TstInfo};
+
+/// The content type of the TSTInfo that RFC 3161 timestamp tokens sign.
+const TST_INFO: &str = "1.2.840.113549.1.9.16.1.4";
+
+/// The content type of Authenticode signatures, a digest of the file signed.
+const SPC_INDIRECT_DATA: &str = "1.3.6.1.4.1.311.2.1.4";
+
+/// Unsigned attributes that hold another signature over this one.
+const COUNTERSIGNATURE: &str = "1.2.840.113549.1.9.6";
+const TIMESTAMP_TOKEN: &str = "1.2.840.113549.1.9.16.2.14";
+/// Authenticode's own attributes for a timestamp token and a second, nested signature.
+const MS_TIMESTAMP_TOKEN: &str = "1.3.6.1.4.1.311.3.3.1";
+const MS_NESTED_SIGNATURE: &str = "1.3.6.1.4.1.311.2.4.1";
+
+/// Signature algorithms a SignerInfo can name. CMS allows the bare key
+/// algorithm, with the hash taken from the digest algorithm.
+const RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+const SHA1_WITH_RSA: &str = "1.2.840.113549.1.1.5";
+const RSASSA_PSS: &str = "1.2.840.113549.1.1.10";
+const SHA256_WITH_RSA: &str = "1.2.840.113549.1.1.11";
+const SHA384_WITH_RSA: &str = "1.2.840.113549.1.1.12";
+const SHA512_WITH_RSA: &str = "1.2.840.113549.1.1.13";
+const EC_PUBLIC_KEY: &str = "1.2.840.10045.2.1";
+const ECDSA_WITH_SHA256: &str = "1.2.840.10045.4.3.2";
+const ECDSA_WITH_SHA384: &str = "1.2.840.10045.4.3.3";
+const ECDSA_WITH_SHA512: &str = "1.2.840.10045.4.3.4";
+const ED25519: &str = "1.3.101.112";
+const MGF1: &str = "1.2.840.113549.1.1.8";
+
+/// The parts of a CMS (or PKCS#7) SignedData worth reporting.
+pub struct SignedData {
+    /// What was signed, unless the signature is detached from it.
+    pub content: Option<Vec<u8>>,
+    /// What a TSA vouched for, if this is a timestamp token.
+    pub timestamp: Option<TstInfo>,
+    /// The digest algorithms the signers are meant to be using.
+    pub digest_algorithms: Vec<String>,
+    /// Every certificate included, in the order they appear.
+    pub certificates: Vec<Vec<u8>>,
+    /// The DER of every CRL included.
+    pub crls: Vec<Vec<u8>>,
+    pub signers: Vec<Signer>,
+}
+
+/// How one signer signed, from its SignerInfo.
+pub struct Signer {
+    id: SignerId,
+    /// The type of what was signed, such as `pkcs7-data`.
+    pub content_type: String,
+    pub digest_algorithm: String,
+    pub signature_algorithm: String,
+    /// The signing time attribute, which signers are free to leave out.
+    pub signing_time: Option<String>,
+    /// Every other signed attribute, by name, with its value.
+    pub attributes: Vec<(String, String)>,
+    /// Whether the signature was checked against the content, and how that went.
+    pub verification: Verification,
+    /// Signers that signed this signature in turn, as timestamps used to be made.
+    pub countersigners: Vec<Signer>,
+    /// RFC 3161 timestamp tokens over this signature.
+    pub timestamps: Vec<SignedData>,
+    /// Further signatures over the same content, as Authenticode dual signing adds.
+    pub nested: Vec<SignedData>,
+    digest: Option<&'static Algorithm>,
+    /// The signature algorithm as an OID with its DER parameters, to check with.
+    signature_oid: String,
+    signature_parameters: Option<Vec<u8>>,
+    message_digest: Option<Vec<u8>>,
+    /// The signed attributes as they were signed, with the SET tag rather than `[0]`.
+    signed_attributes: Option<Vec<u8>>,
+    signature: Vec<u8>,
+}
+
+/// How a SignerInfo names the certificate of its signer.
+enum SignerId {
+    IssuerAndSerial { issuer: Vec<u8>, serial: Vec<u8> },
+    KeyId(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Verification {
+    /// Without the content or the signer's certificate there is nothing to check.
+    NotChecked(String),
+    Valid,
+    Invalid(String),
+    /// The signature uses an algorithm, or parameters, that cannot be checked.
+    Unsupported(String),
+}
+
+impl Signer {
+    /// The signature value, which is the content a countersigner signs.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Whether `cert` is the certificate of this signer.
+    pub fn is_signed_by(&self, cert: &X509Certificate) -> bool {
+        match &self.id {
+            SignerId::IssuerAndSerial { issuer, serial } => {
+                cert.issuer().as_raw() == issuer.as_slice()
+                    && cert.raw_serial() == serial.as_slice()
+            }
+            SignerId::KeyId(key_id) => {
+                cert.extensions()
+                    .iter()
+                    .any(|extension| match extension.parsed_extension() {
+                        ParsedExtension::SubjectKeyIdentifier(id) => id.0 == key_id.as_slice(),
+                        _ => false,
+                    })
+            }
+        }
+    }
+
+    /// Checks the signature over `content` with the key of the signer's certificate,
+    /// by way of the message digest attribute when there are signed attributes.
+    pub fn verify(&mut self, cert: &X509Certificate, content: &[u8]) {
+        self.verification = match self.check(cert, content) {
+            Ok(()) => Verification::Valid,
+            Err(verification) => verification,
+        };
+    }
+
+    fn check(&self, cert: &X509Certificate, content: &[u8]) -> Result<(), Verification> {
+        let digest_algorithm = self.digest.ok_or_else(|| {
+            Verification::Unsupported(format!(
+                "the {} digest algorithm is not supported",
+                self.digest_algorithm
+            ))
+        })?;
+        let signed = match &self.signed_attributes {
+            Some(signed_attributes) => {
+                let expected = digest(digest_algorithm, content);
+                if self.message_digest.as_deref() != Some(expected.as_ref()) {
+                    return Err(Verification::Invalid(String::from(
+                        "the content does not match its message digest",
+                    )));
+                }
+                signed_attributes.as_slice()
+            }
+            None => content,
+        };
+
+        let spki = cert.public_key();
+        let algorithm = self.verification_algorithm(cert, digest_algorithm)?;
+        UnparsedPublicKey::new(algorithm, &spki.subject_public_key.data)
+            .verify(signed, &self.signature)
+            .map_err(|_| {
+                Verification::Invalid(String::from(
+                    "the signature does not match the signer's key",
+                ))
+            })
+    }
+
+    /// The algorithm to check the signature with, from the signature algorithm
+    /// the SignerInfo names and the signer's key.
+    fn verification_algorithm(
+        &self,
+        cert: &X509Certificate,
+        digest: &'static Algorithm,
+    ) -> Result<&'static dyn VerificationAlgorithm, Verification> {
+        let unsupported = || {
+            Verification::Unsupported(format!(
+                "the {} signature algorithm is not supported",
+                self.signature_algorithm
+            ))
+        };
+        let hash: &'static Algorithm = match self.signature_oid.as_str() {
+            RSA_ENCRYPTION | EC_PUBLIC_KEY | ED25519 => digest,
+            SHA1_WITH_RSA => &SHA1_FOR_LEGACY_USE_ONLY,
+            SHA256_WITH_RSA | ECDSA_WITH_SHA256 => &SHA256,
+            SHA384_WITH_RSA | ECDSA_WITH_SHA384 => &SHA384,
+            SHA512_WITH_RSA | ECDSA_WITH_SHA512 => &SHA512,
+            RSASSA_PSS => self.pss_hash().ok_or_else(unsupported)?,
+            _ => return Err(unsupported()),
+        };
+
+        let spki = cert.public_key();
+        let rsa = matches!(spki.parsed(), Ok(PublicKey::RSA(_)))
+            || spki.algorithm.algorithm == OID_PKCS1_RSASSAPSS;
+        let ec = matches!(spki.parsed(), Ok(PublicKey::EC(_)));
+        let ed25519 = spki.algorithm.algorithm == OID_SIG_ED25519;
+        let algorithm: &'static dyn VerificationAlgorithm = match self.signature_oid.as_str() {
+            RSASSA_PSS if rsa => {
+                if *hash == SHA256 {
+                    &signature::RSA_PSS_2048_8192_SHA256
+                } else if *hash == SHA384 {
+                    &signature::RSA_PSS_2048_8192_SHA384
+                } else {
+                    &signature::RSA_PSS_2048_8192_SHA512
+                }
+            }
+            RSA_ENCRYPTION | SHA1_WITH_RSA | SHA256_WITH_RSA | SHA384_WITH_RSA
+            | SHA512_WITH_RSA
+                if rsa =>
+            {
+                if *hash == SHA256 {
+                    &signature::RSA_PKCS1_2048_8192_SHA256
+                } else if *hash == SHA384 {
+                    &signature::RSA_PKCS1_2048_8192_SHA384
+                } else if *hash == SHA512 {
+                    &signature::RSA_PKCS1_2048_8192_SHA512
+                } else {
+                    &signature::RSA_PKCS1_2048_8192_SHA1_FOR_LEGACY_USE_ONLY
+                }
+            }
+            EC_PUBLIC_KEY | ECDSA_WITH_SHA256 | ECDSA_WITH_SHA384 | ECDSA_WITH_SHA512 if ec => {
+                let curve = spki
+                    .algorithm
+                    .parameters
+                    .as_ref()
+                    .and_then(|parameters| Oid::try_from(parameters).ok());
+                match curve {
+                    Some(curve) if curve == OID_EC_P256 && *hash == SHA256 => {
+                        &signature::ECDSA_P256_SHA256_ASN1
+                    }
+                    Some(curve) if curve == OID_EC_P256 && *hash == SHA384 => {
+                        &signature::ECDSA_P256_SHA384_ASN1
+                    }
+                    Some(curve) if curve == OID_NIST_EC_P384 && *hash == SHA256 => {
+                        &signature::ECDSA_P384_SHA256_ASN1
+                    }
+                    Some(curve) if curve == OID_NIST_EC_P384 && *hash == SHA384 => {
+                        &signature::ECDSA_P384_SHA384_ASN1
+                    }
+                    _ => return Err(unsupported()),
+                }
+            }
+            ED25519 if ed25519 => &signature::ED25519,
+            _ => {
+                return Err(Verification::Invalid(format!(
+                    "the signer's key cannot make {} signatures",
+                    self.signature_algorithm
+                )))
+            }
+        };
+        Ok(algorithm)
+    }
+
+    /// The hash of RSASSA-PSS parameters that can be checked: SHA-2 for both the
+    /// hash and MGF1, with a salt as long as the hash.
+    fn pss_hash(&self) -> Option<&'static Algorithm> {
+        let (_, parameters) = Any::from_der(self.signature_parameters.as_deref()?).ok()?;
+        let parameters = RsaSsaPssParams::try_from(&parameters).ok()?;
+        let hash_oid = parameters.hash_algorithm_oid();
+        let hash: &'static Algorithm = [
+            (OID_NIST_HASH_SHA256, &SHA256),
+            (OID_NIST_HASH_SHA384, &SHA384),
+            (OID_NIST_HASH_SHA512, &SHA512),
+        ]
+        .iter()
+        .find(|(oid, _)| oid == hash_oid)
+        .map(|(_, algorithm)| *algorithm)?;
+        let mask = parameters.mask_gen_algorithm().ok()?;
+        let suits = mask.mgf.to_id_string() == MGF1
+            && mask.hash == *hash_oid
+            && parameters.salt_length() as usize == hash.output_len()
+            && parameters.trailer_field() == 1;
+        suits.then_some(hash)
+    }
+}
+
+/// The DER of the SignedData in a file, whether it is binary or in a
+/// `PKCS7` or `CMS` PEM block, as `.p7b` and `.p7s` files can be.
+pub fn find(bytes: &[u8]) -> Option<Vec<u8>> {
+    if is_signed_data(bytes) {
+        return Some(bytes.to_vec());
+    }
+    Pem::iter_from_buffer(bytes)
+        .filter_map(Result::ok)
+        .filter(|pem| pem.label == "PKCS7" || pem.label == "CMS")
+        .map(|pem| pem.contents)
+        .find(|der| is_signed_data(der))
+}
+
+fn is_signed_data(bytes: &[u8]) -> bool {
+    let sniff = || {
+        let (info, _) = element(bytes)?;
+        let content_type = info.children()?.first()?.oid()?;
+        Some(content_type == OID_PKCS7_ID_SIGNED_DATA)
+    };
+    sniff().unwrap_or(false)
+}
+
+/// Reads a ContentInfo holding SignedData, in DER or in the
+/// indefinite length BER that streaming signers write.
+pub fn read(source: &str, bytes: &[u8]) -> Result<SignedData, Box<dyn std::error::Error>> {
+    signed_data(bytes)
+        .ok_or_else(|| format!("Error: {} is not valid CMS SignedData.", source).into())
+}
+
+fn signed_data(bytes: &[u8]) -> Option<SignedData> {
+    let (info, _) = element(bytes)?;
+    let info = info.children()?;
+    if info.first()?.oid()? != OID_PKCS7_ID_SIGNED_DATA {
+        return None;
+    }
+    let explicit = info.get(1)?.children()?;
+    let fields = explicit.first()?.children()?;
+    let mut digest_algorithms = Vec::new();
+    for identifier in fields.get(1)?.children()? {
+        digest_algorithms.push(algorithm(&identifier)?);
+    }
+    let encapsulated = fields.get(2)?.children()?;
+    let content = match encapsulated.get(1) {
+        // PKCS#7 allowed any type of content, as Authenticode's SpcIndirectDataContent,
+        // and then it is its contents that are signed
+        Some(explicit) => {
+            let content = explicit.children()?;
+            let content = content.first()?;
+            Some(
+                content
+                    .octets()
+                    .unwrap_or_else(|| content.contents.to_vec()),
+            )
+        }
+        None => None,
+    };
+
+    let content_oid = encapsulated.first()?.oid()?;
+    let content_type = match content_oid.to_id_string().as_str() {
+        TST_INFO => String::from("id-ct-TSTInfo"),
+        SPC_INDIRECT_DATA => String::from("SpcIndirectDataContent"),
+        _ => oid::name(&content_oid),
+    };
+    let timestamp = match &content {
+        Some(content) if content_oid.to_id_string() == TST_INFO => {
+            Some(timestamp::tst_info(content)?)
+        }
+        _ => None,
+    };
+    let mut signed = SignedData {
+        content,
+        timestamp,
+        digest_algorithms,
+        certificates: Vec::new(),
+        crls: Vec::new(),
+        signers: Vec::new(),
+    };
+    for field in fields.iter().skip(3) {
+        match field.tag {
+            // Attribute certificates and other formats can be mixed in, but aren't X.509
+            0xa0 => signed.certificates.extend(
+                field
+                    .children()?
+                    .iter()
+                    .filter(|cert| cert.tag == 0x30)
+                    .map(|cert| cert.raw.to_vec()),
+            ),
+            0xa1 => signed.crls.extend(
+                field
+                    .children()?
+                    .iter()
+                    .filter(|crl| crl.tag == 0x30)
+                    .map(|crl| crl.raw.to_vec()),
+            ),
+            0x31 => {
+                for info in field.children()? {
+                    signed.signers.push(signer(&info, &content_type)?);
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(signed)
+}
+
+fn signer(info: &Tlv, content_type: &str) -> Option<Signer> {
+    let fields = info.children()?;
+    let mut fields = fields.iter().skip(1);
+    let sid = fields.next()?;
+    let id = match sid.tag {
+        0x30 => {
+            let sid = sid.children()?;
+            SignerId::IssuerAndSerial {
+                issuer: sid.first()?.raw.to_vec(),
+                serial: sid.get(1)?.contents.to_vec(),
+            }
+        }
+        0x80 => SignerId::KeyId(sid.contents.to_vec()),
+        _ => return None,
+    };
+    let digest_identifier = fields.next()?;
+    let digest_oid = digest_identifier.children()?.first()?.oid()?;
+    let digest = [
+        (OID_HASH_SHA1, &SHA1_FOR_LEGACY_USE_ONLY),
+        (OID_NIST_HASH_SHA256, &SHA256),
+        (OID_NIST_HASH_SHA384, &SHA384),
+        (OID_NIST_HASH_SHA512, &SHA512),
+    ]
+    .iter()
+    .find(|(oid, _)| *oid == digest_oid)
+    .map(|(_, algorithm)| *algorithm);
+
+    let mut signer = Signer {
+        id,
+        content_type: String::from(content_type),
+        digest_algorithm: oid::name(&digest_oid),
+        signature_algorithm: String::new(),
+        signing_time: None,
+        attributes: Vec::new(),
+        verification: Verification::NotChecked(String::from("the content is detached")),
+        countersigners: Vec::new(),
+        timestamps: Vec::new(),
+        nested: Vec::new(),
+        digest,
+        signature_oid: String::new(),
+        signature_parameters: None,
+        message_digest: None,
+        signed_attributes: None,
+        signature: Vec::new(),
+    };
+    let mut next = fields.next()?;
+    if next.tag == 0xa0 {
+        // The signature covers the attributes as a SET, not as the implicit tag they are sent with
+        let mut signed_attributes = next.raw.to_vec();
+        signed_attributes[0] = 0x31;
+        signer.signed_attributes = Some(signed_attributes);
+        for attribute in next.children()? {
+            let attribute = attribute.children()?;
+            let oid = attribute.first()?.oid()?;
+            let values = attribute.get(1)?.children()?;
+            let value = values.first()?;
+            if oid == OID_PKCS9_SIGNING_TIME {
+                let (_, time) = ASN1Time::from_der(value.raw).ok()?;
+                signer.signing_time = Some(time.to_string());
+            } else {
+                if oid == OID_PKCS9_ID_MESSAGE_DIGEST {
+                    signer.message_digest = Some(value.contents.to_vec());
+                }
+                signer.attributes.push((oid::name(&oid), describe(value)));
+            }
+        }
+        next = fields.next()?;
+    }
+    signer.signature_algorithm = algorithm(next)?;
+    let identifier = next.children()?;
+    signer.signature_oid = identifier.first()?.oid()?.to_id_string();
+    signer.signature_parameters = identifier.get(1).map(|parameters| parameters.raw.to_vec());
+    signer.signature = fields.next()?.octets()?;
+
+    if let Some(unsigned) = fields.next().filter(|unsigned| unsigned.tag == 0xa1) {
+        for attribute in unsigned.children()? {
+            let attribute = attribute.children()?;
+            let oid = attribute.first()?.oid()?.to_id_string();
+            for value in attribute.get(1)?.children()? {
+                match oid.as_str() {
+                    COUNTERSIGNATURE => signer
+                        .countersigners
+                        .push(self::signer(&value, "countersignature")?),
+                    TIMESTAMP_TOKEN | MS_TIMESTAMP_TOKEN => {
+                        signer.timestamps.push(signed_data(value.raw)?)
+                    }
+                    MS_NESTED_SIGNATURE => signer.nested.push(signed_data(value.raw)?),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Some(signer)
+}
+
+/// The name of the algorithm of an AlgorithmIdentifier, with its parameters for RSASSA-PSS.
+pub fn algorithm(identifier: &Tlv) -> Option<String> {
+    let fields = identifier.children()?;
+    let oid = fields.first()?.oid()?;
+    match fields.get(1) {
+        Some(parameters) if oid == OID_PKCS1_RSASSAPSS => {
+            let (_, parameters) = Any::from_der(parameters.raw).ok()?;
+            Some(format!(
+                "{} ({})",
+                oid::name(&oid),
+                pss::describe(&parameters)?
+            ))
+        }
+        _ => Some(oid::name(&oid)),
+    }
+}
+
+/// A short description of an attribute value, for the kinds signers tend to use.
+fn describe(value: &Tlv) -> String {
+    match value.tag {
+        0x06 => value.oid().map(|oid| oid::name(&oid)).unwrap_or_default(),
+        0x04 => hex(value.contents),
+        0x0c | 0x13 | 0x16 => String::from_utf8_lossy(value.contents).into_owned(),
+        0x17 | 0x18 => ASN1Time::from_der(value.raw)
+            .map(|(_, time)| time.to_string())
+            .unwrap_or_default(),
+        _ => format!("{} bytes", value.raw.len()),
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    hex.join(":")
+}
+
+/// One BER element: its tag, its contents and its whole encoding.
+pub struct Tlv<'a> {
+    pub tag: u8,
+    pub contents: &'a [u8],
+    pub raw: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    pub fn children(&self) -> Option<Vec<Tlv<'a>>> {
+        elements(self.contents)
+    }
+
+    pub fn oid(&self) -> Option<Oid<'a>> {
+        if self.tag != 0x06 {
+            return None;
+        }
+        Some(Oid::new(Cow::Borrowed(self.contents)))
+    }
+
+    /// The contents of an OCTET STRING, which BER allows to be sent in pieces.
+    pub fn octets(&self) -> Option<Vec<u8>> {
+        match self.tag {
+            0x04 => Some(self.contents.to_vec()),
+            0x24 => {
+                let mut octets = Vec::new();
+                for piece in self.children()? {
+                    octets.extend(piece.octets()?);
+                }
+                Some(octets)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An element in DER, with the shortest length that holds its contents.
+pub fn encode(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut der = vec![tag];
+    match contents.len() {
+        len if len < 0x80 => der.push(len as u8),
+        len => {
+            let bytes = len.to_be_bytes();
+            let start = bytes.iter().position(|byte| *byte != 0).unwrap_or(0);
+            der.push(0x80 | (bytes.len() - start) as u8);
+            der.extend_from_slice(&bytes[start..]);
+        }
+    }
+    der.extend_from_slice(contents);
+    der
+}
+
+/// Reads the element at the start of `bytes`, returning it and what follows it.
+pub fn element(bytes: &[u8]) -> Option<(Tlv<'_>, &[u8])> {
+    let tag = *bytes.first()?;
+    // High tag numbers never appear in CMS
+    if tag & 0x1f == 0x1f {
+        return None;
+    }
+    let (len, header) = match *bytes.get(1)? {
+        0x80 => {
+            // Indefinite length contents run until a pair of zero bytes
+            let mut rest = bytes.get(2..)?;
+            while !rest.starts_with(&[0, 0]) {
+                let (_, after) = element(rest)?;
+                rest = after;
+            }
+            let end = bytes.len() - rest.len();
+            let tlv = Tlv {
+                tag,
+                contents: &bytes[2..end],
+                raw: &bytes[..end + 2],
+            };
+            return Some((tlv, &bytes[end + 2..]));
+        }
+        len if len < 0x80 => (len as usize, 2),
+        len => {
+            let count = (len & 0x7f) as usize;
+            if count > 4 {
+                return None;
+            }
+            let len = bytes
+                .get(2..2 + count)?
+                .iter()
+                .fold(0, |len, byte| len << 8 | *byte as usize);
+            (len, 2 + count)
+        }
+    };
+    let raw = bytes.get(..header.checked_add(len)?)?;
+    let tlv = Tlv {
+        tag,
+        contents: &raw[header..],
+        raw,
+    };
+    Some((tlv, &bytes[raw.len()..]))
+}
+
+fn elements(mut bytes: &[u8]) -> Option<Vec<Tlv<'_>>> {
+    let mut elements = Vec::new();
+    while !bytes.is_empty() {
+        let (tlv, rest) = element(bytes)?;
+        elements.push(tlv);
+        bytes = rest;
+    }
+    Some(elements)
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+
+    use super::{encode, find, read, Verification};
+    use crate::test::self_signed_der;
+
+    const SIGNATURE: &[u8] = include_bytes!("../resources/release.txt.p7s");
+    const CONTENT: &[u8] = include_bytes!("../resources/release.txt");
+
+    #[test]
+    fn should_decode_the_signer_infos_and_signed_attributes() {
+        let signed = read("release.txt.p7s", SIGNATURE).unwrap();
+
+        assert!(signed.content.is_none());
+        assert_eq!(signed.digest_algorithms, vec!["sha256"]);
+        assert_eq!(signed.certificates.len(), 3);
+        let signer = &signed.signers[0];
+        assert_eq!(signer.content_type, "pkcs7-data");
+        let names: Vec<_> = signer
+            .attributes
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert!(names.contains(&"contentType") && names.contains(&"id-messageDigest"));
+        assert!(signer.signing_time.is_some());
+        assert_eq!(
+            signer.verification,
+            Verification::NotChecked(String::from("the content is detached"))
+        );
+    }
+
+    #[test]
+    fn should_verify_the_signature_over_detached_content() {
+        let mut signed = read("release.txt.p7s", SIGNATURE).unwrap();
+        let der = self_signed_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+        let signer = &mut signed.signers[0];
+
+        signer.verify(&cert, CONTENT);
+        assert_eq!(signer.verification, Verification::Valid);
+        signer.verify(&cert, b"The release is not signed.\n");
+        assert_eq!(
+            signer.verification,
+            Verification::Invalid(String::from(
+                "the content does not match its message digest"
+            ))
+        );
+    }
+
+    #[test]
+    fn should_verify_an_rsassa_pss_signature_by_its_parameters() {
+        let check = |file: &str, signature: &[u8]| {
+            let mut signed = read(file, signature).unwrap();
+            let der = signed.certificates[0].clone();
+            let (_, cert) = parse_x509_certificate(&der).unwrap();
+            let mut signer = signed.signers.remove(0);
+            signer.verify(&cert, CONTENT);
+            signer.verification
+        };
+
+        assert_eq!(
+            check(
+                "release.txt.pss.p7s",
+                include_bytes!("../resources/release.txt.pss.p7s")
+            ),
+            Verification::Valid
+        );
+        assert!(matches!(
+            check(
+                "release.txt.pss-salt20.p7s",
+                include_bytes!("../resources/release.txt.pss-salt20.p7s")
+            ),
+            Verification::Unsupported(reason) if reason.contains("salt length 20")
+        ));
+    }
+
+    #[test]
+    fn should_encode_long_lengths_in_as_few_bytes_as_possible() {
+        assert_eq!(encode(0x04, &[0; 3])[..2], [0x04, 0x03]);
+        assert_eq!(encode(0x04, &[0; 200])[..3], [0x04, 0x81, 200]);
+        assert_eq!(encode(0x04, &[0; 300])[..4], [0x04, 0x82, 0x01, 0x2c]);
+    }
+
+    #[test]
+    fn should_find_certificates_and_crls_in_a_pem_bundle() {
+        let bundle = find(include_bytes!("../resources/bundle.p7b")).unwrap();
+
+        let signed = read("bundle.p7b", &bundle).unwrap();
+
+        assert_eq!(signed.certificates, vec![self_signed_der()]);
+        assert_eq!(signed.crls.len(), 1);
+        assert!(signed.signers.is_empty());
+        assert!(find(include_bytes!("../resources/google.com.crt")).is_none());
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::oid_registry::OID_PKCS1_RSASSAPSS;
+
+use crate::oid;
+use crate::pss;
+use crate::trust_store::{key_info, Curve, KeyKind, WEAK_SIGNATURES};
+
+/// Signature algorithms with an approved hash: RSA PKCS #1 v1.5 and ECDSA with
+/// SHA-2 and SHA-3.
+const APPROVED_SIGNATURES: &[&str] = &[
+    "1.2.840.113549.1.1.11",
+    "1.2.840.113549.1.1.12",
+    "1.2.840.113549.1.1.13",
+    "1.2.840.113549.1.1.14",
+    "1.2.840.10045.4.3.1",
+    "1.2.840.10045.4.3.2",
+    "1.2.840.10045.4.3.3",
+    "1.2.840.10045.4.3.4",
+    "2.16.840.1.101.3.4.3.9",
+    "2.16.840.1.101.3.4.3.10",
+    "2.16.840.1.101.3.4.3.11",
+    "2.16.840.1.101.3.4.3.12",
+    "2.16.840.1.101.3.4.3.13",
+    "2.16.840.1.101.3.4.3.14",
+    "2.16.840.1.101.3.4.3.15",
+    "2.16.840.1.101.3.4.3.16",
+];
+/// Ed25519 and Ed448, which only FIPS 186-5 approves.
+const EDDSA_SIGNATURES: &[&str] = &["1.3.101.112", "1.3.101.113"];
+
+/// The algorithm rules a certificate is audited against, from `--profile`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    /// FIPS 186-5, what FIPS 140-3 validated modules may sign and verify with.
+    Fips,
+    /// NIST SP 800-131A Rev. 2, which predates FIPS 186-5 and so leaves out EdDSA.
+    NistSp800131a,
+}
+
+impl Profile {
+    pub fn parse(value: &str) -> Result<Profile, Box<dyn std::error::Error>> {
+        match value.to_lowercase().as_str() {
+            "fips" => Ok(Profile::Fips),
+            "nist-sp800-131a" => Ok(Profile::NistSp800131a),
+            _ => Err(format!(
+                "Error: --profile expects fips or nist-sp800-131a, not {}.",
+                value
+            )
+            .into()),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Profile::Fips => write!(f, "FIPS 186-5"),
+            Profile::NistSp800131a => write!(f, "NIST SP 800-131A Rev. 2"),
+        }
+    }
+}
+
+/// An algorithm of a certificate checked against a profile, with why it doesn't
+/// comply if it doesn't.
+#[derive(Debug, PartialEq)]
+pub struct Check {
+    pub item: &'static str,
+    pub value: String,
+    pub problem: Option<String>,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.problem {
+            Some(problem) => write!(
+                f,
+                "{}: {}, non-compliant, {}",
+                self.item, self.value, problem
+            ),
+            None => write!(f, "{}: {}, compliant", self.item, self.value),
+        }
+    }
+}
+
+/// Checks the public key and signature algorithm of a certificate against a profile.
+pub fn check(cert: &X509Certificate, profile: Profile) -> Vec<Check> {
+    vec![public_key(cert, profile), signature(cert, profile)]
+}
+
+fn public_key(cert: &X509Certificate, profile: Profile) -> Check {
+    let key = key_info(cert.public_key());
+    let problem = match (&key.kind, key.bits) {
+        (KeyKind::Rsa, Some(bits)) if bits >= 2048 => None,
+        (KeyKind::Rsa, _) => Some(String::from("RSA keys need at least 2048 bits")),
+        (KeyKind::Ec(Some(Curve::P256 | Curve::P384 | Curve::P521)), _) => None,
+        (KeyKind::Ec(_), _) => Some(String::from(
+            "only the NIST curves P-256, P-384 and P-521 are approved",
+        )),
+        (KeyKind::Dsa, _) => Some(String::from(
+            "DSA may only be used to verify old signatures",
+        )),
+        (KeyKind::Ed25519 | KeyKind::Ed448, _) => match profile {
+            Profile::Fips => None,
+            Profile::NistSp800131a => Some(String::from("EdDSA is not covered")),
+        },
+        _ => Some(String::from("not an approved algorithm")),
+    };
+    Check {
+        item: "Public key",
+        value: key.to_string(),
+        problem,
+    }
+}
+
+fn signature(cert: &X509Certificate, profile: Profile) -> Check {
+    let identifier = &cert.signature_algorithm;
+    let oid = identifier.algorithm.to_id_string();
+    let mut value = oid::name(&identifier.algorithm);
+    let problem = if identifier.algorithm == OID_PKCS1_RSASSAPSS {
+        // Parameters left out default to SHA-1
+        let parameters = identifier.parameters.as_ref().and_then(pss::describe);
+        let sha1 = parameters
+            .as_ref()
+            .is_none_or(|parameters| parameters.to_lowercase().contains("sha1"));
+        value = format!(
+            "{} ({})",
+            value,
+            parameters.as_deref().unwrap_or("SHA-1 defaults")
+        );
+        sha1.then(|| String::from("SHA-1 is not allowed for signatures"))
+    } else if WEAK_SIGNATURES.contains(&oid.as_str()) {
+        Some(String::from("SHA-1 and MD5 are not allowed for signatures"))
+    } else if APPROVED_SIGNATURES.contains(&oid.as_str()) {
+        None
+    } else if EDDSA_SIGNATURES.contains(&oid.as_str()) {
+        match profile {
+            Profile::Fips => None,
+            Profile::NistSp800131a => Some(String::from("EdDSA is not covered")),
+        }
+    } else {
+        Some(String::from("not an approved algorithm"))
+    };
+    Check {
+        item: "Signature algorithm",
+        value,
+        problem,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{check, Profile};
+    use crate::test::google_der;
+
+    #[test]
+    fn should_check_each_algorithm_against_the_profile() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        let checks: Vec<String> = check(&cert, Profile::Fips)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            checks,
+            vec![
+                "Public key: EC P-256, compliant",
+                "Signature algorithm: sha256WithRSAEncryption, compliant",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_not_allow_sha1_signatures() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/sha1-root.crt")).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+
+        let checks = check(&cert, Profile::NistSp800131a);
+
+        assert_eq!(
+            checks[1].problem.as_deref(),
+            Some("SHA-1 and MD5 are not allowed for signatures")
+        );
+    }
+
+    #[test]
+    fn should_parse_profiles() {
+        assert_eq!(
+            Profile::parse("NIST-SP800-131A").unwrap(),
+            Profile::NistSp800131a
+        );
+        assert_eq!(
+            Profile::parse("cnsa").unwrap_err().to_string(),
+            "Error: --profile expects fips or nist-sp800-131a, not cnsa."
+        );
+    }
+}
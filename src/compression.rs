@@ -0,0 +1,84 @@
+use std::io::Read;
+
+use flate2::read::MultiGzDecoder;
+use ruzstd::decoding::StreamingDecoder;
+
+/// Decompressed files bigger than this are refused, so that a small file
+/// crafted to expand without end can't exhaust memory.
+const MAX_DECOMPRESSED_SIZE: u64 = 256 * 1024 * 1024;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Whether the bytes are gzip or zstd compressed.
+pub fn is_compressed(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0x1f, 0x8b]) || bytes.starts_with(&ZSTD_MAGIC)
+}
+
+/// Decompresses gzip or zstd, as CRL mirrors and log dumps often are,
+/// returning `None` for anything else.
+pub fn decompress(
+    source: &str,
+    bytes: &[u8],
+) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let invalid = |error: &dyn std::fmt::Display| {
+        format!("Error: could not decompress {}: {}", source, error)
+    };
+    // gzip files can hold several members one after the other, as `cat a.gz b.gz` makes
+    let reader: Box<dyn Read> = if bytes.starts_with(&[0x1f, 0x8b]) {
+        Box::new(MultiGzDecoder::new(bytes))
+    } else if bytes.starts_with(&ZSTD_MAGIC) {
+        Box::new(StreamingDecoder::new(bytes).map_err(|e| invalid(&e))?)
+    } else {
+        return Ok(None);
+    };
+
+    let mut decompressed = Vec::new();
+    reader
+        .take(MAX_DECOMPRESSED_SIZE + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| invalid(&e))?;
+    if decompressed.len() as u64 > MAX_DECOMPRESSED_SIZE {
+        return Err(format!(
+            "Error: {} decompresses to more than {} MiB, which is too big to be certificates.",
+            source,
+            MAX_DECOMPRESSED_SIZE / 1024 / 1024
+        )
+        .into());
+    }
+    Ok(Some(decompressed))
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+
+    use super::{decompress, is_compressed};
+
+    const CERT: &[u8] = include_bytes!("../resources/google.com.crt");
+
+    #[test]
+    fn should_decompress_gzip_and_zstd() {
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(CERT).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let zstd = compress_to_vec(CERT, CompressionLevel::Fastest);
+
+        assert!(is_compressed(&gzip) && is_compressed(&zstd));
+        assert_eq!(decompress("cert.gz", &gzip).unwrap(), Some(CERT.to_vec()));
+        assert_eq!(decompress("cert.zst", &zstd).unwrap(), Some(CERT.to_vec()));
+        assert_eq!(decompress("cert.pem", CERT).unwrap(), None);
+    }
+
+    #[test]
+    fn should_error_if_the_stream_is_corrupt() {
+        let result = decompress("crl.gz", &[0x1f, 0x8b, 0x08, 0x00, 0xff]);
+
+        assert!(format!("{}", result.err().unwrap())
+            .starts_with("Error: could not decompress crl.gz: "));
+    }
+}
@@ -0,0 +1,162 @@
+use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::asn1_rs::Tag;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+use x509_parser::objects::{oid2sn, oid_registry};
+use x509_parser::parse_x509_certificate;
+use x509_parser::x509::{X509Name, X509Version};
+
+use crate::dn;
+
+/// How closely certificates have to follow DER and RFC 5280, from `--lenient`
+/// and `--strict`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Strictness {
+    /// Decode whatever can be decoded, with a warning about each deviation.
+    Lenient,
+    /// Decode whatever x509-parser accepts, without a word about how.
+    #[default]
+    Default,
+    /// Refuse any certificate that deviates at all.
+    Strict,
+}
+
+/// Where a certificate deviates from DER and RFC 5280 in ways x509-parser lets
+/// pass, as real certificates often do: padding after it, a negative or badly
+/// encoded serial number, strings their type can't hold and extensions that
+/// don't parse.
+pub fn deviations(der: &[u8]) -> Vec<String> {
+    let (rest, cert) = match parse_x509_certificate(der) {
+        Ok(parsed) => parsed,
+        Err(_) => return Vec::new(),
+    };
+    let mut deviations = Vec::new();
+    if !rest.is_empty() {
+        deviations.push(format!(
+            "it is followed by {} bytes of trailing data",
+            rest.len()
+        ));
+    }
+    deviations.extend(serial_number(&cert));
+    if cert.version() != X509Version::V3 && !cert.extensions().is_empty() {
+        deviations.push(String::from("it has extensions but is not version 3"));
+    }
+    deviations.extend(strings("subject", cert.subject()));
+    deviations.extend(strings("issuer", cert.issuer()));
+    for extension in cert.extensions() {
+        match extension.parsed_extension() {
+            ParsedExtension::ParseError { error } => {
+                let name = oid2sn(&extension.oid, oid_registry())
+                    .map(String::from)
+                    .unwrap_or_else(|_| extension.oid.to_id_string());
+                deviations.push(format!("its {} extension does not parse, {}", name, error));
+            }
+            ParsedExtension::SubjectAlternativeName(san) => {
+                for name in &san.general_names {
+                    if let GeneralName::DNSName(name)
+                    | GeneralName::RFC822Name(name)
+                    | GeneralName::URI(name) = name
+                    {
+                        if !name.is_ascii() {
+                            deviations.push(format!(
+                                "its subject alternative name {} is not ASCII, as an IA5String has to be",
+                                name
+                            ));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    deviations
+}
+
+/// x509-parser reads a serial number as unsigned whatever its sign, and takes
+/// any encoding of it.
+fn serial_number(cert: &X509Certificate) -> Vec<String> {
+    let raw = cert.raw_serial();
+    let mut deviations = Vec::new();
+    if raw.first().is_some_and(|byte| byte & 0x80 != 0) {
+        deviations.push(format!(
+            "its serial number is negative, and is shown as {} as if it were not",
+            cert.serial
+        ));
+    }
+    if let [0, second, ..] = raw {
+        if second & 0x80 == 0 {
+            deviations.push(String::from(
+                "its serial number has a leading zero byte it doesn't need",
+            ));
+        }
+    }
+    deviations
+}
+
+/// The attributes of a name whose string type can't hold their value.
+fn strings(which: &str, name: &X509Name) -> Vec<String> {
+    let mut deviations = Vec::new();
+    for attribute in name.iter_attributes() {
+        let data = attribute.attr_value().data;
+        let problem = match attribute.attr_value().tag() {
+            Tag::PrintableString => (!data.iter().all(|byte| printable(*byte)))
+                .then_some("has characters a PrintableString can't hold"),
+            Tag::Ia5String => {
+                (!data.is_ascii()).then_some("is not ASCII, as an IA5String has to be")
+            }
+            Tag::NumericString => (!data
+                .iter()
+                .all(|byte| byte.is_ascii_digit() || *byte == b' '))
+            .then_some("has characters a NumericString can't hold"),
+            Tag::VisibleString => (!data.iter().all(|byte| (0x20..0x7f).contains(byte)))
+                .then_some("has characters a VisibleString can't hold"),
+            Tag::Utf8String => std::str::from_utf8(data)
+                .is_err()
+                .then_some("is not valid UTF-8"),
+            Tag::BmpString => {
+                (data.len() % 2 != 0).then_some("is not a whole number of BMPString characters")
+            }
+            Tag::UniversalString => (data.len() % 4 != 0)
+                .then_some("is not a whole number of UniversalString characters"),
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            deviations.push(format!(
+                "the {} of its {}, {}, {}",
+                dn::attribute_name(attribute),
+                which,
+                dn::value(attribute),
+                problem
+            ));
+        }
+    }
+    deviations
+}
+
+/// The characters of a PrintableString, from X.680.
+fn printable(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b" '()+,-./:=?".contains(&byte)
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::deviations;
+    use crate::test::google_der;
+
+    #[test]
+    fn should_find_deviations_x509_parser_lets_pass() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/sloppy.crt")).unwrap();
+
+        assert_eq!(
+            deviations(&pem.contents),
+            vec![
+                "it is followed by 4 bytes of trailing data",
+                "its serial number is negative, and is shown as 332448088244605016775547121450601670905 as if it were not",
+                "the O of its subject, Smith & Sons_Ltd, has characters a PrintableString can't hold",
+            ]
+        );
+        assert!(deviations(&google_der()).is_empty());
+    }
+}
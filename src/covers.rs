@@ -0,0 +1,202 @@
+use std::net::IpAddr;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+
+use crate::name_constraints;
+use crate::psl::SuffixList;
+
+/// Whether a name asked about with `--covers` is one a certificate is valid for.
+#[derive(Debug, PartialEq)]
+pub struct Coverage {
+    pub name: String,
+    /// The subject alternative name that covers it, or why none does.
+    pub by: Result<String, String>,
+}
+
+/// Checks whether a certificate covers each name as RFC 6125 and browsers match
+/// them: only against subject alternative names, never the common name, with a
+/// wildcard standing for exactly one whole label at the left of a DNS name, and
+/// never over a public suffix.
+pub fn check(cert: &X509Certificate, names: &[String], suffixes: &SuffixList) -> Vec<Coverage> {
+    let sans: Vec<&GeneralName> = match cert.subject_alternative_name() {
+        Ok(Some(san)) => san.value.general_names.iter().collect(),
+        _ => Vec::new(),
+    };
+    names
+        .iter()
+        .map(|name| Coverage {
+            name: name.clone(),
+            by: covered_by(&sans, name, suffixes),
+        })
+        .collect()
+}
+
+fn covered_by(sans: &[&GeneralName], name: &str, suffixes: &SuffixList) -> Result<String, String> {
+    if sans.is_empty() {
+        return Err(String::from(
+            "the certificate has no subject alternative names, and clients no longer match the common name",
+        ));
+    }
+    // Addresses are only matched against IP address names, and never by a wildcard
+    if let Ok(address) = name.parse::<IpAddr>() {
+        return sans
+            .iter()
+            .filter_map(|san| match san {
+                GeneralName::IPAddress(bytes) => name_constraints::address(bytes),
+                _ => None,
+            })
+            .find(|san| *san == address)
+            .map(|san| san.to_string())
+            .ok_or_else(|| String::from("no IP address name matches it"));
+    }
+
+    let name = normalize(name);
+    let dns: Vec<String> = sans
+        .iter()
+        .filter_map(|san| match san {
+            GeneralName::DNSName(dns) => Some(normalize(dns)),
+            _ => None,
+        })
+        .collect();
+    if let Some(exact) = dns.iter().find(|dns| **dns == name) {
+        return Ok(exact.clone());
+    }
+    let mut reasons = Vec::new();
+    for pattern in dns.iter().filter(|dns| dns.contains('*')) {
+        match wildcard(pattern, &name, suffixes) {
+            Ok(()) => return Ok(pattern.clone()),
+            Err(Some(reason)) => reasons.push(reason),
+            Err(None) => {}
+        }
+    }
+    // The reason of the wildcard closest to matching is the one that helps
+    Err(reasons
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| String::from("none of its subject alternative names match it")))
+}
+
+/// Whether a wildcard name covers a name, or if not and the wildcard was meant
+/// for names like it, why not.
+fn wildcard(pattern: &str, name: &str, suffixes: &SuffixList) -> Result<(), Option<String>> {
+    // A wildcard over some other domain has nothing to say about the name
+    let suffix = pattern.rsplit('*').next().unwrap_or_default();
+    if !name.ends_with(suffix) && name != suffix.trim_start_matches('.') {
+        return Err(None);
+    }
+    let (first, rest) = pattern.split_once('.').unwrap_or((pattern, ""));
+    if rest.contains('*') {
+        return Err(Some(format!(
+            "{} has a wildcard past its first label, which clients refuse",
+            pattern
+        )));
+    }
+    if first != "*" {
+        return Err(Some(format!(
+            "{} is a partial wildcard, which browsers refuse",
+            pattern
+        )));
+    }
+    if suffixes.is_public_suffix(rest) {
+        return Err(Some(format!(
+            "{} is a wildcard over the public suffix {}, which clients refuse",
+            pattern, rest
+        )));
+    }
+    if name == rest {
+        return Err(Some(format!(
+            "{} does not cover {} itself, the wildcard has to stand for a label",
+            pattern, rest
+        )));
+    }
+    match name
+        .strip_suffix(rest)
+        .and_then(|label| label.strip_suffix('.'))
+    {
+        Some(label) if !label.contains('.') => Ok(()),
+        Some(_) => Err(Some(format!(
+            "{} only stands for one label, not several",
+            pattern
+        ))),
+        None => Err(None),
+    }
+}
+
+/// A DNS name as it is compared, ignoring case and a trailing dot.
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+
+    use super::{check, wildcard};
+    use crate::psl::SuffixList;
+    use crate::test::google_der;
+
+    #[test]
+    fn should_say_which_name_covers_each_and_why_not() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+        let names = vec![
+            String::from("WWW.Google.com."),
+            String::from("google.com"),
+            String::from("a.b.google.com"),
+            String::from("example.com"),
+            String::from("192.0.2.1"),
+        ];
+
+        let coverage: Vec<Result<String, String>> = check(&cert, &names, &SuffixList::default())
+            .into_iter()
+            .map(|coverage| coverage.by)
+            .collect();
+
+        assert_eq!(
+            coverage,
+            vec![
+                Ok(String::from("*.google.com")),
+                Ok(String::from("google.com")),
+                Err(String::from(
+                    "*.google.com only stands for one label, not several"
+                )),
+                Err(String::from(
+                    "none of its subject alternative names match it"
+                )),
+                Err(String::from("no IP address name matches it")),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_refuse_wildcards_clients_refuse() {
+        let suffixes = SuffixList::parse("com\nuk\nco.uk\n");
+        assert_eq!(
+            wildcard("*.example.com", "example.com", &suffixes),
+            Err(Some(String::from(
+                "*.example.com does not cover example.com itself, the wildcard has to stand for a label"
+            )))
+        );
+        assert_eq!(
+            wildcard("w*.example.com", "www.example.com", &suffixes),
+            Err(Some(String::from(
+                "w*.example.com is a partial wildcard, which browsers refuse"
+            )))
+        );
+        assert!(wildcard("*.com", "example.com", &suffixes).is_err());
+        assert_eq!(wildcard("*.com", "example.org", &suffixes), Err(None));
+        assert!(wildcard("*.*.example.com", "a.b.example.com", &suffixes).is_err());
+        assert_eq!(
+            wildcard("*.example.com", "example.org", &suffixes),
+            Err(None)
+        );
+        assert_eq!(
+            wildcard("*.co.uk", "example.co.uk", &suffixes),
+            Err(Some(String::from(
+                "*.co.uk is a wildcard over the public suffix co.uk, which clients refuse"
+            )))
+        );
+    }
+}
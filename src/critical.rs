@@ -0,0 +1,84 @@
+use x509_parser::certificate::X509Certificate;
+use x509_parser::objects::{oid2sn, oid_registry};
+
+/// The extensions a client following RFC 5280 processes, and the CT poison that
+/// clients reject knowingly. A critical extension a client doesn't recognize makes
+/// it reject the certificate, so any other extension is risky to mark critical.
+const RECOGNIZED: &[&str] = &[
+    // basicConstraints, keyUsage and extKeyUsage
+    "2.5.29.19",
+    "2.5.29.15",
+    "2.5.29.37",
+    // subjectAltName and issuerAltName
+    "2.5.29.17",
+    "2.5.29.18",
+    // nameConstraints
+    "2.5.29.30",
+    // certificatePolicies, policyMappings, policyConstraints and inhibitAnyPolicy
+    "2.5.29.32",
+    "2.5.29.33",
+    "2.5.29.36",
+    "2.5.29.54",
+    // The CT precertificate poison
+    "1.3.6.1.4.1.11129.2.4.3",
+];
+
+/// A critical extension of a certificate.
+pub struct Critical {
+    pub name: String,
+    /// Whether clients can be expected to recognize it.
+    pub recognized: bool,
+}
+
+/// The extensions a certificate marks critical, in the order they appear.
+pub fn extensions(cert: &X509Certificate) -> Vec<Critical> {
+    cert.extensions()
+        .iter()
+        .filter(|extension| extension.critical)
+        .map(|extension| Critical {
+            name: oid2sn(&extension.oid, oid_registry())
+                .map(String::from)
+                .unwrap_or_else(|_| extension.oid.to_id_string()),
+            recognized: RECOGNIZED.contains(&extension.oid.to_id_string().as_str()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::extensions;
+
+    #[test]
+    fn should_list_the_critical_extensions() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/example-root.crt")).unwrap();
+        let root = pem.parse_x509().unwrap();
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/critical.crt")).unwrap();
+        let critical = pem.parse_x509().unwrap();
+
+        let names = |cert| -> Vec<(String, bool)> {
+            extensions(cert)
+                .into_iter()
+                .map(|critical| (critical.name, critical.recognized))
+                .collect()
+        };
+
+        assert_eq!(
+            names(&root),
+            vec![
+                (String::from("basicConstraints"), true),
+                (String::from("keyUsage"), true),
+            ]
+        );
+        assert_eq!(
+            names(&critical),
+            vec![
+                (String::from("basicConstraints"), true),
+                (String::from("keyUsage"), true),
+                (String::from("1.3.6.1.4.1.32473.2"), false),
+            ]
+        );
+    }
+}
@@ -0,0 +1,419 @@
+use x509_parser::extensions::{DistributionPointName, GeneralName, ParsedExtension};
+use x509_parser::num_bigint::BigUint;
+use x509_parser::pem::Pem;
+use x509_parser::prelude::FromDer;
+use x509_parser::revocation_list::CertificateRevocationList;
+use x509_parser::time::ASN1Time;
+
+use crate::cms::encode;
+use crate::cms::{self, element, Tlv};
+
+const DELTA_CRL_INDICATOR: &str = "2.5.29.27";
+const FRESHEST_CRL: &str = "2.5.29.46";
+const REASON_CODE: &str = "2.5.29.21";
+
+/// The names RFC 5280 gives the reasons a certificate is revoked.
+pub const REASONS: &[&str] = &[
+    "unspecified",
+    "keyCompromise",
+    "cACompromise",
+    "affiliationChanged",
+    "superseded",
+    "cessationOfOperation",
+    "certificateHold",
+    "",
+    "removeFromCRL",
+    "privilegeWithdrawn",
+    "aACompromise",
+];
+
+/// A CRL found alongside certificates, such as in a PKCS#7 bundle, or on its own.
+pub struct Crl {
+    pub location: String,
+    pub issuer: String,
+    pub last_update: String,
+    pub next_update: Option<String>,
+    /// How many certificates the CRL revokes.
+    pub count: usize,
+    /// The certificates it revokes, or when serial numbers were searched for,
+    /// those of them it does.
+    pub revoked: Vec<Revoked>,
+    /// The serial numbers searched for with `--find-serial`.
+    pub searched: Vec<String>,
+    pub number: Option<BigUint>,
+    /// For a delta CRL, the number of the base CRL it lists changes since.
+    pub base: Option<BigUint>,
+    /// Where the delta CRLs for this one are, from its Freshest CRL extension.
+    pub freshest: Vec<String>,
+    /// What part of the issuer's certificates a partitioned CRL covers, from its
+    /// Issuing Distribution Point, or empty if it covers them all.
+    pub scope: Vec<String>,
+}
+
+/// A certificate a CRL lists.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Revoked {
+    pub serial: String,
+    pub date: String,
+    pub reason: Option<String>,
+}
+
+/// What a base CRL and a delta CRL say together is revoked, as a client that
+/// fetches both sees it.
+pub struct EffectiveCrl {
+    pub base: String,
+    pub delta: String,
+    pub revoked: Vec<Revoked>,
+    /// Certificates the delta revokes, or changes the reason of.
+    pub added: Vec<Revoked>,
+    /// Certificates the delta takes off hold.
+    pub removed: Vec<String>,
+}
+
+/// The DER of the CRLs in a file, whether it is one in binary or a series of
+/// `X509 CRL` PEM blocks.
+pub fn find(bytes: &[u8]) -> Vec<Vec<u8>> {
+    let header = split(bytes).map(|(header, _)| header);
+    if header.is_some_and(|header| {
+        matches!(CertificateRevocationList::from_der(&header), Ok((rest, _)) if rest.is_empty())
+    }) {
+        return vec![bytes.to_vec()];
+    }
+    Pem::iter_from_buffer(bytes)
+        .filter_map(Result::ok)
+        .filter(|pem| pem.label == "X509 CRL")
+        .map(|pem| pem.contents)
+        .collect()
+}
+
+/// Splits the revoked certificates out of a CRL, giving the rest of it as a
+/// CRL with none, and the list's contents to walk one entry at a time. A CRL
+/// can list millions, which are better not all decoded at once.
+fn split(der: &[u8]) -> Option<(Vec<u8>, &[u8])> {
+    let (crl, rest) = element(der)?;
+    if crl.tag != 0x30 || !rest.is_empty() {
+        return None;
+    }
+    let mut parts = crl.children()?.into_iter();
+    let tbs = parts.next().filter(|tbs| tbs.tag == 0x30)?;
+    let mut fields = Vec::new();
+    let mut list: &[u8] = &[];
+    let mut after_time = false;
+    for field in tbs.children()? {
+        // The list is the SEQUENCE that follows thisUpdate and nextUpdate
+        if field.tag == 0x30 && after_time {
+            list = field.contents;
+        } else {
+            fields.extend_from_slice(field.raw);
+        }
+        after_time = matches!(field.tag, 0x17 | 0x18);
+    }
+    let mut header = encode(0x30, &fields);
+    for part in parts {
+        header.extend_from_slice(part.raw);
+    }
+    Some((encode(0x30, &header), list))
+}
+
+/// Decodes a CRL, with the parser's complaint if it is not valid. When serial
+/// numbers are to be found, only those are kept of the certificates it revokes.
+pub fn read(location: String, der: &[u8], find_serials: &[Vec<u8>]) -> Result<Crl, String> {
+    let (header, mut list) = split(der).ok_or("not DER")?;
+    let (_, crl) =
+        CertificateRevocationList::from_der(&header).map_err(|error| error.to_string())?;
+    let mut decoded = Crl {
+        location,
+        issuer: crl.issuer().to_string(),
+        last_update: crl.last_update().to_string(),
+        next_update: crl.next_update().map(|time| time.to_string()),
+        count: 0,
+        revoked: Vec::new(),
+        searched: find_serials.iter().map(|serial| cms::hex(serial)).collect(),
+        number: crl.crl_number().cloned(),
+        base: None,
+        freshest: Vec::new(),
+        scope: Vec::new(),
+    };
+    while !list.is_empty() {
+        let (entry, rest) = element(list).ok_or("a revoked certificate is not DER")?;
+        list = rest;
+        decoded.count += 1;
+        let fields = entry.children().ok_or("a revoked certificate is not DER")?;
+        let serial = match fields.first() {
+            Some(serial) if serial.tag == 0x02 => serial.contents,
+            _ => return Err(String::from("a revoked certificate has no serial number")),
+        };
+        if !find_serials.is_empty() && !find_serials.iter().any(|find| same_serial(find, serial)) {
+            continue;
+        }
+        decoded.revoked.push(revoked(serial, &fields));
+    }
+
+    for extension in crl.extensions() {
+        match extension.oid.to_id_string().as_str() {
+            DELTA_CRL_INDICATOR => {
+                decoded.base = element(extension.value)
+                    .filter(|(number, _)| number.tag == 0x02)
+                    .map(|(number, _)| BigUint::from_bytes_be(number.contents));
+            }
+            FRESHEST_CRL => {
+                if let Some((points, _)) = element(extension.value) {
+                    uris(&points, &mut decoded.freshest);
+                }
+            }
+            _ => {}
+        }
+        if let ParsedExtension::IssuingDistributionPoint(point) = extension.parsed_extension() {
+            match &point.distribution_point {
+                Some(DistributionPointName::FullName(names)) => {
+                    for name in names {
+                        match name {
+                            GeneralName::URI(uri) => {
+                                decoded.scope.push(format!("distribution point {}", uri))
+                            }
+                            name => decoded.scope.push(format!("distribution point {}", name)),
+                        }
+                    }
+                }
+                Some(DistributionPointName::NameRelativeToCRLIssuer(_)) => decoded
+                    .scope
+                    .push(String::from("distribution point relative to the issuer")),
+                None => {}
+            }
+            let only = [
+                (
+                    point.only_contains_user_certs,
+                    "only end-entity certificates",
+                ),
+                (point.only_contains_ca_certs, "only CA certificates"),
+                (
+                    point.only_contains_attribute_certs,
+                    "only attribute certificates",
+                ),
+                (point.indirect_crl, "indirect, for other issuers too"),
+            ];
+            decoded.scope.extend(
+                only.iter()
+                    .filter(|(set, _)| *set)
+                    .map(|(_, scope)| String::from(*scope)),
+            );
+            if let Some(reasons) = &point.only_some_reasons {
+                decoded.scope.push(format!("only the reasons {}", reasons));
+            }
+        }
+    }
+    Ok(decoded)
+}
+
+/// A revoked certificate's serial number, date and reason.
+fn revoked(serial: &[u8], fields: &[Tlv]) -> Revoked {
+    let date = fields
+        .get(1)
+        .and_then(|date| ASN1Time::from_der(date.raw).ok())
+        .map(|(_, date)| date.to_string())
+        .unwrap_or_default();
+    let mut reason = None;
+    let extensions = fields.get(2).and_then(Tlv::children).unwrap_or_default();
+    for extension in extensions {
+        let parts = extension.children().unwrap_or_default();
+        let oid = parts.first().and_then(Tlv::oid);
+        if oid.is_none_or(|oid| oid.to_id_string() != REASON_CODE) {
+            continue;
+        }
+        // The reason is an ENUMERATED inside the extension's OCTET STRING
+        reason = parts
+            .last()
+            .and_then(|value| element(value.contents))
+            .and_then(|(code, _)| REASONS.get(*code.contents.first()? as usize))
+            .filter(|name| !name.is_empty())
+            .map(|name| String::from(*name));
+    }
+    Revoked {
+        serial: cms::hex(strip_zeros(serial)),
+        date,
+        reason,
+    }
+}
+
+/// Reads a serial number for `--find-serial`, in hex with or without colons,
+/// or with the hyphens Vault separates bytes with.
+pub fn parse_serial(serial: &str) -> Option<Vec<u8>> {
+    let digits: String = serial
+        .chars()
+        .filter(|c| !matches!(c, ':' | '-' | ' '))
+        .collect();
+    let digits = digits.trim_start_matches("0x");
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digits = match digits.len() % 2 {
+        0 => String::from(digits),
+        _ => format!("0{}", digits),
+    };
+    let bytes: Vec<u8> = (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).expect("checked to be hex"))
+        .collect();
+    Some(strip_zeros(&bytes).to_vec())
+}
+
+/// Serial numbers are equal whatever leading zeros they are written with.
+fn same_serial(a: &[u8], b: &[u8]) -> bool {
+    strip_zeros(a) == strip_zeros(b)
+}
+
+fn strip_zeros(serial: &[u8]) -> &[u8] {
+    let start = serial
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(serial.len().saturating_sub(1));
+    &serial[start..]
+}
+
+/// The URIs anywhere in a list of distribution points.
+fn uris(tlv: &Tlv, found: &mut Vec<String>) {
+    if tlv.tag == 0x86 {
+        found.push(String::from_utf8_lossy(tlv.contents).into_owned());
+    } else if tlv.tag & 0x20 != 0 {
+        for child in tlv.children().unwrap_or_default() {
+            uris(&child, found);
+        }
+    }
+}
+
+/// Pairs each delta CRL with the base CRL it follows on from, for the same
+/// issuer and partition, and works out what they revoke together. Deltas
+/// without their base only list changes, which is worth pointing out.
+pub fn combine(crls: &[Crl]) -> (Vec<EffectiveCrl>, Vec<String>) {
+    let mut effective = Vec::new();
+    let mut warnings = Vec::new();
+    for delta in crls {
+        let needed = match &delta.base {
+            Some(needed) => needed,
+            None => continue,
+        };
+        // RFC 5280 5.2.4: the base must be at least as new as the one the delta is from
+        let base = crls.iter().find(|base| {
+            base.base.is_none()
+                && base.issuer == delta.issuer
+                && base.scope == delta.scope
+                && base.number.as_ref().is_some_and(|number| number >= needed)
+        });
+        let base = match base {
+            Some(base) => base,
+            None => {
+                warnings.push(format!(
+                    "Warning: {} is a delta CRL from base CRL number {}, which was not given, so it only lists what changed since.",
+                    delta.location, needed
+                ));
+                continue;
+            }
+        };
+
+        let mut revoked = base.revoked.clone();
+        let mut added = Vec::new();
+        let mut removed = Vec::new();
+        for change in &delta.revoked {
+            revoked.retain(|known| known.serial != change.serial);
+            match change.reason.as_deref() {
+                Some("removeFromCRL") => removed.push(change.serial.clone()),
+                _ => {
+                    revoked.push(change.clone());
+                    added.push(change.clone());
+                }
+            }
+        }
+        effective.push(EffectiveCrl {
+            base: base.location.clone(),
+            delta: delta.location.clone(),
+            revoked,
+            added,
+            removed,
+        });
+    }
+    (effective, warnings)
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::num_bigint::BigUint;
+
+    use super::{combine, find, parse_serial, read};
+
+    const BASE: &[u8] = include_bytes!("../resources/base.crl");
+    const DELTA: &[u8] = include_bytes!("../resources/delta.crl");
+
+    #[test]
+    fn should_decode_the_partition_and_delta_extensions() {
+        let base = read(String::from("base.crl"), &find(BASE)[0], &[]).unwrap();
+        let delta = read(String::from("delta.crl"), &find(DELTA)[0], &[]).unwrap();
+
+        assert_eq!(base.number, Some(BigUint::from(10u8)));
+        assert_eq!(base.base, None);
+        assert_eq!(
+            base.freshest,
+            vec!["http://crl.example/partition1-delta.crl"]
+        );
+        assert_eq!(
+            base.scope,
+            vec![
+                "distribution point http://crl.example/partition1.crl",
+                "only end-entity certificates"
+            ]
+        );
+        assert_eq!(delta.base, Some(BigUint::from(10u8)));
+        assert_eq!(delta.scope, base.scope);
+        assert_eq!(delta.revoked[0].reason.as_deref(), Some("removeFromCRL"));
+    }
+
+    #[test]
+    fn should_combine_a_base_and_delta_crl() {
+        let base = read(String::from("base.crl"), &find(BASE)[0], &[]).unwrap();
+        let delta = read(String::from("delta.crl"), &find(DELTA)[0], &[]).unwrap();
+
+        let (effective, warnings) = combine(&[base, delta]);
+
+        assert!(warnings.is_empty());
+        let serials: Vec<&str> = effective[0]
+            .revoked
+            .iter()
+            .map(|revoked| revoked.serial.as_str())
+            .collect();
+        assert_eq!(serials, vec!["10:01", "10:03"]);
+        assert_eq!(effective[0].removed, vec!["10:02"]);
+    }
+
+    #[test]
+    fn should_warn_about_a_delta_without_its_base() {
+        let delta = read(String::from("delta.crl"), &find(DELTA)[0], &[]).unwrap();
+
+        let (effective, warnings) = combine(&[delta]);
+
+        assert!(effective.is_empty());
+        assert_eq!(
+            warnings,
+            vec!["Warning: delta.crl is a delta CRL from base CRL number 10, which was not given, so it only lists what changed since."]
+        );
+    }
+
+    #[test]
+    fn should_keep_only_the_serials_searched_for() {
+        let serials = vec![
+            parse_serial("1003").unwrap(),
+            parse_serial("00:10:04").unwrap(),
+        ];
+
+        let delta = read(String::from("delta.crl"), &find(DELTA)[0], &serials).unwrap();
+
+        assert_eq!(delta.count, 2);
+        assert_eq!(delta.searched, vec!["10:03", "10:04"]);
+        assert_eq!(delta.revoked.len(), 1);
+        assert_eq!(delta.revoked[0].serial, "10:03");
+        assert_eq!(delta.revoked[0].date, "Sep 30 00:00:00 2026 +00:00");
+        assert_eq!(delta.revoked[0].reason.as_deref(), Some("superseded"));
+        assert_eq!(parse_serial("0x0abc"), Some(vec![0x0a, 0xbc]));
+        assert_eq!(parse_serial("0a-bc"), Some(vec![0x0a, 0xbc]));
+        assert_eq!(parse_serial("xyz"), None);
+    }
+}
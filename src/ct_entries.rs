@@ -0,0 +1,216 @@
+use std::convert::TryInto;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_yaml::Value;
+use x509_parser::time::ASN1Time;
+
+use crate::keystore;
+use crate::precertificate;
+use crate::store::NamedCertificate;
+
+/// The certificates of a CT log's entries, with anything that doesn't add up about them.
+pub struct Entries {
+    pub certificates: Vec<NamedCertificate>,
+    pub warnings: Vec<String>,
+}
+
+/// Whether the bytes are what a CT log's `get-entries` endpoint answers with.
+pub fn is_get_entries(bytes: &[u8]) -> bool {
+    entries(bytes).is_some()
+}
+
+fn entries(bytes: &[u8]) -> Option<Vec<Value>> {
+    let document: Value = serde_yaml::from_slice(bytes).ok()?;
+    let entries = document.get("entries")?.as_sequence()?;
+    match entries
+        .iter()
+        .all(|entry| entry.get("leaf_input").is_some())
+    {
+        true => Some(entries.clone()),
+        false => None,
+    }
+}
+
+/// Decodes the MerkleTreeLeaf of each entry, with the chain the log was given
+/// in its extra data. A precertificate entry only logs the TBS certificate, so
+/// the precertificate is taken from the extra data and checked against it.
+pub fn read(source: &str, bytes: &[u8]) -> Result<Entries, Box<dyn std::error::Error>> {
+    let mut decoded = Entries {
+        certificates: Vec::new(),
+        warnings: Vec::new(),
+    };
+    for (i, entry) in entries(bytes).unwrap_or_default().iter().enumerate() {
+        let invalid = || {
+            format!(
+                "Error: entry {} of {} is not a valid CT log entry.",
+                i + 1,
+                source
+            )
+        };
+        let field = |name: &str| -> Option<Vec<u8>> { STANDARD.decode(entry[name].as_str()?).ok() };
+        let leaf = field("leaf_input").ok_or_else(invalid)?;
+        let extra = field("extra_data").unwrap_or_default();
+        let logged = leaf_entry(&leaf, &extra).ok_or_else(invalid)?;
+
+        let time = ASN1Time::from_timestamp((logged.timestamp / 1000) as i64)
+            .map(|time| time.to_string())
+            .unwrap_or_else(|_| logged.timestamp.to_string());
+        let kind = match logged.tbs {
+            Some(_) => "precertificate",
+            None => "certificate",
+        };
+        let label = format!("entry {} ({}, logged {})", i + 1, kind, time);
+        if let Some(tbs) = &logged.tbs {
+            if precertificate::reconstruct_tbs(
+                &logged.chain[0],
+                logged.chain.get(1).map(Vec::as_slice),
+            )
+            .as_ref()
+                != Some(tbs)
+            {
+                decoded.warnings.push(format!(
+                    "Warning: the precertificate of {}: {} is not the one that was logged.",
+                    source, label
+                ));
+            }
+        }
+        decoded
+            .certificates
+            .extend(keystore::chain_entries(&label, logged.chain.into_iter()));
+    }
+    Ok(decoded)
+}
+
+/// What a log entry holds: the certificate or precertificate and its chain,
+/// and for a precertificate the TBS certificate that was logged.
+struct Logged {
+    timestamp: u64,
+    chain: Vec<Vec<u8>>,
+    tbs: Option<Vec<u8>>,
+}
+
+/// A MerkleTreeLeaf of RFC 6962, and the extra data that comes with it.
+fn leaf_entry(leaf: &[u8], extra: &[u8]) -> Option<Logged> {
+    // The version and leaf type, only v1 and timestamped_entry are defined
+    if leaf.get(..2)? != [0, 0] {
+        return None;
+    }
+    let timestamp = u64::from_be_bytes(leaf.get(2..10)?.try_into().ok()?);
+    let entry_type = u16::from_be_bytes(leaf.get(10..12)?.try_into().ok()?);
+    let mut rest = leaf.get(12..)?;
+    let mut extra = extra;
+    match entry_type {
+        0 => {
+            let certificate = opaque(&mut rest)?;
+            let mut chain = vec![certificate.to_vec()];
+            chain.extend(certificates(opaque(&mut extra)?)?);
+            Some(Logged {
+                timestamp,
+                chain,
+                tbs: None,
+            })
+        }
+        1 => {
+            // The issuer's key hash comes before the TBS certificate
+            let tbs = opaque(&mut rest.get(32..)?)?.to_vec();
+            let precertificate = opaque(&mut extra)?;
+            let mut chain = vec![precertificate.to_vec()];
+            chain.extend(certificates(opaque(&mut extra)?)?);
+            Some(Logged {
+                timestamp,
+                chain,
+                tbs: Some(tbs),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// A value prefixed with its length in three bytes, which is how certificates
+/// and lists of them are written.
+fn opaque<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let header = bytes.get(..3)?;
+    let len = (header[0] as usize) << 16 | (header[1] as usize) << 8 | header[2] as usize;
+    let value = bytes.get(3..3 + len)?;
+    *bytes = &bytes[3 + len..];
+    Some(value)
+}
+
+fn certificates(mut list: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut certificates = Vec::new();
+    while !list.is_empty() {
+        certificates.push(opaque(&mut list)?.to_vec());
+    }
+    Some(certificates)
+}
+
+#[cfg(test)]
+mod test {
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    use super::{is_get_entries, read};
+
+    const ENTRIES: &[u8] = include_bytes!("../resources/ct-entries.json");
+
+    #[test]
+    fn should_decode_certificate_and_precertificate_entries() {
+        let entries = read("entries.json", ENTRIES).unwrap();
+        let names: Vec<&str> = entries
+            .certificates
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "entry 1 (certificate, logged Oct  1 00:05:00 2026 +00:00) [1/2]",
+                "entry 1 (certificate, logged Oct  1 00:05:00 2026 +00:00) [2/2]",
+                "entry 2 (precertificate, logged Oct  1 00:05:01 2026 +00:00) [1/2]",
+                "entry 2 (precertificate, logged Oct  1 00:05:01 2026 +00:00) [2/2]",
+            ]
+        );
+        assert!(entries.warnings.is_empty());
+    }
+
+    #[test]
+    fn should_warn_when_the_precertificate_was_not_the_one_logged() {
+        let document = String::from_utf8_lossy(ENTRIES);
+        let logged = document.split("\"leaf_input\": \"").nth(2).unwrap();
+        let logged = logged.split('"').next().unwrap();
+        // Change a byte of the logged TBS certificate, before the extensions of the leaf
+        let mut leaf = STANDARD.decode(logged).unwrap();
+        let at = leaf.len() - 10;
+        leaf[at] ^= 1;
+        let changed = document.replace(logged, &STANDARD.encode(leaf));
+
+        let entries = read("entries.json", changed.as_bytes()).unwrap();
+
+        assert_eq!(
+            entries.warnings,
+            vec!["Warning: the precertificate of entries.json: entry 2 (precertificate, logged Oct  1 00:05:01 2026 +00:00) is not the one that was logged."]
+        );
+    }
+
+    #[test]
+    fn should_error_on_an_invalid_entry() {
+        let error = read("entries.json", br#"{"entries": [{"leaf_input": "AAE="}]}"#)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "Error: entry 1 of entries.json is not a valid CT log entry."
+        );
+    }
+
+    #[test]
+    fn should_only_take_get_entries_output() {
+        assert!(is_get_entries(ENTRIES));
+        assert!(!is_get_entries(br#"{"keys": []}"#));
+        assert!(!is_get_entries(br#"{"entries": [{"name": "x"}]}"#));
+    }
+}
@@ -0,0 +1,310 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde_yaml::Value;
+use x509_parser::time::ASN1Time;
+
+use crate::ct_search::{self, Logged};
+use crate::http::{Request, Url};
+use crate::json;
+use crate::remote::ConnectOptions;
+use crate::FileProcessor;
+
+/// What `cert-decoder ct-monitor` should watch.
+#[derive(Debug, Default, PartialEq)]
+pub struct CtMonitorOptions {
+    /// The domains whose certificates, and their subdomains', to watch for, from `--domain`.
+    pub domains: Vec<String>,
+    /// Where the crt.sh IDs already seen are kept between checks, from `--state`.
+    pub state: String,
+    /// How long to wait between checks, from `--interval`, or check once if not given.
+    pub interval: Option<Duration>,
+    /// Where to post newly logged certificates as JSON, from `--webhook`.
+    pub webhook: Option<String>,
+}
+
+/// The crt.sh IDs seen for each domain watched.
+type State = Vec<(String, Vec<u64>)>;
+
+/// What one check found for a domain.
+pub struct Observed {
+    pub domain: String,
+    /// Certificates logged since the last check.
+    pub new: Vec<Logged>,
+    /// How many were recorded on the first check of the domain, when nothing is new yet.
+    pub recorded: Option<usize>,
+}
+
+/// What one check found for every domain.
+pub struct Check {
+    pub checked: i64,
+    pub domains: Vec<Observed>,
+}
+
+/// Asks crt.sh what has been logged for each domain and its subdomains, and
+/// compares that with what the state file says was seen before, then records
+/// everything in it. The first check of a domain only records what is already
+/// logged, since none of it was issued while watching.
+pub fn check(
+    processor: &impl FileProcessor,
+    options: &CtMonitorOptions,
+    connect: &ConnectOptions,
+) -> Result<Check, Box<dyn std::error::Error>> {
+    let mut state = match processor.is_file(&options.state) {
+        true => load(&options.state, &processor.read(&options.state)?)?,
+        false => State::new(),
+    };
+
+    let mut domains = Vec::new();
+    for domain in &options.domains {
+        let mut logged = ct_search::list(processor, domain, connect)?;
+        if !domain.contains('%') {
+            let subdomains = format!("%.{}", domain);
+            for entry in ct_search::list(processor, &subdomains, connect)? {
+                if !logged.iter().any(|known| known.id == entry.id) {
+                    logged.push(entry);
+                }
+            }
+        }
+        logged.sort_by_key(|entry| entry.id);
+
+        let ids: Vec<u64> = logged.iter().map(|entry| entry.id).collect();
+        let observed = match state.iter_mut().find(|(known, _)| known == domain) {
+            Some((_, seen)) => {
+                let new: Vec<Logged> = logged
+                    .into_iter()
+                    .filter(|entry| !seen.contains(&entry.id))
+                    .collect();
+                seen.extend(new.iter().map(|entry| entry.id));
+                Observed {
+                    domain: domain.clone(),
+                    new,
+                    recorded: None,
+                }
+            }
+            None => {
+                state.push((domain.clone(), ids.clone()));
+                Observed {
+                    domain: domain.clone(),
+                    new: Vec::new(),
+                    recorded: Some(ids.len()),
+                }
+            }
+        };
+        domains.push(observed);
+    }
+    processor.write(&options.state, save(&state).as_bytes())?;
+
+    let check = Check {
+        checked: processor.now(),
+        domains,
+    };
+    if let Some(webhook) = &options.webhook {
+        if check
+            .domains
+            .iter()
+            .any(|observed| !observed.new.is_empty())
+        {
+            let url = Url::parse(webhook)?;
+            let request = Request::post(url.clone(), notification(&check).into_bytes())
+                .header("Content-Type", "application/json");
+            processor.http(&request, connect)?.success(&url)?;
+        }
+    }
+    Ok(check)
+}
+
+/// The state file as JSON, with the IDs seen for each domain.
+fn save(state: &State) -> String {
+    let domains: Vec<String> = state
+        .iter()
+        .map(|(domain, ids)| {
+            let ids: Vec<String> = ids.iter().map(u64::to_string).collect();
+            format!("    {}: [{}]", json::string(domain), ids.join(", "))
+        })
+        .collect();
+    format!("{{\n  \"seen\": {{\n{}\n  }}\n}}\n", domains.join(",\n"))
+}
+
+/// Reads a state file `save` wrote.
+fn load(path: &str, bytes: &[u8]) -> Result<State, Box<dyn std::error::Error>> {
+    let invalid = || format!("Error: {} is not a ct-monitor state file.", path);
+    let file: Value = serde_yaml::from_slice(bytes).map_err(|_| invalid())?;
+    let seen = file["seen"].as_mapping().ok_or_else(invalid)?;
+    let mut state = State::new();
+    for (domain, ids) in seen {
+        let domain = domain.as_str().ok_or_else(invalid)?;
+        let ids: Option<Vec<u64>> = ids
+            .as_sequence()
+            .ok_or_else(invalid)?
+            .iter()
+            .map(Value::as_u64)
+            .collect();
+        state.push((String::from(domain), ids.ok_or_else(invalid)?));
+    }
+    Ok(state)
+}
+
+/// What is posted to the webhook: a `text` summary chat services show as it
+/// is, and each newly logged certificate for anything else.
+fn notification(check: &Check) -> String {
+    let mut summary = Vec::new();
+    let mut certificates = Vec::new();
+    for observed in check
+        .domains
+        .iter()
+        .filter(|observed| !observed.new.is_empty())
+    {
+        summary.push(format!(
+            "{} new certificate(s) logged for {}",
+            observed.new.len(),
+            observed.domain
+        ));
+        for logged in &observed.new {
+            let names: Vec<String> = logged.names.iter().map(|name| json::string(name)).collect();
+            certificates.push(format!(
+                "    {{\"domain\": {}, \"crtsh_id\": {}, \"names\": [{}], \"issuer\": {}, \"serial\": {}, \"not_before\": {}, \"not_after\": {}}}",
+                json::string(&observed.domain),
+                logged.id,
+                names.join(", "),
+                json::string(&logged.issuer),
+                json::string(&logged.serial),
+                json::string(&logged.not_before),
+                json::string(&logged.not_after)
+            ));
+        }
+    }
+    format!(
+        "{{\n  \"text\": {},\n  \"certificates\": [\n{}\n  ]\n}}\n",
+        json::string(&summary.join(", ")),
+        certificates.join(",\n")
+    )
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let checked = ASN1Time::from_timestamp(self.checked)
+            .map(|checked| checked.to_string())
+            .unwrap_or_default();
+        writeln!(f, "Checked crt.sh at {}", checked)?;
+        for observed in &self.domains {
+            match (observed.recorded, observed.new.len()) {
+                (Some(recorded), _) => writeln!(
+                    f,
+                    "{}: first check, recorded the {} certificate(s) already logged",
+                    observed.domain, recorded
+                )?,
+                (None, 0) => writeln!(f, "{}: no new certificates", observed.domain)?,
+                (None, new) => writeln!(f, "{}: {} new certificate(s)", observed.domain, new)?,
+            }
+            for logged in &observed.new {
+                write!(f, "{}", logged)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{check, CtMonitorOptions};
+    use crate::http::Response;
+    use crate::remote::ConnectOptions;
+    use crate::test::FakeProcessor;
+
+    const LISTING: &str = r#"[
+        {"issuer_name": "C=US, O=Let's Encrypt, CN=R3", "name_value": "example.com\nwww.example.com",
+         "id": 1001, "not_before": "2024-01-01T00:00:00", "not_after": "2024-03-31T00:00:00", "serial_number": "0a1b"}
+    ]"#;
+
+    const SUBDOMAINS: &str = r#"[
+        {"issuer_name": "C=US, O=Let's Encrypt, CN=R3", "name_value": "example.com\nwww.example.com",
+         "id": 1001, "not_before": "2024-01-01T00:00:00", "not_after": "2024-03-31T00:00:00", "serial_number": "0a1b"},
+        {"issuer_name": "C=US, O=Unknown CA, CN=Rogue", "name_value": "login.example.com",
+         "id": 2002, "not_before": "2024-02-01T00:00:00", "not_after": "2024-05-01T00:00:00", "serial_number": "ff01"}
+    ]"#;
+
+    fn ok(body: &str) -> Response {
+        Response {
+            status: 200,
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn options() -> CtMonitorOptions {
+        CtMonitorOptions {
+            domains: vec![String::from("example.com")],
+            state: String::from("state.json"),
+            interval: None,
+            webhook: Some(String::from("https://hooks.example.net/ct")),
+        }
+    }
+
+    #[test]
+    fn should_record_what_is_already_logged_on_the_first_check() {
+        let mut processor = FakeProcessor::default();
+        processor.responses.insert(
+            String::from("https://crt.sh/?q=example.com&output=json"),
+            ok(LISTING),
+        );
+        processor.responses.insert(
+            String::from("https://crt.sh/?q=%25.example.com&output=json"),
+            ok(SUBDOMAINS),
+        );
+
+        let check = check(&processor, &options(), &ConnectOptions::default()).unwrap();
+
+        assert_eq!(check.domains[0].recorded, Some(2));
+        assert!(check
+            .to_string()
+            .ends_with("example.com: first check, recorded the 2 certificate(s) already logged\n"));
+        assert_eq!(
+            String::from_utf8(processor.written.lock().unwrap()["state.json"].clone()).unwrap(),
+            "{\n  \"seen\": {\n    \"example.com\": [1001, 2002]\n  }\n}\n"
+        );
+        assert!(processor.sent.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_report_and_post_newly_logged_certificates() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file(
+            "state.json",
+            "{\"seen\": {\"example.com\": [1001], \"example.org\": [7]}}",
+        );
+        processor.responses.insert(
+            String::from("https://crt.sh/?q=example.com&output=json"),
+            ok(LISTING),
+        );
+        processor.responses.insert(
+            String::from("https://crt.sh/?q=%25.example.com&output=json"),
+            ok(SUBDOMAINS),
+        );
+        processor
+            .responses
+            .insert(String::from("https://hooks.example.net/ct"), ok(""));
+
+        let check = check(&processor, &options(), &ConnectOptions::default()).unwrap();
+
+        assert!(check.to_string().ends_with(
+            "example.com: 1 new certificate(s)\n\
+             crt.sh ID: 2002\n    \
+             Names: login.example.com\n    \
+             Issuer: C=US, O=Unknown CA, CN=Rogue\n    \
+             Serial number: ff01\n    \
+             Not before: 2024-02-01T00:00:00\n    \
+             Not after: 2024-05-01T00:00:00\n    \
+             Precertificate: not known, download it with ct-search --entry to tell\n"
+        ));
+        let state = processor.written.lock().unwrap()["state.json"].clone();
+        assert!(String::from_utf8(state)
+            .unwrap()
+            .contains("\"example.com\": [1001, 2002],\n    \"example.org\": [7]"));
+        let sent = processor.sent.lock().unwrap();
+        let body = String::from_utf8(sent["https://hooks.example.net/ct"].clone()).unwrap();
+        assert!(body.contains("\"text\": \"1 new certificate(s) logged for example.com\""));
+        assert!(body.contains("\"crtsh_id\": 2002, \"names\": [\"login.example.com\"]"));
+    }
+}
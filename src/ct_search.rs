@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde_yaml::Value;
+use x509_parser::pem::Pem;
+
+use crate::http::{percent_encode, Request, Url};
+use crate::remote::ConnectOptions;
+use crate::report::Report;
+use crate::store::NamedCertificate;
+use crate::FileProcessor;
+
+/// The Certificate Transparency aggregator searched, which indexes every major log.
+const CRT_SH: &str = "https://crt.sh/";
+
+/// What `cert-decoder ct-search` should look for.
+#[derive(Debug, Default, PartialEq)]
+pub struct CtSearchOptions {
+    /// The domain to list certificates for, which crt.sh lets contain `%` wildcards.
+    pub domain: String,
+    /// The crt.sh IDs of entries to download and decode, from `--entry`.
+    pub entries: Vec<u64>,
+}
+
+/// A certificate logged for the domain, as crt.sh lists it.
+#[derive(Debug, PartialEq)]
+pub struct Logged {
+    pub id: u64,
+    pub names: Vec<String>,
+    pub issuer: String,
+    pub serial: String,
+    pub not_before: String,
+    pub not_after: String,
+    /// Whether the entry is a precertificate, if that can be told from the listing.
+    pub precertificate: Option<bool>,
+}
+
+/// The certificates logged for a domain, and the decoded entries that were asked for.
+pub struct Search {
+    pub domain: String,
+    pub logged: Vec<Logged>,
+    pub report: Report,
+}
+
+/// Lists the certificates logged for a domain, then downloads and decodes any
+/// entries asked for.
+pub fn search(
+    processor: &impl FileProcessor,
+    options: &CtSearchOptions,
+    connect: &ConnectOptions,
+) -> Result<Search, Box<dyn std::error::Error>> {
+    let logged = list(processor, &options.domain, connect)?;
+    let mut report = Report::default();
+    for id in &options.entries {
+        let source = format!("crt.sh entry {}", id);
+        report.add_named(&source, download(processor, *id, connect));
+    }
+    Ok(Search {
+        domain: options.domain.clone(),
+        logged,
+        report,
+    })
+}
+
+/// Lists the certificates crt.sh has seen logged for a domain.
+pub fn list(
+    processor: &impl FileProcessor,
+    domain: &str,
+    connect: &ConnectOptions,
+) -> Result<Vec<Logged>, Box<dyn std::error::Error>> {
+    let url = Url::parse(&format!(
+        "{}?q={}&output=json",
+        CRT_SH,
+        percent_encode(domain)
+    ))?;
+    let response = processor.http(&Request::get(url.clone()), connect)?;
+    let logged = parse(&response.success(&url)?.body)
+        .ok_or_else(|| format!("Error: {} did not answer with a list of certificates.", url))?;
+    Ok(logged)
+}
+
+/// The entries of a crt.sh JSON listing, newest first as crt.sh gives them.
+fn parse(body: &[u8]) -> Option<Vec<Logged>> {
+    let listing: Value = serde_yaml::from_slice(body).ok()?;
+    let mut logged = Vec::new();
+    for entry in listing.as_sequence()? {
+        let text = |field: &str| entry[field].as_str().map(String::from);
+        logged.push(Logged {
+            id: entry["id"].as_u64()?,
+            names: text("name_value")?.lines().map(String::from).collect(),
+            issuer: text("issuer_name")?,
+            serial: text("serial_number")?,
+            not_before: text("not_before")?,
+            not_after: text("not_after")?,
+            precertificate: None,
+        });
+    }
+
+    // crt.sh doesn't say which entries are precertificates, but when both a
+    // precertificate and its final certificate were logged they share an issuer
+    // and serial number, and the precertificate was logged first
+    let mut ids: HashMap<(&str, &str), Vec<u64>> = HashMap::new();
+    for entry in &logged {
+        ids.entry((&entry.issuer, &entry.serial))
+            .or_default()
+            .push(entry.id);
+    }
+    let precertificates: HashMap<u64, bool> = ids
+        .values()
+        .filter(|ids| ids.len() > 1)
+        .flat_map(|ids| {
+            let first = ids.iter().min().copied();
+            ids.iter().map(move |id| (*id, Some(*id) == first))
+        })
+        .collect();
+    for entry in &mut logged {
+        entry.precertificate = precertificates.get(&entry.id).copied();
+    }
+    Some(logged)
+}
+
+/// The certificate or precertificate crt.sh has under an ID.
+fn download(
+    processor: &impl FileProcessor,
+    id: u64,
+    connect: &ConnectOptions,
+) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    let url = Url::parse(&format!("{}?d={}", CRT_SH, id))?;
+    let response = processor.http(&Request::get(url.clone()), connect)?;
+    let certificates: Vec<NamedCertificate> = Pem::iter_from_buffer(&response.success(&url)?.body)
+        .filter_map(Result::ok)
+        .filter(|pem| pem.label == "CERTIFICATE")
+        .map(|pem| (String::from("certificate"), pem.contents))
+        .collect();
+    if certificates.is_empty() {
+        return Err(format!("Error: {} did not answer with a certificate.", url).into());
+    }
+    Ok(certificates)
+}
+
+impl fmt::Display for Logged {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "crt.sh ID: {}", self.id)?;
+        writeln!(f, "    Names: {}", self.names.join(", "))?;
+        writeln!(f, "    Issuer: {}", self.issuer)?;
+        writeln!(f, "    Serial number: {}", self.serial)?;
+        writeln!(f, "    Not before: {}", self.not_before)?;
+        writeln!(f, "    Not after: {}", self.not_after)?;
+        let precertificate = match self.precertificate {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "not known, download it with ct-search --entry to tell",
+        };
+        writeln!(f, "    Precertificate: {}", precertificate)
+    }
+}
+
+impl fmt::Display for Search {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Certificates logged for {}: {}",
+            self.domain,
+            self.logged.len()
+        )?;
+        for logged in &self.logged {
+            write!(f, "{}", logged)?;
+        }
+        write!(f, "{}", self.report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{search, CtSearchOptions};
+    use crate::http::Response;
+    use crate::remote::ConnectOptions;
+    use crate::test::FakeProcessor;
+
+    const LISTING: &str = r#"[
+        {"issuer_ca_id": 16418, "issuer_name": "C=US, O=Let's Encrypt, CN=R3", "common_name": "example.com",
+         "name_value": "example.com\nwww.example.com", "id": 1002, "entry_timestamp": "2024-01-01T01:00:00.000",
+         "not_before": "2024-01-01T00:00:00", "not_after": "2024-03-31T00:00:00", "serial_number": "0a1b", "result_count": 2},
+        {"issuer_ca_id": 16418, "issuer_name": "C=US, O=Let's Encrypt, CN=R3", "common_name": "example.com",
+         "name_value": "example.com\nwww.example.com", "id": 1001, "entry_timestamp": "2024-01-01T00:00:00.000",
+         "not_before": "2024-01-01T00:00:00", "not_after": "2024-03-31T00:00:00", "serial_number": "0a1b", "result_count": 2},
+        {"issuer_ca_id": 16418, "issuer_name": "C=US, O=Let's Encrypt, CN=R3", "common_name": "example.com",
+         "name_value": "example.com", "id": 900, "entry_timestamp": "2023-10-01T00:00:00.000",
+         "not_before": "2023-10-01T00:00:00", "not_after": "2023-12-30T00:00:00", "serial_number": "03ff", "result_count": 1}
+    ]"#;
+
+    fn ok(body: &[u8]) -> Response {
+        Response {
+            status: 200,
+            headers: Vec::new(),
+            body: body.to_vec(),
+        }
+    }
+
+    #[test]
+    fn should_list_logged_certificates_and_tell_precertificates_apart() {
+        let mut processor = FakeProcessor::default();
+        processor.responses.insert(
+            String::from("https://crt.sh/?q=%25.example.com&output=json"),
+            ok(LISTING.as_bytes()),
+        );
+        let options = CtSearchOptions {
+            domain: String::from("%.example.com"),
+            entries: Vec::new(),
+        };
+
+        let search = search(&processor, &options, &ConnectOptions::default()).unwrap();
+        let precertificates: Vec<Option<bool>> = search
+            .logged
+            .iter()
+            .map(|logged| logged.precertificate)
+            .collect();
+
+        assert_eq!(precertificates, vec![Some(false), Some(true), None]);
+        assert_eq!(
+            search.logged[0].names,
+            vec!["example.com", "www.example.com"]
+        );
+        assert!(search.to_string().starts_with(
+            "Certificates logged for %.example.com: 3\n\
+             crt.sh ID: 1002\n    \
+             Names: example.com, www.example.com\n    \
+             Issuer: C=US, O=Let's Encrypt, CN=R3\n    \
+             Serial number: 0a1b\n    \
+             Not before: 2024-01-01T00:00:00\n    \
+             Not after: 2024-03-31T00:00:00\n    \
+             Precertificate: no\n"
+        ));
+    }
+
+    #[test]
+    fn should_download_and_decode_an_entry() {
+        let mut processor = FakeProcessor::default();
+        processor.responses.insert(
+            String::from("https://crt.sh/?q=example.com&output=json"),
+            ok(LISTING.as_bytes()),
+        );
+        processor.responses.insert(
+            String::from("https://crt.sh/?d=1001"),
+            ok(include_bytes!("../resources/precert.crt")),
+        );
+        let options = CtSearchOptions {
+            domain: String::from("example.com"),
+            entries: vec![1001],
+        };
+
+        let search = search(&processor, &options, &ConnectOptions::default()).unwrap();
+        let (_, expected) = parse_x509_pem(include_bytes!("../resources/precert.crt")).unwrap();
+
+        assert!(search.report.failures.is_empty());
+        assert_eq!(
+            search.report.entries[0].location,
+            "crt.sh entry 1001: certificate"
+        );
+        assert_eq!(search.report.entries[0].der, expected.contents);
+        assert!(search
+            .to_string()
+            .contains("Precertificate: yes, for a Certificate Transparency log\n"));
+    }
+
+    #[test]
+    fn should_error_on_an_unexpected_answer() {
+        let mut processor = FakeProcessor::default();
+        processor.responses.insert(
+            String::from("https://crt.sh/?q=example.com&output=json"),
+            ok(b"<html>busy</html>"),
+        );
+        let options = CtSearchOptions {
+            domain: String::from("example.com"),
+            entries: Vec::new(),
+        };
+
+        let error = search(&processor, &options, &ConnectOptions::default())
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "Error: https://crt.sh/?q=example.com&output=json did not answer with a list of certificates."
+        );
+    }
+}
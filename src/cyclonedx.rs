@@ -0,0 +1,178 @@
+use ring::digest::{digest, SHA256};
+use x509_parser::time::ASN1Time;
+
+use crate::dn;
+use crate::json;
+use crate::oid;
+use crate::report::Report;
+use crate::trust_store::key_type;
+
+/// The certificates of a report as a CycloneDX 1.6 BOM, one cryptographic asset
+/// component for each, with every place it was found as evidence, so SBOM
+/// tooling can take them in alongside software components.
+pub fn to_string(report: &Report, now: i64) -> String {
+    // A component for each certificate, however many places it was found in
+    let mut certificates: Vec<(String, Vec<&str>, usize)> = Vec::new();
+    for (i, entry) in report.entries.iter().enumerate() {
+        let sha256: String = digest(&SHA256, &entry.der)
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let locations = entry.duplicates.iter().map(String::as_str);
+        match certificates
+            .iter_mut()
+            .find(|(known, _, _)| *known == sha256)
+        {
+            Some((_, found, _)) => {
+                found.push(&entry.location);
+                found.extend(locations);
+            }
+            None => {
+                let mut found = vec![entry.location.as_str()];
+                found.extend(locations);
+                certificates.push((sha256, found, i));
+            }
+        }
+    }
+
+    let components: Vec<String> = certificates
+        .iter()
+        .map(|(sha256, found, i)| {
+            let cert = report.entries[*i].certificate();
+            let validity = cert.validity();
+            let subject = dn::to_string(cert.subject());
+            let name = cert
+                .subject()
+                .iter_common_name()
+                .next()
+                .and_then(|cn| cn.as_str().ok())
+                .map_or_else(|| subject.clone(), String::from);
+            let occurrences: Vec<String> = found
+                .iter()
+                .map(|location| format!("{{\"location\": {}}}", json::string(location)))
+                .collect();
+            let properties = [
+                ("cert-decoder:serialNumber", cert.raw_serial_as_string()),
+                (
+                    "cert-decoder:signatureAlgorithm",
+                    oid::name(&cert.signature_algorithm.algorithm),
+                ),
+                ("cert-decoder:publicKey", key_type(cert.public_key())),
+            ];
+            let properties: Vec<String> = properties
+                .iter()
+                .map(|(name, value)| {
+                    format!(
+                        "{{\"name\": {}, \"value\": {}}}",
+                        json::string(name),
+                        json::string(value)
+                    )
+                })
+                .collect();
+            format!(
+                "    {{\n      \"type\": \"cryptographic-asset\",\n      \
+                 \"bom-ref\": \"certificate:sha256:{sha256}\",\n      \
+                 \"name\": {name},\n      \
+                 \"hashes\": [{{\"alg\": \"SHA-256\", \"content\": \"{sha256}\"}}],\n      \
+                 \"cryptoProperties\": {{\n        \"assetType\": \"certificate\",\n        \
+                 \"certificateProperties\": {{\n          \"subjectName\": {subject},\n          \
+                 \"issuerName\": {issuer},\n          \"notValidBefore\": \"{before}\",\n          \
+                 \"notValidAfter\": \"{after}\",\n          \"certificateFormat\": \"X.509\"\n        \
+                 }}\n      }},\n      \
+                 \"properties\": [{properties}],\n      \
+                 \"evidence\": {{\"occurrences\": [{occurrences}]}}\n    }}",
+                sha256 = sha256,
+                name = json::string(&name),
+                subject = json::string(&subject),
+                issuer = json::string(&dn::to_string(cert.issuer())),
+                before = timestamp(&validity.not_before),
+                after = timestamp(&validity.not_after),
+                properties = properties.join(", "),
+                occurrences = occurrences.join(", "),
+            )
+        })
+        .collect();
+
+    let generated = ASN1Time::from_timestamp(now)
+        .map(|now| timestamp(&now))
+        .unwrap_or_default();
+    format!(
+        "{{\n  \"bomFormat\": \"CycloneDX\",\n  \"specVersion\": \"1.6\",\n  \"version\": 1,\n  \
+         \"metadata\": {{\n    \"timestamp\": \"{}\",\n    \"tools\": {{\"components\": \
+         [{{\"type\": \"application\", \"name\": \"cert-decoder\", \"version\": \"{}\"}}]}}\n  }},\n  \
+         \"components\": [\n{}\n  ]\n}}\n",
+        generated,
+        env!("CARGO_PKG_VERSION"),
+        components.join(",\n")
+    )
+}
+
+/// A time as CycloneDX writes it, in RFC 3339 and UTC.
+fn timestamp(time: &ASN1Time) -> String {
+    let time = time.to_datetime();
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        time.year(),
+        u8::from(time.month()),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second()
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_yaml::Value;
+
+    use super::to_string;
+    use crate::options::Options;
+    use crate::report::Report;
+    use crate::target::Target;
+    use crate::test::FakeProcessor;
+
+    #[test]
+    fn should_export_each_certificate_once_with_where_it_was_found() {
+        let mut processor = FakeProcessor::default();
+        let cert = include_str!("../resources/google.com.crt");
+        processor.add_file("google.crt", cert);
+        processor.add_file("copy.crt", cert);
+        processor.add_file("renewal.crt", include_str!("../resources/renewal.crt"));
+        let targets = vec![
+            Target::Path(String::from("google.crt")),
+            Target::Path(String::from("renewal.crt")),
+            Target::Path(String::from("copy.crt")),
+        ];
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        let bom: Value = serde_yaml::from_str(&to_string(&report, 1_780_272_000)).unwrap();
+
+        assert_eq!(bom["bomFormat"].as_str(), Some("CycloneDX"));
+        assert_eq!(
+            bom["metadata"]["timestamp"].as_str(),
+            Some("2026-06-01T00:00:00Z")
+        );
+        let components = bom["components"].as_sequence().unwrap();
+        assert_eq!(components.len(), 2);
+        let google = &components[0];
+        assert_eq!(google["type"].as_str(), Some("cryptographic-asset"));
+        assert_eq!(google["name"].as_str(), Some("*.google.com"));
+        assert_eq!(
+            google["cryptoProperties"]["certificateProperties"]["notValidAfter"].as_str(),
+            Some("2020-09-02T09:34:58Z")
+        );
+        let occurrences: Vec<&str> = google["evidence"]["occurrences"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .filter_map(|occurrence| occurrence["location"].as_str())
+            .collect();
+        assert_eq!(occurrences, vec!["google.crt", "copy.crt"]);
+        assert_eq!(
+            google["hashes"][0]["content"].as_str().map(str::len),
+            Some(64)
+        );
+    }
+}
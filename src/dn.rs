@@ -0,0 +1,123 @@
+use x509_parser::der_parser::asn1_rs::{Tag, ToDer};
+use x509_parser::x509::{AttributeTypeAndValue, X509Name};
+
+/// The attribute types of distinguished names, by OID, with the short names
+/// OpenSSL gives them.
+const ATTRIBUTES: &[(&str, &str)] = &[
+    ("2.5.4.3", "CN"),
+    ("2.5.4.4", "SN"),
+    ("2.5.4.5", "serialNumber"),
+    ("2.5.4.6", "C"),
+    ("2.5.4.7", "L"),
+    ("2.5.4.8", "ST"),
+    ("2.5.4.9", "street"),
+    ("2.5.4.10", "O"),
+    ("2.5.4.11", "OU"),
+    ("2.5.4.12", "title"),
+    ("2.5.4.13", "description"),
+    ("2.5.4.15", "businessCategory"),
+    ("2.5.4.17", "postalCode"),
+    ("2.5.4.20", "telephoneNumber"),
+    ("2.5.4.41", "name"),
+    ("2.5.4.42", "GN"),
+    ("2.5.4.43", "initials"),
+    ("2.5.4.44", "generationQualifier"),
+    ("2.5.4.46", "dnQualifier"),
+    ("2.5.4.65", "pseudonym"),
+    ("2.5.4.97", "organizationIdentifier"),
+    ("0.9.2342.19200300.100.1.1", "UID"),
+    ("0.9.2342.19200300.100.1.25", "DC"),
+    ("1.2.840.113549.1.9.1", "emailAddress"),
+    ("1.2.840.113549.1.9.2", "unstructuredName"),
+    ("1.3.6.1.4.1.311.60.2.1.1", "jurisdictionL"),
+    ("1.3.6.1.4.1.311.60.2.1.2", "jurisdictionST"),
+    ("1.3.6.1.4.1.311.60.2.1.3", "jurisdictionC"),
+];
+
+/// A distinguished name as `C=DE, O=Example, CN=example.de`, in the order it is
+/// written, with every string type decoded. x509-parser reads TeletexStrings as
+/// UTF-8 and gives up on the whole name when one isn't, and shows
+/// UniversalStrings and unknown attribute types as their bytes.
+pub fn to_string(name: &X509Name) -> String {
+    let rdns: Vec<String> = name
+        .iter()
+        .map(|rdn| {
+            let attributes: Vec<String> = rdn
+                .iter()
+                .map(|attribute| format!("{}={}", attribute_name(attribute), value(attribute)))
+                .collect();
+            attributes.join(" + ")
+        })
+        .collect();
+    rdns.join(", ")
+}
+
+/// The short name of an attribute's type, or its OID if it has none.
+pub fn attribute_name(attribute: &AttributeTypeAndValue) -> String {
+    let oid = attribute.attr_type().to_id_string();
+    match ATTRIBUTES.iter().find(|(known, _)| *known == oid) {
+        Some((_, name)) => String::from(*name),
+        None => oid,
+    }
+}
+
+/// The value of an attribute as text, or as `#` and the hex of its DER as RFC 4514
+/// writes a value that isn't a string.
+pub fn value(attribute: &AttributeTypeAndValue) -> String {
+    let any = attribute.attr_value();
+    let data = any.data;
+    match any.tag() {
+        Tag::Utf8String
+        | Tag::PrintableString
+        | Tag::Ia5String
+        | Tag::NumericString
+        | Tag::VisibleString => String::from_utf8_lossy(data).into_owned(),
+        // T.61 in theory, but Latin-1 in practice, or UTF-8 from some libraries
+        Tag::T61String => match std::str::from_utf8(data) {
+            Ok(text) => String::from(text),
+            Err(_) => data.iter().map(|byte| *byte as char).collect(),
+        },
+        Tag::BmpString => {
+            let units: Vec<u16> = data
+                .chunks(2)
+                .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        Tag::UniversalString => data
+            .chunks(4)
+            .map(|quad| {
+                let mut bytes = [0; 4];
+                bytes[..quad.len()].copy_from_slice(quad);
+                char::from_u32(u32::from_be_bytes(bytes)).unwrap_or(char::REPLACEMENT_CHARACTER)
+            })
+            .collect(),
+        _ => {
+            let der = any.to_der_vec().unwrap_or_default();
+            let hex: String = der.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("#{}", hex)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::to_string;
+
+    #[test]
+    fn should_decode_every_attribute_and_string_type() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/dn.crt")).unwrap();
+        let cert = pem.parse_x509().unwrap();
+
+        assert_eq!(
+            to_string(cert.subject()),
+            "C=DE, jurisdictionC=DE, businessCategory=Private Organization, serialNumber=HRB 12345, \
+             organizationIdentifier=VATDE-123456789, O=Müller GmbH, OU=Zentrale – IT, L=Köln, \
+             DC=example, emailAddress=it@example.de, CN=dn.example.de"
+        );
+        assert_eq!(to_string(cert.issuer()), "CN=Example Issuing CA");
+    }
+}
@@ -0,0 +1,491 @@
+use std::convert::TryFrom;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use crate::http::{Request, Url};
+use crate::options::Options;
+use crate::FileProcessor;
+
+/// The record types looked up, by the names dig takes them by.
+const TYPES: &[(&str, u16)] = &[
+    ("A", 1),
+    ("NS", 2),
+    ("CNAME", 5),
+    ("PTR", 12),
+    ("MX", 15),
+    ("TXT", 16),
+    ("AAAA", 28),
+    ("SSHFP", 44),
+    ("TLSA", 52),
+    ("CAA", 257),
+];
+
+/// The header flag a resolver sets when it has validated the answer with DNSSEC.
+const AD: u16 = 0x20;
+
+const RCODES: &[&str] = &[
+    "NOERROR", "FORMERR", "SERVFAIL", "NXDOMAIN", "NOTIMP", "REFUSED",
+];
+
+/// What a lookup found.
+pub struct Records {
+    /// The records one per line, the way `dig +short` prints them.
+    pub text: String,
+    /// Whether the resolver validated the answer with DNSSEC, setting the AD flag.
+    pub validated: bool,
+}
+
+/// Looks up a name's records of a type, with dig or, given `--doh`, the
+/// DNS-over-HTTPS resolver, asking for DNSSEC either way. The records come one
+/// per line the way `dig +short` prints them, so the same parsing works for both.
+pub fn lookup(
+    processor: &impl FileProcessor,
+    options: &Options,
+    name: &str,
+    kind: &str,
+) -> Result<Records, Box<dyn std::error::Error>> {
+    check_name(name)?;
+    let resolver = match &options.doh {
+        Some(resolver) => resolver,
+        None => {
+            // The header comments carry the AD flag, which +short leaves out.
+            // -q and -t keep dig from taking the name for an option or a server
+            let args: Vec<String> = [
+                "+dnssec",
+                "+noall",
+                "+comments",
+                "+answer",
+                "-q",
+                name,
+                "-t",
+                kind,
+            ]
+            .iter()
+            .map(|arg| String::from(*arg))
+            .collect();
+            let output = processor.run("dig", &args, &[])?;
+            return Ok(parse_dig(&String::from_utf8_lossy(&output), kind));
+        }
+    };
+
+    let code = TYPES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(kind))
+        .map(|(_, code)| *code)
+        .ok_or_else(|| {
+            format!(
+                "Error: cannot look up {} records over DNS-over-HTTPS.",
+                kind
+            )
+        })?;
+    // RFC 8484 GET, which resolvers and caches in between handle best
+    let separator = if resolver.path.contains('?') {
+        '&'
+    } else {
+        '?'
+    };
+    let url = Url {
+        path: format!(
+            "{}{}dns={}",
+            resolver.path,
+            separator,
+            URL_SAFE_NO_PAD.encode(query(name, code)?)
+        ),
+        ..resolver.clone()
+    };
+    let request = Request::get(url.clone()).header("Accept", "application/dns-message");
+    let response = processor.http(&request, &options.connect)?.success(&url)?;
+    let invalid = || format!("Error: {} did not answer with a DNS message.", resolver);
+    let records = answers(&response.body).ok_or_else(invalid)?;
+    let records = match records {
+        Ok(records) => records,
+        Err(rcode) => {
+            return Err(format!(
+                "Error: {} could not look up {} {}, {}.",
+                resolver, name, kind, rcode
+            )
+            .into())
+        }
+    };
+    let flags = u16_at(&response.body, 2).unwrap_or_default();
+    Ok(Records {
+        text: records
+            .iter()
+            .filter(|(kind, _)| *kind == code || *kind == 5)
+            .map(|(_, record)| format!("{}\n", record))
+            .collect(),
+        validated: flags & AD != 0,
+    })
+}
+
+/// The records of a type, and the CNAMEs leading to them, in what
+/// `dig +noall +comments +answer` printed, and whether its flags include `ad`.
+fn parse_dig(output: &str, kind: &str) -> Records {
+    let mut text = String::new();
+    let mut validated = false;
+    for line in output.lines() {
+        if let Some(flags) = line.strip_prefix(";; flags:") {
+            let flags = flags.split(';').next().unwrap_or_default();
+            validated = flags.split_whitespace().any(|flag| flag == "ad");
+        }
+        if line.starts_with(';') {
+            continue;
+        }
+        // The owner name, TTL, class and type come before the data
+        let record_kind = line.split_whitespace().nth(3);
+        let data = (0..4).try_fold(line, |rest, _| {
+            let rest = rest.trim_start();
+            rest.find(char::is_whitespace).map(|end| &rest[end..])
+        });
+        match (record_kind, data) {
+            (Some(record_kind), Some(data))
+                if record_kind.eq_ignore_ascii_case(kind) || record_kind == "CNAME" =>
+            {
+                text.push_str(&format!("{}\n", data.trim()));
+            }
+            _ => {}
+        }
+    }
+    Records { text, validated }
+}
+
+/// Checks that a name has the labels of a DNS name, with none empty or over 63
+/// bytes and no more than 255 bytes in all (RFC 1035), and cannot be taken for
+/// one of dig's options or a server to query.
+fn check_name(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let invalid = || format!("Error: {} is not a valid DNS name.", name);
+    let labels: Vec<&str> = name.trim_end_matches('.').split('.').collect();
+    if labels
+        .iter()
+        .any(|label| label.is_empty() || label.len() > 63)
+    {
+        return Err(invalid().into());
+    }
+    // Each label is sent after its length, and the name ends with an empty label
+    let length: usize = labels.iter().map(|label| label.len() + 1).sum::<usize>() + 1;
+    if length > 255 || name.starts_with(['-', '@']) {
+        return Err(invalid().into());
+    }
+    Ok(())
+}
+
+/// A query for one name and type, with recursion desired and the id 0 that
+/// RFC 8484 asks for so that answers can be cached, and an OPT record with the
+/// DO bit set to ask for DNSSEC.
+fn query(name: &str, code: u16) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    check_name(name)?;
+    let mut message = vec![0, 0, 1, 0, 0, 1, 0, 0, 0, 0, 0, 1];
+    for label in name.trim_end_matches('.').split('.') {
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+    message.push(0);
+    message.extend_from_slice(&code.to_be_bytes());
+    message.extend_from_slice(&[0, 1]);
+    // The root name, type OPT, a 1232 byte UDP payload, and the DO bit
+    message.extend_from_slice(&[0, 0, 41, 0x04, 0xd0, 0, 0, 0x80, 0, 0, 0]);
+    Ok(message)
+}
+
+fn u16_at(message: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *message.get(at)?,
+        *message.get(at + 1)?,
+    ]))
+}
+
+/// The name at `at`, following compression pointers, and where it ends.
+fn name_at(message: &[u8], mut at: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Pointers have to point backwards, so there can only be so many of them
+    for _ in 0..message.len() {
+        let length = *message.get(at)? as usize;
+        match length {
+            0 => {
+                let name = format!("{}.", labels.join("."));
+                return Some((name, end.unwrap_or(at + 1)));
+            }
+            1..=63 => {
+                let label = message.get(at + 1..at + 1 + length)?;
+                labels.push(text(label, false));
+                at += 1 + length;
+            }
+            _ if length & 0xc0 == 0xc0 => {
+                let pointer = usize::from(u16_at(message, at)? & 0x3fff);
+                end.get_or_insert(at + 2);
+                if pointer >= at {
+                    return None;
+                }
+                at = pointer;
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Bytes as dig shows them, with quotes and backslashes escaped in quoted text
+/// and anything unprintable as `\DDD`.
+fn text(bytes: &[u8], quoted: bool) -> String {
+    let mut text = String::new();
+    for &byte in bytes {
+        match byte {
+            b'"' | b'\\' if quoted => text.push_str(&format!("\\{}", byte as char)),
+            b'.' if !quoted => text.push_str("\\."),
+            0x20..=0x7e => text.push(byte as char),
+            _ => text.push_str(&format!("\\{:03}", byte)),
+        }
+    }
+    text
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+/// A record's data in dig's presentation format.
+fn presentation(message: &[u8], kind: u16, start: usize, data: &[u8]) -> Option<String> {
+    Some(match kind {
+        1 => Ipv4Addr::from(<[u8; 4]>::try_from(data).ok()?).to_string(),
+        28 => Ipv6Addr::from(<[u8; 16]>::try_from(data).ok()?).to_string(),
+        2 | 5 | 12 => name_at(message, start)?.0,
+        15 => format!("{} {}", u16_at(data, 0)?, name_at(message, start + 2)?.0),
+        16 => {
+            let mut strings = Vec::new();
+            let mut at = 0;
+            while at < data.len() {
+                let length = data[at] as usize;
+                strings.push(format!(
+                    "\"{}\"",
+                    text(data.get(at + 1..at + 1 + length)?, true)
+                ));
+                at += 1 + length;
+            }
+            strings.join(" ")
+        }
+        44 if data.len() > 2 => format!("{} {} {}", data[0], data[1], hex(&data[2..])),
+        52 if data.len() > 3 => format!("{} {} {} {}", data[0], data[1], data[2], hex(&data[3..])),
+        257 if data.len() > 1 => {
+            let length = data[1] as usize;
+            let tag = data.get(2..2 + length)?;
+            let value = &data[2 + length..];
+            format!("{} {} \"{}\"", data[0], text(tag, false), text(value, true))
+        }
+        _ => format!("\\# {} {}", data.len(), hex(data)),
+    })
+}
+
+/// The type and presentation of each record in the answer section, or the
+/// response code if the resolver could not answer. A name that does not exist
+/// has no records, as with dig.
+fn answers(message: &[u8]) -> Option<Result<Vec<(u16, String)>, String>> {
+    let flags = u16_at(message, 2)?;
+    match usize::from(flags & 0xf) {
+        0 | 3 => {}
+        rcode => {
+            let name = RCODES
+                .get(rcode)
+                .map_or_else(|| format!("RCODE {}", rcode), |name| String::from(*name));
+            return Some(Err(name));
+        }
+    }
+    let questions = u16_at(message, 4)?;
+    let count = u16_at(message, 6)?;
+
+    let mut at = 12;
+    for _ in 0..questions {
+        at = name_at(message, at)?.1 + 4;
+    }
+    let mut records = Vec::new();
+    for _ in 0..count {
+        at = name_at(message, at)?.1;
+        let kind = u16_at(message, at)?;
+        let length = usize::from(u16_at(message, at + 8)?);
+        let start = at + 10;
+        let data = message.get(start..start + length)?;
+        records.push((kind, presentation(message, kind, start, data)?));
+        at = start + length;
+    }
+    Some(Ok(records))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{answers, lookup, parse_dig, query, AD};
+    use crate::http::{Response, Url};
+    use crate::options::Options;
+    use crate::test::FakeProcessor;
+
+    /// What `dig +dnssec +noall +comments +answer` prints for an answer that
+    /// the resolver did not validate.
+    const DIG_MX: &str = "\
+;; Got answer:
+;; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 4412
+;; flags: qr rd ra; QUERY: 1, ANSWER: 1, AUTHORITY: 0, ADDITIONAL: 1
+
+;; OPT PSEUDOSECTION:
+; EDNS: version: 0, flags: do; udp: 1232
+;; ANSWER SECTION:
+example.com.\t\t300\tIN\tMX\t10 mx1.example.com.
+";
+
+    /// A response to `query`, with each answer's owner name a pointer to the question.
+    /// `flags` is the second byte of the header flags, with the AD bit and response code.
+    fn response(flags: u8, question: &[u8], records: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut message = vec![
+            0,
+            0,
+            0x81,
+            0x80 | flags,
+            0,
+            1,
+            0,
+            records.len() as u8,
+            0,
+            0,
+            0,
+            0,
+        ];
+        // The question, without the OPT record that follows it
+        message.extend_from_slice(&question[12..question.len() - 11]);
+        for (kind, data) in records {
+            message.extend_from_slice(&[0xc0, 0x0c]);
+            message.extend_from_slice(&kind.to_be_bytes());
+            message.extend_from_slice(&[0, 1, 0, 0, 0x0e, 0x10]);
+            message.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            message.extend_from_slice(data);
+        }
+        message
+    }
+
+    #[test]
+    fn should_encode_a_query() {
+        assert_eq!(
+            query("example.com.", 15).unwrap(),
+            b"\0\0\x01\0\0\x01\0\0\0\0\0\x01\x07example\x03com\0\0\x0f\0\x01\0\0\x29\x04\xd0\0\0\x80\0\0\0".to_vec()
+        );
+        assert!(query("example..com", 1).is_err());
+        let long = vec!["a".repeat(63); 4].join(".");
+        assert!(query(&long, 1).is_err());
+        assert!(query(&long[2..], 1).is_ok());
+    }
+
+    #[test]
+    fn should_show_answers_as_dig_does() {
+        let question = query("example.com", 15).unwrap();
+        let records = [
+            // mx1 followed by a pointer to example.com in the question
+            (15, b"\0\x0a\x03mx1\xc0\x0c".to_vec()),
+            (16, b"\x0ev=STSv1; id=20\x04\"24\"".to_vec()),
+            (44, vec![4, 2, 0xab, 0xcd]),
+            (52, vec![3, 1, 1, 0x01, 0xff]),
+            (257, b"\0\x05issueletsencrypt.org".to_vec()),
+            (1, vec![192, 0, 2, 1]),
+            (99, vec![1, 2]),
+        ];
+        let message = response(0, &question, &records);
+        let shown: Vec<String> = answers(&message)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect();
+        assert_eq!(
+            shown,
+            vec![
+                "10 mx1.example.com.",
+                "\"v=STSv1; id=20\" \"\\\"24\\\"\"",
+                "4 2 ABCD",
+                "3 1 1 01FF",
+                "0 issue \"letsencrypt.org\"",
+                "192.0.2.1",
+                "\\# 2 0102",
+            ]
+        );
+
+        assert_eq!(answers(&response(3, &question, &[])), Some(Ok(Vec::new())));
+        assert_eq!(
+            answers(&response(2, &question, &[])),
+            Some(Err(String::from("SERVFAIL")))
+        );
+        // A pointer to itself
+        assert_eq!(
+            answers(&response(0, &question, &[(5, vec![0xc0, 0x29])])),
+            None
+        );
+    }
+
+    #[test]
+    fn should_read_the_records_and_ad_flag_dig_printed() {
+        let output = "\
+;; flags: qr rd ra ad; QUERY: 1, ANSWER: 3, AUTHORITY: 0, ADDITIONAL: 1
+;; ANSWER SECTION:
+ssh.example.com.\t300\tIN\tCNAME\tweb01.example.com.
+web01.example.com. 300 IN SSHFP 4 2 05132813D5865257 FD843AAD
+web01.example.com. 300 IN RRSIG SSHFP 13 3 300 20261101000000 20261011000000 2371 example.com. abc=
+";
+
+        let records = parse_dig(output, "SSHFP");
+
+        assert_eq!(
+            records.text,
+            "web01.example.com.\n4 2 05132813D5865257 FD843AAD\n"
+        );
+        assert!(records.validated);
+        assert!(!parse_dig(DIG_MX, "MX").validated);
+    }
+
+    #[test]
+    fn should_look_up_records_over_https() {
+        let mut processor = FakeProcessor::default();
+        let options = Options {
+            doh: Some(Url::parse("https://dns.example.net/dns-query").unwrap()),
+            ..Options::default()
+        };
+        let question = query("_mta-sts.example.com", 16).unwrap();
+        let body = response(
+            AD as u8,
+            &question,
+            &[
+                (5, b"\x03sts\x07example\x03net\0".to_vec()),
+                (16, b"\x11v=STSv1; id=2024;".to_vec()),
+            ],
+        );
+        processor.responses.insert(
+            String::from("https://dns.example.net/dns-query?dns=AAABAAABAAAAAAABCF9tdGEtc3RzB2V4YW1wbGUDY29tAAAQAAEAACkE0AAAgAAAAA"),
+            Response {
+                status: 200,
+                headers: Vec::new(),
+                body,
+            },
+        );
+
+        let records = lookup(&processor, &options, "_mta-sts.example.com", "TXT").unwrap();
+        assert_eq!(records.text, "sts.example.net.\n\"v=STSv1; id=2024;\"\n");
+        assert!(records.validated);
+        assert!(lookup(&processor, &options, "example.com", "HINFO")
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("cannot look up HINFO records"));
+
+        processor.commands.insert(
+            String::from("dig +dnssec +noall +comments +answer -q example.com -t MX"),
+            String::from(DIG_MX),
+        );
+        let records = lookup(&processor, &Options::default(), "example.com", "MX").unwrap();
+        assert_eq!(records.text, "10 mx1.example.com.\n");
+        assert!(!records.validated);
+        for name in ["-f/etc/passwd", "@evil.example"] {
+            assert!(lookup(&processor, &Options::default(), name, "MX")
+                .err()
+                .unwrap()
+                .to_string()
+                .ends_with("is not a valid DNS name."));
+        }
+    }
+}
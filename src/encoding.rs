@@ -0,0 +1,149 @@
+use base64::engine::general_purpose::{STANDARD, URL_SAFE};
+use base64::Engine;
+use x509_parser::parse_x509_certificate;
+use x509_parser::pem::Pem;
+
+/// How a certificate file is encoded, from `--input-encoding`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum InputEncoding {
+    /// Work it out from the contents.
+    #[default]
+    Auto,
+    Pem,
+    /// Binary DER.
+    Der,
+    /// DER in base64 without the PEM armor, as in LDIF and many logs.
+    Base64,
+    /// DER in hex, optionally with colons or spaces between the bytes.
+    Hex,
+}
+
+impl InputEncoding {
+    pub fn parse(value: &str) -> Result<InputEncoding, Box<dyn std::error::Error>> {
+        match value.to_lowercase().as_str() {
+            "auto" => Ok(InputEncoding::Auto),
+            "pem" => Ok(InputEncoding::Pem),
+            "der" => Ok(InputEncoding::Der),
+            "base64" => Ok(InputEncoding::Base64),
+            "hex" => Ok(InputEncoding::Hex),
+            _ => Err(format!(
+                "Error: {} is not an input encoding, use auto, pem, der, base64 or hex.",
+                value
+            )
+            .into()),
+        }
+    }
+}
+
+/// Finds a lone certificate that isn't PEM: binary DER, or DER as hex or
+/// base64 text. Only an encoding that yields a certificate is taken, so text
+/// that merely looks like hex or base64 isn't mistaken for one.
+pub fn sniff(bytes: &[u8]) -> Option<Vec<u8>> {
+    let encodings = [
+        InputEncoding::Der,
+        InputEncoding::Hex,
+        InputEncoding::Base64,
+    ];
+    encodings.iter().find_map(|encoding| {
+        let der = decode(*encoding, bytes).ok()?;
+        parse_x509_certificate(&der).ok()?;
+        Some(der)
+    })
+}
+
+/// Turns a lone certificate in any of the encodings into DER.
+pub fn decode(encoding: InputEncoding, bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let text = || String::from_utf8_lossy(bytes);
+    match encoding {
+        InputEncoding::Auto => {
+            sniff(bytes).ok_or_else(|| String::from("not a DER, hex or base64 certificate"))
+        }
+        InputEncoding::Pem => match Pem::iter_from_buffer(bytes).next() {
+            Some(Ok(pem)) => Ok(pem.contents),
+            Some(Err(error)) => Err(error.to_string()),
+            None => Err(String::from("no PEM block found")),
+        },
+        InputEncoding::Der => Ok(bytes.to_vec()),
+        InputEncoding::Hex => hex(&text()),
+        InputEncoding::Base64 => base64(&text()),
+    }
+}
+
+fn hex(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    let digits: Vec<u8> = text
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace() && *b != b':')
+        .collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return Err(String::from("not an even number of hex digits"));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).map_err(|_| String::from("not hex"))?;
+            u8::from_str_radix(pair, 16).map_err(|_| format!("{} is not a hex byte", pair))
+        })
+        .collect()
+}
+
+/// Accepts an LDIF attribute too, such as `userCertificate;binary:: MIIC...`,
+/// whose value is folded onto lines that start with a space.
+fn base64(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    let text = match text.split_once(":: ") {
+        Some((attribute, value)) if !attribute.contains(char::is_whitespace) => value,
+        _ => text,
+    };
+    let encoded: String = text.split_whitespace().collect();
+    STANDARD
+        .decode(&encoded)
+        .or_else(|_| URL_SAFE.decode(&encoded))
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod test {
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    use super::{decode, sniff, InputEncoding};
+    use crate::test::google_der;
+
+    #[test]
+    fn should_sniff_der_hex_and_base64() {
+        let der = google_der();
+        let hex: Vec<String> = der.iter().map(|b| format!("{:02x}", b)).collect();
+        let base64 = STANDARD.encode(&der);
+        let ldif = format!(
+            "userCertificate;binary:: {}\n {}\n",
+            &base64[..60],
+            &base64[60..]
+        );
+
+        assert_eq!(sniff(&der), Some(der.clone()));
+        assert_eq!(sniff(hex.join(":").as_bytes()), Some(der.clone()));
+        assert_eq!(
+            sniff(format!("0x{}", hex.concat()).as_bytes()),
+            Some(der.clone())
+        );
+        assert_eq!(sniff(base64.as_bytes()), Some(der.clone()));
+        assert_eq!(sniff(ldif.as_bytes()), Some(der));
+        assert_eq!(sniff(b"deadbeef"), None);
+    }
+
+    #[test]
+    fn should_parse_input_encodings() {
+        assert_eq!(InputEncoding::parse("HEX").unwrap(), InputEncoding::Hex);
+        assert_eq!(
+            format!("{}", InputEncoding::parse("utf8").err().unwrap()),
+            "Error: utf8 is not an input encoding, use auto, pem, der, base64 or hex."
+        );
+        assert!(decode(InputEncoding::Hex, b"abc").is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use std::fmt;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::parse_x509_certificate;
+
+use crate::cms;
+use crate::dn;
+use crate::http::{Request, Trust, Url};
+use crate::remote::ConnectOptions;
+use crate::report::Report;
+use crate::FileProcessor;
+
+/// Where RFC 7030 puts an EST server's operations.
+const WELL_KNOWN: &str = "/.well-known/est";
+
+/// The CA certificates an EST server hands out, and how they fit together.
+pub struct Est {
+    pub url: String,
+    pub content_type: Option<String>,
+    /// Each certificate's name and what it is, indented under its issuer.
+    pub hierarchy: Vec<(usize, String)>,
+    pub report: Report,
+}
+
+/// The cacerts URL of an EST server, given its host, its base URL, or the
+/// URL of one of its labels.
+pub fn cacerts_url(target: &str) -> Result<Url, Box<dyn std::error::Error>> {
+    let target = match target.contains("://") {
+        true => String::from(target),
+        false => format!("https://{}", target),
+    };
+    let mut url = Url::parse(&target)?;
+    url.path = match url.path.as_str() {
+        "/" => format!("{}/cacerts", WELL_KNOWN),
+        path if path.ends_with("/cacerts") => String::from(path),
+        path => format!("{}/cacerts", path.trim_end_matches('/')),
+    };
+    Ok(url)
+}
+
+/// Fetches the CA certificates an EST server distributes, as a device would
+/// before enrolling. The server is not authenticated, as RFC 7030 allows for
+/// bootstrapping, so the root's fingerprint has to be checked out of band.
+pub fn cacerts(
+    processor: &impl FileProcessor,
+    target: &str,
+    connect: &ConnectOptions,
+) -> Result<Est, Box<dyn std::error::Error>> {
+    let url = cacerts_url(target)?;
+    let mut request = Request::get(url.clone()).header("Accept", "application/pkcs7-mime");
+    request.trust = Trust::Insecure;
+    let response = processor.http(&request, connect)?.success(&url)?;
+
+    // RFC 7030 has the PKCS#7 sent in base64, though some servers send it as DER or PEM
+    let der = cms::find(&response.body)
+        .or_else(|| {
+            let text: Vec<u8> = response
+                .body
+                .iter()
+                .filter(|byte| !byte.is_ascii_whitespace())
+                .copied()
+                .collect();
+            STANDARD.decode(text).ok().and_then(|der| cms::find(&der))
+        })
+        .ok_or_else(|| format!("Error: {} did not answer with PKCS#7 CA certificates.", url))?;
+    let certificates = cms::read(&url.to_string(), &der)?.certificates;
+    if certificates.is_empty() {
+        return Err(format!("Error: {} answered without any CA certificates.", url).into());
+    }
+
+    let mut report = Report::default();
+    let content_type = response.header("Content-Type").map(String::from);
+    if !content_type
+        .as_deref()
+        .is_some_and(|content_type| content_type.starts_with("application/pkcs7-mime"))
+    {
+        report.warnings.push(format!(
+            "Warning: {} answered with Content-Type {}, RFC 7030 requires application/pkcs7-mime.",
+            url,
+            content_type.as_deref().unwrap_or("none")
+        ));
+    }
+    let parsed: Vec<X509Certificate> = certificates
+        .iter()
+        .filter_map(|der| parse_x509_certificate(der).ok().map(|(_, cert)| cert))
+        .collect();
+    let hierarchy = hierarchy(&parsed);
+    for cert in &parsed {
+        let name = dn::to_string(cert.subject());
+        if !cert.is_ca() {
+            report.warnings.push(format!(
+                "Warning: the EST server sent {}, which is not a CA certificate.",
+                name
+            ));
+        }
+        if issuer(cert, &parsed).is_none() && !self_signed(cert) {
+            report.warnings.push(format!(
+                "Warning: the EST server sent {}, issued by {}, which it did not send, so devices can't build a path to a root.",
+                name,
+                dn::to_string(cert.issuer())
+            ));
+        }
+    }
+
+    let count = certificates.len();
+    let items = certificates
+        .into_iter()
+        .enumerate()
+        .map(|(i, der)| (format!("cacerts [{}/{}]", i + 1, count), der))
+        .collect();
+    report.add_named(&url.to_string(), Ok(items));
+    Ok(Est {
+        url: url.to_string(),
+        content_type,
+        hierarchy,
+        report,
+    })
+}
+
+fn self_signed(cert: &X509Certificate) -> bool {
+    cert.subject().as_raw() == cert.issuer().as_raw() && cert.verify_signature(None).is_ok()
+}
+
+/// Which of the other certificates signed one, preferring a self-signed one
+/// when a root's key was rolled over and two share its name and key.
+fn issuer(cert: &X509Certificate, all: &[X509Certificate]) -> Option<usize> {
+    if self_signed(cert) {
+        return None;
+    }
+    let candidates = all.iter().enumerate().filter(|(_, candidate)| {
+        candidate.subject().as_raw() == cert.issuer().as_raw()
+            && !std::ptr::eq(*candidate, cert)
+            && cert.verify_signature(Some(candidate.public_key())).is_ok()
+    });
+    let candidates: Vec<(usize, &X509Certificate)> = candidates.collect();
+    candidates
+        .iter()
+        .find(|(_, candidate)| self_signed(candidate))
+        .or_else(|| candidates.first())
+        .map(|(i, _)| *i)
+}
+
+/// Each certificate under the one that issued it, roots and those whose
+/// issuer is missing first.
+fn hierarchy(all: &[X509Certificate]) -> Vec<(usize, String)> {
+    let issuers: Vec<Option<usize>> = all.iter().map(|cert| issuer(cert, all)).collect();
+    let mut lines = Vec::new();
+    let mut shown = vec![false; all.len()];
+    let mut stack: Vec<(usize, usize)> = issuers
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, issuer)| issuer.is_none())
+        .map(|(i, _)| (i, 0))
+        .collect();
+    loop {
+        let (i, depth) = match stack.pop() {
+            Some(next) => next,
+            // A cycle of certificates signing each other, such as rollover
+            // certificates sent without the roots, has no top to start from
+            None => match shown.iter().position(|shown| !shown) {
+                Some(i) => (i, 0),
+                None => break,
+            },
+        };
+        if shown[i] {
+            continue;
+        }
+        shown[i] = true;
+        lines.push((depth, describe(&all[i], issuers[i].is_some())));
+        let children = issuers
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, issuer)| **issuer == Some(i));
+        stack.extend(children.map(|(child, _)| (child, depth + 1)));
+    }
+    lines
+}
+
+fn describe(cert: &X509Certificate, issued: bool) -> String {
+    let name = dn::to_string(cert.subject());
+    if self_signed(cert) {
+        format!("{}, a self-signed root", name)
+    } else if cert.subject().as_raw() == cert.issuer().as_raw() {
+        // RFC 7030's OldWithNew and NewWithOld certificates
+        format!("{}, a root key rollover certificate", name)
+    } else if !issued {
+        format!(
+            "{}, issued by {}, which was not sent",
+            name,
+            dn::to_string(cert.issuer())
+        )
+    } else {
+        name
+    }
+}
+
+impl fmt::Display for Est {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "EST server: {}", self.url)?;
+        writeln!(
+            f,
+            "Server authentication: skipped, as for bootstrapping, so check the root's fingerprint out of band"
+        )?;
+        if let Some(content_type) = &self.content_type {
+            writeln!(f, "Content type: {}", content_type)?;
+        }
+        writeln!(f, "CA certificates: {}", self.hierarchy.len())?;
+        for (depth, line) in &self.hierarchy {
+            writeln!(f, "{}{}", "  ".repeat(depth + 1), line)?;
+        }
+        write!(f, "{}", self.report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{cacerts, cacerts_url};
+    use crate::http::Response;
+    use crate::remote::ConnectOptions;
+    use crate::test::FakeProcessor;
+
+    const URL: &str = "https://est.example.com/.well-known/est/cacerts";
+
+    #[test]
+    fn should_find_the_cacerts_url() {
+        let urls = [
+            ("est.example.com", URL),
+            ("https://est.example.com", URL),
+            ("https://est.example.com/.well-known/est", URL),
+            (
+                "https://est.example.com:8443/.well-known/est/iot/",
+                "https://est.example.com:8443/.well-known/est/iot/cacerts",
+            ),
+            (URL, URL),
+        ];
+        for (target, url) in urls {
+            assert_eq!(cacerts_url(target).unwrap().to_string(), url);
+        }
+    }
+
+    #[test]
+    fn should_decode_the_ca_hierarchy() {
+        let mut processor = FakeProcessor::default();
+        processor.responses.insert(
+            String::from(URL),
+            Response {
+                status: 200,
+                headers: vec![(
+                    String::from("Content-Type"),
+                    String::from("application/pkcs7-mime"),
+                )],
+                body: include_bytes!("../resources/est-cacerts.b64").to_vec(),
+            },
+        );
+
+        let est = cacerts(&processor, "est.example.com", &ConnectOptions::default()).unwrap();
+        let output = est.to_string();
+        assert!(output.starts_with(
+            "EST server: https://est.example.com/.well-known/est/cacerts\nServer authentication: skipped, as for bootstrapping, so check the root's fingerprint out of band\nContent type: application/pkcs7-mime\nCA certificates: 2\n  CN=Example Root CA, a self-signed root\n    CN=Example Issuing CA\n"
+        ));
+        assert!(output
+            .contains("==> https://est.example.com/.well-known/est/cacerts: cacerts [2/2] <=="));
+        assert!(est.report.warnings.is_empty());
+    }
+
+    #[test]
+    fn should_warn_about_what_clients_would_trip_over() {
+        let mut processor = FakeProcessor::default();
+        processor.responses.insert(
+            String::from(URL),
+            Response {
+                status: 200,
+                headers: Vec::new(),
+                body: include_bytes!("../resources/bundle.p7b").to_vec(),
+            },
+        );
+
+        let est = cacerts(&processor, URL, &ConnectOptions::default()).unwrap();
+        assert_eq!(
+            est.hierarchy,
+            vec![(
+                0,
+                String::from("CN=self-signed.example, a self-signed root")
+            )]
+        );
+        assert_eq!(
+            est.report.warnings[0],
+            "Warning: https://est.example.com/.well-known/est/cacerts answered with Content-Type none, RFC 7030 requires application/pkcs7-mime."
+        );
+
+        processor.responses.get_mut(URL).unwrap().body = b"<html></html>".to_vec();
+        assert_eq!(
+            cacerts(&processor, URL, &ConnectOptions::default())
+                .err()
+                .unwrap()
+                .to_string(),
+            "Error: https://est.example.com/.well-known/est/cacerts did not answer with PKCS#7 CA certificates."
+        );
+    }
+}
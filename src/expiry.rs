@@ -0,0 +1,208 @@
+use std::fmt;
+use std::time::Duration;
+
+use x509_parser::certificate::X509Certificate;
+
+use crate::report::Report;
+use crate::trust_store::list;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// When every certificate in a report expires, grouped by how soon,
+/// printed instead of every decoded certificate.
+pub struct Expiry<'a> {
+    report: &'a Report,
+    now: i64,
+    expiring_within: Duration,
+}
+
+impl<'a> Expiry<'a> {
+    pub fn new(report: &'a Report, now: i64, expiring_within: Duration) -> Expiry<'a> {
+        Expiry {
+            report,
+            now,
+            expiring_within,
+        }
+    }
+}
+
+impl fmt::Display for Expiry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut entries: Vec<_> = self
+            .report
+            .entries
+            .iter()
+            .map(|entry| (entry, entry.certificate()))
+            .collect();
+        entries.sort_by_key(|(_, cert)| cert.validity().not_after.timestamp());
+        let horizon = self.now + self.expiring_within.as_secs() as i64;
+
+        let mut expired = Vec::new();
+        let mut not_yet_valid = Vec::new();
+        let mut expiring = Vec::new();
+        let mut valid = Vec::new();
+        for (entry, cert) in &entries {
+            let validity = cert.validity();
+            let not_after = validity.not_after.timestamp();
+            let line = |when: String| {
+                format!(
+                    "{}: {}, {} ({})",
+                    entry.location,
+                    cert.subject(),
+                    when,
+                    validity.not_after
+                )
+            };
+            if not_after < self.now {
+                expired.push(line(format!("expired {} ago", days(self.now - not_after))));
+            } else if validity.not_before.timestamp() > self.now {
+                let starts = validity.not_before.timestamp() - self.now;
+                not_yet_valid.push(line(format!("valid in {}", days(starts))));
+            } else if not_after < horizon {
+                expiring.push(line(format!("expires in {}", days(not_after - self.now))));
+            } else {
+                valid.push(line(format!("expires in {}", days(not_after - self.now))));
+            }
+        }
+
+        writeln!(f, "Certificates: {}", entries.len())?;
+        list(f, "Expired", &expired)?;
+        list(f, "Not yet valid", &not_yet_valid)?;
+        let within = self.expiring_within.as_secs() as i64 / DAY;
+        list(f, &format!("Expiring within {} days", within), &expiring)?;
+        list(f, "Valid", &valid)?;
+
+        for failure in &self.report.failures {
+            writeln!(f, "==> {} <==", failure.location)?;
+            writeln!(f, "{}", failure.error)?;
+        }
+        for warning in &self.report.warnings {
+            writeln!(f, "{}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+/// Which certificates to keep when scanning, from `--expired`, `--not-yet-valid`
+/// and `--expiring-within`, to turn a scan into a renewal list. A certificate is
+/// kept if it is any of those asked for.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExpiryFilter {
+    pub expired: bool,
+    pub not_yet_valid: bool,
+    /// Valid now, but not for this much longer.
+    pub expiring_within: Option<Duration>,
+}
+
+impl ExpiryFilter {
+    pub fn is_set(&self) -> bool {
+        self.expired || self.not_yet_valid || self.expiring_within.is_some()
+    }
+
+    pub fn matches(&self, cert: &X509Certificate, now: i64) -> bool {
+        let validity = cert.validity();
+        let not_after = validity.not_after.timestamp();
+        if not_after < now {
+            return self.expired;
+        }
+        if validity.not_before.timestamp() > now {
+            return self.not_yet_valid;
+        }
+        self.expiring_within
+            .is_some_and(|within| not_after < now + within.as_secs() as i64)
+    }
+}
+
+impl fmt::Display for ExpiryFilter {
+    /// What is kept, as `expired or expiring within 30 days`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut kept = Vec::new();
+        if self.expired {
+            kept.push(String::from("expired"));
+        }
+        if self.not_yet_valid {
+            kept.push(String::from("not yet valid"));
+        }
+        if let Some(within) = self.expiring_within {
+            kept.push(format!("expiring within {}", days(within.as_secs() as i64)));
+        }
+        write!(f, "{}", kept.join(" or "))
+    }
+}
+
+/// Rounds a number of seconds down to whole days, as `1 day` or `12 days`.
+fn days(seconds: i64) -> String {
+    match seconds / DAY {
+        1 => String::from("1 day"),
+        days => format!("{} days", days),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::time::Duration;
+
+    use super::{Expiry, ExpiryFilter};
+    use crate::report::Report;
+    use crate::target::Target;
+    use crate::test::FakeProcessor;
+
+    #[test]
+    fn should_group_certificates_by_when_they_expire() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("sha1.crt", include_str!("../resources/sha1-root.crt"));
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        let targets = vec![
+            Target::Path(String::from("sha1.crt")),
+            Target::Path(String::from("google.crt")),
+        ];
+        let report = Report::build(&processor, &targets, &Default::default());
+        let google = report.entries[1].certificate();
+        // Ten days before the google.com certificate expires
+        let now = google.validity().not_after.timestamp() - 10 * 24 * 60 * 60;
+
+        let summary = Expiry::new(&report, now, Duration::from_secs(30 * 24 * 60 * 60)).to_string();
+
+        assert!(
+            summary.starts_with("Certificates: 2\nExpired: none\nNot yet valid:\n    sha1.crt: ")
+        );
+        assert!(summary.contains("Expiring within 30 days:\n    google.crt: "));
+        assert!(summary.contains(", expires in 10 days ("));
+        assert!(summary.ends_with("Valid: none\n"));
+    }
+
+    #[test]
+    fn should_keep_only_the_certificates_the_filter_asks_for() {
+        let processor = FakeProcessor {
+            is_file: true,
+            file_str: String::from(include_str!("../resources/google.com.crt")),
+            ..FakeProcessor::default()
+        };
+        let targets = vec![Target::Path(String::from("google.crt"))];
+        let report = Report::build(&processor, &targets, &Default::default());
+        let google = report.entries[0].certificate();
+        let not_after = google.validity().not_after.timestamp();
+        let expiring = ExpiryFilter {
+            expiring_within: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            ..ExpiryFilter::default()
+        };
+        let expired = ExpiryFilter {
+            expired: true,
+            ..ExpiryFilter::default()
+        };
+
+        assert!(expiring.matches(&google, not_after - 10 * 24 * 60 * 60));
+        assert!(!expiring.matches(&google, not_after - 40 * 24 * 60 * 60));
+        assert!(!expiring.matches(&google, not_after + 1));
+        assert!(expired.matches(&google, not_after + 1));
+        assert_eq!(
+            ExpiryFilter {
+                expired: true,
+                ..expiring
+            }
+            .to_string(),
+            "expired or expiring within 30 days"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+/// What the fields of the TBS certificate dump mean, by name.
+const FIELDS: &[(&str, &str)] = &[
+    (
+        "version",
+        "The X.509 format version, counted from 0, so 2 is v3, the only one with extensions.",
+    ),
+    (
+        "serial",
+        "The number the issuer gave this certificate, unique among everything it issued.",
+    ),
+    (
+        "signature",
+        "The algorithm the issuer signed this certificate with.",
+    ),
+    ("issuer", "The CA that vouches for this certificate."),
+    (
+        "validity",
+        "When this certificate can be used, outside of which it is rejected.",
+    ),
+    (
+        "not_before",
+        "The certificate is not valid before this time.",
+    ),
+    ("not_after", "The certificate expires at this time."),
+    (
+        "subject",
+        "Who this certificate was issued to, often empty for websites, which use the alternative names.",
+    ),
+    (
+        "subject_pki",
+        "The public key this certificate binds to the subject, and what kind of key it is.",
+    ),
+    (
+        "issuer_uid",
+        "A leftover from X.509 v2 to tell apart issuers with the same name, no longer used.",
+    ),
+    (
+        "subject_uid",
+        "A leftover from X.509 v2 to tell apart subjects with the same name, no longer used.",
+    ),
+    (
+        "extensions",
+        "Everything X.509 v3 added, each one marked critical if software that doesn't know it must reject the certificate.",
+    ),
+];
+
+/// What the common extensions are for, by OID.
+const EXTENSIONS: &[(&str, &str)] = &[
+    (
+        "2.5.29.14",
+        "Subject Key Identifier: a short ID of this certificate's key, which certificates it issues point back to.",
+    ),
+    (
+        "2.5.29.15",
+        "Key Usage: the operations the key may be used for, such as signing or encrypting keys.",
+    ),
+    (
+        "2.5.29.17",
+        "Subject Alternative Name: the DNS names, IP addresses and emails this certificate is valid for.",
+    ),
+    (
+        "2.5.29.18",
+        "Issuer Alternative Name: other names for the issuer.",
+    ),
+    (
+        "2.5.29.19",
+        "Basic Constraints: whether this is a CA that can issue certificates, and how many CAs may follow it.",
+    ),
+    (
+        "2.5.29.30",
+        "Name Constraints: the names a CA may and may not issue certificates for.",
+    ),
+    (
+        "2.5.29.31",
+        "CRL Distribution Points: where to download the list of revoked certificates.",
+    ),
+    (
+        "2.5.29.32",
+        "Certificate Policies: the rules the issuer followed, such as how it validated the subject.",
+    ),
+    (
+        "2.5.29.33",
+        "Policy Mappings: which of the issuer's policies match which of another CA's.",
+    ),
+    (
+        "2.5.29.35",
+        "Authority Key Identifier: which of the issuer's keys signed this, so the right issuer is found when it has several.",
+    ),
+    (
+        "2.5.29.36",
+        "Policy Constraints: when the certificates below a CA must have a policy.",
+    ),
+    (
+        "2.5.29.37",
+        "Extended Key Usage: what the certificate is for, such as TLS servers, clients or code signing.",
+    ),
+    (
+        "2.5.29.54",
+        "Inhibit anyPolicy: how many CAs down the anyPolicy policy stops counting.",
+    ),
+    (
+        "1.3.6.1.5.5.7.1.1",
+        "Authority Information Access: where to fetch the issuer's certificate and check revocation with OCSP.",
+    ),
+    (
+        "1.3.6.1.5.5.7.1.24",
+        "TLS Feature: the server must staple an OCSP response, or the certificate is rejected.",
+    ),
+    (
+        "1.3.6.1.4.1.11129.2.4.2",
+        "Signed Certificate Timestamps: proof the certificate was logged publicly in Certificate Transparency.",
+    ),
+    (
+        "1.3.6.1.4.1.11129.2.4.3",
+        "Precertificate poison: makes this unusable, it only exists to be logged before the real certificate is issued.",
+    ),
+];
+
+/// What the lines the report adds before the dump mean, by label.
+const LABELS: &[(&str, &str)] = &[
+    (
+        "Subject",
+        "Who this certificate was issued to, as a distinguished name.",
+    ),
+    (
+        "Issuer",
+        "The CA that signed this certificate, which has to be trusted or lead to one that is.",
+    ),
+    (
+        "Critical extensions",
+        "Extensions software must understand to accept the certificate at all.",
+    ),
+    (
+        "Signed certificate timestamps",
+        "Promises from Certificate Transparency logs to publish this certificate, which browsers require.",
+    ),
+    (
+        "Trust anchor",
+        "The root CA this certificate chains to, which is trusted because it was installed.",
+    ),
+    (
+        "Precertificate",
+        "A certificate made to be logged in Certificate Transparency, not to be used.",
+    ),
+];
+
+/// The report with a line explaining each field it knows in plain English, above
+/// that field and indented like it, for people learning what a certificate holds.
+pub fn annotate(report: &str) -> String {
+    let mut annotated = String::new();
+    let mut in_tbs = false;
+    for line in report.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let trimmed = line.trim_start();
+        let explanation = if in_tbs {
+            let field = trimmed.split(':').next().unwrap_or_default();
+            match indent {
+                4 => lookup(FIELDS, field),
+                8 if field == "not_before" || field == "not_after" => lookup(FIELDS, field),
+                12 => trimmed
+                    .strip_prefix("oid: OID(")
+                    .and_then(|oid| oid.strip_suffix("),"))
+                    .and_then(|oid| lookup(EXTENSIONS, oid)),
+                _ => None,
+            }
+        } else if indent == 0 {
+            line.split_once(':')
+                .and_then(|(label, _)| lookup(LABELS, label))
+        } else {
+            None
+        };
+        if let Some(explanation) = explanation {
+            annotated.push_str(&format!("{}# {}\n", &line[..indent], explanation));
+        }
+        annotated.push_str(line);
+        annotated.push('\n');
+        if line.ends_with("TbsCertificate {") {
+            in_tbs = true;
+        } else if line == "}" {
+            in_tbs = false;
+        }
+    }
+    annotated
+}
+
+fn lookup(table: &[(&str, &'static str)], key: &str) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(known, _)| *known == key)
+        .map(|(_, explanation)| *explanation)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::annotate;
+
+    #[test]
+    fn should_explain_each_field_above_it() {
+        let report = "Subject: CN=example.com\n\
+                      TbsCertificate {\n    \
+                      serial: 1,\n    \
+                      validity: Validity {\n        \
+                      not_after: ASN1Time {\n        \
+                      },\n    \
+                      },\n    \
+                      extensions: [\n        \
+                      X509Extension {\n            \
+                      oid: OID(2.5.29.35),\n            \
+                      critical: false,\n        \
+                      },\n    \
+                      ],\n\
+                      }\n\
+                      serial: 2\n";
+
+        let annotated = annotate(report);
+
+        assert!(annotated.starts_with(
+            "# Who this certificate was issued to, as a distinguished name.\n\
+             Subject: CN=example.com\n\
+             TbsCertificate {\n    \
+             # The number the issuer gave this certificate, unique among everything it issued.\n    \
+             serial: 1,\n"
+        ));
+        assert!(annotated.contains(
+            "        # The certificate expires at this time.\n        not_after: ASN1Time {\n"
+        ));
+        assert!(annotated.contains(
+            "            # Authority Key Identifier: which of the issuer's keys signed this, \
+             so the right issuer is found when it has several.\n            oid: OID(2.5.29.35),\n"
+        ));
+        assert!(annotated.ends_with("}\nserial: 2\n"));
+    }
+}
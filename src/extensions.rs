@@ -0,0 +1,252 @@
+use std::fmt;
+
+use x509_parser::asn1_rs::{Any, Class, FromDer};
+use x509_parser::certificate::X509Certificate;
+
+use crate::android;
+use crate::gost;
+use crate::idevid;
+use crate::matter;
+use crate::microsoft;
+use crate::netscape;
+use crate::other_name;
+use crate::pq;
+use crate::pss;
+use crate::qc;
+use crate::sgx;
+use crate::shaken;
+use crate::sm2;
+use crate::tpm;
+
+/// An extension x509-parser leaves opaque, decoded into named fields.
+pub struct Decoded {
+    pub name: &'static str,
+    pub fields: Vec<Field>,
+}
+
+/// A decoded value, which can be a group of further fields.
+pub struct Field {
+    pub name: String,
+    pub value: String,
+    pub fields: Vec<Field>,
+}
+
+impl Field {
+    pub fn new(name: &str, value: impl Into<String>) -> Field {
+        Field {
+            name: String::from(name),
+            value: value.into(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn group(name: &str, fields: Vec<Field>) -> Field {
+        Field {
+            name: String::from(name),
+            value: String::new(),
+            fields,
+        }
+    }
+}
+
+/// The extensions this can decode, by OID, with what they are called.
+type Decoder = fn(&[u8]) -> Option<Vec<Field>>;
+const DECODERS: &[(&str, &str, Decoder)] = &[
+    (
+        android::KEY_ATTESTATION,
+        "Android key attestation",
+        android::key_description,
+    ),
+    (
+        microsoft::TEMPLATE_NAME,
+        "Microsoft certificate template name",
+        microsoft::template_name,
+    ),
+    (
+        microsoft::CA_VERSION,
+        "Microsoft CA version",
+        microsoft::ca_version,
+    ),
+    (
+        microsoft::TEMPLATE,
+        "Microsoft certificate template",
+        microsoft::template,
+    ),
+    (
+        microsoft::APPLICATION_POLICIES,
+        "Microsoft application policies",
+        microsoft::application_policies,
+    ),
+    (
+        microsoft::NTDS_CA_SECURITY,
+        "Microsoft NTDS CA security",
+        microsoft::ntds_ca_security,
+    ),
+    (
+        pq::ALT_PUBLIC_KEY,
+        "Alternative public key",
+        pq::alt_public_key,
+    ),
+    (
+        pq::ALT_SIGNATURE_ALGORITHM,
+        "Alternative signature algorithm",
+        pq::alt_signature_algorithm,
+    ),
+    (
+        pq::ALT_SIGNATURE_VALUE,
+        "Alternative signature",
+        pq::alt_signature_value,
+    ),
+    (
+        qc::QC_STATEMENTS,
+        "Qualified certificate statements",
+        qc::statements,
+    ),
+    (
+        sgx::SGX_EXTENSIONS,
+        "SGX PCK extensions",
+        sgx::pck_extensions,
+    ),
+    (
+        shaken::TN_AUTH_LIST,
+        "STIR/SHAKEN TNAuthList",
+        shaken::tn_auth_list,
+    ),
+];
+
+/// Decodes the vendor extensions of a certificate, noting any that don't parse,
+/// what Matter, TPM and 802.1AR certificates spread across standard fields,
+/// the otherNames of smart card certificates, RSASSA-PSS parameters, GOST, SM2 and post-quantum keys, and the Netscape
+/// extensions of old certificates.
+pub fn decode(cert: &X509Certificate) -> Vec<Decoded> {
+    let mut decoded = Vec::new();
+    for extension in cert.extensions() {
+        let oid = extension.oid.to_id_string();
+        if let Some((_, name, decoder)) = DECODERS.iter().find(|(known, _, _)| *known == oid) {
+            let fields = decoder(extension.value).unwrap_or_else(|| {
+                vec![Field::new(
+                    "Error",
+                    format!("not valid, {} bytes", extension.value.len()),
+                )]
+            });
+            decoded.push(Decoded { name, fields });
+        }
+    }
+    if let Some(fields) = matter::attestation(cert) {
+        decoded.push(Decoded {
+            name: "Matter device attestation",
+            fields,
+        });
+    }
+    if let Some(fields) = tpm::endorsement_key(cert) {
+        decoded.push(Decoded {
+            name: "TPM endorsement key",
+            fields,
+        });
+    }
+    if let Some(fields) = idevid::device_identity(cert) {
+        decoded.push(Decoded {
+            name: "IEEE 802.1AR device identity",
+            fields,
+        });
+    }
+    if let Some(fields) = other_name::names(cert) {
+        decoded.push(Decoded {
+            name: "Other names",
+            fields,
+        });
+    }
+    if let Some(fields) = pss::parameters(cert) {
+        decoded.push(Decoded {
+            name: "RSASSA-PSS parameters",
+            fields,
+        });
+    }
+    if let Some(fields) = gost::key(cert) {
+        decoded.push(Decoded {
+            name: "GOST R 34.10 key",
+            fields,
+        });
+    }
+    if let Some(fields) = sm2::key(cert) {
+        decoded.push(Decoded {
+            name: "SM2 key",
+            fields,
+        });
+    }
+    if let Some(fields) = pq::key(cert) {
+        decoded.push(Decoded {
+            name: "Post-quantum key",
+            fields,
+        });
+    }
+    if let Some(fields) = netscape::extensions(cert) {
+        decoded.push(Decoded {
+            name: "Netscape extensions",
+            fields,
+        });
+    }
+    decoded
+}
+
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}:", self.name)?;
+        write_fields(f, &self.fields, 1)
+    }
+}
+
+fn write_fields(f: &mut fmt::Formatter, fields: &[Field], depth: usize) -> fmt::Result {
+    for field in fields {
+        let indent = "    ".repeat(depth);
+        match field.value.is_empty() {
+            true => writeln!(f, "{}{}:", indent, field.name)?,
+            false => writeln!(f, "{}{}: {}", indent, field.name, field.value)?,
+        }
+        write_fields(f, &field.fields, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// The elements DER encoded one after another, such as the contents of a SEQUENCE.
+pub fn elements(mut bytes: &[u8]) -> Option<Vec<Any<'_>>> {
+    let mut elements = Vec::new();
+    while !bytes.is_empty() {
+        let (rest, element) = Any::from_der(bytes).ok()?;
+        elements.push(element);
+        bytes = rest;
+    }
+    Some(elements)
+}
+
+/// The number of a context specific tag, like the `[701]` of an Android authorization list.
+pub fn context_tag(element: &Any) -> Option<u32> {
+    match element.class() {
+        Class::ContextSpecific => Some(element.tag().0),
+        _ => None,
+    }
+}
+
+/// A non-negative INTEGER or ENUMERATED that fits in 64 bits.
+pub fn unsigned(element: &Any) -> Option<u64> {
+    if element.data.first().is_some_and(|byte| byte & 0x80 != 0) {
+        return None;
+    }
+    let bytes = element.data.strip_prefix(&[0]).unwrap_or(element.data);
+    if bytes.len() > 8 {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .fold(0, |value, byte| value << 8 | *byte as u64),
+    )
+}
+
+/// Bytes as text when they are printable, as identifiers usually are, otherwise as hex.
+pub fn text_or_hex(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) if !text.is_empty() && !text.chars().any(char::is_control) => String::from(text),
+        _ => crate::cms::hex(bytes),
+    }
+}
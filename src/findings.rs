@@ -0,0 +1,250 @@
+use crate::dn;
+use crate::report::Report;
+
+/// How to print what was found, from `--output`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    /// Every certificate decoded, for people.
+    #[default]
+    Text,
+    /// Only the problems found, as SARIF for code scanning dashboards.
+    Sarif,
+    /// Every check of every certificate as a JUnit test case, for CI servers.
+    Junit,
+    /// Only the problems found, as GitHub Actions annotations.
+    Github,
+    /// Every certificate as a component of a CycloneDX BOM, for SBOM tooling.
+    Cyclonedx,
+}
+
+impl OutputFormat {
+    pub fn parse(value: &str) -> Result<OutputFormat, Box<dyn std::error::Error>> {
+        match value.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "sarif" => Ok(OutputFormat::Sarif),
+            "junit" => Ok(OutputFormat::Junit),
+            "github" => Ok(OutputFormat::Github),
+            "cyclonedx" => Ok(OutputFormat::Cyclonedx),
+            _ => Err(format!(
+                "Error: --output expects text, sarif, junit, github or cyclonedx, not {}.",
+                value
+            )
+            .into()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+/// The kinds of problem found, by ID, with what each means.
+pub const RULES: &[(&str, &str)] = &[
+    ("decode", "The certificate could not be decoded."),
+    ("expired", "The certificate has expired."),
+    ("not-yet-valid", "The certificate is not valid yet."),
+    ("untrusted", "The certificate does not chain to a trust anchor."),
+    (
+        "warning",
+        "The certificate has a problem worth pointing out, such as a lint finding or an untrusted chain.",
+    ),
+];
+
+/// A problem found with a certificate, and where.
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    /// One of `RULES`.
+    pub rule: &'static str,
+    pub level: Level,
+    /// Where the certificate was found, as it is shown in the report.
+    pub location: String,
+    pub message: String,
+}
+
+impl Finding {
+    /// The file or server the certificate came from, without its place in a chain.
+    pub fn source(&self) -> &str {
+        match self.location.rsplit_once(" [") {
+            Some((source, index)) if index.ends_with(']') => source,
+            _ => &self.location,
+        }
+    }
+}
+
+/// Every problem in a report: what could not be decoded, certificates that
+/// aren't valid at `now` or trusted, and every other warning.
+pub fn collect(report: &Report, now: i64) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for failure in &report.failures {
+        let message = failure.error.to_string();
+        findings.push(Finding {
+            rule: "decode",
+            level: Level::Error,
+            location: failure.location.clone(),
+            message: String::from(message.strip_prefix("Error: ").unwrap_or(&message)),
+        });
+    }
+    for entry in &report.entries {
+        let cert = entry.certificate();
+        let validity = cert.validity();
+        let (rule, message) = if validity.not_after.timestamp() < now {
+            ("expired", format!("expired on {}", validity.not_after))
+        } else if validity.not_before.timestamp() > now {
+            (
+                "not-yet-valid",
+                format!("is not valid until {}", validity.not_before),
+            )
+        } else {
+            continue;
+        };
+        findings.push(Finding {
+            rule,
+            level: Level::Error,
+            location: entry.location.clone(),
+            message: format!("{} {}.", dn::to_string(cert.subject()), message),
+        });
+    }
+    let mut untrusted = Vec::new();
+    for entry in &report.entries {
+        let problem = entry.trust.first().and_then(|path| path.problem.as_ref());
+        if let Some(problem) = problem {
+            untrusted.push(format!(
+                "Warning: {} is not trusted, {}.",
+                entry.location, problem
+            ));
+            findings.push(Finding {
+                rule: "untrusted",
+                level: Level::Error,
+                location: entry.location.clone(),
+                message: format!(
+                    "{} is not trusted, {}.",
+                    dn::to_string(entry.certificate().subject()),
+                    problem
+                ),
+            });
+        }
+    }
+    // Those that say a certificate isn't trusted are findings of their own
+    for warning in report.warnings.iter().filter(|w| !untrusted.contains(w)) {
+        let message = warning.strip_prefix("Warning: ").unwrap_or(warning);
+        // Warnings are written for people, so where they are about is in their text
+        let location = report
+            .entries
+            .iter()
+            .map(|entry| entry.location.as_str())
+            .chain(
+                report
+                    .failures
+                    .iter()
+                    .map(|failure| failure.location.as_str()),
+            )
+            .filter(|location| message.contains(location))
+            .max_by_key(|location| location.len())
+            .unwrap_or_default();
+        findings.push(Finding {
+            rule: "warning",
+            level: Level::Warning,
+            location: String::from(location),
+            message: String::from(message),
+        });
+    }
+    findings
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{collect, Finding, Level};
+    use crate::options::Options;
+    use crate::report::Report;
+    use crate::target::Target;
+    use crate::test::FakeProcessor;
+    use crate::trust_path;
+
+    #[test]
+    fn should_find_failures_invalid_certificates_and_warnings() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        processor.add_file("bad.crt", include_str!("../resources/bad.crt"));
+        processor.add_file("critical.crt", include_str!("../resources/critical.crt"));
+        let targets = vec![
+            Target::Path(String::from("google.crt")),
+            Target::Path(String::from("bad.crt")),
+            Target::Path(String::from("critical.crt")),
+        ];
+        let report = Report::build(&processor, &targets, &Options::default());
+        let now = report.entries[1]
+            .certificate()
+            .validity()
+            .not_before
+            .timestamp();
+
+        let findings = collect(&report, now);
+
+        let summary: Vec<(&str, Level, &str)> = findings
+            .iter()
+            .map(|finding| (finding.rule, finding.level, finding.location.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("decode", Level::Error, "bad.crt"),
+                ("expired", Level::Error, "google.crt"),
+                ("warning", Level::Warning, "critical.crt"),
+            ]
+        );
+        assert_eq!(
+            findings[1].message,
+            "C=US, ST=California, L=Mountain View, O=Google LLC, CN=*.google.com expired on Sep  2 09:34:58 2020 +00:00."
+        );
+    }
+
+    #[test]
+    fn should_find_certificates_that_are_not_trusted() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file(
+            "self-signed.crt",
+            include_str!("../resources/self-signed.crt"),
+        );
+        processor.add_file("roots.pem", include_str!("../resources/example-root.crt"));
+        let targets = vec![Target::Path(String::from("self-signed.crt"))];
+        let options = Options {
+            anchors: Some(trust_path::load(&processor, &[String::from("roots.pem")]).unwrap()),
+            ..Options::default()
+        };
+        let report = Report::build(&processor, &targets, &options);
+        let now = report.entries[0]
+            .certificate()
+            .validity()
+            .not_before
+            .timestamp();
+
+        let findings = collect(&report, now);
+
+        assert_eq!(
+            findings,
+            vec![Finding {
+                rule: "untrusted",
+                level: Level::Error,
+                location: String::from("self-signed.crt"),
+                message: String::from(
+                    "CN=self-signed.example is not trusted, CN=self-signed.example is self-signed and not a trust anchor."
+                ),
+            }]
+        );
+    }
+
+    #[test]
+    fn should_name_the_source_of_a_certificate_in_a_chain() {
+        let finding = Finding {
+            rule: "expired",
+            level: Level::Error,
+            location: String::from("certs/chain.pem [2/3]"),
+            message: String::new(),
+        };
+
+        assert_eq!(finding.source(), "certs/chain.pem");
+    }
+}
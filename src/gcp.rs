@@ -0,0 +1,135 @@
+use serde_yaml::Value;
+use x509_parser::pem::Pem;
+
+use crate::http::{percent_encode, Request, Url};
+use crate::remote::ConnectOptions;
+use crate::store::NamedCertificate;
+use crate::FileProcessor;
+
+const API: &str = "https://certificatemanager.googleapis.com/v1";
+
+/// Uses GOOGLE_OAUTH_ACCESS_TOKEN if set, or else asks gcloud for a token.
+fn token(processor: &impl FileProcessor) -> Result<String, Box<dyn std::error::Error>> {
+    if let Some(token) = processor.var("GOOGLE_OAUTH_ACCESS_TOKEN") {
+        return Ok(token);
+    }
+    let args = [String::from("auth"), String::from("print-access-token")];
+    let output = processor.run("gcloud", &args, &[]).map_err(|error| {
+        format!(
+            "Error: could not get a Google Cloud access token, set GOOGLE_OAUTH_ACCESS_TOKEN or run gcloud auth login: {}",
+            error
+        )
+    })?;
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+/// Reads every certificate Certificate Manager holds for a project in a
+/// location, by the leaf of the PEM chain it lists them with. Certificates
+/// still being provisioned have no chain yet and are left out.
+pub fn read(
+    processor: &impl FileProcessor,
+    project: &str,
+    location: &str,
+    connect: &ConnectOptions,
+) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    let token = token(processor)?;
+    let list = format!(
+        "{}/projects/{}/locations/{}/certificates",
+        API, project, location
+    );
+
+    let mut certificates = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let url = match &page_token {
+            Some(page_token) => format!("{}?pageToken={}", list, percent_encode(page_token)),
+            None => list.clone(),
+        };
+        let url = Url::parse(&url)?;
+        let request =
+            Request::get(url.clone()).header("Authorization", &format!("Bearer {}", token));
+        let response = processor.http(&request, connect)?.success(&url)?;
+        let page: Value = serde_yaml::from_slice(&response.body)?;
+
+        for certificate in page["certificates"].as_sequence().into_iter().flatten() {
+            let leaf = certificate["pemCertificate"].as_str().and_then(|pem| {
+                Pem::iter_from_buffer(pem.as_bytes())
+                    .filter_map(Result::ok)
+                    .find(|block| block.label == "CERTIFICATE")
+            });
+            if let Some(leaf) = leaf {
+                let name = certificate["name"].as_str().unwrap_or_default();
+                let name = name.rsplit('/').next().unwrap_or(name);
+                certificates.push((String::from(name), leaf.contents));
+            }
+        }
+        page_token = page["nextPageToken"].as_str().map(String::from);
+        if page_token.is_none() {
+            break;
+        }
+    }
+    Ok(certificates)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::collections::HashMap;
+
+    use super::read;
+    use crate::http::Response;
+    use crate::remote::ConnectOptions;
+    use crate::test::{self_signed_der, FakeProcessor};
+
+    fn ok(body: String) -> Response {
+        Response {
+            status: 200,
+            headers: Vec::new(),
+            body: body.into_bytes(),
+        }
+    }
+
+    #[test]
+    fn should_read_the_certificates_in_certificate_manager() {
+        let mut processor = FakeProcessor {
+            vars: HashMap::from([(
+                String::from("GOOGLE_OAUTH_ACCESS_TOKEN"),
+                String::from("ya29"),
+            )]),
+            ..FakeProcessor::default()
+        };
+        let pem = crate::json::string(include_str!("../resources/self-signed.crt"));
+        let url = "https://certificatemanager.googleapis.com/v1/projects/shop/locations/global/certificates";
+        processor.responses.insert(
+            String::from(url),
+            ok(format!(
+                r#"{{"certificates":[{{"name":"projects/shop/locations/global/certificates/www","pemCertificate":{}}},{{"name":"projects/shop/locations/global/certificates/provisioning"}}],"nextPageToken":"p/2"}}"#,
+                pem
+            )),
+        );
+        processor.responses.insert(
+            format!("{}?pageToken=p%2F2", url),
+            ok(format!(
+                r#"{{"certificates":[{{"name":"projects/shop/locations/global/certificates/api","pemCertificate":{}}}]}}"#,
+                pem
+            )),
+        );
+
+        let certificates = read(&processor, "shop", "global", &ConnectOptions::default()).unwrap();
+        let names: Vec<&str> = certificates.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["www", "api"]);
+        assert_eq!(certificates[0].1, self_signed_der());
+    }
+
+    #[test]
+    fn should_ask_gcloud_for_a_token() {
+        let processor = FakeProcessor::default();
+        assert!(
+            read(&processor, "shop", "global", &ConnectOptions::default())
+                .err()
+                .unwrap()
+                .to_string()
+                .starts_with("Error: could not get a Google Cloud access token")
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::findings::{Finding, Level};
+use crate::FileProcessor;
+
+/// The findings as GitHub Actions workflow commands, which annotate the lines
+/// of a pull request that add a bad certificate. A certificate in a PEM file is
+/// annotated at its BEGIN line, anything else at its file or not at all.
+pub fn annotations(processor: &impl FileProcessor, findings: &[Finding]) -> String {
+    let mut commands = String::new();
+    for finding in findings {
+        let command = match finding.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+        let mut properties = Vec::new();
+        let source = finding.source();
+        if !source.is_empty() && processor.is_file(source) {
+            properties.push(format!("file={}", property(source)));
+            if let Ok(bytes) = processor.read(source) {
+                if let Some(line) = line(&bytes, index(&finding.location)) {
+                    properties.push(format!("line={}", line));
+                }
+            }
+        }
+        properties.push(format!("title={}", property(finding.rule)));
+        commands.push_str(&format!(
+            "::{} {}::{}\n",
+            command,
+            properties.join(","),
+            data(&finding.message)
+        ));
+    }
+    commands
+}
+
+/// Which certificate of a chain a location is, counting from zero.
+fn index(location: &str) -> usize {
+    location
+        .rsplit_once(" [")
+        .and_then(|(_, index)| index.split_once('/'))
+        .and_then(|(index, _)| index.parse::<usize>().ok())
+        .map_or(0, |index| index.saturating_sub(1))
+}
+
+/// The line of the BEGIN of the `index`th PEM certificate in a file.
+fn line(bytes: &[u8], index: usize) -> Option<usize> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains("-----BEGIN CERTIFICATE-----"))
+        .nth(index)
+        .map(|(number, _)| number + 1)
+}
+
+/// A message, with what would end the command escaped.
+fn data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// A property value, which also can't hold the separators between properties.
+fn property(text: &str) -> String {
+    data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::annotations;
+    use crate::findings::{Finding, Level};
+    use crate::test::FakeProcessor;
+
+    #[test]
+    fn should_annotate_the_line_each_certificate_begins_on() {
+        let mut processor = FakeProcessor::default();
+        let cert = include_str!("../resources/google.com.crt");
+        processor.add_file("certs/chain.pem", &format!("# web\n{}{}", cert, cert));
+        let findings = vec![
+            Finding {
+                rule: "expired",
+                level: Level::Error,
+                location: String::from("certs/chain.pem [2/2]"),
+                message: String::from("CN=*.google.com expired on Sep  2 09:34:58 2020 +00:00."),
+            },
+            Finding {
+                rule: "warning",
+                level: Level::Warning,
+                location: String::from("example.com:443"),
+                message: String::from("100% sure\nit is wrong"),
+            },
+        ];
+
+        let output = annotations(&processor, &findings);
+
+        let second = 2 + cert.lines().count();
+        assert_eq!(
+            output,
+            format!(
+                "::error file=certs/chain.pem,line={},title=expired::CN=*.google.com expired on Sep  2 09:34:58 2020 +00:00.\n\
+                 ::warning title=warning::100%25 sure%0Ait is wrong\n",
+                second
+            )
+        );
+    }
+}
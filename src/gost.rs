@@ -0,0 +1,133 @@
+use std::borrow::Cow;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::oid::Oid;
+
+use crate::extensions::{elements, Field};
+use crate::oid;
+
+/// The GOST algorithms, digests and parameter sets of RFC 4357, RFC 7091 and
+/// the TC 26 recommendations, which Russian government certificates use.
+const NAMES: &[(&str, &str)] = &[
+    ("1.2.643.2.2.3", "GOST R 34.11-94 with GOST R 34.10-2001"),
+    ("1.2.643.2.2.9", "GOST R 34.11-94"),
+    ("1.2.643.2.2.19", "GOST R 34.10-2001"),
+    ("1.2.643.2.2.30.1", "GOST R 34.11-94 CryptoPro parameters"),
+    ("1.2.643.2.2.35.1", "CryptoPro-A"),
+    ("1.2.643.2.2.35.2", "CryptoPro-B"),
+    ("1.2.643.2.2.35.3", "CryptoPro-C"),
+    ("1.2.643.2.2.36.0", "CryptoPro-XchA"),
+    ("1.2.643.2.2.36.1", "CryptoPro-XchB"),
+    ("1.2.643.7.1.1.1.1", "GOST R 34.10-2012 (256 bit)"),
+    ("1.2.643.7.1.1.1.2", "GOST R 34.10-2012 (512 bit)"),
+    ("1.2.643.7.1.1.2.2", "GOST R 34.11-2012 (256 bit)"),
+    ("1.2.643.7.1.1.2.3", "GOST R 34.11-2012 (512 bit)"),
+    (
+        "1.2.643.7.1.1.3.2",
+        "GOST R 34.11-2012 with GOST R 34.10-2012 (256 bit)",
+    ),
+    (
+        "1.2.643.7.1.1.3.3",
+        "GOST R 34.11-2012 with GOST R 34.10-2012 (512 bit)",
+    ),
+    ("1.2.643.7.1.2.1.1.1", "TC26 256-bit parameter set A"),
+    ("1.2.643.7.1.2.1.1.2", "TC26 256-bit parameter set B"),
+    ("1.2.643.7.1.2.1.1.3", "TC26 256-bit parameter set C"),
+    ("1.2.643.7.1.2.1.1.4", "TC26 256-bit parameter set D"),
+    ("1.2.643.7.1.2.1.2.1", "TC26 512-bit parameter set A"),
+    ("1.2.643.7.1.2.1.2.2", "TC26 512-bit parameter set B"),
+    ("1.2.643.7.1.2.1.2.3", "TC26 512-bit parameter set C"),
+];
+
+/// The name of a GOST algorithm or parameter set.
+pub fn name(oid: &Oid) -> Option<&'static str> {
+    let oid = oid.to_id_string();
+    NAMES
+        .iter()
+        .find(|(known, _)| *known == oid)
+        .map(|(_, name)| *name)
+}
+
+/// Decodes the GOST R 34.10 key of a certificate: the curve it is on, which
+/// is given as a parameter set, and the digest it is used with. Other
+/// certificates are left alone.
+pub fn key(cert: &X509Certificate) -> Option<Vec<Field>> {
+    let spki = cert.public_key();
+    let algorithm = name(&spki.algorithm.algorithm)?;
+    let mut fields = vec![Field::new("Algorithm", algorithm)];
+
+    // The parameters are a SEQUENCE of the key's parameter set, then the digest's
+    let parameters = spki
+        .algorithm
+        .parameters
+        .as_ref()
+        .and_then(|parameters| elements(parameters.data));
+    let labels = ["Parameter set", "Digest", "Encryption parameters"];
+    for (label, parameter) in labels.iter().zip(parameters.iter().flatten()) {
+        let oid = Oid::new(Cow::Borrowed(parameter.data));
+        let value = name(&oid)
+            .map(String::from)
+            .unwrap_or_else(|| oid.to_id_string());
+        fields.push(Field::new(label, value));
+    }
+
+    // The key is the two coordinates, little-endian, in an OCTET STRING
+    if let Some(key) = elements(&spki.subject_public_key.data)
+        .and_then(|key| key.into_iter().next())
+        .filter(|key| key.tag().0 == 4)
+    {
+        fields.push(Field::new(
+            "Key size",
+            format!("{} bits", key.data.len() * 8 / 2),
+        ));
+    }
+    fields.push(Field::new(
+        "Signature algorithm",
+        oid::name(&cert.signature_algorithm.algorithm),
+    ));
+    Some(fields)
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::key;
+    use crate::test::google_der;
+
+    #[test]
+    fn should_name_the_algorithm_parameter_set_and_digest() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/gost.crt")).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+
+        let fields = key(&cert).unwrap();
+        let fields: Vec<(&str, &str)> = fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.value.as_str()))
+            .collect();
+
+        assert_eq!(
+            fields,
+            vec![
+                ("Algorithm", "GOST R 34.10-2012 (256 bit)"),
+                ("Parameter set", "TC26 256-bit parameter set A"),
+                ("Digest", "GOST R 34.11-2012 (256 bit)"),
+                ("Key size", "256 bits"),
+                (
+                    "Signature algorithm",
+                    "GOST R 34.11-2012 with GOST R 34.10-2012 (256 bit)"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_leave_other_certificates_alone() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        assert!(key(&cert).is_none());
+    }
+}
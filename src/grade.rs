@@ -0,0 +1,284 @@
+use std::fmt;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::ParsedExtension;
+use x509_parser::oid_registry::OID_PKIX_ACCESS_DESCRIPTOR_OCSP;
+
+use crate::lifetime::{self, MaxValidity};
+use crate::oid;
+use crate::report::{Entry, Report};
+use crate::trust_store::{key_info, Curve, KeyKind, WEAK_SIGNATURES};
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// A letter grade, best first, so the worst of several is the greatest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Letter {
+    A,
+    B,
+    C,
+    F,
+}
+
+impl fmt::Display for Letter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// One criterion a certificate is graded on, with what earned the grade.
+#[derive(Debug, PartialEq)]
+pub struct Criterion {
+    pub name: &'static str,
+    pub letter: Letter,
+    pub detail: String,
+}
+
+/// Grades a certificate on its key, signature, validity, revocation information and
+/// Certificate Transparency, each as SSL Labs would, so the weakest shows.
+pub fn criteria(entry: &Entry, now: i64) -> Vec<Criterion> {
+    let cert = entry.certificate();
+    let ca = matches!(cert.basic_constraints(), Ok(Some(basic)) if basic.value.ca);
+    let self_signed = cert.subject() == cert.issuer();
+    vec![
+        key_strength(&cert),
+        signature_algorithm(&cert, self_signed),
+        validity_period(&cert, now),
+        revocation(&cert, self_signed),
+        transparency(entry, &cert, ca),
+    ]
+}
+
+fn key_strength(cert: &X509Certificate) -> Criterion {
+    let key = key_info(cert.public_key());
+    let letter = match (&key.kind, key.bits) {
+        (KeyKind::Rsa, Some(bits)) if bits >= 2048 => Letter::A,
+        (KeyKind::Rsa, _) => Letter::F,
+        (KeyKind::Ec(Some(Curve::P256 | Curve::P384 | Curve::P521)), _) => Letter::A,
+        (KeyKind::Ed25519 | KeyKind::Ed448, _) => Letter::A,
+        (KeyKind::Dsa, _) => Letter::F,
+        // Sound, but few clients can use them
+        _ => Letter::B,
+    };
+    Criterion {
+        name: "Key strength",
+        letter,
+        detail: key.to_string(),
+    }
+}
+
+fn signature_algorithm(cert: &X509Certificate, self_signed: bool) -> Criterion {
+    let algorithm = &cert.signature_algorithm.algorithm;
+    let name = oid::name(algorithm);
+    let (letter, detail) = if self_signed {
+        // Roots are trusted for being installed, not for their signature
+        (
+            Letter::A,
+            format!("{}, not relied on for a self-signed certificate", name),
+        )
+    } else if WEAK_SIGNATURES.contains(&algorithm.to_id_string().as_str()) {
+        (Letter::F, format!("{}, which can be forged", name))
+    } else {
+        (Letter::A, name)
+    };
+    Criterion {
+        name: "Signature algorithm",
+        letter,
+        detail,
+    }
+}
+
+fn validity_period(cert: &X509Certificate, now: i64) -> Criterion {
+    let validity = cert.validity();
+    let days = (validity.not_after.timestamp() - validity.not_before.timestamp() + 1) / DAY;
+    let (letter, detail) = if validity.not_after.timestamp() < now {
+        (Letter::F, format!("expired on {}", validity.not_after))
+    } else if validity.not_before.timestamp() > now {
+        (
+            Letter::F,
+            format!("not valid until {}", validity.not_before),
+        )
+    } else if let Some(problem) = lifetime::check(cert, &MaxValidity::Cabf, now) {
+        (Letter::B, problem)
+    } else if validity.not_after.timestamp() - now < 30 * DAY {
+        (
+            Letter::B,
+            format!("expires within 30 days, on {}", validity.not_after),
+        )
+    } else {
+        (
+            Letter::A,
+            format!("{} days, until {}", days, validity.not_after),
+        )
+    };
+    Criterion {
+        name: "Validity period",
+        letter,
+        detail,
+    }
+}
+
+fn revocation(cert: &X509Certificate, self_signed: bool) -> Criterion {
+    let mut ways = Vec::new();
+    for extension in cert.extensions() {
+        match extension.parsed_extension() {
+            ParsedExtension::AuthorityInfoAccess(access)
+                if access.accessdescs.iter().any(|description| {
+                    description.access_method == OID_PKIX_ACCESS_DESCRIPTOR_OCSP
+                }) =>
+            {
+                ways.push("OCSP")
+            }
+            ParsedExtension::CRLDistributionPoints(_) => ways.push("CRL"),
+            _ => {}
+        }
+    }
+    let (letter, detail) = match (ways.as_slice(), self_signed) {
+        ([], true) => (
+            Letter::A,
+            String::from("none, not needed for a self-signed certificate"),
+        ),
+        ([], false) => (Letter::C, String::from("none, so it cannot be revoked")),
+        (ways, _) => (Letter::A, ways.join(" and ")),
+    };
+    Criterion {
+        name: "Revocation information",
+        letter,
+        detail,
+    }
+}
+
+fn transparency(entry: &Entry, cert: &X509Certificate, ca: bool) -> Criterion {
+    let embedded = cert
+        .extensions()
+        .iter()
+        .find_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::SCT(scts) => Some(scts.len()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let scts = embedded.max(entry.scts.len());
+    // Browsers want SCTs from at least two logs
+    let (letter, detail) = match (scts, ca) {
+        (_, true) => (Letter::A, String::from("not needed for a CA")),
+        (0, false) => (
+            Letter::C,
+            String::from("no SCTs, so browsers that require CT reject it"),
+        ),
+        (1, false) => (Letter::B, String::from("1 SCT, browsers want at least 2")),
+        (scts, false) => (Letter::A, format!("{} SCTs", scts)),
+    };
+    Criterion {
+        name: "Certificate Transparency",
+        letter,
+        detail,
+    }
+}
+
+/// The grade of each certificate in a report with its breakdown, printed by the
+/// `grade` command instead of the decoded certificates.
+pub struct Grades<'a> {
+    report: &'a Report,
+    now: i64,
+}
+
+impl<'a> Grades<'a> {
+    pub fn new(report: &'a Report, now: i64) -> Grades<'a> {
+        Grades { report, now }
+    }
+}
+
+impl fmt::Display for Grades<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let headers = self.report.entries.len() + self.report.failures.len() > 1;
+        let mut overall = None;
+        for entry in &self.report.entries {
+            let criteria = criteria(entry, self.now);
+            let letter = criteria
+                .iter()
+                .map(|criterion| criterion.letter)
+                .max()
+                .unwrap_or(Letter::A);
+            overall = overall.max(Some(letter));
+            if headers {
+                writeln!(f, "==> {} <==", entry.location)?;
+            }
+            writeln!(f, "Grade: {}", letter)?;
+            for criterion in &criteria {
+                writeln!(
+                    f,
+                    "    {}: {}, {}",
+                    criterion.name, criterion.letter, criterion.detail
+                )?;
+            }
+        }
+        for failure in &self.report.failures {
+            writeln!(f, "==> {} <==", failure.location)?;
+            writeln!(f, "{}", failure.error)?;
+        }
+        // A chain is only as strong as its weakest certificate
+        if let (Some(overall), true) = (overall, self.report.entries.len() > 1) {
+            writeln!(f, "Overall grade: {}", overall)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{criteria, Grades, Letter};
+    use crate::options::Options;
+    use crate::report::Report;
+    use crate::target::Target;
+    use crate::test::FakeProcessor;
+
+    // 2020-07-01, while the google.com certificate was valid
+    const NOW: i64 = 1_593_561_600;
+
+    #[test]
+    fn should_grade_each_criterion() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        let targets = vec![Target::Path(String::from("google.crt"))];
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        let grades: Vec<(&str, Letter)> = criteria(&report.entries[0], NOW)
+            .iter()
+            .map(|criterion| (criterion.name, criterion.letter))
+            .collect();
+
+        assert_eq!(
+            grades,
+            vec![
+                ("Key strength", Letter::A),
+                ("Signature algorithm", Letter::A),
+                ("Validity period", Letter::A),
+                ("Revocation information", Letter::A),
+                ("Certificate Transparency", Letter::A),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_grade_a_chain_by_its_weakest_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        processor.add_file("sha1.crt", include_str!("../resources/sha1-root.crt"));
+        let targets = vec![
+            Target::Path(String::from("google.crt")),
+            Target::Path(String::from("sha1.crt")),
+        ];
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        let output = Grades::new(&report, NOW + 365 * 24 * 60 * 60).to_string();
+
+        assert!(output.starts_with(
+            "==> google.crt <==\n\
+             Grade: F\n    \
+             Key strength: A, EC P-256\n"
+        ));
+        assert!(output.contains("    Validity period: F, expired on Sep  2 09:34:58 2020 +00:00\n"));
+        assert!(output.ends_with("Overall grade: F\n"));
+    }
+}
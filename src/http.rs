@@ -0,0 +1,460 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::ring;
+use rustls::pki_types::{CertificateDer, ServerName};
+use rustls::sign::CertifiedKey;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use x509_parser::pem::Pem;
+
+use crate::proxy::{self, Proxy, ProxyKind};
+use crate::remote::{self, AcceptAnyCert, ConnectOptions, Server};
+use crate::trust_store;
+
+/// Which servers an HTTPS request will trust.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trust {
+    /// The roots in the system CA bundle.
+    System,
+    /// Only these DER encoded roots, such as a cluster's own CA.
+    Roots(Vec<Vec<u8>>),
+    /// Any server at all, for when the user explicitly asked to skip verification.
+    Insecure,
+}
+
+/// A URL split into the parts needed to make a request to it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Url {
+    pub https: bool,
+    pub host: String,
+    pub port: u16,
+    /// The path and query, always starting with `/`.
+    pub path: String,
+}
+
+impl Url {
+    pub fn parse(url: &str) -> Result<Url, Box<dyn std::error::Error>> {
+        let invalid = || format!("Error: {} is not a valid http or https URL.", url);
+        let (https, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (true, rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (false, rest)
+        } else {
+            return Err(invalid().into());
+        };
+
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(i) if rest[i..].starts_with('/') => (&rest[..i], String::from(&rest[i..])),
+            Some(i) => (&rest[..i], format!("/{}", &rest[i..])),
+            None => (rest, String::from("/")),
+        };
+        let default_port = if https { 443 } else { 80 };
+        let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+            let (host, port) = bracketed.split_once(']').ok_or_else(invalid)?;
+            (host, port.strip_prefix(':'))
+        } else {
+            match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            }
+        };
+        let port = match port {
+            Some(port) => port.parse().map_err(|_| invalid())?,
+            None => default_port,
+        };
+        if host.is_empty() {
+            return Err(invalid().into());
+        }
+
+        Ok(Url {
+            https,
+            host: String::from(host),
+            port,
+            path,
+        })
+    }
+
+    /// The host and port as written in a URL or Host header, leaving out a default port.
+    pub fn authority(&self) -> String {
+        let host = match self.host.contains(':') {
+            true => format!("[{}]", self.host),
+            false => self.host.clone(),
+        };
+        match (self.https, self.port) {
+            (true, 443) | (false, 80) => host,
+            (_, port) => format!("{}:{}", host, port),
+        }
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scheme = if self.https { "https" } else { "http" };
+        write!(f, "{}://{}{}", scheme, self.authority(), self.path)
+    }
+}
+
+/// Percent-encodes a query parameter or path segment.
+pub fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Decodes the `%XX` escapes of a URL part, such as the user and password of a proxy.
+pub fn percent_decode(value: &str) -> Option<String> {
+    let mut decoded = Vec::new();
+    let mut bytes = value.bytes();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'%' => {
+                let high = char::from(bytes.next()?).to_digit(16)?;
+                let low = char::from(bytes.next()?).to_digit(16)?;
+                decoded.push((high * 16 + low) as u8);
+            }
+            byte => decoded.push(byte),
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// An HTTP request, made with `FileProcessor::http`.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub url: Url,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub trust: Trust,
+    /// A client certificate to present if the server asks for one.
+    pub identity: Option<Arc<CertifiedKey>>,
+}
+
+impl Request {
+    pub fn get(url: Url) -> Request {
+        Request {
+            method: String::from("GET"),
+            url,
+            headers: Vec::new(),
+            body: Vec::new(),
+            trust: Trust::System,
+            identity: None,
+        }
+    }
+
+    pub fn post(url: Url, body: Vec<u8>) -> Request {
+        Request {
+            method: String::from("POST"),
+            body,
+            ..Request::get(url)
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Request {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// The value of a header, matching its name without regard to case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Turns anything but a 2xx response into an error naming the URL.
+    pub fn success(self, url: &Url) -> Result<Response, Box<dyn std::error::Error>> {
+        if (200..300).contains(&self.status) {
+            return Ok(self);
+        }
+        Err(format!("Error: {} answered with HTTP status {}", url, self.status).into())
+    }
+}
+
+/// Makes an HTTP/1.1 request, through the proxy if one applies.
+/// Connections are never reused, so every request asks the server to close.
+pub fn send(
+    request: &Request,
+    options: &ConnectOptions,
+) -> Result<Response, Box<dyn std::error::Error>> {
+    let url = &request.url;
+    let server = Server {
+        host: url.host.clone(),
+        port: url.port,
+        address: None,
+    };
+    let proxy = match url.https {
+        true => options.proxy.as_ref(),
+        false => options.http_proxy.as_ref(),
+    };
+    let proxy = proxy::for_host(proxy, &options.no_proxy, &url.host);
+    // Plain HTTP goes to an HTTP proxy whole, as many refuse CONNECT to any port but 443
+    let forward = proxy.filter(|proxy| !url.https && proxy.kind == ProxyKind::Http);
+    let socket = match forward {
+        Some(proxy) => remote::connect_to_proxy(proxy, options)?,
+        None => remote::connect_through(&server, proxy, options)?,
+    };
+
+    let mut message = head(request, forward).into_bytes();
+    message.extend_from_slice(&request.body);
+
+    let raw = if url.https {
+        let config = tls_config(request)?;
+        let name = ServerName::try_from(url.host.clone())?;
+        let connection = ClientConnection::new(Arc::new(config), name)?;
+        exchange(StreamOwned::new(connection, socket), &message)
+    } else {
+        exchange(socket, &message)
+    };
+    let raw = raw.map_err(|error| format!("Error: could not request {}: {}", url, error))?;
+    parse_response(url, &raw)
+}
+
+/// The request line and headers, with the absolute URL that a proxy forwarding
+/// the request needs.
+fn head(request: &Request, forward: Option<&Proxy>) -> String {
+    let url = &request.url;
+    let target = match forward {
+        Some(_) => url.to_string(),
+        None => url.path.clone(),
+    };
+    let mut head = format!("{} {} HTTP/1.1\r\n", request.method, target);
+    head.push_str(&format!(
+        "Host: {}\r\nConnection: close\r\n",
+        url.authority()
+    ));
+    head.push_str("User-Agent: cert-decoder\r\n");
+    if let Some(authorization) = forward.and_then(Proxy::authorization) {
+        head.push_str(&format!("Proxy-Authorization: {}\r\n", authorization));
+    }
+    for (name, value) in &request.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if !request.body.is_empty() {
+        head.push_str(&format!("Content-Length: {}\r\n", request.body.len()));
+    }
+    head.push_str("\r\n");
+    head
+}
+
+fn exchange<S: Read + Write>(mut stream: S, message: &[u8]) -> io::Result<Vec<u8>> {
+    stream.write_all(message)?;
+    stream.flush()?;
+    let mut raw = Vec::new();
+    match stream.read_to_end(&mut raw) {
+        Ok(_) => Ok(raw),
+        // Plenty of servers close without a TLS close_notify once they have answered
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof && !raw.is_empty() => Ok(raw),
+        Err(error) => Err(error),
+    }
+}
+
+fn tls_config(request: &Request) -> Result<ClientConfig, Box<dyn std::error::Error>> {
+    let mut config = client_config(&request.trust, request.identity.clone())?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// A TLS client config that verifies servers against `trust`, for other
+/// protocols over TLS as well as HTTPS.
+pub fn client_config(
+    trust: &Trust,
+    identity: Option<Arc<CertifiedKey>>,
+) -> Result<ClientConfig, Box<dyn std::error::Error>> {
+    let provider = Arc::new(ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let builder = match trust {
+        Trust::Insecure => builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCert(ring::default_provider()))),
+        trust => {
+            let mut roots = RootCertStore::empty();
+            let ders = match trust {
+                Trust::Roots(ders) => ders.clone(),
+                _ => system_roots()?,
+            };
+            for der in ders {
+                // One unusable root shouldn't stop the rest being trusted
+                let _ = roots.add(CertificateDer::from(der));
+            }
+            let verifier =
+                WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider).build()?;
+            builder.with_webpki_verifier(verifier)
+        }
+    };
+
+    Ok(match identity {
+        Some(identity) => builder.with_client_cert_resolver(Arc::new(FixedIdentity(identity))),
+        None => builder.with_no_client_auth(),
+    })
+}
+
+/// Always answers a CertificateRequest with the same identity.
+#[derive(Debug)]
+struct FixedIdentity(Arc<CertifiedKey>);
+
+impl rustls::client::ResolvesClientCert for FixedIdentity {
+    fn resolve(&self, _: &[&[u8]], _: &[rustls::SignatureScheme]) -> Option<Arc<CertifiedKey>> {
+        Some(self.0.clone())
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+/// The roots of the first system CA bundle found.
+fn system_roots() -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+    for bundle in trust_store::BUNDLES {
+        if let Ok(bytes) = std::fs::read(bundle) {
+            return Ok(Pem::iter_from_buffer(&bytes)
+                .filter_map(Result::ok)
+                .filter(|pem| pem.label == "CERTIFICATE")
+                .map(|pem| pem.contents)
+                .collect());
+        }
+    }
+    Err(
+        String::from("Error: could not find the system CA bundle to verify HTTPS servers with.")
+            .into(),
+    )
+}
+
+/// Splits a raw response into its status, headers and (de-chunked) body.
+fn parse_response(url: &Url, raw: &[u8]) -> Result<Response, Box<dyn std::error::Error>> {
+    let malformed = || format!("Error: {} sent a malformed HTTP response", url);
+    let split = raw
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(malformed)?;
+    let head = String::from_utf8_lossy(&raw[..split]);
+    let mut body = raw[split + 4..].to_vec();
+
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(malformed)?;
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (String::from(name.trim()), String::from(value.trim())))
+        .collect();
+
+    let mut response = Response {
+        status,
+        headers,
+        body: Vec::new(),
+    };
+    if response
+        .header("Transfer-Encoding")
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+    {
+        body = dechunk(&body).ok_or_else(malformed)?;
+    } else if let Some(length) = response
+        .header("Content-Length")
+        .and_then(|l| l.parse().ok())
+    {
+        body.truncate(length);
+    }
+    response.body = body;
+    Ok(response)
+}
+
+fn dechunk(mut chunked: &[u8]) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = chunked.windows(2).position(|window| window == b"\r\n")?;
+        let size = std::str::from_utf8(&chunked[..line_end]).ok()?;
+        // Chunk extensions follow a semicolon and can be ignored
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        chunked = &chunked[line_end + 2..];
+        if size == 0 {
+            return Some(body);
+        }
+        body.extend_from_slice(chunked.get(..size)?);
+        chunked = chunked.get(size + 2..)?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{head, parse_response, Request, Url};
+    use crate::proxy::Proxy;
+
+    #[test]
+    fn should_parse_urls() {
+        let url = Url::parse("https://example.com:6443/api/v1/secrets?limit=5").unwrap();
+        let bare = Url::parse("http://[::1]").unwrap();
+
+        assert_eq!(url.host, "example.com");
+        assert_eq!(url.port, 6443);
+        assert_eq!(url.path, "/api/v1/secrets?limit=5");
+        assert_eq!(bare.to_string(), "http://[::1]/");
+        assert_eq!(url.authority(), "example.com:6443");
+        assert!(Url::parse("ftp://example.com").is_err());
+        assert!(Url::parse("https://example.com:https/").is_err());
+    }
+
+    #[test]
+    fn should_send_plain_http_to_a_proxy_as_an_absolute_url() {
+        let request = Request::get(Url::parse("http://crl.example.com/ca.crl").unwrap());
+        let proxy = Proxy::parse("http://u:p@proxy:3128").unwrap();
+
+        let direct = head(&request, None);
+        let forwarded = head(&request, Some(&proxy));
+
+        assert!(direct.starts_with("GET /ca.crl HTTP/1.1\r\nHost: crl.example.com\r\n"));
+        assert!(!direct.contains("Proxy-Authorization"));
+        assert!(forwarded.starts_with("GET http://crl.example.com/ca.crl HTTP/1.1\r\n"));
+        assert!(forwarded.contains("Proxy-Authorization: Basic dTpw\r\n"));
+    }
+
+    #[test]
+    fn should_parse_chunked_responses() {
+        let url = Url::parse("http://example.com").unwrap();
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5;x=y\r\nhello\r\n6\r\n world\r\n0\r\n\r\n";
+
+        let response = parse_response(&url, raw).unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"hello world");
+    }
+
+    #[test]
+    fn should_truncate_bodies_to_their_content_length() {
+        let url = Url::parse("http://example.com").unwrap();
+        let raw = b"HTTP/1.0 404 Not Found\r\ncontent-length: 2\r\n\r\nno and more";
+
+        let response = parse_response(&url, raw).unwrap();
+
+        assert_eq!(response.status, 404);
+        assert_eq!(response.header("Content-Length"), Some("2"));
+        assert_eq!(response.body, b"no");
+        assert_eq!(
+            format!("{}", response.success(&url).err().unwrap()),
+            "Error: http://example.com/ answered with HTTP status 404"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::borrow::Cow;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::oid::Oid;
+use x509_parser::extensions::GeneralName;
+
+use crate::extensions::{elements, text_or_hex, Field};
+use crate::oid;
+
+/// The otherName of RFC 4108 that names the hardware module a device identity was given to.
+const HARDWARE_MODULE_NAME: &str = "1.3.6.1.5.5.7.8.4";
+
+/// The extension of RFC 8995 that points a BRSKI pledge at its manufacturer's MASA.
+const MASA_URL: &str = "1.3.6.1.5.5.7.1.32";
+
+const SERIAL_NUMBER: &str = "2.5.4.5";
+
+/// 99991231235959Z, which RFC 5280 sets aside for certificates with no
+/// well-defined expiration, as 802.1AR asks of IDevIDs.
+const NO_EXPIRATION: i64 = 253_402_300_799;
+
+/// Decodes what IEEE 802.1AR device identities carry for onboarding: the
+/// hardware module and serial number of the device, and where its MASA is.
+/// Other certificates are left alone.
+pub fn device_identity(cert: &X509Certificate) -> Option<Vec<Field>> {
+    let mut fields = Vec::new();
+    let names = cert.subject_alternative_name().ok().flatten();
+    for name in names.iter().flat_map(|names| &names.value.general_names) {
+        if let GeneralName::OtherName(oid, value) = name {
+            if oid.to_id_string() == HARDWARE_MODULE_NAME {
+                fields.extend(hardware_module_name(value)?);
+            }
+        }
+    }
+
+    let serial = cert
+        .subject()
+        .iter_attributes()
+        .find(|attribute| attribute.attr_type().to_id_string() == SERIAL_NUMBER)
+        .and_then(|attribute| attribute.as_str().ok());
+    let never_expires = cert.validity().not_after.timestamp() == NO_EXPIRATION;
+    let masa = cert
+        .extensions()
+        .iter()
+        .find(|extension| extension.oid.to_id_string() == MASA_URL)
+        .and_then(|extension| elements(extension.value)?.into_iter().next())
+        .map(|url| String::from_utf8_lossy(url.data).into_owned());
+
+    // A serial number alone is common, it takes the expiry or a SAN to mark a device
+    if fields.is_empty() && masa.is_none() && !(serial.is_some() && never_expires) {
+        return None;
+    }
+    if let Some(serial) = serial {
+        fields.push(Field::new("Device serial number", serial));
+    }
+    if let Some(masa) = masa {
+        fields.push(Field::new("MASA URL", masa));
+    }
+    if never_expires {
+        fields.push(Field::new("Expires", "never (99991231235959Z)"));
+    }
+    Some(fields)
+}
+
+/// The hwType and hwSerialNum of a HardwareModuleName, which sits in the `[0]` of the otherName.
+fn hardware_module_name(value: &[u8]) -> Option<Vec<Field>> {
+    let explicit = elements(value)?;
+    let sequence = elements(explicit.first()?.data)?;
+    let module = elements(sequence.first()?.data)?;
+    let hardware_type = Oid::new(Cow::Borrowed(module.first()?.data));
+    Some(vec![
+        Field::new("Hardware type", oid::name(&hardware_type)),
+        Field::new("Hardware serial number", text_or_hex(module.get(1)?.data)),
+    ])
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{device_identity, hardware_module_name};
+    use crate::test::google_der;
+
+    const CERT: &[u8] = include_bytes!("../resources/idevid.crt");
+
+    #[test]
+    fn should_decode_the_hardware_module_and_serial_number() {
+        let (_, pem) = parse_x509_pem(CERT).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+
+        let fields = device_identity(&cert).unwrap();
+        let fields: Vec<(&str, &str)> = fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.value.as_str()))
+            .collect();
+
+        assert_eq!(
+            fields,
+            vec![
+                ("Hardware type", "1.3.6.1.4.1.32473.3.1"),
+                ("Hardware serial number", "SN-00042"),
+                ("Device serial number", "SN-00042"),
+                ("MASA URL", "https://masa.example.com/.well-known/brski"),
+                ("Expires", "never (99991231235959Z)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_show_a_binary_hardware_serial_number_in_hex() {
+        let value = [
+            0xa0, 0x0c, 0x30, 0x0a, 0x06, 0x03, 0x2a, 0x03, 0x04, 0x04, 0x03, 0x00, 0x01, 0x02,
+        ];
+
+        let fields = hardware_module_name(&value).unwrap();
+
+        assert_eq!(fields[0].value, "1.2.3.4");
+        assert_eq!(fields[1].value, "00:01:02");
+    }
+
+    #[test]
+    fn should_leave_other_certificates_alone() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        assert!(device_identity(&cert).is_none());
+    }
+}
@@ -0,0 +1,303 @@
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+
+// The parameters of Punycode, from RFC 3492 section 5
+const BASE: u32 = 36;
+const TMIN: u32 = 1;
+const TMAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+/// Letters of other scripts that can't be told apart from Latin ones, by the
+/// Latin letter each passes for. A label of nothing but these spoofs a Latin one.
+const CONFUSABLES: &[(char, char)] = &[
+    // Cyrillic
+    ('а', 'a'),
+    ('с', 'c'),
+    ('ԁ', 'd'),
+    ('е', 'e'),
+    ('һ', 'h'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('ӏ', 'l'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('ԛ', 'q'),
+    ('ѕ', 's'),
+    ('у', 'y'),
+    ('ԝ', 'w'),
+    ('х', 'x'),
+    // Greek
+    ('α', 'a'),
+    ('ι', 'i'),
+    ('κ', 'k'),
+    ('ν', 'v'),
+    ('ο', 'o'),
+    ('ρ', 'p'),
+    ('υ', 'u'),
+];
+
+/// The scripts that may share a label, as UTS #39 allows for Chinese, Japanese
+/// and Korean names. Any other mix of scripts is a sign of spoofing.
+const MIXES: &[&[&str]] = &[
+    &["Latin", "Han", "Hiragana", "Katakana"],
+    &["Latin", "Han", "Hangul"],
+    &["Latin", "Han", "Bopomofo"],
+];
+
+/// An internationalized name in a certificate, with its Unicode form.
+pub struct Name {
+    pub ascii: String,
+    pub unicode: String,
+    /// Why the name looks like it is out to spoof another, if it does.
+    pub homograph: Option<String>,
+}
+
+/// The names in a certificate's subject alternative names and common names that
+/// have Punycode labels.
+pub fn names(cert: &X509Certificate) -> Vec<Name> {
+    let mut found: Vec<String> = Vec::new();
+    for extension in cert.extensions() {
+        if let ParsedExtension::SubjectAlternativeName(san) = extension.parsed_extension() {
+            for name in &san.general_names {
+                match name {
+                    GeneralName::DNSName(name) => found.push(String::from(*name)),
+                    GeneralName::RFC822Name(mailbox) => {
+                        if let Some((_, domain)) = mailbox.rsplit_once('@') {
+                            found.push(String::from(domain));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    found.extend(
+        cert.subject()
+            .iter_common_name()
+            .filter_map(|name| name.as_str().ok())
+            .map(String::from),
+    );
+
+    let mut names: Vec<Name> = Vec::new();
+    for ascii in found {
+        if names
+            .iter()
+            .any(|name| name.ascii.eq_ignore_ascii_case(&ascii))
+        {
+            continue;
+        }
+        let unicode = match to_unicode(&ascii) {
+            Some(unicode) => unicode,
+            None => continue,
+        };
+        names.push(Name {
+            homograph: unicode.split('.').find_map(homograph),
+            ascii,
+            unicode,
+        });
+    }
+    names
+}
+
+/// The Unicode form of a domain name, if it has any Punycode labels.
+pub fn to_unicode(name: &str) -> Option<String> {
+    let mut punycode = false;
+    let mut labels = Vec::new();
+    for label in name.split('.') {
+        match label.get(..4) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("xn--") => {
+                labels.push(decode(&label[4..])?);
+                punycode = true;
+            }
+            _ => labels.push(String::from(label)),
+        }
+    }
+    match punycode {
+        true => Some(labels.join(".")),
+        false => None,
+    }
+}
+
+/// Decodes a Punycode label, without its `xn--` prefix, following RFC 3492 section 6.2.
+fn decode(input: &str) -> Option<String> {
+    let (basic, extended) = match input.rfind('-') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => ("", input),
+    };
+    if !basic.is_ascii() {
+        return None;
+    }
+    let mut output: Vec<char> = basic.chars().collect();
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut digits = extended.bytes().peekable();
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let digit = match digits.next()? {
+                byte @ b'a'..=b'z' => byte - b'a',
+                byte @ b'A'..=b'Z' => byte - b'A',
+                byte @ b'0'..=b'9' => byte - b'0' + 26,
+                _ => return None,
+            } as u32;
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = if k <= bias {
+                TMIN
+            } else if k >= bias + TMAX {
+                TMAX
+            } else {
+                k - bias
+            };
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+        let length = output.len() as u32 + 1;
+        bias = adapt(i - old_i, length, old_i == 0);
+        n = n.checked_add(i / length)?;
+        i %= length;
+        output.insert(i as usize, char::from_u32(n)?);
+        i += 1;
+    }
+    Some(output.into_iter().collect())
+}
+
+fn adapt(delta: u32, points: u32, first: bool) -> u32 {
+    let mut delta = if first { delta / DAMP } else { delta / 2 };
+    delta += delta / points;
+    let mut k = 0;
+    while delta > ((BASE - TMIN) * TMAX) / 2 {
+        delta /= BASE - TMIN;
+        k += BASE;
+    }
+    k + (BASE - TMIN + 1) * delta / (delta + SKEW)
+}
+
+/// Why a label looks like it is out to spoof another: it mixes scripts that
+/// don't go together, or it is all letters that pass for Latin ones.
+fn homograph(label: &str) -> Option<String> {
+    let mut scripts: Vec<&str> = Vec::new();
+    for script in label.chars().filter_map(script) {
+        if !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+    let allowed = MIXES
+        .iter()
+        .any(|mix| scripts.iter().all(|script| mix.contains(script)));
+    if scripts.len() > 1 && !allowed {
+        let (last, rest) = scripts.split_last()?;
+        return Some(format!(
+            "its label {} mixes {} and {} letters",
+            label,
+            rest.join(", "),
+            last
+        ));
+    }
+    let spoofed: Option<String> = label
+        .chars()
+        .map(|c| match c {
+            '-' | '0'..='9' => Some(c),
+            c => CONFUSABLES
+                .iter()
+                .find(|(confusable, _)| *confusable == c)
+                .map(|(_, latin)| *latin),
+        })
+        .collect();
+    match (spoofed, scripts.as_slice()) {
+        (Some(spoofed), [script]) if *script != "Latin" => Some(format!(
+            "its label {} is {} letters that pass for the Latin {}",
+            label, script, spoofed
+        )),
+        _ => None,
+    }
+}
+
+/// The script of a letter, or `None` for digits, hyphens and the like.
+fn script(c: char) -> Option<&'static str> {
+    let script = match c as u32 {
+        0x41..=0x5a | 0x61..=0x7a | 0xc0..=0x24f | 0x1e00..=0x1eff => "Latin",
+        0x370..=0x3ff | 0x1f00..=0x1fff => "Greek",
+        0x400..=0x52f => "Cyrillic",
+        0x530..=0x58f => "Armenian",
+        0x590..=0x5ff => "Hebrew",
+        0x600..=0x6ff | 0x750..=0x77f => "Arabic",
+        0xe00..=0xe7f => "Thai",
+        0x3040..=0x309f => "Hiragana",
+        0x30a0..=0x30ff => "Katakana",
+        0x3100..=0x312f => "Bopomofo",
+        0x1100..=0x11ff | 0xac00..=0xd7af => "Hangul",
+        0x4e00..=0x9fff | 0x3400..=0x4dbf => "Han",
+        _ if c.is_alphabetic() => "Other",
+        _ => return None,
+    };
+    Some(script)
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{names, to_unicode};
+
+    #[test]
+    fn should_decode_punycode_labels() {
+        assert_eq!(
+            to_unicode("www.xn--mller-kva.example").as_deref(),
+            Some("www.müller.example")
+        );
+        assert_eq!(to_unicode("XN--r8jz45g.jp").as_deref(), Some("例え.jp"));
+        assert_eq!(to_unicode("www.example.com"), None);
+        assert_eq!(to_unicode("xn--!!"), None);
+    }
+
+    #[test]
+    fn should_point_out_names_that_spoof_others() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/idn.crt")).unwrap();
+        let cert = pem.parse_x509().unwrap();
+
+        let names: Vec<(String, String, Option<String>)> = names(&cert)
+            .into_iter()
+            .map(|name| (name.ascii, name.unicode, name.homograph))
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                (
+                    String::from("xn--mller-kva.example"),
+                    String::from("müller.example"),
+                    None
+                ),
+                (
+                    String::from("xn--80ak6aa92e.com"),
+                    String::from("аррӏе.com"),
+                    Some(String::from(
+                        "its label аррӏе is Cyrillic letters that pass for the Latin apple"
+                    ))
+                ),
+                (
+                    String::from("xn--pple-43d.com"),
+                    String::from("аpple.com"),
+                    Some(String::from(
+                        "its label аpple mixes Cyrillic and Latin letters"
+                    ))
+                ),
+                (
+                    String::from("www.xn--r8jz45g.jp"),
+                    String::from("www.例え.jp"),
+                    None
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use std::fmt;
+
+use serde_yaml::Value;
+
+use crate::dn;
+use crate::json;
+use crate::report::Report;
+use crate::trust_path::fingerprint;
+use crate::trust_store::list;
+
+/// What the `inventory` command does with its snapshot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InventoryCommand {
+    /// Record every certificate found in the snapshot, from `inventory save`.
+    Save,
+    /// Compare every certificate found with the snapshot, from `inventory diff`.
+    Diff,
+}
+
+/// Set by the `inventory save` and `inventory diff` commands, with the snapshot
+/// file as the first target.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InventoryOptions {
+    pub command: InventoryCommand,
+    pub snapshot: String,
+}
+
+/// A certificate as a snapshot records it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub location: String,
+    pub subject: String,
+    pub issuer: String,
+    pub serial: String,
+    pub not_after: String,
+    /// The SHA-256 fingerprint of the certificate, as colon separated hex.
+    pub sha256: String,
+    /// The SHA-256 of its SubjectPublicKeyInfo, to tell a renewal from a new key.
+    pub key_sha256: String,
+}
+
+impl Record {
+    /// What a certificate is known by from one snapshot to the next: where it
+    /// was found, without its place in a chain that may since have changed length,
+    /// and who it was issued to.
+    fn key(&self) -> (&str, &str) {
+        let place = match self.location.rsplit_once(" [") {
+            Some((place, index)) if index.ends_with(']') => place,
+            _ => &self.location,
+        };
+        (place, &self.subject)
+    }
+
+    fn line(&self) -> String {
+        format!(
+            "{}: {}, expires {}",
+            self.location, self.subject, self.not_after
+        )
+    }
+}
+
+/// The certificates of a report as snapshot records.
+pub fn records(report: &Report) -> Vec<Record> {
+    report
+        .entries
+        .iter()
+        .map(|entry| {
+            let cert = entry.certificate();
+            Record {
+                location: entry.location.clone(),
+                subject: dn::to_string(cert.subject()),
+                issuer: dn::to_string(cert.issuer()),
+                serial: cert.raw_serial_as_string(),
+                not_after: cert.validity().not_after.to_string(),
+                sha256: fingerprint(&entry.der),
+                key_sha256: fingerprint(cert.public_key().raw),
+            }
+        })
+        .collect()
+}
+
+/// A snapshot as JSON, one certificate per object.
+pub fn save(records: &[Record], taken: i64) -> String {
+    let objects: Vec<String> = records
+        .iter()
+        .map(|record| {
+            let fields = [
+                ("location", &record.location),
+                ("subject", &record.subject),
+                ("issuer", &record.issuer),
+                ("serial", &record.serial),
+                ("not_after", &record.not_after),
+                ("sha256", &record.sha256),
+                ("key_sha256", &record.key_sha256),
+            ];
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(name, value)| format!("      \"{}\": {}", name, json::string(value)))
+                .collect();
+            format!("    {{\n{}\n    }}", fields.join(",\n"))
+        })
+        .collect();
+    format!(
+        "{{\n  \"taken\": {},\n  \"certificates\": [\n{}\n  ]\n}}\n",
+        taken,
+        objects.join(",\n")
+    )
+}
+
+/// Reads a snapshot `save` wrote, with when it was taken.
+pub fn load(path: &str, bytes: &[u8]) -> Result<(i64, Vec<Record>), Box<dyn std::error::Error>> {
+    let invalid = || format!("Error: {} is not an inventory snapshot.", path);
+    let snapshot: Value = serde_yaml::from_slice(bytes).map_err(|_| invalid())?;
+    let taken = snapshot["taken"].as_i64().ok_or_else(invalid)?;
+    let certificates = snapshot["certificates"].as_sequence().ok_or_else(invalid)?;
+    let mut records = Vec::new();
+    for certificate in certificates {
+        let field = |name: &str| {
+            certificate[name]
+                .as_str()
+                .map(String::from)
+                .ok_or_else(invalid)
+        };
+        records.push(Record {
+            location: field("location")?,
+            subject: field("subject")?,
+            issuer: field("issuer")?,
+            serial: field("serial")?,
+            not_after: field("not_after")?,
+            sha256: field("sha256")?,
+            key_sha256: field("key_sha256")?,
+        });
+    }
+    Ok((taken, records))
+}
+
+/// How the certificates found have changed since a snapshot.
+pub struct Drift {
+    pub added: Vec<Record>,
+    pub removed: Vec<Record>,
+    /// Certificates replaced by another for the same subject, with the same key.
+    pub renewed: Vec<(Record, Record)>,
+    /// Certificates replaced by another for the same subject, with a new key.
+    pub rekeyed: Vec<(Record, Record)>,
+    pub unchanged: usize,
+}
+
+impl Drift {
+    pub fn new(before: &[Record], after: &[Record]) -> Drift {
+        let mut drift = Drift {
+            added: Vec::new(),
+            removed: Vec::new(),
+            renewed: Vec::new(),
+            rekeyed: Vec::new(),
+            unchanged: 0,
+        };
+        for record in after {
+            match before.iter().find(|old| old.key() == record.key()) {
+                None => drift.added.push(record.clone()),
+                Some(old) if old.sha256 == record.sha256 => drift.unchanged += 1,
+                Some(old) if old.key_sha256 == record.key_sha256 => {
+                    drift.renewed.push((old.clone(), record.clone()))
+                }
+                Some(old) => drift.rekeyed.push((old.clone(), record.clone())),
+            }
+        }
+        for old in before {
+            if !after.iter().any(|record| record.key() == old.key()) {
+                drift.removed.push(old.clone());
+            }
+        }
+        drift
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let replaced = |pairs: &[(Record, Record)]| -> Vec<String> {
+            pairs
+                .iter()
+                .map(|(old, new)| {
+                    format!(
+                        "{}: {}, expires {} (was serial {}, expiring {})",
+                        new.location, new.subject, new.not_after, old.serial, old.not_after
+                    )
+                })
+                .collect()
+        };
+        list(
+            f,
+            "Added",
+            &self.added.iter().map(Record::line).collect::<Vec<_>>(),
+        )?;
+        list(
+            f,
+            "Removed",
+            &self.removed.iter().map(Record::line).collect::<Vec<_>>(),
+        )?;
+        list(f, "Renewed", &replaced(&self.renewed))?;
+        list(f, "Renewed with a new key", &replaced(&self.rekeyed))?;
+        writeln!(f, "Unchanged: {}", self.unchanged)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{load, records, save, Drift, Record};
+    use crate::report::Report;
+    use crate::target::Target;
+    use crate::test::FakeProcessor;
+
+    fn scan(files: &[(&str, &str)]) -> Vec<Record> {
+        let mut processor = FakeProcessor::default();
+        for (path, contents) in files {
+            processor.add_file(path, contents);
+        }
+        let targets: Vec<Target> = files
+            .iter()
+            .map(|(path, _)| Target::Path(String::from(*path)))
+            .collect();
+        records(&Report::build(&processor, &targets, &Default::default()))
+    }
+
+    #[test]
+    fn should_load_the_snapshot_it_saved() {
+        let records = scan(&[
+            ("web.crt", include_str!("../resources/google.com.crt")),
+            ("dn \"quoted\".crt", include_str!("../resources/dn.crt")),
+        ]);
+
+        let (taken, loaded) =
+            load("inventory.json", save(&records, 1_780_272_000).as_bytes()).unwrap();
+
+        assert_eq!(taken, 1_780_272_000);
+        assert_eq!(loaded, records);
+        assert_eq!(
+            format!("{}", load("inventory.json", b"[]").err().unwrap()),
+            "Error: inventory.json is not an inventory snapshot."
+        );
+    }
+
+    #[test]
+    fn should_tell_renewals_from_new_keys_additions_and_removals() {
+        let google = include_str!("../resources/google.com.crt");
+        let dn = include_str!("../resources/dn.crt");
+        let renewal = include_str!("../resources/renewal.crt");
+        let before = scan(&[("web.crt", google), ("old.crt", dn)]);
+        let mut after = scan(&[("web.crt", google), ("new.crt", renewal)]);
+        let mut renewed = after[0].clone();
+        renewed.sha256 = String::from("renewed");
+        let mut rekeyed = after[0].clone();
+        rekeyed.sha256 = String::from("rekeyed");
+        rekeyed.key_sha256 = String::from("rekeyed");
+
+        let drift = Drift::new(&before, &after);
+        after[0] = renewed;
+        let renewals = Drift::new(&before, &after);
+        after[0] = rekeyed;
+        let rekeys = Drift::new(&before, &after);
+
+        assert_eq!(drift.unchanged, 1);
+        assert_eq!(drift.added[0].location, "new.crt");
+        assert_eq!(drift.removed[0].location, "old.crt");
+        assert!(drift.renewed.is_empty() && drift.rekeyed.is_empty());
+        assert_eq!(renewals.renewed.len(), 1);
+        assert_eq!(rekeys.rekeyed.len(), 1);
+        assert!(drift
+            .to_string()
+            .starts_with("Added:\n    new.crt: CN=renewal.example.com, expires "));
+        assert!(drift
+            .to_string()
+            .ends_with("Renewed: none\nRenewed with a new key: none\nUnchanged: 1\n"));
+    }
+}
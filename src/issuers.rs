@@ -0,0 +1,346 @@
+use std::fmt;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::ParsedExtension;
+
+use crate::cms::hex;
+use crate::dn;
+
+/// How browsers regard the certificates a certificate authority issues.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Standing {
+    Trusted,
+    /// No longer trusted for certificates issued after a time, or any at all.
+    Distrusted {
+        after: Option<i64>,
+        reason: &'static str,
+    },
+}
+
+/// A certificate authority well known enough to recognize.
+#[derive(Debug, PartialEq)]
+pub struct KnownCa {
+    pub operator: &'static str,
+    /// The organization names its CAs put in their subjects.
+    pub organizations: &'static [&'static str],
+    /// The subject key identifiers of its roots and main intermediates, which
+    /// unlike its name no one else can put in a certificate they issue.
+    pub key_ids: &'static [&'static str],
+    pub standing: Standing,
+}
+
+pub const KNOWN: &[KnownCa] = &[
+    KnownCa {
+        operator: "Let's Encrypt",
+        organizations: &["Let's Encrypt", "Internet Security Research Group"],
+        key_ids: &[
+            // ISRG Root X1, ISRG Root X2 and R3
+            "79:b4:59:e6:7b:b6:e5:e4:01:73:80:08:88:c8:1a:58:f6:e9:9b:6e",
+            "7c:42:96:ae:de:4b:48:3b:fa:92:f8:9e:8c:cf:6d:8b:a9:72:37:95",
+            "14:2e:b3:17:b7:58:56:cb:ae:50:09:40:e6:1f:af:9d:8b:14:c2:c6",
+        ],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "Google Trust Services",
+        organizations: &["Google Trust Services LLC", "Google Trust Services"],
+        key_ids: &[
+            // GTS Root R1 to R4 and GTS CA 1O1
+            "e4:af:2b:26:71:1a:2b:48:27:85:2f:52:66:2c:ef:f0:89:13:71:3e",
+            "bb:ff:ca:8e:23:9f:4f:99:ca:db:e2:68:a6:a5:15:27:17:1e:d9:0e",
+            "c1:f1:26:ba:a0:2d:ae:85:81:cf:d3:f1:2a:12:bd:b8:0a:67:fd:bc",
+            "80:4c:d6:eb:74:ff:49:36:a3:d5:d8:fc:b5:3e:c5:6a:f0:94:1d:8c",
+            "98:d1:f8:6e:10:eb:cf:9b:ec:60:9f:18:90:1b:a0:eb:7d:09:fd:2b",
+        ],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "DigiCert",
+        organizations: &["DigiCert Inc", "DigiCert, Inc.", "Baltimore"],
+        key_ids: &[
+            // DigiCert Global Root CA, DigiCert Global Root G2 and Baltimore CyberTrust Root
+            "03:de:50:35:56:d1:4c:bb:66:f0:a3:e2:1b:1b:c3:97:b2:3d:d1:55",
+            "4e:22:54:20:18:95:e6:e3:6e:e6:0f:fa:fa:b9:12:ed:06:17:8f:39",
+            "e5:9d:59:30:82:47:58:cc:ac:fa:08:54:36:86:7b:3a:b5:04:4d:f0",
+        ],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "Sectigo",
+        organizations: &[
+            "Sectigo Limited",
+            "COMODO CA Limited",
+            "The USERTRUST Network",
+            "ZeroSSL",
+        ],
+        key_ids: &[
+            // USERTrust RSA and ECC Certification Authority
+            "53:79:bf:5a:aa:2b:4a:cf:54:80:e1:d8:9b:c0:9d:f2:b2:03:66:cb",
+            "3a:e1:09:86:d4:cf:19:c2:96:76:74:49:76:dc:e0:35:c6:63:63:9a",
+        ],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "GlobalSign",
+        organizations: &["GlobalSign nv-sa", "GlobalSign"],
+        key_ids: &["60:7b:66:1a:45:0d:97:ca:89:50:2f:7d:04:cd:34:a8:ff:fc:fd:4b"],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "Amazon",
+        organizations: &["Amazon"],
+        key_ids: &["84:18:cc:85:34:ec:bc:0c:94:94:2e:08:59:9c:c7:b2:10:4e:0a:08"],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "Microsoft",
+        organizations: &["Microsoft Corporation"],
+        key_ids: &[],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "GoDaddy",
+        organizations: &["GoDaddy.com, Inc.", "Starfield Technologies, Inc."],
+        key_ids: &[],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "IdenTrust",
+        organizations: &["IdenTrust"],
+        key_ids: &[],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "SSL.com",
+        organizations: &["SSL Corporation"],
+        key_ids: &[],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "Buypass",
+        organizations: &["Buypass AS-983163327"],
+        key_ids: &[],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "Certum",
+        organizations: &["Unizeto Technologies S.A.", "Asseco Data Systems S.A."],
+        key_ids: &[],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "HARICA",
+        organizations: &["Hellenic Academic and Research Institutions CA"],
+        key_ids: &[],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "Cloudflare",
+        organizations: &["Cloudflare, Inc."],
+        key_ids: &[],
+        standing: Standing::Trusted,
+    },
+    KnownCa {
+        operator: "Entrust",
+        organizations: &["Entrust, Inc.", "Entrust.net"],
+        key_ids: &["6a:72:26:7a:d0:1e:ef:7d:e7:3b:69:51:d4:6c:8d:9f:90:12:66:ab"],
+        standing: Standing::Distrusted {
+            // Nov 11 23:59:59 2024
+            after: Some(1_731_369_599),
+            reason: "Chrome and Mozilla stopped trusting Entrust's TLS certificates issued after November 11, 2024",
+        },
+    },
+    KnownCa {
+        operator: "Chunghwa Telecom",
+        organizations: &["Chunghwa Telecom Co., Ltd."],
+        key_ids: &[],
+        standing: Standing::Distrusted {
+            // Jul 31 23:59:59 2025
+            after: Some(1_754_006_399),
+            reason: "Chrome stopped trusting Chunghwa Telecom's TLS certificates issued after July 31, 2025",
+        },
+    },
+    KnownCa {
+        operator: "NetLock",
+        organizations: &["NetLock Kft."],
+        key_ids: &[],
+        standing: Standing::Distrusted {
+            after: Some(1_754_006_399),
+            reason: "Chrome stopped trusting NetLock's TLS certificates issued after July 31, 2025",
+        },
+    },
+    KnownCa {
+        operator: "Symantec",
+        organizations: &["Symantec Corporation", "VeriSign, Inc.", "GeoTrust Inc.", "thawte, Inc."],
+        key_ids: &[],
+        standing: Standing::Distrusted {
+            after: None,
+            reason: "browsers stopped trusting Symantec's CAs in 2018 after years of misissuance",
+        },
+    },
+    KnownCa {
+        operator: "WoSign",
+        organizations: &["WoSign CA Limited", "StartCom Ltd."],
+        key_ids: &[],
+        standing: Standing::Distrusted {
+            after: None,
+            reason: "browsers stopped trusting WoSign and StartCom in 2017 for backdating certificates",
+        },
+    },
+    KnownCa {
+        operator: "Camerfirma",
+        organizations: &["AC Camerfirma S.A."],
+        key_ids: &[],
+        standing: Standing::Distrusted {
+            after: None,
+            reason: "browsers stopped trusting Camerfirma in 2021 after repeated compliance failures",
+        },
+    },
+    KnownCa {
+        operator: "TrustCor",
+        organizations: &["TrustCor Systems S. de R.L."],
+        key_ids: &[],
+        standing: Standing::Distrusted {
+            after: None,
+            reason: "browsers stopped trusting TrustCor in 2022 over its ties to a spyware maker",
+        },
+    },
+];
+
+/// Who issued a certificate, as far as can be told without its chain.
+#[derive(Debug, PartialEq)]
+pub struct Issuer {
+    /// The issuing CA's common name, with its operator's name in front when
+    /// the common name alone doesn't say, such as `Let's Encrypt R3`.
+    pub name: String,
+    pub known: Option<&'static KnownCa>,
+    /// Why browsers won't trust the certificate, if they won't.
+    pub distrusted: Option<&'static str>,
+}
+
+impl fmt::Display for Issuer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.known, self.distrusted) {
+            (_, Some(reason)) => write!(f, "{} (distrusted, {})", self.name, reason),
+            (Some(_), None) => write!(f, "{} (publicly trusted)", self.name),
+            (None, None) => write!(f, "{} (unknown or private issuer)", self.name),
+        }
+    }
+}
+
+/// Recognizes the CA that issued a certificate, by the key it was signed with
+/// if that is one of the bundled ones, or else by the organization it names.
+pub fn identify(cert: &X509Certificate) -> Issuer {
+    let key_id = cert
+        .extensions()
+        .iter()
+        .find_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::AuthorityKeyIdentifier(aki) => aki.key_identifier.as_ref(),
+            _ => None,
+        })
+        .map(|id| hex(id.0));
+    let organization = cert
+        .issuer()
+        .iter_organization()
+        .next()
+        .and_then(|o| o.as_str().ok());
+    let known = lookup(key_id.as_deref(), organization);
+    let common_name = cert
+        .issuer()
+        .iter_common_name()
+        .next()
+        .and_then(|cn| cn.as_str().ok());
+    let name = match (known, common_name) {
+        (Some(ca), Some(cn)) if !mentions(cn, ca.operator) => format!("{} {}", ca.operator, cn),
+        (_, Some(cn)) => String::from(cn),
+        (Some(ca), None) => String::from(ca.operator),
+        (None, None) => dn::to_string(cert.issuer()),
+    };
+    let issued = cert.validity().not_before.timestamp();
+    Issuer {
+        name,
+        known,
+        distrusted: known.and_then(|ca| distrusted(ca, issued)),
+    }
+}
+
+fn lookup(key_id: Option<&str>, organization: Option<&str>) -> Option<&'static KnownCa> {
+    key_id
+        .and_then(|id| KNOWN.iter().find(|ca| ca.key_ids.contains(&id)))
+        .or_else(|| {
+            let organization = organization?;
+            KNOWN
+                .iter()
+                .find(|ca| ca.organizations.contains(&organization))
+        })
+}
+
+/// Whether a CA's name already starts with its operator's, like `DigiCert
+/// Global G2 TLS RSA SHA256 2020 CA1`.
+fn mentions(common_name: &str, operator: &str) -> bool {
+    let first = operator.split_whitespace().next().unwrap_or(operator);
+    common_name.starts_with(first)
+}
+
+fn distrusted(ca: &KnownCa, issued: i64) -> Option<&'static str> {
+    match ca.standing {
+        Standing::Distrusted { after, reason } if after.is_none_or(|after| issued > after) => {
+            Some(reason)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+
+    use super::{distrusted, identify, lookup};
+    use crate::test::{google_der, self_signed_der};
+
+    #[test]
+    fn should_recognize_a_publicly_trusted_issuer_by_its_key() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        let issuer = identify(&cert);
+
+        assert_eq!(
+            issuer.to_string(),
+            "Google Trust Services GTS CA 1O1 (publicly trusted)"
+        );
+        assert_eq!(
+            lookup(
+                Some("14:2e:b3:17:b7:58:56:cb:ae:50:09:40:e6:1f:af:9d:8b:14:c2:c6"),
+                None
+            )
+            .unwrap()
+            .operator,
+            "Let's Encrypt"
+        );
+    }
+
+    #[test]
+    fn should_call_other_issuers_unknown() {
+        let der = self_signed_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        assert_eq!(
+            identify(&cert).to_string(),
+            "self-signed.example (unknown or private issuer)"
+        );
+    }
+
+    #[test]
+    fn should_flag_distrusted_issuers_for_what_they_issued_after_the_distrust() {
+        let symantec = lookup(None, Some("GeoTrust Inc.")).unwrap();
+        let entrust = lookup(None, Some("Entrust, Inc.")).unwrap();
+
+        assert!(distrusted(symantec, 0).is_some());
+        assert_eq!(distrusted(entrust, 1_700_000_000), None);
+        assert!(distrusted(entrust, 1_740_000_000)
+            .unwrap()
+            .contains("after November 11, 2024"));
+    }
+}
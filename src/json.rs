@@ -0,0 +1,31 @@
+/// A string as a JSON string, quoted and escaped.
+pub fn string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::string;
+
+    #[test]
+    fn should_escape_what_json_strings_cannot_hold() {
+        assert_eq!(
+            string("C:\\certs\\\"web\"\n\u{1}é"),
+            "\"C:\\\\certs\\\\\\\"web\\\"\\n\\u0001é\""
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use crate::findings::Finding;
+use crate::report::Report;
+
+/// A check of a certificate, and what it found if it failed.
+struct TestCase<'a> {
+    name: &'static str,
+    failures: Vec<&'a Finding>,
+}
+
+/// Every check of every certificate as JUnit XML, a test suite for each place
+/// certificates were found in, so Jenkins and GitLab show certificate health as
+/// test results. Whether a certificate decoded and is valid now are always
+/// checked, its trust path when paths were built, and any warnings about it.
+pub fn to_string(report: &Report, findings: &[Finding]) -> String {
+    let mut suites: Vec<(&str, Vec<TestCase>)> = Vec::new();
+    let at = |location: &str, rules: &[&str]| -> Vec<&Finding> {
+        findings
+            .iter()
+            .filter(|finding| finding.location == location && rules.contains(&finding.rule))
+            .collect()
+    };
+    for entry in &report.entries {
+        let location = entry.location.as_str();
+        let mut cases = vec![
+            TestCase {
+                name: "decode",
+                failures: Vec::new(),
+            },
+            TestCase {
+                name: "validity",
+                failures: at(location, &["expired", "not-yet-valid"]),
+            },
+        ];
+        if !entry.trust.is_empty() {
+            cases.push(TestCase {
+                name: "chain",
+                failures: at(location, &["untrusted"]),
+            });
+        }
+        cases.push(TestCase {
+            name: "warnings",
+            failures: at(location, &["warning"]),
+        });
+        suites.push((location, cases));
+    }
+    for failure in &report.failures {
+        let location = failure.location.as_str();
+        let cases = vec![TestCase {
+            name: "decode",
+            failures: at(location, &["decode"]),
+        }];
+        suites.push((location, cases));
+    }
+    // Warnings about none of the certificates, such as an empty Kubernetes secret
+    let other = at("", &["warning"]);
+    if !other.is_empty() {
+        let cases = vec![TestCase {
+            name: "warnings",
+            failures: other,
+        }];
+        suites.push(("cert-decoder", cases));
+    }
+
+    let count = |cases: &[TestCase]| {
+        cases
+            .iter()
+            .filter(|case| !case.failures.is_empty())
+            .count()
+    };
+    let tests: usize = suites.iter().map(|(_, cases)| cases.len()).sum();
+    let failures: usize = suites.iter().map(|(_, cases)| count(cases)).sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"cert-decoder\" tests=\"{}\" failures=\"{}\">\n",
+        tests, failures
+    ));
+    for (location, cases) in &suites {
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            escape(location),
+            cases.len(),
+            count(cases)
+        ));
+        for case in cases {
+            let open = format!(
+                "    <testcase classname=\"{}\" name=\"{}\"",
+                escape(location),
+                case.name
+            );
+            let first = match case.failures.first() {
+                Some(first) => first,
+                None => {
+                    xml.push_str(&format!("{}/>\n", open));
+                    continue;
+                }
+            };
+            let messages: Vec<&str> = case
+                .failures
+                .iter()
+                .map(|finding| finding.message.as_str())
+                .collect();
+            xml.push_str(&format!("{}>\n", open));
+            xml.push_str(&format!(
+                "      <failure type=\"{}\" message=\"{}\">{}</failure>\n",
+                first.rule,
+                escape(&first.message),
+                escape(&messages.join("\n"))
+            ));
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Text as XML character data or an attribute value.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#10;"),
+            // Control characters are not allowed in XML 1.0 at all
+            c if (c as u32) < 0x20 && c != '\t' => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{escape, to_string};
+    use crate::findings;
+    use crate::options::Options;
+    use crate::report::Report;
+    use crate::target::Target;
+    use crate::test::FakeProcessor;
+
+    #[test]
+    fn should_write_a_test_case_for_each_check_of_each_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        processor.add_file("bad.crt", include_str!("../resources/bad.crt"));
+        let targets = vec![
+            Target::Path(String::from("google.crt")),
+            Target::Path(String::from("bad.crt")),
+        ];
+        let report = Report::build(&processor, &targets, &Options::default());
+        let now = report.entries[0]
+            .certificate()
+            .validity()
+            .not_after
+            .timestamp()
+            + 1;
+
+        let xml = to_string(&report, &findings::collect(&report, now));
+
+        assert!(xml.starts_with(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <testsuites name=\"cert-decoder\" tests=\"4\" failures=\"2\">\n  \
+             <testsuite name=\"google.crt\" tests=\"3\" failures=\"1\">\n    \
+             <testcase classname=\"google.crt\" name=\"decode\"/>\n    \
+             <testcase classname=\"google.crt\" name=\"validity\">\n      \
+             <failure type=\"expired\" message=\"C=US, ST=California, L=Mountain View, O=Google LLC, CN=*.google.com expired on Sep  2 09:34:58 2020 +00:00.\">"
+        ));
+        assert!(xml.contains(
+            "    <testcase classname=\"google.crt\" name=\"warnings\"/>\n  </testsuite>\n  \
+             <testsuite name=\"bad.crt\" tests=\"1\" failures=\"1\">\n    \
+             <testcase classname=\"bad.crt\" name=\"decode\">\n      <failure type=\"decode\" "
+        ));
+        assert!(xml.ends_with("  </testsuite>\n</testsuites>\n"));
+    }
+
+    #[test]
+    fn should_escape_what_xml_cannot_hold() {
+        assert_eq!(
+            escape("<a href=\"x\">&\n\u{1}"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&#10;\u{fffd}"
+        );
+    }
+}
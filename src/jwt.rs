@@ -0,0 +1,290 @@
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY, SHA256};
+use serde_yaml::Value;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::parse_x509_certificate;
+use x509_parser::public_key::PublicKey;
+
+use crate::keystore;
+use crate::store::NamedCertificate;
+
+/// The certificates found in a JWT or JWKS, with anything that doesn't add up about them.
+pub struct Chains {
+    pub certificates: Vec<NamedCertificate>,
+    pub warnings: Vec<String>,
+}
+
+/// Whether the bytes are a compact JWT, or a JWK or JWKS document.
+pub fn is_jwt_or_jwks(bytes: &[u8]) -> bool {
+    header(bytes).is_some() || keys(bytes).is_some()
+}
+
+/// Extracts the `x5c` chains of a JWT's header or a JWKS's keys, checking each
+/// against its `x5t` thumbprints, its key and itself, which is where OIDC
+/// providers tend to go wrong after rotating keys.
+pub fn read(source: &str, bytes: &[u8]) -> Result<Chains, Box<dyn std::error::Error>> {
+    let mut chains = Chains {
+        certificates: Vec::new(),
+        warnings: Vec::new(),
+    };
+    if let Some(header) = header(bytes) {
+        check(&mut chains, "header x5c", &header)?;
+        // The signing key can also be embedded in the header
+        check(&mut chains, "header jwk x5c", &header["jwk"])?;
+        if chains.certificates.is_empty() {
+            return Err(format!("Error: the header of {} has no x5c chain.", source).into());
+        }
+        return Ok(chains);
+    }
+
+    for (i, key) in keys(bytes).unwrap_or_default().iter().enumerate() {
+        let label = match key["kid"].as_str() {
+            Some(kid) => format!("kid {} x5c", kid),
+            None => format!("key {} x5c", i + 1),
+        };
+        check(&mut chains, &label, key)?;
+    }
+    if chains.certificates.is_empty() {
+        return Err(format!("Error: no key in {} has an x5c chain.", source).into());
+    }
+    Ok(chains)
+}
+
+/// The decoded header of a compact JWT.
+fn header(bytes: &[u8]) -> Option<Value> {
+    let token = std::str::from_utf8(bytes).ok()?.trim();
+    let segments: Vec<&str> = token.split('.').collect();
+    // Three segments for a signed token, five for an encrypted one
+    if segments.len() != 3 && segments.len() != 5 {
+        return None;
+    }
+    let header = URL_SAFE_NO_PAD
+        .decode(segments[0].trim_end_matches('='))
+        .ok()?;
+    let header: Value = serde_yaml::from_slice(&header).ok()?;
+    header["alg"].as_str()?;
+    Some(header)
+}
+
+/// The keys of a JWKS, or a lone JWK as a set of one.
+fn keys(bytes: &[u8]) -> Option<Vec<Value>> {
+    let document: Value = serde_yaml::from_slice(bytes).ok()?;
+    if let Some(keys) = document["keys"].as_sequence() {
+        return Some(keys.clone());
+    }
+    document["kty"].as_str()?;
+    Some(vec![document])
+}
+
+/// Decodes the `x5c` chain of a header or JWK, if it has one, and checks it.
+fn check(chains: &mut Chains, label: &str, key: &Value) -> Result<(), Box<dyn std::error::Error>> {
+    let mut chain = Vec::new();
+    for encoded in key["x5c"].as_sequence().into_iter().flatten() {
+        // Unlike the rest of a JWT, x5c uses standard base64 with padding
+        let der = encoded
+            .as_str()
+            .and_then(|encoded| STANDARD.decode(encoded.trim()).ok())
+            .ok_or_else(|| format!("Error: the {} chain is not valid base64.", label))?;
+        chain.push(der);
+    }
+    let leaf = match chain.first() {
+        Some(leaf) => leaf.clone(),
+        None => return Ok(()),
+    };
+
+    let thumbprints = [
+        ("x5t", digest(&SHA1_FOR_LEGACY_USE_ONLY, &leaf)),
+        ("x5t#S256", digest(&SHA256, &leaf)),
+    ];
+    for (name, thumbprint) in &thumbprints {
+        if let Some(expected) = key[*name].as_str() {
+            if expected.trim_end_matches('=') != URL_SAFE_NO_PAD.encode(thumbprint) {
+                chains.warnings.push(format!(
+                    "Warning: the {} of {} does not match its first certificate.",
+                    name, label
+                ));
+            }
+        }
+    }
+
+    let certificates = chain
+        .iter()
+        .map(|der| parse_x509_certificate(der).map(|(_, cert)| cert))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| format!("Error: the {} chain is not valid: {}", label, error))?;
+    if key_matches(key, &certificates[0]) == Some(false) {
+        chains.warnings.push(format!(
+            "Warning: the key of {} is not the key of its first certificate.",
+            label
+        ));
+    }
+    for (i, pair) in certificates.windows(2).enumerate() {
+        let (cert, issuer) = (&pair[0], &pair[1]);
+        let problem = if cert.issuer() != issuer.subject() {
+            "was not issued by"
+        } else if cert.verify_signature(Some(issuer.public_key())).is_err() {
+            "does not have a valid signature from"
+        } else {
+            continue;
+        };
+        chains.warnings.push(format!(
+            "Warning: certificate {} of {} {} the certificate after it.",
+            i + 1,
+            label,
+            problem
+        ));
+    }
+
+    chains
+        .certificates
+        .extend(keystore::chain_entries(label, chain.into_iter()));
+    Ok(())
+}
+
+/// Whether the public key of a JWK is the one in the certificate,
+/// or `None` if there is no key to compare, as in a JWT header.
+fn key_matches(key: &Value, cert: &X509Certificate) -> Option<bool> {
+    let field = |name: &str| {
+        key[name]
+            .as_str()
+            .and_then(|value| URL_SAFE_NO_PAD.decode(value.trim_end_matches('=')).ok())
+    };
+    // JWKs leave out leading zeros, DER keeps one to mark a number positive
+    let unsigned = |bytes: &[u8]| {
+        let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+        bytes[start..].to_vec()
+    };
+
+    let spki = cert.public_key();
+    let matches = match (key["kty"].as_str()?, spki.parsed()) {
+        ("RSA", Ok(PublicKey::RSA(rsa))) => {
+            unsigned(&field("n")?) == unsigned(rsa.modulus)
+                && unsigned(&field("e")?) == unsigned(rsa.exponent)
+        }
+        ("EC", Ok(PublicKey::EC(point))) => {
+            // An uncompressed point is 0x04 followed by both coordinates
+            let mut expected = vec![4];
+            expected.extend(field("x")?);
+            expected.extend(field("y")?);
+            point.data() == expected.as_slice()
+        }
+        ("OKP", _) => spki.subject_public_key.data.as_ref() == field("x")?.as_slice(),
+        _ => false,
+    };
+    Some(matches)
+}
+
+#[cfg(test)]
+mod test {
+
+    use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+    use base64::Engine;
+    use ring::digest::{digest, SHA256};
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::Pem;
+    use x509_parser::public_key::PublicKey;
+
+    use super::{is_jwt_or_jwks, read};
+
+    /// The signer certificate and the CA that issued it.
+    fn chain() -> Vec<Vec<u8>> {
+        let pem = include_bytes!("../resources/signing-chain.pem");
+        Pem::iter_from_buffer(pem)
+            .map(|pem| pem.unwrap().contents)
+            .collect()
+    }
+
+    fn x5c(chain: &[Vec<u8>]) -> String {
+        let encoded: Vec<_> = chain
+            .iter()
+            .map(|der| format!("\"{}\"", STANDARD.encode(der)))
+            .collect();
+        format!("[{}]", encoded.join(", "))
+    }
+
+    /// A JWK for the signer's RSA key, with the chain given for it.
+    fn jwk(kid: &str, chain: &[Vec<u8>]) -> String {
+        let signer = &self::chain()[0];
+        let (_, cert) = parse_x509_certificate(signer).unwrap();
+        let rsa = match cert.public_key().parsed().unwrap() {
+            PublicKey::RSA(rsa) => (rsa.modulus.to_vec(), rsa.exponent.to_vec()),
+            _ => panic!("the signer has an RSA key"),
+        };
+        format!(
+            r#"{{"kty": "RSA", "kid": "{}", "n": "{}", "e": "{}", "x5c": {}, "x5t#S256": "{}"}}"#,
+            kid,
+            URL_SAFE_NO_PAD.encode(&rsa.0[1..]),
+            URL_SAFE_NO_PAD.encode(&rsa.1),
+            x5c(chain),
+            URL_SAFE_NO_PAD.encode(digest(&SHA256, &chain[0]))
+        )
+    }
+
+    #[test]
+    fn should_decode_the_x5c_chain_of_a_jwt() {
+        let header = format!(
+            r#"{{"alg": "RS256", "x5c": {}, "x5t": "bm9wZQ"}}"#,
+            x5c(&chain())
+        );
+        let token = format!("{}.e30.c2ln\n", URL_SAFE_NO_PAD.encode(header));
+
+        let chains = read("token", token.as_bytes()).unwrap();
+
+        assert!(is_jwt_or_jwks(token.as_bytes()));
+        assert_eq!(chains.certificates.len(), 2);
+        assert_eq!(chains.certificates[0].0, "header x5c [1/2]");
+        assert_eq!(
+            chains.warnings,
+            vec!["Warning: the x5t of header x5c does not match its first certificate."]
+        );
+    }
+
+    #[test]
+    fn should_check_the_keys_and_chains_of_a_jwks() {
+        let mut chain = chain();
+        let consistent = jwk("good", &chain);
+        chain.reverse();
+        let reversed = jwk("rotated", &chain);
+        let jwks = format!(r#"{{"keys": [{}, {}]}}"#, consistent, reversed);
+
+        let chains = read("jwks.json", jwks.as_bytes()).unwrap();
+
+        let labels: Vec<_> = chains
+            .certificates
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "kid good x5c [1/2]",
+                "kid good x5c [2/2]",
+                "kid rotated x5c [1/2]",
+                "kid rotated x5c [2/2]"
+            ]
+        );
+        assert_eq!(
+            chains.warnings,
+            vec![
+                "Warning: the key of kid rotated x5c is not the key of its first certificate.",
+                "Warning: certificate 1 of kid rotated x5c was not issued by the certificate after it."
+            ]
+        );
+    }
+
+    #[test]
+    fn should_error_if_there_is_no_chain() {
+        let jwks = br#"{"keys": [{"kty": "oct", "k": "c2VjcmV0"}]}"#;
+
+        let result = read("jwks.json", jwks);
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: no key in jwks.json has an x5c chain."
+        );
+        assert!(!is_jwt_or_jwks(include_bytes!(
+            "../resources/google.com.crt"
+        )));
+    }
+}
@@ -0,0 +1,159 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Deserialize;
+use serde_yaml::Value;
+use x509_parser::pem::Pem;
+
+use crate::store::NamedCertificate;
+
+/// The keys of a `kubernetes.io/tls` secret that hold certificates.
+const CERTIFICATE_KEYS: &[&str] = &["tls.crt", "ca.crt"];
+
+/// Finds the certificates in Kubernetes Secret manifests, as written by
+/// `kubectl get secret -o yaml` (or `-o json`, which is also YAML). Lists and
+/// multi-document files are searched too. Each certificate is labelled with
+/// the secret and key it came from, e.g. `prod/web tls.crt`.
+pub fn secret_certificates(
+    source: &str,
+    bytes: &[u8],
+) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    let mut found = Vec::new();
+    for document in serde_yaml::Deserializer::from_slice(bytes) {
+        let document = Value::deserialize(document).map_err(|error| {
+            format!("Error: {} is not a Kubernetes manifest: {}", source, error)
+        })?;
+        collect(&document, None, &mut found)?;
+    }
+
+    if found.is_empty() {
+        return Err(format!(
+            "Error: no Secret in {} has a tls.crt or ca.crt key.",
+            source
+        )
+        .into());
+    }
+    Ok(found)
+}
+
+/// Finds the certificates in a document. The items of a typed list such as
+/// a SecretList leave out their kind, so it is passed down as `kind`.
+pub fn collect(
+    document: &Value,
+    kind: Option<&str>,
+    found: &mut Vec<NamedCertificate>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match document["kind"].as_str().or(kind) {
+        Some("List") => {
+            for item in document["items"].as_sequence().into_iter().flatten() {
+                collect(item, None, found)?;
+            }
+        }
+        Some("SecretList") => {
+            for item in document["items"].as_sequence().into_iter().flatten() {
+                collect(item, Some("Secret"), found)?;
+            }
+        }
+        Some("Secret") => {
+            let metadata = &document["metadata"];
+            let name = metadata["name"].as_str().unwrap_or("(unnamed)");
+            let secret = match metadata["namespace"].as_str() {
+                Some(namespace) => format!("{}/{}", namespace, name),
+                None => String::from(name),
+            };
+
+            for key in CERTIFICATE_KEYS {
+                let label = format!("{} {}", secret, key);
+                // data is base64 encoded, stringData is how it is often written by hand
+                let pem = if let Some(data) = document["data"][key].as_str() {
+                    STANDARD.decode(data.trim()).map_err(|error| {
+                        format!("Error: {} is not valid base64: {}", label, error)
+                    })?
+                } else if let Some(data) = document["stringData"][key].as_str() {
+                    data.as_bytes().to_vec()
+                } else {
+                    continue;
+                };
+                found.extend(pem_chain(&label, &pem)?);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Splits the PEM under a key (often a whole chain) into its certificates.
+pub fn pem_chain(
+    label: &str,
+    pem: &[u8],
+) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    let mut chain = Vec::new();
+    for block in Pem::iter_from_buffer(pem) {
+        let block = block.map_err(|error| format!("Error: {} is not PEM: {}", label, error))?;
+        if block.label == "CERTIFICATE" {
+            chain.push(block.contents);
+        }
+    }
+
+    let count = chain.len();
+    Ok(chain
+        .into_iter()
+        .enumerate()
+        .map(|(i, der)| match count {
+            1 => (String::from(label), der),
+            _ => (format!("{} [{}/{}]", label, i + 1, count), der),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    use super::secret_certificates;
+    use crate::test::self_signed_der;
+
+    const CERT: &str = include_str!("../resources/self-signed.crt");
+
+    #[test]
+    fn should_decode_tls_secrets_from_yaml() {
+        let manifest = format!(
+            "apiVersion: v1\nkind: Secret\ntype: kubernetes.io/tls\nmetadata:\n  name: web\n  namespace: prod\ndata:\n  tls.crt: {}\n  tls.key: c2VjcmV0\n",
+            STANDARD.encode(CERT)
+        );
+
+        let found = secret_certificates("-", manifest.as_bytes()).unwrap();
+
+        assert_eq!(
+            found,
+            vec![(String::from("prod/web tls.crt"), self_signed_der())]
+        );
+    }
+
+    #[test]
+    fn should_decode_lists_of_secrets_from_json() {
+        let manifest = format!(
+            r#"{{"kind": "List", "items": [{{"kind": "Secret", "metadata": {{"name": "a"}}, "data": {{"ca.crt": "{}"}}}}, {{"kind": "ConfigMap"}}]}}"#,
+            STANDARD.encode(CERT)
+        );
+
+        let found = secret_certificates("-", manifest.as_bytes()).unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "a ca.crt");
+    }
+
+    #[test]
+    fn should_error_if_no_secret_holds_certificates() {
+        let result = secret_certificates(
+            "secret.yaml",
+            b"kind: Secret\ndata:\n  password: aHVudGVyMg==\n",
+        );
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: no Secret in secret.yaml has a tls.crt or ca.crt key."
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use std::convert::TryInto;
+
+use p12_keystore::{KeyStore, KeyStoreEntry, Pkcs12ImportPolicy};
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+use x509_parser::der_parser::der::parse_der;
+use x509_parser::oid_registry::OID_PKCS7_ID_DATA;
+
+use crate::store::NamedCertificate;
+
+const JKS_MAGIC: &[u8] = &[0xFE, 0xED, 0xFE, 0xED];
+const JCEKS_MAGIC: &[u8] = &[0xCE, 0xCE, 0xCE, 0xCE];
+
+/// The certificates found in a keystore, by alias.
+pub struct Keystore {
+    pub certificates: Vec<NamedCertificate>,
+    /// Whether the store password was given and checked against the store.
+    pub verified: bool,
+}
+
+/// Whether the bytes are a JKS or JCEKS keystore.
+pub fn is_java_keystore(bytes: &[u8]) -> bool {
+    bytes.starts_with(JKS_MAGIC) || bytes.starts_with(JCEKS_MAGIC)
+}
+
+/// Whether the bytes look like a PKCS#12 file, which is what keytool
+/// writes by default nowadays: a SEQUENCE of version 3 and PKCS#7 data.
+pub fn is_pkcs12(bytes: &[u8]) -> bool {
+    let sniff = || {
+        let (_, pfx) = parse_der(bytes).ok()?;
+        let pfx = pfx.as_sequence().ok()?;
+        let version = pfx.first()?.as_u32().ok()?;
+        let content_type = pfx.get(1)?.as_sequence().ok()?.first()?.as_oid().ok()?;
+        Some(version == 3 && *content_type == OID_PKCS7_ID_DATA)
+    };
+    sniff().unwrap_or(false)
+}
+
+/// Reads the certificates of a keystore of either kind. Java keystores can be
+/// listed without the password, as keytool allows, but PKCS#12 ones can't.
+pub fn read(
+    source: &str,
+    bytes: &[u8],
+    password: Option<&str>,
+) -> Result<Keystore, Box<dyn std::error::Error>> {
+    if is_java_keystore(bytes) {
+        return read_java(source, bytes, password);
+    }
+
+    let keystore =
+        KeyStore::from_pkcs12(bytes, password.unwrap_or(""), Pkcs12ImportPolicy::Relaxed).map_err(
+            |error| {
+                format!(
+                    "Error: could not open the keystore {}: {}, check --storepass.",
+                    source, error
+                )
+            },
+        )?;
+    let mut certificates = Vec::new();
+    for (alias, entry) in keystore.entries() {
+        match entry {
+            KeyStoreEntry::PrivateKeyChain(chain) => certificates.extend(chain_entries(
+                alias,
+                chain.certs().iter().map(|cert| cert.as_der().to_vec()),
+            )),
+            KeyStoreEntry::Certificate(cert) => {
+                certificates.push((alias.clone(), cert.as_der().to_vec()))
+            }
+            KeyStoreEntry::Secret(_) => {}
+        }
+    }
+    Ok(Keystore {
+        certificates,
+        verified: true,
+    })
+}
+
+/// Labels each certificate of a private key's chain with the alias and its place in the chain.
+pub fn chain_entries(
+    alias: &str,
+    chain: impl ExactSizeIterator<Item = Vec<u8>>,
+) -> Vec<NamedCertificate> {
+    let count = chain.len();
+    chain
+        .enumerate()
+        .map(|(i, der)| match count {
+            1 => (String::from(alias), der),
+            _ => (format!("{} [{}/{}]", alias, i + 1, count), der),
+        })
+        .collect()
+}
+
+/// Reads a JKS or JCEKS keystore, as described by the JDK's JavaKeyStore.
+fn read_java(
+    source: &str,
+    bytes: &[u8],
+    password: Option<&str>,
+) -> Result<Keystore, Box<dyn std::error::Error>> {
+    // The store ends with a SHA-1 digest keyed on the password
+    if bytes.len() < 20 {
+        return Err(format!("Error: the keystore {} is truncated.", source).into());
+    }
+    let (data, expected) = bytes.split_at(bytes.len() - 20);
+
+    let verified = match password {
+        Some(password) => {
+            let mut keyed: Vec<u8> = password.encode_utf16().flat_map(u16::to_be_bytes).collect();
+            keyed.extend_from_slice(b"Mighty Aphrodite");
+            keyed.extend_from_slice(data);
+            if digest(&SHA1_FOR_LEGACY_USE_ONLY, &keyed).as_ref() != expected {
+                return Err(format!(
+                    "Error: the keystore {} was tampered with, or --storepass is incorrect.",
+                    source
+                )
+                .into());
+            }
+            true
+        }
+        None => false,
+    };
+
+    let mut reader = Reader {
+        bytes: data,
+        source,
+    };
+    reader.take(4)?;
+    let version = reader.u32()?;
+    if version != 1 && version != 2 {
+        return Err(format!(
+            "Error: the keystore {} has unknown version {}.",
+            source, version
+        )
+        .into());
+    }
+
+    let mut certificates = Vec::new();
+    for _ in 0..reader.u32()? {
+        let tag = reader.u32()?;
+        let alias = reader.utf()?;
+        reader.take(8)?; // creation date
+        match tag {
+            // Private key, with the chain for it
+            1 => {
+                let key_len = reader.u32()? as usize;
+                reader.take(key_len)?;
+                let mut chain = Vec::new();
+                for _ in 0..reader.u32()? {
+                    chain.push(reader.certificate(version)?);
+                }
+                certificates.extend(chain_entries(&alias, chain.into_iter()));
+            }
+            // Trusted certificate
+            2 => certificates.push((alias, reader.certificate(version)?)),
+            // JCEKS secret keys are serialized Java objects with no length to skip them by
+            3 => {
+                return Err(format!(
+                    "Error: the keystore {} holds a secret key ({}), which cannot be read past.",
+                    source, alias
+                )
+                .into())
+            }
+            _ => {
+                return Err(format!(
+                    "Error: the keystore {} has an unknown entry type {}.",
+                    source, tag
+                )
+                .into())
+            }
+        }
+    }
+
+    Ok(Keystore {
+        certificates,
+        verified,
+    })
+}
+
+/// Reads the big endian fields of a Java keystore.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    source: &'a str,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Box<dyn std::error::Error>> {
+        if self.bytes.len() < len {
+            return Err(format!("Error: the keystore {} is truncated.", self.source).into());
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, Box<dyn std::error::Error>> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, Box<dyn std::error::Error>> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    /// Java's modified UTF-8 only differs for NUL and astral characters, neither likely in an alias.
+    fn utf(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        let len = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    /// Version 2 stores name the certificate type before each certificate.
+    fn certificate(&mut self, version: u32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        if version == 2 {
+            self.utf()?;
+        }
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{is_java_keystore, is_pkcs12, read};
+    use crate::test::google_der;
+
+    const JKS: &[u8] = include_bytes!("../resources/keystore.jks");
+    const PKCS12: &[u8] = include_bytes!("../resources/keystore.p12");
+
+    fn aliases(certificates: &[(String, Vec<u8>)]) -> Vec<&str> {
+        certificates
+            .iter()
+            .map(|(alias, _)| alias.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn should_recognise_keystores() {
+        assert!(is_java_keystore(JKS));
+        assert!(is_pkcs12(PKCS12));
+        assert!(!is_pkcs12(&google_der()));
+        assert!(!is_java_keystore(b"-----BEGIN CERTIFICATE-----"));
+    }
+
+    #[test]
+    fn should_read_every_alias_of_a_jks_keystore() {
+        let keystore = read("keystore.jks", JKS, Some("changeit")).unwrap();
+
+        assert!(keystore.verified);
+        assert_eq!(aliases(&keystore.certificates), vec!["google", "server"]);
+        assert_eq!(keystore.certificates[0].1, google_der());
+    }
+
+    #[test]
+    fn should_read_a_jks_keystore_without_checking_it_if_there_is_no_password() {
+        let keystore = read("keystore.jks", JKS, None).unwrap();
+
+        assert!(!keystore.verified);
+        assert_eq!(keystore.certificates.len(), 2);
+    }
+
+    #[test]
+    fn should_error_if_the_jks_password_is_wrong() {
+        let result = read("keystore.jks", JKS, Some("letmein"));
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: the keystore keystore.jks was tampered with, or --storepass is incorrect."
+        );
+    }
+
+    #[test]
+    fn should_read_every_alias_of_a_pkcs12_keystore() {
+        let keystore = read("keystore.p12", PKCS12, Some("changeit")).unwrap();
+
+        assert_eq!(aliases(&keystore.certificates), vec!["google", "server"]);
+        assert!(read("keystore.p12", PKCS12, Some("letmein")).is_err());
+    }
+}
@@ -0,0 +1,382 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_yaml::Value;
+
+use crate::k8s;
+use crate::report::Report;
+use crate::store::NamedCertificate;
+use crate::FileProcessor;
+
+/// Certificate or key material, either inline (the `-data` fields) or in a file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Material {
+    Data(Vec<u8>),
+    File(String),
+}
+
+impl Material {
+    /// Reads the material, with relative file paths taken from the kubeconfig's directory.
+    pub fn load(
+        &self,
+        processor: &impl FileProcessor,
+        kubeconfig: &str,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        match self {
+            Material::Data(data) => Ok(data.clone()),
+            Material::File(path) if path.starts_with('/') => processor.read(path),
+            Material::File(path) => match kubeconfig.rsplit_once('/') {
+                Some((dir, _)) => processor.read(&format!("{}/{}", dir, path)),
+                None => processor.read(path),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cluster {
+    pub name: String,
+    pub server: String,
+    pub certificate_authority: Option<Material>,
+    pub insecure_skip_tls_verify: bool,
+}
+
+/// A client-go credential plugin, as used by EKS, GKE and AKS.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Exec {
+    /// The version of ExecCredential the plugin speaks.
+    pub api_version: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct User {
+    pub name: String,
+    pub client_certificate: Option<Material>,
+    pub client_key: Option<Material>,
+    pub token: Option<String>,
+    pub token_file: Option<String>,
+    pub exec: Option<Exec>,
+    pub auth_provider: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Context {
+    pub name: String,
+    pub cluster: String,
+    pub user: String,
+    pub namespace: Option<String>,
+}
+
+/// The parts of a kubeconfig needed to reach a cluster and to audit its certificates.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Kubeconfig {
+    /// Where the kubeconfig was read from.
+    pub path: String,
+    pub current_context: Option<String>,
+    pub contexts: Vec<Context>,
+    pub clusters: Vec<Cluster>,
+    pub users: Vec<User>,
+}
+
+impl Kubeconfig {
+    /// Reads the kubeconfig given with `--kubeconfig`, or where kubectl would find it:
+    /// the first file in `KUBECONFIG`, then `~/.kube/config`.
+    pub fn load(
+        processor: &impl FileProcessor,
+        path: Option<&str>,
+    ) -> Result<Kubeconfig, Box<dyn std::error::Error>> {
+        let path = match path {
+            Some(path) => String::from(path),
+            None => default_path(processor)?,
+        };
+        let contents = processor.read_to_string(&path)?;
+        Kubeconfig::parse(&path, &contents)
+    }
+
+    pub fn parse(path: &str, contents: &str) -> Result<Kubeconfig, Box<dyn std::error::Error>> {
+        let config: Value = serde_yaml::from_str(contents)
+            .map_err(|error| format!("Error: {} is not a valid kubeconfig: {}", path, error))?;
+        let string = |value: &Value| value.as_str().map(String::from);
+        let named = |key: &str| config[key].as_sequence().cloned().unwrap_or_default();
+
+        let mut kubeconfig = Kubeconfig {
+            path: String::from(path),
+            current_context: string(&config["current-context"]).filter(|name| !name.is_empty()),
+            ..Kubeconfig::default()
+        };
+        for entry in named("contexts") {
+            let context = &entry["context"];
+            kubeconfig.contexts.push(Context {
+                name: string(&entry["name"]).unwrap_or_default(),
+                cluster: string(&context["cluster"]).unwrap_or_default(),
+                user: string(&context["user"]).unwrap_or_default(),
+                namespace: string(&context["namespace"]),
+            });
+        }
+        for entry in named("clusters") {
+            let cluster = &entry["cluster"];
+            let name = string(&entry["name"]).unwrap_or_default();
+            kubeconfig.clusters.push(Cluster {
+                certificate_authority: material(&name, cluster, "certificate-authority")?,
+                server: string(&cluster["server"]).unwrap_or_default(),
+                insecure_skip_tls_verify: cluster["insecure-skip-tls-verify"]
+                    .as_bool()
+                    .unwrap_or(false),
+                name,
+            });
+        }
+        for entry in named("users") {
+            let user = &entry["user"];
+            let name = string(&entry["name"]).unwrap_or_default();
+            let exec = match &user["exec"] {
+                Value::Null => None,
+                exec => Some(Exec {
+                    api_version: string(&exec["apiVersion"]).unwrap_or_default(),
+                    command: string(&exec["command"]).unwrap_or_default(),
+                    args: exec["args"]
+                        .as_sequence()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(string)
+                        .collect(),
+                    env: exec["env"]
+                        .as_sequence()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|var| Some((string(&var["name"])?, string(&var["value"])?)))
+                        .collect(),
+                }),
+            };
+            kubeconfig.users.push(User {
+                client_certificate: material(&name, user, "client-certificate")?,
+                client_key: material(&name, user, "client-key")?,
+                token: string(&user["token"]),
+                token_file: string(&user["tokenFile"]),
+                exec,
+                auth_provider: string(&user["auth-provider"]["name"]),
+                name,
+            });
+        }
+        Ok(kubeconfig)
+    }
+
+    /// Finds a context by name, or the current one if no name is given.
+    pub fn context(&self, name: Option<&str>) -> Result<&Context, Box<dyn std::error::Error>> {
+        let name = match name.or(self.current_context.as_deref()) {
+            Some(name) => name,
+            None => {
+                return Err(format!(
+                    "Error: {} has no current context, choose one with --context.",
+                    self.path
+                )
+                .into())
+            }
+        };
+        self.contexts
+            .iter()
+            .find(|context| context.name == name)
+            .ok_or_else(|| format!("Error: {} has no context named {}.", self.path, name).into())
+    }
+
+    /// Decodes the cluster CA and client certificate of every context, or just the
+    /// one named, so that client certificates kubeadm issued don't expire unnoticed.
+    pub fn inspect(
+        &self,
+        processor: &impl FileProcessor,
+        context: Option<&str>,
+    ) -> Result<Report, Box<dyn std::error::Error>> {
+        let contexts = match context {
+            Some(name) => vec![self.context(Some(name))?],
+            None => self.contexts.iter().collect(),
+        };
+        if contexts.is_empty() {
+            return Err(format!("Error: {} has no contexts.", self.path).into());
+        }
+
+        let mut report = Report::default();
+        for context in contexts {
+            match self.certificates(processor, context) {
+                Ok(items) if items.is_empty() => report.warnings.push(format!(
+                    "Warning: context {} has no certificates, it uses the system roots and no client certificate.",
+                    context.name
+                )),
+                result => report.add_named(&format!("context {}", context.name), result),
+            }
+        }
+        Ok(report)
+    }
+
+    /// The certificates of a context's cluster CA and user.
+    fn certificates(
+        &self,
+        processor: &impl FileProcessor,
+        context: &Context,
+    ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        let mut found = Vec::new();
+        let cluster = self.cluster(&context.cluster)?;
+        if let Some(ca) = &cluster.certificate_authority {
+            let label = format!("cluster {} CA", cluster.name);
+            found.extend(k8s::pem_chain(&label, &ca.load(processor, &self.path)?)?);
+        }
+        let user = self.user(&context.user)?;
+        if let Some(cert) = &user.client_certificate {
+            let label = format!("user {} client certificate", user.name);
+            found.extend(k8s::pem_chain(&label, &cert.load(processor, &self.path)?)?);
+        }
+        Ok(found)
+    }
+
+    pub fn cluster(&self, name: &str) -> Result<&Cluster, Box<dyn std::error::Error>> {
+        self.clusters
+            .iter()
+            .find(|cluster| cluster.name == name)
+            .ok_or_else(|| format!("Error: {} has no cluster named {}.", self.path, name).into())
+    }
+
+    pub fn user(&self, name: &str) -> Result<&User, Box<dyn std::error::Error>> {
+        self.users
+            .iter()
+            .find(|user| user.name == name)
+            .ok_or_else(|| format!("Error: {} has no user named {}.", self.path, name).into())
+    }
+}
+
+fn default_path(processor: &impl FileProcessor) -> Result<String, Box<dyn std::error::Error>> {
+    if let Some(paths) = processor.var("KUBECONFIG") {
+        if let Some(path) = paths.split(':').find(|path| !path.is_empty()) {
+            return Ok(String::from(path));
+        }
+    }
+    match processor.var("HOME") {
+        Some(home) => Ok(format!("{}/.kube/config", home)),
+        None => Err(String::from(
+            "Error: could not find a kubeconfig, give its path with --kubeconfig.",
+        )
+        .into()),
+    }
+}
+
+/// Reads `<key>-data` (base64) or `<key>` (a path) from a cluster or user.
+fn material(
+    name: &str,
+    section: &Value,
+    key: &str,
+) -> Result<Option<Material>, Box<dyn std::error::Error>> {
+    if let Some(data) = section[format!("{}-data", key).as_str()].as_str() {
+        let data = STANDARD.decode(data.trim()).map_err(|error| {
+            format!(
+                "Error: {}-data of {} is not valid base64: {}",
+                key, name, error
+            )
+        })?;
+        return Ok(Some(Material::Data(data)));
+    }
+    Ok(section[key]
+        .as_str()
+        .map(|path| Material::File(String::from(path))))
+}
+
+#[cfg(test)]
+mod test {
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    use super::{Kubeconfig, Material};
+    use crate::test::FakeProcessor;
+
+    const KUBECONFIG: &str = "
+apiVersion: v1
+kind: Config
+current-context: dev
+contexts:
+- name: dev
+  context:
+    cluster: dev-cluster
+    user: dev-admin
+    namespace: web
+clusters:
+- name: dev-cluster
+  cluster:
+    server: https://192.0.2.10:6443
+    certificate-authority-data: aGVsbG8=
+users:
+- name: dev-admin
+  user:
+    client-certificate: certs/admin.crt
+    exec:
+      command: aws
+      args: [eks, get-token]
+      env:
+      - name: AWS_PROFILE
+        value: dev
+";
+
+    #[test]
+    fn should_parse_contexts_clusters_and_users() {
+        let config = Kubeconfig::parse("/home/me/.kube/config", KUBECONFIG).unwrap();
+
+        let context = config.context(None).unwrap();
+        assert_eq!(context.namespace, Some(String::from("web")));
+        let cluster = config.cluster(&context.cluster).unwrap();
+        assert_eq!(cluster.server, "https://192.0.2.10:6443");
+        assert_eq!(
+            cluster.certificate_authority,
+            Some(Material::Data(b"hello".to_vec()))
+        );
+        let user = config.user(&context.user).unwrap();
+        assert_eq!(
+            user.client_certificate,
+            Some(Material::File(String::from("certs/admin.crt")))
+        );
+        let exec = user.exec.as_ref().unwrap();
+        assert_eq!(exec.args, vec!["eks", "get-token"]);
+        assert_eq!(
+            exec.env,
+            vec![(String::from("AWS_PROFILE"), String::from("dev"))]
+        );
+    }
+
+    #[test]
+    fn should_decode_the_certificates_of_every_context() {
+        let cert = include_str!("../resources/self-signed.crt");
+        let config = format!(
+            "contexts:\n- name: dev\n  context: {{cluster: dev, user: admin}}\n- name: ci\n  context: {{cluster: public, user: bot}}\nclusters:\n- name: dev\n  cluster: {{certificate-authority-data: {}}}\n- name: public\n  cluster: {{}}\nusers:\n- name: admin\n  user: {{client-certificate: pki/admin.crt}}\n- name: bot\n  user: {{token: abc}}\n",
+            STANDARD.encode(cert)
+        );
+        let mut processor = FakeProcessor::default();
+        processor.add_file("/etc/kubernetes/pki/admin.crt", cert);
+        let config = Kubeconfig::parse("/etc/kubernetes/admin.conf", &config).unwrap();
+
+        let report = config.inspect(&processor, None).unwrap();
+
+        let locations: Vec<_> = report
+            .entries
+            .iter()
+            .map(|entry| entry.location.as_str())
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                "context dev: cluster dev CA",
+                "context dev: user admin client certificate"
+            ]
+        );
+        assert_eq!(
+            report.warnings,
+            vec!["Warning: context ci has no certificates, it uses the system roots and no client certificate."]
+        );
+    }
+
+    #[test]
+    fn should_error_on_an_unknown_context() {
+        let config = Kubeconfig::parse("config", KUBECONFIG).unwrap();
+
+        assert_eq!(
+            format!("{}", config.context(Some("prod")).err().unwrap()),
+            "Error: config has no context named prod."
+        );
+    }
+}
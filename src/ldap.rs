@@ -0,0 +1,721 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConnection, StreamOwned};
+use x509_parser::pem::Pem;
+
+use crate::cms::encode;
+use crate::cms::{self, Tlv};
+use crate::http::{self, Trust};
+use crate::remote::{self, ConnectOptions, Server};
+use crate::store::NamedCertificate;
+use crate::FileProcessor;
+
+/// What is searched for unless `--filter` says otherwise.
+const DEFAULT_FILTER: &str = "(|(userCertificate=*)(cACertificate=*))";
+
+/// Asked for with and without the binary option, as Active Directory and
+/// OpenLDAP each name them their own way. Both hold DER certificates.
+const ATTRIBUTES: &[&str] = &[
+    "userCertificate",
+    "userCertificate;binary",
+    "cACertificate",
+    "cACertificate;binary",
+];
+
+const BIND_RESPONSE: u8 = 0x61;
+const UNBIND_REQUEST: u8 = 0x42;
+const SEARCH_RESULT_ENTRY: u8 = 0x64;
+const SEARCH_RESULT_DONE: u8 = 0x65;
+const SEARCH_RESULT_REFERENCE: u8 = 0x73;
+const EXTENDED_RESPONSE: u8 = 0x78;
+
+/// What `cert-decoder ldap` should search, with `--base`, `--filter`,
+/// `--bind-dn` and `--password-env`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LdapOptions {
+    pub url: String,
+    pub base: String,
+    pub filter: Option<String>,
+    pub bind_dn: Option<String>,
+    pub password_env: Option<String>,
+}
+
+impl fmt::Display for LdapOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.url)
+    }
+}
+
+/// An LDAP server, and which servers to trust when it is reached over TLS.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Directory {
+    pub tls: bool,
+    pub host: String,
+    pub port: u16,
+    pub trust: Trust,
+}
+
+impl fmt::Display for Directory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scheme = if self.tls { "ldaps" } else { "ldap" };
+        write!(f, "{}://{}:{}", scheme, self.host, self.port)
+    }
+}
+
+/// The certificates a search found, and what to warn about.
+pub struct Found {
+    pub certificates: Vec<NamedCertificate>,
+    pub warnings: Vec<String>,
+}
+
+/// Finds the server in an `ldap://` or `ldaps://` URL, ignoring the base DN
+/// and the rest a full RFC 4516 URL may have, as those are given separately.
+pub fn directory(url: &str, trust: Trust) -> Result<Directory, Box<dyn std::error::Error>> {
+    let err_msg = || format!("Error: {} is not an ldap:// or ldaps:// URL.", url);
+    let (tls, rest) = match url.split_once("://") {
+        Some((scheme, rest)) if scheme.eq_ignore_ascii_case("ldap") => (false, rest),
+        Some((scheme, rest)) if scheme.eq_ignore_ascii_case("ldaps") => (true, rest),
+        _ => return Err(err_msg().into()),
+    };
+    let authority = rest.split('/').next().unwrap_or_default();
+    let default_port = if tls { 636 } else { 389 };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !host.ends_with(']') || authority.starts_with('[') => {
+            (host, port.parse().map_err(|_| err_msg())?)
+        }
+        _ => (authority, default_port),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(err_msg().into());
+    }
+    Ok(Directory {
+        tls,
+        host: String::from(host),
+        port,
+        trust,
+    })
+}
+
+fn integer(value: u8) -> Vec<u8> {
+    encode(0x02, &[value])
+}
+
+fn message(id: u8, operation: Vec<u8>) -> Vec<u8> {
+    encode(0x30, &[integer(id), operation].concat())
+}
+
+/// A simple bind, as LDAPv3.
+pub fn bind(id: u8, dn: &str, password: &str) -> Vec<u8> {
+    let contents = [
+        integer(3),
+        encode(0x04, dn.as_bytes()),
+        encode(0x80, password.as_bytes()),
+    ];
+    message(id, encode(0x60, &contents.concat()))
+}
+
+/// A search of the whole subtree under `base`, for the certificate attributes.
+pub fn search(id: u8, base: &str, filter: &[u8]) -> Vec<u8> {
+    let attributes: Vec<u8> = ATTRIBUTES
+        .iter()
+        .flat_map(|attribute| encode(0x04, attribute.as_bytes()))
+        .collect();
+    let contents = [
+        encode(0x04, base.as_bytes()),
+        // wholeSubtree, and aliases never dereferenced
+        encode(0x0a, &[2]),
+        encode(0x0a, &[0]),
+        // No size or time limit beyond the server's own
+        integer(0),
+        integer(0),
+        encode(0x01, &[0]),
+        filter.to_vec(),
+        encode(0x30, &attributes),
+    ];
+    message(id, encode(0x63, &contents.concat()))
+}
+
+fn unbind(id: u8) -> Vec<u8> {
+    message(id, vec![UNBIND_REQUEST, 0])
+}
+
+/// Encodes a filter written as RFC 4515 has it, such as
+/// `(&(objectClass=user)(sAMAccountName=alice))`, as RFC 4511's Filter.
+/// Extensible matches are not supported.
+pub fn filter(text: &str) -> Result<Vec<u8>, String> {
+    let err_msg = || {
+        format!(
+            "Error: {} is not an LDAP filter cert-decoder understands.",
+            text
+        )
+    };
+    match parse_filter(text.trim()) {
+        Some((encoded, "")) => Ok(encoded),
+        _ => Err(err_msg()),
+    }
+}
+
+fn parse_filter(text: &str) -> Option<(Vec<u8>, &str)> {
+    let text = text.strip_prefix('(')?;
+    let (encoded, rest) = match text.chars().next()? {
+        operator @ ('&' | '|') => {
+            let mut rest = &text[1..];
+            let mut filters = Vec::new();
+            while rest.starts_with('(') {
+                let (encoded, after) = parse_filter(rest)?;
+                filters.extend(encoded);
+                rest = after;
+            }
+            let tag = if operator == '&' { 0xa0 } else { 0xa1 };
+            (encode(tag, &filters), rest)
+        }
+        '!' => {
+            let (encoded, rest) = parse_filter(&text[1..])?;
+            (encode(0xa2, &encoded), rest)
+        }
+        // Values escape parentheses, so the first one closes the item
+        _ => {
+            let end = text.find(')')?;
+            (item(&text[..end])?, &text[end..])
+        }
+    };
+    Some((encoded, rest.strip_prefix(')')?))
+}
+
+fn item(text: &str) -> Option<Vec<u8>> {
+    let equals = text.find('=')?;
+    let (attribute, tag) = match text[..equals].chars().last()? {
+        '~' => (&text[..equals - 1], 0xa8),
+        '>' => (&text[..equals - 1], 0xa5),
+        '<' => (&text[..equals - 1], 0xa6),
+        ':' => return None,
+        _ => (&text[..equals], 0xa3),
+    };
+    let value = &text[equals + 1..];
+    if attribute.is_empty() {
+        return None;
+    }
+    if tag == 0xa3 && value == "*" {
+        return Some(encode(0x87, attribute.as_bytes()));
+    }
+    let attribute = encode(0x04, attribute.as_bytes());
+    if tag != 0xa3 || !value.contains('*') {
+        let contents = [attribute, encode(0x04, &unescape(value)?)].concat();
+        return Some(encode(tag, &contents));
+    }
+
+    let parts: Vec<&str> = value.split('*').collect();
+    let mut substrings = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        if part.is_empty() {
+            continue;
+        }
+        let tag = match i {
+            0 => 0x80,
+            i if i == parts.len() - 1 => 0x82,
+            _ => 0x81,
+        };
+        substrings.extend(encode(tag, &unescape(part)?));
+    }
+    let contents = [attribute, encode(0x30, &substrings)].concat();
+    Some(encode(0xa4, &contents))
+}
+
+/// Undoes the `\XX` escapes of RFC 4515.
+fn unescape(value: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut rest = value.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        if byte == b'\\' {
+            let hex = std::str::from_utf8(after.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &after[2..];
+        } else {
+            bytes.push(byte);
+            rest = after;
+        }
+    }
+    Some(bytes)
+}
+
+/// The names RFC 4511 gives the result codes a search is likely to end with.
+fn result_name(code: u8) -> &'static str {
+    match code {
+        1 => "operationsError",
+        2 => "protocolError",
+        3 => "timeLimitExceeded",
+        4 => "sizeLimitExceeded",
+        8 => "strongerAuthRequired",
+        10 => "referral",
+        32 => "noSuchObject",
+        34 => "invalidDNSyntax",
+        49 => "invalidCredentials",
+        50 => "insufficientAccessRights",
+        51 => "busy",
+        52 => "unavailable",
+        53 => "unwillingToPerform",
+        _ => "error",
+    }
+}
+
+/// The operation in an LDAP message, after its message ID.
+fn operation(message: &[u8]) -> Option<Tlv<'_>> {
+    let (message, _) = cms::element(message)?;
+    message.children()?.into_iter().nth(1)
+}
+
+/// The result code and diagnostic message of a response.
+fn result(operation: &Tlv) -> Option<(u8, String)> {
+    let fields = operation.children()?;
+    let code = *fields.first()?.contents.last()?;
+    let diagnostic = fields
+        .get(2)
+        .map(|field| String::from_utf8_lossy(field.contents).trim().to_string())
+        .unwrap_or_default();
+    Some((code, diagnostic))
+}
+
+fn failed(directory: &Directory, what: &str, code: u8, diagnostic: &str) -> String {
+    let diagnostic = match diagnostic.is_empty() {
+        true => String::new(),
+        false => format!(": {}", diagnostic),
+    };
+    format!(
+        "Error: {} refused the {}, {} ({}){}",
+        directory,
+        what,
+        result_name(code),
+        code,
+        diagnostic
+    )
+}
+
+/// Searches a directory for entries with certificates, binding as `--bind-dn`
+/// with the password from `--password-env` if given, or else anonymously.
+pub fn read(
+    processor: &impl FileProcessor,
+    options: &LdapOptions,
+    connect: &ConnectOptions,
+) -> Result<Found, Box<dyn std::error::Error>> {
+    // The same variables as OpenLDAP's tools, so an ldaprc setup carries over
+    let trust = match (
+        processor.var("LDAPTLS_REQCERT").as_deref(),
+        processor.var("LDAPTLS_CACERT"),
+    ) {
+        (Some("never" | "allow"), _) => Trust::Insecure,
+        (_, Some(path)) => Trust::Roots(
+            Pem::iter_from_buffer(&processor.read(&path)?)
+                .filter_map(Result::ok)
+                .filter(|block| block.label == "CERTIFICATE")
+                .map(|block| block.contents)
+                .collect(),
+        ),
+        _ => Trust::System,
+    };
+    let directory = directory(&options.url, trust)?;
+    let filter = filter(options.filter.as_deref().unwrap_or(DEFAULT_FILTER))?;
+
+    let mut warnings = Vec::new();
+    let mut requests = Vec::new();
+    if let Some(dn) = &options.bind_dn {
+        let name = options.password_env.as_deref().unwrap_or_default();
+        let password = processor.var(name).ok_or_else(|| {
+            format!(
+                "Error: the password environment variable {} is not set.",
+                name
+            )
+        })?;
+        if !directory.tls {
+            warnings.push(format!(
+                "Warning: the password for {} was sent to {} unencrypted, use ldaps:// instead.",
+                dn, directory
+            ));
+        }
+        requests.push(bind(1, dn, &password));
+    }
+    requests.push(search(2, &options.base, &filter));
+    requests.push(unbind(3));
+
+    let mut certificates: Vec<NamedCertificate> = Vec::new();
+    let mut references = 0;
+    let mut done = false;
+    for response in processor.ldap(&directory, &requests, connect)? {
+        let operation = operation(&response)
+            .ok_or_else(|| format!("Error: {} sent a message that is not LDAP.", directory))?;
+        match operation.tag {
+            BIND_RESPONSE => match result(&operation) {
+                Some((0, _)) => {}
+                Some((code, diagnostic)) => {
+                    return Err(failed(&directory, "bind", code, &diagnostic).into())
+                }
+                None => {
+                    return Err(format!("Error: {} sent a bad bind response.", directory).into())
+                }
+            },
+            SEARCH_RESULT_ENTRY => {
+                let fields = operation.children().unwrap_or_default();
+                let dn = fields
+                    .first()
+                    .map(|dn| String::from_utf8_lossy(dn.contents).into_owned())
+                    .unwrap_or_default();
+                let attributes = fields
+                    .get(1)
+                    .and_then(|attributes| attributes.children())
+                    .unwrap_or_default();
+                // The same certificate comes back under each name it was asked for
+                let mut seen: Vec<&[u8]> = Vec::new();
+                for attribute in attributes {
+                    let parts = attribute.children().unwrap_or_default();
+                    let (name, values) = match (parts.first(), parts.get(1)) {
+                        (Some(name), Some(values)) => (name, values),
+                        _ => continue,
+                    };
+                    let name = format!("{} {}", dn, String::from_utf8_lossy(name.contents));
+                    for value in values.children().unwrap_or_default() {
+                        if !seen.contains(&value.contents) {
+                            seen.push(value.contents);
+                            certificates.push((name.clone(), value.contents.to_vec()));
+                        }
+                    }
+                }
+            }
+            SEARCH_RESULT_REFERENCE => references += 1,
+            SEARCH_RESULT_DONE => {
+                done = true;
+                match result(&operation) {
+                    Some((0, _)) => {}
+                    Some((code @ (3 | 4), _)) => warnings.push(format!(
+                        "Warning: {} stopped the search early, {}, so not every entry was returned.",
+                        directory,
+                        result_name(code)
+                    )),
+                    Some((code, diagnostic)) => {
+                        return Err(failed(&directory, "search", code, &diagnostic).into())
+                    }
+                    None => {
+                        let err_msg = format!("Error: {} sent a bad search result.", directory);
+                        return Err(err_msg.into());
+                    }
+                }
+            }
+            // A notice of disconnection, with why
+            EXTENDED_RESPONSE => {
+                let (code, diagnostic) = result(&operation).unwrap_or((1, String::new()));
+                return Err(failed(&directory, "connection", code, &diagnostic).into());
+            }
+            _ => {}
+        }
+    }
+    if !done {
+        let err_msg = format!("Error: {} did not finish the search.", directory);
+        return Err(err_msg.into());
+    }
+    if references > 0 {
+        warnings.push(format!(
+            "Warning: {} referred {} part(s) of the search to other servers, which were not followed.",
+            directory, references
+        ));
+    }
+    Ok(Found {
+        certificates,
+        warnings,
+    })
+}
+
+/// Sends each request to the directory and reads the responses to it, the
+/// search's entries and then its result. Used by `FileProcessor::ldap`.
+pub fn exchange(
+    directory: &Directory,
+    requests: &[Vec<u8>],
+    connect: &ConnectOptions,
+) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+    let server = Server {
+        host: directory.host.clone(),
+        port: directory.port,
+        address: None,
+    };
+    let socket = remote::connect(&server, connect)?;
+    let responses = if directory.tls {
+        let config = http::client_config(&directory.trust, None)?;
+        let name = ServerName::try_from(directory.host.clone())?;
+        let connection = ClientConnection::new(Arc::new(config), name)?;
+        converse(StreamOwned::new(connection, socket), requests)
+    } else {
+        converse(socket, requests)
+    };
+    responses.map_err(|error| format!("Error: could not search {}: {}", directory, error).into())
+}
+
+fn converse<S: Read + Write>(mut stream: S, requests: &[Vec<u8>]) -> io::Result<Vec<Vec<u8>>> {
+    let mut responses = Vec::new();
+    for request in requests {
+        stream.write_all(request)?;
+        stream.flush()?;
+        // Nothing answers an unbind, the server just closes the connection
+        if operation(request).is_some_and(|operation| operation.tag == UNBIND_REQUEST) {
+            break;
+        }
+        loop {
+            let response = read_message(&mut stream)?;
+            let last = operation(&response).is_none_or(|operation| {
+                matches!(
+                    operation.tag,
+                    BIND_RESPONSE | SEARCH_RESULT_DONE | EXTENDED_RESPONSE
+                )
+            });
+            responses.push(response);
+            if last {
+                break;
+            }
+        }
+    }
+    Ok(responses)
+}
+
+/// Reads one whole message, by the length in its header.
+fn read_message(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut message = vec![0; 2];
+    stream.read_exact(&mut message)?;
+    let len = match message[1] {
+        len if len < 0x80 => usize::from(len),
+        count if count & 0x7f <= 4 => {
+            let mut bytes = vec![0; usize::from(count & 0x7f)];
+            stream.read_exact(&mut bytes)?;
+            message.extend_from_slice(&bytes);
+            bytes
+                .iter()
+                .fold(0, |len, byte| len << 8 | usize::from(*byte))
+        }
+        _ => {
+            let err_msg = "the server sent a message too long to be LDAP";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, err_msg));
+        }
+    };
+    let start = message.len();
+    message.resize(start + len, 0);
+    stream.read_exact(&mut message[start..])?;
+    Ok(message)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    use super::{bind, directory, filter, read, read_message, search, LdapOptions};
+    use crate::cms::encode;
+    use crate::http::Trust;
+    use crate::remote::ConnectOptions;
+    use crate::test::{google_der, self_signed_der, FakeProcessor};
+
+    fn done(id: u8, code: u8, diagnostic: &str) -> Vec<u8> {
+        let result = [
+            encode(0x0a, &[code]),
+            encode(0x04, b""),
+            encode(0x04, diagnostic.as_bytes()),
+        ];
+        encode(
+            0x30,
+            &[encode(0x02, &[id]), encode(0x65, &result.concat())].concat(),
+        )
+    }
+
+    fn entry(dn: &str, attributes: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let attributes: Vec<u8> = attributes
+            .iter()
+            .flat_map(|(name, der)| {
+                let values = encode(0x31, &encode(0x04, der));
+                encode(0x30, &[encode(0x04, name.as_bytes()), values].concat())
+            })
+            .collect();
+        let entry = [encode(0x04, dn.as_bytes()), encode(0x30, &attributes)];
+        encode(
+            0x30,
+            &[encode(0x02, &[2]), encode(0x64, &entry.concat())].concat(),
+        )
+    }
+
+    fn options() -> LdapOptions {
+        LdapOptions {
+            url: String::from("ldaps://dc1.corp.example.com"),
+            base: String::from("DC=corp,DC=example,DC=com"),
+            ..LdapOptions::default()
+        }
+    }
+
+    #[test]
+    fn should_encode_filters() {
+        assert_eq!(
+            filter("(cn=alice)").unwrap(),
+            b"\xa3\x0b\x04\x02cn\x04\x05alice"
+        );
+        assert_eq!(
+            filter("(userCertificate=*)").unwrap(),
+            b"\x87\x0fuserCertificate"
+        );
+        assert_eq!(
+            filter("(&(objectClass=user)(!(cn=a*b*c)))").unwrap(),
+            [
+                &b"\xa0\x28\xa3\x13\x04\x0bobjectClass\x04\x04user"[..],
+                b"\xa2\x11\xa4\x0f\x04\x02cn\x30\x09\x80\x01a\x81\x01b\x82\x01c",
+            ]
+            .concat()
+        );
+        assert_eq!(
+            filter("(cn=Smith\\2c John \\28IT\\29)").unwrap(),
+            b"\xa3\x16\x04\x02cn\x04\x10Smith, John (IT)"
+        );
+        assert!(filter("(cn=alice").is_err());
+        assert!(filter("(cn:dn:=alice)").is_err());
+        assert!(filter("(=alice)").is_err());
+    }
+
+    #[test]
+    fn should_find_the_directory_in_a_url() {
+        let found = directory("ldaps://dc1.corp.example.com/DC=corp", Trust::System).unwrap();
+        assert_eq!(found.to_string(), "ldaps://dc1.corp.example.com:636");
+        let found = directory("ldap://[::1]:3389", Trust::System).unwrap();
+        assert_eq!((found.host.as_str(), found.port), ("::1", 3389));
+        assert!(directory("https://dc1.corp.example.com", Trust::System).is_err());
+    }
+
+    #[test]
+    fn should_read_the_certificates_in_a_directory() {
+        let mut processor = FakeProcessor::default();
+        processor.directories.insert(
+            String::from("ldaps://dc1.corp.example.com:636"),
+            vec![
+                entry(
+                    "CN=Alice,CN=Users,DC=corp,DC=example,DC=com",
+                    &[
+                        ("userCertificate", self_signed_der()),
+                        ("userCertificate;binary", self_signed_der()),
+                    ],
+                ),
+                entry(
+                    "CN=Corp CA,CN=AIA,CN=Public Key Services,DC=corp,DC=example,DC=com",
+                    &[("cACertificate", google_der())],
+                ),
+                done(2, 0, ""),
+            ],
+        );
+
+        let found = read(&processor, &options(), &ConnectOptions::default()).unwrap();
+        let names: Vec<&str> = found
+            .certificates
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "CN=Alice,CN=Users,DC=corp,DC=example,DC=com userCertificate",
+                "CN=Corp CA,CN=AIA,CN=Public Key Services,DC=corp,DC=example,DC=com cACertificate",
+            ]
+        );
+        assert!(found.warnings.is_empty());
+    }
+
+    #[test]
+    fn should_bind_with_the_password_from_the_environment() {
+        let mut processor = FakeProcessor::default();
+        let ldap = LdapOptions {
+            url: String::from("ldap://dc1.corp.example.com"),
+            bind_dn: Some(String::from("CN=svc-audit,DC=corp,DC=example,DC=com")),
+            password_env: Some(String::from("LDAP_PASSWORD")),
+            ..options()
+        };
+        assert_eq!(
+            read(&processor, &ldap, &ConnectOptions::default())
+                .err()
+                .unwrap()
+                .to_string(),
+            "Error: the password environment variable LDAP_PASSWORD is not set."
+        );
+
+        processor.vars = HashMap::from([(String::from("LDAP_PASSWORD"), String::from("hunter2"))]);
+        let refused = encode(
+            0x30,
+            &[
+                encode(0x02, &[1]),
+                encode(
+                    0x61,
+                    &[
+                        encode(0x0a, &[49]),
+                        encode(0x04, b""),
+                        encode(0x04, b"80090308: LdapErr: DSID-0C09044E"),
+                    ]
+                    .concat(),
+                ),
+            ]
+            .concat(),
+        );
+        processor.directories.insert(
+            String::from("ldap://dc1.corp.example.com:389"),
+            vec![refused],
+        );
+        assert_eq!(
+            read(&processor, &ldap, &ConnectOptions::default())
+                .err()
+                .unwrap()
+                .to_string(),
+            "Error: ldap://dc1.corp.example.com:389 refused the bind, invalidCredentials (49): 80090308: LdapErr: DSID-0C09044E"
+        );
+        let sent = processor.sent.lock().unwrap();
+        let sent = &sent["ldap://dc1.corp.example.com:389"];
+        let bind = bind(1, "CN=svc-audit,DC=corp,DC=example,DC=com", "hunter2");
+        assert!(sent.starts_with(&bind));
+        let search = search(
+            2,
+            "DC=corp,DC=example,DC=com",
+            &filter("(|(userCertificate=*)(cACertificate=*))").unwrap(),
+        );
+        assert_eq!(sent[bind.len()..bind.len() + search.len()], search[..]);
+    }
+
+    #[test]
+    fn should_warn_when_the_search_stops_early() {
+        let mut processor = FakeProcessor::default();
+        processor.directories.insert(
+            String::from("ldaps://dc1.corp.example.com:636"),
+            vec![
+                entry(
+                    "CN=Alice,DC=corp",
+                    &[("userCertificate", self_signed_der())],
+                ),
+                done(2, 4, ""),
+            ],
+        );
+        let found = read(&processor, &options(), &ConnectOptions::default()).unwrap();
+        assert_eq!(found.certificates.len(), 1);
+        assert_eq!(
+            found.warnings,
+            vec![String::from("Warning: ldaps://dc1.corp.example.com:636 stopped the search early, sizeLimitExceeded, so not every entry was returned.")]
+        );
+
+        processor.directories.insert(
+            String::from("ldaps://dc1.corp.example.com:636"),
+            vec![done(2, 32, "0000208D: NameErr: DSID-03100241")],
+        );
+        assert!(read(&processor, &options(), &ConnectOptions::default())
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("refused the search, noSuchObject (32)"));
+    }
+
+    #[test]
+    fn should_read_whole_messages() {
+        let long = done(2, 0, &"x".repeat(200));
+        let mut stream = Cursor::new([long.clone(), done(3, 0, "")].concat());
+        assert_eq!(read_message(&mut stream).unwrap(), long);
+        assert_eq!(read_message(&mut stream).unwrap(), done(3, 0, ""));
+        assert!(read_message(&mut stream).is_err());
+    }
+}
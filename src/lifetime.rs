@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::time::ASN1Time;
+
+use crate::options::parse_duration;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// The longest the CA/B Forum Baseline Requirements let a TLS server certificate be
+/// valid, from when each limit applies to certificates issued on or after it, in
+/// seconds since the epoch and days.
+const BASELINE_REQUIREMENTS: &[(i64, i64)] = &[
+    // Mar  1 2018
+    (1_519_862_400, 825),
+    // Sep  1 2020
+    (1_598_918_400, 398),
+    // Mar 15 2026
+    (1_773_532_800, 200),
+    // Mar 15 2027
+    (1_805_068_800, 100),
+    // Mar 15 2029
+    (1_868_227_200, 47),
+];
+
+/// How long a certificate may be valid, from `--max-validity`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MaxValidity {
+    /// The limit of the Baseline Requirements when the certificate was issued,
+    /// for TLS server certificates only.
+    Cabf,
+    /// A limit of one's own, for every certificate that isn't a CA.
+    Fixed(Duration),
+}
+
+impl MaxValidity {
+    /// Parses `cabf`, or a duration such as `90d`.
+    pub fn parse(value: &str) -> Option<MaxValidity> {
+        match value {
+            "cabf" => Some(MaxValidity::Cabf),
+            value => parse_duration(value).map(MaxValidity::Fixed),
+        }
+    }
+}
+
+/// Whether a certificate is valid for longer than it may be. Under the Baseline
+/// Requirements that also means longer than the next limit, when it was issued
+/// under the one in force now, so it's clear what renewals will have to fit.
+pub fn check(cert: &X509Certificate, max: &MaxValidity, now: i64) -> Option<String> {
+    let ca = matches!(cert.basic_constraints(), Ok(Some(basic)) if basic.value.ca);
+    if ca {
+        return None;
+    }
+    let validity = cert.validity();
+    let issued = validity.not_before.timestamp();
+    // The period includes both its first and last second
+    let period = validity.not_after.timestamp() - issued + 1;
+
+    let limit = match max {
+        MaxValidity::Fixed(limit) => {
+            let limit = limit.as_secs() as i64;
+            return (period > limit).then(|| {
+                format!(
+                    "is valid for {}, more than the {} given with --max-validity",
+                    describe(period),
+                    describe(limit)
+                )
+            });
+        }
+        MaxValidity::Cabf => {
+            let server_auth =
+                matches!(cert.extended_key_usage(), Ok(Some(usage)) if usage.value.server_auth);
+            if !server_auth {
+                return None;
+            }
+            BASELINE_REQUIREMENTS
+                .iter()
+                .rposition(|(from, _)| *from <= issued)?
+        }
+    };
+
+    let (from, days) = BASELINE_REQUIREMENTS[limit];
+    if period > days * DAY {
+        return Some(format!(
+            "is valid for {}, more than the {} days the CA/B Forum allows for TLS server certificates issued from {}",
+            describe(period),
+            days,
+            date(from)
+        ));
+    }
+    let current = BASELINE_REQUIREMENTS
+        .iter()
+        .rposition(|(from, _)| *from <= now);
+    let (next, days) = match (current, BASELINE_REQUIREMENTS.get(limit + 1)) {
+        (Some(current), Some(next)) if current == limit => next,
+        _ => return None,
+    };
+    (period > days * DAY).then(|| {
+        format!(
+            "is valid for {}, more than the {} days the CA/B Forum will allow for TLS server certificates issued from {}",
+            describe(period),
+            days,
+            date(*next)
+        )
+    })
+}
+
+/// A period in days, keeping the extra second of one that ends on the same time of day
+/// it started, which is a common way to overstep a limit.
+pub fn describe(seconds: i64) -> String {
+    match seconds % DAY {
+        0 => format!("{} days", seconds / DAY),
+        1 => format!("{} days and 1 second", seconds / DAY),
+        _ => format!("{} days", seconds / DAY + 1),
+    }
+}
+
+fn date(seconds: i64) -> String {
+    ASN1Time::from_timestamp(seconds)
+        .map(|time| time.to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::time::Duration;
+
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{check, MaxValidity};
+
+    // Jun  1 2026, under the 200 day limit
+    const NOW: i64 = 1_780_272_000;
+
+    #[test]
+    fn should_flag_certificates_valid_for_longer_than_the_cabf_allows() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/example-chain.pem")).unwrap();
+        let leaf = pem.parse_x509().unwrap();
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/renewal.crt")).unwrap();
+        let renewal = pem.parse_x509().unwrap();
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/google.com.crt")).unwrap();
+        let google = pem.parse_x509().unwrap();
+
+        assert_eq!(
+            check(&leaf, &MaxValidity::Cabf, NOW).as_deref(),
+            Some("is valid for 3652 days and 1 second, more than the 398 days the CA/B Forum allows for TLS server certificates issued from Sep  1 00:00:00 2020 +00:00")
+        );
+        assert_eq!(
+            check(&renewal, &MaxValidity::Cabf, NOW).as_deref(),
+            Some("is valid for 199 days and 1 second, more than the 100 days the CA/B Forum will allow for TLS server certificates issued from Mar 15 00:00:00 2027 +00:00")
+        );
+        // Issued under the 825 day limit, which it is well within
+        assert_eq!(check(&google, &MaxValidity::Cabf, NOW), None);
+    }
+
+    #[test]
+    fn should_take_a_limit_of_ones_own() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/renewal.crt")).unwrap();
+        let renewal = pem.parse_x509().unwrap();
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/example-root.crt")).unwrap();
+        let root = pem.parse_x509().unwrap();
+        let limit = MaxValidity::parse("90d").unwrap();
+
+        assert_eq!(
+            limit,
+            MaxValidity::Fixed(Duration::from_secs(90 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            check(&renewal, &limit, NOW).as_deref(),
+            Some("is valid for 199 days and 1 second, more than the 90 days given with --max-validity")
+        );
+        assert_eq!(check(&root, &limit, NOW), None);
+        assert_eq!(MaxValidity::parse("forever"), None);
+    }
+}
@@ -0,0 +1,291 @@
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+use x509_parser::num_bigint::BigUint;
+use x509_parser::objects::{oid2sn, oid_registry};
+
+use crate::name_constraints;
+
+/// Serial numbers closer than this from the same issuer look counted, since two
+/// drawn with 64 random bits are this close about once in two billion.
+const SEQUENTIAL: u64 = 1 << 32;
+
+/// Where a certificate strays from RFC 5280 and the CA/B Forum Baseline
+/// Requirements, from `--lint`.
+pub fn lint(cert: &X509Certificate) -> Vec<String> {
+    let mut issues = serial_number(cert);
+    issues.extend(duplicate_extensions(cert));
+    issues.extend(contradictions(cert));
+    issues.extend(common_name(cert));
+    issues.extend(missing_extensions(cert));
+    issues
+}
+
+/// The serial number has to be positive and at most 20 octets, and since 2016 carry
+/// at least 64 bits from a CSPRNG, which fewer than 8 octets can't hold.
+fn serial_number(cert: &X509Certificate) -> Vec<String> {
+    let raw = cert.raw_serial();
+    let mut issues = Vec::new();
+    if raw.first().is_some_and(|byte| byte & 0x80 != 0) {
+        issues.push(String::from("its serial number is negative"));
+    } else if raw.iter().all(|byte| *byte == 0) {
+        issues.push(String::from("its serial number is zero"));
+    }
+    if raw.len() > 20 {
+        issues.push(String::from("its serial number is longer than 20 octets"));
+    }
+    let significant: Vec<&u8> = raw.iter().skip_while(|byte| **byte == 0).collect();
+    if significant.len() < 8 {
+        let bits = match significant.first() {
+            Some(first) => significant.len() * 8 - first.leading_zeros() as usize,
+            None => 0,
+        };
+        issues.push(format!(
+            "its serial number has only {} bits, too few to hold the 64 random bits it should",
+            bits
+        ));
+    }
+    issues
+}
+
+/// An extension may only appear once, and which of several copies a client reads
+/// is anyone's guess.
+fn duplicate_extensions(cert: &X509Certificate) -> Vec<String> {
+    let mut seen = Vec::new();
+    let mut issues = Vec::new();
+    for extension in cert.extensions() {
+        if seen.contains(&extension.oid) {
+            continue;
+        }
+        let count = cert
+            .extensions()
+            .iter()
+            .filter(|other| other.oid == extension.oid)
+            .count();
+        if count > 1 {
+            let name = oid2sn(&extension.oid, oid_registry())
+                .map(String::from)
+                .unwrap_or_else(|_| extension.oid.to_id_string());
+            issues.push(format!("it has the {} extension {} times", name, count));
+        }
+        seen.push(extension.oid.clone());
+    }
+    issues
+}
+
+/// Extensions that say a certificate both is and isn't a CA. The first copy of
+/// each is taken, the accessors refuse duplicates.
+fn contradictions(cert: &X509Certificate) -> Vec<String> {
+    let mut basic = None;
+    let mut key_usage = None;
+    let mut name_constraints = false;
+    for extension in cert.extensions() {
+        match extension.parsed_extension() {
+            ParsedExtension::BasicConstraints(value) if basic.is_none() => basic = Some(value),
+            ParsedExtension::KeyUsage(value) if key_usage.is_none() => key_usage = Some(value),
+            ParsedExtension::NameConstraints(_) => name_constraints = true,
+            _ => {}
+        }
+    }
+    let ca = basic.is_some_and(|basic| basic.ca);
+
+    let mut issues = Vec::new();
+    match (ca, key_usage) {
+        (false, Some(usage)) if usage.key_cert_sign() => issues.push(String::from(
+            "its key usage allows signing certificates, but its basic constraints say it is not a CA",
+        )),
+        (true, Some(usage)) if !usage.key_cert_sign() => issues.push(String::from(
+            "its basic constraints say it is a CA, but its key usage does not allow signing certificates",
+        )),
+        _ => {}
+    }
+    if !ca && basic.is_some_and(|basic| basic.path_len_constraint.is_some()) {
+        issues.push(String::from(
+            "it has a path length constraint, but its basic constraints say it is not a CA",
+        ));
+    }
+    if !ca && name_constraints {
+        issues.push(String::from(
+            "it has name constraints, but its basic constraints say it is not a CA",
+        ));
+    }
+    issues
+}
+
+/// The extensions RFC 5280 requires of every CA, or of every certificate a CA
+/// issued, and those the Baseline Requirements add for server certificates.
+fn missing_extensions(cert: &X509Certificate) -> Vec<String> {
+    let has = |name: &str| {
+        cert.extensions().iter().any(|extension| {
+            oid2sn(&extension.oid, oid_registry()).is_ok_and(|known| known == name)
+        })
+    };
+    let basic = cert
+        .extensions()
+        .iter()
+        .find_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::BasicConstraints(basic) => Some((basic.ca, extension.critical)),
+            _ => None,
+        });
+    let ca = basic.is_some_and(|(ca, _)| ca);
+    let server_auth = cert.extensions().iter().any(|extension| {
+        matches!(extension.parsed_extension(), ParsedExtension::ExtendedKeyUsage(usage) if usage.server_auth)
+    });
+
+    let mut required = Vec::new();
+    if cert.subject() != cert.issuer() {
+        required.push("authorityKeyIdentifier");
+    }
+    if ca {
+        required.extend(["subjectKeyIdentifier", "keyUsage"]);
+    }
+    if server_auth && !ca {
+        required.extend(["certificatePolicies", "authorityInfoAccess"]);
+    }
+    let mut issues: Vec<String> = required
+        .into_iter()
+        .filter(|name| !has(name))
+        .map(|name| format!("it has no {} extension", name))
+        .collect();
+    if let Some((true, false)) = basic {
+        issues.push(String::from(
+            "its basicConstraints extension is not critical, though it is a CA",
+        ));
+    }
+    issues
+}
+
+/// The common name of a server certificate has to be one of its subject
+/// alternative names, which are all that clients match against now.
+fn common_name(cert: &X509Certificate) -> Vec<String> {
+    let server_auth = cert.extensions().iter().any(|extension| {
+        matches!(extension.parsed_extension(), ParsedExtension::ExtendedKeyUsage(usage) if usage.server_auth)
+    });
+    if !server_auth {
+        return Vec::new();
+    }
+    let names: Vec<String> = cert
+        .extensions()
+        .iter()
+        .filter_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::SubjectAlternativeName(san) => Some(&san.general_names),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|name| match name {
+            GeneralName::DNSName(name) => Some(name.to_lowercase()),
+            GeneralName::IPAddress(bytes) => Some(name_constraints::address(bytes)?.to_string()),
+            _ => None,
+        })
+        .collect();
+
+    let mut issues = Vec::new();
+    for common_name in cert.subject().iter_common_name() {
+        let common_name = match common_name.as_str() {
+            Ok(common_name) => common_name,
+            Err(_) => continue,
+        };
+        if names.is_empty() {
+            issues.push(format!(
+                "it has the common name {} but no subject alternative names, which clients match against",
+                common_name
+            ));
+        } else if !names.contains(&common_name.to_lowercase()) {
+            issues.push(format!(
+                "its common name {} is not among its subject alternative names",
+                common_name
+            ));
+        }
+    }
+    issues
+}
+
+/// Finds certificates from the same issuer with serial numbers so close together
+/// they look like they came from a counter, as misconfigured internal CAs give,
+/// naming the first such pair of each issuer.
+pub fn sequential(certificates: &[(&str, X509Certificate)]) -> Vec<String> {
+    let mut issuers: Vec<String> = Vec::new();
+    let mut issues = Vec::new();
+    for (_, cert) in certificates {
+        let issuer = cert.issuer().to_string();
+        if issuers.contains(&issuer) {
+            continue;
+        }
+        let mut serials: Vec<(BigUint, &str)> = certificates
+            .iter()
+            .filter(|(_, other)| other.issuer() == cert.issuer())
+            .map(|(location, other)| (other.serial.clone(), *location))
+            .collect();
+        serials.sort();
+        serials.dedup_by(|a, b| a.0 == b.0);
+        let close = serials
+            .windows(2)
+            .map(|pair| (&pair[1].0 - &pair[0].0, pair[0].1, pair[1].1))
+            .find(|(apart, _, _)| *apart < BigUint::from(SEQUENTIAL));
+        if let Some((apart, first, second)) = close {
+            issues.push(format!(
+                "{} and {}, both issued by {}, have serial numbers only {} apart, as if counted rather than random",
+                first, second, issuer, apart
+            ));
+        }
+        issuers.push(issuer);
+    }
+    issues
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{lint, sequential};
+
+    #[test]
+    fn should_flag_serial_numbers_too_short_to_be_random() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/renewal.crt")).unwrap();
+        let short = pem.parse_x509().unwrap();
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/google.com.crt")).unwrap();
+        let google = pem.parse_x509().unwrap();
+
+        assert_eq!(
+            lint(&short)[0],
+            "its serial number has only 5 bits, too few to hold the 64 random bits it should"
+        );
+        assert!(lint(&google).is_empty());
+    }
+
+    #[test]
+    fn should_flag_duplicate_and_conflicting_extensions() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/conflicting.crt")).unwrap();
+        let conflicting = pem.parse_x509().unwrap();
+
+        assert_eq!(
+            lint(&conflicting),
+            vec![
+                "it has the subjectAltName extension 2 times",
+                "its key usage allows signing certificates, but its basic constraints say it is not a CA",
+                    "its common name conflicting.example.com is not among its subject alternative names",
+                "it has no certificatePolicies extension",
+                "it has no authorityInfoAccess extension",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_flag_serial_numbers_that_look_counted() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/example-chain.pem")).unwrap();
+        let leaf = pem.parse_x509().unwrap();
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/renewal.crt")).unwrap();
+        let renewal = pem.parse_x509().unwrap();
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/google.com.crt")).unwrap();
+        let google = pem.parse_x509().unwrap();
+
+        assert_eq!(
+            sequential(&[
+                ("renewal.crt", renewal),
+                ("google.crt", google),
+                ("chain.pem", leaf)
+            ]),
+            vec!["chain.pem and renewal.crt, both issued by CN=Example Issuing CA, have serial numbers only 19 apart, as if counted rather than random"]
+        );
+    }
+}
@@ -1,12 +1,201 @@
+mod acme;
+mod android;
+mod apk;
+mod archive;
+mod attribute_certificate;
+mod authenticode;
+mod aws;
+mod azure;
+mod clipboard;
+mod cloud;
+mod cluster;
+mod cmp;
+mod cms;
+mod compliance;
+mod compression;
+mod conformance;
+mod covers;
+mod critical;
+mod crl;
+mod ct_entries;
+mod ct_monitor;
+mod ct_search;
+mod cyclonedx;
+mod dn;
+mod dns;
+mod encoding;
+mod est;
+mod expiry;
+mod explain;
+mod extensions;
+mod findings;
+mod gcp;
+mod github;
+mod gost;
+mod grade;
+mod http;
+mod idevid;
+mod idn;
+mod inventory;
+mod issuers;
+mod json;
+mod junit;
+mod jwt;
+mod k8s;
+mod keystore;
+mod kubeconfig;
+mod ldap;
+mod lifetime;
+mod lint;
+mod matter;
+mod microsoft;
+mod mta_sts;
+mod name_constraints;
+mod netscape;
+mod nss;
+mod ocsp;
+mod offline;
+mod oid;
+mod options;
+mod other_name;
+mod partial;
+mod pcsc;
+mod pem;
+mod pin;
+mod piv;
+mod pkcs11;
+mod policy;
+mod policy_file;
+mod pq;
+mod precertificate;
+mod provisioning;
+mod proxy;
+mod psl;
+mod pss;
+mod qc;
+#[cfg(feature = "quic")]
+mod quic;
+mod raw_key;
+mod remote;
+mod report;
+mod responder;
+mod sarif;
+mod scan;
+mod scep;
+mod sct;
+mod server_config;
+mod sgx;
+mod shaken;
+mod sm2;
+mod smime;
+mod sort;
+mod ssh;
+mod store;
+mod target;
+mod timestamp;
+mod tpm;
+mod trust_path;
+mod trust_store;
+mod usage;
+mod vault;
+
+use std::io::Read;
+use std::net::IpAddr;
 use std::path::Path;
-use x509_parser::parse_x509_der;
-use x509_parser::pem::pem_to_der;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use x509_parser::time::ASN1Time;
+
+use cluster::ClusterCommand;
+use findings::OutputFormat;
+use inventory::InventoryCommand;
+use kubeconfig::Kubeconfig;
+use ldap::Directory;
+use ocsp::OcspCommand;
+use offline::Offline;
+use options::Options;
+use pcsc::Exchange;
+use pkcs11::Pkcs11Options;
+use policy_file::PolicyFile;
+use remote::{ConnectOptions, Family, Handshake, Server};
+use report::Report;
+use store::{NamedCertificate, StoreOptions};
 
 /// This trait helps abstract away IO operations.
 /// It allows a fake implementation to be used in testing.
-trait FileProcessor {
+/// It must be `Sync` as servers are contacted from several threads at once.
+trait FileProcessor: Sync {
     fn is_file(&self, path: &str) -> bool;
+    fn is_dir(&self, path: &str) -> bool;
+    fn read(&self, path: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
     fn read_to_string(&self, path: &str) -> Result<String, Box<dyn std::error::Error>>;
+    fn read_stdin(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+    fn read_clipboard(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+    fn write_clipboard(&self, text: &str) -> Result<(), Box<dyn std::error::Error>>;
+    fn write(&self, path: &str, contents: &[u8]) -> Result<(), Box<dyn std::error::Error>>;
+    /// Lists the paths of the entries in a directory, sorted so output is stable.
+    fn read_dir(&self, path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>>;
+    /// Returns the value of an environment variable, if set.
+    fn var(&self, name: &str) -> Option<String>;
+    /// Looks up the addresses of a host, keeping only those of `family` if given.
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        family: Option<Family>,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error>>;
+    /// Performs a TLS handshake, returning the chain the server presented and what was negotiated.
+    fn handshake(
+        &self,
+        server: &Server,
+        options: &ConnectOptions,
+    ) -> Result<Handshake, Box<dyn std::error::Error>>;
+    /// Reads every certificate in a Windows system store.
+    fn read_store(&self, store: &StoreOptions) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>>;
+    /// Reads every certificate in an NSS database directory, with its nickname.
+    fn read_nss(&self, dir: &str) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>>;
+    /// The current time in seconds since the Unix epoch, so tests can pin it.
+    fn now(&self) -> i64;
+    /// Reads every certificate in a macOS keychain, with its label.
+    fn read_keychain(
+        &self,
+        keychain: &str,
+    ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>>;
+    /// Reads every certificate object on a PKCS#11 token, with its label.
+    fn read_pkcs11(
+        &self,
+        token: &Pkcs11Options,
+        pin: Option<&str>,
+    ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>>;
+    /// Sends APDUs to the card in a smart card reader in one transaction,
+    /// returning the reader's name and each response with its status word.
+    fn transmit(
+        &self,
+        reader: Option<&str>,
+        commands: &[Vec<u8>],
+    ) -> Result<Exchange, Box<dyn std::error::Error>>;
+    /// Sends LDAP requests to a directory, returning every message it answered with.
+    fn ldap(
+        &self,
+        directory: &Directory,
+        requests: &[Vec<u8>],
+        options: &ConnectOptions,
+    ) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>>;
+    /// Makes an HTTP request, such as to a Kubernetes API server.
+    fn http(
+        &self,
+        request: &http::Request,
+        options: &ConnectOptions,
+    ) -> Result<http::Response, Box<dyn std::error::Error>>;
+    /// Runs a program with extra environment variables, returning what it printed.
+    fn run(
+        &self,
+        program: &str,
+        args: &[String],
+        env: &[(String, String)],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
 }
 
 /// The "real" version of the FileProcessor
@@ -16,37 +205,389 @@ impl FileProcessor for CertProcessor {
     fn is_file(&self, path: &str) -> bool {
         Path::new(path).is_file()
     }
+    fn is_dir(&self, path: &str) -> bool {
+        Path::new(path).is_dir()
+    }
+    fn read(&self, path: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let bytes = std::fs::read(path)?;
+        Ok(bytes)
+    }
     fn read_to_string(&self, path: &str) -> Result<String, Box<dyn std::error::Error>> {
         let path_str = std::fs::read_to_string(path)?;
         Ok(path_str)
     }
+    fn read_stdin(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+    fn write(&self, path: &str, contents: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, contents)
+            .map_err(|error| format!("Error: could not write {}: {}.", path, error))?;
+        Ok(())
+    }
+    fn read_clipboard(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        clipboard::read()
+    }
+    fn write_clipboard(&self, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+        clipboard::write(text)
+    }
+    fn read_dir(&self, path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            paths.push(entry?.path().to_string_lossy().into_owned());
+        }
+        paths.sort();
+        Ok(paths)
+    }
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        family: Option<Family>,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error>> {
+        let addresses = remote::resolve(host, port, family)?;
+        Ok(addresses)
+    }
+    fn handshake(
+        &self,
+        server: &Server,
+        options: &ConnectOptions,
+    ) -> Result<Handshake, Box<dyn std::error::Error>> {
+        remote::handshake(server, options)
+    }
+    fn read_store(&self, store: &StoreOptions) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+        store::read(store)
+    }
+    fn read_keychain(
+        &self,
+        keychain: &str,
+    ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        store::read_keychain(keychain)
+    }
+    fn read_pkcs11(
+        &self,
+        token: &Pkcs11Options,
+        pin: Option<&str>,
+    ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        pkcs11::read(token, pin)
+    }
+    fn transmit(
+        &self,
+        reader: Option<&str>,
+        commands: &[Vec<u8>],
+    ) -> Result<Exchange, Box<dyn std::error::Error>> {
+        pcsc::transmit(reader, commands)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+    fn read_nss(&self, dir: &str) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        nss::read(dir)
+    }
+    fn now(&self) -> i64 {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        since_epoch.as_secs() as i64
+    }
+    fn ldap(
+        &self,
+        directory: &Directory,
+        requests: &[Vec<u8>],
+        options: &ConnectOptions,
+    ) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+        ldap::exchange(directory, requests, options)
+    }
+    fn http(
+        &self,
+        request: &http::Request,
+        options: &ConnectOptions,
+    ) -> Result<http::Response, Box<dyn std::error::Error>> {
+        http::send(request, options)
+    }
+    fn run(
+        &self,
+        program: &str,
+        args: &[String],
+        env: &[(String, String)],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        // stderr is left alone so a plugin can tell the user why it failed
+        let output = Command::new(program)
+            .args(args)
+            .envs(env.iter().map(|(name, value)| (name, value)))
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|error| format!("Error: could not run {}: {}", program, error))?;
+        if !output.status.success() {
+            return Err(format!("Error: {} failed, {}.", program, output.status).into());
+        }
+        Ok(output.stdout)
+    }
 }
 
 fn execute(
     processor: impl FileProcessor,
     args: Vec<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Check args length
-    if args.len() != 1 {
-        let err_msg = String::from("Error: did not receive a single argument, please invoke cert-decoder as follows: ./cert-decoder /path/to/cert.");
-        return Err(err_msg.into());
+    let mut options = Options::parse(args)?;
+    let processor = Offline {
+        processor,
+        enabled: options.offline,
+    };
+    proxy_from_env(&processor, &mut options)?;
+    if let (Some(cert), Some(key)) = (&options.client_cert, &options.client_key) {
+        let identity = remote::load_identity(&processor.read(cert)?, &processor.read(key)?)?;
+        options.connect.identity = Some(identity);
+    }
+    if let Some(content) = &options.content {
+        options.detached_content = Some(processor.read(content)?);
+    }
+    if let Some(log_list) = &options.ct_log_list {
+        options.ct_logs = Some(sct::log_list(&processor.read(log_list)?)?);
+    }
+    if options.explain_trust || options.find_anchor || !options.required_policies.is_empty() {
+        options.anchors = Some(trust_path::load(&processor, &options.trust_anchors)?);
+    }
+    if let Some(policy_file) = &options.policy_file {
+        let text = processor.read_to_string(policy_file)?;
+        options.policy = Some(PolicyFile::parse(policy_file, &text)?);
+    }
+    if options.domains || !options.covers.is_empty() {
+        let path = options.public_suffix_list.as_deref();
+        options.suffixes = Some(psl::load(&processor, path)?);
+    }
+    if let Some(known_hosts) = &options.known_hosts {
+        options.host_keys.known_hosts = Some(processor.read_to_string(known_hosts)?);
+    }
+    if let Some(host) = &options.sshfp {
+        let found = dns::lookup(&processor, &options, host, "SSHFP")?;
+        let records = ssh::parse_sshfp(&found.text);
+        options.host_keys.sshfp = Some((host.clone(), records, found.validated));
+    }
+    // A trust store audit is a summary of the roots in the system CA bundle
+    let bundle = match &options.trust_store {
+        Some(trust_store) => Some(trust_store::locate(&processor, trust_store)?),
+        None => None,
+    };
+    options.targets.extend(bundle.clone());
+
+    if let Some(cluster) = &options.cluster {
+        let (heading, mut report) = match cluster.command {
+            ClusterCommand::Scan => {
+                let scan = cluster::scan(&processor, cluster, &options.connect)?;
+                (format!("Cluster: {}", scan.cluster), scan.report)
+            }
+            ClusterCommand::Kubeconfig => {
+                let config = Kubeconfig::load(&processor, cluster.kubeconfig.as_deref())?;
+                let report = config.inspect(&processor, cluster.context.as_deref())?;
+                (format!("Kubeconfig: {}", config.path), report)
+            }
+        };
+        if report.entries.is_empty() && report.failures.len() == 1 {
+            return Err(report.failures.remove(0).error);
+        }
+        if let Some(max) = &options.max_validity {
+            report.check_validity_periods(max, processor.now());
+        }
+        print!(
+            "{}\n{}",
+            heading,
+            expiry::Expiry::new(&report, processor.now(), cluster.expiring_within)
+        );
+        return report.status();
+    }
+
+    if let Some(inventory) = &options.inventory {
+        let targets = target::collect(&processor, &options)?;
+        let report = Report::build(&processor, &targets, &options);
+        let records = inventory::records(&report);
+        match inventory.command {
+            InventoryCommand::Save => {
+                let snapshot = inventory::save(&records, processor.now());
+                processor.write(&inventory.snapshot, snapshot.as_bytes())?;
+                println!(
+                    "Saved {} certificates to {}.",
+                    records.len(),
+                    inventory.snapshot
+                );
+            }
+            InventoryCommand::Diff => {
+                let bytes = processor.read(&inventory.snapshot)?;
+                let (taken, before) = inventory::load(&inventory.snapshot, &bytes)?;
+                let taken = ASN1Time::from_timestamp(taken)
+                    .map(|taken| taken.to_string())
+                    .unwrap_or_default();
+                print!(
+                    "Inventory: {}, taken {}\n{}",
+                    inventory.snapshot,
+                    taken,
+                    inventory::Drift::new(&before, &records)
+                );
+            }
+        }
+        for failure in &report.failures {
+            println!("==> {} <==\n{}", failure.location, failure.error);
+        }
+        return report.status();
+    }
+
+    if let Some(ct_search) = &options.ct_search {
+        let search = ct_search::search(&processor, ct_search, &options.connect)?;
+        print!("{}", search);
+        return search.report.status();
+    }
+
+    if let Some(target) = &options.acme {
+        let acme = acme::inspect(&processor, target, &options.connect)?;
+        print!("{}", acme);
+        return acme.report.status();
+    }
+
+    if let Some(target) = &options.est {
+        let est = est::cacerts(&processor, target, &options.connect)?;
+        print!("{}", est);
+        return est.report.status();
+    }
+
+    if let Some(domain) = &options.mta_sts {
+        let mta_sts = mta_sts::check(&processor, domain, &options)?;
+        print!("{}", mta_sts);
+        return mta_sts.report.status();
+    }
+
+    if let Some(vault) = &options.vault {
+        let vault = vault::fetch(&processor, vault, &options.connect)?;
+        print!("{}", vault);
+        return vault.report.status();
+    }
+
+    if let Some(piv) = &options.piv {
+        let piv = piv::inspect(&processor, piv)?;
+        print!("{}", piv);
+        return piv.report.status();
+    }
+
+    if let Some(target) = &options.scep {
+        let scep = scep::get_ca_cert(&processor, target, &options.connect)?;
+        print!("{}", scep);
+        return scep.report.status();
     }
 
-    let path = &args[0];
+    if let Some(ocsp) = &options.ocsp {
+        match ocsp.command {
+            OcspCommand::Request => print!("{}", ocsp::build(&processor, ocsp)?),
+            OcspCommand::Test => {
+                let conformance = responder::test(&processor, ocsp, &options.connect)?;
+                print!("{}", conformance);
+                return conformance.status();
+            }
+        }
+        return Ok(());
+    }
 
-    // Check if arg is a file
-    if !processor.is_file(path) {
-        let err_msg = String::from("Error: path given as argument is not a regular file, it must be a path to a certificate!");
-        return Err(err_msg.into());
+    if let Some(ct_monitor) = &options.ct_monitor {
+        loop {
+            match ct_monitor::check(&processor, ct_monitor, &options.connect) {
+                Ok(check) => print!("{}", check),
+                // A check that fails while watching is tried again at the next one
+                Err(error) if ct_monitor.interval.is_some() => eprintln!("{}", error),
+                Err(error) => return Err(error),
+            }
+            match ct_monitor.interval {
+                Some(interval) => thread::sleep(interval),
+                None => return Ok(()),
+            }
+        }
     }
 
-    // Convert pem file to der file then parse it
-    let cert = processor.read_to_string(path)?;
-    let (_, pem) = pem_to_der(cert.as_bytes())?;
-    let (_, parsed_cert) = parse_x509_der(&pem.contents)?;
-    let output = format!("{:#?}", parsed_cert.tbs_certificate);
+    let targets = target::collect(&processor, &options)?;
+    let mut report = Report::build(&processor, &targets, &options);
+
+    // What is written for tools includes a lone failure like any other
+    if options.output != OutputFormat::Text {
+        let findings = findings::collect(&report, processor.now());
+        match options.output {
+            OutputFormat::Sarif => print!("{}", sarif::to_string(&findings)),
+            OutputFormat::Junit => print!("{}", junit::to_string(&report, &findings)),
+            OutputFormat::Github => print!("{}", github::annotations(&processor, &findings)),
+            OutputFormat::Cyclonedx => {
+                print!("{}", cyclonedx::to_string(&report, processor.now()))
+            }
+            OutputFormat::Text => {}
+        }
+        return report.status();
+    }
 
-    println!("{}", output);
+    if let (Some(path), Some(policy)) = (&options.policy_file, &options.policy) {
+        let checks = policy_file::Checks::new(&report, path, policy);
+        print!("{}", checks);
+        report.status()?;
+        return checks.status();
+    }
+
+    if options.grade {
+        print!("{}", grade::Grades::new(&report, processor.now()));
+        return report.status();
+    }
+
+    // A lone failure is just an error, there is nothing to report alongside it
+    if report.entries.is_empty() && report.failures.len() == 1 {
+        return Err(report.failures.remove(0).error);
+    }
+
+    match (&bundle, &options.trust_store) {
+        (Some(bundle), Some(trust_store)) => print!(
+            "Trust store: {}\n{}",
+            bundle,
+            trust_store::Summary::new(&report, processor.now(), trust_store)
+        ),
+        _ if options.explain => print!("{}", explain::annotate(&report.to_string())),
+        _ => print!("{}", report),
+    }
+    if let Some((name, text)) = options.copy.and_then(|copy| copy.text(&report.entries)) {
+        processor.write_clipboard(&text)?;
+        println!("Copied the {} to the clipboard.", name);
+    }
+    if options.expect.is_set() {
+        for met in pin::verify(&report, &options.expect)? {
+            println!("{}", met);
+        }
+    }
+
+    report.status()
+}
+
+/// Picks up `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` (or their
+/// lowercase forms) like other tools do, each URL scheme taking its own proxy
+/// and `ALL_PROXY` standing in for whichever is not set.
+fn proxy_from_env(
+    processor: &impl FileProcessor,
+    options: &mut Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let var = |name: &str| {
+        processor
+            .var(name)
+            .or_else(|| processor.var(&name.to_lowercase()))
+            .filter(|value| !value.is_empty())
+    };
+    let proxy = |name: &str| {
+        var(name)
+            .or_else(|| var("ALL_PROXY"))
+            .map(|url| proxy::Proxy::parse(&url))
+            .transpose()
+    };
+
+    if options.connect.proxy.is_none() {
+        options.connect.proxy = proxy("HTTPS_PROXY")?;
+    }
+    if options.connect.http_proxy.is_none() {
+        options.connect.http_proxy = proxy("HTTP_PROXY")?;
+    }
+    if let Some(no_proxy) = var("NO_PROXY") {
+        options.connect.no_proxy = proxy::parse_no_proxy(&no_proxy);
+    }
 
     Ok(())
 }
@@ -54,29 +595,283 @@ fn execute(
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = std::env::args().skip(1).collect();
     let processor = CertProcessor;
-    execute(processor, args)
+    match execute(processor, args) {
+        // Deployment scripts tell a certificate that isn't the one expected from other errors
+        Err(error) if error.is::<pin::Mismatch>() => {
+            eprintln!("{}", error);
+            std::process::exit(pin::MISMATCH_STATUS);
+        }
+        result => result,
+    }
 }
 
 #[cfg(test)]
 mod test {
 
-    use crate::{execute, FileProcessor};
+    use std::collections::HashMap;
+    use std::net::IpAddr;
+    use std::sync::{Arc, Mutex};
+
+    use crate::cms::hex;
+    use crate::http::{Request, Response};
+    use crate::ldap::Directory;
+    use crate::options::Options;
+    use crate::pcsc::Exchange;
+    use crate::pkcs11::Pkcs11Options;
+    use crate::remote::{ConnectOptions, Family, Handshake, Server, Session};
+    use crate::store::{NamedCertificate, StoreOptions};
+    use crate::{execute, proxy_from_env, FileProcessor};
 
     // deriving default gives a basic implementation of the struct with default fields
     // i.e. false for bool and "" for String
+    // `is_file` and `file_str` apply to every path, the maps let a test lay out
+    // specific files, directories and servers when it needs more than one
     #[derive(Default)]
-    struct FakeProcessor {
-        is_file: bool,
-        file_str: String,
+    pub struct FakeProcessor {
+        pub is_file: bool,
+        pub file_str: String,
+        pub files: HashMap<String, String>,
+        pub binaries: HashMap<String, Vec<u8>>,
+        pub stdin: String,
+        pub clipboard: String,
+        /// What was copied to the clipboard, if anything.
+        pub copied: Arc<Mutex<Option<String>>>,
+        /// The files written, by path.
+        pub written: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+        pub dirs: HashMap<String, Vec<String>>,
+        pub remotes: HashMap<String, Handshake>,
+        pub addresses: HashMap<String, Vec<IpAddr>>,
+        pub vars: HashMap<String, String>,
+        pub stores: HashMap<String, Vec<Vec<u8>>>,
+        pub keychains: HashMap<String, Vec<NamedCertificate>>,
+        pub nss: HashMap<String, Vec<NamedCertificate>>,
+        /// The certificates on PKCS#11 tokens, by module and slot.
+        pub tokens: HashMap<String, Vec<NamedCertificate>>,
+        /// What a smart card answers each APDU with, by the command in hex.
+        pub apdus: HashMap<String, Vec<u8>>,
+        /// The messages each LDAP directory answers with, by its URL.
+        pub directories: HashMap<String, Vec<Vec<u8>>>,
+        pub now: i64,
+        /// Responses by URL, or by URL and body for APIs that take every
+        /// request at one URL.
+        pub responses: HashMap<String, Response>,
+        /// The bodies of requests other than GETs, and the LDAP requests sent to a
+        /// directory, by URL.
+        pub sent: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+        /// What each program prints when run, by the program, or by the program
+        /// and its arguments for programs run several ways.
+        pub commands: HashMap<String, String>,
+    }
+
+    impl FakeProcessor {
+        pub fn add_file(&mut self, path: &str, contents: &str) {
+            self.files
+                .insert(String::from(path), String::from(contents));
+        }
+        pub fn add_dir(&mut self, path: &str, entries: &[&str]) {
+            let entries = entries.iter().map(|entry| String::from(*entry)).collect();
+            self.dirs.insert(String::from(path), entries);
+        }
+        pub fn add_remote(&mut self, target: &str, chain: Vec<Vec<u8>>) {
+            let session = Session {
+                version: String::from("TLS 1.3"),
+                cipher_suite: String::from("TLS13_AES_128_GCM_SHA256"),
+                alpn: Some(String::from("h2")),
+                client_cert_requested: false,
+                client_cert_sent: false,
+                client_ca_names: Vec::new(),
+            };
+            let handshake = Handshake { chain, session };
+            self.remotes.insert(String::from(target), handshake);
+        }
+        pub fn add_addresses(&mut self, host: &str, addresses: &[IpAddr]) {
+            self.addresses
+                .insert(String::from(host), addresses.to_vec());
+        }
     }
 
     impl FileProcessor for FakeProcessor {
-        fn is_file(&self, _: &str) -> bool {
-            self.is_file
+        fn is_file(&self, path: &str) -> bool {
+            self.is_file || self.files.contains_key(path) || self.binaries.contains_key(path)
+        }
+        fn is_dir(&self, path: &str) -> bool {
+            self.dirs.contains_key(path)
+        }
+        fn read(&self, path: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+            if let Some(bytes) = self.binaries.get(path) {
+                return Ok(bytes.clone());
+            }
+            Ok(self.read_to_string(path)?.into_bytes())
+        }
+        fn read_to_string(&self, path: &str) -> Result<String, Box<dyn std::error::Error>> {
+            Ok(self.files.get(path).unwrap_or(&self.file_str).clone())
+        }
+        fn read_stdin(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+            Ok(self.stdin.clone().into_bytes())
+        }
+        fn write(&self, path: &str, contents: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+            let mut written = self.written.lock().unwrap();
+            written.insert(String::from(path), contents.to_vec());
+            Ok(())
+        }
+        fn read_clipboard(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+            Ok(self.clipboard.clone().into_bytes())
+        }
+        fn write_clipboard(&self, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+            *self.copied.lock().unwrap() = Some(String::from(text));
+            Ok(())
+        }
+        fn read_dir(&self, path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            Ok(self.dirs.get(path).cloned().unwrap_or_default())
+        }
+        fn resolve(
+            &self,
+            host: &str,
+            _: u16,
+            family: Option<Family>,
+        ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error>> {
+            let addresses = self.addresses.get(host).cloned().unwrap_or_default();
+            Ok(addresses
+                .into_iter()
+                .filter(|address| family.is_none_or(|family| family.matches(address)))
+                .collect())
+        }
+        fn handshake(
+            &self,
+            server: &Server,
+            _: &ConnectOptions,
+        ) -> Result<Handshake, Box<dyn std::error::Error>> {
+            let target = server.to_string();
+            match self.remotes.get(&target) {
+                Some(handshake) => Ok(handshake.clone()),
+                None => Err(format!("Error: could not connect to {}", target).into()),
+            }
         }
-        fn read_to_string(&self, _: &str) -> Result<String, Box<dyn std::error::Error>> {
-            Ok(self.file_str.clone())
+        fn var(&self, name: &str) -> Option<String> {
+            self.vars.get(name).cloned()
         }
+        fn read_nss(&self, dir: &str) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+            match self.nss.get(dir) {
+                Some(certificates) => Ok(certificates.clone()),
+                None => Err(format!("Error: could not read the NSS database {}", dir).into()),
+            }
+        }
+        fn now(&self) -> i64 {
+            self.now
+        }
+        fn read_store(
+            &self,
+            store: &StoreOptions,
+        ) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+            match self.stores.get(&store.to_string()) {
+                Some(certificates) => Ok(certificates.clone()),
+                None => {
+                    Err(format!("Error: could not open the {} certificate store", store).into())
+                }
+            }
+        }
+        fn read_keychain(
+            &self,
+            keychain: &str,
+        ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+            match self.keychains.get(keychain) {
+                Some(certificates) => Ok(certificates.clone()),
+                None => Err(format!("Error: could not open the {} keychain", keychain).into()),
+            }
+        }
+        fn read_pkcs11(
+            &self,
+            token: &Pkcs11Options,
+            _: Option<&str>,
+        ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+            match self.tokens.get(&token.to_string()) {
+                Some(certificates) => Ok(certificates.clone()),
+                None => Err(format!("Error: could not load {}", token).into()),
+            }
+        }
+        fn transmit(
+            &self,
+            reader: Option<&str>,
+            commands: &[Vec<u8>],
+        ) -> Result<Exchange, Box<dyn std::error::Error>> {
+            if self.apdus.is_empty() {
+                return Err(String::from("Error: no smart card reader was found.").into());
+            }
+            let reader = reader.unwrap_or("Yubico YubiKey OTP+FIDO+CCID 00 00");
+            // Commands the card doesn't know get "file or application not found"
+            let responses = commands
+                .iter()
+                .map(|command| match self.apdus.get(&hex(command)) {
+                    Some(response) => response.clone(),
+                    None => vec![0x6a, 0x82],
+                })
+                .collect();
+            Ok((String::from(reader), responses))
+        }
+        fn ldap(
+            &self,
+            directory: &Directory,
+            requests: &[Vec<u8>],
+            _: &ConnectOptions,
+        ) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+            let url = directory.to_string();
+            let mut sent = self.sent.lock().unwrap();
+            sent.insert(url.clone(), requests.concat());
+            match self.directories.get(&url) {
+                Some(responses) => Ok(responses.clone()),
+                None => Err(format!("Error: could not connect to {}", url).into()),
+            }
+        }
+        fn http(
+            &self,
+            request: &Request,
+            _: &ConnectOptions,
+        ) -> Result<Response, Box<dyn std::error::Error>> {
+            let url = request.url.to_string();
+            if request.method != "GET" {
+                let mut sent = self.sent.lock().unwrap();
+                sent.insert(url.clone(), request.body.clone());
+            }
+            let keyed = format!("{} {}", url, String::from_utf8_lossy(&request.body));
+            match self
+                .responses
+                .get(&keyed)
+                .or_else(|| self.responses.get(&url))
+            {
+                Some(response) => Ok(response.clone()),
+                None => Err(format!("Error: could not connect to {}", url).into()),
+            }
+        }
+        fn run(
+            &self,
+            program: &str,
+            args: &[String],
+            _: &[(String, String)],
+        ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+            let keyed = format!("{} {}", program, args.join(" "));
+            match self
+                .commands
+                .get(&keyed)
+                .or_else(|| self.commands.get(program))
+            {
+                Some(output) => Ok(output.clone().into_bytes()),
+                None => Err(format!("Error: could not run {}", program).into()),
+            }
+        }
+    }
+
+    /// The DER encoding of the google.com certificate in resources.
+    pub fn google_der() -> Vec<u8> {
+        let cert = include_str!("../resources/google.com.crt");
+        let (_, pem) = x509_parser::pem::parse_x509_pem(cert.as_bytes()).unwrap();
+        pem.contents
+    }
+
+    /// The DER encoding of the self-signed certificate in resources.
+    pub fn self_signed_der() -> Vec<u8> {
+        let cert = include_str!("../resources/self-signed.crt");
+        let (_, pem) = x509_parser::pem::parse_x509_pem(cert.as_bytes()).unwrap();
+        pem.contents
     }
 
     #[test]
@@ -129,6 +924,7 @@ mod test {
         let processor = FakeProcessor {
             is_file: true,
             file_str: String::from(cert),
+            ..FakeProcessor::default()
         };
 
         let result = execute(processor, args);
@@ -143,10 +939,167 @@ mod test {
         let processor = FakeProcessor {
             is_file: true,
             file_str: String::from(cert),
+            ..FakeProcessor::default()
         };
 
         let result = execute(processor, args);
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn should_succeed_with_targets_file_covering_files_directories_and_servers() {
+        let cert = include_str!("../resources/google.com.crt");
+        let mut processor = FakeProcessor::default();
+        processor.add_file("targets.txt", "google.crt\ncerts\ngoogle.com:443\n");
+        processor.add_file("google.crt", cert);
+        processor.add_dir("certs", &["certs/notes.txt", "certs/nested"]);
+        processor.add_file("certs/notes.txt", "not a certificate");
+        processor.add_dir("certs/nested", &["certs/nested/chain.pem"]);
+        processor.add_file("certs/nested/chain.pem", &format!("{}\n{}", cert, cert));
+        processor.add_remote("google.com:443", vec![google_der(), google_der()]);
+        let args = vec![String::from("--targets"), String::from("targets.txt")];
+
+        let result = execute(processor, args);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn should_error_if_any_target_fails_to_decode() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("targets.txt", "google.crt\nbad.crt\n");
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        processor.add_file("bad.crt", include_str!("../resources/bad.crt"));
+        let args = vec![String::from("--targets"), String::from("targets.txt")];
+
+        let result = execute(processor, args);
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: 1 location(s) could not be decoded, see the report above."
+        );
+    }
+
+    #[test]
+    fn should_error_if_https_proxy_is_not_a_valid_url() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        processor
+            .vars
+            .insert(String::from("https_proxy"), String::from("gopher://proxy"));
+        let args = vec![String::from("google.crt")];
+
+        let result = execute(processor, args);
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: gopher://proxy is not a valid proxy URL."
+        );
+    }
+
+    #[test]
+    fn should_pick_the_proxy_for_each_scheme_from_the_environment() {
+        let mut processor = FakeProcessor::default();
+        processor
+            .vars
+            .insert(String::from("http_proxy"), String::from("http://web:3128"));
+        processor.vars.insert(
+            String::from("ALL_PROXY"),
+            String::from("socks5h://all:1080"),
+        );
+        let mut options = Options::default();
+
+        proxy_from_env(&processor, &mut options).unwrap();
+
+        assert_eq!(options.connect.http_proxy.unwrap().host, "web");
+        assert_eq!(options.connect.proxy.unwrap().host, "all");
+    }
+
+    #[test]
+    fn should_decode_hex_from_stdin_when_told_to() {
+        let hex: String = google_der().iter().map(|b| format!("{:02X} ", b)).collect();
+        let processor = FakeProcessor {
+            stdin: hex,
+            ..FakeProcessor::default()
+        };
+        let args = vec![
+            String::from("--input-encoding"),
+            String::from("hex"),
+            String::from("-"),
+        ];
+
+        let result = execute(processor, args);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn should_decode_a_kubernetes_secret_from_stdin() {
+        let manifest = "kind: Secret\nmetadata:\n  name: web\nstringData:\n  tls.crt: |\n";
+        let cert = include_str!("../resources/self-signed.crt").replace('\n', "\n    ");
+        let processor = FakeProcessor {
+            stdin: format!("{}    {}", manifest, cert),
+            ..FakeProcessor::default()
+        };
+        let args = vec![String::from("--k8s"), String::from("-")];
+
+        let result = execute(processor, args);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn should_decode_the_clipboard_and_copy_the_fingerprint_back() {
+        let paste = include_str!("../resources/google.com.crt").replace('\n', "\n> ");
+        let processor = FakeProcessor {
+            clipboard: format!("> {}", paste),
+            ..FakeProcessor::default()
+        };
+        let copied = Arc::clone(&processor.copied);
+        let args = vec![
+            String::from("--clipboard"),
+            String::from("--copy"),
+            String::from("fingerprint"),
+        ];
+
+        let result = execute(processor, args);
+
+        assert!(result.is_ok());
+        assert_eq!(
+            copied.lock().unwrap().as_deref(),
+            Some(crate::trust_path::fingerprint(&google_der()).as_str())
+        );
+    }
+
+    #[test]
+    fn should_save_an_inventory_and_diff_against_it() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        let written = Arc::clone(&processor.written);
+        let args = vec![
+            String::from("inventory"),
+            String::from("save"),
+            String::from("inventory.json"),
+            String::from("google.crt"),
+        ];
+
+        let saved = execute(processor, args);
+        let snapshot = written.lock().unwrap().remove("inventory.json").unwrap();
+        let mut processor = FakeProcessor::default();
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        processor
+            .binaries
+            .insert(String::from("inventory.json"), snapshot);
+        let args = vec![
+            String::from("inventory"),
+            String::from("diff"),
+            String::from("inventory.json"),
+            String::from("google.crt"),
+        ];
+        let diffed = execute(processor, args);
+
+        assert!(saved.is_ok());
+        assert!(diffed.is_ok());
+    }
 }
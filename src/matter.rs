@@ -0,0 +1,274 @@
+use x509_parser::certificate::X509Certificate;
+
+use crate::extensions::Field;
+
+/// The subject attributes Matter identifies vendors and products by.
+const VENDOR_ID: &str = "1.3.6.1.4.1.37244.2.1";
+const PRODUCT_ID: &str = "1.3.6.1.4.1.37244.2.2";
+
+/// Vendor IDs the CSA set aside for testing, which production devices must not use.
+const TEST_VENDOR_IDS: std::ops::RangeInclusive<u16> = 0xfff1..=0xfff4;
+
+const ECDSA_WITH_SHA256: &str = "1.2.840.10045.4.3.2";
+const EC_PUBLIC_KEY: &str = "1.2.840.10045.2.1";
+const PRIME256V1: &str = "1.2.840.10045.3.1.7";
+
+/// Where a certificate sits in a Matter device attestation chain.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    /// Product attestation authority, the root.
+    Paa,
+    /// Product attestation intermediate, which issues the DACs of a vendor.
+    Pai,
+    /// Device attestation certificate, one per device.
+    Dac,
+}
+
+/// The vendor and product IDs of a certificate, and whether they came from the
+/// common name as the first Matter certificates carried them.
+struct Ids {
+    vendor: Option<String>,
+    product: Option<String>,
+    legacy: bool,
+}
+
+/// Decodes the VID and PID of a Matter device attestation certificate. Other
+/// certificates, including PAAs that leave the VID out, are left alone.
+pub fn attestation(cert: &X509Certificate) -> Option<Vec<Field>> {
+    let ids = ids(cert)?;
+    let mut fields = vec![Field::new("Certificate type", kind(cert).name())];
+    if let Some(vendor) = &ids.vendor {
+        let test = u16::from_str_radix(vendor, 16).is_ok_and(|id| TEST_VENDOR_IDS.contains(&id));
+        fields.push(Field::new(
+            "Vendor ID",
+            match test {
+                true => format!("0x{} (test vendor)", vendor),
+                false => format!("0x{}", vendor),
+            },
+        ));
+    }
+    if let Some(product) = &ids.product {
+        fields.push(Field::new("Product ID", format!("0x{}", product)));
+    }
+    if ids.legacy {
+        fields.push(Field::new("IDs given in", "the common name"));
+    }
+    Some(fields)
+}
+
+/// Where a Matter attestation certificate strays from the certificate profile of
+/// the Matter specification, which devices and commissioners enforce.
+pub fn lint(cert: &X509Certificate) -> Vec<String> {
+    let ids = match ids(cert) {
+        Some(ids) => ids,
+        None => return Vec::new(),
+    };
+    let kind = kind(cert);
+    let mut issues = Vec::new();
+
+    if cert.version().0 != 2 {
+        issues.push(String::from("it is not a version 3 certificate"));
+    }
+    if cert.raw_serial().len() > 20 {
+        issues.push(String::from("its serial number is longer than 20 octets"));
+    }
+    if cert.signature_algorithm.algorithm.to_id_string() != ECDSA_WITH_SHA256 {
+        issues.push(String::from("it is not signed with ecdsa-with-SHA256"));
+    }
+    let key = &cert.public_key().algorithm;
+    let curve = key
+        .parameters
+        .as_ref()
+        .and_then(|parameters| parameters.as_oid().ok());
+    if key.algorithm.to_id_string() != EC_PUBLIC_KEY
+        || curve.is_none_or(|curve| curve.to_id_string() != PRIME256V1)
+    {
+        issues.push(String::from("its key is not on the P-256 curve"));
+    }
+
+    match cert.basic_constraints().ok().flatten() {
+        None => issues.push(String::from("it has no basic constraints")),
+        Some(constraints) => {
+            if !constraints.critical {
+                issues.push(String::from("its basic constraints are not critical"));
+            }
+            if kind == Kind::Pai && constraints.value.path_len_constraint != Some(0) {
+                issues.push(String::from("a PAI must have a path length of 0"));
+            }
+        }
+    }
+    match cert.key_usage().ok().flatten() {
+        None => issues.push(String::from("it has no key usage")),
+        Some(usage) => {
+            if !usage.critical {
+                issues.push(String::from("its key usage is not critical"));
+            }
+            let usage = usage.value;
+            let allowed = match kind {
+                Kind::Dac => usage.flags == 1 << 0,
+                // A CA may also use its key for signatures
+                Kind::Pai | Kind::Paa => {
+                    usage.key_cert_sign() && usage.crl_sign() && usage.flags & !0b110_0001 == 0
+                }
+            };
+            if !allowed {
+                issues.push(match kind {
+                    Kind::Dac => String::from("a DAC's key usage must be digitalSignature only"),
+                    _ => format!(
+                        "a {}'s key usage must be keyCertSign and cRLSign",
+                        kind.name()
+                    ),
+                });
+            }
+        }
+    }
+    let has = |oid: &str| {
+        cert.extensions()
+            .iter()
+            .any(|extension| extension.oid.to_id_string() == oid)
+    };
+    if !has("2.5.29.14") {
+        issues.push(String::from("it has no subject key identifier"));
+    }
+    if kind != Kind::Paa && !has("2.5.29.35") {
+        issues.push(String::from("it has no authority key identifier"));
+    }
+
+    match (kind, &ids.vendor, &ids.product) {
+        (Kind::Dac, None, _) | (Kind::Pai, None, _) => {
+            issues.push(format!("a {} must give a vendor ID", kind.name()))
+        }
+        (Kind::Dac, _, None) => issues.push(String::from("a DAC must give a product ID")),
+        _ => {}
+    }
+    for id in ids.vendor.iter().chain(&ids.product) {
+        if id.len() != 4
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())
+        {
+            issues.push(format!(
+                "{} is not written as four uppercase hex digits",
+                id
+            ));
+        }
+    }
+    issues
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Paa => "PAA",
+            Kind::Pai => "PAI",
+            Kind::Dac => "DAC",
+        }
+    }
+}
+
+fn kind(cert: &X509Certificate) -> Kind {
+    let ca = cert
+        .basic_constraints()
+        .ok()
+        .flatten()
+        .is_some_and(|constraints| constraints.value.ca);
+    match (ca, cert.subject().as_raw() == cert.issuer().as_raw()) {
+        (false, _) => Kind::Dac,
+        (true, true) => Kind::Paa,
+        (true, false) => Kind::Pai,
+    }
+}
+
+/// The VID and PID from the subject, falling back on the `Mvid:FFF1 Mpid:8000`
+/// the common name held before Matter gave them attributes of their own.
+fn ids(cert: &X509Certificate) -> Option<Ids> {
+    let mut ids = Ids {
+        vendor: None,
+        product: None,
+        legacy: false,
+    };
+    for attribute in cert.subject().iter_attributes() {
+        let value = attribute.as_str().ok().map(String::from);
+        match attribute.attr_type().to_id_string().as_str() {
+            VENDOR_ID => ids.vendor = value,
+            PRODUCT_ID => ids.product = value,
+            _ => {}
+        }
+    }
+    if ids.vendor.is_none() && ids.product.is_none() {
+        for name in cert.subject().iter_common_name() {
+            let name = name.as_str().ok()?;
+            let find = |prefix: &str| {
+                let start = name.find(prefix)? + prefix.len();
+                name.get(start..start + 4).map(String::from)
+            };
+            ids.vendor = find("Mvid:");
+            ids.product = find("Mpid:");
+            ids.legacy = ids.vendor.is_some() || ids.product.is_some();
+        }
+    }
+    match ids.vendor.is_some() || ids.product.is_some() {
+        true => Some(ids),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::Pem;
+
+    use super::{attestation, lint};
+    use crate::test::google_der;
+
+    const CHAIN: &[u8] = include_bytes!("../resources/matter-dac-chain.pem");
+    const BAD: &[u8] = include_bytes!("../resources/matter-bad-dac.crt");
+
+    fn pems(bytes: &[u8]) -> Vec<Pem> {
+        Pem::iter_from_buffer(bytes).map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn should_tell_a_dac_from_a_pai() {
+        let chain = pems(CHAIN);
+        let (_, dac) = parse_x509_certificate(&chain[0].contents).unwrap();
+        let (_, pai) = parse_x509_certificate(&chain[1].contents).unwrap();
+
+        let dac_fields = attestation(&dac).unwrap();
+        let pai_fields = attestation(&pai).unwrap();
+
+        assert_eq!(dac_fields[0].value, "DAC");
+        assert_eq!(dac_fields[1].value, "0xFFF1 (test vendor)");
+        assert_eq!(dac_fields[2].value, "0x8000");
+        assert_eq!(pai_fields[0].value, "PAI");
+        assert_eq!(pai_fields.len(), 2);
+        assert!(lint(&dac).is_empty());
+        assert!(lint(&pai).is_empty());
+    }
+
+    #[test]
+    fn should_lint_against_the_matter_certificate_profile() {
+        let bad = pems(BAD);
+        let (_, cert) = parse_x509_certificate(&bad[0].contents).unwrap();
+
+        assert_eq!(
+            lint(&cert),
+            vec![
+                "its basic constraints are not critical",
+                "a DAC's key usage must be digitalSignature only",
+                "800a is not written as four uppercase hex digits",
+            ]
+        );
+        assert_eq!(attestation(&cert).unwrap()[3].value, "the common name");
+    }
+
+    #[test]
+    fn should_leave_other_certificates_alone() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        assert!(attestation(&cert).is_none());
+        assert!(lint(&cert).is_empty());
+    }
+}
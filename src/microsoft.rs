@@ -0,0 +1,177 @@
+use std::borrow::Cow;
+
+use x509_parser::der_parser::oid::Oid;
+
+use crate::extensions::{context_tag, elements, unsigned, Field};
+use crate::oid;
+
+/// The extensions AD CS adds to the certificates it issues.
+pub const TEMPLATE_NAME: &str = "1.3.6.1.4.1.311.20.2";
+pub const CA_VERSION: &str = "1.3.6.1.4.1.311.21.1";
+pub const TEMPLATE: &str = "1.3.6.1.4.1.311.21.7";
+pub const APPLICATION_POLICIES: &str = "1.3.6.1.4.1.311.21.10";
+pub const NTDS_CA_SECURITY: &str = "1.3.6.1.4.1.311.25.2";
+
+/// The otherName the NTDS CA security extension holds the subject's SID in.
+const OBJECT_SID: &str = "1.3.6.1.4.1.311.25.2.1";
+
+/// The application policies AD CS templates most often give, which share
+/// their OIDs with the extended key usages.
+const POLICIES: &[(&str, &str)] = &[
+    ("1.3.6.1.5.5.7.3.1", "Server Authentication"),
+    ("1.3.6.1.5.5.7.3.2", "Client Authentication"),
+    ("1.3.6.1.5.5.7.3.3", "Code Signing"),
+    ("1.3.6.1.5.5.7.3.4", "Secure Email"),
+    ("1.3.6.1.5.5.7.3.8", "Time Stamping"),
+    ("1.3.6.1.5.5.7.3.9", "OCSP Signing"),
+    ("1.3.6.1.4.1.311.10.3.4", "Encrypting File System"),
+    ("1.3.6.1.4.1.311.10.3.4.1", "File Recovery"),
+    ("1.3.6.1.4.1.311.10.3.12", "Document Signing"),
+    ("1.3.6.1.4.1.311.20.2.1", "Certificate Request Agent"),
+    ("1.3.6.1.4.1.311.20.2.2", "Smart Card Logon"),
+    ("1.3.6.1.4.1.311.21.5", "Private Key Archival"),
+    ("1.3.6.1.4.1.311.21.6", "Key Recovery Agent"),
+    ("1.3.6.1.5.2.3.5", "KDC Authentication"),
+    ("2.5.29.37.0", "Any Purpose"),
+];
+
+/// The name of a version 1 template, as a BMPString.
+pub fn template_name(value: &[u8]) -> Option<Vec<Field>> {
+    let name = elements(value)?.into_iter().next()?;
+    Some(vec![Field::new("Template", bmp_string(name.data)?)])
+}
+
+/// The template a version 2 or later template certificate was issued from, by
+/// OID, with the version of the template at the time.
+pub fn template(value: &[u8]) -> Option<Vec<Field>> {
+    let template = elements(value)?;
+    let fields = elements(template.first()?.data)?;
+    let id = Oid::new(Cow::Borrowed(fields.first()?.data));
+    let mut version = unsigned(fields.get(1)?)?.to_string();
+    if let Some(minor) = fields.get(2) {
+        version = format!("{}.{}", version, unsigned(minor)?);
+    }
+    Some(vec![
+        Field::new("Template", oid::name(&id)),
+        Field::new("Template version", version),
+    ])
+}
+
+/// The application policies, which Windows checks in place of the extended
+/// key usage when present.
+pub fn application_policies(value: &[u8]) -> Option<Vec<Field>> {
+    let policies = elements(value)?;
+    elements(policies.first()?.data)?
+        .iter()
+        .map(|policy| {
+            let policy = elements(policy.data)?;
+            let id = Oid::new(Cow::Borrowed(policy.first()?.data));
+            Some(Field::new(
+                "Policy",
+                match POLICIES
+                    .iter()
+                    .find(|(known, _)| *known == id.to_id_string())
+                {
+                    Some((_, name)) => String::from(*name),
+                    None => oid::name(&id),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// The security identifier of the account a certificate was issued to, which
+/// domain controllers map it back to since strong certificate mapping.
+pub fn ntds_ca_security(value: &[u8]) -> Option<Vec<Field>> {
+    let names = elements(value)?;
+    let mut fields = Vec::new();
+    for name in elements(names.first()?.data)? {
+        if context_tag(&name)? != 0 {
+            continue;
+        }
+        let other = elements(name.data)?;
+        let id = Oid::new(Cow::Borrowed(other.first()?.data));
+        let value = elements(other.get(1)?.data)?.into_iter().next()?;
+        let sid = String::from_utf8_lossy(value.data).into_owned();
+        match id.to_id_string().as_str() {
+            OBJECT_SID => fields.push(Field::new("Object SID", sid)),
+            _ => fields.push(Field::new(&oid::name(&id), sid)),
+        }
+    }
+    Some(fields)
+}
+
+/// How many times a CA's certificate and key have been renewed, which AD CS
+/// packs into the two halves of one integer.
+pub fn ca_version(value: &[u8]) -> Option<Vec<Field>> {
+    let version = unsigned(elements(value)?.first()?)?;
+    Some(vec![
+        Field::new("Certificate index", (version & 0xffff).to_string()),
+        Field::new("Key index", (version >> 16).to_string()),
+    ])
+}
+
+fn bmp_string(bytes: &[u8]) -> Option<String> {
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Some(u16::from_be_bytes([*high, *low])),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    String::from_utf16(&units).ok()
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::certificate::X509Certificate;
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{
+        application_policies, ca_version, ntds_ca_security, template, template_name,
+        APPLICATION_POLICIES, NTDS_CA_SECURITY, TEMPLATE, TEMPLATE_NAME,
+    };
+
+    const CERT: &[u8] = include_bytes!("../resources/adcs-web.crt");
+
+    fn value<'a>(cert: &'a X509Certificate, oid: &str) -> &'a [u8] {
+        cert.extensions()
+            .iter()
+            .find(|extension| extension.oid.to_id_string() == oid)
+            .unwrap()
+            .value
+    }
+
+    #[test]
+    fn should_decode_the_template_policies_and_sid() {
+        let (_, pem) = parse_x509_pem(CERT).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+
+        let name = template_name(value(&cert, TEMPLATE_NAME)).unwrap();
+        let template = template(value(&cert, TEMPLATE)).unwrap();
+        let policies = application_policies(value(&cert, APPLICATION_POLICIES)).unwrap();
+        let sid = ntds_ca_security(value(&cert, NTDS_CA_SECURITY)).unwrap();
+
+        assert_eq!(name[0].value, "WebServer");
+        assert_eq!(
+            template[0].value,
+            "1.3.6.1.4.1.311.21.8.1234567.7654321.100.200.300.4000.55.66"
+        );
+        assert_eq!(template[1].value, "100.4");
+        assert_eq!(policies[0].value, "Server Authentication");
+        assert_eq!(policies[1].value, "Client Authentication");
+        assert_eq!(sid[0].name, "Object SID");
+        assert_eq!(sid[0].value, "S-1-5-21-3623811015-3361044348-30300820-1013");
+    }
+
+    #[test]
+    fn should_split_the_ca_version_into_certificate_and_key_index() {
+        // V2.1: the certificate renewed twice, once with a new key
+        let fields = ca_version(&[0x02, 0x03, 0x01, 0x00, 0x02]).unwrap();
+
+        assert_eq!(fields[0].value, "2");
+        assert_eq!(fields[1].value, "1");
+    }
+}
@@ -0,0 +1,529 @@
+use std::fmt;
+
+use crate::covers;
+use crate::dns;
+use crate::http::{Request, Url};
+use crate::options::Options;
+use crate::report::Report;
+use crate::store::NamedCertificate;
+use crate::target::Target;
+use crate::trust_path;
+use crate::FileProcessor;
+
+/// RFC 8461 caps how long a policy may be cached at about a year.
+const MAX_AGE_LIMIT: u64 = 31_557_600;
+
+/// A domain's MTA-STS policy, from `https://mta-sts.DOMAIN/.well-known/mta-sts.txt`.
+#[derive(Debug, PartialEq)]
+pub struct Policy {
+    /// `enforce`, `testing` or `none`.
+    pub mode: String,
+    /// The names mail servers must have, `*.` standing for one label.
+    pub mx: Vec<String>,
+    /// How long senders cache the policy, in seconds.
+    pub max_age: u64,
+}
+
+impl Policy {
+    /// Parses a policy, which is `key: value` lines.
+    pub fn parse(text: &str) -> Result<Policy, String> {
+        let mut version = None;
+        let mut mode = None;
+        let mut mx = Vec::new();
+        let mut max_age = None;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("the line \"{}\" is not a key and value", line))?;
+            let value = value.trim();
+            match key.trim() {
+                "version" => version = Some(value),
+                "mode" => mode = Some(value),
+                "mx" => mx.push(value.trim_end_matches('.').to_lowercase()),
+                "max_age" => {
+                    let err_msg = format!("max_age {} is not a number of seconds", value);
+                    max_age = Some(value.parse::<u64>().map_err(|_| err_msg)?);
+                }
+                // Other keys are extensions, which are ignored
+                _ => {}
+            }
+        }
+        if version != Some("STSv1") {
+            return Err(String::from("it does not start with version: STSv1"));
+        }
+        let mode = match mode {
+            Some(mode @ ("enforce" | "testing" | "none")) => String::from(mode),
+            Some(mode) => return Err(format!("its mode {} is not enforce, testing or none", mode)),
+            None => return Err(String::from("it has no mode")),
+        };
+        let max_age = match max_age {
+            Some(max_age) if max_age <= MAX_AGE_LIMIT => max_age,
+            Some(max_age) => {
+                return Err(format!(
+                    "its max_age {} is over the limit of {}",
+                    max_age, MAX_AGE_LIMIT
+                ))
+            }
+            None => return Err(String::from("it has no max_age")),
+        };
+        if mx.is_empty() && mode != "none" {
+            return Err(String::from("it lists no mx"));
+        }
+        Ok(Policy { mode, mx, max_age })
+    }
+
+    /// Whether a mail server's name matches one of the policy's mx patterns.
+    pub fn allows(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        self.mx
+            .iter()
+            .any(|pattern| match pattern.strip_prefix("*.") {
+                Some(parent) => host
+                    .split_once('.')
+                    .is_some_and(|(label, rest)| !label.is_empty() && rest == parent),
+                None => *pattern == host,
+            })
+    }
+}
+
+/// A mail server of the domain, and why it would not satisfy the policy.
+#[derive(Debug, PartialEq)]
+pub struct MailServer {
+    pub preference: u16,
+    pub host: String,
+    pub problems: Vec<String>,
+}
+
+/// How a mail domain's servers fare against its MTA-STS policy.
+pub struct MtaSts {
+    pub domain: String,
+    /// The id of the policy in the `_mta-sts` TXT record, if there is one.
+    pub id: Option<String>,
+    pub policy: Result<Policy, String>,
+    pub servers: Vec<MailServer>,
+    pub report: Report,
+}
+
+/// The mail servers in what a `dig +short MX` lookup printed, most preferred first.
+/// A domain without MX records receives mail on its own address.
+pub fn parse_mx(domain: &str, output: &str) -> Result<Vec<(u16, String)>, String> {
+    let mut servers: Vec<(u16, String)> = output
+        .lines()
+        .filter_map(|line| {
+            let (preference, host) = line.trim().split_once(' ')?;
+            Some((
+                preference.parse().ok()?,
+                host.trim().trim_end_matches('.').to_lowercase(),
+            ))
+        })
+        .collect();
+    if servers.iter().any(|(_, host)| host.is_empty()) {
+        return Err(format!(
+            "Error: {} has a null MX record, it does not accept mail.",
+            domain
+        ));
+    }
+    servers.sort();
+    if servers.is_empty() {
+        servers.push((0, String::from(domain)));
+    }
+    Ok(servers)
+}
+
+/// The policy id in what `dig +short TXT _mta-sts.DOMAIN` printed. TXT records
+/// can be split into several quoted strings, which are joined back together.
+pub fn parse_record(output: &str) -> Option<String> {
+    let records: Vec<String> = output
+        .lines()
+        .map(|line| line.split('"').skip(1).step_by(2).collect::<String>())
+        .filter(|record| record.starts_with("v=STSv1"))
+        .collect();
+    // RFC 8461 has senders treat more than one record as none
+    match records.as_slice() {
+        [record] => record
+            .split(';')
+            .filter_map(|field| field.trim().strip_prefix("id="))
+            .map(String::from)
+            .next(),
+        _ => None,
+    }
+}
+
+/// Fetches a domain's policy, which has to be served over HTTPS the system
+/// trusts, as text/plain.
+fn fetch_policy(
+    processor: &impl FileProcessor,
+    domain: &str,
+    options: &Options,
+) -> Result<(Policy, Vec<String>), String> {
+    let url = Url::parse(&format!(
+        "https://mta-sts.{}/.well-known/mta-sts.txt",
+        domain
+    ))
+    .map_err(|error| error.to_string())?;
+    let response = processor
+        .http(&Request::get(url.clone()), &options.connect)
+        .and_then(|response| response.success(&url))
+        .map_err(|error| error.to_string().trim_start_matches("Error: ").to_string())?;
+    let policy = Policy::parse(&String::from_utf8_lossy(&response.body))?;
+    let mut warnings = Vec::new();
+    let content_type = response.header("Content-Type").unwrap_or_default();
+    if !content_type.to_lowercase().starts_with("text/plain") {
+        warnings.push(format!(
+            "Warning: {} is served as {}, senders only accept text/plain.",
+            url,
+            if content_type.is_empty() {
+                "nothing"
+            } else {
+                content_type
+            }
+        ));
+    }
+    Ok((policy, warnings))
+}
+
+/// Checks the certificate a mail server presented as RFC 8461 has senders do:
+/// it has to cover the server's name, be in its validity period and chain to a
+/// trust anchor.
+fn check_chain(
+    processor: &impl FileProcessor,
+    host: &str,
+    chain: &[NamedCertificate],
+    anchors: &[trust_path::Anchor],
+    options: &Options,
+    now: i64,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    let (_, leaf) = match x509_parser::parse_x509_certificate(&chain[0].1) {
+        Ok(leaf) => leaf,
+        Err(_) => return vec![String::from("its certificate could not be decoded")],
+    };
+    let suffixes = options.suffixes.clone().unwrap_or_default();
+    for coverage in covers::check(&leaf, &[String::from(host)], &suffixes) {
+        if let Err(why) = coverage.by {
+            problems.push(format!("its certificate does not cover {}, {}", host, why));
+        }
+    }
+    let validity = leaf.validity();
+    if validity.not_after.timestamp() < now {
+        problems.push(format!("its certificate expired {}", validity.not_after));
+    } else if validity.not_before.timestamp() > now {
+        problems.push(format!(
+            "its certificate is not valid until {}",
+            validity.not_before
+        ));
+    }
+    let paths = trust_path::build_fetching(
+        processor,
+        &options.connect,
+        &chain[0],
+        &chain[1..],
+        anchors,
+        now,
+    );
+    if let Some(problem) = paths.first().and_then(|path| path.problem.as_ref()) {
+        problems.push(format!("its certificate is not trusted, {}", problem));
+    }
+    problems
+}
+
+/// Finds a domain's mail servers and MTA-STS policy, gets the certificate each
+/// server presents after STARTTLS, and checks them against the policy.
+pub fn check(
+    processor: &impl FileProcessor,
+    domain: &str,
+    options: &Options,
+) -> Result<MtaSts, Box<dyn std::error::Error>> {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    let mx = parse_mx(
+        &domain,
+        &dns::lookup(processor, options, &domain, "MX")?.text,
+    )?;
+    let id = parse_record(
+        &dns::lookup(processor, options, &format!("_mta-sts.{}", domain), "TXT")?.text,
+    );
+    let anchors = match &options.anchors {
+        Some(anchors) => anchors.clone(),
+        None => trust_path::load(processor, &options.trust_anchors)?,
+    };
+
+    let (policy, warnings) = match fetch_policy(processor, &domain, options) {
+        Ok((policy, warnings)) => (Ok(policy), warnings),
+        Err(error) => (Err(error), Vec::new()),
+    };
+    let targets: Vec<Target> = mx
+        .iter()
+        .map(|(_, host)| Target::Remote {
+            host: host.clone(),
+            port: 25,
+        })
+        .collect();
+    let mut report = Report::build(processor, &targets, options);
+    report.warnings.extend(warnings);
+    if let (Some(_), Err(error)) = (&id, &policy) {
+        report.warnings.push(format!(
+            "Warning: {} publishes an MTA-STS record, but its policy could not be used, {}.",
+            domain, error
+        ));
+    }
+
+    let now = processor.now();
+    let mut servers = Vec::new();
+    for (preference, host) in mx {
+        let location = format!("{}:25", host);
+        let at = |found: &str| found == location || found.starts_with(&format!("{} ", location));
+        let mut problems = Vec::new();
+        if let Some(policy) = policy.as_ref().ok().filter(|policy| policy.mode != "none") {
+            if !policy.allows(&host) {
+                problems.push(String::from("the policy does not list it as an mx"));
+            }
+        }
+        let chain: Vec<NamedCertificate> = report
+            .entries
+            .iter()
+            .filter(|entry| at(&entry.location))
+            .map(|entry| (entry.location.clone(), entry.der.clone()))
+            .collect();
+        match report.failures.iter().find(|failure| at(&failure.location)) {
+            Some(failure) => problems.push(
+                failure
+                    .error
+                    .to_string()
+                    .trim_start_matches("Error: ")
+                    .to_string(),
+            ),
+            None if !chain.is_empty() => problems.extend(check_chain(
+                processor, &host, &chain, &anchors, options, now,
+            )),
+            None => {}
+        }
+
+        match policy.as_ref().map(|policy| policy.mode.as_str()) {
+            Ok("enforce") if !problems.is_empty() => report.warnings.push(format!(
+                "Warning: senders that follow MTA-STS will not deliver mail for {} to {}, {}.",
+                domain,
+                host,
+                problems.join("; ")
+            )),
+            Ok("testing") if !problems.is_empty() => report.warnings.push(format!(
+                "Warning: {} fails the MTA-STS policy of {}, which is only being tested, {}.",
+                host,
+                domain,
+                problems.join("; ")
+            )),
+            _ => {}
+        }
+        servers.push(MailServer {
+            preference,
+            host,
+            problems,
+        });
+    }
+
+    Ok(MtaSts {
+        domain,
+        id,
+        policy,
+        servers,
+        report,
+    })
+}
+
+impl fmt::Display for MtaSts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Mail domain: {}", self.domain)?;
+        match &self.id {
+            Some(id) => writeln!(f, "MTA-STS record: id {}", id)?,
+            None => writeln!(f, "MTA-STS record: none")?,
+        }
+        let enforced = match &self.policy {
+            Ok(policy) => {
+                writeln!(
+                    f,
+                    "MTA-STS policy: {}, cached for {} seconds, mx {}",
+                    policy.mode,
+                    policy.max_age,
+                    policy.mx.join(", ")
+                )?;
+                policy.mode != "none"
+            }
+            Err(error) => {
+                writeln!(f, "MTA-STS policy: none, {}", error)?;
+                false
+            }
+        };
+        for server in &self.servers {
+            let verdict = match (server.problems.is_empty(), enforced) {
+                (true, true) => String::from("satisfies the policy"),
+                (true, false) => String::from("would satisfy a policy"),
+                (false, true) => format!(
+                    "does not satisfy the policy, {}",
+                    server.problems.join("; ")
+                ),
+                (false, false) => {
+                    format!("would not satisfy a policy, {}", server.problems.join("; "))
+                }
+            };
+            writeln!(f, "MX {} {}: {}", server.preference, server.host, verdict)?;
+        }
+        write!(f, "{}", self.report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{check, parse_mx, parse_record, Policy};
+    use crate::http::Response;
+    use crate::options::Options;
+    use crate::test::{self_signed_der, FakeProcessor};
+    use crate::trust_path::Anchor;
+
+    const POLICY: &str = "version: STSv1\r\nmode: enforce\r\nmx: mx1.example.com\r\nmx: *.backup.example.net\r\nmax_age: 604800\r\n";
+
+    fn mx1_der() -> Vec<u8> {
+        let cert = include_str!("../resources/mx1.example.com.crt");
+        let (_, pem) = x509_parser::pem::parse_x509_pem(cert.as_bytes()).unwrap();
+        pem.contents
+    }
+
+    fn processor() -> FakeProcessor {
+        let mut processor = FakeProcessor {
+            // 2027, when both test certificates are valid
+            now: 1800000000,
+            ..FakeProcessor::default()
+        };
+        processor.commands.insert(
+            String::from("dig +dnssec +noall +comments +answer -q example.com -t MX"),
+            String::from(
+                "example.com. 300 IN MX 20 mx2.backup.example.net.\n\
+                 example.com. 300 IN MX 10 mx1.example.com.\n",
+            ),
+        );
+        processor.commands.insert(
+            String::from("dig +dnssec +noall +comments +answer -q _mta-sts.example.com -t TXT"),
+            String::from(
+                "_mta-sts.example.com. 300 IN TXT \"v=STSv1; \" \"id=20261015T000000;\"\n",
+            ),
+        );
+        processor.responses.insert(
+            String::from("https://mta-sts.example.com/.well-known/mta-sts.txt"),
+            Response {
+                status: 200,
+                headers: vec![(String::from("Content-Type"), String::from("text/plain"))],
+                body: POLICY.as_bytes().to_vec(),
+            },
+        );
+        processor.add_remote("mx1.example.com:25", vec![mx1_der()]);
+        processor.add_remote("mx2.backup.example.net:25", vec![self_signed_der()]);
+        processor
+    }
+
+    fn options() -> Options {
+        Options {
+            anchors: Some(vec![Anchor {
+                store: String::from("anchors.pem"),
+                der: mx1_der(),
+            }]),
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn should_parse_a_policy() {
+        let policy = Policy::parse(POLICY).unwrap();
+        assert_eq!(policy.mode, "enforce");
+        assert_eq!(policy.max_age, 604800);
+        assert!(policy.allows("mx1.example.com."));
+        assert!(policy.allows("mx2.backup.example.net"));
+        assert!(!policy.allows("a.mx2.backup.example.net"));
+        assert!(!policy.allows("backup.example.net"));
+
+        assert_eq!(
+            Policy::parse("version: STSv1\nmode: enforce\nmax_age: 86400\n"),
+            Err(String::from("it lists no mx"))
+        );
+        assert_eq!(
+            Policy::parse("mode: enforce\n"),
+            Err(String::from("it does not start with version: STSv1"))
+        );
+    }
+
+    #[test]
+    fn should_parse_dns_answers() {
+        assert_eq!(
+            parse_mx("example.com", "20 b.example.com.\n10 a.example.com.\n").unwrap(),
+            vec![
+                (10, String::from("a.example.com")),
+                (20, String::from("b.example.com"))
+            ]
+        );
+        assert_eq!(
+            parse_mx("example.com", "").unwrap(),
+            vec![(0, String::from("example.com"))]
+        );
+        assert!(parse_mx("example.com", "0 .\n").is_err());
+
+        assert_eq!(
+            parse_record("\"v=STSv1; id=abc;\"\n\"v=spf1 -all\"\n"),
+            Some(String::from("abc"))
+        );
+        assert_eq!(
+            parse_record("\"v=STSv1; id=abc;\"\n\"v=STSv1; id=def;\"\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn should_check_each_mail_server_against_the_policy() {
+        let processor = processor();
+        let checked = check(&processor, "example.com", &options()).unwrap();
+
+        assert_eq!(checked.id, Some(String::from("20261015T000000")));
+        assert_eq!(checked.servers[0].host, "mx1.example.com");
+        assert!(checked.servers[0].problems.is_empty());
+        assert_eq!(checked.servers[1].host, "mx2.backup.example.net");
+        assert_eq!(
+            checked.servers[1].problems,
+            vec![
+                String::from("its certificate does not cover mx2.backup.example.net, the certificate has no subject alternative names, and clients no longer match the common name"),
+                String::from("its certificate is not trusted, CN=self-signed.example is self-signed and not a trust anchor"),
+            ]
+        );
+        let output = checked.to_string();
+        assert!(output.contains("MX 10 mx1.example.com: satisfies the policy\n"));
+        assert!(output.contains("MX 20 mx2.backup.example.net: does not satisfy the policy, "));
+        assert!(checked.report.warnings.iter().any(|warning| warning
+            .starts_with("Warning: senders that follow MTA-STS will not deliver mail for example.com to mx2.backup.example.net")));
+    }
+
+    #[test]
+    fn should_report_servers_without_starttls_and_a_missing_policy() {
+        let mut processor = processor();
+        processor.remotes.remove("mx2.backup.example.net:25");
+        processor
+            .responses
+            .remove("https://mta-sts.example.com/.well-known/mta-sts.txt");
+        let checked = check(&processor, "example.com", &options()).unwrap();
+
+        assert_eq!(
+            checked.policy,
+            Err(String::from(
+                "could not connect to https://mta-sts.example.com/.well-known/mta-sts.txt"
+            ))
+        );
+        assert_eq!(
+            checked.servers[1].problems,
+            vec![String::from(
+                "could not connect to mx2.backup.example.net:25"
+            )]
+        );
+        assert!(checked
+            .to_string()
+            .contains("MX 20 mx2.backup.example.net: would not satisfy a policy, "));
+        assert_eq!(
+            checked.report.warnings,
+            vec![String::from("Warning: example.com publishes an MTA-STS record, but its policy could not be used, could not connect to https://mta-sts.example.com/.well-known/mta-sts.txt.")]
+        );
+    }
+}
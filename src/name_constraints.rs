@@ -0,0 +1,300 @@
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{GeneralName, GeneralSubtree, ParsedExtension};
+use x509_parser::x509::X509Name;
+
+/// Checks the names of the leaf of a path, given root first, against the name
+/// constraints of every CA above it, returning each constraint a name breaks.
+/// The names are the leaf's subject alternative names and its subject.
+pub fn check(path: &[X509Certificate]) -> Vec<String> {
+    let (leaf, cas) = match path.split_last() {
+        Some(split) => split,
+        None => return Vec::new(),
+    };
+    let mut names: Vec<GeneralName> = leaf
+        .extensions()
+        .iter()
+        .filter_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::SubjectAlternativeName(san) => Some(san.general_names.clone()),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    if leaf.subject().iter().next().is_some() {
+        names.push(GeneralName::DirectoryName(leaf.subject().clone()));
+    }
+
+    let mut problems = Vec::new();
+    for ca in cas {
+        let constraints = match ca.name_constraints() {
+            Ok(Some(constraints)) => constraints.value,
+            _ => continue,
+        };
+        let permitted = constraints
+            .permitted_subtrees
+            .as_deref()
+            .unwrap_or_default();
+        let excluded = constraints.excluded_subtrees.as_deref().unwrap_or_default();
+        for name in &names {
+            let excluded = excluded
+                .iter()
+                .find(|subtree| matches(name, &subtree.base, true));
+            if let Some(subtree) = excluded {
+                problems.push(format!(
+                    "{} is in the excluded subtree {} of {}",
+                    describe(name),
+                    describe(&subtree.base),
+                    ca.subject()
+                ));
+            }
+            // Permitted subtrees only limit the names of their own type
+            let same_type: Vec<&GeneralSubtree> = permitted
+                .iter()
+                .filter(|subtree| same_type(name, &subtree.base))
+                .collect();
+            if !same_type.is_empty()
+                && !same_type
+                    .iter()
+                    .any(|subtree| matches(name, &subtree.base, false))
+            {
+                let subtrees: Vec<String> = same_type
+                    .iter()
+                    .map(|subtree| describe(&subtree.base))
+                    .collect();
+                problems.push(format!(
+                    "{} is not in the permitted subtrees of {} ({})",
+                    describe(name),
+                    ca.subject(),
+                    subtrees.join(", ")
+                ));
+            }
+        }
+    }
+    problems
+}
+
+fn same_type(name: &GeneralName, base: &GeneralName) -> bool {
+    std::mem::discriminant(name) == std::mem::discriminant(base)
+}
+
+/// Whether a name falls within a subtree, following RFC 5280 section 4.2.1.10.
+/// Against an excluded subtree, a wildcard counts if any name it covers would.
+fn matches(name: &GeneralName, base: &GeneralName, excluded: bool) -> bool {
+    match (name, base) {
+        (GeneralName::DNSName(name), GeneralName::DNSName(base)) => {
+            dns_matches(name, base, excluded)
+        }
+        (GeneralName::RFC822Name(name), GeneralName::RFC822Name(base)) => email_matches(name, base),
+        (GeneralName::URI(name), GeneralName::URI(base)) => match uri_host(name) {
+            Some(host) => host_matches(&host, base),
+            None => false,
+        },
+        (GeneralName::IPAddress(name), GeneralName::IPAddress(base)) => ip_matches(name, base),
+        (GeneralName::DirectoryName(name), GeneralName::DirectoryName(base)) => {
+            directory_matches(name, base)
+        }
+        _ => false,
+    }
+}
+
+/// A DNS name is in a subtree if it is the base or below it, or only below it when
+/// the base starts with a dot as some CAs write it. With `wildcards`, a wildcard is
+/// also in a subtree if any name it covers is, so it can't slip past an excluded
+/// name beneath it.
+fn dns_matches(name: &str, base: &str, wildcards: bool) -> bool {
+    let name = name.to_lowercase();
+    let base = base.to_lowercase();
+    if base.is_empty() {
+        return true;
+    }
+    if let (Some(wildcarded), true) = (name.strip_prefix("*."), wildcards) {
+        let base = base.trim_start_matches('.');
+        if base.ends_with(&format!(".{}", wildcarded)) {
+            return true;
+        }
+    }
+    match base.strip_prefix('.') {
+        Some(domain) => name.ends_with(&format!(".{}", domain)),
+        None => name == base || name.ends_with(&format!(".{}", base)),
+    }
+}
+
+/// A mailbox is in a subtree that is the same mailbox, its host, or with a leading
+/// dot any host below a domain.
+fn email_matches(name: &str, base: &str) -> bool {
+    let name = name.to_lowercase();
+    let base = base.to_lowercase();
+    let host = match name.rsplit_once('@') {
+        Some((_, host)) => host,
+        None => return false,
+    };
+    if base.contains('@') {
+        return name == base;
+    }
+    match base.strip_prefix('.') {
+        Some(domain) => host.ends_with(&format!(".{}", domain)),
+        None => host == base,
+    }
+}
+
+/// A URI is in a subtree by its host, which with a leading dot can be any host below it.
+fn host_matches(host: &str, base: &str) -> bool {
+    let host = host.to_lowercase();
+    let base = base.to_lowercase();
+    match base.strip_prefix('.') {
+        Some(domain) => host.ends_with(&format!(".{}", domain)),
+        None => host == base,
+    }
+}
+
+fn uri_host(uri: &str) -> Option<String> {
+    let rest = uri.split_once("://")?.1;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next()?,
+        None => host.split(':').next()?,
+    };
+    Some(String::from(host))
+}
+
+/// An address is in a subtree given as an address and mask of the same family.
+fn ip_matches(name: &[u8], base: &[u8]) -> bool {
+    if base.len() != name.len() * 2 {
+        return false;
+    }
+    let (address, mask) = base.split_at(name.len());
+    name.iter()
+        .zip(address)
+        .zip(mask)
+        .all(|((name, address), mask)| name & mask == address & mask)
+}
+
+/// A name is in a subtree whose RDNs it starts with.
+fn directory_matches(name: &X509Name, base: &X509Name) -> bool {
+    let rdns = |name: &X509Name| -> Vec<Vec<(String, Vec<u8>)>> {
+        name.iter()
+            .map(|rdn| {
+                rdn.iter()
+                    .map(|attribute| {
+                        let value = attribute.as_str().map(str::to_lowercase);
+                        (
+                            attribute.attr_type().to_id_string(),
+                            value.map_or_else(
+                                |_| attribute.attr_value().data.to_vec(),
+                                String::into_bytes,
+                            ),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    };
+    let (name, base) = (rdns(name), rdns(base));
+    name.len() >= base.len() && name[..base.len()] == base[..]
+}
+
+/// A name as OpenSSL prints it, with an address range given as a prefix length.
+fn describe(name: &GeneralName) -> String {
+    match name {
+        GeneralName::DNSName(name) => format!("DNS:{}", name),
+        GeneralName::RFC822Name(name) => format!("email:{}", name),
+        GeneralName::URI(name) => format!("URI:{}", name),
+        GeneralName::DirectoryName(name) => format!("DirName:{}", name),
+        GeneralName::IPAddress(bytes) => match address(bytes) {
+            Some(address) => format!("IP:{}", address),
+            None => match (address(&bytes[..bytes.len() / 2]), bytes.len()) {
+                (Some(address), 8 | 32) => {
+                    let mask = &bytes[bytes.len() / 2..];
+                    let prefix: u32 = mask.iter().map(|byte| byte.count_ones()).sum();
+                    format!("IP:{}/{}", address, prefix)
+                }
+                _ => String::from("IP:invalid"),
+            },
+        },
+        other => format!("{}", other),
+    }
+}
+
+/// An IPv4 or IPv6 address, from its bytes.
+pub fn address(bytes: &[u8]) -> Option<IpAddr> {
+    match bytes.len() {
+        4 => Some(IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+        16 => Some(IpAddr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::Pem;
+
+    use super::{check, dns_matches, email_matches, ip_matches, uri_host};
+
+    fn path(bytes: &[u8]) -> Vec<Vec<u8>> {
+        let mut chain: Vec<Vec<u8>> = Pem::iter_from_buffer(bytes)
+            .map(|pem| pem.unwrap().contents)
+            .collect();
+        chain.reverse();
+        chain
+    }
+
+    #[test]
+    fn should_say_which_constraint_each_name_breaks() {
+        let chain = path(include_bytes!("../resources/constrained-chain.pem"));
+        let certificates: Vec<_> = chain
+            .iter()
+            .map(|der| parse_x509_certificate(der).unwrap().1)
+            .collect();
+
+        assert_eq!(
+            check(&certificates),
+            vec![
+                "DNS:admin.example.com is in the excluded subtree DNS:admin.example.com of CN=Example Constrained CA",
+                "DNS:www.example.net is not in the permitted subtrees of CN=Example Constrained CA (DNS:example.com)",
+                "IP:192.168.1.1 is not in the permitted subtrees of CN=Example Constrained CA (IP:10.0.0.0/8)",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_pass_a_chain_without_constraints() {
+        let chain = path(include_bytes!("../resources/example-chain.pem"));
+        let certificates: Vec<_> = chain
+            .iter()
+            .map(|der| parse_x509_certificate(der).unwrap().1)
+            .collect();
+
+        assert!(check(&certificates).is_empty());
+    }
+
+    #[test]
+    fn should_match_names_to_subtrees() {
+        assert!(dns_matches("www.Example.com", "example.com", false));
+        assert!(dns_matches("example.com", "example.com", false));
+        assert!(!dns_matches("badexample.com", "example.com", false));
+        assert!(!dns_matches("example.com", ".example.com", false));
+        assert!(dns_matches("*.example.com", "admin.example.com", true));
+        assert!(!dns_matches("*.example.com", "admin.example.com", false));
+        assert!(email_matches("alice@example.com", "example.com"));
+        assert!(!email_matches("alice@mail.example.com", "example.com"));
+        assert!(email_matches("alice@mail.example.com", ".example.com"));
+        assert!(email_matches("alice@example.com", "alice@example.com"));
+        assert!(ip_matches(&[10, 1, 2, 3], &[10, 0, 0, 0, 255, 0, 0, 0]));
+        assert!(!ip_matches(&[11, 1, 2, 3], &[10, 0, 0, 0, 255, 0, 0, 0]));
+        assert_eq!(
+            uri_host("https://user@[::1]:8443/path").as_deref(),
+            Some("::1")
+        );
+        assert_eq!(
+            uri_host("ldap://ldap.example.com/cn=ca").as_deref(),
+            Some("ldap.example.com")
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use x509_parser::certificate::X509Certificate;
+
+use crate::cms::hex;
+use crate::extensions::{elements, Field};
+
+/// The arc Netscape numbered its certificate extensions under.
+const NETSCAPE_EXTENSIONS: &str = "2.16.840.1.113730.1.";
+
+/// The extensions Netscape defined, by their last arc. All but the first are IA5Strings.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("1", "Certificate type"),
+    ("2", "Base URL"),
+    ("3", "Revocation URL"),
+    ("4", "CA revocation URL"),
+    ("7", "Renewal URL"),
+    ("8", "CA policy URL"),
+    ("12", "SSL server name"),
+    ("13", "Comment"),
+];
+
+/// What each bit of the certificate type allows, from the most significant.
+const CERTIFICATE_TYPES: &[&str] = &[
+    "SSL client",
+    "SSL server",
+    "S/MIME",
+    "object signing",
+    "reserved",
+    "SSL CA",
+    "S/MIME CA",
+    "object signing CA",
+];
+
+/// Decodes the extensions Netscape browsers used before there were standard
+/// ones for the same things, which old internal CAs still add. Certificates
+/// without any are left alone.
+pub fn extensions(cert: &X509Certificate) -> Option<Vec<Field>> {
+    let mut fields = Vec::new();
+    for extension in cert.extensions() {
+        let oid = extension.oid.to_id_string();
+        let number = match oid.strip_prefix(NETSCAPE_EXTENSIONS) {
+            Some(number) => number,
+            None => continue,
+        };
+        let value = elements(extension.value).and_then(|value| value.into_iter().next());
+        let field = match (EXTENSIONS.iter().find(|(known, _)| *known == number), value) {
+            (Some(("1", name)), Some(value)) => Field::new(name, certificate_type(value.data)),
+            (Some((_, name)), Some(value)) => Field::new(name, String::from_utf8_lossy(value.data)),
+            _ => Field::new(&oid, hex(extension.value)),
+        };
+        fields.push(field);
+    }
+    match fields.is_empty() {
+        true => None,
+        false => Some(fields),
+    }
+}
+
+/// The uses a certificate type BIT STRING allows, after its count of unused bits.
+fn certificate_type(bits: &[u8]) -> String {
+    let flags = bits.get(1).copied().unwrap_or(0);
+    let types: Vec<&str> = CERTIFICATE_TYPES
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| flags & (0x80 >> bit) != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    match types.is_empty() {
+        true => String::from("none"),
+        false => types.join(", "),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{certificate_type, extensions};
+    use crate::test::google_der;
+
+    #[test]
+    fn should_decode_the_certificate_type_and_comment() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/netscape.crt")).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+
+        let fields = extensions(&cert).unwrap();
+        let fields: Vec<(&str, &str)> = fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.value.as_str()))
+            .collect();
+
+        assert_eq!(
+            fields,
+            vec![
+                ("Certificate type", "SSL client, SSL server"),
+                ("Comment", "Generated by the intranet CA"),
+                ("Base URL", "https://ca.intranet.example/"),
+                ("Revocation URL", "revoke.cgi?"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_name_the_certificate_types_from_the_most_significant_bit() {
+        assert_eq!(
+            certificate_type(&[0x00, 0x07]),
+            "SSL CA, S/MIME CA, object signing CA"
+        );
+        assert_eq!(certificate_type(&[0x07, 0x00]), "none");
+    }
+
+    #[test]
+    fn should_leave_other_certificates_alone() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        assert!(extensions(&cert).is_none());
+    }
+}
@@ -0,0 +1,89 @@
+use crate::store::NamedCertificate;
+use crate::FileProcessor;
+
+/// The SQLite database NSS keeps certificates in. Keys live alongside in
+/// key4.db, but only the certificates are needed to audit what's installed.
+const CERT_DB: &str = "cert9.db";
+
+/// The path of the certificate database in an NSS database directory.
+pub fn cert_db(dir: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), CERT_DB)
+}
+
+/// Whether a directory is an NSS database, such as a Firefox profile.
+pub fn is_database(processor: &impl FileProcessor, dir: &str) -> bool {
+    processor.is_dir(dir) && processor.is_file(&cert_db(dir))
+}
+
+/// Reads the nickname and DER of every certificate in an NSS database.
+#[cfg(feature = "nss")]
+pub fn read(dir: &str) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    use rusqlite::{Connection, OpenFlags};
+
+    // Objects are stored in nssPublic with a column per PKCS#11 attribute,
+    // named a<hex type>: CKA_CLASS is a0, CKA_LABEL a3 and CKA_VALUE a11
+    const CKO_CERTIFICATE: [u8; 4] = [0, 0, 0, 1];
+
+    let open = |error: rusqlite::Error| {
+        format!("Error: could not read the NSS database {}: {}", dir, error)
+    };
+    let db = Connection::open_with_flags(cert_db(dir), OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(open)?;
+    let mut statement = db
+        .prepare("SELECT a3, a11 FROM nssPublic WHERE a0 = ?1")
+        .map_err(open)?;
+    let rows = statement
+        .query_map([&CKO_CERTIFICATE[..]], |row| {
+            let label: Option<Vec<u8>> = row.get(0)?;
+            let der: Vec<u8> = row.get(1)?;
+            Ok((label, der))
+        })
+        .map_err(open)?;
+
+    let mut certificates = Vec::new();
+    for row in rows {
+        let (label, der) = row.map_err(open)?;
+        let label = label
+            .map(|label| String::from_utf8_lossy(&label).into_owned())
+            .unwrap_or_else(|| String::from("(no nickname)"));
+        certificates.push((label, der));
+    }
+    Ok(certificates)
+}
+
+#[cfg(not(feature = "nss"))]
+pub fn read(dir: &str) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    Err(format!(
+        "Error: {} is an NSS database, rebuild cert-decoder with --features nss to read it.",
+        dir
+    )
+    .into())
+}
+
+#[cfg(all(test, feature = "nss"))]
+mod test {
+
+    use super::read;
+    use crate::test::google_der;
+
+    #[test]
+    fn should_read_certificates_from_cert9_db() {
+        let dir = std::env::temp_dir().join(format!("cert-decoder-nss-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = rusqlite::Connection::open(dir.join("cert9.db")).unwrap();
+        db.execute_batch("CREATE TABLE nssPublic (id PRIMARY KEY, a0, a3, a11)")
+            .unwrap();
+        let insert = "INSERT INTO nssPublic (id, a0, a3, a11) VALUES (?1, ?2, ?3, ?4)";
+        let certificate: &[u8] = &[0, 0, 0, 1];
+        let public_key: &[u8] = &[0, 0, 0, 2];
+        db.execute(insert, (1, certificate, &b"Google"[..], google_der()))
+            .unwrap();
+        db.execute(insert, (2, public_key, &b"Google"[..], &b"key"[..]))
+            .unwrap();
+
+        let certificates = read(dir.to_str().unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(certificates, vec![(String::from("Google"), google_der())]);
+    }
+}
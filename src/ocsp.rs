@@ -0,0 +1,838 @@
+use std::fmt;
+
+use ring::digest::{digest, Algorithm, SHA1_FOR_LEGACY_USE_ONLY, SHA256, SHA384, SHA512};
+use ring::rand::{SecureRandom, SystemRandom};
+use x509_parser::asn1_rs::BitString;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+use x509_parser::oid_registry::OID_PKIX_ACCESS_DESCRIPTOR_OCSP;
+use x509_parser::parse_x509_certificate;
+use x509_parser::pem::Pem;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+use x509_parser::verify::verify_signature;
+use x509_parser::x509::{AlgorithmIdentifier, X509Name};
+
+use crate::cms::encode;
+use crate::cms::{self, element, Tlv};
+use crate::crl;
+use crate::dn;
+use crate::encoding::{self, InputEncoding};
+use crate::report::Entry;
+use crate::FileProcessor;
+
+/// The hashes a CertID can identify the issuer by, with the DER of their OIDs.
+const HASHES: &[(&str, &Algorithm, &[u8])] = &[
+    (
+        "sha1",
+        &SHA1_FOR_LEGACY_USE_ONLY,
+        &[0x2b, 0x0e, 0x03, 0x02, 0x1a],
+    ),
+    (
+        "sha256",
+        &SHA256,
+        &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01],
+    ),
+    (
+        "sha384",
+        &SHA384,
+        &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02],
+    ),
+    (
+        "sha512",
+        &SHA512,
+        &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03],
+    ),
+];
+
+/// The DER of the nonce extension's OID.
+const NONCE_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x02];
+
+/// Whether to write a request for a certificate or test a responder with several.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OcspCommand {
+    #[default]
+    Request,
+    Test,
+}
+
+/// What `cert-decoder ocsp request` should build, or `cert-decoder ocsp test`
+/// should test a responder with.
+#[derive(Debug, Default, PartialEq)]
+pub struct OcspOptions {
+    pub command: OcspCommand,
+    /// The certificate to ask the status of, from `--cert`.
+    pub cert: String,
+    /// Its issuer, from `--issuer`, or else the next certificate in `cert`.
+    pub issuer: Option<String>,
+    /// Where to write the request, from `-o`.
+    pub output: String,
+    /// The hash to identify the issuer by, from `--hash`, or else SHA-1, which
+    /// RFC 5019 says every responder must accept.
+    pub hash: Option<String>,
+    /// Whether to add a random nonce, from `--nonce`.
+    pub nonce: bool,
+    /// The responder to test, from `--url`, or else the one the certificate names.
+    pub url: Option<String>,
+}
+
+/// The nonce extension, and the other request extensions RFC 6960 defines.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("1.3.6.1.5.5.7.48.1.2", "nonce"),
+    ("1.3.6.1.5.5.7.48.1.3", "CRL references"),
+    ("1.3.6.1.5.5.7.48.1.4", "acceptable response types"),
+    ("1.3.6.1.5.5.7.48.1.7", "service locator"),
+    ("1.3.6.1.5.5.7.48.1.8", "preferred signature algorithms"),
+];
+
+const NONCE: &str = "1.3.6.1.5.5.7.48.1.2";
+const BASIC: &str = "1.3.6.1.5.5.7.48.1.1";
+
+/// The longest nonce RFC 8954 allows, responders may refuse longer ones.
+const MAX_NONCE: usize = 32;
+
+/// An RFC 6960 OCSP request, as a client sends it to a responder.
+pub struct OcspRequest {
+    pub location: String,
+    pub version: u64,
+    pub requestor: Option<String>,
+    pub certificates: Vec<CertId>,
+    /// The nonce in hex, without the OCTET STRING RFC 8954 wraps it in.
+    pub nonce: Option<String>,
+    pub nonce_length: usize,
+    /// The names of the request extensions other than the nonce.
+    pub extensions: Vec<String>,
+    /// The algorithm the request is signed with, which few clients do.
+    pub signature_algorithm: Option<String>,
+}
+
+/// Which certificate's status is asked for: its issuer, by the hashes of its
+/// name and key, and its serial number.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CertId {
+    pub hash_algorithm: String,
+    pub issuer_name_hash: Vec<u8>,
+    pub issuer_key_hash: Vec<u8>,
+    pub serial: String,
+}
+
+impl CertId {
+    /// Whether a certificate is the issuer this asks about, when the hash is
+    /// one that can be computed.
+    pub fn issued_by(&self, entry: &Entry) -> bool {
+        let algorithm = match HASHES
+            .iter()
+            .find(|(name, _, _)| *name == self.hash_algorithm)
+        {
+            Some((_, algorithm, _)) => *algorithm,
+            None => return false,
+        };
+        let cert = entry.certificate();
+        digest(algorithm, cert.subject().as_raw()).as_ref() == self.issuer_name_hash.as_slice()
+            && digest(algorithm, &cert.public_key().subject_public_key.data).as_ref()
+                == self.issuer_key_hash.as_slice()
+    }
+}
+
+/// The DER of an OCSP request in binary, as a pcap export or a POST body
+/// saved from a proxy holds it, or in base64, as the last part of the URL of
+/// a GET request is.
+pub fn find(bytes: &[u8]) -> Option<Vec<u8>> {
+    if request("", bytes).is_some() {
+        return Some(bytes.to_vec());
+    }
+    let text = std::str::from_utf8(bytes).ok()?.trim();
+    let text = match text.starts_with("http://") || text.starts_with("https://") {
+        true => text.rsplit('/').next()?,
+        false => text,
+    };
+    let text = text
+        .replace("%2B", "+")
+        .replace("%2F", "/")
+        .replace("%3D", "=")
+        .replace("%2b", "+")
+        .replace("%2f", "/")
+        .replace("%3d", "=");
+    let der = encoding::decode(InputEncoding::Base64, text.as_bytes()).ok()?;
+    request("", &der).map(|_| der)
+}
+
+pub fn read(source: &str, der: &[u8]) -> Result<OcspRequest, Box<dyn std::error::Error>> {
+    request(source, der)
+        .ok_or_else(|| format!("Error: {} is not a valid OCSP request.", source).into())
+}
+
+fn request(source: &str, der: &[u8]) -> Option<OcspRequest> {
+    let (request, rest) = element(der)?;
+    if request.tag != 0x30 || !rest.is_empty() {
+        return None;
+    }
+    let fields = request.children()?;
+    let tbs = fields.first().filter(|tbs| tbs.tag == 0x30)?.children()?;
+    let signature_algorithm = match fields.get(1) {
+        Some(signature) if signature.tag == 0xa0 => {
+            let signature = signature.children()?.first()?.children()?;
+            Some(cms::algorithm(signature.first()?)?)
+        }
+        Some(_) => return None,
+        None => None,
+    };
+
+    let mut ocsp = OcspRequest {
+        location: String::from(source),
+        version: 1,
+        requestor: None,
+        certificates: Vec::new(),
+        nonce: None,
+        nonce_length: 0,
+        extensions: Vec::new(),
+        signature_algorithm,
+    };
+    let mut list = None;
+    for field in &tbs {
+        match field.tag {
+            0xa0 => ocsp.version = unsigned(field.children()?.first()?.contents)? + 1,
+            0xa1 => {
+                let name = field.children()?;
+                ocsp.requestor = match GeneralName::from_der(name.first()?.raw).ok()? {
+                    (_, GeneralName::DirectoryName(name)) => Some(dn::to_string(&name)),
+                    (_, name) => Some(name.to_string()),
+                };
+            }
+            0x30 => list = Some(field.children()?),
+            0xa2 => {
+                for extension in field.children()?.first()?.children()? {
+                    let parts = extension.children()?;
+                    let oid = parts.first()?.oid()?.to_id_string();
+                    let value = parts.last().filter(|value| value.tag == 0x04)?;
+                    if oid == NONCE {
+                        // RFC 8954 wraps the nonce in an OCTET STRING, older clients didn't
+                        let nonce = match element(value.contents) {
+                            Some((inner, [])) if inner.tag == 0x04 => inner.contents,
+                            _ => value.contents,
+                        };
+                        ocsp.nonce = Some(cms::hex(nonce));
+                        ocsp.nonce_length = nonce.len();
+                    } else {
+                        let name = EXTENSIONS.iter().find(|(known, _)| *known == oid);
+                        ocsp.extensions
+                            .push(name.map_or(oid, |(_, name)| String::from(*name)));
+                    }
+                }
+            }
+            _ => return None,
+        }
+    }
+    for single in list? {
+        let fields = single.children()?;
+        ocsp.certificates.push(cert_id(fields.first()?)?);
+    }
+    if ocsp.certificates.is_empty() {
+        return None;
+    }
+    Some(ocsp)
+}
+
+impl OcspRequest {
+    /// What is worth pointing out about the request.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.nonce.is_some() && !(1..=MAX_NONCE).contains(&self.nonce_length) {
+            warnings.push(format!(
+                "Warning: {} has a nonce of {} bytes, RFC 8954 allows 1 to {} and responders may reject it.",
+                self.location, self.nonce_length, MAX_NONCE
+            ));
+        }
+        warnings
+    }
+}
+
+/// The certificate IDs of a request, with the issuer each names if one of the
+/// certificates decoded alongside it is that issuer.
+pub fn describe(id: &CertId, entries: &[Entry]) -> Vec<String> {
+    let mut lines = vec![
+        format!("Serial number: {}", id.serial),
+        format!("Hash algorithm: {}", id.hash_algorithm),
+        format!("Issuer name hash: {}", cms::hex(&id.issuer_name_hash)),
+        format!("Issuer key hash: {}", cms::hex(&id.issuer_key_hash)),
+    ];
+    if let Some(issuer) = entries.iter().find(|entry| id.issued_by(entry)) {
+        lines.push(format!(
+            "Issuer: {}, from {}",
+            dn::to_string(issuer.certificate().subject()),
+            issuer.location
+        ));
+    }
+    lines
+}
+
+fn cert_id(tlv: &Tlv) -> Option<CertId> {
+    if tlv.tag != 0x30 {
+        return None;
+    }
+    match tlv.children()?.as_slice() {
+        [algorithm, name_hash, key_hash, serial]
+            if algorithm.tag == 0x30
+                && name_hash.tag == 0x04
+                && key_hash.tag == 0x04
+                && serial.tag == 0x02 =>
+        {
+            Some(CertId {
+                hash_algorithm: hash_name(algorithm)?,
+                issuer_name_hash: name_hash.contents.to_vec(),
+                issuer_key_hash: key_hash.contents.to_vec(),
+                serial: cms::hex(serial.contents),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The name of a CertID's hash, as `--hash` takes it when it is one of those.
+fn hash_name(algorithm: &Tlv) -> Option<String> {
+    let oid = algorithm.children()?.first()?.contents;
+    match HASHES.iter().find(|(_, _, known)| *known == oid) {
+        Some((name, _, _)) => Some(String::from(*name)),
+        None => cms::algorithm(algorithm),
+    }
+}
+
+/// The statuses RFC 6960 gives a responder to answer with, by their number.
+const RESPONSE_STATUSES: &[&str] = &[
+    "successful",
+    "malformedRequest",
+    "internalError",
+    "tryLater",
+    "",
+    "sigRequired",
+    "unauthorized",
+];
+
+/// An RFC 6960 OCSP response, as a responder answers a request.
+pub struct OcspResponse {
+    pub status: String,
+    /// The basic response, which only a successful one has.
+    pub basic: Option<BasicResponse>,
+}
+
+/// The signed part of a successful response.
+pub struct BasicResponse {
+    pub responder: String,
+    pub produced_at: i64,
+    pub responses: Vec<SingleResponse>,
+    pub nonce: Option<Vec<u8>>,
+    /// The DER of the response data, signature algorithm and signature, to
+    /// check the signature with.
+    signed: Vec<u8>,
+    algorithm: Vec<u8>,
+    signature: Vec<u8>,
+    /// Certificates included to check the signature with, such as a delegated responder's.
+    pub certificates: Vec<Vec<u8>>,
+}
+
+/// The status of one certificate.
+pub struct SingleResponse {
+    pub id: CertId,
+    pub status: CertStatus,
+    pub this_update: i64,
+    pub next_update: Option<i64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CertStatus {
+    Good,
+    Revoked { at: i64, reason: Option<String> },
+    Unknown,
+}
+
+impl fmt::Display for CertStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CertStatus::Good => write!(f, "good"),
+            CertStatus::Revoked { at, reason } => {
+                let at = ASN1Time::from_timestamp(*at)
+                    .map(|at| at.to_string())
+                    .unwrap_or_default();
+                match reason {
+                    Some(reason) => write!(f, "revoked on {} ({})", at, reason),
+                    None => write!(f, "revoked on {}", at),
+                }
+            }
+            CertStatus::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Decodes an OCSP response, saying what is wrong with it if it can't.
+pub fn response(der: &[u8]) -> Result<OcspResponse, String> {
+    let invalid = || String::from("it is not a valid OCSP response");
+    let (response, _) = element(der)
+        .filter(|(response, _)| response.tag == 0x30)
+        .ok_or_else(invalid)?;
+    let fields = response.children().ok_or_else(invalid)?;
+    let status = match fields.first() {
+        Some(status) if status.tag == 0x0a && status.contents.len() == 1 => RESPONSE_STATUSES
+            .get(status.contents[0] as usize)
+            .filter(|name| !name.is_empty())
+            .map_or_else(
+                || format!("status {}", status.contents[0]),
+                |name| String::from(*name),
+            ),
+        _ => return Err(invalid()),
+    };
+    let bytes = match fields.get(1) {
+        Some(bytes) if bytes.tag == 0xa0 => bytes,
+        _ => {
+            return Ok(OcspResponse {
+                status,
+                basic: None,
+            })
+        }
+    };
+    let bytes = bytes
+        .children()
+        .and_then(|bytes| bytes.into_iter().next())
+        .ok_or_else(invalid)?;
+    let parts = bytes.children().ok_or_else(invalid)?;
+    match parts.as_slice() {
+        [kind, response] if kind.oid().is_some_and(|oid| oid.to_id_string() == BASIC) => {
+            let basic = basic(response.contents).ok_or_else(invalid)?;
+            Ok(OcspResponse {
+                status,
+                basic: Some(basic),
+            })
+        }
+        _ => Err(String::from("it is not a basic OCSP response")),
+    }
+}
+
+fn basic(der: &[u8]) -> Option<BasicResponse> {
+    let (basic, _) = element(der)?;
+    let parts = basic.children()?;
+    let (data, algorithm, signature) = match parts.as_slice() {
+        [data, algorithm, signature, ..] if signature.tag == 0x03 => (data, algorithm, signature),
+        _ => return None,
+    };
+    let certificates = match parts.get(3) {
+        Some(certs) if certs.tag == 0xa0 => certs
+            .children()?
+            .first()?
+            .children()?
+            .iter()
+            .map(|cert| cert.raw.to_vec())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut fields = data.children()?.into_iter().peekable();
+    if fields.peek()?.tag == 0xa0 {
+        fields.next();
+    }
+    let responder = fields.next()?;
+    let responder = match responder.tag {
+        0xa1 => {
+            let name = responder.children()?;
+            let (_, name) = X509Name::from_der(name.first()?.raw).ok()?;
+            dn::to_string(&name)
+        }
+        0xa2 => format!("key {}", cms::hex(responder.children()?.first()?.contents)),
+        _ => return None,
+    };
+    let produced_at = time(&fields.next()?)?;
+    let mut responses = Vec::new();
+    for single in fields.next()?.children()? {
+        responses.push(single_response(&single)?);
+    }
+    let mut nonce = None;
+    if let Some(extensions) = fields.next().filter(|extensions| extensions.tag == 0xa1) {
+        for extension in extensions.children()?.first()?.children()? {
+            let parts = extension.children()?;
+            if parts.first()?.oid()?.to_id_string() == NONCE {
+                let value = parts.last()?.contents;
+                nonce = Some(match element(value) {
+                    Some((inner, [])) if inner.tag == 0x04 => inner.contents.to_vec(),
+                    _ => value.to_vec(),
+                });
+            }
+        }
+    }
+    Some(BasicResponse {
+        responder,
+        produced_at,
+        responses,
+        nonce,
+        signed: data.raw.to_vec(),
+        algorithm: algorithm.raw.to_vec(),
+        signature: signature.raw.to_vec(),
+        certificates,
+    })
+}
+
+fn single_response(single: &Tlv) -> Option<SingleResponse> {
+    let fields = single.children()?;
+    let id = cert_id(fields.first()?)?;
+    let status = fields.get(1)?;
+    let status = match status.tag {
+        0x80 => CertStatus::Good,
+        0xa1 => {
+            let info = status.children()?;
+            let reason = info
+                .get(1)
+                .and_then(|reason| reason.children())
+                .and_then(|reason| reason.first().map(|code| code.contents.to_vec()))
+                .and_then(|code| crl::REASONS.get(*code.first()? as usize))
+                .filter(|name| !name.is_empty())
+                .map(|name| String::from(*name));
+            CertStatus::Revoked {
+                at: time(info.first()?)?,
+                reason,
+            }
+        }
+        0x82 => CertStatus::Unknown,
+        _ => return None,
+    };
+    let this_update = time(fields.get(2)?)?;
+    let next_update = match fields.get(3) {
+        Some(next) if next.tag == 0xa0 => Some(time(next.children()?.first()?)?),
+        _ => None,
+    };
+    Some(SingleResponse {
+        id,
+        status,
+        this_update,
+        next_update,
+    })
+}
+
+fn time(tlv: &Tlv) -> Option<i64> {
+    let (_, time) = ASN1Time::from_der(tlv.raw).ok()?;
+    Some(time.timestamp())
+}
+
+impl BasicResponse {
+    /// Whether a certificate's key made the signature over the response.
+    pub fn is_signed_by(&self, cert: &X509Certificate) -> bool {
+        let algorithm = AlgorithmIdentifier::from_der(&self.algorithm);
+        let signature = BitString::from_der(&self.signature);
+        match (algorithm, signature) {
+            (Ok((_, algorithm)), Ok((_, signature))) => {
+                verify_signature(cert.public_key(), &algorithm, &signature, &self.signed).is_ok()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// An OCSP request built for a certificate, written but not sent.
+pub struct Built {
+    pub output: String,
+    pub serial: String,
+    pub issuer: String,
+    pub hash: String,
+    pub nonce: Option<String>,
+    /// The OCSP responders the certificate names, to send the request to.
+    pub responders: Vec<String>,
+}
+
+/// Builds an unsigned OCSP request for the status of one certificate and
+/// writes it in DER, ready to be posted to a responder.
+pub fn build(
+    processor: &impl FileProcessor,
+    options: &OcspOptions,
+) -> Result<Built, Box<dyn std::error::Error>> {
+    let (cert_der, issuer_der) = load(processor, options)?;
+    let (_, cert) = parse_x509_certificate(&cert_der)?;
+    let (_, issuer) = parse_x509_certificate(&issuer_der)?;
+    let hash = options.hash.as_deref().unwrap_or("sha1");
+    let nonce = match options.nonce {
+        true => Some(random(16)?),
+        false => None,
+    };
+    let request = encode_request(&issuer, cert.raw_serial(), hash, nonce.as_deref())?;
+    processor.write(&options.output, &request)?;
+
+    Ok(Built {
+        output: options.output.clone(),
+        serial: cms::hex(cert.raw_serial()),
+        issuer: dn::to_string(issuer.subject()),
+        hash: String::from(hash),
+        nonce: nonce.map(|nonce| cms::hex(&nonce)),
+        responders: match &options.url {
+            Some(url) => vec![url.clone()],
+            None => responders(&cert),
+        },
+    })
+}
+
+/// The DER of the certificate `--cert` names and of its issuer, from
+/// `--issuer` or else the certificate after it.
+pub fn load(
+    processor: &impl FileProcessor,
+    options: &OcspOptions,
+) -> Result<(Vec<u8>, Vec<u8>), Box<dyn std::error::Error>> {
+    let mut chain = certificates(&options.cert, &processor.read(&options.cert)?)?;
+    let issuer_der = match &options.issuer {
+        Some(issuer) => certificates(issuer, &processor.read(issuer)?)?.remove(0),
+        None if chain.len() > 1 => chain.remove(1),
+        None => {
+            let err_msg = format!(
+                "Error: {} has no issuer certificate after it, please give one with --issuer.",
+                options.cert
+            );
+            return Err(err_msg.into());
+        }
+    };
+    let (_, cert) = parse_x509_certificate(&chain[0])?;
+    let (_, issuer) = parse_x509_certificate(&issuer_der)?;
+    if cert.issuer().as_raw() != issuer.subject().as_raw() {
+        let err_msg = format!(
+            "Error: {} was issued by {}, not {}.",
+            options.cert,
+            dn::to_string(cert.issuer()),
+            dn::to_string(issuer.subject())
+        );
+        return Err(err_msg.into());
+    }
+    let cert_der = chain.remove(0);
+    Ok((cert_der, issuer_der))
+}
+
+/// Random bytes, for nonces and serial numbers no CA will have issued.
+pub fn random(len: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut bytes = vec![0; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "Error: could not generate random bytes.")?;
+    Ok(bytes)
+}
+
+/// The DER of an unsigned request for the status of one serial number of an
+/// issuer, identified by the hash of its name and key.
+pub fn encode_request(
+    issuer: &X509Certificate,
+    serial: &[u8],
+    hash: &str,
+    nonce: Option<&[u8]>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let (_, algorithm, oid) = HASHES
+        .iter()
+        .find(|(name, _, _)| *name == hash)
+        .ok_or_else(|| {
+            format!(
+                "Error: --hash expects sha1, sha256, sha384 or sha512, not {}.",
+                hash
+            )
+        })?;
+    let mut cert_id = encode(0x30, &[encode(0x06, oid), vec![0x05, 0x00]].concat());
+    cert_id.extend(encode(
+        0x04,
+        digest(algorithm, issuer.subject().as_raw()).as_ref(),
+    ));
+    cert_id.extend(encode(
+        0x04,
+        digest(algorithm, &issuer.public_key().subject_public_key.data).as_ref(),
+    ));
+    cert_id.extend(encode(0x02, serial));
+    let mut tbs = encode(0x30, &encode(0x30, &encode(0x30, &cert_id)));
+    if let Some(nonce) = nonce {
+        let extension = [encode(0x06, NONCE_OID), encode(0x04, &encode(0x04, nonce))];
+        let extensions = encode(0x30, &encode(0x30, &extension.concat()));
+        tbs.extend(encode(0xa2, &extensions));
+    }
+    Ok(encode(0x30, &encode(0x30, &tbs)))
+}
+
+/// The certificates in a file, whether PEM or a single one in DER.
+fn certificates(path: &str, bytes: &[u8]) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+    let pems: Vec<Vec<u8>> = Pem::iter_from_buffer(bytes)
+        .filter_map(Result::ok)
+        .filter(|pem| pem.label == "CERTIFICATE")
+        .map(|pem| pem.contents)
+        .collect();
+    if !pems.is_empty() {
+        return Ok(pems);
+    }
+    match parse_x509_certificate(bytes) {
+        Ok(_) => Ok(vec![bytes.to_vec()]),
+        Err(_) => Err(format!("Error: {} does not hold a certificate.", path).into()),
+    }
+}
+
+/// The OCSP URLs in a certificate's Authority Information Access.
+pub fn responders(cert: &X509Certificate) -> Vec<String> {
+    let mut urls = Vec::new();
+    for extension in cert.extensions() {
+        if let ParsedExtension::AuthorityInfoAccess(access) = extension.parsed_extension() {
+            for description in &access.accessdescs {
+                match &description.access_location {
+                    GeneralName::URI(url)
+                        if description.access_method == OID_PKIX_ACCESS_DESCRIPTOR_OCSP =>
+                    {
+                        urls.push(String::from(*url))
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    urls
+}
+
+impl fmt::Display for Built {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Wrote an OCSP request to {}", self.output)?;
+        writeln!(f, "Serial number: {}", self.serial)?;
+        writeln!(f, "Issuer: {}", self.issuer)?;
+        writeln!(f, "Hash algorithm: {}", self.hash)?;
+        match &self.nonce {
+            Some(nonce) => writeln!(f, "Nonce: {}", nonce)?,
+            None => writeln!(f, "Nonce: none")?,
+        }
+        let responder = match self.responders.first() {
+            Some(responder) => responder.as_str(),
+            None => "RESPONDER_URL",
+        };
+        writeln!(
+            f,
+            "Send it with: curl --data-binary @{} -H 'Content-Type: application/ocsp-request' {}",
+            self.output, responder
+        )
+    }
+}
+
+fn unsigned(bytes: &[u8]) -> Option<u64> {
+    if bytes.is_empty() || bytes.len() > 8 {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .fold(0, |value, byte| value << 8 | *byte as u64),
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{build, find, read, response, CertStatus, OcspOptions};
+    use crate::test::{google_der, FakeProcessor};
+
+    const REQUEST: &[u8] = include_bytes!("../resources/ocsp-request.der");
+
+    #[test]
+    fn should_decode_the_serials_hashes_and_nonce() {
+        let request = read("request.der", &find(REQUEST).unwrap()).unwrap();
+
+        assert_eq!(request.version, 1);
+        let serials: Vec<&str> = request
+            .certificates
+            .iter()
+            .map(|id| id.serial.as_str())
+            .collect();
+        assert_eq!(serials, vec!["1a:2b", "03:ff"]);
+        assert_eq!(request.certificates[0].hash_algorithm, "sha256");
+        assert_eq!(request.certificates[0].issuer_name_hash.len(), 32);
+        assert_eq!(
+            request.nonce.as_deref(),
+            Some("f7:d0:e4:71:0c:03:2b:07:ee:f7:4a:4f:ed:72:d2:b3")
+        );
+        assert_eq!(request.nonce_length, 16);
+        assert!(request.extensions.is_empty());
+        assert_eq!(request.signature_algorithm, None);
+        assert!(request.warnings().is_empty());
+    }
+
+    #[test]
+    fn should_find_a_request_in_a_get_url() {
+        let url = "http://ocsp.example.com/MIHhMIHeMIG2MFkwVzANBglghkgBZQMEAgEFAAQgWYeNbO8QPM4G%2Ft2aroikq68YTAZ49gPX3EoDBTKHHcMEIPfeC%2BBv9fL1ChLf3amtk6eOC5Bch7i087H%2Fo%2FNdfLTbAgIaKzBZMFcwDQYJYIZIAWUDBAIBBQAEIFmHjWzvEDzOBv7dmq6IpKuvGEwGePYD19xKAwUyhx3DBCD33gvgb%2FXy9QoS392prZOnjguQXIe4tPOx%2F6PzXXy02wICA%2F%2BiIzAhMB8GCSsGAQUFBzABAgQSBBD30ORxDAMrB%2B73Sk%2FtctKz\n";
+
+        assert_eq!(find(url.as_bytes()).as_deref(), Some(REQUEST));
+    }
+
+    #[test]
+    fn should_not_take_a_certificate_for_a_request() {
+        assert_eq!(find(&google_der()), None);
+        assert!(read("google.der", &google_der()).is_err());
+    }
+
+    #[test]
+    fn should_build_a_request_for_a_certificate_and_the_issuer_after_it() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("chain.pem", include_str!("../resources/example-chain.pem"));
+        let options = OcspOptions {
+            cert: String::from("chain.pem"),
+            output: String::from("req.der"),
+            ..OcspOptions::default()
+        };
+
+        let built = build(&processor, &options).unwrap();
+
+        assert_eq!(built.serial, "03");
+        assert_eq!(built.issuer, "CN=Example Issuing CA");
+        let der = processor.written.lock().unwrap()["req.der"].clone();
+        let request = read("req.der", &der).unwrap();
+        assert_eq!(request.certificates[0].serial, "03");
+        assert_eq!(request.certificates[0].hash_algorithm, "sha1");
+        assert_eq!(request.nonce, None);
+        assert!(built.to_string().ends_with(
+            "Send it with: curl --data-binary @req.der -H 'Content-Type: application/ocsp-request' RESPONDER_URL\n"
+        ));
+    }
+
+    #[test]
+    fn should_decode_a_response_and_check_who_signed_it() {
+        let response = response(include_bytes!("../resources/ocsp-response.der")).unwrap();
+        assert_eq!(response.status, "successful");
+        let basic = response.basic.unwrap();
+        assert_eq!(basic.nonce, None);
+        assert_eq!(basic.responses.len(), 1);
+        let single = &basic.responses[0];
+        assert_eq!(single.id.hash_algorithm, "sha1");
+        assert_eq!(single.status, CertStatus::Good);
+        assert_eq!(single.this_update, 1791936000);
+        assert_eq!(single.next_update, Some(1792281600));
+
+        let pem = include_str!("../resources/self-signed.crt");
+        let der = parse_x509_pem(pem.as_bytes()).unwrap().1.contents;
+        assert!(basic.is_signed_by(&parse_x509_certificate(&der).unwrap().1));
+        let google = google_der();
+        assert!(!basic.is_signed_by(&parse_x509_certificate(&google).unwrap().1));
+
+        assert_eq!(
+            super::response(&[0x30, 0x03, 0x0a, 0x01, 0x01])
+                .unwrap()
+                .status,
+            "malformedRequest"
+        );
+        assert!(super::response(b"not a response").is_err());
+    }
+
+    #[test]
+    fn should_add_a_nonce_and_refuse_the_wrong_issuer() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("chain.pem", include_str!("../resources/example-chain.pem"));
+        processor.add_file("root.pem", include_str!("../resources/example-root.crt"));
+        let mut options = OcspOptions {
+            cert: String::from("chain.pem"),
+            output: String::from("req.der"),
+            hash: Some(String::from("sha256")),
+            nonce: true,
+            ..OcspOptions::default()
+        };
+
+        build(&processor, &options).unwrap();
+        let der = processor.written.lock().unwrap()["req.der"].clone();
+        let request = read("req.der", &der).unwrap();
+        assert_eq!(request.certificates[0].hash_algorithm, "sha256");
+        assert_eq!(request.nonce_length, 16);
+
+        options.issuer = Some(String::from("root.pem"));
+        assert_eq!(
+            build(&processor, &options).err().unwrap().to_string(),
+            "Error: chain.pem was issued by CN=Example Issuing CA, not CN=Example Root CA."
+        );
+    }
+}
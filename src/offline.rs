@@ -0,0 +1,202 @@
+use std::net::IpAddr;
+
+use crate::http::{Request, Response};
+use crate::ldap::Directory;
+use crate::pcsc::Exchange;
+use crate::pkcs11::Pkcs11Options;
+use crate::remote::{ConnectOptions, Family, Handshake, Server};
+use crate::store::{NamedCertificate, StoreOptions};
+use crate::FileProcessor;
+
+/// Passes every call through to `processor`, except that with `--offline` it
+/// refuses whatever would reach the network: looking up hosts, handshakes, LDAP
+/// and HTTP, which covers AIA, OCSP, CRLs, CT logs and DNS-over-HTTPS, and
+/// running programs, which are only ever dig or a helper that gets a token.
+pub struct Offline<P> {
+    pub processor: P,
+    pub enabled: bool,
+}
+
+impl<P> Offline<P> {
+    fn check(&self, what: impl FnOnce() -> String) -> Result<(), Box<dyn std::error::Error>> {
+        if self.enabled {
+            return Err(format!(
+                "Error: {} needs the network, which --offline turns off.",
+                what()
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl<P: FileProcessor> FileProcessor for Offline<P> {
+    fn is_file(&self, path: &str) -> bool {
+        self.processor.is_file(path)
+    }
+    fn is_dir(&self, path: &str) -> bool {
+        self.processor.is_dir(path)
+    }
+    fn read(&self, path: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.processor.read(path)
+    }
+    fn read_to_string(&self, path: &str) -> Result<String, Box<dyn std::error::Error>> {
+        self.processor.read_to_string(path)
+    }
+    fn read_stdin(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.processor.read_stdin()
+    }
+    fn read_clipboard(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.processor.read_clipboard()
+    }
+    fn write_clipboard(&self, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.processor.write_clipboard(text)
+    }
+    fn write(&self, path: &str, contents: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        self.processor.write(path, contents)
+    }
+    fn read_dir(&self, path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        self.processor.read_dir(path)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.processor.var(name)
+    }
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        family: Option<Family>,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error>> {
+        self.check(|| format!("Looking up {}", host))?;
+        self.processor.resolve(host, port, family)
+    }
+    fn handshake(
+        &self,
+        server: &Server,
+        options: &ConnectOptions,
+    ) -> Result<Handshake, Box<dyn std::error::Error>> {
+        self.check(|| format!("Connecting to {}", server))?;
+        self.processor.handshake(server, options)
+    }
+    fn read_store(&self, store: &StoreOptions) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+        self.processor.read_store(store)
+    }
+    fn read_nss(&self, dir: &str) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        self.processor.read_nss(dir)
+    }
+    fn now(&self) -> i64 {
+        self.processor.now()
+    }
+    fn read_keychain(
+        &self,
+        keychain: &str,
+    ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        self.processor.read_keychain(keychain)
+    }
+    fn read_pkcs11(
+        &self,
+        token: &Pkcs11Options,
+        pin: Option<&str>,
+    ) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+        self.processor.read_pkcs11(token, pin)
+    }
+    fn transmit(
+        &self,
+        reader: Option<&str>,
+        commands: &[Vec<u8>],
+    ) -> Result<Exchange, Box<dyn std::error::Error>> {
+        self.processor.transmit(reader, commands)
+    }
+    fn ldap(
+        &self,
+        directory: &Directory,
+        requests: &[Vec<u8>],
+        options: &ConnectOptions,
+    ) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+        self.check(|| format!("Searching {}", directory))?;
+        self.processor.ldap(directory, requests, options)
+    }
+    fn http(
+        &self,
+        request: &Request,
+        options: &ConnectOptions,
+    ) -> Result<Response, Box<dyn std::error::Error>> {
+        self.check(|| format!("Fetching {}", request.url))?;
+        self.processor.http(request, options)
+    }
+    fn run(
+        &self,
+        program: &str,
+        args: &[String],
+        env: &[(String, String)],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.check(|| format!("Running {}", program))?;
+        self.processor.run(program, args, env)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Offline;
+    use crate::http::{Request, Response, Url};
+    use crate::remote::{ConnectOptions, Server};
+    use crate::test::{self_signed_der, FakeProcessor};
+    use crate::{execute, FileProcessor};
+
+    fn processor() -> FakeProcessor {
+        let mut processor = FakeProcessor::default();
+        processor.files.insert(
+            String::from("self-signed.crt"),
+            String::from(include_str!("../resources/self-signed.crt")),
+        );
+        processor.add_remote("example.com:443", vec![self_signed_der()]);
+        processor.responses.insert(
+            String::from("http://crl.example.com/ca.crl"),
+            Response {
+                status: 200,
+                headers: Vec::new(),
+                body: Vec::new(),
+            },
+        );
+        processor
+    }
+
+    #[test]
+    fn should_refuse_the_network_when_offline() {
+        let connect = ConnectOptions::default();
+        let server = Server {
+            host: String::from("example.com"),
+            port: 443,
+            address: None,
+        };
+        let request = Request::get(Url::parse("http://crl.example.com/ca.crl").unwrap());
+
+        let online = Offline {
+            processor: processor(),
+            enabled: false,
+        };
+        assert!(online.handshake(&server, &connect).is_ok());
+        assert!(online.http(&request, &connect).is_ok());
+
+        let offline = Offline {
+            processor: processor(),
+            enabled: true,
+        };
+        assert_eq!(
+            offline.http(&request, &connect).err().unwrap().to_string(),
+            "Error: Fetching http://crl.example.com/ca.crl needs the network, which --offline turns off."
+        );
+        assert!(offline.handshake(&server, &connect).is_err());
+        assert!(offline.run("dig", &[], &[]).is_err());
+        assert!(offline.read("self-signed.crt").is_ok());
+    }
+
+    #[test]
+    fn should_still_decode_files_offline() {
+        let args = |args: &[&str]| args.iter().map(|arg| String::from(*arg)).collect();
+        assert!(execute(processor(), args(&["--offline", "self-signed.crt"])).is_ok());
+        assert!(execute(processor(), args(&["--offline", "example.com:443"])).is_err());
+        assert!(execute(processor(), args(&["example.com:443"])).is_ok());
+    }
+}
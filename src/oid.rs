@@ -0,0 +1,21 @@
+use x509_parser::der_parser::oid::Oid;
+use x509_parser::objects::{oid2sn, oid_registry};
+
+use crate::gost;
+use crate::pq;
+use crate::sm2;
+
+/// The short name of an OID, falling back to its dotted form. GOST, SM and
+/// post-quantum algorithms, which the registry knows by identifiers at best,
+/// are named as their standards write them.
+pub fn name(oid: &Oid) -> String {
+    let name = gost::name(oid)
+        .or_else(|| sm2::name(oid))
+        .or_else(|| pq::name(oid));
+    if let Some(name) = name {
+        return String::from(name);
+    }
+    oid2sn(oid, oid_registry())
+        .map(String::from)
+        .unwrap_or_else(|_| oid.to_id_string())
+}
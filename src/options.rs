@@ -0,0 +1,1818 @@
+use std::time::Duration;
+
+use crate::aws::{AwsOptions, AwsService};
+use crate::clipboard::Copyable;
+use crate::cloud::Cloud;
+use crate::cluster::{ClusterCommand, ClusterOptions};
+use crate::compliance::Profile;
+use crate::conformance::Strictness;
+use crate::crl;
+use crate::ct_monitor::CtMonitorOptions;
+use crate::ct_search::CtSearchOptions;
+use crate::encoding::InputEncoding;
+use crate::expiry::ExpiryFilter;
+use crate::findings::OutputFormat;
+use crate::http::Url;
+use crate::inventory::{InventoryCommand, InventoryOptions};
+use crate::ldap::LdapOptions;
+use crate::lifetime::MaxValidity;
+use crate::ocsp::{OcspCommand, OcspOptions};
+use crate::pin::Expectations;
+use crate::piv::PivOptions;
+use crate::pkcs11::Pkcs11Options;
+use crate::policy_file::PolicyFile;
+use crate::proxy::Proxy;
+use crate::psl::SuffixList;
+use crate::remote::{ConnectOptions, Family};
+use crate::scan::ScanOptions;
+use crate::sct::Log;
+use crate::sort::{GroupBy, SortBy};
+use crate::ssh::HostKeyReferences;
+use crate::store::{KeychainOptions, StoreOptions};
+use crate::trust_path::Anchor;
+use crate::trust_store::TrustStoreOptions;
+use crate::vault::{VaultCommand, VaultOptions};
+
+/// Everything the user asked for on the command line.
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Certificates, directories or `host:port` pairs given directly as arguments.
+    pub targets: Vec<String>,
+    /// A file listing one target per line, given with `--targets`.
+    pub targets_file: Option<String>,
+    /// Decode what was copied to the clipboard, from `--clipboard`.
+    pub clipboard: bool,
+    /// What to copy to the clipboard once certificates are decoded, from `--copy`.
+    pub copy: Option<Copyable>,
+    /// How to print what was found, from `--output`.
+    pub output: OutputFormat,
+    /// Files and stdin hold Kubernetes Secret manifests, from `--k8s`.
+    pub k8s: bool,
+    /// Files are web server configs to follow to their certificates, from `--server-config`.
+    pub server_config: bool,
+    /// How files and stdin are encoded, from `--input-encoding`.
+    pub input_encoding: InputEncoding,
+    /// The file a detached CMS signature was made over, from `--content`,
+    /// read into `detached_content` before use.
+    pub content: Option<String>,
+    pub detached_content: Option<Vec<u8>>,
+    /// Explain each field shown in plain English, from `--explain`.
+    pub explain: bool,
+    /// Show what the TBS certificate of a precertificate will be in the final
+    /// certificate, from `--reconstruct-tbs`.
+    pub reconstruct_tbs: bool,
+    /// The known_hosts file and the host whose SSHFP records to compare SSH host
+    /// keys with, from `--known-hosts` and `--sshfp`, read into `host_keys` before use.
+    pub known_hosts: Option<String>,
+    pub sshfp: Option<String>,
+    pub host_keys: HostKeyReferences,
+    /// The DNS-over-HTTPS resolver to look up SSHFP, MX and TXT records with
+    /// instead of dig and the local resolver, from `--doh`.
+    pub doh: Option<Url>,
+    /// A newer CT log list to check SCTs against than the bundled one, from
+    /// `--ct-log-list`, read into `ct_logs` before use.
+    pub ct_log_list: Option<String>,
+    pub ct_logs: Option<Vec<Log>>,
+    /// Build the path from each certificate to a trust anchor and explain every
+    /// step of it, from `--explain-trust`.
+    pub explain_trust: bool,
+    /// Name the trust anchor each certificate chains to, fetching any intermediates
+    /// left out, from `--find-anchor`.
+    pub find_anchor: bool,
+    /// The certificate policies that have to be valid for each certificate's path
+    /// to a trust anchor, from `--require-policy`.
+    pub required_policies: Vec<String>,
+    /// The longest a certificate may be valid for, the CA/B Forum's limit or one's
+    /// own, from `--max-validity`.
+    pub max_validity: Option<MaxValidity>,
+    /// Warn about where certificates stray from RFC 5280 and the CA/B Forum Baseline
+    /// Requirements, from `--lint`.
+    pub lint: bool,
+    /// The serial numbers to look for in CRLs, from `--find-serial`, instead of
+    /// keeping every certificate they revoke.
+    pub find_serials: Vec<Vec<u8>>,
+    /// The public keys and fingerprints the certificates found have to match, from
+    /// `--expect-pin` and `--expect-fingerprint`.
+    pub expect: Expectations,
+    /// The names to say whether each certificate covers, from `--covers`.
+    pub covers: Vec<String>,
+    /// Summarize the registrable domains each certificate covers and warn about
+    /// names over public suffixes, from `--domains`.
+    pub domains: bool,
+    /// The Public Suffix List to use instead of the system's, from
+    /// `--public-suffix-list`, read into `suffixes` before use.
+    pub public_suffix_list: Option<String>,
+    pub suffixes: Option<SuffixList>,
+    /// The algorithm rules to check each certificate against, from `--profile`.
+    pub profile: Option<Profile>,
+    /// Whether to warn about certificates that deviate from DER and RFC 5280, or
+    /// refuse them, from `--lenient` and `--strict`.
+    pub strictness: Strictness,
+    /// Only show certificates that are expired, not yet valid or expiring soon,
+    /// from `--expired`, `--not-yet-valid` and `--expiring-within`.
+    pub expiry_filter: ExpiryFilter,
+    /// Show a certificate found in several places once, with every place it was
+    /// found, from `--dedupe`.
+    pub dedupe: bool,
+    /// The order to show certificates in and what to gather them under, from
+    /// `--sort-by` and `--group-by`.
+    pub sort_by: Option<SortBy>,
+    pub group_by: Option<GroupBy>,
+    /// The trust stores to take anchors from instead of the system CA bundle, from
+    /// `--trust-anchors`, read into `anchors` before use.
+    pub trust_anchors: Vec<String>,
+    pub anchors: Option<Vec<Anchor>>,
+    /// The password for keystores, from `--storepass`.
+    pub storepass: Option<String>,
+    /// `--timeout`, `--proxy`, `--alpn`, `--quic`, `-4` and `-6`
+    pub connect: ConnectOptions,
+    /// Refuse anything that needs the network instead of reaching it, from `--offline`.
+    pub offline: bool,
+    /// `--client-cert` and `--client-key`, loaded into `connect` before use.
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    /// `--jobs`, `--rate-limit`, `--retries` and `--all-addresses`
+    pub scan: ScanOptions,
+    /// Set by the `store` command, with `--location`, `--store` and `--subject`.
+    pub store: Option<StoreOptions>,
+    /// Set by the `keychain` command, with `--keychain` and `--label`.
+    pub keychains: Option<KeychainOptions>,
+    /// Set by the `piv` command, with `--reader`.
+    pub piv: Option<PivOptions>,
+    /// The token to read, from `--pkcs11`, `--slot` and `--pin-env`.
+    pub pkcs11: Option<Pkcs11Options>,
+    /// Set by the `trust-store` command, with `--bundle` and `--expiring-within`.
+    pub trust_store: Option<TrustStoreOptions>,
+    /// Set by the `k8s scan` and `k8s kubeconfig` commands, with `--kubeconfig`,
+    /// `--context`, `--namespace`, `--webhooks`, `--apiserver` and `--expiring-within`.
+    pub cluster: Option<ClusterOptions>,
+    /// Set by the `ct-search` command, with the domain and `--entry`.
+    pub ct_search: Option<CtSearchOptions>,
+    /// Set by the `ct-monitor` command, with `--domain`, `--state`, `--interval`
+    /// and `--webhook`.
+    pub ct_monitor: Option<CtMonitorOptions>,
+    /// Set by the `grade` command, which grades what it is given instead of decoding it.
+    pub grade: bool,
+    /// Set by the `check` command, with the policy file from `--policy`, read into
+    /// `policy` before use.
+    pub check: bool,
+    pub policy_file: Option<String>,
+    pub policy: Option<PolicyFile>,
+    /// Set by the `inventory save` and `inventory diff` commands, with the snapshot.
+    pub inventory: Option<InventoryOptions>,
+    /// Set by the `acme` command, with the order URL, ACME client directory or
+    /// domain to find the certificate of.
+    pub acme: Option<String>,
+    /// Set by the `est` command, with the EST server to fetch the CA certificates of.
+    pub est: Option<String>,
+    /// Set by the `scep` command, with the SCEP endpoint to ask for its CA certificates.
+    pub scep: Option<String>,
+    /// Set by the `mta-sts` command, with the mail domain to check.
+    pub mta_sts: Option<String>,
+    /// Set by the `ldap` command, with the directory's URL, `--base`, `--filter`,
+    /// `--bind-dn` and `--password-env`.
+    pub ldap: Option<LdapOptions>,
+    /// Set by the `ocsp request` and `ocsp test` commands, with `--cert`,
+    /// `--issuer`, `-o`, `--hash`, `--nonce` and `--url`.
+    pub ocsp: Option<OcspOptions>,
+    /// Set by the `vault ca`, `vault crl` and `vault cert` commands, with `--mount`.
+    pub vault: Option<VaultOptions>,
+    /// Set by the `aws acm` and `aws iam` commands, with `--region`.
+    pub aws: Option<AwsOptions>,
+    /// The cloud certificate services to read, from `--cloud`.
+    pub clouds: Vec<Cloud>,
+}
+
+impl Options {
+    pub fn parse(args: Vec<String>) -> Result<Options, Box<dyn std::error::Error>> {
+        let mut options = Options::default();
+        // `k8s` on its own could be a file, so it is only a command with a subcommand
+        let cluster = match (args.first(), args.get(1)) {
+            (Some(k8s), Some(subcommand)) if k8s == "k8s" => match subcommand.as_str() {
+                "scan" => Some(ClusterCommand::Scan),
+                "kubeconfig" => Some(ClusterCommand::Kubeconfig),
+                _ => None,
+            },
+            _ => None,
+        };
+        let inventory = match (args.first(), args.get(1)) {
+            (Some(inventory), Some(subcommand)) if inventory == "inventory" => {
+                match subcommand.as_str() {
+                    "save" => Some(InventoryCommand::Save),
+                    "diff" => Some(InventoryCommand::Diff),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let ocsp = match (args.first(), args.get(1)) {
+            (Some(ocsp), Some(subcommand)) if ocsp == "ocsp" => match subcommand.as_str() {
+                "request" => Some(OcspCommand::Request),
+                "test" => Some(OcspCommand::Test),
+                _ => None,
+            },
+            _ => None,
+        };
+        let aws = match (args.first(), args.get(1)) {
+            (Some(aws), Some(subcommand)) if aws == "aws" => match subcommand.as_str() {
+                "acm" => Some(AwsService::Acm),
+                "iam" => Some(AwsService::Iam),
+                _ => None,
+            },
+            _ => None,
+        };
+        let vault = match (args.first(), args.get(1)) {
+            (Some(vault), Some(subcommand)) if vault == "vault" => match subcommand.as_str() {
+                "ca" => Some(VaultCommand::Ca),
+                "crl" => Some(VaultCommand::Crl),
+                "cert" => Some(VaultCommand::Cert),
+                _ => None,
+            },
+            _ => None,
+        };
+        // Like `k8s`, these commands on their own could be files
+        let grade = args.len() > 1 && args[0] == "grade";
+        let check = args.len() > 1 && args[0] == "check";
+        let acme = args.len() > 1 && args[0] == "acme";
+        let est = args.len() > 1 && args[0] == "est";
+        let scep = args.len() > 1 && args[0] == "scep";
+        let mta_sts = args.len() > 1 && args[0] == "mta-sts";
+        let ldap = args.len() > 1 && args[0] == "ldap";
+        let store = args.len() > 1 && args[0] == "store";
+        let keychain = args.len() > 1 && args[0] == "keychain";
+        let trust_store = args.len() > 1 && args[0] == "trust-store";
+        let ct_search = args.len() > 1 && args[0] == "ct-search";
+        let ct_monitor = args.len() > 1 && args[0] == "ct-monitor";
+        let piv = args.len() > 1 && args[0] == "piv";
+        let mut args = args.into_iter().peekable();
+
+        // Commands that read from somewhere other than files and servers come first
+        let command = match args.peek().map(String::as_str) {
+            Some("k8s") if cluster.is_some() => {
+                args.next();
+                let command = cluster.expect("checked by the guard");
+                options.cluster = Some(ClusterOptions {
+                    command,
+                    ..ClusterOptions::default()
+                });
+                Some(match command {
+                    ClusterCommand::Scan => "k8s scan",
+                    ClusterCommand::Kubeconfig => "k8s kubeconfig",
+                })
+            }
+            Some("inventory") if inventory.is_some() => {
+                args.next();
+                let command = inventory.expect("checked by the guard");
+                options.inventory = Some(InventoryOptions {
+                    command,
+                    snapshot: String::new(),
+                });
+                Some(match command {
+                    InventoryCommand::Save => "inventory save",
+                    InventoryCommand::Diff => "inventory diff",
+                })
+            }
+            Some("grade") if grade => {
+                args.next();
+                options.grade = true;
+                None
+            }
+            Some("check") if check => {
+                args.next();
+                options.check = true;
+                None
+            }
+            Some("acme") if acme => {
+                options.acme = Some(String::new());
+                Some("acme")
+            }
+            Some("est") if est => {
+                options.est = Some(String::new());
+                Some("est")
+            }
+            Some("scep") if scep => {
+                options.scep = Some(String::new());
+                Some("scep")
+            }
+            Some("mta-sts") if mta_sts => {
+                options.mta_sts = Some(String::new());
+                // Mail servers are reached on port 25 and upgraded with STARTTLS, without ALPN
+                options.connect.starttls = true;
+                options.connect.alpn = Vec::new();
+                Some("mta-sts")
+            }
+            Some("ldap") if ldap => {
+                options.ldap = Some(LdapOptions::default());
+                Some("ldap")
+            }
+            Some("ocsp") if ocsp.is_some() => {
+                args.next();
+                let command = ocsp.expect("checked by the guard");
+                options.ocsp = Some(OcspOptions {
+                    command,
+                    ..OcspOptions::default()
+                });
+                Some(match command {
+                    OcspCommand::Request => "ocsp request",
+                    OcspCommand::Test => "ocsp test",
+                })
+            }
+            Some("vault") if vault.is_some() => {
+                args.next();
+                let command = vault.expect("checked by the guard");
+                options.vault = Some(VaultOptions {
+                    command,
+                    ..VaultOptions::default()
+                });
+                Some(match command {
+                    VaultCommand::Ca => "vault ca",
+                    VaultCommand::Crl => "vault crl",
+                    VaultCommand::Cert => "vault cert",
+                })
+            }
+            Some("aws") if aws.is_some() => {
+                args.next();
+                let service = aws.expect("checked by the guard");
+                options.aws = Some(AwsOptions {
+                    service,
+                    region: None,
+                });
+                Some(match service {
+                    AwsService::Acm => "aws acm",
+                    AwsService::Iam => "aws iam",
+                })
+            }
+            Some("store") if store => {
+                options.store = Some(StoreOptions::default());
+                Some("store")
+            }
+            Some("piv") if piv => {
+                options.piv = Some(PivOptions::default());
+                Some("piv")
+            }
+            Some("keychain") if keychain => {
+                options.keychains = Some(KeychainOptions::default());
+                Some("keychain")
+            }
+            Some("trust-store") if trust_store => {
+                options.trust_store = Some(TrustStoreOptions::default());
+                Some("trust-store")
+            }
+            Some("ct-search") if ct_search => {
+                options.ct_search = Some(CtSearchOptions::default());
+                Some("ct-search")
+            }
+            Some("ct-monitor") if ct_monitor => {
+                options.ct_monitor = Some(CtMonitorOptions::default());
+                Some("ct-monitor")
+            }
+            _ => None,
+        };
+        if command.is_some() {
+            args.next();
+        }
+        let mut keychains = Vec::new();
+        let mut slot = None;
+        let mut pin_env = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--targets" => options.targets_file = Some(value_of(&arg, args.next())?),
+                "--proxy" => {
+                    let proxy = Proxy::parse(&value_of(&arg, args.next())?)?;
+                    options.connect.http_proxy = Some(proxy.clone());
+                    options.connect.proxy = Some(proxy);
+                }
+                "-4" | "-6" => {
+                    let family = if arg == "-4" { Family::V4 } else { Family::V6 };
+                    if options
+                        .connect
+                        .family
+                        .is_some_and(|chosen| chosen != family)
+                    {
+                        return Err(
+                            String::from("Error: -4 and -6 cannot be used together.").into()
+                        );
+                    }
+                    options.connect.family = Some(family);
+                }
+                "--alpn" => {
+                    let protocols = value_of(&arg, args.next())?;
+                    options.connect.alpn = protocols
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|protocol| !protocol.is_empty())
+                        .map(String::from)
+                        .collect();
+                }
+                "--clipboard" => options.clipboard = true,
+                "--pkcs11" => {
+                    options.pkcs11 = Some(Pkcs11Options {
+                        module: value_of(&arg, args.next())?,
+                        ..Pkcs11Options::default()
+                    })
+                }
+                "--slot" => {
+                    let err_msg = format!("Error: {} expects a slot number.", arg);
+                    slot = Some(value_of(&arg, args.next())?.parse().map_err(|_| err_msg)?);
+                }
+                "--pin-env" => pin_env = Some(value_of(&arg, args.next())?),
+                "--copy" => options.copy = Some(Copyable::parse(&value_of(&arg, args.next())?)?),
+                "--output" => options.output = OutputFormat::parse(&value_of(&arg, args.next())?)?,
+                "--k8s" => options.k8s = true,
+                "--server-config" => options.server_config = true,
+                "--input-encoding" => {
+                    options.input_encoding = InputEncoding::parse(&value_of(&arg, args.next())?)?
+                }
+                "--content" => options.content = Some(value_of(&arg, args.next())?),
+                "--explain" => options.explain = true,
+                "--reconstruct-tbs" => options.reconstruct_tbs = true,
+                "--known-hosts" => options.known_hosts = Some(value_of(&arg, args.next())?),
+                "--sshfp" => options.sshfp = Some(value_of(&arg, args.next())?),
+                "--doh" => {
+                    let url = Url::parse(&value_of(&arg, args.next())?)?;
+                    if !url.https {
+                        return Err(String::from(
+                            "Error: --doh expects the https:// URL of a DNS-over-HTTPS resolver.",
+                        )
+                        .into());
+                    }
+                    options.doh = Some(url);
+                }
+                "--ct-log-list" => options.ct_log_list = Some(value_of(&arg, args.next())?),
+                "--explain-trust" => options.explain_trust = true,
+                "--find-anchor" => options.find_anchor = true,
+                "--require-policy" => {
+                    let policy = value_of(&arg, args.next())?;
+                    let valid = policy.split('.').count() > 1
+                        && policy
+                            .split('.')
+                            .all(|arc| !arc.is_empty() && arc.bytes().all(|b| b.is_ascii_digit()));
+                    if !valid {
+                        let err_msg = format!(
+                            "Error: --require-policy expects a policy OID such as 2.23.140.1.1, not {}.",
+                            policy
+                        );
+                        return Err(err_msg.into());
+                    }
+                    options.required_policies.push(policy);
+                }
+                "--trust-anchors" => options.trust_anchors.push(value_of(&arg, args.next())?),
+                "--lint" => options.lint = true,
+                "--lenient" | "--strict" => {
+                    let strictness = match arg.as_str() {
+                        "--lenient" => Strictness::Lenient,
+                        _ => Strictness::Strict,
+                    };
+                    if options.strictness != Strictness::Default && options.strictness != strictness
+                    {
+                        return Err(String::from(
+                            "Error: --lenient and --strict cannot be used together.",
+                        )
+                        .into());
+                    }
+                    options.strictness = strictness;
+                }
+                "--max-validity" => {
+                    let value = value_of(&arg, args.next())?;
+                    let max = MaxValidity::parse(&value).ok_or_else(|| {
+                        format!(
+                            "Error: --max-validity expects cabf or a duration such as 90d, not {}.",
+                            value
+                        )
+                    })?;
+                    options.max_validity = Some(max);
+                }
+                "--storepass" => options.storepass = Some(value_of(&arg, args.next())?),
+                "--client-cert" => options.client_cert = Some(value_of(&arg, args.next())?),
+                "--client-key" => options.client_key = Some(value_of(&arg, args.next())?),
+                "--quic" if cfg!(feature = "quic") => options.connect.quic = true,
+                "--quic" => {
+                    let err_msg =
+                        "Error: --quic needs cert-decoder to be built with the quic feature.";
+                    return Err(String::from(err_msg).into());
+                }
+                "--location" | "--store" | "--subject" => {
+                    let store = options.store.as_mut().ok_or_else(|| {
+                        format!("Error: {} can only be used with the store command.", arg)
+                    })?;
+                    let value = value_of(&arg, args.next())?;
+                    match arg.as_str() {
+                        "--location" => store.location = StoreOptions::parse_location(&value)?,
+                        "--store" => store.name = value,
+                        _ => store.subject = Some(value),
+                    }
+                }
+                "--base" | "--filter" | "--bind-dn" | "--password-env" => {
+                    let ldap = options.ldap.as_mut().ok_or_else(|| {
+                        format!("Error: {} can only be used with the ldap command.", arg)
+                    })?;
+                    let value = value_of(&arg, args.next())?;
+                    match arg.as_str() {
+                        "--base" => ldap.base = value,
+                        "--filter" => ldap.filter = Some(value),
+                        "--bind-dn" => ldap.bind_dn = Some(value),
+                        _ => ldap.password_env = Some(value),
+                    }
+                }
+                "--mount" => {
+                    let vault = options.vault.as_mut().ok_or_else(|| {
+                        format!("Error: {} can only be used with the vault commands.", arg)
+                    })?;
+                    vault.mount = Some(value_of(&arg, args.next())?);
+                }
+                "--cloud" => options
+                    .clouds
+                    .push(Cloud::parse(&value_of(&arg, args.next())?)?),
+                "--region" => {
+                    let aws = options.aws.as_mut().ok_or_else(|| {
+                        format!("Error: {} can only be used with the aws commands.", arg)
+                    })?;
+                    aws.region = Some(value_of(&arg, args.next())?);
+                }
+                "--reader" => {
+                    let piv = options.piv.as_mut().ok_or_else(|| {
+                        format!("Error: {} can only be used with the piv command.", arg)
+                    })?;
+                    piv.reader = Some(value_of(&arg, args.next())?);
+                }
+                "--keychain" | "--label" => {
+                    let chosen = options.keychains.as_mut().ok_or_else(|| {
+                        format!("Error: {} can only be used with the keychain command.", arg)
+                    })?;
+                    let value = value_of(&arg, args.next())?;
+                    match arg.as_str() {
+                        "--keychain" => keychains.push(value),
+                        _ => chosen.label = Some(value),
+                    }
+                }
+                "--expiring-within" if options.cluster.is_some() => {
+                    let cluster = options.cluster.as_mut().expect("checked by the guard");
+                    cluster.expiring_within = duration(&arg, args.next())?;
+                }
+                "--kubeconfig" | "--context" => {
+                    let cluster = options.cluster.as_mut().ok_or_else(|| {
+                        format!(
+                            "Error: {} can only be used with the k8s scan or k8s kubeconfig command.",
+                            arg
+                        )
+                    })?;
+                    let value = Some(value_of(&arg, args.next())?);
+                    match arg.as_str() {
+                        "--kubeconfig" => cluster.kubeconfig = value,
+                        _ => cluster.context = value,
+                    }
+                }
+                "--namespace" | "--webhooks" | "--apiserver" => {
+                    let cluster = options
+                        .cluster
+                        .as_mut()
+                        .filter(|cluster| cluster.command == ClusterCommand::Scan)
+                        .ok_or_else(|| {
+                            format!("Error: {} can only be used with the k8s scan command.", arg)
+                        })?;
+                    match arg.as_str() {
+                        "--namespace" => cluster.namespace = Some(value_of(&arg, args.next())?),
+                        "--webhooks" => cluster.webhooks = true,
+                        _ => cluster.apiserver = true,
+                    }
+                }
+                "--profile" => {
+                    options.profile = Some(Profile::parse(&value_of(&arg, args.next())?)?)
+                }
+                "--policy" if options.check => {
+                    options.policy_file = Some(value_of(&arg, args.next())?)
+                }
+                "--expect-pin" => options.expect.add_pin(&value_of(&arg, args.next())?)?,
+                "--expect-fingerprint" => options
+                    .expect
+                    .add_fingerprint(&value_of(&arg, args.next())?)?,
+                "--covers" => {
+                    let names = value_of(&arg, args.next())?;
+                    options.covers.extend(
+                        names
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|name| !name.is_empty())
+                            .map(String::from),
+                    );
+                }
+                "--find-serial" => {
+                    let serial = value_of(&arg, args.next())?;
+                    let bytes = crl::parse_serial(&serial).ok_or_else(|| {
+                        format!(
+                            "Error: --find-serial expects a serial number in hex, not {}.",
+                            serial
+                        )
+                    })?;
+                    options.find_serials.push(bytes);
+                }
+                "--domains" => options.domains = true,
+                "--public-suffix-list" => {
+                    options.public_suffix_list = Some(value_of(&arg, args.next())?)
+                }
+                "--dedupe" => options.dedupe = true,
+                "--sort-by" => {
+                    options.sort_by = Some(SortBy::parse(&value_of(&arg, args.next())?)?)
+                }
+                "--group-by" => {
+                    options.group_by = Some(GroupBy::parse(&value_of(&arg, args.next())?)?)
+                }
+                "--expired" => options.expiry_filter.expired = true,
+                "--not-yet-valid" => options.expiry_filter.not_yet_valid = true,
+                "--expiring-within" if options.trust_store.is_none() => {
+                    options.expiry_filter.expiring_within = Some(duration(&arg, args.next())?)
+                }
+                "--bundle" | "--expiring-within" => {
+                    let trust_store = options.trust_store.as_mut().ok_or_else(|| {
+                        format!(
+                            "Error: {} can only be used with the trust-store command.",
+                            arg
+                        )
+                    })?;
+                    match arg.as_str() {
+                        "--bundle" => trust_store.bundle = Some(value_of(&arg, args.next())?),
+                        _ => trust_store.expiring_within = duration(&arg, args.next())?,
+                    }
+                }
+                "--entry" => {
+                    let ct_search = options.ct_search.as_mut().ok_or_else(|| {
+                        format!(
+                            "Error: {} can only be used with the ct-search command.",
+                            arg
+                        )
+                    })?;
+                    let err_msg = format!("Error: {} expects a crt.sh ID.", arg);
+                    let id = value_of(&arg, args.next())?.parse().map_err(|_| err_msg)?;
+                    ct_search.entries.push(id);
+                }
+                "--domain" | "--state" | "--interval" | "--webhook" => {
+                    let ct_monitor = options.ct_monitor.as_mut().ok_or_else(|| {
+                        format!(
+                            "Error: {} can only be used with the ct-monitor command.",
+                            arg
+                        )
+                    })?;
+                    match arg.as_str() {
+                        "--domain" => ct_monitor.domains.push(value_of(&arg, args.next())?),
+                        "--state" => ct_monitor.state = value_of(&arg, args.next())?,
+                        "--interval" => ct_monitor.interval = Some(duration(&arg, args.next())?),
+                        _ => ct_monitor.webhook = Some(value_of(&arg, args.next())?),
+                    }
+                }
+                "--cert" | "--issuer" | "--url" => {
+                    let ocsp = options.ocsp.as_mut().ok_or_else(|| {
+                        format!(
+                            "Error: {} can only be used with the ocsp request or ocsp test command.",
+                            arg
+                        )
+                    })?;
+                    let value = value_of(&arg, args.next())?;
+                    match arg.as_str() {
+                        "--cert" => ocsp.cert = value,
+                        "--issuer" => ocsp.issuer = Some(value),
+                        _ => ocsp.url = Some(value),
+                    }
+                }
+                "-o" | "--hash" | "--nonce" => {
+                    let ocsp = options
+                        .ocsp
+                        .as_mut()
+                        .filter(|ocsp| ocsp.command == OcspCommand::Request)
+                        .ok_or_else(|| {
+                            format!(
+                                "Error: {} can only be used with the ocsp request command.",
+                                arg
+                            )
+                        })?;
+                    match arg.as_str() {
+                        "-o" => ocsp.output = value_of(&arg, args.next())?,
+                        "--hash" => ocsp.hash = Some(value_of(&arg, args.next())?),
+                        _ => ocsp.nonce = true,
+                    }
+                }
+                "--all-addresses" => options.scan.all_addresses = true,
+                "--jobs" => options.scan.jobs = positive(&arg, args.next())?,
+                "--offline" => options.offline = true,
+                "--timeout" => options.connect.timeout = Some(duration(&arg, args.next())?),
+                "--rate-limit" => {
+                    let err_msg =
+                        format!("Error: {} expects a number of handshakes per second.", arg);
+                    let rate = value_of(&arg, args.next())?
+                        .parse::<f64>()
+                        .map_err(|_| err_msg.clone())?;
+                    // A rate so small that the time between handshakes overflows is refused too
+                    let interval = Some(rate)
+                        .filter(|rate| *rate > 0.0 && rate.is_finite())
+                        .and_then(|rate| Duration::try_from_secs_f64(1.0 / rate).ok())
+                        .ok_or(err_msg)?;
+                    options.scan.rate_limit = Some(interval);
+                }
+                "--retries" => {
+                    let err_msg = format!("Error: {} expects a number.", arg);
+                    options.scan.retries =
+                        value_of(&arg, args.next())?.parse().map_err(|_| err_msg)?;
+                }
+                _ => options.targets.push(arg),
+            }
+        }
+
+        // Without a targets file or store we still expect exactly one certificate
+        if let Some(ct_search) = options.ct_search.as_mut() {
+            if options.targets.len() != 1 {
+                let err_msg = "Error: the ct-search command expects a single domain.";
+                return Err(String::from(err_msg).into());
+            }
+            ct_search.domain = options.targets.remove(0);
+        } else if let Some(acme) = options.acme.as_mut() {
+            if options.targets.len() != 1 {
+                let err_msg =
+                    "Error: the acme command expects a single order URL, directory or domain.";
+                return Err(String::from(err_msg).into());
+            }
+            *acme = options.targets.remove(0);
+        } else if let Some(est) = options.est.as_mut() {
+            if options.targets.len() != 1 {
+                let err_msg = "Error: the est command expects a single EST server.";
+                return Err(String::from(err_msg).into());
+            }
+            *est = options.targets.remove(0);
+        } else if let Some(scep) = options.scep.as_mut() {
+            if options.targets.len() != 1 {
+                let err_msg = "Error: the scep command expects a single SCEP endpoint URL.";
+                return Err(String::from(err_msg).into());
+            }
+            *scep = options.targets.remove(0);
+        } else if let Some(ldap) = options.ldap.as_mut() {
+            if options.targets.len() != 1 {
+                let err_msg = "Error: the ldap command expects a single ldap:// or ldaps:// URL.";
+                return Err(String::from(err_msg).into());
+            }
+            if ldap.base.is_empty() {
+                let err_msg =
+                    "Error: the ldap command needs the base DN to search under, given with --base.";
+                return Err(String::from(err_msg).into());
+            }
+            if ldap.bind_dn.is_some() != ldap.password_env.is_some() {
+                let err_msg = "Error: --bind-dn and --password-env have to be given together.";
+                return Err(String::from(err_msg).into());
+            }
+            ldap.url = options.targets.remove(0);
+        } else if let Some(mta_sts) = options.mta_sts.as_mut() {
+            if options.targets.len() != 1 {
+                let err_msg = "Error: the mta-sts command expects a single mail domain.";
+                return Err(String::from(err_msg).into());
+            }
+            *mta_sts = options.targets.remove(0);
+        } else if let Some(vault) = options
+            .vault
+            .as_mut()
+            .filter(|vault| vault.command == VaultCommand::Cert)
+        {
+            if options.targets.is_empty() {
+                let err_msg =
+                    "Error: the vault cert command expects the serial numbers of the certificates to fetch.";
+                return Err(String::from(err_msg).into());
+            }
+            vault.serials = std::mem::take(&mut options.targets);
+        } else if let Some(inventory) = options.inventory.as_mut() {
+            // The snapshot comes first, then what to scan
+            if options.targets.is_empty()
+                || (options.targets.len() == 1 && options.targets_file.is_none())
+            {
+                let err_msg = "Error: the inventory commands expect a snapshot file and what to scan, or --targets.";
+                return Err(String::from(err_msg).into());
+            }
+            inventory.snapshot = options.targets.remove(0);
+        } else if let Some(command) = command {
+            if !options.targets.is_empty() {
+                let err_msg = format!(
+                    "Error: the {} command does not take other targets.",
+                    command
+                );
+                return Err(err_msg.into());
+            }
+        } else if options.targets_file.is_none()
+            && options.clouds.is_empty()
+            && options.targets.len()
+                + usize::from(options.clipboard)
+                + usize::from(options.pkcs11.is_some())
+                != 1
+        {
+            let err_msg = String::from("Error: did not receive a single argument, please invoke cert-decoder as follows: ./cert-decoder /path/to/cert.");
+            return Err(err_msg.into());
+        }
+
+        match options.pkcs11.as_mut() {
+            Some(token) => {
+                token.slot = slot;
+                token.pin_env = pin_env;
+            }
+            None if slot.is_some() || pin_env.is_some() => {
+                let err_msg = "Error: --slot and --pin-env can only be used with --pkcs11.";
+                return Err(String::from(err_msg).into());
+            }
+            None => {}
+        }
+
+        // Naming a keychain replaces the default pair rather than adding to it
+        if let (Some(chosen), false) = (options.keychains.as_mut(), keychains.is_empty()) {
+            chosen.keychains = keychains;
+        }
+
+        let paths =
+            options.explain_trust || options.find_anchor || !options.required_policies.is_empty();
+        if !options.trust_anchors.is_empty() && !paths {
+            let err_msg = String::from(
+                "Error: --trust-anchors can only be used with --explain-trust, --find-anchor or --require-policy.",
+            );
+            return Err(err_msg.into());
+        }
+
+        if let Some(ct_monitor) = &options.ct_monitor {
+            if ct_monitor.domains.is_empty() || ct_monitor.state.is_empty() {
+                let err_msg =
+                    String::from("Error: the ct-monitor command expects --domain and --state.");
+                return Err(err_msg.into());
+            }
+        }
+        match &options.ocsp {
+            Some(ocsp)
+                if ocsp.command == OcspCommand::Request
+                    && (ocsp.cert.is_empty() || ocsp.output.is_empty()) =>
+            {
+                let err_msg =
+                    String::from("Error: the ocsp request command expects --cert and -o.");
+                return Err(err_msg.into());
+            }
+            Some(ocsp) if ocsp.command == OcspCommand::Test && ocsp.cert.is_empty() => {
+                let err_msg = String::from("Error: the ocsp test command expects --cert.");
+                return Err(err_msg.into());
+            }
+            _ => {}
+        }
+
+        if options.check && options.policy_file.is_none() {
+            let err_msg = String::from("Error: the check command expects --policy.");
+            return Err(err_msg.into());
+        }
+
+        if options.client_cert.is_some() != options.client_key.is_some() {
+            let err_msg =
+                String::from("Error: --client-cert and --client-key must be given together.");
+            return Err(err_msg.into());
+        }
+
+        Ok(options)
+    }
+}
+
+/// Returns the value following a flag, or an error naming the flag if there isn't one.
+fn value_of(flag: &str, value: Option<String>) -> Result<String, Box<dyn std::error::Error>> {
+    value.ok_or_else(|| format!("Error: {} expects a value.", flag).into())
+}
+
+/// Parses a flag value that must be a whole number above zero.
+fn positive(flag: &str, value: Option<String>) -> Result<usize, Box<dyn std::error::Error>> {
+    match value_of(flag, value)?.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("Error: {} expects a positive number.", flag).into()),
+    }
+}
+
+/// Parses a flag value that is a duration.
+fn duration(flag: &str, value: Option<String>) -> Result<Duration, Box<dyn std::error::Error>> {
+    let value = value_of(flag, value)?;
+    parse_duration(&value)
+        .ok_or_else(|| format!("Error: {} expects a duration such as 10s or 500ms.", flag).into())
+}
+
+/// Parses a duration such as `500ms`, `10s`, `5m`, `2h` or `30d`.
+/// A bare number is taken as seconds, zero is never a useful duration so it is rejected.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let duration = match unit {
+        "ms" => Duration::from_millis(number),
+        "" | "s" => Duration::from_secs(number),
+        "m" => Duration::from_secs(number.checked_mul(60)?),
+        "h" => Duration::from_secs(number.checked_mul(60 * 60)?),
+        "d" => Duration::from_secs(number.checked_mul(24 * 60 * 60)?),
+        _ => return None,
+    };
+    if duration == Duration::from_secs(0) {
+        return None;
+    }
+    Some(duration)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::time::Duration;
+
+    use super::{parse_duration, Options};
+    use crate::aws::{AwsOptions, AwsService};
+    use crate::clipboard::Copyable;
+    use crate::cluster::ClusterCommand;
+    use crate::conformance::Strictness;
+    use crate::findings::OutputFormat;
+    use crate::inventory::{InventoryCommand, InventoryOptions};
+    use crate::ldap::LdapOptions;
+    use crate::lifetime::MaxValidity;
+    use crate::ocsp::OcspCommand;
+    use crate::pkcs11::Pkcs11Options;
+    use crate::remote::Family;
+    use crate::vault::{VaultCommand, VaultOptions};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| String::from(*arg)).collect()
+    }
+
+    #[test]
+    fn should_accept_a_single_target() {
+        let options = Options::parse(args(&["cert.pem"])).unwrap();
+
+        assert_eq!(options.targets, args(&["cert.pem"]));
+        assert_eq!(options.targets_file, None);
+    }
+
+    #[test]
+    fn should_accept_a_targets_file_without_other_targets() {
+        let options = Options::parse(args(&["--targets", "targets.txt"])).unwrap();
+
+        assert!(options.targets.is_empty());
+        assert_eq!(options.targets_file, Some(String::from("targets.txt")));
+    }
+
+    #[test]
+    fn should_error_if_targets_flag_has_no_value() {
+        let result = Options::parse(args(&["--targets"]));
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --targets expects a value."
+        );
+    }
+
+    #[test]
+    fn should_error_if_given_several_targets_without_a_targets_file() {
+        let result = Options::parse(args(&["a.pem", "b.pem"]));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn should_parse_scan_settings() {
+        let options = Options::parse(args(&[
+            "--targets",
+            "targets.txt",
+            "--jobs",
+            "32",
+            "--timeout",
+            "3s",
+            "--rate-limit",
+            "2.5",
+            "--retries",
+            "0",
+        ]))
+        .unwrap();
+
+        assert_eq!(options.scan.jobs, 32);
+        assert_eq!(options.connect.timeout, Some(Duration::from_secs(3)));
+        assert_eq!(options.scan.rate_limit, Some(Duration::from_millis(400)));
+        assert_eq!(options.scan.retries, 0);
+    }
+
+    #[test]
+    fn should_refuse_a_rate_limit_too_small_to_wait_for() {
+        for rate in ["0", "-1", "inf", "1e-30"] {
+            assert!(
+                Options::parse(args(&["--targets", "targets.txt", "--rate-limit", rate])).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn should_parse_the_output_format() {
+        let options = Options::parse(args(&["certs", "--output", "SARIF"])).unwrap();
+
+        assert_eq!(options.output, OutputFormat::Sarif);
+        assert_eq!(
+            format!(
+                "{}",
+                Options::parse(args(&["certs", "--output", "xml"]))
+                    .err()
+                    .unwrap()
+            ),
+            "Error: --output expects text, sarif, junit, github or cyclonedx, not xml."
+        );
+    }
+
+    #[test]
+    fn should_take_the_clipboard_as_the_single_target() {
+        let options = Options::parse(args(&["--clipboard", "--copy", "pin"])).unwrap();
+
+        assert!(options.clipboard);
+        assert_eq!(options.copy, Some(Copyable::Pin));
+        assert!(options.targets.is_empty());
+        assert!(Options::parse(args(&["--clipboard", "cert.pem"])).is_err());
+    }
+
+    #[test]
+    fn should_parse_expiry_filters_for_scans_and_the_trust_store_command() {
+        let options =
+            Options::parse(args(&["certs", "--expired", "--expiring-within", "30d"])).unwrap();
+        let trust_store =
+            Options::parse(args(&["trust-store", "--expiring-within", "365d"])).unwrap();
+
+        assert!(options.expiry_filter.expired);
+        assert!(!options.expiry_filter.not_yet_valid);
+        assert_eq!(
+            options.expiry_filter.expiring_within,
+            Some(Duration::from_secs(30 * 24 * 60 * 60))
+        );
+        assert!(!trust_store.expiry_filter.is_set());
+    }
+
+    #[test]
+    fn should_parse_the_inventory_commands() {
+        let save = Options::parse(args(&["inventory", "save", "inventory.json", "certs"])).unwrap();
+        let diff = Options::parse(args(&[
+            "inventory",
+            "diff",
+            "inventory.json",
+            "--targets",
+            "targets.txt",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            save.inventory,
+            Some(InventoryOptions {
+                command: InventoryCommand::Save,
+                snapshot: String::from("inventory.json"),
+            })
+        );
+        assert_eq!(save.targets, args(&["certs"]));
+        assert_eq!(diff.inventory.unwrap().command, InventoryCommand::Diff);
+        assert!(diff.targets.is_empty());
+        assert!(Options::parse(args(&["inventory", "save", "inventory.json"])).is_err());
+    }
+
+    #[test]
+    fn should_parse_the_grade_command() {
+        let grade = Options::parse(args(&["grade", "example.com:443"])).unwrap();
+        let file = Options::parse(args(&["grade"])).unwrap();
+
+        assert!(grade.grade);
+        assert_eq!(grade.targets, args(&["example.com:443"]));
+        assert!(!file.grade);
+        assert_eq!(file.targets, args(&["grade"]));
+    }
+
+    #[test]
+    fn should_parse_the_check_command() {
+        let options = Options::parse(args(&["check", "--policy", "corp.toml", "certs/"])).unwrap();
+
+        assert!(options.check);
+        assert_eq!(options.policy_file.as_deref(), Some("corp.toml"));
+        assert_eq!(options.targets, args(&["certs/"]));
+        assert_eq!(
+            Options::parse(args(&["check", "certs/"]))
+                .unwrap_err()
+                .to_string(),
+            "Error: the check command expects --policy."
+        );
+    }
+
+    #[test]
+    fn should_parse_expected_pins_and_fingerprints() {
+        let pin = "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=";
+        let options = Options::parse(args(&[
+            "example.com:443",
+            "--expect-pin",
+            pin,
+            "--expect-pin",
+            pin,
+            "--expect-fingerprint",
+            &"AB".repeat(32),
+        ]))
+        .unwrap();
+
+        assert_eq!(options.expect.pins.len(), 2);
+        assert_eq!(options.expect.fingerprints, vec![vec![0xab; 32]]);
+        assert!(Options::parse(args(&["example.com:443", "--expect-fingerprint", "AB"])).is_err());
+    }
+
+    #[test]
+    fn should_parse_domains_with_a_public_suffix_list() {
+        let options = Options::parse(args(&[
+            "--domains",
+            "--public-suffix-list",
+            "suffixes.dat",
+            "cert.pem",
+        ]))
+        .unwrap();
+
+        assert!(options.domains);
+        assert_eq!(options.public_suffix_list.as_deref(), Some("suffixes.dat"));
+        assert_eq!(options.suffixes, None);
+    }
+
+    #[test]
+    fn should_parse_address_family() {
+        let options = Options::parse(args(&["-6", "example.com:443", "--all-addresses"])).unwrap();
+
+        assert_eq!(options.connect.family, Some(Family::V6));
+        assert!(options.scan.all_addresses);
+    }
+
+    #[test]
+    fn should_parse_host_key_references() {
+        let options = Options::parse(args(&[
+            "host.pub",
+            "--known-hosts",
+            "known_hosts",
+            "--sshfp",
+            "web01.example.com",
+        ]))
+        .unwrap();
+
+        assert_eq!(options.known_hosts.as_deref(), Some("known_hosts"));
+        assert_eq!(options.sshfp.as_deref(), Some("web01.example.com"));
+    }
+
+    #[test]
+    fn should_parse_the_doh_resolver() {
+        let options = Options::parse(args(&[
+            "mta-sts",
+            "example.com",
+            "--doh",
+            "https://cloudflare-dns.com/dns-query",
+        ]))
+        .unwrap();
+        let doh = options.doh.unwrap();
+        assert_eq!(doh.host, "cloudflare-dns.com");
+        assert_eq!(doh.path, "/dns-query");
+
+        assert!(
+            Options::parse(args(&["host.pub", "--doh", "http://127.0.0.1/dns-query"])).is_err()
+        );
+    }
+
+    #[test]
+    fn should_parse_the_ct_search_command() {
+        let options = Options::parse(args(&[
+            "ct-search",
+            "example.com",
+            "--entry",
+            "1001",
+            "--entry",
+            "1002",
+        ]))
+        .unwrap();
+
+        let ct_search = options.ct_search.unwrap();
+        assert_eq!(ct_search.domain, "example.com");
+        assert_eq!(ct_search.entries, vec![1001, 1002]);
+        assert!(options.targets.is_empty());
+        assert!(Options::parse(args(&["ct-search", "--entry", "1"])).is_err());
+        assert_eq!(
+            Options::parse(args(&["ct-search"])).unwrap().targets,
+            args(&["ct-search"])
+        );
+        assert!(Options::parse(args(&["example.crt", "--entry", "1"])).is_err());
+    }
+
+    #[test]
+    fn should_parse_serials_to_find() {
+        let options = Options::parse(args(&[
+            "big.crl",
+            "--find-serial",
+            "0a:1b",
+            "--find-serial",
+            "ff",
+        ]))
+        .unwrap();
+
+        assert_eq!(options.find_serials, vec![vec![0x0a, 0x1b], vec![0xff]]);
+        assert_eq!(
+            Options::parse(args(&["big.crl", "--find-serial", "xyz"]))
+                .unwrap_err()
+                .to_string(),
+            "Error: --find-serial expects a serial number in hex, not xyz."
+        );
+    }
+
+    #[test]
+    fn should_parse_the_ocsp_request_command() {
+        let options = Options::parse(args(&[
+            "ocsp", "request", "--cert", "leaf.pem", "--issuer", "int.pem", "-o", "req.der",
+            "--nonce",
+        ]))
+        .unwrap();
+
+        let ocsp = options.ocsp.unwrap();
+        assert_eq!(ocsp.cert, "leaf.pem");
+        assert_eq!(ocsp.issuer.as_deref(), Some("int.pem"));
+        assert_eq!(ocsp.output, "req.der");
+        assert!(ocsp.nonce);
+        assert_eq!(
+            Options::parse(args(&["ocsp", "request", "--cert", "leaf.pem"]))
+                .unwrap_err()
+                .to_string(),
+            "Error: the ocsp request command expects --cert and -o."
+        );
+        assert!(Options::parse(args(&["example.crt", "-o", "req.der"])).is_err());
+    }
+
+    #[test]
+    fn should_parse_the_ocsp_test_command() {
+        let options = Options::parse(args(&[
+            "ocsp",
+            "test",
+            "--url",
+            "http://ocsp.example.com",
+            "--cert",
+            "leaf.pem",
+        ]))
+        .unwrap();
+
+        let ocsp = options.ocsp.unwrap();
+        assert_eq!(ocsp.command, OcspCommand::Test);
+        assert_eq!(ocsp.url.as_deref(), Some("http://ocsp.example.com"));
+        assert_eq!(ocsp.cert, "leaf.pem");
+        assert_eq!(
+            Options::parse(args(&["ocsp", "test", "--cert", "leaf.pem", "--nonce"]))
+                .unwrap_err()
+                .to_string(),
+            "Error: --nonce can only be used with the ocsp request command."
+        );
+        assert_eq!(
+            Options::parse(args(&["ocsp", "test"]))
+                .unwrap_err()
+                .to_string(),
+            "Error: the ocsp test command expects --cert."
+        );
+    }
+
+    #[test]
+    fn should_parse_the_est_command() {
+        let options = Options::parse(args(&[
+            "est",
+            "https://est.example.com/.well-known/est/iot",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            options.est.as_deref(),
+            Some("https://est.example.com/.well-known/est/iot")
+        );
+        assert!(options.targets.is_empty());
+        assert_eq!(Options::parse(args(&["est"])).unwrap().targets, vec!["est"]);
+        assert_eq!(
+            Options::parse(args(&["est", "est.example.com", "est.example.org"]))
+                .unwrap_err()
+                .to_string(),
+            "Error: the est command expects a single EST server."
+        );
+    }
+
+    #[test]
+    fn should_parse_the_scep_command() {
+        let options = Options::parse(args(&["scep", "http://mdm.example.com/scep"])).unwrap();
+
+        assert_eq!(options.scep.as_deref(), Some("http://mdm.example.com/scep"));
+        assert!(options.targets.is_empty());
+        assert_eq!(
+            Options::parse(args(&["scep"])).unwrap().targets,
+            vec!["scep"]
+        );
+        assert!(Options::parse(args(&["scep", "a", "b"])).is_err());
+    }
+
+    #[test]
+    fn should_parse_the_ldap_command() {
+        let options = Options::parse(args(&[
+            "ldap",
+            "ldaps://dc1.corp.example.com",
+            "--base",
+            "DC=corp,DC=example,DC=com",
+            "--filter",
+            "(sAMAccountName=alice)",
+            "--bind-dn",
+            "CN=svc-audit,DC=corp,DC=example,DC=com",
+            "--password-env",
+            "LDAP_PASSWORD",
+        ]))
+        .unwrap();
+        assert_eq!(
+            options.ldap,
+            Some(LdapOptions {
+                url: String::from("ldaps://dc1.corp.example.com"),
+                base: String::from("DC=corp,DC=example,DC=com"),
+                filter: Some(String::from("(sAMAccountName=alice)")),
+                bind_dn: Some(String::from("CN=svc-audit,DC=corp,DC=example,DC=com")),
+                password_env: Some(String::from("LDAP_PASSWORD")),
+            })
+        );
+        assert!(options.targets.is_empty());
+
+        let result = Options::parse(args(&["ldap", "ldaps://dc1.corp.example.com"]));
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Error: the ldap command needs the base DN to search under, given with --base."
+        );
+        let result = Options::parse(args(&["--filter", "(cn=alice)", "cert.pem"]));
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Error: --filter can only be used with the ldap command."
+        );
+        assert!(Options::parse(args(&[
+            "ldap",
+            "ldap://dc1",
+            "--base",
+            "DC=corp",
+            "--bind-dn",
+            "CN=svc-audit"
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn should_parse_the_mta_sts_command() {
+        let options = Options::parse(args(&["mta-sts", "example.com"])).unwrap();
+
+        assert_eq!(options.mta_sts.as_deref(), Some("example.com"));
+        assert!(options.connect.starttls);
+        assert!(options.connect.alpn.is_empty());
+        assert_eq!(
+            Options::parse(args(&["mta-sts", "a.example", "b.example"]))
+                .err()
+                .unwrap()
+                .to_string(),
+            "Error: the mta-sts command expects a single mail domain."
+        );
+    }
+
+    #[test]
+    fn should_parse_the_acme_command() {
+        let options = Options::parse(args(&["acme", "/etc/letsencrypt/live/example.com"])).unwrap();
+
+        assert_eq!(
+            options.acme.as_deref(),
+            Some("/etc/letsencrypt/live/example.com")
+        );
+        assert!(options.targets.is_empty());
+        assert_eq!(
+            Options::parse(args(&["acme"])).unwrap().targets,
+            vec!["acme"]
+        );
+        assert!(Options::parse(args(&["acme", "example.com", "example.org"])).is_err());
+    }
+
+    #[test]
+    fn should_parse_the_ct_monitor_command() {
+        let options = Options::parse(args(&[
+            "ct-monitor",
+            "--domain",
+            "example.com",
+            "--domain",
+            "example.org",
+            "--state",
+            "ct.json",
+            "--interval",
+            "1h",
+            "--webhook",
+            "https://hooks.example.net/ct",
+        ]))
+        .unwrap();
+
+        let ct_monitor = options.ct_monitor.unwrap();
+        assert_eq!(ct_monitor.domains, vec!["example.com", "example.org"]);
+        assert_eq!(ct_monitor.state, "ct.json");
+        assert_eq!(ct_monitor.interval, Some(Duration::from_secs(60 * 60)));
+        assert_eq!(
+            ct_monitor.webhook.as_deref(),
+            Some("https://hooks.example.net/ct")
+        );
+        assert_eq!(
+            Options::parse(args(&["ct-monitor", "--domain", "example.com"]))
+                .unwrap_err()
+                .to_string(),
+            "Error: the ct-monitor command expects --domain and --state."
+        );
+        assert!(Options::parse(args(&["example.crt", "--state", "ct.json"])).is_err());
+        assert_eq!(
+            Options::parse(args(&["ct-monitor"])).unwrap().targets,
+            args(&["ct-monitor"])
+        );
+    }
+
+    #[test]
+    fn should_parse_trust_anchors_for_explain_trust() {
+        let options = Options::parse(args(&[
+            "chain.pem",
+            "--explain-trust",
+            "--trust-anchors",
+            "roots.pem",
+            "--trust-anchors",
+            "private-roots.pem",
+        ]))
+        .unwrap();
+        let result = Options::parse(args(&["chain.pem", "--trust-anchors", "roots.pem"]));
+
+        assert!(options.explain_trust);
+        assert_eq!(
+            options.trust_anchors,
+            args(&["roots.pem", "private-roots.pem"])
+        );
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --trust-anchors can only be used with --explain-trust, --find-anchor or --require-policy."
+        );
+        assert!(
+            Options::parse(args(&[
+                "chain.pem",
+                "--find-anchor",
+                "--trust-anchors",
+                "roots.pem"
+            ]))
+            .unwrap()
+            .find_anchor
+        );
+    }
+
+    #[test]
+    fn should_parse_required_policies() {
+        let options = Options::parse(args(&[
+            "chain.pem",
+            "--require-policy",
+            "2.23.140.1.1",
+            "--require-policy",
+            "2.16.840.1.101.3.2.1.3.13",
+            "--trust-anchors",
+            "roots.pem",
+        ]))
+        .unwrap();
+        let result = Options::parse(args(&["chain.pem", "--require-policy", "ev"]));
+
+        assert_eq!(
+            options.required_policies,
+            args(&["2.23.140.1.1", "2.16.840.1.101.3.2.1.3.13"])
+        );
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --require-policy expects a policy OID such as 2.23.140.1.1, not ev."
+        );
+    }
+
+    #[test]
+    fn should_parse_the_max_validity() {
+        let cabf = Options::parse(args(&["example.crt", "--max-validity", "cabf"])).unwrap();
+        let own = Options::parse(args(&["example.crt", "--max-validity", "90d"])).unwrap();
+        let result = Options::parse(args(&["example.crt", "--max-validity", "soon"]));
+
+        assert_eq!(cabf.max_validity, Some(MaxValidity::Cabf));
+        assert!(
+            Options::parse(args(&["example.crt", "--lint"]))
+                .unwrap()
+                .lint
+        );
+        assert_eq!(
+            own.max_validity,
+            Some(MaxValidity::Fixed(Duration::from_secs(90 * 24 * 60 * 60)))
+        );
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --max-validity expects cabf or a duration such as 90d, not soon."
+        );
+    }
+
+    #[test]
+    fn should_error_if_given_both_address_families() {
+        let result = Options::parse(args(&["-4", "-6", "example.com:443"]));
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: -4 and -6 cannot be used together."
+        );
+    }
+
+    #[test]
+    fn should_parse_the_strictness() {
+        let lenient = Options::parse(args(&["example.crt", "--lenient"])).unwrap();
+        let result = Options::parse(args(&["--strict", "example.crt", "--lenient"]));
+
+        assert_eq!(lenient.strictness, Strictness::Lenient);
+        assert_eq!(
+            Options::parse(args(&["example.crt"])).unwrap().strictness,
+            Strictness::Default
+        );
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --lenient and --strict cannot be used together."
+        );
+    }
+
+    #[test]
+    fn should_error_if_client_cert_is_given_without_key() {
+        let result = Options::parse(args(&["example.com:443", "--client-cert", "me.pem"]));
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --client-cert and --client-key must be given together."
+        );
+    }
+
+    #[test]
+    fn should_only_accept_quic_when_built_with_it() {
+        let result = Options::parse(args(&["--quic", "example.com:443"]));
+
+        if cfg!(feature = "quic") {
+            assert!(result.unwrap().connect.quic);
+        } else {
+            assert_eq!(
+                format!("{}", result.err().unwrap()),
+                "Error: --quic needs cert-decoder to be built with the quic feature."
+            );
+        }
+    }
+
+    #[test]
+    fn should_parse_the_store_command() {
+        let options = Options::parse(args(&[
+            "store",
+            "--location",
+            "LocalMachine",
+            "--store",
+            "Root",
+            "--subject",
+            "contoso",
+        ]))
+        .unwrap();
+
+        let store = options.store.unwrap();
+        assert_eq!(store.to_string(), "LocalMachine\\Root");
+        assert_eq!(store.subject, Some(String::from("contoso")));
+        assert_eq!(
+            Options::parse(args(&["store"])).unwrap().targets,
+            args(&["store"])
+        );
+    }
+
+    #[test]
+    fn should_error_on_store_flags_without_the_store_command() {
+        let result = Options::parse(args(&["cert.pem", "--store", "My"]));
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --store can only be used with the store command."
+        );
+    }
+
+    #[test]
+    fn should_error_on_an_unknown_store_location() {
+        let result = Options::parse(args(&["store", "--location", "Elsewhere"]));
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: Elsewhere is not a store location, use CurrentUser or LocalMachine."
+        );
+    }
+
+    #[test]
+    fn should_parse_the_keychain_command() {
+        let default = Options::parse(args(&["keychain", "--label", "Apple"])).unwrap();
+        let chosen = Options::parse(args(&[
+            "keychain",
+            "--keychain",
+            "roots",
+            "--label",
+            "Apple",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            default.keychains.unwrap().keychains,
+            args(&["system", "login"])
+        );
+        let chosen = chosen.keychains.unwrap();
+        assert_eq!(chosen.keychains, args(&["roots"]));
+        assert_eq!(chosen.label, Some(String::from("Apple")));
+        assert_eq!(
+            Options::parse(args(&["keychain"])).unwrap().targets,
+            args(&["keychain"])
+        );
+    }
+
+    #[test]
+    fn should_parse_the_aws_commands_and_cloud_sources() {
+        let options = Options::parse(args(&["aws", "acm", "--region", "eu-west-1"])).unwrap();
+        assert_eq!(
+            options.aws,
+            Some(AwsOptions {
+                service: AwsService::Acm,
+                region: Some(String::from("eu-west-1")),
+            })
+        );
+        let options = Options::parse(args(&["aws", "iam", "--expiring-within", "30d"])).unwrap();
+        assert_eq!(options.aws.unwrap().service, AwsService::Iam);
+
+        let result = Options::parse(args(&["--region", "eu-west-1", "cert.pem"]));
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Error: --region can only be used with the aws commands."
+        );
+        assert!(Options::parse(args(&["aws", "acm", "cert.pem"])).is_err());
+
+        let options = Options::parse(args(&[
+            "--cloud",
+            "aws-iam",
+            "--cloud",
+            "azure:contoso",
+            "--expiring-within",
+            "30d",
+        ]))
+        .unwrap();
+        assert_eq!(options.clouds.len(), 2);
+        assert!(options.targets.is_empty());
+        let result = Options::parse(args(&["--cloud", "azure"]));
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Error: --cloud expects aws-acm[:REGION], aws-iam, azure:VAULT or gcp:PROJECT[:LOCATION]."
+        );
+    }
+
+    #[test]
+    fn should_parse_the_vault_commands() {
+        let options = Options::parse(args(&["vault", "ca", "--mount", "pki_int"])).unwrap();
+        assert_eq!(
+            options.vault,
+            Some(VaultOptions {
+                command: VaultCommand::Ca,
+                mount: Some(String::from("pki_int")),
+                serials: Vec::new(),
+            })
+        );
+        let options = Options::parse(args(&["vault", "cert", "0a-1b", "2c:3d"])).unwrap();
+        assert_eq!(options.vault.unwrap().serials, args(&["0a-1b", "2c:3d"]));
+
+        let result = Options::parse(args(&["vault", "cert"]));
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: the vault cert command expects the serial numbers of the certificates to fetch."
+        );
+        assert!(Options::parse(args(&["vault", "crl", "0a-1b"])).is_err());
+        assert_eq!(
+            Options::parse(args(&["vault"])).unwrap().targets,
+            args(&["vault"])
+        );
+    }
+
+    #[test]
+    fn should_parse_the_piv_command() {
+        let options = Options::parse(args(&["piv", "--reader", "YubiKey"])).unwrap();
+        assert_eq!(options.piv.unwrap().reader, Some(String::from("YubiKey")));
+        assert_eq!(
+            Options::parse(args(&["piv"])).unwrap().targets,
+            args(&["piv"])
+        );
+
+        let result = Options::parse(args(&["--reader", "YubiKey", "cert.pem"]));
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --reader can only be used with the piv command."
+        );
+        assert!(Options::parse(args(&["piv", "cert.pem"])).is_err());
+    }
+
+    #[test]
+    fn should_parse_the_pkcs11_token_options() {
+        let options = Options::parse(args(&[
+            "--slot",
+            "1",
+            "--pkcs11",
+            "/usr/lib/libykcs11.so",
+            "--pin-env",
+            "YUBIKEY_PIN",
+        ]))
+        .unwrap();
+        assert_eq!(
+            options.pkcs11,
+            Some(Pkcs11Options {
+                module: String::from("/usr/lib/libykcs11.so"),
+                slot: Some(1),
+                pin_env: Some(String::from("YUBIKEY_PIN")),
+            })
+        );
+
+        let result = Options::parse(args(&["--slot", "1", "cert.pem"]));
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --slot and --pin-env can only be used with --pkcs11."
+        );
+        let result = Options::parse(args(&["--pkcs11", "opensc-pkcs11.so", "--slot", "PIV"]));
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --slot expects a slot number."
+        );
+        assert!(Options::parse(args(&["--pkcs11", "opensc-pkcs11.so", "cert.pem"])).is_err());
+    }
+
+    #[test]
+    fn should_parse_the_trust_store_command() {
+        let options = Options::parse(args(&[
+            "trust-store",
+            "--bundle",
+            "roots.pem",
+            "--expiring-within",
+            "90d",
+        ]))
+        .unwrap();
+
+        let trust_store = options.trust_store.unwrap();
+        assert_eq!(trust_store.bundle, Some(String::from("roots.pem")));
+        assert_eq!(
+            trust_store.expiring_within,
+            Duration::from_secs(90 * 24 * 60 * 60)
+        );
+        assert_eq!(
+            Options::parse(args(&["trust-store"])).unwrap().targets,
+            args(&["trust-store"])
+        );
+    }
+
+    #[test]
+    fn should_parse_the_k8s_scan_command() {
+        let options = Options::parse(args(&[
+            "k8s",
+            "scan",
+            "--context",
+            "prod",
+            "--namespace",
+            "web",
+            "--webhooks",
+            "--expiring-within",
+            "14d",
+        ]))
+        .unwrap();
+
+        let cluster = options.cluster.unwrap();
+        assert_eq!(cluster.context, Some(String::from("prod")));
+        assert_eq!(cluster.namespace, Some(String::from("web")));
+        assert!(cluster.webhooks && !cluster.apiserver);
+        assert_eq!(
+            cluster.expiring_within,
+            Duration::from_secs(14 * 24 * 60 * 60)
+        );
+        // A file called k8s is still just a file
+        assert_eq!(
+            Options::parse(args(&["k8s"])).unwrap().targets,
+            args(&["k8s"])
+        );
+    }
+
+    #[test]
+    fn should_only_accept_scan_flags_with_the_k8s_scan_command() {
+        let options = Options::parse(args(&["k8s", "kubeconfig", "--kubeconfig", "admin.conf"]))
+            .unwrap()
+            .cluster
+            .unwrap();
+        let result = Options::parse(args(&["k8s", "kubeconfig", "--webhooks"]));
+
+        assert_eq!(options.command, ClusterCommand::Kubeconfig);
+        assert_eq!(options.kubeconfig, Some(String::from("admin.conf")));
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --webhooks can only be used with the k8s scan command."
+        );
+    }
+
+    #[test]
+    fn should_error_on_zero_jobs() {
+        let result = Options::parse(args(&["cert.pem", "--jobs", "0"]));
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: --jobs expects a positive number."
+        );
+    }
+
+    #[test]
+    fn should_parse_durations() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("10"), Some(Duration::from_secs(10)));
+        assert_eq!(
+            parse_duration("30d"),
+            Some(Duration::from_secs(30 * 24 * 60 * 60))
+        );
+        assert_eq!(parse_duration("0s"), None);
+        assert_eq!(parse_duration("10 minutes"), None);
+        assert_eq!(parse_duration("s"), None);
+    }
+}
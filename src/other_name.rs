@@ -0,0 +1,220 @@
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+
+use crate::cms::hex;
+use crate::extensions::{elements, Field};
+
+/// The otherName Windows smart card logon maps to an Active Directory account.
+const USER_PRINCIPAL_NAME: &str = "1.3.6.1.4.1.311.20.2.3";
+
+/// The otherName of RFC 4556 that Kerberos PKINIT maps to a principal.
+const KERBEROS_PRINCIPAL: &str = "1.3.6.1.5.2.2";
+
+/// The otherName of FIPS 201 that carries the FASC-N of a PIV card.
+const FASC_N: &str = "2.16.840.1.101.3.6.6";
+
+const XMPP_ADDRESS: &str = "1.3.6.1.5.5.7.8.5";
+const SMTP_UTF8_MAILBOX: &str = "1.3.6.1.5.5.7.8.9";
+
+/// The otherName of RFC 4108 that device identities use, decoded with them.
+const HARDWARE_MODULE_NAME: &str = "1.3.6.1.5.5.7.8.4";
+
+// The control characters of a FASC-N, among its digits
+const START_SENTINEL: u8 = 11;
+const FIELD_SEPARATOR: u8 = 13;
+const END_SENTINEL: u8 = 15;
+
+/// The fields of a FASC-N, by where their digits sit among its 40 characters,
+/// from the PIV Technical Implementation Guidance.
+const FASC_N_FIELDS: &[(&str, usize, usize)] = &[
+    ("Agency code", 1, 4),
+    ("System code", 6, 4),
+    ("Credential number", 11, 6),
+    ("Credential series", 18, 1),
+    ("Individual credential issue", 20, 1),
+    ("Person identifier", 22, 10),
+    ("Organizational category", 32, 1),
+    ("Organizational identifier", 33, 4),
+    ("Association category", 37, 1),
+];
+
+/// Decodes the otherNames among the subject alternative names, which smart card
+/// logon and enterprise client certificates identify their users with and
+/// x509-parser leaves as bytes. Certificates without any are left alone.
+pub fn names(cert: &X509Certificate) -> Option<Vec<Field>> {
+    let names = cert.subject_alternative_name().ok().flatten()?;
+    let mut fields = Vec::new();
+    for name in &names.value.general_names {
+        let (oid, value) = match name {
+            GeneralName::OtherName(oid, value) => (oid.to_id_string(), *value),
+            _ => continue,
+        };
+        let field = match oid.as_str() {
+            USER_PRINCIPAL_NAME => string(value).map(|upn| Field::new("User principal name", upn)),
+            KERBEROS_PRINCIPAL => kerberos_principal(value)
+                .map(|principal| Field::new("Kerberos principal", principal)),
+            FASC_N => fasc_n(value),
+            XMPP_ADDRESS => string(value).map(|address| Field::new("XMPP address", address)),
+            SMTP_UTF8_MAILBOX => {
+                string(value).map(|mailbox| Field::new("SMTP UTF-8 mailbox", mailbox))
+            }
+            HARDWARE_MODULE_NAME => continue,
+            _ => Some(Field::new(&oid, hex(inner(value).unwrap_or(value)))),
+        };
+        fields.push(
+            field.unwrap_or_else(|| Field::new(&oid, format!("not valid, {} bytes", value.len()))),
+        );
+    }
+    match fields.is_empty() {
+        true => None,
+        false => Some(fields),
+    }
+}
+
+/// The contents of the `[0]` an otherName's value is explicitly tagged with.
+fn inner(value: &[u8]) -> Option<&[u8]> {
+    Some(elements(value)?.first()?.data)
+}
+
+/// A value that is a single string, as a UPN's UTF8String.
+fn string(value: &[u8]) -> Option<String> {
+    let string = elements(inner(value)?)?.into_iter().next()?;
+    Some(String::from_utf8_lossy(string.data).into_owned())
+}
+
+/// A KRB5PrincipalName as `name/instance@REALM`.
+fn kerberos_principal(value: &[u8]) -> Option<String> {
+    let sequence = elements(inner(value)?)?;
+    let principal = elements(sequence.first()?.data)?;
+    let realm = elements(principal.first()?.data)?;
+    let name = elements(principal.get(1)?.data)?;
+    let name = elements(name.first()?.data)?;
+    let components = elements(elements(name.get(1)?.data)?.first()?.data)?;
+    let components: Vec<String> = components
+        .iter()
+        .map(|component| String::from_utf8_lossy(component.data).into_owned())
+        .collect();
+    Some(format!(
+        "{}@{}",
+        components.join("/"),
+        String::from_utf8_lossy(realm.first()?.data)
+    ))
+}
+
+/// A FASC-N, 40 characters of four bits least significant first and an odd
+/// parity bit, split into its fields.
+fn fasc_n(value: &[u8]) -> Option<Field> {
+    let bytes = elements(inner(value)?)?.into_iter().next()?.data;
+    if bytes.len() != 25 {
+        return None;
+    }
+    let bit = |i: usize| (bytes[i / 8] >> (7 - i % 8)) & 1;
+    let mut characters = Vec::new();
+    for i in 0..40 {
+        let bits: Vec<u8> = (0..5).map(|j| bit(i * 5 + j)).collect();
+        if bits.iter().sum::<u8>() % 2 == 0 {
+            return None;
+        }
+        characters.push(bits[0] | bits[1] << 1 | bits[2] << 2 | bits[3] << 3);
+    }
+    let lrc = characters[..39].iter().fold(0, |lrc, c| lrc ^ c);
+    let separators = [5, 10, 17, 19, 21];
+    if characters[0] != START_SENTINEL
+        || characters[38] != END_SENTINEL
+        || characters[39] != lrc
+        || separators.iter().any(|i| characters[*i] != FIELD_SEPARATOR)
+    {
+        return None;
+    }
+
+    let mut fields = Vec::new();
+    for (name, start, length) in FASC_N_FIELDS {
+        let digits = &characters[*start..start + length];
+        if digits.iter().any(|digit| *digit > 9) {
+            return None;
+        }
+        let digits: String = digits.iter().map(|digit| (b'0' + digit) as char).collect();
+        let meaning = match (*name, digits.as_str()) {
+            ("Organizational category", "1") => "federal government agency",
+            ("Organizational category", "2") => "state government agency",
+            ("Organizational category", "3") => "commercial enterprise",
+            ("Organizational category", "4") => "foreign government",
+            ("Association category", "1") => "employee",
+            ("Association category", "2") => "civil",
+            ("Association category", "3") => "executive staff",
+            ("Association category", "4") => "uniformed service",
+            ("Association category", "5") => "contractor",
+            ("Association category", "6") => "organizational affiliate",
+            ("Association category", "7") => "organizational beneficiary",
+            _ => "",
+        };
+        match meaning {
+            "" => fields.push(Field::new(name, digits)),
+            meaning => fields.push(Field::new(name, format!("{} ({})", digits, meaning))),
+        }
+    }
+    Some(Field {
+        name: String::from("FASC-N"),
+        value: hex(bytes),
+        fields,
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::names;
+
+    #[test]
+    fn should_decode_the_other_names_of_a_smart_card_certificate() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/smartcard.crt")).unwrap();
+        let cert = pem.parse_x509().unwrap();
+
+        let fields = names(&cert).unwrap();
+
+        let values: Vec<(&str, &str)> = fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.value.as_str()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("User principal name", "alice@corp.example"),
+                ("Kerberos principal", "alice@CORP.EXAMPLE"),
+                (
+                    "FASC-N",
+                    "d0:43:94:58:21:0c:2c:19:a0:84:6d:83:68:5a:10:82:10:8c:e7:39:84:10:8c:a3:fc"
+                ),
+            ]
+        );
+        let fasc_n: Vec<(&str, &str)> = fields[2]
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.value.as_str()))
+            .collect();
+        assert_eq!(
+            fasc_n,
+            vec![
+                ("Agency code", "0032"),
+                ("System code", "0001"),
+                ("Credential number", "092446"),
+                ("Credential series", "0"),
+                ("Individual credential issue", "1"),
+                ("Person identifier", "1112223333"),
+                ("Organizational category", "1 (federal government agency)"),
+                ("Organizational identifier", "1223"),
+                ("Association category", "2 (civil)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_leave_certificates_without_other_names_alone() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/google.com.crt")).unwrap();
+        let cert = pem.parse_x509().unwrap();
+
+        assert!(names(&cert).is_none());
+    }
+}
@@ -0,0 +1,492 @@
+use x509_parser::error::X509Error;
+use x509_parser::extensions::X509Extension;
+use x509_parser::nom::Err;
+use x509_parser::num_bigint::BigUint;
+use x509_parser::objects::{oid2sn, oid_registry};
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+use x509_parser::x509::{AlgorithmIdentifier, SubjectPublicKeyInfo, X509Name, X509Version};
+
+use crate::dn;
+use crate::oid;
+
+/// A field of a certificate, with the tags it may have.
+struct Field {
+    name: &'static str,
+    tags: &'static [u8],
+    optional: bool,
+    kind: Kind,
+}
+
+enum Kind {
+    /// A field decoded whole, from its tag on, into what it shows.
+    Value(fn(&[u8]) -> Result<String, String>),
+    /// A field made of further fields.
+    Fields(&'static [Field]),
+    /// A field holding any number of the same field.
+    Each(&'static Field),
+}
+
+const SEQUENCE: &[u8] = &[0x30];
+
+/// The structure of a certificate, from RFC 5280 section 4.1.
+const CERTIFICATE: Field = Field {
+    name: "certificate",
+    tags: SEQUENCE,
+    optional: false,
+    kind: Kind::Fields(&[TBS_CERTIFICATE, algorithm("signature algorithm"), SIGNATURE]),
+};
+
+const TBS_CERTIFICATE: Field = Field {
+    name: "TBS certificate",
+    tags: SEQUENCE,
+    optional: false,
+    kind: Kind::Fields(&[
+        Field {
+            name: "version",
+            tags: &[0xa0],
+            optional: true,
+            kind: Kind::Value(version),
+        },
+        Field {
+            name: "serial number",
+            tags: &[0x02],
+            optional: false,
+            kind: Kind::Value(serial_number),
+        },
+        algorithm("signature algorithm"),
+        name("issuer"),
+        Field {
+            name: "validity",
+            tags: SEQUENCE,
+            optional: false,
+            kind: Kind::Fields(&[time("not before"), time("not after")]),
+        },
+        name("subject"),
+        Field {
+            name: "subject public key info",
+            tags: SEQUENCE,
+            optional: false,
+            kind: Kind::Value(public_key),
+        },
+        unique_id("issuer unique ID", &[0x81]),
+        unique_id("subject unique ID", &[0x82]),
+        Field {
+            name: "extensions",
+            tags: &[0xa3],
+            optional: true,
+            kind: Kind::Fields(&[Field {
+                name: "extensions",
+                tags: SEQUENCE,
+                optional: false,
+                kind: Kind::Each(&Field {
+                    name: "extension",
+                    tags: SEQUENCE,
+                    optional: false,
+                    kind: Kind::Value(extension),
+                }),
+            }]),
+        },
+    ]),
+};
+
+const SIGNATURE: Field = Field {
+    name: "signature",
+    tags: &[0x03],
+    optional: false,
+    kind: Kind::Value(bit_string),
+};
+
+const fn algorithm(name: &'static str) -> Field {
+    Field {
+        name,
+        tags: SEQUENCE,
+        optional: false,
+        kind: Kind::Value(algorithm_identifier),
+    }
+}
+
+const fn name(name: &'static str) -> Field {
+    Field {
+        name,
+        tags: SEQUENCE,
+        optional: false,
+        kind: Kind::Value(distinguished_name),
+    }
+}
+
+const fn time(name: &'static str) -> Field {
+    Field {
+        name,
+        tags: &[0x17, 0x18],
+        optional: false,
+        kind: Kind::Value(asn1_time),
+    }
+}
+
+const fn unique_id(name: &'static str, tags: &'static [u8]) -> Field {
+    Field {
+        name,
+        tags,
+        optional: true,
+        kind: Kind::Value(bit_string),
+    }
+}
+
+/// Where decoding stopped, and why.
+struct Failure {
+    offset: usize,
+    /// The fields it was in, innermost first.
+    path: Vec<String>,
+    /// The tag and length of the field, when its header could be read.
+    tlv: Option<(u8, usize)>,
+    problem: String,
+}
+
+/// Explains why a certificate could not be decoded, rather than leaving it at
+/// x509-parser's error: the field it failed in, at what byte, and everything
+/// that decoded before it.
+pub fn explain(location: &str, der: &[u8], error: Err<X509Error>) -> String {
+    let mut decoded = Vec::new();
+    let certificate = Kind::Fields(&[CERTIFICATE]);
+    let mut message = match walk(der, 0, &certificate, &mut Vec::new(), &mut decoded) {
+        Err(mut failure) => {
+            // The certificate goes without saying, and the [3] around the
+            // extensions has the same name as they do
+            if failure.path.len() > 1 {
+                failure.path.pop();
+            }
+            failure.path.dedup();
+            let tlv = match failure.tlv {
+                Some((tag, length)) => format!(" ({} of {} bytes)", tag_name(tag), length),
+                None => String::new(),
+            };
+            format!(
+                "Error: could not decode {} past byte {} of {}, in the {}{}: {}.",
+                location,
+                failure.offset,
+                der.len(),
+                failure.path.join(" of the "),
+                tlv,
+                failure.problem
+            )
+        }
+        Ok(_) => format!("Error: could not decode {}, {}.", location, problem(error)),
+    };
+    if !decoded.is_empty() {
+        message.push_str("\nDecoded before it:");
+        for (name, value) in decoded {
+            message.push_str(&format!("\n    {}: {}", name, value));
+        }
+    }
+    message
+}
+
+/// Decodes the fields at the start of `bytes`, which begin `offset` bytes into
+/// the certificate, noting each that decodes until one doesn't. Returns how many
+/// bytes they took up.
+fn walk(
+    bytes: &[u8],
+    offset: usize,
+    kind: &Kind,
+    path: &mut Vec<String>,
+    decoded: &mut Vec<(String, String)>,
+) -> Result<usize, Failure> {
+    let mut position = 0;
+    match kind {
+        Kind::Fields(fields) => {
+            for field in *fields {
+                let tag = bytes.get(position);
+                if field.optional && !tag.is_some_and(|tag| field.tags.contains(tag)) {
+                    continue;
+                }
+                let name = String::from(field.name);
+                position += element(
+                    &bytes[position..],
+                    offset + position,
+                    field,
+                    name,
+                    path,
+                    decoded,
+                )?;
+            }
+        }
+        Kind::Each(field) => {
+            let mut i = 0;
+            while position < bytes.len() {
+                i += 1;
+                let name = format!("{} {}", field.name, i);
+                position += element(
+                    &bytes[position..],
+                    offset + position,
+                    field,
+                    name,
+                    path,
+                    decoded,
+                )?;
+            }
+        }
+        Kind::Value(_) => {}
+    }
+    Ok(position)
+}
+
+/// Decodes one field, returning how many bytes it took up.
+fn element(
+    bytes: &[u8],
+    offset: usize,
+    field: &Field,
+    name: String,
+    path: &mut Vec<String>,
+    decoded: &mut Vec<(String, String)>,
+) -> Result<usize, Failure> {
+    path.insert(0, name.clone());
+    let fail = |path: &[String], tlv, problem: String| Failure {
+        offset,
+        path: path.to_vec(),
+        tlv,
+        problem,
+    };
+
+    let tag = match bytes.first() {
+        Some(tag) => *tag,
+        None => {
+            return Err(fail(
+                path,
+                None,
+                String::from("the data ends where it should start"),
+            ))
+        }
+    };
+    if !field.tags.contains(&tag) {
+        let expected: Vec<&str> = field.tags.iter().map(|tag| tag_name(*tag)).collect();
+        let problem = format!(
+            "expected {} but found {}",
+            expected.join(" or "),
+            tag_name(tag)
+        );
+        return Err(fail(path, None, problem));
+    }
+    let (header, length) = header(bytes).map_err(|problem| fail(path, None, problem))?;
+    let tlv = Some((tag, length));
+    let available = bytes.len() - header;
+
+    match field.kind {
+        Kind::Value(value) if length <= available => {
+            let value =
+                value(&bytes[..header + length]).map_err(|problem| fail(path, tlv, problem))?;
+            let mut name = name;
+            name[..1].make_ascii_uppercase();
+            decoded.push((name, value));
+        }
+        Kind::Fields(_) | Kind::Each(_) => {
+            // A truncated field still has its first fields decoded
+            let contents = &bytes[header..header + length.min(available)];
+            let used = walk(contents, offset + header, &field.kind, path, decoded)?;
+            if used < length.min(available) {
+                let problem = format!(
+                    "{} bytes follow its last field",
+                    length.min(available) - used
+                );
+                return Err(fail(path, tlv, problem));
+            }
+        }
+        Kind::Value(_) => {}
+    }
+    if length > available {
+        let problem = format!(
+            "its length is {} bytes, but only {} follow",
+            length, available
+        );
+        return Err(fail(path, tlv, problem));
+    }
+    path.remove(0);
+    Ok(header + length)
+}
+
+/// The length of the header of the DER element at the start of `bytes`, and of
+/// its contents.
+fn header(bytes: &[u8]) -> Result<(usize, usize), String> {
+    if bytes[0] & 0x1f == 0x1f {
+        return Err(String::from(
+            "its tag takes several bytes, as none in a certificate do",
+        ));
+    }
+    let first = match bytes.get(1) {
+        Some(first) => *first,
+        None => return Err(String::from("its length is cut off")),
+    };
+    if first < 0x80 {
+        return Ok((2, first as usize));
+    }
+    let count = (first & 0x7f) as usize;
+    if count == 0 {
+        return Err(String::from(
+            "its length is indefinite, which DER doesn't allow",
+        ));
+    }
+    if count > 4 {
+        return Err(format!("its length takes {} bytes, far too many", count));
+    }
+    let octets = bytes
+        .get(2..2 + count)
+        .ok_or_else(|| String::from("its length is cut off"))?;
+    let length = octets
+        .iter()
+        .fold(0, |length, octet| length << 8 | *octet as usize);
+    Ok((2 + count, length))
+}
+
+fn tag_name(tag: u8) -> &'static str {
+    match tag {
+        0x02 => "an INTEGER",
+        0x03 => "a BIT STRING",
+        0x04 => "an OCTET STRING",
+        0x05 => "a NULL",
+        0x06 => "an OBJECT IDENTIFIER",
+        0x0c => "a UTF8String",
+        0x13 => "a PrintableString",
+        0x17 => "a UTCTime",
+        0x18 => "a GeneralizedTime",
+        0x30 => "a SEQUENCE",
+        0x31 => "a SET",
+        0x81 => "a [1]",
+        0x82 => "a [2]",
+        0xa0 => "a [0]",
+        0xa3 => "a [3]",
+        _ => "an unexpected tag",
+    }
+}
+
+fn problem(error: Err<X509Error>) -> String {
+    match error {
+        Err::Incomplete(_) => String::from("it is cut off"),
+        Err::Error(error) | Err::Failure(error) => error.to_string(),
+    }
+}
+
+fn contents(element: &[u8]) -> &[u8] {
+    match header(element) {
+        Ok((header, _)) => &element[header..],
+        Err(_) => &[],
+    }
+}
+
+fn version(element: &[u8]) -> Result<String, String> {
+    let (_, version) = X509Version::from_der(contents(element)).map_err(problem)?;
+    Ok((version.0 + 1).to_string())
+}
+
+fn serial_number(element: &[u8]) -> Result<String, String> {
+    match contents(element) {
+        [] => Err(String::from("it is empty")),
+        serial => Ok(BigUint::from_bytes_be(serial).to_string()),
+    }
+}
+
+fn algorithm_identifier(element: &[u8]) -> Result<String, String> {
+    let (_, algorithm) = AlgorithmIdentifier::from_der(element).map_err(problem)?;
+    Ok(oid::name(&algorithm.algorithm))
+}
+
+fn distinguished_name(element: &[u8]) -> Result<String, String> {
+    let (_, name) = X509Name::from_der(element).map_err(problem)?;
+    Ok(dn::to_string(&name))
+}
+
+fn asn1_time(element: &[u8]) -> Result<String, String> {
+    let (_, time) = ASN1Time::from_der(element).map_err(problem)?;
+    Ok(time.to_string())
+}
+
+fn public_key(element: &[u8]) -> Result<String, String> {
+    let (_, key) = SubjectPublicKeyInfo::from_der(element).map_err(problem)?;
+    Ok(oid::name(&key.algorithm.algorithm))
+}
+
+fn extension(element: &[u8]) -> Result<String, String> {
+    let (_, extension) = X509Extension::from_der(element).map_err(problem)?;
+    let name = oid2sn(&extension.oid, oid_registry())
+        .map(String::from)
+        .unwrap_or_else(|_| extension.oid.to_id_string());
+    Ok(match extension.critical {
+        true => format!("{}, critical", name),
+        false => name,
+    })
+}
+
+fn bit_string(element: &[u8]) -> Result<String, String> {
+    match contents(element) {
+        [] => Err(String::from("it is empty")),
+        [_, bits @ ..] => Ok(format!("{} bytes", bits.len())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::explain;
+
+    fn renewal() -> Vec<u8> {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/renewal.crt")).unwrap();
+        pem.contents
+    }
+
+    #[test]
+    fn should_point_at_where_a_truncated_certificate_ends() {
+        let der = &renewal()[..300];
+        let error = parse_x509_certificate(der).err().unwrap();
+
+        assert_eq!(
+            explain("renewal.der", der, error),
+            "Error: could not decode renewal.der past byte 298 of 300, in the extension 4 of the extensions \
+             of the TBS certificate (a SEQUENCE of 14 bytes): its length is 14 bytes, but only 0 follow.\n\
+             Decoded before it:\n    \
+             Version: 3\n    \
+             Serial number: 22\n    \
+             Signature algorithm: ecdsa-with-SHA256\n    \
+             Issuer: CN=Example Issuing CA\n    \
+             Not before: Apr  1 00:00:00 2026 +00:00\n    \
+             Not after: Oct 17 00:00:00 2026 +00:00\n    \
+             Subject: CN=renewal.example.com\n    \
+             Subject public key info: id-ecPublicKey\n    \
+             Extension 1: basicConstraints, critical\n    \
+             Extension 2: subjectKeyIdentifier\n    \
+             Extension 3: authorityKeyIdentifier"
+        );
+    }
+
+    #[test]
+    fn should_name_the_field_with_the_wrong_tag() {
+        let mut der = renewal();
+        // The UTCTime of notBefore, made an OCTET STRING
+        der[61] = 0x04;
+        let error = parse_x509_certificate(&der).err().unwrap();
+
+        assert_eq!(
+            explain("renewal.der", &der, error),
+            "Error: could not decode renewal.der past byte 61 of 452, in the not before of the validity \
+             of the TBS certificate: expected a UTCTime or a GeneralizedTime but found an OCTET STRING.\n\
+             Decoded before it:\n    \
+             Version: 3\n    \
+             Serial number: 22\n    \
+             Signature algorithm: ecdsa-with-SHA256\n    \
+             Issuer: CN=Example Issuing CA"
+        );
+    }
+
+    #[test]
+    fn should_point_out_lengths_der_does_not_allow() {
+        let der = [0x30, 0x80, 0x00, 0x00];
+        let error = parse_x509_certificate(&der).err().unwrap();
+
+        assert_eq!(
+            explain("indefinite.der", &der, error),
+            "Error: could not decode indefinite.der past byte 0 of 4, in the certificate: \
+             its length is indefinite, which DER doesn't allow."
+        );
+    }
+}
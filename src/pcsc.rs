@@ -0,0 +1,380 @@
+/// The name of the reader a card was in and the card's response to each command.
+pub type Exchange = (String, Vec<Vec<u8>>);
+
+/// The reader whose name contains `wanted`, or when none was asked for a
+/// YubiKey's, since laptops often have a built-in reader listed first.
+#[cfg(any(test, all(feature = "pcsc", unix)))]
+pub fn choose(
+    readers: &[String],
+    wanted: Option<&str>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if readers.is_empty() {
+        return Err(String::from("Error: no smart card reader was found.").into());
+    }
+    let containing = |part: &str| {
+        let part = part.to_lowercase();
+        readers
+            .iter()
+            .find(|reader| reader.to_lowercase().contains(&part))
+            .cloned()
+    };
+    match wanted {
+        Some(wanted) => containing(wanted).ok_or_else(|| {
+            format!(
+                "Error: no smart card reader matches {}, the readers are: {}.",
+                wanted,
+                readers.join(", ")
+            )
+            .into()
+        }),
+        None => Ok(containing("yubikey").unwrap_or_else(|| readers[0].clone())),
+    }
+}
+
+/// Sends commands to the card in a reader within one transaction, so that no
+/// other program can select another application in between. Returns the
+/// reader's name and each response, ending with its status word.
+#[cfg(all(feature = "pcsc", unix))]
+pub fn transmit(
+    reader: Option<&str>,
+    commands: &[Vec<u8>],
+) -> Result<Exchange, Box<dyn std::error::Error>> {
+    let context = ffi::Context::establish()?;
+    let reader = choose(&context.readers()?, reader)?;
+    let card = context.connect(&reader)?;
+    let responses = commands
+        .iter()
+        .map(|command| card.transmit(command))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((reader, responses))
+}
+
+#[cfg(all(feature = "pcsc", not(unix)))]
+pub fn transmit(
+    _reader: Option<&str>,
+    _commands: &[Vec<u8>],
+) -> Result<Exchange, Box<dyn std::error::Error>> {
+    Err(String::from("Error: smart cards can only be read on Unix-like systems.").into())
+}
+
+#[cfg(not(feature = "pcsc"))]
+pub fn transmit(
+    _reader: Option<&str>,
+    _commands: &[Vec<u8>],
+) -> Result<Exchange, Box<dyn std::error::Error>> {
+    let err_msg =
+        "Error: reading smart cards needs cert-decoder to be built with the pcsc feature.";
+    Err(String::from(err_msg).into())
+}
+
+/// Just enough of the PC/SC API to send commands to a card, loaded from the
+/// system's library so that no headers are needed to build.
+#[cfg(all(feature = "pcsc", unix))]
+mod ffi {
+
+    use std::ffi::{c_void, CStr, CString};
+    use std::os::raw::c_char;
+
+    // pcsc-lite uses the C types, Apple's PC/SC framework fixed 32-bit ones
+    #[cfg(not(target_os = "macos"))]
+    type Long = std::os::raw::c_long;
+    #[cfg(not(target_os = "macos"))]
+    type Dword = std::os::raw::c_ulong;
+    #[cfg(target_os = "macos")]
+    type Long = i32;
+    #[cfg(target_os = "macos")]
+    type Dword = u32;
+
+    #[cfg(not(target_os = "macos"))]
+    const LIBRARY: &[u8] = b"libpcsclite.so.1\0";
+    #[cfg(target_os = "macos")]
+    const LIBRARY: &[u8] = b"/System/Library/Frameworks/PCSC.framework/PCSC\0";
+
+    const SCARD_S_SUCCESS: u32 = 0;
+    const SCARD_E_NO_READERS_AVAILABLE: u32 = 0x8010_002e;
+    const SCARD_SCOPE_SYSTEM: Dword = 2;
+    const SCARD_SHARE_SHARED: Dword = 2;
+    const SCARD_PROTOCOL_T0: Dword = 1;
+    const SCARD_PROTOCOL_T1: Dword = 2;
+    const SCARD_LEAVE_CARD: Dword = 0;
+
+    /// The names of the return values a user can do something about.
+    const RETURN_VALUES: &[(u32, &str)] = &[
+        (0x8010_0009, "the reader is unknown"),
+        (0x8010_000b, "another program has the card open exclusively"),
+        (0x8010_000c, "there is no card in the reader"),
+        (0x8010_0017, "the reader is unavailable"),
+        (0x8010_001d, "the PC/SC service is not running, start pcscd"),
+        (0x8010_002e, "no smart card reader was found"),
+        (0x8010_0066, "the card is not responding"),
+        (0x8010_0069, "the card was removed"),
+    ];
+
+    #[repr(C)]
+    struct IoRequest {
+        protocol: Dword,
+        length: Dword,
+    }
+
+    type Establish = unsafe extern "C" fn(Dword, *const c_void, *const c_void, *mut Long) -> Long;
+    type Release = unsafe extern "C" fn(Long) -> Long;
+    type ListReaders = unsafe extern "C" fn(Long, *const c_char, *mut c_char, *mut Dword) -> Long;
+    type Connect =
+        unsafe extern "C" fn(Long, *const c_char, Dword, Dword, *mut Long, *mut Dword) -> Long;
+    type Disconnect = unsafe extern "C" fn(Long, Dword) -> Long;
+    type BeginTransaction = unsafe extern "C" fn(Long) -> Long;
+    type EndTransaction = unsafe extern "C" fn(Long, Dword) -> Long;
+    type Transmit = unsafe extern "C" fn(
+        Long,
+        *const IoRequest,
+        *const u8,
+        Dword,
+        *mut IoRequest,
+        *mut u8,
+        *mut Dword,
+    ) -> Long;
+
+    /// The PC/SC library and a context with its service, released on drop.
+    pub struct Context {
+        library: *mut c_void,
+        handle: Long,
+    }
+
+    /// A card connected to within a transaction, ended on drop.
+    pub struct Card<'a> {
+        context: &'a Context,
+        handle: Long,
+        protocol: Dword,
+    }
+
+    fn check(call: &str, rv: Long) -> Result<(), Box<dyn std::error::Error>> {
+        // pcsc-lite's codes are positive longs, Apple's negative 32-bit ones
+        let rv = rv as u32;
+        if rv == SCARD_S_SUCCESS {
+            return Ok(());
+        }
+        let reason = RETURN_VALUES
+            .iter()
+            .find(|(value, _)| *value == rv)
+            .map_or_else(
+                || format!("0x{:08x}", rv),
+                |(_, reason)| String::from(*reason),
+            );
+        Err(format!("Error: {} failed: {}.", call, reason).into())
+    }
+
+    impl Context {
+        pub fn establish() -> Result<Context, Box<dyn std::error::Error>> {
+            // SAFETY: loading the system's PC/SC library runs nothing but its initializers
+            let library =
+                unsafe { libc::dlopen(LIBRARY.as_ptr() as *const c_char, libc::RTLD_NOW) };
+            if library.is_null() {
+                // SAFETY: dlerror returns a string owned by libc, valid until the next dl call
+                let reason = unsafe { CStr::from_ptr(libc::dlerror()) };
+                return Err(format!(
+                    "Error: could not load the PC/SC library: {}.",
+                    reason.to_string_lossy()
+                )
+                .into());
+            }
+            let mut context = Context { library, handle: 0 };
+            let establish: Establish = context.function(b"SCardEstablishContext\0")?;
+            // SAFETY: the reserved arguments are null, as they must be
+            let rv = unsafe {
+                establish(
+                    SCARD_SCOPE_SYSTEM,
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    &mut context.handle,
+                )
+            };
+            check("SCardEstablishContext", rv)?;
+            Ok(context)
+        }
+
+        /// Looks up a function of the library by name.
+        fn function<F: Copy>(&self, name: &[u8]) -> Result<F, Box<dyn std::error::Error>> {
+            // SAFETY: the name is nul terminated
+            let function = unsafe { libc::dlsym(self.library, name.as_ptr() as *const c_char) };
+            if function.is_null() {
+                let name = String::from_utf8_lossy(&name[..name.len() - 1]);
+                return Err(format!("Error: the PC/SC library has no {}.", name).into());
+            }
+            // SAFETY: F is the function pointer type the PC/SC API declares for this name
+            Ok(unsafe { std::mem::transmute_copy(&function) })
+        }
+
+        pub fn readers(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            let list: ListReaders = self.function(b"SCardListReaders\0")?;
+            let mut length: Dword = 0;
+            // SAFETY: a null buffer asks only for the length
+            let rv = unsafe {
+                list(
+                    self.handle,
+                    std::ptr::null(),
+                    std::ptr::null_mut(),
+                    &mut length,
+                )
+            };
+            if rv as u32 == SCARD_E_NO_READERS_AVAILABLE {
+                return Ok(Vec::new());
+            }
+            check("SCardListReaders", rv)?;
+            let mut names = vec![0u8; length as usize];
+            // SAFETY: the buffer has room for the length just given
+            let rv = unsafe {
+                list(
+                    self.handle,
+                    std::ptr::null(),
+                    names.as_mut_ptr() as *mut c_char,
+                    &mut length,
+                )
+            };
+            check("SCardListReaders", rv)?;
+            // The names are nul separated, with an extra nul at the end
+            Ok(names
+                .split(|byte| *byte == 0)
+                .filter(|name| !name.is_empty())
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .collect())
+        }
+
+        pub fn connect(&self, reader: &str) -> Result<Card<'_>, Box<dyn std::error::Error>> {
+            let connect: Connect = self.function(b"SCardConnect\0")?;
+            let begin: BeginTransaction = self.function(b"SCardBeginTransaction\0")?;
+            let name = CString::new(reader)
+                .map_err(|_| format!("Error: {} is not a valid reader name.", reader))?;
+            let mut card = Card {
+                context: self,
+                handle: 0,
+                protocol: 0,
+            };
+            // SAFETY: the handle and protocol are written on success
+            let rv = unsafe {
+                connect(
+                    self.handle,
+                    name.as_ptr(),
+                    SCARD_SHARE_SHARED,
+                    SCARD_PROTOCOL_T0 | SCARD_PROTOCOL_T1,
+                    &mut card.handle,
+                    &mut card.protocol,
+                )
+            };
+            check(&format!("Connecting to {}", reader), rv)?;
+            // SAFETY: the card was just connected to, dropping it on failure disconnects
+            let rv = unsafe { begin(card.handle) };
+            check("SCardBeginTransaction", rv)?;
+            Ok(card)
+        }
+    }
+
+    impl Drop for Context {
+        fn drop(&mut self) {
+            if let Ok(release) = self.function::<Release>(b"SCardReleaseContext\0") {
+                // SAFETY: every card borrows the context, so all are disconnected by now
+                unsafe { release(self.handle) };
+            }
+            // SAFETY: nothing from the library is used after this
+            unsafe { libc::dlclose(self.library) };
+        }
+    }
+
+    impl Card<'_> {
+        /// Sends a command, fetching the rest of a long response as T=0 hands it out.
+        pub fn transmit(&self, command: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+            let transmit: Transmit = self.context.function(b"SCardTransmit\0")?;
+            let send = IoRequest {
+                protocol: self.protocol,
+                length: std::mem::size_of::<IoRequest>() as Dword,
+            };
+            let mut data = Vec::new();
+            let mut command = command.to_vec();
+            loop {
+                let mut buffer = vec![0u8; 258];
+                let mut length = buffer.len() as Dword;
+                // SAFETY: the buffer has room for the length given
+                let rv = unsafe {
+                    transmit(
+                        self.handle,
+                        &send,
+                        command.as_ptr(),
+                        command.len() as Dword,
+                        std::ptr::null_mut(),
+                        buffer.as_mut_ptr(),
+                        &mut length,
+                    )
+                };
+                check("SCardTransmit", rv)?;
+                buffer.truncate(length as usize);
+                if buffer.len() < 2 {
+                    return Err(String::from(
+                        "Error: the card sent a response without a status word.",
+                    )
+                    .into());
+                }
+                let status = buffer.split_off(buffer.len() - 2);
+                data.extend(buffer);
+                match status[..] {
+                    // 61xx means xx more bytes are waiting for a GET RESPONSE
+                    [0x61, more] => command = vec![0x00, 0xc0, 0x00, 0x00, more],
+                    _ => {
+                        data.extend(status);
+                        return Ok(data);
+                    }
+                }
+            }
+        }
+    }
+
+    impl Drop for Card<'_> {
+        fn drop(&mut self) {
+            let end = self
+                .context
+                .function::<EndTransaction>(b"SCardEndTransaction\0");
+            let disconnect = self.context.function::<Disconnect>(b"SCardDisconnect\0");
+            // SAFETY: the card is connected in a transaction and no longer used
+            unsafe {
+                if let Ok(end) = end {
+                    end(self.handle, SCARD_LEAVE_CARD);
+                }
+                if let Ok(disconnect) = disconnect {
+                    disconnect(self.handle, SCARD_LEAVE_CARD);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::choose;
+
+    #[test]
+    fn should_prefer_a_yubikey_unless_a_reader_is_named() {
+        let readers = vec![
+            String::from("Alcor Micro AU9560 00 00"),
+            String::from("Yubico YubiKey OTP+FIDO+CCID 01 00"),
+        ];
+        assert_eq!(
+            choose(&readers, None).unwrap(),
+            "Yubico YubiKey OTP+FIDO+CCID 01 00"
+        );
+        assert_eq!(
+            choose(&readers, Some("alcor")).unwrap(),
+            "Alcor Micro AU9560 00 00"
+        );
+        assert_eq!(
+            choose(&readers[..1], None).unwrap(),
+            "Alcor Micro AU9560 00 00"
+        );
+        assert_eq!(
+            choose(&readers, Some("Gemalto")).err().unwrap().to_string(),
+            "Error: no smart card reader matches Gemalto, the readers are: Alcor Micro AU9560 00 00, Yubico YubiKey OTP+FIDO+CCID 01 00."
+        );
+        assert_eq!(
+            choose(&[], None).err().unwrap().to_string(),
+            "Error: no smart card reader was found."
+        );
+    }
+}
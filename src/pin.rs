@@ -0,0 +1,235 @@
+use std::error::Error;
+use std::fmt;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+
+use crate::report::{Entry, Report};
+use crate::trust_path::fingerprint;
+
+/// The exit status when a certificate doesn't match what was expected, apart
+/// from the 1 of every other error so deployment scripts can tell them apart.
+pub const MISMATCH_STATUS: i32 = 3;
+
+/// What the certificates found have to match, from `--expect-pin` and
+/// `--expect-fingerprint`. Either can be given more than once, for a backup
+/// key or a certificate about to be replaced, and any of them matching will do.
+#[derive(Debug, Default, PartialEq)]
+pub struct Expectations {
+    /// SHA-256 hashes of public keys, one of which some certificate of each chain has.
+    pub pins: Vec<Vec<u8>>,
+    /// SHA-256 fingerprints, one of which the leaf of each chain has.
+    pub fingerprints: Vec<Vec<u8>>,
+}
+
+impl Expectations {
+    pub fn is_set(&self) -> bool {
+        !self.pins.is_empty() || !self.fingerprints.is_empty()
+    }
+
+    /// Adds a pin written in base64 as HPKP and curl's `--pinnedpubkey` take it,
+    /// with or without their `pin-sha256=` and `sha256//` prefixes, or in hex.
+    pub fn add_pin(&mut self, value: &str) -> Result<(), Box<dyn Error>> {
+        let pin = value
+            .trim_start_matches("pin-sha256=")
+            .trim_start_matches("sha256//")
+            .trim_matches('"');
+        let hash = STANDARD
+            .decode(pin)
+            .ok()
+            .filter(|hash| hash.len() == 32)
+            .or_else(|| hex(pin))
+            .ok_or_else(|| {
+                format!(
+                    "Error: --expect-pin expects the base64 or hex SHA-256 of a public key, not {}.",
+                    value
+                )
+            })?;
+        self.pins.push(hash);
+        Ok(())
+    }
+
+    /// Adds a fingerprint in hex, with or without colons between the bytes.
+    pub fn add_fingerprint(&mut self, value: &str) -> Result<(), Box<dyn Error>> {
+        let hash = hex(value).ok_or_else(|| {
+            format!(
+                "Error: --expect-fingerprint expects a SHA-256 fingerprint in hex, not {}.",
+                value
+            )
+        })?;
+        self.fingerprints.push(hash);
+        Ok(())
+    }
+}
+
+/// 32 bytes written in hex, which may be separated by colons or spaces.
+fn hex(value: &str) -> Option<Vec<u8>> {
+    let digits: String = value
+        .chars()
+        .filter(|c| *c != ':' && !c.is_whitespace())
+        .collect();
+    if digits.len() != 64 || !digits.is_ascii() {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// A certificate that didn't match what was expected.
+#[derive(Debug)]
+pub struct Mismatch(String);
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for Mismatch {}
+
+/// The SHA-256 of a certificate's public key, the way HPKP writes it.
+fn pin(entry: &Entry) -> String {
+    STANDARD.encode(digest(&SHA256, entry.certificate().public_key().raw))
+}
+
+/// Checks every chain in a report against the expectations, saying where each
+/// was met, or failing with a `Mismatch` naming what was found instead.
+pub fn verify(report: &Report, expect: &Expectations) -> Result<Vec<String>, Box<dyn Error>> {
+    // The certificates found in each place, leaf first
+    let mut chains: Vec<(&str, Vec<&Entry>)> = Vec::new();
+    for entry in &report.entries {
+        let source = match entry.location.rsplit_once(" [") {
+            Some((source, index)) if index.ends_with(']') => source,
+            _ => &entry.location,
+        };
+        match chains.iter_mut().find(|(known, _)| *known == source) {
+            Some((_, chain)) => chain.push(entry),
+            None => chains.push((source, vec![entry])),
+        }
+    }
+    if chains.is_empty() {
+        return Err(Mismatch(String::from(
+            "Error: no certificates were found to check against what was expected.",
+        ))
+        .into());
+    }
+
+    let mut met = Vec::new();
+    let mut problems = Vec::new();
+    for (source, chain) in &chains {
+        if !expect.fingerprints.is_empty() {
+            let leaf = chain[0];
+            let actual = digest(&SHA256, &leaf.der);
+            match expect
+                .fingerprints
+                .iter()
+                .any(|hash| hash == actual.as_ref())
+            {
+                true => met.push(format!("{} has the expected fingerprint.", leaf.location)),
+                false => problems.push(format!(
+                    "Error: {} does not match --expect-fingerprint, its fingerprint is {}.",
+                    leaf.location,
+                    fingerprint(&leaf.der)
+                )),
+            }
+        }
+        if !expect.pins.is_empty() {
+            let pinned = chain.iter().find(|entry| {
+                let hash = digest(&SHA256, entry.certificate().public_key().raw);
+                expect.pins.iter().any(|pin| pin == hash.as_ref())
+            });
+            match pinned {
+                Some(entry) => met.push(format!("{} has the expected pin.", entry.location)),
+                None => {
+                    let pins: Vec<String> = chain.iter().map(|entry| pin(entry)).collect();
+                    problems.push(format!(
+                        "Error: {} does not match --expect-pin, its pins are {}.",
+                        source,
+                        pins.join(", ")
+                    ));
+                }
+            }
+        }
+    }
+    match problems.is_empty() {
+        true => Ok(met),
+        false => Err(Mismatch(problems.join("\n")).into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{pin, verify, Expectations, Mismatch};
+    use crate::options::Options;
+    use crate::report::Report;
+    use crate::target::Target;
+    use crate::test::FakeProcessor;
+    use crate::trust_path::fingerprint;
+
+    fn report() -> Report {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("chain.pem", include_str!("../resources/example-chain.pem"));
+        let targets = vec![Target::Path(String::from("chain.pem"))];
+        Report::build(&processor, &targets, &Options::default())
+    }
+
+    #[test]
+    fn should_read_pins_and_fingerprints_as_they_are_usually_written() {
+        let mut expect = Expectations::default();
+        let hash = "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=";
+
+        expect.add_pin(hash).unwrap();
+        expect.add_pin(&format!("pin-sha256=\"{}\"", hash)).unwrap();
+        expect.add_pin(&format!("sha256//{}", hash)).unwrap();
+        expect
+            .add_fingerprint(
+                "E3:B0:C4:42:98:FC:1C:14:9A:FB:F4:C8:99:6F:B9:24:27:AE:41:E4:64:9B:93:4C:A4:95:99:1B:78:52:B8:55",
+            )
+            .unwrap();
+
+        assert_eq!(expect.pins[0], expect.pins[1]);
+        assert_eq!(expect.pins[0], expect.pins[2]);
+        assert_eq!(expect.pins[0], expect.fingerprints[0]);
+        assert_eq!(
+            expect.add_pin("abc").unwrap_err().to_string(),
+            "Error: --expect-pin expects the base64 or hex SHA-256 of a public key, not abc."
+        );
+    }
+
+    #[test]
+    fn should_match_a_pin_anywhere_in_the_chain() {
+        let report = report();
+        let root = pin(&report.entries[1]);
+        let mut expect = Expectations::default();
+        expect.add_pin(&root).unwrap();
+
+        assert_eq!(
+            verify(&report, &expect).unwrap(),
+            vec!["chain.pem [2/2] has the expected pin."]
+        );
+    }
+
+    #[test]
+    fn should_fail_with_a_mismatch_when_the_leaf_is_not_the_one_expected() {
+        let report = report();
+        let mut expect = Expectations::default();
+        expect
+            .add_fingerprint(&fingerprint(&report.entries[1].der))
+            .unwrap();
+
+        let error = verify(&report, &expect).unwrap_err();
+
+        assert!(error.is::<Mismatch>());
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Error: chain.pem [1/2] does not match --expect-fingerprint, its fingerprint is {}.",
+                fingerprint(&report.entries[0].der)
+            )
+        );
+    }
+}
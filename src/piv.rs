@@ -0,0 +1,395 @@
+use std::convert::TryInto;
+use std::fmt;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::parse_x509_certificate;
+
+use crate::cms;
+use crate::compression;
+use crate::dn;
+use crate::report::Report;
+use crate::FileProcessor;
+
+/// Which reader to talk to, from the `piv` command's `--reader`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PivOptions {
+    pub reader: Option<String>,
+}
+
+/// The PIV application, without its version, as YubiKeys accept selecting it.
+const AID: [u8; 5] = [0xa0, 0x00, 0x00, 0x03, 0x08];
+
+/// The key slots SP 800-73 keeps certificates for, and the data object each
+/// slot's certificate is stored in.
+const SLOTS: &[(u8, &str, [u8; 3])] = &[
+    (0x9a, "PIV Authentication", [0x5f, 0xc1, 0x05]),
+    (0x9c, "Digital Signature", [0x5f, 0xc1, 0x0a]),
+    (0x9d, "Key Management", [0x5f, 0xc1, 0x0b]),
+    (0x9e, "Card Authentication", [0x5f, 0xc1, 0x01]),
+];
+
+/// Where a YubiKey keeps the certificate, for slot f9, that signs its attestations.
+const ATTESTATION_OBJECT: [u8; 3] = [0x5f, 0xff, 0x01];
+
+/// Yubico's extensions to attestation certificates.
+const FIRMWARE: &str = "1.3.6.1.4.1.41482.3.3";
+const POLICY: &str = "1.3.6.1.4.1.41482.3.8";
+
+const PIN_POLICIES: &[(u8, &str)] = &[(1, "never"), (2, "once per session"), (3, "always")];
+const TOUCH_POLICIES: &[(u8, &str)] = &[(1, "never"), (2, "always"), (3, "cached for 15s")];
+
+/// A key slot and what is in it.
+pub struct Slot {
+    pub id: u8,
+    pub name: &'static str,
+    /// The subject of the certificate in the slot, if there is one.
+    pub subject: Option<String>,
+    /// The PIN and touch policies an attestation vouches for, when the key
+    /// was generated on the YubiKey rather than imported.
+    pub attested: Option<Vec<String>>,
+}
+
+/// What a PIV card, such as a YubiKey, holds in its key slots.
+pub struct Piv {
+    pub reader: String,
+    /// The YubiKey's firmware version and serial number, which other cards don't answer.
+    pub firmware: Option<String>,
+    pub serial: Option<u32>,
+    pub slots: Vec<Slot>,
+    pub report: Report,
+}
+
+/// A command without data, expecting up to 256 bytes back.
+fn command(instruction: u8, p1: u8) -> Vec<u8> {
+    vec![0x00, instruction, p1, 0x00, 0x00]
+}
+
+fn get_data(object: &[u8; 3]) -> Vec<u8> {
+    let mut command = vec![0x00, 0xcb, 0x3f, 0xff, 0x05, 0x5c, 0x03];
+    command.extend(object);
+    command.push(0x00);
+    command
+}
+
+/// A response's data, if its status word says it succeeded.
+fn data(response: &[u8]) -> Option<&[u8]> {
+    match response.len().checked_sub(2) {
+        Some(end) if response[end..] == [0x90, 0x00] => Some(&response[..end]),
+        _ => None,
+    }
+}
+
+/// The certificate in a PIV data object: the DER in tag 70, gzipped when the
+/// CertInfo byte in tag 71 says so, as YubiKeys do for large certificates.
+fn certificate(source: &str, object: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let invalid = || format!("Error: {} does not hold a PIV certificate object.", source);
+    let (container, _) = cms::element(object)
+        .filter(|(tlv, _)| tlv.tag == 0x53)
+        .ok_or_else(invalid)?;
+    let fields = container.children().ok_or_else(invalid)?;
+    let der = fields
+        .iter()
+        .find(|field| field.tag == 0x70)
+        .ok_or_else(invalid)?;
+    let compressed = fields
+        .iter()
+        .find(|field| field.tag == 0x71)
+        .is_some_and(|info| info.contents.first().is_some_and(|info| info & 0x01 != 0));
+    match compressed {
+        true => compression::decompress(source, der.contents)?.ok_or_else(|| invalid().into()),
+        false => Ok(der.contents.to_vec()),
+    }
+}
+
+/// Reads the certificates in a PIV card's key slots, and on a YubiKey the
+/// attestation of each key it generated, checked against its slot f9
+/// certificate. Nothing is written to the card and no PIN is needed.
+pub fn inspect(
+    processor: &impl FileProcessor,
+    options: &PivOptions,
+) -> Result<Piv, Box<dyn std::error::Error>> {
+    let mut commands = vec![
+        [vec![0x00, 0xa4, 0x04, 0x00, AID.len() as u8], AID.to_vec()].concat(),
+        command(0xfd, 0x00),
+        command(0xf8, 0x00),
+        get_data(&ATTESTATION_OBJECT),
+    ];
+    for (id, _, object) in SLOTS {
+        commands.push(get_data(object));
+        commands.push(command(0xf9, *id));
+    }
+    let (reader, responses) = processor.transmit(options.reader.as_deref(), &commands)?;
+    if data(&responses[0]).is_none() {
+        return Err(format!("Error: the card in {} has no PIV application.", reader).into());
+    }
+
+    let firmware = data(&responses[1])
+        .filter(|version| version.len() == 3)
+        .map(|version| format!("{}.{}.{}", version[0], version[1], version[2]));
+    let serial = data(&responses[2])
+        .and_then(|serial| serial.try_into().ok())
+        .map(u32::from_be_bytes);
+
+    let mut report = Report::default();
+    let mut items = Vec::new();
+    let f9 = data(&responses[3]).and_then(|object| certificate("slot f9", object).ok());
+    let f9_parsed = f9
+        .as_deref()
+        .and_then(|der| parse_x509_certificate(der).ok())
+        .map(|(_, cert)| cert);
+
+    let mut slots = Vec::new();
+    for (i, (id, name, _)) in SLOTS.iter().enumerate() {
+        let label = format!("slot {:02x} {}", id, name);
+        let stored = match data(&responses[4 + 2 * i]).map(|object| certificate(&label, object)) {
+            Some(Ok(der)) => Some(der),
+            Some(Err(error)) => {
+                report.add_named(&label, Err(error));
+                None
+            }
+            None => None,
+        };
+        let attestation = data(&responses[5 + 2 * i]).map(<[u8]>::to_vec);
+
+        let parsed = stored
+            .as_deref()
+            .and_then(|der| parse_x509_certificate(der).ok())
+            .map(|(_, cert)| cert);
+        let attested = attestation
+            .as_deref()
+            .and_then(|der| parse_x509_certificate(der).ok())
+            .map(|(_, cert)| cert);
+        if let Some(attested) = &attested {
+            let signed = f9_parsed
+                .as_ref()
+                .is_some_and(|f9| attested.verify_signature(Some(f9.public_key())).is_ok());
+            if !signed {
+                report.warnings.push(format!(
+                    "Warning: the attestation for slot {:02x} is not signed by the YubiKey's attestation certificate in slot f9.",
+                    id
+                ));
+            }
+            if parsed
+                .as_ref()
+                .is_some_and(|cert| cert.public_key().raw != attested.public_key().raw)
+            {
+                report.warnings.push(format!(
+                    "Warning: the certificate in slot {:02x} is for a different key than the one the YubiKey generated there.",
+                    id
+                ));
+            }
+        }
+        slots.push(Slot {
+            id: *id,
+            name,
+            subject: parsed.as_ref().map(|cert| dn::to_string(cert.subject())),
+            attested: attested.as_ref().map(policies),
+        });
+
+        if let Some(der) = stored {
+            items.push((label.clone(), der));
+        }
+        if let Some(der) = attestation {
+            items.push((format!("{} attestation", label), der));
+        }
+    }
+    if !items.is_empty() {
+        if let Some(der) = f9 {
+            items.push((String::from("slot f9 attestation certificate"), der));
+        }
+        report.add_named(&reader, Ok(items));
+    }
+
+    Ok(Piv {
+        reader,
+        firmware,
+        serial,
+        slots,
+        report,
+    })
+}
+
+/// What an attestation certificate says about how its key may be used.
+fn policies(cert: &X509Certificate) -> Vec<String> {
+    let mut policies = vec![String::from("key generated on the YubiKey")];
+    for extension in cert.extensions() {
+        match (extension.oid.to_id_string().as_str(), extension.value) {
+            (FIRMWARE, [major, minor, patch]) => {
+                policies.push(format!("firmware {}.{}.{}", major, minor, patch))
+            }
+            (POLICY, [pin, touch]) => {
+                let describe = |table: &[(u8, &str)], value: &u8| {
+                    table
+                        .iter()
+                        .find(|(policy, _)| policy == value)
+                        .map_or_else(
+                            || format!("0x{:02x}", value),
+                            |(_, name)| String::from(*name),
+                        )
+                };
+                policies.push(format!("PIN {}", describe(PIN_POLICIES, pin)));
+                policies.push(format!("touch {}", describe(TOUCH_POLICIES, touch)));
+            }
+            _ => {}
+        }
+    }
+    policies
+}
+
+impl fmt::Display for Piv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Reader: {}", self.reader)?;
+        match (&self.firmware, self.serial) {
+            (Some(firmware), Some(serial)) => {
+                writeln!(f, "YubiKey: firmware {}, serial {}", firmware, serial)?
+            }
+            (Some(firmware), None) => writeln!(f, "YubiKey: firmware {}", firmware)?,
+            _ => {}
+        }
+        writeln!(f, "Slots:")?;
+        for slot in &self.slots {
+            let subject = slot.subject.as_deref().unwrap_or("no certificate");
+            match &slot.attested {
+                Some(policies) => writeln!(
+                    f,
+                    "    {:02x} {}: {}, {}",
+                    slot.id,
+                    slot.name,
+                    subject,
+                    policies.join(", ")
+                )?,
+                None if slot.subject.is_some() => writeln!(
+                    f,
+                    "    {:02x} {}: {}, key not attested",
+                    slot.id, slot.name, subject
+                )?,
+                None => writeln!(f, "    {:02x} {}: empty", slot.id, slot.name)?,
+            }
+        }
+        write!(f, "{}", self.report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::{inspect, PivOptions};
+    use crate::cms::encode;
+    use crate::cms::hex;
+    use crate::test::FakeProcessor;
+
+    const OK: [u8; 2] = [0x90, 0x00];
+
+    fn key(command: &str) -> String {
+        hex(&command
+            .split(' ')
+            .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+            .collect::<Vec<u8>>())
+    }
+
+    /// A GET DATA response holding a certificate object.
+    fn object(der: &[u8], compressed: bool) -> Vec<u8> {
+        let (der, info) = match compressed {
+            true => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(der).unwrap();
+                (encoder.finish().unwrap(), 0x01)
+            }
+            false => (der.to_vec(), 0x00),
+        };
+        let contents = [encode(0x70, &der), encode(0x71, &[info]), encode(0xfe, &[])].concat();
+        [encode(0x53, &contents), OK.to_vec()].concat()
+    }
+
+    fn yubikey() -> HashMap<String, Vec<u8>> {
+        let attestation = include_bytes!("../resources/piv-attestation-9a.der");
+        HashMap::from([
+            (key("00 a4 04 00 05 a0 00 00 03 08"), OK.to_vec()),
+            (key("00 fd 00 00 00"), [&[5, 4, 3][..], &OK].concat()),
+            (
+                key("00 f8 00 00 00"),
+                [&[0x00, 0xbc, 0x61, 0x4e][..], &OK].concat(),
+            ),
+            (
+                key("00 cb 3f ff 05 5c 03 5f ff 01 00"),
+                object(include_bytes!("../resources/piv-f9.der"), false),
+            ),
+            (
+                key("00 cb 3f ff 05 5c 03 5f c1 05 00"),
+                object(include_bytes!("../resources/piv-9a.der"), true),
+            ),
+            (key("00 f9 9a 00 00"), [&attestation[..], &OK].concat()),
+        ])
+    }
+
+    #[test]
+    fn should_decode_the_slots_and_attestations_of_a_yubikey() {
+        let processor = FakeProcessor {
+            apdus: yubikey(),
+            ..FakeProcessor::default()
+        };
+
+        let piv = inspect(&processor, &PivOptions::default()).unwrap();
+        let output = piv.to_string();
+        assert!(output.starts_with(
+            "Reader: Yubico YubiKey OTP+FIDO+CCID 00 00\n\
+            YubiKey: firmware 5.4.3, serial 12345678\n\
+            Slots:\n    \
+            9a PIV Authentication: CN=Alice, key generated on the YubiKey, firmware 5.4.3, PIN once per session, touch cached for 15s\n    \
+            9c Digital Signature: empty\n    \
+            9d Key Management: empty\n    \
+            9e Card Authentication: empty\n"
+        ));
+        assert!(output.contains(
+            "==> Yubico YubiKey OTP+FIDO+CCID 00 00: slot 9a PIV Authentication attestation <=="
+        ));
+        assert!(output.contains(
+            "==> Yubico YubiKey OTP+FIDO+CCID 00 00: slot f9 attestation certificate <=="
+        ));
+        assert!(piv.report.warnings.is_empty());
+    }
+
+    #[test]
+    fn should_warn_when_a_slot_holds_a_certificate_for_another_key() {
+        let mut apdus = yubikey();
+        apdus.insert(
+            key("00 cb 3f ff 05 5c 03 5f c1 05 00"),
+            object(&crate::test::self_signed_der(), false),
+        );
+        apdus.remove(&key("00 cb 3f ff 05 5c 03 5f ff 01 00"));
+        let processor = FakeProcessor {
+            apdus,
+            ..FakeProcessor::default()
+        };
+
+        let piv = inspect(&processor, &PivOptions::default()).unwrap();
+        assert_eq!(
+            piv.report.warnings,
+            vec![
+                "Warning: the attestation for slot 9a is not signed by the YubiKey's attestation certificate in slot f9.",
+                "Warning: the certificate in slot 9a is for a different key than the one the YubiKey generated there.",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_refuse_cards_without_a_piv_application() {
+        let processor = FakeProcessor {
+            apdus: HashMap::from([(key("00 a4 04 00 05 a0 00 00 03 08"), vec![0x6a, 0x82])]),
+            ..FakeProcessor::default()
+        };
+        assert_eq!(
+            inspect(&processor, &PivOptions::default())
+                .err()
+                .unwrap()
+                .to_string(),
+            "Error: the card in Yubico YubiKey OTP+FIDO+CCID 00 00 has no PIV application."
+        );
+    }
+}
@@ -0,0 +1,468 @@
+use std::fmt;
+
+use crate::store::NamedCertificate;
+use crate::FileProcessor;
+
+/// Which PKCS#11 token to read, from `--pkcs11`, `--slot` and `--pin-env`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pkcs11Options {
+    /// The module that drives the token, such as opensc-pkcs11.so or libykcs11.so.
+    pub module: String,
+    /// Only this slot, rather than every slot with a token present.
+    pub slot: Option<u64>,
+    /// The environment variable holding the PIN, for tokens that keep
+    /// certificates behind a login. The PIN is never taken as an argument so
+    /// that it stays out of shell history and process listings.
+    pub pin_env: Option<String>,
+}
+
+impl fmt::Display for Pkcs11Options {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PKCS#11 module {}", self.module)?;
+        if let Some(slot) = self.slot {
+            write!(f, " slot {}", slot)?;
+        }
+        Ok(())
+    }
+}
+
+/// The PIN to log in to the token with, if one was asked for.
+pub fn pin(
+    processor: &impl FileProcessor,
+    token: &Pkcs11Options,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    match &token.pin_env {
+        Some(name) => match processor.var(name) {
+            Some(pin) => Ok(Some(pin)),
+            None => Err(format!("Error: the PIN environment variable {} is not set.", name).into()),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Reads every certificate object on the token, or on each token the module
+/// has present, naming them by slot, token label and object label.
+#[cfg(all(feature = "pkcs11", unix))]
+pub fn read(
+    token: &Pkcs11Options,
+    pin: Option<&str>,
+) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    let module = ffi::Module::load(&token.module)?;
+    let slots = match token.slot {
+        Some(slot) => vec![slot as std::os::raw::c_ulong],
+        None => module.slots()?,
+    };
+    if slots.is_empty() {
+        return Err(format!("Error: no token is present in any slot of {}.", token).into());
+    }
+
+    let mut certificates = Vec::new();
+    for slot in slots {
+        let session = module.open(slot)?;
+        if let Some(pin) = pin {
+            session.login(pin)?;
+        }
+        let token_label = module.token_label(slot)?;
+        for object in session.certificates()? {
+            let der = match session.attribute(object, ffi::CKA_VALUE)? {
+                Some(der) => der,
+                None => continue,
+            };
+            let label = match session.attribute(object, ffi::CKA_LABEL)? {
+                Some(label) if !label.is_empty() => String::from_utf8_lossy(&label).into_owned(),
+                _ => match session.attribute(object, ffi::CKA_ID)? {
+                    Some(id) if !id.is_empty() => format!("id {}", hex(&id)),
+                    _ => String::from("(no label)"),
+                },
+            };
+            certificates.push((format!("slot {} ({}): {}", slot, token_label, label), der));
+        }
+    }
+    Ok(certificates)
+}
+
+#[cfg(all(feature = "pkcs11", not(unix)))]
+pub fn read(
+    _token: &Pkcs11Options,
+    _pin: Option<&str>,
+) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    Err(String::from("Error: PKCS#11 modules can only be loaded on Unix-like systems.").into())
+}
+
+#[cfg(not(feature = "pkcs11"))]
+pub fn read(
+    token: &Pkcs11Options,
+    _pin: Option<&str>,
+) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    Err(format!(
+        "Error: {} is a PKCS#11 module, rebuild cert-decoder with --features pkcs11 to read its tokens.",
+        token.module
+    )
+    .into())
+}
+
+#[cfg(all(feature = "pkcs11", unix))]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Just enough of the PKCS#11 C API to list certificate objects, called
+/// through the function list the module hands out.
+#[cfg(all(feature = "pkcs11", unix))]
+mod ffi {
+
+    use std::ffi::{c_void, CStr, CString};
+    use std::os::raw::{c_char, c_ulong};
+
+    type Rv = c_ulong;
+
+    pub const CKA_LABEL: c_ulong = 0x03;
+    pub const CKA_VALUE: c_ulong = 0x11;
+    pub const CKA_ID: c_ulong = 0x102;
+    const CKA_CLASS: c_ulong = 0x00;
+    const CKO_CERTIFICATE: c_ulong = 0x01;
+    const CKF_SERIAL_SESSION: c_ulong = 0x04;
+    const CKU_USER: c_ulong = 0x01;
+    const CK_UNAVAILABLE_INFORMATION: c_ulong = !0;
+
+    const CKR_OK: Rv = 0x00;
+    const CKR_USER_ALREADY_LOGGED_IN: Rv = 0x100;
+    const CKR_CRYPTOKI_ALREADY_INITIALIZED: Rv = 0x191;
+
+    /// The names of the return values a user can do something about.
+    const RETURN_VALUES: &[(Rv, &str)] = &[
+        (0x03, "CKR_SLOT_ID_INVALID"),
+        (0x05, "CKR_GENERAL_ERROR"),
+        (0x30, "CKR_DEVICE_ERROR"),
+        (0x32, "CKR_DEVICE_REMOVED"),
+        (0xa0, "CKR_PIN_INCORRECT"),
+        (0xa4, "CKR_PIN_LOCKED"),
+        (0xe0, "CKR_TOKEN_NOT_PRESENT"),
+        (0xe1, "CKR_TOKEN_NOT_RECOGNIZED"),
+        (0x101, "CKR_USER_NOT_LOGGED_IN"),
+    ];
+
+    #[repr(C)]
+    struct Attribute {
+        kind: c_ulong,
+        value: *mut c_void,
+        len: c_ulong,
+    }
+
+    /// CK_TOKEN_INFO starts with the label, the rest is room for the fields after it.
+    #[repr(C)]
+    struct TokenInfo {
+        label: [u8; 32],
+        rest: [c_ulong; 64],
+    }
+
+    type Unused = *const c_void;
+
+    /// CK_FUNCTION_LIST, up to the last function used.
+    #[repr(C)]
+    struct FunctionList {
+        version: [u8; 2],
+        initialize: unsafe extern "C" fn(*mut c_void) -> Rv,
+        finalize: unsafe extern "C" fn(*mut c_void) -> Rv,
+        _info: [Unused; 2],
+        get_slot_list: unsafe extern "C" fn(u8, *mut c_ulong, *mut c_ulong) -> Rv,
+        _slot_info: Unused,
+        get_token_info: unsafe extern "C" fn(c_ulong, *mut TokenInfo) -> Rv,
+        _token_management: [Unused; 5],
+        open_session:
+            unsafe extern "C" fn(c_ulong, c_ulong, *mut c_void, Unused, *mut c_ulong) -> Rv,
+        close_session: unsafe extern "C" fn(c_ulong) -> Rv,
+        _session_management: [Unused; 4],
+        login: unsafe extern "C" fn(c_ulong, c_ulong, *const u8, c_ulong) -> Rv,
+        _object_management: [Unused; 5],
+        get_attribute_value: unsafe extern "C" fn(c_ulong, c_ulong, *mut Attribute, c_ulong) -> Rv,
+        _set_attribute_value: Unused,
+        find_objects_init: unsafe extern "C" fn(c_ulong, *const Attribute, c_ulong) -> Rv,
+        find_objects: unsafe extern "C" fn(c_ulong, *mut c_ulong, c_ulong, *mut c_ulong) -> Rv,
+        find_objects_final: unsafe extern "C" fn(c_ulong) -> Rv,
+    }
+
+    /// A loaded and initialized module, finalized and unloaded on drop.
+    pub struct Module {
+        path: String,
+        handle: *mut c_void,
+        functions: &'static FunctionList,
+        /// Whether this initialized the library, rather than something else in the process.
+        initialized: bool,
+    }
+
+    /// An open read-only session with a token, closed on drop.
+    pub struct Session<'a> {
+        module: &'a Module,
+        slot: c_ulong,
+        handle: c_ulong,
+    }
+
+    fn check(module: &str, call: &str, rv: Rv) -> Result<(), Box<dyn std::error::Error>> {
+        if rv == CKR_OK {
+            return Ok(());
+        }
+        let name = RETURN_VALUES
+            .iter()
+            .find(|(value, _)| *value == rv)
+            .map_or_else(
+                || format!("CKR 0x{:x}", rv),
+                |(_, name)| String::from(*name),
+            );
+        Err(format!(
+            "Error: {} failed in PKCS#11 module {}: {}.",
+            call, module, name
+        )
+        .into())
+    }
+
+    impl Module {
+        pub fn load(path: &str) -> Result<Module, Box<dyn std::error::Error>> {
+            let c_path = CString::new(path)
+                .map_err(|_| format!("Error: {} is not a valid module path.", path))?;
+            // SAFETY: loading a module runs its initializers, which is what asking for it means
+            let handle =
+                unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+            if handle.is_null() {
+                // SAFETY: dlerror returns a string owned by libc, valid until the next dl call
+                let reason = unsafe { CStr::from_ptr(libc::dlerror()) };
+                return Err(format!(
+                    "Error: could not load PKCS#11 module {}: {}.",
+                    path,
+                    reason.to_string_lossy()
+                )
+                .into());
+            }
+            let unload = |message: String| {
+                // SAFETY: nothing from the module is used after this
+                unsafe { libc::dlclose(handle) };
+                message
+            };
+
+            // SAFETY: C_GetFunctionList is looked up by its standard name and signature
+            let get_function_list =
+                unsafe { libc::dlsym(handle, b"C_GetFunctionList\0".as_ptr() as *const c_char) };
+            if get_function_list.is_null() {
+                return Err(unload(format!(
+                    "Error: {} is not a PKCS#11 module, it has no C_GetFunctionList.",
+                    path
+                ))
+                .into());
+            }
+            let get_function_list: unsafe extern "C" fn(*mut *const FunctionList) -> Rv =
+                unsafe { std::mem::transmute(get_function_list) };
+            let mut functions: *const FunctionList = std::ptr::null();
+            // SAFETY: the module fills in a pointer to its static function list
+            let rv = unsafe { get_function_list(&mut functions) };
+            if rv != CKR_OK || functions.is_null() {
+                let error = check(path, "C_GetFunctionList", rv)
+                    .err()
+                    .map_or_else(|| String::from("no function list"), |e| e.to_string());
+                return Err(unload(error).into());
+            }
+            // SAFETY: the function list lives as long as the module stays loaded
+            let functions: &'static FunctionList = unsafe { &*functions };
+
+            // SAFETY: no arguments means the module may not create threads of its own
+            let rv = unsafe { (functions.initialize)(std::ptr::null_mut()) };
+            if rv != CKR_OK && rv != CKR_CRYPTOKI_ALREADY_INITIALIZED {
+                let error = check(path, "C_Initialize", rv).unwrap_err().to_string();
+                return Err(unload(error).into());
+            }
+            Ok(Module {
+                path: String::from(path),
+                handle,
+                functions,
+                initialized: rv == CKR_OK,
+            })
+        }
+
+        /// The slots that have a token present.
+        pub fn slots(&self) -> Result<Vec<c_ulong>, Box<dyn std::error::Error>> {
+            let mut count: c_ulong = 0;
+            // SAFETY: a null list asks only for the count
+            let rv = unsafe { (self.functions.get_slot_list)(1, std::ptr::null_mut(), &mut count) };
+            check(&self.path, "C_GetSlotList", rv)?;
+            let mut slots: Vec<c_ulong> = vec![0; count as usize];
+            // SAFETY: the list has room for the count just given
+            let rv = unsafe { (self.functions.get_slot_list)(1, slots.as_mut_ptr(), &mut count) };
+            check(&self.path, "C_GetSlotList", rv)?;
+            slots.truncate(count as usize);
+            Ok(slots)
+        }
+
+        /// The label of the token in a slot, which modules pad with spaces.
+        pub fn token_label(&self, slot: c_ulong) -> Result<String, Box<dyn std::error::Error>> {
+            let mut info = TokenInfo {
+                label: [b' '; 32],
+                rest: [0; 64],
+            };
+            // SAFETY: info is larger than any CK_TOKEN_INFO
+            let rv = unsafe { (self.functions.get_token_info)(slot, &mut info) };
+            check(&self.path, "C_GetTokenInfo", rv)?;
+            Ok(String::from_utf8_lossy(&info.label).trim_end().to_string())
+        }
+
+        pub fn open(&self, slot: c_ulong) -> Result<Session<'_>, Box<dyn std::error::Error>> {
+            let mut handle: c_ulong = 0;
+            // SAFETY: no application pointer or notify callback is passed
+            let rv = unsafe {
+                (self.functions.open_session)(
+                    slot,
+                    CKF_SERIAL_SESSION,
+                    std::ptr::null_mut(),
+                    std::ptr::null(),
+                    &mut handle,
+                )
+            };
+            check(&self.path, "C_OpenSession", rv)?;
+            Ok(Session {
+                module: self,
+                slot,
+                handle,
+            })
+        }
+    }
+
+    impl Drop for Module {
+        fn drop(&mut self) {
+            // SAFETY: every session borrows the module, so all are closed by now
+            unsafe {
+                if self.initialized {
+                    (self.functions.finalize)(std::ptr::null_mut());
+                }
+                libc::dlclose(self.handle);
+            }
+        }
+    }
+
+    impl Session<'_> {
+        pub fn login(&self, pin: &str) -> Result<(), Box<dyn std::error::Error>> {
+            let functions = self.module.functions;
+            // SAFETY: the PIN is passed with its length, it need not be terminated
+            let rv = unsafe {
+                (functions.login)(self.handle, CKU_USER, pin.as_ptr(), pin.len() as c_ulong)
+            };
+            if rv == CKR_USER_ALREADY_LOGGED_IN {
+                return Ok(());
+            }
+            let call = format!("C_Login to slot {}", self.slot);
+            check(&self.module.path, &call, rv)
+        }
+
+        /// The handles of every certificate object the session can see.
+        pub fn certificates(&self) -> Result<Vec<c_ulong>, Box<dyn std::error::Error>> {
+            let functions = self.module.functions;
+            let mut class = CKO_CERTIFICATE;
+            let template = Attribute {
+                kind: CKA_CLASS,
+                value: &mut class as *mut c_ulong as *mut c_void,
+                len: std::mem::size_of::<c_ulong>() as c_ulong,
+            };
+            // SAFETY: the template outlives the search
+            let rv = unsafe { (functions.find_objects_init)(self.handle, &template, 1) };
+            check(&self.module.path, "C_FindObjectsInit", rv)?;
+
+            let mut objects = Vec::new();
+            let mut batch: [c_ulong; 32] = [0; 32];
+            let result = loop {
+                let mut found: c_ulong = 0;
+                // SAFETY: the batch has room for the count asked for
+                let rv = unsafe {
+                    (functions.find_objects)(
+                        self.handle,
+                        batch.as_mut_ptr(),
+                        batch.len() as c_ulong,
+                        &mut found,
+                    )
+                };
+                if let Err(error) = check(&self.module.path, "C_FindObjects", rv) {
+                    break Err(error);
+                }
+                if found == 0 {
+                    break Ok(());
+                }
+                objects.extend_from_slice(&batch[..found as usize]);
+            };
+            // SAFETY: a search was started above
+            unsafe { (functions.find_objects_final)(self.handle) };
+            result.map(|_| objects)
+        }
+
+        /// An attribute of an object, or None when it is missing or sensitive.
+        pub fn attribute(
+            &self,
+            object: c_ulong,
+            kind: c_ulong,
+        ) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+            let functions = self.module.functions;
+            let mut attribute = Attribute {
+                kind,
+                value: std::ptr::null_mut(),
+                len: 0,
+            };
+            // SAFETY: a null value asks only for the length
+            let rv =
+                unsafe { (functions.get_attribute_value)(self.handle, object, &mut attribute, 1) };
+            if rv != CKR_OK || attribute.len == CK_UNAVAILABLE_INFORMATION {
+                return Ok(None);
+            }
+            let mut value = vec![0u8; attribute.len as usize];
+            attribute.value = value.as_mut_ptr() as *mut c_void;
+            // SAFETY: the value has room for the length just given
+            let rv =
+                unsafe { (functions.get_attribute_value)(self.handle, object, &mut attribute, 1) };
+            check(&self.module.path, "C_GetAttributeValue", rv)?;
+            value.truncate(attribute.len as usize);
+            Ok(Some(value))
+        }
+    }
+
+    impl Drop for Session<'_> {
+        fn drop(&mut self) {
+            // SAFETY: the session is open and no longer used
+            unsafe { (self.module.functions.close_session)(self.handle) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{pin, Pkcs11Options};
+    use crate::test::FakeProcessor;
+
+    #[test]
+    fn should_name_the_module_and_slot() {
+        let mut token = Pkcs11Options {
+            module: String::from("/usr/lib/libykcs11.so"),
+            ..Pkcs11Options::default()
+        };
+        assert_eq!(token.to_string(), "PKCS#11 module /usr/lib/libykcs11.so");
+        token.slot = Some(1);
+        assert_eq!(
+            token.to_string(),
+            "PKCS#11 module /usr/lib/libykcs11.so slot 1"
+        );
+    }
+
+    #[test]
+    fn should_take_the_pin_from_the_environment() {
+        let mut processor = FakeProcessor::default();
+        let mut token = Pkcs11Options {
+            module: String::from("opensc-pkcs11.so"),
+            ..Pkcs11Options::default()
+        };
+        assert_eq!(pin(&processor, &token).unwrap(), None);
+
+        token.pin_env = Some(String::from("TOKEN_PIN"));
+        assert_eq!(
+            pin(&processor, &token).err().unwrap().to_string(),
+            "Error: the PIN environment variable TOKEN_PIN is not set."
+        );
+        processor
+            .vars
+            .insert(String::from("TOKEN_PIN"), String::from("123456"));
+        assert_eq!(pin(&processor, &token).unwrap().as_deref(), Some("123456"));
+    }
+}
@@ -0,0 +1,332 @@
+use std::fmt;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::ParsedExtension;
+
+/// The policy a CA asserts to leave the choice of policies to the certificates below it.
+const ANY_POLICY: &str = "2.5.29.32.0";
+
+/// Whether a policy required with `--require-policy` is valid for the leaf of a path.
+#[derive(Debug, PartialEq)]
+pub struct PolicyCheck {
+    pub policy: String,
+    /// Why the policy is not valid, if it isn't.
+    pub problem: Option<String>,
+}
+
+/// A branch of RFC 5280's valid_policy_tree, as the policy of the anchor's domain
+/// it stands for, anyPolicy until a certificate asserts one, and the policies the
+/// next certificate has to assert to carry it on, which policy mappings change.
+#[derive(Clone, PartialEq)]
+struct Branch {
+    policy: String,
+    expected: Vec<String>,
+}
+
+/// Runs the policy processing of RFC 5280 section 6.1 over a path, given root
+/// first with the trust anchor, which isn't processed. A required policy is valid
+/// if a branch of the tree carries it to the leaf. Requiring a policy is what
+/// requireExplicitPolicy asks for, so that constraint adds nothing here.
+pub fn check(path: &[X509Certificate], required: &[String]) -> Vec<PolicyCheck> {
+    let certificates = path.get(1..).unwrap_or_default();
+    let n = certificates.len();
+    let mut branches = vec![Branch {
+        policy: String::from(ANY_POLICY),
+        expected: vec![String::from(ANY_POLICY)],
+    }];
+    let mut inhibit_any = n + 1;
+    let mut inhibit_mapping = n + 1;
+    let mut problems: Vec<Option<String>> = vec![None; required.len()];
+
+    for (i, cert) in certificates.iter().enumerate() {
+        let leaf = i + 1 == n;
+        let self_issued = cert.subject() == cert.issuer();
+        let asserted = certificate_policies(cert);
+        let any_allowed = inhibit_any > 0 || (!leaf && self_issued);
+        let before = branches;
+        let processed = match &asserted {
+            Some(asserted) => process(&before, asserted, any_allowed),
+            None => Vec::new(),
+        };
+        branches = match leaf {
+            true => processed.clone(),
+            false => map(cert, &processed, inhibit_mapping > 0),
+        };
+
+        for (policy, problem) in required.iter().zip(problems.iter_mut()) {
+            if problem.is_some() || carries(&branches, policy) {
+                continue;
+            }
+            *problem = Some(match &asserted {
+                None => format!("{} has no certificate policies", cert.subject()),
+                Some(_) if carries(&processed, policy) => format!(
+                    "{} maps it, which policy mapping inhibited above it does not allow",
+                    cert.subject()
+                ),
+                Some(asserted) if asserted.iter().any(|p| p == ANY_POLICY) && !any_allowed => {
+                    format!(
+                        "{} only asserts it through anyPolicy, which inhibitAnyPolicy above it does not allow",
+                        cert.subject()
+                    )
+                }
+                Some(asserted) => {
+                    let mapped = before.iter().any(|branch| {
+                        (branch.policy == *policy || branch.policy == ANY_POLICY)
+                            && branch
+                                .expected
+                                .iter()
+                                .any(|expected| expected != policy && expected != ANY_POLICY)
+                    });
+                    let asserted: Vec<&str> = asserted.iter().map(|p| name(p)).collect();
+                    format!(
+                        "{} does not assert it{}, only {}",
+                        cert.subject(),
+                        if mapped {
+                            " or what it is mapped to"
+                        } else {
+                            ""
+                        },
+                        asserted.join(", ")
+                    )
+                }
+            });
+        }
+
+        if leaf {
+            continue;
+        }
+        if !self_issued {
+            inhibit_mapping = inhibit_mapping.saturating_sub(1);
+            inhibit_any = inhibit_any.saturating_sub(1);
+        }
+        if let Ok(Some(constraints)) = cert.policy_constraints() {
+            if let Some(skip) = constraints.value.inhibit_policy_mapping {
+                inhibit_mapping = inhibit_mapping.min(skip as usize);
+            }
+        }
+        if let Ok(Some(inhibit)) = cert.inhibit_anypolicy() {
+            inhibit_any = inhibit_any.min(inhibit.value.skip_certs as usize);
+        }
+    }
+
+    required
+        .iter()
+        .zip(problems)
+        .map(|(policy, problem)| PolicyCheck {
+            policy: policy.clone(),
+            problem,
+        })
+        .collect()
+}
+
+/// Grows the tree by the policies a certificate asserts, following 6.1.3 (d).
+fn process(branches: &[Branch], asserted: &[String], any_allowed: bool) -> Vec<Branch> {
+    let mut next = Vec::new();
+    let mut grow = |parent: &Branch, policy: &str| {
+        let child = Branch {
+            policy: match parent.policy == ANY_POLICY {
+                true => String::from(policy),
+                false => parent.policy.clone(),
+            },
+            expected: vec![String::from(policy)],
+        };
+        if !next.contains(&child) {
+            next.push(child);
+        }
+    };
+    for policy in asserted.iter().filter(|policy| *policy != ANY_POLICY) {
+        let expecting: Vec<&Branch> = branches
+            .iter()
+            .filter(|branch| branch.expected.contains(policy))
+            .collect();
+        match expecting.is_empty() {
+            false => expecting.iter().for_each(|branch| grow(branch, policy)),
+            true => branches
+                .iter()
+                .filter(|branch| branch.expected == [ANY_POLICY])
+                .for_each(|branch| grow(branch, policy)),
+        }
+    }
+    // anyPolicy carries on whatever was expected that the certificate didn't assert
+    if any_allowed && asserted.iter().any(|policy| policy == ANY_POLICY) {
+        for branch in branches {
+            for expected in &branch.expected {
+                if !asserted.contains(expected) || expected == ANY_POLICY {
+                    grow(branch, expected);
+                }
+            }
+        }
+    }
+    next
+}
+
+/// Applies a CA's policy mappings to what the certificates below it have to
+/// assert, following 6.1.4 (b), or prunes the mapped policies if mapping is inhibited.
+fn map(cert: &X509Certificate, branches: &[Branch], allowed: bool) -> Vec<Branch> {
+    let mappings: Vec<(String, String)> = match cert.policy_mappings() {
+        Ok(Some(mappings)) => mappings
+            .value
+            .mappings
+            .iter()
+            .map(|mapping| {
+                (
+                    mapping.issuer_domain_policy.to_id_string(),
+                    mapping.subject_domain_policy.to_id_string(),
+                )
+            })
+            .filter(|(issuer, subject)| issuer != ANY_POLICY && subject != ANY_POLICY)
+            .collect(),
+        _ => return branches.to_vec(),
+    };
+    let subjects = |issuer: &str| -> Vec<String> {
+        mappings
+            .iter()
+            .filter(|(from, _)| from == issuer)
+            .map(|(_, to)| to.clone())
+            .collect()
+    };
+
+    let mut mapped = Vec::new();
+    for branch in branches {
+        let valid = &branch.expected[0];
+        let to = subjects(valid);
+        match (to.is_empty(), allowed) {
+            (false, true) => mapped.push(Branch {
+                policy: branch.policy.clone(),
+                expected: to,
+            }),
+            (false, false) => {}
+            (true, _) => mapped.push(branch.clone()),
+        }
+        // A policy the CA took through anyPolicy can be mapped as well
+        if valid == ANY_POLICY && allowed {
+            for (issuer, _) in &mappings {
+                let asserted = branches.iter().any(|other| other.expected[0] == *issuer);
+                let branch = Branch {
+                    policy: issuer.clone(),
+                    expected: subjects(issuer),
+                };
+                if !asserted && !mapped.contains(&branch) {
+                    mapped.push(branch);
+                }
+            }
+        }
+    }
+    mapped
+}
+
+/// Whether a branch carries a policy, or anyPolicy that stands for it.
+fn carries(branches: &[Branch], policy: &str) -> bool {
+    branches
+        .iter()
+        .any(|branch| branch.policy == policy || branch.policy == ANY_POLICY)
+}
+
+/// The policies in a certificate's certificate policies extension, if it has one.
+fn certificate_policies(cert: &X509Certificate) -> Option<Vec<String>> {
+    cert.extensions()
+        .iter()
+        .find_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::CertificatePolicies(policies) => Some(
+                policies
+                    .iter()
+                    .map(|policy| policy.policy_id.to_id_string())
+                    .collect(),
+            ),
+            _ => None,
+        })
+}
+
+fn name(policy: &str) -> &str {
+    match policy {
+        ANY_POLICY => "anyPolicy",
+        policy => policy,
+    }
+}
+
+impl fmt::Display for PolicyCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.problem {
+            None => write!(f, "Required policy {}: valid", self.policy),
+            Some(problem) => write!(f, "Required policy {}: not valid, {}", self.policy, problem),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::Pem;
+
+    use super::{check, PolicyCheck};
+
+    const DV: &str = "2.23.140.1.2.1";
+    const EXAMPLE_EV: &str = "1.3.6.1.4.1.32473.1";
+
+    fn path(chain: &[u8]) -> Vec<Vec<u8>> {
+        let mut path: Vec<Vec<u8>> = Pem::iter_from_buffer(chain)
+            .chain(Pem::iter_from_buffer(include_bytes!(
+                "../resources/example-root.crt"
+            )))
+            .map(|pem| pem.unwrap().contents)
+            .collect();
+        path.reverse();
+        path
+    }
+
+    #[test]
+    fn should_find_the_policies_valid_for_the_leaf() {
+        let chain = path(include_bytes!("../resources/policy-chain.pem"));
+        let certificates: Vec<_> = chain
+            .iter()
+            .map(|der| parse_x509_certificate(der).unwrap().1)
+            .collect();
+        let required = vec![
+            String::from(DV),
+            String::from(EXAMPLE_EV),
+            String::from("2.23.140.1.1"),
+        ];
+
+        assert_eq!(
+            check(&certificates, &required),
+            vec![
+                PolicyCheck {
+                    policy: String::from(DV),
+                    problem: None,
+                },
+                PolicyCheck {
+                    policy: String::from(EXAMPLE_EV),
+                    problem: Some(String::from(
+                        "CN=policy.example.com does not assert it, only 2.23.140.1.2.1"
+                    )),
+                },
+                PolicyCheck {
+                    policy: String::from("2.23.140.1.1"),
+                    problem: Some(String::from(
+                        "CN=Example Policy CA does not assert it, only 2.23.140.1.2.1, 1.3.6.1.4.1.32473.1"
+                    )),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_need_every_certificate_to_assert_policies() {
+        let chain = path(include_bytes!("../resources/example-chain.pem"));
+        let certificates: Vec<_> = chain
+            .iter()
+            .map(|der| parse_x509_certificate(der).unwrap().1)
+            .collect();
+
+        assert_eq!(
+            check(&certificates, &[String::from(DV)])[0]
+                .problem
+                .as_deref(),
+            Some("CN=Example Issuing CA has no certificate policies")
+        );
+        assert!(check(&certificates[..1], &[String::from(DV)])[0]
+            .problem
+            .is_none());
+    }
+}
@@ -0,0 +1,519 @@
+use std::fmt;
+use std::time::Duration;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+
+use crate::dn;
+use crate::lifetime;
+use crate::options::parse_duration;
+use crate::report::Report;
+use crate::trust_store::{key_info, KeyKind};
+use crate::usage;
+
+/// The rules of a policy file, given to the `check` command with `--policy`. Each
+/// is only checked when the file sets it. The file is TOML with top-level keys only:
+///
+/// ```toml
+/// key_types = ["RSA", "EC P-256", "EC P-384"]
+/// min_rsa_bits = 3072
+/// required_ekus = ["serverAuth"]
+/// max_validity = "90d"
+/// banned_issuers = ["O=Old Corp CA"]
+/// san_patterns = ["*.corp.example.com"]
+/// ```
+#[derive(Debug, Default, PartialEq)]
+pub struct PolicyFile {
+    /// The key types allowed, as `key_type` names them, where `RSA` allows any size.
+    pub key_types: Vec<String>,
+    pub min_rsa_bits: Option<usize>,
+    /// The extended key usages every certificate that isn't a CA must have.
+    pub required_ekus: Vec<String>,
+    /// The longest a certificate that isn't a CA may be valid for.
+    pub max_validity: Option<Duration>,
+    /// Issuers whose distinguished name contains any of these are not allowed.
+    pub banned_issuers: Vec<String>,
+    /// Every DNS name of a certificate that isn't a CA has to match one of these,
+    /// where `*` stands for any part of a label.
+    pub san_patterns: Vec<String>,
+}
+
+/// A value in a policy file.
+enum Value {
+    Text(String),
+    Number(i64),
+    List(Vec<String>),
+}
+
+impl PolicyFile {
+    /// Reads a policy file, naming the file and line of anything it doesn't understand.
+    pub fn parse(path: &str, text: &str) -> Result<PolicyFile, Box<dyn std::error::Error>> {
+        let mut policy = PolicyFile::default();
+        for (number, key, value) in entries(text)
+            .map_err(|(number, problem)| format!("Error: {} line {}: {}.", path, number, problem))?
+        {
+            let error = |problem: &str| format!("Error: {} line {}: {}.", path, number, problem);
+            match (key.as_str(), value) {
+                ("key_types", Value::List(list)) => policy.key_types = list,
+                ("min_rsa_bits", Value::Number(bits)) if bits > 0 => {
+                    policy.min_rsa_bits = Some(bits as usize)
+                }
+                ("required_ekus", Value::List(list)) => policy.required_ekus = list,
+                ("max_validity", Value::Text(text)) => {
+                    let duration = parse_duration(&text)
+                        .ok_or_else(|| error("max_validity expects a duration such as 90d"))?;
+                    policy.max_validity = Some(duration);
+                }
+                ("banned_issuers", Value::List(list)) => policy.banned_issuers = list,
+                ("san_patterns", Value::List(list)) => policy.san_patterns = list,
+                ("key_types" | "required_ekus" | "banned_issuers" | "san_patterns", _) => {
+                    return Err(error(&format!("{} expects a list of strings", key)).into())
+                }
+                ("min_rsa_bits", _) => {
+                    return Err(error("min_rsa_bits expects a positive number").into())
+                }
+                ("max_validity", _) => {
+                    return Err(error("max_validity expects a duration such as 90d").into())
+                }
+                _ => return Err(error(&format!("{} is not a rule", key)).into()),
+            }
+        }
+        Ok(policy)
+    }
+}
+
+/// A key and its value, after the number of the line it is on.
+type Entry = (usize, String, Value);
+
+/// The `key = value` pairs of TOML with their line numbers, for the strings, whole
+/// numbers and lists of strings a policy needs. A list may span several lines.
+fn entries(text: &str) -> Result<Vec<Entry>, (usize, String)> {
+    let mut entries = Vec::new();
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+    while let Some((number, line)) = lines.next() {
+        let mut line = String::from(strip_comment(line).trim());
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err((number, String::from("tables are not supported")));
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| (number, String::from("expected key = value")))?;
+        let key = String::from(key.trim());
+        let list = value.trim_start().starts_with('[');
+        while list && !line.ends_with(']') {
+            let (_, more) = lines
+                .next()
+                .ok_or_else(|| (number, String::from("the list is not closed")))?;
+            line.push(' ');
+            line.push_str(strip_comment(more).trim());
+        }
+        let (_, value) = line.split_once('=').unwrap_or_default();
+        let value = parse_value(value.trim()).ok_or_else(|| {
+            (
+                number,
+                format!("{} expects a string, a number or a list of strings", key),
+            )
+        })?;
+        entries.push((number, key, value));
+    }
+    Ok(entries)
+}
+
+fn parse_value(value: &str) -> Option<Value> {
+    if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        let mut list = Vec::new();
+        let mut rest = items.trim();
+        while !rest.is_empty() {
+            let (item, after) = parse_string(rest)?;
+            list.push(item);
+            rest = after.trim_start();
+            rest = match rest.strip_prefix(',') {
+                Some(after) => after.trim_start(),
+                None if rest.is_empty() => rest,
+                None => return None,
+            };
+        }
+        return Some(Value::List(list));
+    }
+    if let Ok(number) = value.replace('_', "").parse() {
+        return Some(Value::Number(number));
+    }
+    match parse_string(value)? {
+        (text, "") => Some(Value::Text(text)),
+        _ => None,
+    }
+}
+
+/// A basic or literal string at the start of `value`, and what follows it.
+fn parse_string(value: &str) -> Option<(String, &str)> {
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let mut text = String::new();
+    let mut chars = value.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Some((text, &value[i + 1..])),
+            '\\' if quote == '"' => match chars.next()?.1 {
+                'n' => text.push('\n'),
+                't' => text.push('\t'),
+                escaped => text.push(escaped),
+            },
+            c => text.push(c),
+        }
+    }
+    None
+}
+
+/// A line without its comment, leaving a `#` inside a string alone.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('#', None) => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// How a certificate fared against one rule.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Pass(String),
+    Fail(String),
+    /// The rule doesn't apply to the certificate, such as a SAN pattern to a CA.
+    Skip(String),
+}
+
+/// A rule of a policy file checked against a certificate.
+#[derive(Debug, PartialEq)]
+pub struct RuleCheck {
+    /// The key of the rule in the policy file.
+    pub rule: &'static str,
+    pub outcome: Outcome,
+}
+
+impl fmt::Display for RuleCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.outcome {
+            Outcome::Pass(detail) => write!(f, "PASS {}: {}", self.rule, detail),
+            Outcome::Fail(detail) => write!(f, "FAIL {}: {}", self.rule, detail),
+            Outcome::Skip(detail) => write!(f, "SKIP {}: {}", self.rule, detail),
+        }
+    }
+}
+
+/// Checks a certificate against every rule the policy sets.
+pub fn check(cert: &X509Certificate, policy: &PolicyFile) -> Vec<RuleCheck> {
+    let ca = matches!(cert.basic_constraints(), Ok(Some(basic)) if basic.value.ca);
+    let mut checks = Vec::new();
+    let mut push = |rule, outcome| checks.push(RuleCheck { rule, outcome });
+    let info = key_info(cert.public_key());
+    let key = info.to_string();
+
+    if !policy.key_types.is_empty() {
+        // The kind alone, such as `RSA`, allows a key of any size
+        let kind = info.kind.to_string();
+        let allowed = policy
+            .key_types
+            .iter()
+            .any(|allowed| *allowed == key || *allowed == kind);
+        push(
+            "key_types",
+            match allowed {
+                true => Outcome::Pass(key.clone()),
+                false => Outcome::Fail(format!("{} is not allowed", key)),
+            },
+        );
+    }
+    if let Some(min) = policy.min_rsa_bits {
+        let bits = info.bits.filter(|_| info.kind == KeyKind::Rsa);
+        push(
+            "min_rsa_bits",
+            match bits {
+                Some(bits) if bits >= min => Outcome::Pass(key.clone()),
+                Some(bits) => Outcome::Fail(format!("{} bits, fewer than {}", bits, min)),
+                None => Outcome::Skip(format!("{} is not an RSA key", key)),
+            },
+        );
+    }
+    if !policy.required_ekus.is_empty() {
+        let present = match cert.extended_key_usage() {
+            Ok(Some(extended)) => usage::names(extended.value),
+            _ => Vec::new(),
+        };
+        let missing: Vec<&str> = policy
+            .required_ekus
+            .iter()
+            .filter(|required| !present.contains(required))
+            .map(String::as_str)
+            .collect();
+        push(
+            "required_ekus",
+            match (ca, missing.as_slice()) {
+                (true, _) => Outcome::Skip(String::from("not checked for a CA")),
+                (false, []) => Outcome::Pass(present.join(", ")),
+                (false, missing) => Outcome::Fail(format!("it lacks {}", missing.join(", "))),
+            },
+        );
+    }
+    if let Some(max) = policy.max_validity {
+        let validity = cert.validity();
+        // The period includes both its first and last second
+        let period = validity.not_after.timestamp() - validity.not_before.timestamp() + 1;
+        let max = max.as_secs() as i64;
+        push(
+            "max_validity",
+            match (ca, period > max) {
+                (true, _) => Outcome::Skip(String::from("not checked for a CA")),
+                (false, false) => Outcome::Pass(lifetime::describe(period)),
+                (false, true) => Outcome::Fail(format!(
+                    "{}, more than {}",
+                    lifetime::describe(period),
+                    lifetime::describe(max)
+                )),
+            },
+        );
+    }
+    if !policy.banned_issuers.is_empty() {
+        let issuer = dn::to_string(cert.issuer());
+        let banned = policy
+            .banned_issuers
+            .iter()
+            .find(|banned| issuer.to_lowercase().contains(&banned.to_lowercase()));
+        push(
+            "banned_issuers",
+            match banned {
+                Some(banned) => Outcome::Fail(format!("{} is banned by {}", issuer, banned)),
+                None => Outcome::Pass(issuer),
+            },
+        );
+    }
+    if !policy.san_patterns.is_empty() {
+        let names: Vec<String> = match cert.subject_alternative_name() {
+            Ok(Some(san)) => san
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name) => Some(name.to_lowercase()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let stray: Vec<&str> = names
+            .iter()
+            .filter(|name| {
+                !policy
+                    .san_patterns
+                    .iter()
+                    .any(|pattern| matches(&pattern.to_lowercase(), name))
+            })
+            .map(String::as_str)
+            .collect();
+        push(
+            "san_patterns",
+            match (ca, names.is_empty(), stray.as_slice()) {
+                (true, _, _) => Outcome::Skip(String::from("not checked for a CA")),
+                (false, true, _) => Outcome::Fail(String::from("it has no DNS names")),
+                (false, false, []) => Outcome::Pass(names.join(", ")),
+                (false, false, stray) => {
+                    Outcome::Fail(format!("{} match none of the patterns", stray.join(", ")))
+                }
+            },
+        );
+    }
+    checks
+}
+
+/// Whether a name matches a pattern, where `*` matches any run of characters
+/// within a label.
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((before, after)) => {
+            let rest = match name.strip_prefix(before) {
+                Some(rest) => rest,
+                None => return false,
+            };
+            // Try every length of the label the star stands for
+            let label = rest.find('.').unwrap_or(rest.len());
+            (0..=label).any(|i| matches(after, &rest[i..]))
+        }
+    }
+}
+
+/// Every certificate of a report checked against a policy file, printed by the
+/// `check` command instead of the decoded certificates.
+pub struct Checks<'a> {
+    report: &'a Report,
+    path: &'a str,
+    results: Vec<Vec<RuleCheck>>,
+}
+
+impl<'a> Checks<'a> {
+    pub fn new(report: &'a Report, path: &'a str, policy: &PolicyFile) -> Checks<'a> {
+        let results = report
+            .entries
+            .iter()
+            .map(|entry| check(&entry.certificate(), policy))
+            .collect();
+        Checks {
+            report,
+            path,
+            results,
+        }
+    }
+
+    fn failed(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|checks| {
+                checks
+                    .iter()
+                    .any(|check| matches!(check.outcome, Outcome::Fail(_)))
+            })
+            .count()
+    }
+
+    /// An error when any certificate breaks a rule, so scripts can tell.
+    pub fn status(&self) -> Result<(), Box<dyn std::error::Error>> {
+        match self.failed() {
+            0 => Ok(()),
+            failed => Err(format!(
+                "Error: {} certificate(s) do not meet the policy in {}.",
+                failed, self.path
+            )
+            .into()),
+        }
+    }
+}
+
+impl fmt::Display for Checks<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (entry, checks) in self.report.entries.iter().zip(&self.results) {
+            writeln!(f, "==> {} <==", entry.location)?;
+            for check in checks {
+                writeln!(f, "{}", check)?;
+            }
+        }
+        for failure in &self.report.failures {
+            writeln!(f, "==> {} <==", failure.location)?;
+            writeln!(f, "{}", failure.error)?;
+        }
+        let total = self.results.len();
+        writeln!(
+            f,
+            "Checked {} certificate(s) against {}: {} passed, {} failed.",
+            total,
+            self.path,
+            total - self.failed(),
+            self.failed()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::time::Duration;
+
+    use x509_parser::parse_x509_certificate;
+
+    use super::{check, matches, PolicyFile};
+    use crate::test::google_der;
+
+    #[test]
+    fn should_read_the_rules_of_a_policy_file() {
+        let text = "# Corporate TLS policy\n\
+                    key_types = [\"RSA\", 'EC P-256'] # no P-384 yet\n\
+                    min_rsa_bits = 3_072\n\
+                    required_ekus = [\n  \"serverAuth\",\n  \"clientAuth\",\n]\n\
+                    max_validity = \"90d\"\n\
+                    banned_issuers = [\"O=Old # Corp\"]\n\
+                    san_patterns = []\n";
+
+        let policy = PolicyFile::parse("corp.toml", text).unwrap();
+
+        assert_eq!(
+            policy,
+            PolicyFile {
+                key_types: vec![String::from("RSA"), String::from("EC P-256")],
+                min_rsa_bits: Some(3072),
+                required_ekus: vec![String::from("serverAuth"), String::from("clientAuth")],
+                max_validity: Some(Duration::from_secs(90 * 24 * 60 * 60)),
+                banned_issuers: vec![String::from("O=Old # Corp")],
+                san_patterns: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn should_name_the_line_it_does_not_understand() {
+        let error = |text| {
+            PolicyFile::parse("corp.toml", text)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error("max_validity = \"90d\"\nmax_key_age = 1\n"),
+            "Error: corp.toml line 2: max_key_age is not a rule."
+        );
+        assert_eq!(
+            error("min_rsa_bits = \"2048\""),
+            "Error: corp.toml line 1: min_rsa_bits expects a positive number."
+        );
+        assert_eq!(
+            error("[rules]"),
+            "Error: corp.toml line 1: tables are not supported."
+        );
+        assert_eq!(
+            error("san_patterns = [\"*.example.com\""),
+            "Error: corp.toml line 1: the list is not closed."
+        );
+    }
+
+    #[test]
+    fn should_check_each_rule_the_policy_sets() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+        let policy = PolicyFile {
+            key_types: vec![String::from("RSA")],
+            min_rsa_bits: Some(2048),
+            required_ekus: vec![String::from("serverAuth")],
+            max_validity: Some(Duration::from_secs(90 * 24 * 60 * 60)),
+            banned_issuers: vec![String::from("o=google trust services")],
+            san_patterns: vec![String::from("*.google.com"), String::from("google.com")],
+        };
+
+        let summary: Vec<String> = check(&cert, &policy)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(summary.len(), 6);
+        assert_eq!(summary[0], "FAIL key_types: EC P-256 is not allowed");
+        assert_eq!(summary[1], "SKIP min_rsa_bits: EC P-256 is not an RSA key");
+        assert_eq!(summary[2], "PASS required_ekus: serverAuth");
+        assert_eq!(summary[3], "PASS max_validity: 84 days and 1 second");
+        assert_eq!(
+            summary[4],
+            "FAIL banned_issuers: C=US, O=Google Trust Services, CN=GTS CA 1O1 is banned by o=google trust services"
+        );
+        assert!(summary[5].starts_with("FAIL san_patterns: "));
+    }
+
+    #[test]
+    fn should_match_a_star_within_a_label() {
+        assert!(matches("*.example.com", "www.example.com"));
+        assert!(matches("web-*.example.com", "web-01.example.com"));
+        assert!(!matches("*.example.com", "a.b.example.com"));
+        assert!(!matches("*.example.com", "example.com"));
+    }
+}
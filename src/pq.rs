@@ -0,0 +1,272 @@
+use std::borrow::Cow;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::oid::Oid;
+
+use crate::extensions::{elements, Field};
+use crate::oid;
+
+/// The extensions of X.509 (2019) that give a certificate a second key and
+/// signature, which hybrid certificates carry a post-quantum pair in.
+pub const ALT_PUBLIC_KEY: &str = "2.5.29.72";
+pub const ALT_SIGNATURE_ALGORITHM: &str = "2.5.29.73";
+pub const ALT_SIGNATURE_VALUE: &str = "2.5.29.74";
+
+/// Where an algorithm is defined, as its OID may change until it is standardised.
+#[derive(Clone, Copy)]
+enum Status {
+    Standard(&'static str),
+    Experimental(&'static str),
+}
+
+/// Post-quantum algorithms: the NIST standards, the IETF composite drafts,
+/// and the round 3 Dilithium OIDs Open Quantum Safe issued before FIPS 204.
+const ALGORITHMS: &[(&str, &str, Status)] = &[
+    ("2.16.840.1.101.3.4.3.17", "ML-DSA-44", FIPS_204),
+    ("2.16.840.1.101.3.4.3.18", "ML-DSA-65", FIPS_204),
+    ("2.16.840.1.101.3.4.3.19", "ML-DSA-87", FIPS_204),
+    ("2.16.840.1.101.3.4.3.20", "SLH-DSA-SHA2-128s", FIPS_205),
+    ("2.16.840.1.101.3.4.3.21", "SLH-DSA-SHA2-128f", FIPS_205),
+    ("2.16.840.1.101.3.4.3.22", "SLH-DSA-SHA2-192s", FIPS_205),
+    ("2.16.840.1.101.3.4.3.23", "SLH-DSA-SHA2-192f", FIPS_205),
+    ("2.16.840.1.101.3.4.3.24", "SLH-DSA-SHA2-256s", FIPS_205),
+    ("2.16.840.1.101.3.4.3.25", "SLH-DSA-SHA2-256f", FIPS_205),
+    ("2.16.840.1.101.3.4.3.26", "SLH-DSA-SHAKE-128s", FIPS_205),
+    ("2.16.840.1.101.3.4.3.27", "SLH-DSA-SHAKE-128f", FIPS_205),
+    ("2.16.840.1.101.3.4.3.28", "SLH-DSA-SHAKE-192s", FIPS_205),
+    ("2.16.840.1.101.3.4.3.29", "SLH-DSA-SHAKE-192f", FIPS_205),
+    ("2.16.840.1.101.3.4.3.30", "SLH-DSA-SHAKE-256s", FIPS_205),
+    ("2.16.840.1.101.3.4.3.31", "SLH-DSA-SHAKE-256f", FIPS_205),
+    (
+        "2.16.840.1.101.3.4.3.32",
+        "HashML-DSA-44 with SHA-512",
+        FIPS_204,
+    ),
+    (
+        "2.16.840.1.101.3.4.3.33",
+        "HashML-DSA-65 with SHA-512",
+        FIPS_204,
+    ),
+    (
+        "2.16.840.1.101.3.4.3.34",
+        "HashML-DSA-87 with SHA-512",
+        FIPS_204,
+    ),
+    ("2.16.840.1.101.3.4.4.1", "ML-KEM-512", FIPS_203),
+    ("2.16.840.1.101.3.4.4.2", "ML-KEM-768", FIPS_203),
+    ("2.16.840.1.101.3.4.4.3", "ML-KEM-1024", FIPS_203),
+    (
+        "1.3.6.1.5.5.7.6.37",
+        "ML-DSA-44 with RSA-PSS 2048",
+        COMPOSITE,
+    ),
+    ("1.3.6.1.5.5.7.6.38", "ML-DSA-44 with RSA 2048", COMPOSITE),
+    ("1.3.6.1.5.5.7.6.39", "ML-DSA-44 with Ed25519", COMPOSITE),
+    (
+        "1.3.6.1.5.5.7.6.40",
+        "ML-DSA-44 with ECDSA P-256",
+        COMPOSITE,
+    ),
+    (
+        "1.3.6.1.5.5.7.6.41",
+        "ML-DSA-65 with RSA-PSS 3072",
+        COMPOSITE,
+    ),
+    ("1.3.6.1.5.5.7.6.42", "ML-DSA-65 with RSA 3072", COMPOSITE),
+    (
+        "1.3.6.1.5.5.7.6.43",
+        "ML-DSA-65 with RSA-PSS 4096",
+        COMPOSITE,
+    ),
+    ("1.3.6.1.5.5.7.6.44", "ML-DSA-65 with RSA 4096", COMPOSITE),
+    (
+        "1.3.6.1.5.5.7.6.45",
+        "ML-DSA-65 with ECDSA P-256",
+        COMPOSITE,
+    ),
+    (
+        "1.3.6.1.5.5.7.6.46",
+        "ML-DSA-65 with ECDSA P-384",
+        COMPOSITE,
+    ),
+    (
+        "1.3.6.1.5.5.7.6.47",
+        "ML-DSA-65 with ECDSA brainpoolP256r1",
+        COMPOSITE,
+    ),
+    ("1.3.6.1.5.5.7.6.48", "ML-DSA-65 with Ed25519", COMPOSITE),
+    (
+        "1.3.6.1.5.5.7.6.49",
+        "ML-DSA-87 with ECDSA P-384",
+        COMPOSITE,
+    ),
+    (
+        "1.3.6.1.5.5.7.6.50",
+        "ML-DSA-87 with ECDSA brainpoolP384r1",
+        COMPOSITE,
+    ),
+    ("1.3.6.1.5.5.7.6.51", "ML-DSA-87 with Ed448", COMPOSITE),
+    (
+        "1.3.6.1.5.5.7.6.52",
+        "ML-DSA-87 with RSA-PSS 3072",
+        COMPOSITE,
+    ),
+    (
+        "1.3.6.1.5.5.7.6.53",
+        "ML-DSA-87 with RSA-PSS 4096",
+        COMPOSITE,
+    ),
+    (
+        "1.3.6.1.5.5.7.6.54",
+        "ML-DSA-87 with ECDSA P-521",
+        COMPOSITE,
+    ),
+    ("1.3.6.1.4.1.2.267.7.4.4", "Dilithium2", DILITHIUM),
+    ("1.3.6.1.4.1.2.267.7.6.5", "Dilithium3", DILITHIUM),
+    ("1.3.6.1.4.1.2.267.7.8.7", "Dilithium5", DILITHIUM),
+];
+
+const FIPS_203: Status = Status::Standard("FIPS 203");
+const FIPS_204: Status = Status::Standard("FIPS 204");
+const FIPS_205: Status = Status::Standard("FIPS 205");
+const COMPOSITE: Status = Status::Experimental("draft-ietf-lamps-pq-composite-sigs");
+const DILITHIUM: Status = Status::Experimental("the round 3 NIST submission");
+
+/// The name of a post-quantum or composite algorithm.
+pub fn name(oid: &Oid) -> Option<&'static str> {
+    find(oid).map(|(name, _)| name)
+}
+
+fn find(oid: &Oid) -> Option<(&'static str, Status)> {
+    let oid = oid.to_id_string();
+    ALGORITHMS
+        .iter()
+        .find(|(known, _, _)| *known == oid)
+        .map(|(_, name, status)| (*name, *status))
+}
+
+impl Status {
+    fn describe(self) -> String {
+        match self {
+            Status::Standard(standard) => format!("standardised in {}", standard),
+            Status::Experimental(source) => format!("experimental, from {}", source),
+        }
+    }
+}
+
+/// Decodes a post-quantum or composite key, saying how settled its algorithm
+/// is. Other certificates are left alone.
+pub fn key(cert: &X509Certificate) -> Option<Vec<Field>> {
+    let spki = cert.public_key();
+    let (algorithm, status) = find(&spki.algorithm.algorithm)?;
+    Some(vec![
+        Field::new("Algorithm", algorithm),
+        Field::new("Status", status.describe()),
+        Field::new(
+            "Key size",
+            format!("{} bytes", spki.subject_public_key.data.len()),
+        ),
+        Field::new(
+            "Signature algorithm",
+            oid::name(&cert.signature_algorithm.algorithm),
+        ),
+    ])
+}
+
+/// The second SubjectPublicKeyInfo of a hybrid certificate.
+pub fn alt_public_key(value: &[u8]) -> Option<Vec<Field>> {
+    let spki = elements(value)?;
+    let fields = elements(spki.first()?.data)?;
+    let algorithm = elements(fields.first()?.data)?;
+    let mut decoded = algorithm_fields(Oid::new(Cow::Borrowed(algorithm.first()?.data)));
+    // The key is a BIT STRING, after its count of unused bits
+    let key = fields.get(1)?.data.len().saturating_sub(1);
+    decoded.push(Field::new("Key size", format!("{} bytes", key)));
+    Some(decoded)
+}
+
+/// The algorithm the second signature of a hybrid certificate was made with.
+pub fn alt_signature_algorithm(value: &[u8]) -> Option<Vec<Field>> {
+    let identifier = elements(value)?;
+    let algorithm = elements(identifier.first()?.data)?;
+    Some(algorithm_fields(Oid::new(Cow::Borrowed(
+        algorithm.first()?.data,
+    ))))
+}
+
+pub fn alt_signature_value(value: &[u8]) -> Option<Vec<Field>> {
+    let signature = elements(value)?;
+    let size = signature.first()?.data.len().saturating_sub(1);
+    Some(vec![Field::new(
+        "Signature size",
+        format!("{} bytes", size),
+    )])
+}
+
+fn algorithm_fields(oid: Oid) -> Vec<Field> {
+    let mut fields = vec![Field::new("Algorithm", oid::name(&oid))];
+    if let Some((_, status)) = find(&oid) {
+        fields.push(Field::new("Status", status.describe()));
+    }
+    fields
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{alt_public_key, key, ALT_PUBLIC_KEY};
+    use crate::test::google_der;
+
+    fn fields(bytes: &[u8]) -> Vec<(String, String)> {
+        let (_, pem) = parse_x509_pem(bytes).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+        key(&cert)
+            .unwrap()
+            .into_iter()
+            .map(|field| (field.name, field.value))
+            .collect()
+    }
+
+    #[test]
+    fn should_tell_standard_algorithms_from_experimental_ones() {
+        let ml_dsa = fields(include_bytes!("../resources/ml-dsa.crt"));
+        let composite = fields(include_bytes!("../resources/composite.crt"));
+
+        assert_eq!(ml_dsa[0].1, "ML-DSA-65");
+        assert_eq!(ml_dsa[1].1, "standardised in FIPS 204");
+        assert_eq!(ml_dsa[2].1, "1952 bytes");
+        assert_eq!(composite[0].1, "ML-DSA-44 with ECDSA P-256");
+        assert_eq!(
+            composite[1].1,
+            "experimental, from draft-ietf-lamps-pq-composite-sigs"
+        );
+    }
+
+    #[test]
+    fn should_decode_the_alternative_key_of_a_hybrid_certificate() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/hybrid.crt")).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+        let extension = cert
+            .extensions()
+            .iter()
+            .find(|extension| extension.oid.to_id_string() == ALT_PUBLIC_KEY)
+            .unwrap();
+
+        let fields = alt_public_key(extension.value).unwrap();
+
+        assert!(key(&cert).is_none());
+        assert_eq!(fields[0].value, "ML-DSA-44");
+        assert_eq!(fields[2].value, "1312 bytes");
+    }
+
+    #[test]
+    fn should_leave_other_certificates_alone() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        assert!(key(&cert).is_none());
+    }
+}
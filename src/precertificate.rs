@@ -0,0 +1,209 @@
+use x509_parser::certificate::X509Certificate;
+use x509_parser::parse_x509_certificate;
+
+use crate::cms::{element, encode, Tlv};
+
+/// The critical extension RFC 6962 adds to a precertificate so that no client will accept it.
+const POISON: &str = "1.3.6.1.4.1.11129.2.4.3";
+/// The extended key usage of a certificate a CA only signs precertificates with.
+const PRECERTIFICATE_SIGNING: &str = "1.3.6.1.4.1.11129.2.4.4";
+const AUTHORITY_KEY_IDENTIFIER: &str = "2.5.29.35";
+
+/// Whether a certificate is a Certificate Transparency precertificate, and if
+/// so whether its poison extension is critical as RFC 6962 requires.
+pub fn poison(cert: &X509Certificate) -> Option<bool> {
+    cert.extensions()
+        .iter()
+        .find(|extension| extension.oid.to_id_string() == POISON)
+        .map(|extension| extension.critical)
+}
+
+/// Whether a certificate is a precertificate signing certificate, which RFC 6962
+/// lets a CA sign precertificates with instead of its own key.
+pub fn is_precertificate_signer(cert: &X509Certificate) -> bool {
+    match cert.extended_key_usage() {
+        Ok(Some(usage)) => usage
+            .value
+            .other
+            .iter()
+            .any(|oid| oid.to_id_string() == PRECERTIFICATE_SIGNING),
+        _ => false,
+    }
+}
+
+/// The TBS certificate of a precertificate with its poison extension taken out,
+/// which is what the CT log signed and what the final certificate will hold
+/// once the SCTs are added. When `issuer` is a precertificate signing
+/// certificate, the issuer and authority key identifier are also changed to its
+/// own issuer and authority key identifier, those of the CA that will issue the
+/// final certificate. Without `issuer`, the precertificate is taken to be
+/// signed by that CA.
+pub fn reconstruct_tbs(der: &[u8], issuer: Option<&[u8]>) -> Option<Vec<u8>> {
+    let signer = match issuer.map(|issuer| (issuer, parse_x509_certificate(issuer))) {
+        Some((issuer, Ok((_, cert)))) if is_precertificate_signer(&cert) => {
+            let fields = tbs_fields(issuer)?;
+            let name = fields.get(issuer_index(&fields))?.raw;
+            Some((name, extension(&fields, AUTHORITY_KEY_IDENTIFIER)))
+        }
+        _ => None,
+    };
+
+    let fields = tbs_fields(der)?;
+    let issuer_at = issuer_index(&fields);
+    let mut edited = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        match (&signer, field.tag) {
+            (Some((name, _)), _) if i == issuer_at => edited.extend_from_slice(name),
+            (_, 0xa3) => {
+                let mut kept = Vec::new();
+                for extension in field.children()?.first()?.children()? {
+                    let id = extension.children()?.first()?.oid()?.to_id_string();
+                    match (&signer, id.as_str()) {
+                        (_, POISON) => {}
+                        (Some((_, aki)), AUTHORITY_KEY_IDENTIFIER) => {
+                            kept.extend_from_slice(aki.unwrap_or_default())
+                        }
+                        _ => kept.extend_from_slice(extension.raw),
+                    }
+                }
+                if !kept.is_empty() {
+                    edited.extend(encode(0xa3, &encode(0x30, &kept)));
+                }
+            }
+            _ => edited.extend_from_slice(field.raw),
+        }
+    }
+    Some(encode(0x30, &edited))
+}
+
+/// The fields of a certificate's TBS certificate.
+fn tbs_fields(der: &[u8]) -> Option<Vec<Tlv<'_>>> {
+    let (certificate, _) = element(der)?;
+    certificate.children()?.into_iter().next()?.children()
+}
+
+/// Where the issuer is among the fields of a TBS certificate, after the
+/// version if there is one, the serial number and the signature algorithm.
+fn issuer_index(fields: &[Tlv]) -> usize {
+    match fields.first() {
+        Some(field) if field.tag == 0xa0 => 3,
+        _ => 2,
+    }
+}
+
+/// The whole of an extension among the fields of a TBS certificate.
+fn extension<'a>(fields: &[Tlv<'a>], oid: &str) -> Option<&'a [u8]> {
+    let extensions = fields.iter().find(|field| field.tag == 0xa3)?;
+    extensions
+        .children()?
+        .first()?
+        .children()?
+        .into_iter()
+        .find(|extension| {
+            extension
+                .children()
+                .and_then(|parts| parts.first()?.oid())
+                .is_some_and(|id| id.to_id_string() == oid)
+        })
+        .map(|extension| extension.raw)
+}
+
+/// The TBS certificate of a certificate with one extension taken out.
+pub fn tbs_without(der: &[u8], oid: &str) -> Option<Vec<u8>> {
+    let mut fields = Vec::new();
+    for field in tbs_fields(der)? {
+        if field.tag != 0xa3 {
+            fields.extend_from_slice(field.raw);
+            continue;
+        }
+        let extensions = field.children()?.into_iter().next()?;
+        let mut kept = Vec::new();
+        for extension in extensions.children()? {
+            let id = extension.children()?.first()?.oid()?;
+            if id.to_id_string() != oid {
+                kept.extend_from_slice(extension.raw);
+            }
+        }
+        // A certificate with no extensions leaves the field out rather than empty
+        if !kept.is_empty() {
+            fields.extend(encode(0xa3, &encode(0x30, &kept)));
+        }
+    }
+    Some(encode(0x30, &fields))
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::certificate::TbsCertificate;
+    use x509_parser::extensions::X509Extension;
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::{parse_x509_pem, Pem};
+    use x509_parser::prelude::FromDer;
+
+    use super::{is_precertificate_signer, poison, reconstruct_tbs};
+    use crate::test::google_der;
+
+    #[test]
+    fn should_take_the_poison_out_of_the_tbs_certificate() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/precert.crt")).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+
+        let tbs = reconstruct_tbs(&pem.contents, None).unwrap();
+        let (rest, reconstructed) = TbsCertificate::from_der(&tbs).unwrap();
+
+        assert_eq!(poison(&cert), Some(true));
+        assert!(rest.is_empty());
+        assert_eq!(
+            reconstructed.extensions().len(),
+            cert.extensions().len() - 1
+        );
+        assert_eq!(reconstructed.subject, cert.subject);
+    }
+
+    #[test]
+    fn should_take_the_issuer_of_a_precertificate_signing_certificate() {
+        let chain: Vec<Vec<u8>> =
+            Pem::iter_from_buffer(include_bytes!("../resources/precert-signer-chain.pem"))
+                .map(|pem| pem.unwrap().contents)
+                .collect();
+        let (_, precert) = parse_x509_certificate(&chain[0]).unwrap();
+        let (_, signer) = parse_x509_certificate(&chain[1]).unwrap();
+        let (_, ca) = parse_x509_certificate(&chain[2]).unwrap();
+        assert!(is_precertificate_signer(&signer));
+        assert!(!is_precertificate_signer(&ca));
+
+        let tbs = reconstruct_tbs(&chain[0], Some(&chain[1])).unwrap();
+        let (_, reconstructed) = TbsCertificate::from_der(&tbs).unwrap();
+        assert_eq!(reconstructed.issuer, ca.tbs_certificate.subject);
+        let aki = |extensions: &[X509Extension]| {
+            extensions
+                .iter()
+                .find(|extension| extension.oid.to_id_string() == "2.5.29.35")
+                .map(|extension| extension.value.to_vec())
+        };
+        assert_eq!(aki(reconstructed.extensions()), aki(signer.extensions()));
+        assert_ne!(aki(reconstructed.extensions()), aki(precert.extensions()));
+        assert_eq!(
+            reconstructed.extensions().len(),
+            precert.extensions().len() - 1
+        );
+
+        // Signed by the CA itself, only the poison goes
+        let tbs = reconstruct_tbs(&chain[0], Some(&chain[2])).unwrap();
+        let (_, reconstructed) = TbsCertificate::from_der(&tbs).unwrap();
+        assert_eq!(reconstructed.issuer, precert.tbs_certificate.issuer);
+    }
+
+    #[test]
+    fn should_leave_a_certificate_without_poison_as_it_was() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        assert_eq!(poison(&cert), None);
+        assert_eq!(
+            reconstruct_tbs(&der, None).unwrap(),
+            cert.tbs_certificate.as_ref()
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// What an Apple provisioning profile grants, as read from the plist it signs.
+pub struct Profile {
+    pub location: String,
+    pub name: String,
+    pub uuid: Option<String>,
+    pub app_id_name: Option<String>,
+    /// The team identifier, with the team's name when the profile gives it.
+    pub team: Option<String>,
+    pub team_id: Option<String>,
+    /// Development, ad hoc, enterprise or App Store, going by what it provisions.
+    pub kind: &'static str,
+    pub platforms: Vec<String>,
+    pub created: String,
+    pub expires: String,
+    /// `expires` in seconds since the epoch, to compare against certificates.
+    pub expires_at: i64,
+    pub devices: usize,
+    /// Each entitlement with its value, arrays joined and dictionaries summarised.
+    pub entitlements: Vec<(String, String)>,
+    /// The certificates apps signed under the profile may be signed with.
+    pub certificates: Vec<Vec<u8>>,
+}
+
+/// A plist value, with dates and numbers kept as written.
+enum Value {
+    String(String),
+    Data(Vec<u8>),
+    Date(String),
+    Number(String),
+    Bool(bool),
+    Array(Vec<Value>),
+    Dict(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dict(entries) => entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn string(&self) -> Option<&str> {
+        match self {
+            Value::String(value) | Value::Date(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn array(&self) -> &[Value] {
+        match self {
+            Value::Array(values) => values,
+            _ => &[],
+        }
+    }
+
+    /// How the value reads in a one-line summary.
+    fn summary(&self) -> String {
+        match self {
+            Value::String(value) | Value::Date(value) | Value::Number(value) => value.clone(),
+            Value::Data(data) => format!("{} bytes", data.len()),
+            Value::Bool(value) => value.to_string(),
+            Value::Array(values) => values
+                .iter()
+                .map(Value::summary)
+                .collect::<Vec<_>>()
+                .join(", "),
+            Value::Dict(entries) => format!("{} keys", entries.len()),
+        }
+    }
+}
+
+/// Reads a provisioning profile from the content of its CMS signature. Anything
+/// that isn't an XML plist with developer certificates is some other signed content.
+pub fn read(source: &str, content: &[u8]) -> Option<Profile> {
+    let text = std::str::from_utf8(content).ok()?;
+    let start = text.find("<plist")?;
+    let mut parser = Parser {
+        text: &text[start..],
+    };
+    parser.open()?; // <plist version="1.0">
+    let plist = parser.value()?;
+    let certificates = plist
+        .get("DeveloperCertificates")?
+        .array()
+        .iter()
+        .map(|value| match value {
+            Value::Data(der) => Some(der.clone()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let team_id = plist
+        .get("TeamIdentifier")
+        .and_then(|ids| ids.array().first())
+        .and_then(Value::string)
+        .map(String::from);
+    let team_name = plist.get("TeamName").and_then(Value::string);
+    let expires = plist.get("ExpirationDate")?.string()?;
+    let entitlements = match plist.get("Entitlements") {
+        Some(Value::Dict(entries)) => entries
+            .iter()
+            .map(|(name, value)| (name.clone(), value.summary()))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let string = |key| plist.get(key).and_then(Value::string).map(String::from);
+
+    let devices = plist
+        .get("ProvisionedDevices")
+        .map(|devices| devices.array().len());
+    let debuggable = matches!(
+        plist
+            .get("Entitlements")
+            .and_then(|e| e.get("get-task-allow")),
+        Some(Value::Bool(true))
+    );
+    let kind = match (devices, plist.get("ProvisionsAllDevices")) {
+        (_, Some(Value::Bool(true))) => "enterprise",
+        (Some(_), _) if debuggable => "development",
+        (Some(_), _) => "ad hoc",
+        (None, _) => "App Store",
+    };
+
+    Some(Profile {
+        location: String::from(source),
+        name: string("Name")?,
+        uuid: string("UUID"),
+        app_id_name: string("AppIDName"),
+        team: match (&team_id, team_name) {
+            (Some(id), Some(name)) => Some(format!("{} ({})", name, id)),
+            (id, name) => id.clone().or_else(|| name.map(String::from)),
+        },
+        team_id,
+        kind,
+        platforms: plist
+            .get("Platform")
+            .map(|platforms| platforms.array().iter().map(Value::summary).collect())
+            .unwrap_or_default(),
+        created: string("CreationDate").unwrap_or_default(),
+        expires: String::from(expires),
+        expires_at: epoch_seconds(expires)?,
+        devices: devices.unwrap_or(0),
+        entitlements,
+        certificates,
+    })
+}
+
+/// Just enough of an XML reader for the plists Apple writes: elements, text
+/// and the predefined entities, skipping the prolog and comments.
+struct Parser<'a> {
+    text: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    /// Reads the next start tag, returning its name and whether it closes itself.
+    fn open(&mut self) -> Option<(&'a str, bool)> {
+        loop {
+            self.text = self.text.trim_start();
+            if self.text.starts_with("<!--") {
+                let end = self.text.find("-->")?;
+                self.text = &self.text[end + 3..];
+                continue;
+            }
+            if !self.text.starts_with('<') || self.text.starts_with("</") {
+                return None;
+            }
+            let end = self.text.find('>')?;
+            let tag = &self.text[1..end];
+            self.text = &self.text[end + 1..];
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+            let empty = tag.ends_with('/');
+            let name = tag.trim_end_matches('/').split_whitespace().next()?;
+            return Some((name, empty));
+        }
+    }
+
+    /// Reads the text up to the end tag of `name`, consuming the end tag.
+    fn text(&mut self, name: &str) -> Option<String> {
+        let end = format!("</{}>", name);
+        let at = self.text.find(&end)?;
+        let text = &self.text[..at];
+        self.text = &self.text[at + end.len()..];
+        Some(
+            text.replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&"),
+        )
+    }
+
+    /// Consumes the end tag of `name`.
+    fn close(&mut self, name: &str) -> Option<()> {
+        self.text = self.text.trim_start();
+        self.text = self.text.strip_prefix(&format!("</{}>", name))?;
+        Some(())
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        let (name, empty) = self.open()?;
+        let value = match (name, empty) {
+            ("true", true) => Value::Bool(true),
+            ("false", true) => Value::Bool(false),
+            ("string", true) => Value::String(String::new()),
+            ("array", true) => Value::Array(Vec::new()),
+            ("dict", true) => Value::Dict(Vec::new()),
+            ("string", false) => Value::String(self.text(name)?),
+            ("date", false) => Value::Date(self.text(name)?),
+            ("integer", false) | ("real", false) => Value::Number(self.text(name)?),
+            ("data", false) => {
+                let encoded: String = self
+                    .text(name)?
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect();
+                Value::Data(STANDARD.decode(encoded).ok()?)
+            }
+            ("array", false) => {
+                let mut values = Vec::new();
+                while let Some(value) = self.value() {
+                    values.push(value);
+                }
+                self.close(name)?;
+                Value::Array(values)
+            }
+            ("dict", false) => {
+                let mut entries = Vec::new();
+                while let Some(("key", false)) = self.open() {
+                    let key = self.text("key")?;
+                    entries.push((key, self.value()?));
+                }
+                self.close(name)?;
+                Value::Dict(entries)
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Seconds since the epoch of a plist date, which is always UTC: `2027-10-15T00:00:00Z`.
+/// CT log lists write their dates the same way.
+pub fn epoch_seconds(date: &str) -> Option<i64> {
+    let number = |range: std::ops::Range<usize>| date.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    // Days from the civil date, counting years from March so leap days come last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{epoch_seconds, read};
+
+    const PLIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<!-- distribution profiles provision no devices -->
+	<key>DeveloperCertificates</key>
+	<array/>
+	<key>Entitlements</key>
+	<dict>
+		<key>aps-environment</key>
+		<string>production</string>
+		<key>beta-reports-active</key>
+		<true/>
+	</dict>
+	<key>ExpirationDate</key>
+	<date>2027-10-15T00:00:00Z</date>
+	<key>Name</key>
+	<string>Example &amp; Co Distribution</string>
+	<key>TeamIdentifier</key>
+	<array>
+		<string>ABCDE12345</string>
+	</array>
+</dict>
+</plist>"#;
+
+    #[test]
+    fn should_read_a_provisioning_profile() {
+        let profile = read("profile", PLIST.as_bytes()).unwrap();
+
+        assert_eq!(profile.name, "Example & Co Distribution");
+        assert_eq!(profile.kind, "App Store");
+        assert_eq!(profile.team.as_deref(), Some("ABCDE12345"));
+        assert_eq!(profile.expires_at, 1_823_558_400);
+        assert_eq!(
+            profile.entitlements,
+            vec![
+                (String::from("aps-environment"), String::from("production")),
+                (String::from("beta-reports-active"), String::from("true")),
+            ]
+        );
+        assert!(profile.certificates.is_empty());
+    }
+
+    #[test]
+    fn should_not_read_other_plists_as_profiles() {
+        let plist =
+            r#"<plist version="1.0"><dict><key>Name</key><string>x</string></dict></plist>"#;
+
+        assert!(read("plist", plist.as_bytes()).is_none());
+        assert!(read("hello", b"hello").is_none());
+        assert_eq!(epoch_seconds("2000-03-01T00:00:00Z"), Some(951_868_800));
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashSet;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+
+use crate::idn;
+use crate::FileProcessor;
+
+/// Where distributions keep the Public Suffix List, from their publicsuffix package.
+pub const LISTS: &[&str] = &[
+    "/usr/share/publicsuffix/public_suffix_list.dat",
+    "/usr/share/publicsuffix/effective_tld_names.dat",
+    // Homebrew
+    "/opt/homebrew/share/publicsuffix/public_suffix_list.dat",
+    "/usr/local/share/publicsuffix/public_suffix_list.dat",
+];
+
+/// More registrable domains than this in one certificate is broader than a
+/// site and its aliases usually need.
+pub const BROAD: usize = 10;
+
+/// The rules of the Public Suffix List, which say where anyone can register a
+/// domain. Without a list only the default rule applies, that every top-level
+/// domain is a public suffix.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SuffixList {
+    /// Rules as written, including wildcard rules like `*.ck`.
+    rules: HashSet<String>,
+    /// Exception rules, without their `!`.
+    exceptions: HashSet<String>,
+}
+
+impl SuffixList {
+    /// Reads a list in the format of `public_suffix_list.dat`.
+    pub fn parse(text: &str) -> SuffixList {
+        let mut list = SuffixList::default();
+        for line in text.lines() {
+            let rule = match line.split_whitespace().next() {
+                Some(rule) if !rule.starts_with("//") => rule.to_lowercase(),
+                _ => continue,
+            };
+            match rule.strip_prefix('!') {
+                Some(exception) => list.exceptions.insert(String::from(exception)),
+                None => list.rules.insert(rule),
+            };
+        }
+        list
+    }
+
+    /// The number of labels at the end of a name that are its public suffix,
+    /// following the algorithm at publicsuffix.org. The list is in Unicode, so
+    /// Punycode labels are decoded to look them up.
+    fn suffix_labels(&self, name: &str) -> usize {
+        let name = name.trim_end_matches('.').to_lowercase();
+        let name = idn::to_unicode(&name).unwrap_or(name);
+        let labels: Vec<&str> = name.split('.').collect();
+        let n = labels.len();
+        // An exception wins over every other rule, and means its parent is the suffix
+        if let Some(i) = (0..n).find(|i| self.exceptions.contains(&labels[*i..].join("."))) {
+            return n - i - 1;
+        }
+        for i in 0..n {
+            let candidate = labels[i..].join(".");
+            let wildcard = match labels.get(i + 1..) {
+                Some(parent) if !parent.is_empty() => format!("*.{}", parent.join(".")),
+                _ => String::from("*"),
+            };
+            if self.rules.contains(&candidate) || self.rules.contains(&wildcard) {
+                return n - i;
+            }
+        }
+        // The default rule
+        1
+    }
+
+    /// The public suffix of a name, such as `co.uk` for `www.example.co.uk`.
+    pub fn public_suffix(&self, name: &str) -> String {
+        last_labels(name, self.suffix_labels(name))
+    }
+
+    /// Whether a name is itself a public suffix, which no one can register.
+    pub fn is_public_suffix(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('.');
+        self.suffix_labels(name) >= name.split('.').count()
+    }
+
+    /// The domain someone registered that a name is under, such as
+    /// `example.co.uk` for `www.example.co.uk`, or `None` for a public suffix.
+    pub fn registrable_domain(&self, name: &str) -> Option<String> {
+        let name = name.trim_end_matches('.');
+        let labels = self.suffix_labels(name) + 1;
+        (labels <= name.split('.').count()).then(|| last_labels(name, labels))
+    }
+}
+
+fn last_labels(name: &str, count: usize) -> String {
+    let name = name.trim_end_matches('.').to_lowercase();
+    let labels: Vec<&str> = name.split('.').collect();
+    labels[labels.len().saturating_sub(count)..].join(".")
+}
+
+/// Loads the list given with `--public-suffix-list`, or else the first one the
+/// system has, or else none, leaving only the default rule.
+pub fn load(
+    processor: &impl FileProcessor,
+    path: Option<&str>,
+) -> Result<SuffixList, Box<dyn std::error::Error>> {
+    let path = match path {
+        Some(path) => path,
+        None => match LISTS.iter().find(|path| processor.is_file(path)) {
+            Some(path) => path,
+            None => return Ok(SuffixList::default()),
+        },
+    };
+    Ok(SuffixList::parse(&processor.read_to_string(path)?))
+}
+
+/// The DNS names among a certificate's subject alternative names.
+pub fn dns_names(cert: &X509Certificate) -> Vec<String> {
+    match cert.subject_alternative_name() {
+        Ok(Some(san)) => san
+            .value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(name) => Some(name.to_lowercase()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The DNS names of a certificate gathered under the domains they were
+/// registrable under, in the order first seen, with each name's count.
+pub fn registrable_domains(list: &SuffixList, names: &[String]) -> Vec<(String, usize)> {
+    let mut domains: Vec<(String, usize)> = Vec::new();
+    for name in names {
+        let domain = list
+            .registrable_domain(name.trim_start_matches("*."))
+            .unwrap_or_else(|| list.public_suffix(name));
+        match domains.iter_mut().find(|(known, _)| *known == domain) {
+            Some((_, count)) => *count += 1,
+            None => domains.push((domain, 1)),
+        }
+    }
+    domains
+}
+
+/// What is worth pointing out about the names of a certificate: wildcards over
+/// a public suffix, names that are one, and covering more registrable domains
+/// than a site usually needs.
+pub fn problems(list: &SuffixList, names: &[String]) -> Vec<String> {
+    let mut problems = Vec::new();
+    for name in names {
+        match name.strip_prefix("*.") {
+            Some(base) if list.is_public_suffix(base) => problems.push(format!(
+                "has the wildcard {}, which covers every domain registered under the public suffix {}",
+                name, base
+            )),
+            None if list.is_public_suffix(name) => problems.push(format!(
+                "names the public suffix {}, which no one can register",
+                name
+            )),
+            _ => {}
+        }
+    }
+    let domains = registrable_domains(list, names).len();
+    if domains > BROAD {
+        problems.push(format!(
+            "covers names under {} registrable domains, more than a site usually needs",
+            domains
+        ));
+    }
+    problems
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{load, problems, registrable_domains, SuffixList};
+    use crate::test::FakeProcessor;
+
+    const LIST: &str = "// ===BEGIN ICANN DOMAINS===\n\
+                        com\n\
+                        uk\n\
+                        co.uk\n\
+                        *.ck\n\
+                        !www.ck\n\
+                        公司.cn\n\
+                        cn\n\
+                        // ===BEGIN PRIVATE DOMAINS===\n\
+                        github.io\n";
+
+    #[test]
+    fn should_find_the_registrable_domain_of_a_name() {
+        let list = SuffixList::parse(LIST);
+
+        assert_eq!(
+            list.registrable_domain("www.example.co.uk").as_deref(),
+            Some("example.co.uk")
+        );
+        assert_eq!(list.registrable_domain("co.uk"), None);
+        assert_eq!(list.public_suffix("a.b.example.ck"), "example.ck");
+        assert_eq!(list.registrable_domain("www.ck").as_deref(), Some("www.ck"));
+        assert_eq!(
+            list.registrable_domain("WWW.Example.xn--55qx5d.cn.")
+                .as_deref(),
+            Some("example.xn--55qx5d.cn")
+        );
+        assert_eq!(
+            list.registrable_domain("www.example.test").as_deref(),
+            Some("example.test")
+        );
+    }
+
+    #[test]
+    fn should_flag_wildcards_over_public_suffixes_and_broad_certificates() {
+        let list = SuffixList::parse(LIST);
+        let mut names = vec![
+            String::from("*.github.io"),
+            String::from("*.example.com"),
+            String::from("co.uk"),
+        ];
+
+        assert_eq!(
+            problems(&list, &names),
+            vec![
+                "has the wildcard *.github.io, which covers every domain registered under the public suffix github.io",
+                "names the public suffix co.uk, which no one can register",
+            ]
+        );
+
+        names.extend((0..10).map(|i| format!("www.site{}.com", i)));
+        assert!(problems(&list, &names)
+            .last()
+            .unwrap()
+            .starts_with("covers names under 13 registrable domains"));
+        assert_eq!(
+            registrable_domains(&list, &names[..3]),
+            vec![
+                (String::from("github.io"), 1),
+                (String::from("example.com"), 1),
+                (String::from("co.uk"), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_fall_back_to_the_default_rule_without_a_list() {
+        let processor = FakeProcessor::default();
+
+        let list = load(&processor, None).unwrap();
+
+        assert_eq!(
+            list.registrable_domain("www.example.co.uk").as_deref(),
+            Some("co.uk")
+        );
+        assert!(list.is_public_suffix("com"));
+    }
+}
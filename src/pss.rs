@@ -0,0 +1,137 @@
+use std::convert::TryFrom;
+
+use x509_parser::asn1_rs::Any;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::oid_registry::OID_PKCS1_RSASSAPSS;
+use x509_parser::signature_algorithm::RsaSsaPssParams;
+use x509_parser::x509::AlgorithmIdentifier;
+
+use crate::extensions::Field;
+use crate::oid;
+
+/// The RSASSA-PSS parameters of a certificate's signature and, when the key is
+/// restricted to PSS, of its key. Other certificates are left alone.
+pub fn parameters(cert: &X509Certificate) -> Option<Vec<Field>> {
+    let mut fields = Vec::new();
+    if let Some(signature) = fields_of(&cert.signature_algorithm) {
+        fields.push(Field::group("Signature", signature));
+    }
+    let key = &cert.public_key().algorithm;
+    if key.algorithm == OID_PKCS1_RSASSAPSS {
+        // A PSS key without parameters can be used with any of them
+        let restrictions = fields_of(key)
+            .unwrap_or_else(|| vec![Field::new("Restrictions", "none, any parameters")]);
+        fields.push(Field::group("Public key", restrictions));
+    }
+    match fields.is_empty() {
+        true => None,
+        false => Some(fields),
+    }
+}
+
+/// The parameters in one line, like `sha256, MGF1 with sha256, salt length 32`,
+/// for where an algorithm is named alongside others.
+pub fn describe(parameters: &Any) -> Option<String> {
+    let fields = fields_from(parameters)?;
+    Some(
+        fields
+            .iter()
+            .filter(|field| field.name != "Trailer field" || field.value != "1")
+            .map(|field| match field.name.as_str() {
+                "Salt length" => format!("salt length {}", field.value),
+                _ => field.value.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+    )
+}
+
+fn fields_of(identifier: &AlgorithmIdentifier) -> Option<Vec<Field>> {
+    if identifier.algorithm != OID_PKCS1_RSASSAPSS {
+        return None;
+    }
+    fields_from(identifier.parameters.as_ref()?)
+}
+
+/// The hash, mask generation function, salt length and trailer field, with
+/// the SHA-1 defaults RFC 4055 gives any that are left out.
+fn fields_from(parameters: &Any) -> Option<Vec<Field>> {
+    let parameters = RsaSsaPssParams::try_from(parameters).ok()?;
+    let mask = parameters.mask_gen_algorithm().ok()?;
+    let mask_name = match oid::name(&mask.mgf).as_str() {
+        "mgf1" | "id-mgf1" => String::from("MGF1"),
+        name => String::from(name),
+    };
+    Some(vec![
+        Field::new("Hash", oid::name(parameters.hash_algorithm_oid())),
+        Field::new(
+            "Mask generation",
+            format!("{} with {}", mask_name, oid::name(&mask.hash)),
+        ),
+        Field::new("Salt length", parameters.salt_length().to_string()),
+        Field::new("Trailer field", parameters.trailer_field().to_string()),
+    ])
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::asn1_rs::{Any, FromDer};
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{describe, parameters};
+    use crate::cms;
+    use crate::test::google_der;
+
+    #[test]
+    fn should_decode_the_signature_and_key_parameters() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/rsa-pss.crt")).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+
+        let fields = parameters(&cert).unwrap();
+        let values = |group: usize| -> Vec<String> {
+            fields[group]
+                .fields
+                .iter()
+                .map(|field| field.value.clone())
+                .collect()
+        };
+
+        assert_eq!(fields[0].name, "Signature");
+        assert_eq!(values(0), vec!["sha256", "MGF1 with sha256", "64", "1"]);
+        assert_eq!(fields[1].name, "Public key");
+        assert_eq!(values(1), vec!["sha256", "MGF1 with sha256", "32", "1"]);
+    }
+
+    #[test]
+    fn should_fill_in_the_sha1_defaults() {
+        // An empty RSASSA-PSS-params SEQUENCE
+        let (_, empty) = Any::from_der(&[0x30, 0x00]).unwrap();
+
+        assert_eq!(
+            describe(&empty).unwrap(),
+            "id-SHA1, MGF1 with id-SHA1, salt length 20"
+        );
+    }
+
+    #[test]
+    fn should_give_the_parameters_where_cms_names_the_algorithm() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/rsa-pss.crt")).unwrap();
+        let (certificate, _) = cms::element(&pem.contents).unwrap();
+        let fields = certificate.children().unwrap();
+
+        assert_eq!(
+            cms::algorithm(&fields[1]).unwrap(),
+            "rsassa-pss (sha256, MGF1 with sha256, salt length 64)"
+        );
+    }
+
+    #[test]
+    fn should_leave_other_certificates_alone() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        assert!(parameters(&cert).is_none());
+    }
+}
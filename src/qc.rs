@@ -0,0 +1,238 @@
+use std::borrow::Cow;
+
+use x509_parser::asn1_rs::Any;
+use x509_parser::der_parser::oid::Oid;
+
+use crate::cms::hex;
+use crate::extensions::{elements, unsigned, Field};
+use crate::oid;
+
+/// The extension of RFC 3739 that eIDAS qualified certificates make their statements in.
+pub const QC_STATEMENTS: &str = "1.3.6.1.5.5.7.1.3";
+
+/// The kinds of qualified certificate ETSI EN 319 412-5 defines.
+const QC_TYPES: &[(&str, &str)] = &[
+    ("0.4.0.1862.1.6.1", "electronic signature"),
+    ("0.4.0.1862.1.6.2", "electronic seal"),
+    ("0.4.0.1862.1.6.3", "website authentication"),
+];
+
+/// The roles a payment service provider can be authorised for under PSD2, from ETSI TS 119 495.
+const PSP_ROLES: &[(&str, &str)] = &[
+    ("0.4.0.19495.1.1", "account servicing"),
+    ("0.4.0.19495.1.2", "payment initiation"),
+    ("0.4.0.19495.1.3", "account information"),
+    (
+        "0.4.0.19495.1.4",
+        "issuing of card-based payment instruments",
+    ),
+];
+
+/// What the subject of a qualified certificate is, from ETSI EN 319 412-1.
+const SEMANTICS: &[(&str, &str)] = &[
+    ("0.4.0.194121.1.1", "natural person"),
+    ("0.4.0.194121.1.2", "legal person"),
+    ("0.4.0.194121.1.3", "eIDAS natural person"),
+    ("0.4.0.194121.1.4", "eIDAS legal person"),
+];
+
+/// Decodes the statements a qualified certificate makes about itself, such as
+/// the kind of qualified certificate it is and the PSD2 roles of its subject.
+pub fn statements(value: &[u8]) -> Option<Vec<Field>> {
+    let statements = elements(value)?;
+    let mut fields = Vec::new();
+    for statement in elements(statements.first()?.data)? {
+        let mut parts = elements(statement.data)?.into_iter();
+        let id = Oid::new(Cow::Borrowed(parts.next()?.data));
+        let info = parts.next();
+        let field = match (id.to_id_string().as_str(), info) {
+            ("0.4.0.1862.1.1", _) => Field::new("Compliance", "EU qualified certificate"),
+            ("0.4.0.1862.1.2", Some(info)) => Field::new("Transaction limit", limit(&info)?),
+            ("0.4.0.1862.1.3", Some(info)) => {
+                Field::new("Retention period", format!("{} years", unsigned(&info)?))
+            }
+            ("0.4.0.1862.1.4", _) => {
+                Field::new("Key storage", "qualified signature creation device")
+            }
+            ("0.4.0.1862.1.5", Some(info)) => {
+                Field::group("PKI disclosure statements", disclosure_statements(&info)?)
+            }
+            ("0.4.0.1862.1.6", Some(info)) => Field::new("Type", named(&info, QC_TYPES)?),
+            ("0.4.0.1862.1.7", Some(info)) => Field::new(
+                "Legislation",
+                elements(info.data)?
+                    .iter()
+                    .map(|country| String::from_utf8_lossy(country.data))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            ("0.4.0.19495.2", Some(info)) => Field::group("PSD2", psd2(&info)?),
+            ("1.3.6.1.5.5.7.11.1", info) | ("1.3.6.1.5.5.7.11.2", info) => {
+                Field::new("Semantics", semantics(info.as_ref())?)
+            }
+            (_, info) => Field::new(
+                &oid::name(&id),
+                info.map(|info| hex(info.data)).unwrap_or_default(),
+            ),
+        };
+        fields.push(field);
+    }
+    Some(fields)
+}
+
+/// A monetary limit, as a currency and an amount times a power of ten.
+fn limit(info: &Any) -> Option<String> {
+    let value = elements(info.data)?;
+    let currency = match value.first()?.tag().0 {
+        // The currency can also be given as its ISO 4217 number
+        2 => unsigned(value.first()?)?.to_string(),
+        _ => String::from_utf8_lossy(value.first()?.data).into_owned(),
+    };
+    let amount = unsigned(value.get(1)?)?;
+    let exponent = unsigned(value.get(2)?)?;
+    let amount = match 10u64
+        .checked_pow(exponent as u32)
+        .and_then(|scale| amount.checked_mul(scale))
+    {
+        Some(amount) => amount.to_string(),
+        None => format!("{} × 10^{}", amount, exponent),
+    };
+    Some(format!("{} {}", amount, currency))
+}
+
+/// Where the PKI disclosure statements are, by language.
+fn disclosure_statements(info: &Any) -> Option<Vec<Field>> {
+    elements(info.data)?
+        .iter()
+        .map(|statement| {
+            let statement = elements(statement.data)?;
+            Some(Field::new(
+                &String::from_utf8_lossy(statement.get(1)?.data),
+                String::from_utf8_lossy(statement.first()?.data),
+            ))
+        })
+        .collect()
+}
+
+/// The roles of a payment service provider and the national competent authority that granted them.
+fn psd2(info: &Any) -> Option<Vec<Field>> {
+    let fields = elements(info.data)?;
+    let roles = elements(fields.first()?.data)?
+        .iter()
+        .map(|role| {
+            let role = elements(role.data)?;
+            let id = Oid::new(Cow::Borrowed(role.first()?.data)).to_id_string();
+            let name = String::from_utf8_lossy(role.get(1)?.data);
+            Some(match PSP_ROLES.iter().find(|(known, _)| *known == id) {
+                Some((_, description)) => format!("{} ({})", name, description),
+                None => name.into_owned(),
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(vec![
+        Field::new("Roles", roles.join(", ")),
+        Field::new("NCA name", String::from_utf8_lossy(fields.get(1)?.data)),
+        Field::new("NCA ID", String::from_utf8_lossy(fields.get(2)?.data)),
+    ])
+}
+
+/// The semantics identifier of RFC 3739, which tells how to read the subject's identifiers.
+fn semantics(info: Option<&Any>) -> Option<String> {
+    let identifier = match info {
+        Some(info) => elements(info.data)?
+            .into_iter()
+            .find(|field| field.tag().0 == 6),
+        None => None,
+    };
+    match identifier {
+        Some(identifier) => {
+            let id = Oid::new(Cow::Borrowed(identifier.data));
+            Some(
+                match SEMANTICS
+                    .iter()
+                    .find(|(known, _)| *known == id.to_id_string())
+                {
+                    Some((_, name)) => String::from(*name),
+                    None => oid::name(&id),
+                },
+            )
+        }
+        None => Some(String::from("none given")),
+    }
+}
+
+/// A SEQUENCE OF OID, by name where the names are known.
+fn named(info: &Any, names: &[(&str, &str)]) -> Option<String> {
+    Some(
+        elements(info.data)?
+            .iter()
+            .map(|oid| {
+                let oid = Oid::new(Cow::Borrowed(oid.data));
+                match names.iter().find(|(known, _)| *known == oid.to_id_string()) {
+                    Some((_, name)) => String::from(*name),
+                    None => oid::name(&oid),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{statements, QC_STATEMENTS};
+
+    const CERT: &[u8] = include_bytes!("../resources/psd2-qwac.crt");
+
+    #[test]
+    fn should_decode_the_psd2_roles_and_qc_type() {
+        let (_, pem) = parse_x509_pem(CERT).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+        let extension = cert
+            .extensions()
+            .iter()
+            .find(|extension| extension.oid.to_id_string() == QC_STATEMENTS)
+            .unwrap();
+
+        let fields = statements(extension.value).unwrap();
+        let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "Compliance",
+                "Transaction limit",
+                "Retention period",
+                "PKI disclosure statements",
+                "Type",
+                "PSD2",
+                "Semantics",
+            ]
+        );
+        assert_eq!(fields[1].value, "50000 EUR");
+        assert_eq!(fields[4].value, "website authentication");
+        assert_eq!(
+            fields[5].fields[0].value,
+            "PSP_AI (account information), PSP_PI (payment initiation)"
+        );
+        assert_eq!(fields[5].fields[2].value, "DE-BAFIN");
+        assert_eq!(fields[6].value, "legal person");
+    }
+
+    #[test]
+    fn should_show_a_statement_it_does_not_know_by_oid() {
+        // A QCStatement of 1.2.3.4 with an INTEGER of 1
+        let value = [
+            0x30, 0x0a, 0x30, 0x08, 0x06, 0x03, 0x2a, 0x03, 0x04, 0x02, 0x01, 0x01,
+        ];
+
+        let fields = statements(&value).unwrap();
+
+        assert_eq!(fields[0].name, "1.2.3.4");
+        assert_eq!(fields[0].value, "01");
+    }
+}
@@ -0,0 +1,79 @@
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use quinn::crypto::rustls::{HandshakeData, QuicClientConfig};
+use rustls::pki_types::CertificateDer;
+
+use crate::proxy;
+use crate::remote::{self, ConnectOptions, Handshake, Server, Session};
+
+/// Performs a QUIC handshake with the server, offering HTTP/3, and returns
+/// the chain it presented. Some CDNs serve a different chain on UDP/443.
+pub fn handshake(
+    server: &Server,
+    options: &ConnectOptions,
+) -> Result<Handshake, Box<dyn std::error::Error>> {
+    if proxy::for_host(options.proxy.as_ref(), &options.no_proxy, &server.host).is_some() {
+        let err_msg =
+            "Error: QUIC cannot be tunnelled through a proxy, set NO_PROXY for this host.";
+        return Err(String::from(err_msg).into());
+    }
+
+    let address = match server.address {
+        Some(address) => address,
+        None => remote::resolve(&server.host, server.port, options.family)?[0],
+    };
+    let alpn = [String::from("h3")];
+    let (config, client_cert_request) =
+        remote::client_config(options, &[&rustls::version::TLS13], &alpn)?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let (chain, alpn) = runtime.block_on(async {
+        let bind: SocketAddr = match address.is_ipv4() {
+            true => "0.0.0.0:0".parse()?,
+            false => "[::]:0".parse()?,
+        };
+        let mut endpoint = quinn::Endpoint::client(bind)?;
+        let config = QuicClientConfig::try_from(config)?;
+        endpoint.set_default_client_config(quinn::ClientConfig::new(Arc::new(config)));
+
+        let connecting = endpoint.connect(SocketAddr::new(address, server.port), &server.host)?;
+        let connection = match options.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, connecting)
+                .await
+                .map_err(|_| format!("Error: QUIC handshake with {} timed out", server))??,
+            None => connecting.await?,
+        };
+
+        let chain = connection
+            .peer_identity()
+            .and_then(|identity| identity.downcast::<Vec<CertificateDer<'static>>>().ok())
+            .map(|chain| chain.iter().map(|cert| cert.to_vec()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let alpn = connection
+            .handshake_data()
+            .and_then(|data| data.downcast::<HandshakeData>().ok())
+            .and_then(|data| data.protocol)
+            .map(|protocol| String::from_utf8_lossy(&protocol).into_owned());
+        connection.close(0u32.into(), b"done");
+
+        Ok::<_, Box<dyn std::error::Error>>((chain, alpn))
+    })?;
+
+    if chain.is_empty() {
+        return Err(format!("Error: {} did not present a certificate over QUIC", server).into());
+    }
+    // quinn doesn't expose the negotiated cipher suite
+    let mut session = Session {
+        version: String::from("TLS 1.3 (QUIC)"),
+        cipher_suite: String::from("not reported over QUIC"),
+        alpn,
+        ..Session::default()
+    };
+    client_cert_request.record(&mut session);
+
+    Ok(Handshake { chain, session })
+}
@@ -0,0 +1,99 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+use x509_parser::pem::Pem;
+use x509_parser::prelude::FromDer;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+use crate::oid;
+use crate::trust_store::key_type;
+
+/// An RFC 7250 raw public key: a SubjectPublicKeyInfo on its own, which
+/// DTLS and IoT deployments use in place of a certificate.
+pub struct RawKey {
+    pub location: String,
+    pub algorithm: String,
+    pub key: String,
+    /// The base64 SHA-256 of the SubjectPublicKeyInfo, as HPKP and curl's `--pinnedpubkey` take it.
+    pub pin: String,
+    /// The hex SHA-256 of the SubjectPublicKeyInfo, as a `3 1 1` TLSA record holds it.
+    pub sha256: String,
+}
+
+/// The DER of the public keys in a file, whether it is one in binary or a
+/// series of `PUBLIC KEY` PEM blocks.
+pub fn find(bytes: &[u8]) -> Vec<Vec<u8>> {
+    if is_public_key(bytes) {
+        return vec![bytes.to_vec()];
+    }
+    Pem::iter_from_buffer(bytes)
+        .filter_map(Result::ok)
+        .filter(|pem| pem.label == "PUBLIC KEY")
+        .map(|pem| pem.contents)
+        .collect()
+}
+
+/// A certificate never parses as a SubjectPublicKeyInfo, as its first field
+/// is a SEQUENCE where an algorithm's OID would be.
+fn is_public_key(bytes: &[u8]) -> bool {
+    matches!(SubjectPublicKeyInfo::from_der(bytes), Ok((rest, _)) if rest.is_empty())
+}
+
+pub fn read(source: &str, der: &[u8]) -> Result<RawKey, Box<dyn std::error::Error>> {
+    let (_, spki) = SubjectPublicKeyInfo::from_der(der)
+        .map_err(|_| format!("Error: {} is not a valid public key.", source))?;
+    let hash = digest(&SHA256, der);
+    Ok(RawKey {
+        location: String::from(source),
+        algorithm: oid::name(&spki.algorithm.algorithm),
+        key: key_type(&spki),
+        pin: STANDARD.encode(hash.as_ref()),
+        sha256: hash
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+
+    use super::{find, read};
+    use crate::test::google_der;
+
+    const KEY: &[u8] = include_bytes!("../resources/raw-key.pem");
+
+    #[test]
+    fn should_decode_the_key_and_its_pins() {
+        let der = find(KEY);
+        let key = read("raw-key.pem", &der[0]).unwrap();
+
+        assert_eq!(der.len(), 1);
+        assert_eq!(key.algorithm, "id-ecPublicKey");
+        assert_eq!(key.key, "EC P-256");
+        assert_eq!(key.pin, "2ZUv5Vyy15HT+pAm2114/2XbUzS8i+a5KVJozgyP6Ds=");
+        assert_eq!(
+            key.sha256,
+            "d9952fe55cb2d791d3fa9026db5d78ff65db5334bc8be6b9295268ce0c8fe83b"
+        );
+    }
+
+    #[test]
+    fn should_find_a_key_in_binary() {
+        let der = find(KEY);
+
+        assert_eq!(find(&der[0]), der);
+    }
+
+    #[test]
+    fn should_not_take_a_certificate_for_a_key() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        assert!(find(&der).is_empty());
+        assert!(find(cert.public_key().raw).len() == 1);
+    }
+}
@@ -0,0 +1,612 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::ResolvesClientCert;
+use rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::sign::CertifiedKey;
+use rustls::{
+    ClientConfig, ClientConnection, DigitallySignedStruct, ProtocolVersion, SignatureScheme,
+};
+
+use x509_parser::prelude::{FromDer, X509Name};
+
+use crate::proxy::{self, Proxy, ProxyKind};
+
+/// A TLS server to fetch a chain from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Server {
+    pub host: String,
+    pub port: u16,
+    /// Connect to this address instead of whatever `host` resolves to,
+    /// so that each of a host's addresses can be checked separately.
+    pub address: Option<IpAddr>,
+}
+
+impl fmt::Display for Server {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.host.contains(':') {
+            true => write!(f, "[{}]:{}", self.host, self.port)?,
+            false => write!(f, "{}:{}", self.host, self.port)?,
+        }
+        if let Some(address) = self.address {
+            write!(f, " ({})", address)?;
+        }
+        Ok(())
+    }
+}
+
+/// Restricts connections to IPv4 (`-4`) or IPv6 (`-6`) addresses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    pub fn matches(self, address: &IpAddr) -> bool {
+        match self {
+            Family::V4 => address.is_ipv4(),
+            Family::V6 => address.is_ipv6(),
+        }
+    }
+}
+
+/// How to reach a TLS server.
+#[derive(Debug)]
+pub struct ConnectOptions {
+    /// Applies to connecting and to every read and write during the handshake.
+    pub timeout: Option<Duration>,
+    pub family: Option<Family>,
+    /// Protocols offered with ALPN, from `--alpn`.
+    pub alpn: Vec<String>,
+    /// From `--proxy`, or `HTTPS_PROXY` or `ALL_PROXY` when that isn't given.
+    pub proxy: Option<Proxy>,
+    /// The proxy for plain `http://` requests, from `--proxy`, `HTTP_PROXY` or `ALL_PROXY`.
+    pub http_proxy: Option<Proxy>,
+    /// Hosts to reach directly even when a proxy is set, from `NO_PROXY`.
+    pub no_proxy: Vec<String>,
+    /// Handshake over QUIC rather than TCP, from `--quic`.
+    pub quic: bool,
+    /// The certificate and key to authenticate with if the server asks,
+    /// loaded from `--client-cert` and `--client-key`.
+    pub identity: Option<Arc<CertifiedKey>>,
+    /// Speak SMTP up to STARTTLS before the handshake, for mail servers.
+    pub starttls: bool,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> ConnectOptions {
+        ConnectOptions {
+            timeout: Some(Duration::from_secs(10)),
+            family: None,
+            alpn: vec![String::from("h2"), String::from("http/1.1")],
+            proxy: None,
+            http_proxy: None,
+            no_proxy: Vec::new(),
+            quic: false,
+            identity: None,
+            starttls: false,
+        }
+    }
+}
+
+/// What was negotiated during a handshake, as these usually
+/// need checking together with the certificate.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Session {
+    pub version: String,
+    pub cipher_suite: String,
+    pub alpn: Option<String>,
+    pub client_cert_requested: bool,
+    /// Whether we answered the request with `--client-cert`.
+    pub client_cert_sent: bool,
+    /// The CA names the server listed in its CertificateRequest, if any.
+    pub client_ca_names: Vec<String>,
+}
+
+/// Everything learned from a handshake with a server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Handshake {
+    /// The DER encoded chain presented by the server, leaf first.
+    pub chain: Vec<Vec<u8>>,
+    pub session: Session,
+}
+
+/// What the server sent in its CertificateRequest.
+#[derive(Debug, Default)]
+struct Request {
+    requested: bool,
+    ca_names: Vec<Vec<u8>>,
+}
+
+/// Answers a CertificateRequest with the identity given, if any,
+/// noting what the server asked for either way.
+#[derive(Debug)]
+pub struct ClientCertRequest {
+    identity: Option<Arc<CertifiedKey>>,
+    request: Mutex<Request>,
+}
+
+impl ClientCertRequest {
+    /// Fills in the client certificate details of a finished handshake.
+    pub fn record(&self, session: &mut Session) {
+        let request = self.request.lock().unwrap();
+        session.client_cert_requested = request.requested;
+        session.client_cert_sent = request.requested && self.identity.is_some();
+        session.client_ca_names = request
+            .ca_names
+            .iter()
+            .map(|name| name_to_string(name))
+            .collect();
+    }
+}
+
+impl ResolvesClientCert for ClientCertRequest {
+    fn resolve(
+        &self,
+        root_hint_subjects: &[&[u8]],
+        _: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        let mut request = self.request.lock().unwrap();
+        request.requested = true;
+        request.ca_names = root_hint_subjects
+            .iter()
+            .map(|name| name.to_vec())
+            .collect();
+        self.identity.clone()
+    }
+
+    fn has_certs(&self) -> bool {
+        self.identity.is_some()
+    }
+}
+
+/// Loads a client certificate chain and its private key from PEM,
+/// checking that the key belongs to the certificate.
+pub fn load_identity(
+    cert_pem: &[u8],
+    key_pem: &[u8],
+) -> Result<Arc<CertifiedKey>, Box<dyn std::error::Error>> {
+    let chain = CertificateDer::pem_slice_iter(cert_pem).collect::<Result<Vec<_>, _>>()?;
+    if chain.is_empty() {
+        return Err(String::from(
+            "Error: no PEM encoded certificate found in the client certificate.",
+        )
+        .into());
+    }
+    let key = PrivateKeyDer::from_pem_slice(key_pem)
+        .map_err(|error| format!("Error: could not read the client key: {}", error))?;
+
+    let identity =
+        CertifiedKey::from_der(chain, key, &ring::default_provider()).map_err(|error| {
+            format!(
+                "Error: could not use the client certificate and key: {}",
+                error
+            )
+        })?;
+    Ok(Arc::new(identity))
+}
+
+/// Renders a DER encoded distinguished name like `CN=..., O=...`.
+fn name_to_string(der: &[u8]) -> String {
+    match X509Name::from_der(der) {
+        Ok((_, name)) => name.to_string(),
+        Err(_) => String::from("<invalid name>"),
+    }
+}
+
+/// Accepts whatever chain the server presents.
+/// We are here to decode certificates, not to judge them, so an expired or
+/// self-signed chain must still make it through the handshake.
+/// Handshake signatures are still checked so the chain we report is the one
+/// the server actually holds the key for.
+#[derive(Debug)]
+pub struct AcceptAnyCert(pub CryptoProvider);
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: &ServerName<'_>,
+        _: &[u8],
+        _: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Performs a TLS handshake with the server, returning the chain it presented
+/// and what was negotiated.
+pub fn handshake(
+    server: &Server,
+    options: &ConnectOptions,
+) -> Result<Handshake, Box<dyn std::error::Error>> {
+    #[cfg(feature = "quic")]
+    if options.quic {
+        return crate::quic::handshake(server, options);
+    }
+
+    let versions = rustls::DEFAULT_VERSIONS;
+    let (config, client_cert_request) = client_config(options, versions, &options.alpn)?;
+
+    let server_name = ServerName::try_from(server.host.clone())?;
+    let mut connection = ClientConnection::new(Arc::new(config), server_name)?;
+    let mut socket = connect(server, options)?;
+    if options.starttls {
+        starttls(&mut socket, server)?;
+    }
+    while connection.is_handshaking() {
+        connection.complete_io(&mut socket)?;
+    }
+
+    let chain = match connection.peer_certificates() {
+        Some(chain) if !chain.is_empty() => chain.iter().map(|cert| cert.to_vec()).collect(),
+        _ => return Err(format!("Error: {} did not present a certificate", server).into()),
+    };
+    let mut session = Session {
+        version: connection
+            .protocol_version()
+            .map(version_name)
+            .unwrap_or_default(),
+        cipher_suite: connection
+            .negotiated_cipher_suite()
+            .map(|suite| match suite.suite().as_str() {
+                Some(name) => String::from(name),
+                None => format!("{:#06x}", u16::from(suite.suite())),
+            })
+            .unwrap_or_default(),
+        alpn: connection
+            .alpn_protocol()
+            .map(|protocol| String::from_utf8_lossy(protocol).into_owned()),
+        ..Session::default()
+    };
+    client_cert_request.record(&mut session);
+
+    Ok(Handshake { chain, session })
+}
+
+/// Asks a mail server to start TLS, as RFC 3207 has SMTP clients do.
+fn starttls(
+    socket: &mut (impl Read + Write),
+    server: &Server,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let greeting = smtp_reply(socket)?;
+    if !greeting.starts_with("220") {
+        let err_msg = format!(
+            "Error: {} did not greet us with SMTP: {}",
+            server,
+            greeting.trim()
+        );
+        return Err(err_msg.into());
+    }
+    socket.write_all(b"EHLO cert-decoder.invalid\r\n")?;
+    let extensions = smtp_reply(socket)?;
+    let offered = extensions.starts_with("250")
+        && extensions.lines().any(|line| {
+            line.get(4..)
+                .is_some_and(|name| name.trim().eq_ignore_ascii_case("STARTTLS"))
+        });
+    if !offered {
+        return Err(format!("Error: {} does not offer STARTTLS", server).into());
+    }
+    socket.write_all(b"STARTTLS\r\n")?;
+    let reply = smtp_reply(socket)?;
+    if !reply.starts_with("220") {
+        let err_msg = format!("Error: {} refused STARTTLS: {}", server, reply.trim());
+        return Err(err_msg.into());
+    }
+    Ok(())
+}
+
+/// Reads an SMTP reply, whose last line has a space rather than a dash after
+/// the code. It is read a byte at a time so none of the handshake is read too.
+fn smtp_reply(socket: &mut impl Read) -> io::Result<String> {
+    let mut reply = Vec::new();
+    let mut byte = [0];
+    loop {
+        if socket.read(&mut byte)? == 0 {
+            let err_msg = "Error: the server closed the connection during SMTP";
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, err_msg));
+        }
+        reply.push(byte[0]);
+        if byte[0] == b'\n' {
+            let line_start = reply[..reply.len() - 1]
+                .iter()
+                .rposition(|byte| *byte == b'\n')
+                .map_or(0, |newline| newline + 1);
+            if reply.get(line_start + 3) != Some(&b'-') {
+                return Ok(String::from_utf8_lossy(&reply).into_owned());
+            }
+        }
+    }
+}
+
+/// Builds a client config that accepts any server chain and answers client
+/// certificate requests with `--client-cert`, returning the resolver too
+/// so what the server asked for can be recorded after the handshake.
+pub fn client_config(
+    options: &ConnectOptions,
+    versions: &[&'static rustls::SupportedProtocolVersion],
+    alpn: &[String],
+) -> Result<(ClientConfig, Arc<ClientCertRequest>), Box<dyn std::error::Error>> {
+    let provider = ring::default_provider();
+    let client_cert_request = Arc::new(ClientCertRequest {
+        identity: options.identity.clone(),
+        request: Mutex::new(Request::default()),
+    });
+    let mut config = ClientConfig::builder_with_provider(Arc::new(provider.clone()))
+        .with_protocol_versions(versions)?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCert(provider)))
+        .with_client_cert_resolver(client_cert_request.clone());
+    config.alpn_protocols = alpn
+        .iter()
+        .map(|protocol| protocol.clone().into_bytes())
+        .collect();
+
+    Ok((config, client_cert_request))
+}
+
+fn version_name(version: ProtocolVersion) -> String {
+    match version {
+        ProtocolVersion::TLSv1_2 => String::from("TLS 1.2"),
+        ProtocolVersion::TLSv1_3 => String::from("TLS 1.3"),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Resolves the addresses of `host`, keeping only those of the requested family.
+pub fn resolve(host: &str, port: u16, family: Option<Family>) -> io::Result<Vec<IpAddr>> {
+    let mut addresses = Vec::new();
+    for address in (host, port).to_socket_addrs()? {
+        let ip = address.ip();
+        if family.is_none_or(|family| family.matches(&ip)) && !addresses.contains(&ip) {
+            addresses.push(ip);
+        }
+    }
+
+    if addresses.is_empty() {
+        let err_msg = match family {
+            Some(Family::V4) => format!("Error: {} has no IPv4 address", host),
+            Some(Family::V6) => format!("Error: {} has no IPv6 address", host),
+            None => format!("Error: {} did not resolve to any address", host),
+        };
+        return Err(io::Error::new(io::ErrorKind::NotFound, err_msg));
+    }
+    Ok(addresses)
+}
+
+/// Opens a connection to the server, through the proxy if one applies.
+pub fn connect(server: &Server, options: &ConnectOptions) -> io::Result<TcpStream> {
+    let proxy = proxy::for_host(options.proxy.as_ref(), &options.no_proxy, &server.host);
+    connect_through(server, proxy, options)
+}
+
+/// Opens a connection to the server, tunnelled through `proxy` if there is one.
+pub fn connect_through(
+    server: &Server,
+    proxy: Option<&Proxy>,
+    options: &ConnectOptions,
+) -> io::Result<TcpStream> {
+    match proxy {
+        Some(proxy) => {
+            // A pinned address has to be passed on, or the proxy would pick one itself,
+            // and so does one of the family asked for with -4 or -6
+            let address = match (server.address, options.family) {
+                (Some(address), _) => Some(address),
+                (None, Some(_)) if proxy.kind == (ProxyKind::Socks5 { remote_dns: true }) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Error: -4 and -6 cannot be used with a socks5h:// proxy, which resolves host names itself, use socks5:// instead",
+                    ));
+                }
+                (None, Some(family)) => Some(resolve(&server.host, server.port, Some(family))?[0]),
+                (None, None) => None,
+            };
+            let mut socket = connect_to_proxy(proxy, options)?;
+            match address {
+                Some(address) => proxy.tunnel(&mut socket, &address.to_string(), server.port)?,
+                None => proxy.tunnel(&mut socket, &server.host, server.port)?,
+            }
+            Ok(socket)
+        }
+        None => {
+            let addresses = match server.address {
+                Some(address) => vec![address],
+                None => resolve(&server.host, server.port, options.family)?,
+            };
+            connect_any(&addresses, server.port, options)
+        }
+    }
+}
+
+/// Opens a connection to the proxy itself, for requests it forwards whole.
+pub fn connect_to_proxy(proxy: &Proxy, options: &ConnectOptions) -> io::Result<TcpStream> {
+    let addresses = resolve(&proxy.host, proxy.port, None)?;
+    connect_any(&addresses, proxy.port, options)
+}
+
+/// Tries each address in turn until one accepts the connection.
+fn connect_any(addresses: &[IpAddr], port: u16, options: &ConnectOptions) -> io::Result<TcpStream> {
+    let mut last_error = None;
+    for address in addresses {
+        let address = SocketAddr::new(*address, port);
+        let result = match options.timeout {
+            Some(timeout) => TcpStream::connect_timeout(&address, timeout),
+            None => TcpStream::connect(address),
+        };
+        match result {
+            Ok(socket) => {
+                socket.set_read_timeout(options.timeout)?;
+                socket.set_write_timeout(options.timeout)?;
+                return Ok(socket);
+            }
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    Err(last_error.expect("addresses are never empty"))
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::{self, Cursor, Read, Write};
+
+    use super::{connect, load_identity, name_to_string, starttls, ConnectOptions, Family, Server};
+    use crate::proxy::Proxy;
+
+    /// A mail server that answers from a script, noting what it was sent.
+    struct Smtp {
+        replies: Cursor<Vec<u8>>,
+        sent: Vec<u8>,
+    }
+
+    impl Read for Smtp {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for Smtp {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.sent.write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn smtp(replies: &str) -> Smtp {
+        Smtp {
+            replies: Cursor::new(replies.as_bytes().to_vec()),
+            sent: Vec::new(),
+        }
+    }
+
+    const SELF_SIGNED_CERT: &[u8] = include_bytes!("../resources/self-signed.crt");
+    const SELF_SIGNED_KEY: &[u8] = include_bytes!("../resources/self-signed.key");
+
+    #[test]
+    fn should_speak_smtp_up_to_starttls() {
+        let server = Server {
+            host: String::from("mx1.example.com"),
+            port: 25,
+            address: None,
+        };
+        let mut socket = smtp(
+            "220 mx1.example.com ESMTP\r\n250-mx1.example.com\r\n250-PIPELINING\r\n250-STARTTLS\r\n250 8BITMIME\r\n220 2.0.0 Ready to start TLS\r\n\x16\x03\x03",
+        );
+        starttls(&mut socket, &server).unwrap();
+        assert_eq!(socket.sent, b"EHLO cert-decoder.invalid\r\nSTARTTLS\r\n");
+        // The first bytes of the handshake are left for TLS
+        assert_eq!(
+            socket.replies.position(),
+            socket.replies.get_ref().len() as u64 - 3
+        );
+
+        let mut socket =
+            smtp("220 mx1.example.com ESMTP\r\n250-mx1.example.com\r\n250 8BITMIME\r\n");
+        assert_eq!(
+            starttls(&mut socket, &server).err().unwrap().to_string(),
+            "Error: mx1.example.com:25 does not offer STARTTLS"
+        );
+    }
+
+    #[test]
+    fn should_load_matching_client_certificate_and_key() {
+        let identity = load_identity(SELF_SIGNED_CERT, SELF_SIGNED_KEY).unwrap();
+
+        assert_eq!(identity.cert.len(), 1);
+    }
+
+    #[test]
+    fn should_error_if_client_key_does_not_match_certificate() {
+        let cert = include_bytes!("../resources/google.com.crt");
+
+        let result = load_identity(cert, SELF_SIGNED_KEY);
+
+        assert!(format!("{}", result.err().unwrap())
+            .starts_with("Error: could not use the client certificate and key"));
+    }
+
+    #[test]
+    fn should_error_if_client_key_is_missing() {
+        let result = load_identity(SELF_SIGNED_CERT, SELF_SIGNED_CERT);
+
+        assert!(format!("{}", result.err().unwrap())
+            .starts_with("Error: could not read the client key"));
+    }
+
+    #[test]
+    fn should_render_ca_names_from_certificate_request() {
+        // SEQUENCE { SET { SEQUENCE { OID commonName, UTF8String "Test CA" } } }
+        let der = [
+            0x30, 0x12, 0x31, 0x10, 0x30, 0x0e, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x07, b'T',
+            b'e', b's', b't', b' ', b'C', b'A',
+        ];
+
+        assert_eq!(name_to_string(&der), "CN=Test CA");
+        assert_eq!(name_to_string(&[0x30]), "<invalid name>");
+    }
+
+    #[test]
+    fn should_refuse_a_family_the_proxy_would_not_keep_to() {
+        let options = ConnectOptions {
+            family: Some(Family::V6),
+            proxy: Some(Proxy::parse("socks5h://127.0.0.1:1").unwrap()),
+            ..ConnectOptions::default()
+        };
+        let server = Server {
+            host: String::from("example.com"),
+            port: 443,
+            address: None,
+        };
+
+        assert!(connect(&server, &options)
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("Error: -4 and -6 cannot be used with a socks5h:// proxy"));
+    }
+}
@@ -0,0 +1,3399 @@
+use std::collections::hash_map::{self, HashMap};
+use std::fmt;
+use std::ops::Range;
+
+use x509_parser::certificate::{TbsCertificate, X509Certificate};
+use x509_parser::parse_x509_certificate;
+use x509_parser::prelude::FromDer;
+
+use crate::apk;
+use crate::archive;
+use crate::attribute_certificate::{self, AttributeCertificate};
+use crate::authenticode;
+use crate::cloud;
+use crate::cmp::{self, PkiMessage};
+use crate::cms::{self, SignedData, Signer, Verification};
+use crate::compliance;
+use crate::compression;
+use crate::conformance::{self, Strictness};
+use crate::covers;
+use crate::critical;
+use crate::crl::{self, Crl, EffectiveCrl};
+use crate::ct_entries;
+use crate::dn;
+use crate::encoding::{self, InputEncoding};
+use crate::expiry::ExpiryFilter;
+use crate::extensions;
+use crate::idn;
+use crate::issuers;
+use crate::jwt;
+use crate::k8s;
+use crate::keystore;
+use crate::ldap;
+use crate::lifetime::{self, MaxValidity};
+use crate::lint;
+use crate::matter;
+use crate::nss;
+use crate::ocsp::{self, OcspRequest};
+use crate::options::Options;
+use crate::partial;
+use crate::pem;
+use crate::pkcs11;
+use crate::precertificate;
+use crate::provisioning::{self, Profile};
+use crate::psl::{self, SuffixList};
+use crate::raw_key::{self, RawKey};
+use crate::remote::{ConnectOptions, Handshake, Server, Session};
+use crate::scan;
+use crate::sct::{self, Log, SctCheck};
+use crate::server_config;
+use crate::smime;
+use crate::sort::{self, GroupBy};
+use crate::ssh::{self, HostKeyCheck, SshCertificate, SshKey};
+use crate::store::{NamedCertificate, StoreOptions};
+use crate::target::Target;
+use crate::timestamp::{self, TstInfo};
+use crate::trust_path::{self, Anchor, TrustPath};
+use crate::FileProcessor;
+
+/// A single decoded certificate and where it was found.
+pub struct Entry {
+    pub location: String,
+    pub der: Vec<u8>,
+    /// For the leaf of a chain fetched from a server, what the handshake negotiated.
+    pub session: Option<Session>,
+    /// For the certificate that signed a CMS structure, how it signed.
+    pub signer: Option<Signer>,
+    /// For the certificate of a TSA, what it vouched for in a timestamp token.
+    pub timestamp: Option<TstInfo>,
+    /// For a certificate in the key rotation lineage of an APK, what it is still trusted with.
+    pub capabilities: Option<Vec<&'static str>>,
+    /// The SCTs embedded in the certificate, and how they checked out.
+    pub scts: Vec<SctCheck>,
+    /// For the leaf of what was found in one place, the paths to a trust anchor
+    /// when `--explain-trust` asked for them, the one taken first.
+    pub trust: Vec<TrustPath>,
+    /// The other places the same certificate was found, when `--dedupe` merged them.
+    pub duplicates: Vec<String>,
+}
+
+impl Entry {
+    /// Only certificates that parse make it into an entry, so later
+    /// stages can re-parse the DER without having to handle errors again.
+    fn new(location: String, der: Vec<u8>) -> Result<Entry, Box<dyn std::error::Error>> {
+        if let Err(error) = parse_x509_certificate(&der) {
+            return Err(partial::explain(&location, &der, error).into());
+        }
+        Ok(Entry {
+            location,
+            der,
+            session: None,
+            signer: None,
+            timestamp: None,
+            capabilities: None,
+            scts: Vec::new(),
+            trust: Vec::new(),
+            duplicates: Vec::new(),
+        })
+    }
+
+    pub fn certificate(&self) -> X509Certificate<'_> {
+        let (_, cert) =
+            parse_x509_certificate(&self.der).expect("entries are validated on creation");
+        cert
+    }
+}
+
+/// A target (or a file within a directory target) that could not be decoded.
+pub struct Failure {
+    pub location: String,
+    pub error: Box<dyn std::error::Error>,
+}
+
+/// The combined result of decoding every target.
+#[derive(Default)]
+pub struct Report {
+    pub entries: Vec<Entry>,
+    pub failures: Vec<Failure>,
+    pub crls: Vec<Crl>,
+    /// What each delta CRL and its base revoke together.
+    pub effective_crls: Vec<EffectiveCrl>,
+    pub profiles: Vec<Profile>,
+    pub attribute_certificates: Vec<AttributeCertificate>,
+    pub raw_keys: Vec<RawKey>,
+    pub ocsp_requests: Vec<OcspRequest>,
+    pub cmp_messages: Vec<PkiMessage>,
+    pub ssh_certificates: Vec<SshCertificate>,
+    pub ssh_keys: Vec<SshKey>,
+    /// Problems worth pointing out that aren't failures to decode.
+    pub warnings: Vec<String>,
+    /// When the report was built, to tell what has expired.
+    now: i64,
+    /// Whether to show the final TBS certificate of each precertificate.
+    reconstruct_tbs: bool,
+    /// The CT logs to check embedded SCTs against, if they are to be checked.
+    ct_logs: Option<Vec<Log>>,
+    /// The trust anchors to build paths to, if paths are to be built.
+    anchors: Option<Vec<Anchor>>,
+    /// Whether to explain every step of a path, rather than only name its anchor.
+    explain_trust: bool,
+    /// The certificate policies that have to be valid for the leaf of each path.
+    required_policies: Vec<String>,
+    /// Whether to warn about where certificates stray from RFC 5280 and the
+    /// Baseline Requirements.
+    lint: bool,
+    /// Whether certificates that deviate from DER and RFC 5280 are warned about or refused.
+    strictness: Strictness,
+    /// The algorithm rules to check certificates against, if any.
+    profile: Option<compliance::Profile>,
+    /// The names to say whether each certificate that isn't a CA covers.
+    covers: Vec<String>,
+    /// The serial numbers to look for in CRLs, keeping only those of what they revoke.
+    find_serials: Vec<Vec<u8>>,
+    /// Whether to summarize the registrable domains of each certificate that isn't a CA.
+    domains: bool,
+    /// The Public Suffix List that names are matched and summarized with.
+    suffixes: SuffixList,
+    /// Which certificates were kept for being expired, not yet valid or expiring soon.
+    expiry_filter: ExpiryFilter,
+    /// What certificates are gathered under a heading by, if anything.
+    group_by: Option<GroupBy>,
+    /// The entries found in each place, the first of which a path is built from.
+    groups: Vec<Range<usize>>,
+}
+
+impl Report {
+    /// Decodes every target. Failures are recorded rather than returned so
+    /// that one bad target doesn't hide the results for all the others.
+    pub fn build(processor: &impl FileProcessor, targets: &[Target], options: &Options) -> Report {
+        // Work out which servers each remote target stands for
+        let servers: Vec<Result<Vec<Server>, Box<dyn std::error::Error>>> = targets
+            .iter()
+            .map(|target| match target {
+                Target::Remote { host, port } => servers_for(processor, host, *port, options),
+                _ => Ok(Vec::new()),
+            })
+            .collect();
+
+        // Handshakes are slow, so all servers are contacted up front and concurrently
+        let all: Vec<&Server> = servers.iter().flatten().flatten().collect();
+        let mut handshakes = scan::fetch_all(&all, &options.scan, |server| {
+            processor.handshake(server, &options.connect)
+        })
+        .into_iter();
+
+        let mut report = Report {
+            now: processor.now(),
+            reconstruct_tbs: options.reconstruct_tbs,
+            ct_logs: Some(options.ct_logs.clone().unwrap_or_else(sct::bundled)),
+            anchors: options.anchors.clone(),
+            explain_trust: options.explain_trust,
+            required_policies: options.required_policies.clone(),
+            lint: options.lint,
+            strictness: options.strictness,
+            profile: options.profile,
+            covers: options.covers.clone(),
+            find_serials: options.find_serials.clone(),
+            domains: options.domains,
+            suffixes: options.suffixes.clone().unwrap_or_default(),
+            expiry_filter: options.expiry_filter,
+            group_by: options.group_by,
+            ..Report::default()
+        };
+        for (target, servers) in targets.iter().zip(servers) {
+            match target {
+                Target::Path(path) if nss::is_database(processor, path) => {
+                    let source = format!("NSS database {}", path);
+                    let result = processor
+                        .read_nss(path)
+                        .and_then(|items| decode_named(&source, None, items));
+                    report.add(path, result);
+                }
+                Target::Path(path) if processor.is_dir(path) => report.add_dir(processor, path),
+                Target::Path(path) if options.server_config => {
+                    report.add_server_config(processor, path)
+                }
+                Target::Path(path) => match processor.read(path) {
+                    Ok(bytes) => report.add_file(path, &bytes, options),
+                    Err(error) => report.add(path, Err(error)),
+                },
+                Target::Stdin | Target::Clipboard => {
+                    let source = target.to_string();
+                    let read = match target {
+                        Target::Stdin => processor.read_stdin(),
+                        _ => processor.read_clipboard(),
+                    };
+                    match read {
+                        Ok(bytes) => report.add_file(&source, &bytes, options),
+                        Err(error) => report.add(&source, Err(error)),
+                    }
+                }
+                Target::Store(store) => {
+                    let result = processor
+                        .read_store(store)
+                        .and_then(|chain| decode_store(store, chain));
+                    report.add(&target.to_string(), result);
+                }
+                Target::Keychain { keychain, label } => {
+                    let result = processor.read_keychain(keychain).and_then(|items| {
+                        decode_named(&target.to_string(), label.as_deref(), items)
+                    });
+                    report.add(&target.to_string(), result);
+                }
+                Target::Pkcs11(token) => {
+                    let result = pkcs11::pin(processor, token)
+                        .and_then(|pin| processor.read_pkcs11(token, pin.as_deref()))
+                        .and_then(|items| decode_named(&target.to_string(), None, items));
+                    report.add(&target.to_string(), result);
+                }
+                Target::Cloud(cloud) => {
+                    let result = cloud::read(processor, cloud, &options.connect)
+                        .and_then(|items| decode_named(&target.to_string(), None, items));
+                    report.add(&target.to_string(), result);
+                }
+                Target::Ldap(ldap) => {
+                    let result = ldap::read(processor, ldap, &options.connect).and_then(|found| {
+                        report.warnings.extend(found.warnings);
+                        decode_named(&target.to_string(), None, found.certificates)
+                    });
+                    report.add(&target.to_string(), result);
+                }
+                Target::Remote { .. } => match servers {
+                    Ok(servers) => report.add_servers(target, &servers, &mut handshakes),
+                    Err(error) => report.add(&target.to_string(), Err(error)),
+                },
+            }
+        }
+        report.build_trust_paths(processor, &options.connect);
+        // Only whole lists can be combined, not those searched for serial numbers
+        if options.find_serials.is_empty() {
+            let (effective_crls, warnings) = crl::combine(&report.crls);
+            report.effective_crls = effective_crls;
+            report.warnings.extend(warnings);
+        }
+        // Linting includes the Baseline Requirements' limit, unless another was given
+        let cabf = options.lint.then_some(&MaxValidity::Cabf);
+        if let Some(max) = options.max_validity.as_ref().or(cabf) {
+            report.check_validity_periods(max, report.now);
+        }
+        if options.lint {
+            let certificates: Vec<(&str, X509Certificate)> = report
+                .entries
+                .iter()
+                .map(|entry| (entry.location.as_str(), entry.certificate()))
+                .collect();
+            let issues = lint::sequential(&certificates);
+            drop(certificates);
+            report
+                .warnings
+                .extend(issues.iter().map(|issue| format!("Warning: {}.", issue)));
+        }
+        // Only once paths are built, so those kept still have their issuers
+        if options.expiry_filter.is_set() {
+            let now = report.now;
+            report
+                .entries
+                .retain(|entry| options.expiry_filter.matches(&entry.certificate(), now));
+            report.groups.clear();
+        }
+        if options.dedupe {
+            report.dedupe();
+        }
+        if options.sort_by.is_some() || options.group_by.is_some() {
+            sort::arrange(&mut report.entries, options.sort_by, options.group_by);
+            report.groups.clear();
+        }
+        report
+    }
+
+    /// Merges certificates found in several places into the first of them, so a
+    /// wildcard certificate shared by many servers is shown once.
+    fn dedupe(&mut self) {
+        let mut first: HashMap<String, usize> = HashMap::new();
+        let mut kept: Vec<Entry> = Vec::new();
+        for entry in self.entries.drain(..) {
+            match first.entry(trust_path::fingerprint(&entry.der)) {
+                hash_map::Entry::Occupied(found) => {
+                    kept[*found.get()].duplicates.push(entry.location)
+                }
+                hash_map::Entry::Vacant(vacant) => {
+                    vacant.insert(kept.len());
+                    kept.push(entry);
+                }
+            }
+        }
+        self.entries = kept;
+        self.groups.clear();
+    }
+
+    fn add_servers(
+        &mut self,
+        target: &Target,
+        servers: &[Server],
+        handshakes: &mut impl Iterator<Item = Result<Handshake, String>>,
+    ) {
+        let mut leaves = Vec::new();
+        for server in servers {
+            let location = server.to_string();
+            let handshake = handshakes
+                .next()
+                .expect("a handshake is made with every server");
+            let result = handshake
+                .map_err(|error| error.into())
+                .and_then(|handshake| {
+                    leaves.extend(handshake.chain.first().cloned());
+                    let mut entries = decode_chain(&location, handshake.chain)?;
+                    entries[0].session = Some(handshake.session);
+                    Ok(entries)
+                });
+            self.add(&location, result);
+        }
+
+        leaves.sort();
+        leaves.dedup();
+        if leaves.len() > 1 {
+            self.warnings.push(format!(
+                "Warning: the addresses of {} did not all serve the same certificate.",
+                target
+            ));
+        }
+    }
+
+    /// Decodes a certificate file, the signers of a JAR or APK, or every certificate
+    /// in it if it is an archive, decompressing it first if need be.
+    fn add_file(&mut self, path: &str, bytes: &[u8], options: &Options) {
+        match compression::decompress(path, bytes) {
+            Ok(Some(decompressed)) => return self.add_file(path, &decompressed, options),
+            Ok(None) => {}
+            Err(error) => return self.add(path, Err(error)),
+        }
+        let explicit = options.k8s || options.input_encoding != InputEncoding::Auto;
+        if explicit || !archive::is_archive(bytes) {
+            let result = self.decode_file(path, bytes, options);
+            return self.add(path, result);
+        }
+        if apk::is_package(bytes) {
+            let result = self.decode_package(path, bytes);
+            return self.add(path, result);
+        }
+
+        let entries = match archive::entries(path, bytes) {
+            Ok(entries) => entries,
+            Err(error) => return self.add(path, Err(error)),
+        };
+        self.warnings.extend(entries.warnings);
+        let (found, failures) = (self.entries.len(), self.failures.len());
+        for (name, contents) in entries.files {
+            if archive::is_candidate(&name, &contents)
+                || contains_pem_certificate(&contents)
+                || keystore::is_java_keystore(&contents)
+                || keystore::is_pkcs12(&contents)
+            {
+                self.add_file(&format!("{}: {}", path, name), &contents, options);
+            }
+        }
+
+        if self.entries.len() == found && self.failures.len() == failures {
+            let error = format!("Error: no certificates found in archive {}.", path);
+            self.add(path, Err(error.into()));
+        }
+    }
+
+    /// Decodes a certificate file, or a keystore, JWT, JWKS, S/MIME message, PKCS#7 file,
+    /// timestamp, provisioning profile, attribute certificate, raw public key, CRL, OCSP
+    /// request, CMP message, OpenSSH certificate, CT log entries or signed Windows binary.
+    fn decode_file(
+        &mut self,
+        path: &str,
+        bytes: &[u8],
+        options: &Options,
+    ) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+        if options.k8s {
+            return decode_named(path, None, k8s::secret_certificates(path, bytes)?);
+        }
+        if smime::is_smime(bytes) {
+            return self.decode_signed(path, smime::read(path, bytes)?, None);
+        }
+        if authenticode::is_pe(bytes) || authenticode::is_msi(bytes) {
+            return self.decode_authenticode(path, bytes);
+        }
+        if timestamp::is_response(bytes) {
+            let token = timestamp::token(path, bytes)?;
+            return self.decode_signed(path, cms::read(path, &token)?, None);
+        }
+        if let Some(der) = cms::find(bytes) {
+            let signed = cms::read(path, &der)?;
+            let profile = signed
+                .content
+                .as_deref()
+                .and_then(|content| provisioning::read(path, content));
+            if let Some(profile) = profile {
+                return self.decode_profile(path, signed, profile);
+            }
+            return self.decode_signed(path, signed, options.detached_content.as_deref());
+        }
+        let attribute_certificates = attribute_certificate::find(bytes);
+        if !attribute_certificates.is_empty() {
+            self.add_attribute_certificates(path, attribute_certificates)?;
+            // Any certificates the holder's come with are in the same file
+            return match contains_pem_certificate(bytes) {
+                true => decode_pem(path, bytes),
+                false => Ok(Vec::new()),
+            };
+        }
+        let raw_keys = raw_key::find(bytes);
+        if !raw_keys.is_empty() {
+            self.add_raw_keys(path, raw_keys)?;
+            return match contains_pem_certificate(bytes) {
+                true => decode_pem(path, bytes),
+                false => Ok(Vec::new()),
+            };
+        }
+        let crls = crl::find(bytes);
+        if !crls.is_empty() {
+            self.add_crls(path, crls)?;
+            return match contains_pem_certificate(bytes) {
+                true => decode_pem(path, bytes),
+                false => Ok(Vec::new()),
+            };
+        }
+        if let Some(der) = ocsp::find(bytes) {
+            let request = ocsp::read(path, &der)?;
+            self.warnings.extend(request.warnings());
+            self.ocsp_requests.push(request);
+            return Ok(Vec::new());
+        }
+        if let Some(der) = cmp::find(bytes) {
+            let message = cmp::read(path, &der)?;
+            self.warnings.extend(message.warnings());
+            let certificates = message.certificates.clone();
+            self.cmp_messages.push(message);
+            return match certificates.is_empty() {
+                true => Ok(Vec::new()),
+                false => decode_named(path, None, certificates),
+            };
+        }
+        let ssh_certificates = ssh::find(bytes);
+        let ssh_keys = ssh::find_keys(bytes);
+        if !ssh_certificates.is_empty() || !ssh_keys.is_empty() {
+            self.add_ssh_certificates(path, ssh_certificates, ssh_keys, options)?;
+            return Ok(Vec::new());
+        }
+        if ct_entries::is_get_entries(bytes) {
+            let entries = ct_entries::read(path, bytes)?;
+            self.warnings.extend(entries.warnings);
+            return decode_named(path, None, entries.certificates);
+        }
+        if jwt::is_jwt_or_jwks(bytes) {
+            let chains = jwt::read(path, bytes)?;
+            self.warnings.extend(chains.warnings);
+            return decode_named(path, None, chains.certificates);
+        }
+        match options.input_encoding {
+            InputEncoding::Auto => {}
+            InputEncoding::Pem => return decode_pem(path, bytes),
+            encoding => {
+                let der = encoding::decode(encoding, bytes)
+                    .map_err(|error| format!("Error: could not decode {}: {}.", path, error))?;
+                return decode_chain(path, vec![der]);
+            }
+        }
+        if !keystore::is_java_keystore(bytes) && !keystore::is_pkcs12(bytes) {
+            // A certificate pasted from a log or LDAP often has no PEM armor
+            if !contains_pem_certificate(bytes) {
+                if let Some(der) = encoding::sniff(bytes) {
+                    return decode_chain(path, vec![der]);
+                }
+                // DER that fails to parse is better explained than taken for not being PEM
+                if bytes.starts_with(&[0x30, 0x82]) {
+                    return decode_chain(path, vec![bytes.to_vec()]);
+                }
+            }
+            return decode_pem(path, bytes);
+        }
+
+        let keystore = keystore::read(path, bytes, options.storepass.as_deref())?;
+        if !keystore.verified {
+            self.warnings.push(format!(
+                "Warning: the integrity of {} was not checked, give --storepass to check it.",
+                path
+            ));
+        }
+        decode_named(path, None, keystore.certificates)
+    }
+
+    /// Decodes the Authenticode signatures of a PE image or MSI, with their
+    /// countersignatures and timestamps.
+    fn decode_authenticode(
+        &mut self,
+        path: &str,
+        bytes: &[u8],
+    ) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+        let signatures = authenticode::signatures(path, bytes)?;
+        let count = signatures.len();
+        let mut entries = Vec::new();
+        for (i, der) in signatures.iter().enumerate() {
+            let source = match count {
+                1 => String::from(path),
+                _ => format!("{} signature [{}/{}]", path, i + 1, count),
+            };
+            let signed = cms::read(&source, der)?;
+            entries.append(&mut self.decode_signed(&source, signed, None)?);
+        }
+        Ok(entries)
+    }
+
+    /// Decodes attribute certificates, which hold no key and so are kept apart from
+    /// the certificate entries.
+    fn add_attribute_certificates(
+        &mut self,
+        path: &str,
+        certificates: Vec<Vec<u8>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let count = certificates.len();
+        for (i, der) in certificates.iter().enumerate() {
+            let location = match count {
+                1 => String::from(path),
+                _ => format!("{} [{}/{}]", path, i + 1, count),
+            };
+            let certificate = attribute_certificate::read(&location, der)?;
+            self.attribute_certificates.push(certificate);
+        }
+        Ok(())
+    }
+
+    /// Decodes RFC 7250 raw public keys, which like attribute certificates are
+    /// kept apart from the certificate entries.
+    fn add_raw_keys(
+        &mut self,
+        path: &str,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let count = keys.len();
+        for (i, der) in keys.iter().enumerate() {
+            let location = match count {
+                1 => String::from(path),
+                _ => format!("{} [{}/{}]", path, i + 1, count),
+            };
+            self.raw_keys.push(raw_key::read(&location, der)?);
+        }
+        Ok(())
+    }
+
+    /// Decodes CRLs given on their own rather than in a PKCS#7 bundle.
+    fn add_crls(
+        &mut self,
+        path: &str,
+        crls: Vec<Vec<u8>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let count = crls.len();
+        for (i, der) in crls.iter().enumerate() {
+            let location = match count {
+                1 => String::from(path),
+                _ => format!("{} [{}/{}]", path, i + 1, count),
+            };
+            let crl = crl::read(location, der, &self.find_serials)
+                .map_err(|error| format!("Error: {} is not a valid CRL: {}", path, error))?;
+            self.crls.push(crl);
+        }
+        Ok(())
+    }
+
+    /// Decodes OpenSSH certificates and public keys, which are not X.509 and so
+    /// are kept apart from the certificate entries. Host keys are compared with
+    /// known_hosts and DNS when asked to.
+    fn add_ssh_certificates(
+        &mut self,
+        path: &str,
+        certificates: Vec<Vec<u8>>,
+        keys: Vec<(Vec<u8>, Option<String>)>,
+        options: &Options,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let count = certificates.len() + keys.len();
+        let location = |i: usize| match count {
+            1 => String::from(path),
+            _ => format!("{} [{}/{}]", path, i + 1, count),
+        };
+        for (i, blob) in certificates.iter().enumerate() {
+            let mut certificate = ssh::read(&location(i), blob)?;
+            if certificate.kind == "host" {
+                certificate.checks = ssh::check(
+                    &certificate.key,
+                    Some(&certificate.ca_key),
+                    &options.host_keys,
+                );
+                self.warn_about_host_key(&certificate.location, &certificate.checks);
+            }
+            self.ssh_certificates.push(certificate);
+        }
+        for (i, (blob, comment)) in keys.into_iter().enumerate() {
+            let mut key = ssh::read_key(&location(certificates.len() + i), &blob, comment)?;
+            key.checks = ssh::check(&blob, None, &options.host_keys);
+            self.warn_about_host_key(&key.location, &key.checks);
+            self.ssh_keys.push(key);
+        }
+        Ok(())
+    }
+
+    fn warn_about_host_key(&mut self, location: &str, checks: &[HostKeyCheck]) {
+        for check in checks.iter().filter(|check| !check.confirmed) {
+            self.warnings.push(format!(
+                "Warning: the host key in {} could not be confirmed by {}, {}.",
+                location, check.source, check.outcome
+            ));
+        }
+    }
+
+    /// Decodes the signer of an Apple provisioning profile and the developer
+    /// certificates it names, checking they can be used for as long as the profile.
+    fn decode_profile(
+        &mut self,
+        path: &str,
+        signed: SignedData,
+        profile: Profile,
+    ) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+        let mut entries = self.decode_signed(path, signed, None)?;
+        let certificates = profile.certificates.iter().cloned();
+        for (name, der) in keystore::chain_entries("developer certificate", certificates) {
+            let entry = Entry::new(format!("{}: {}", path, name), der)?;
+            let cert = entry.certificate();
+            let not_after = &cert.validity().not_after;
+            if not_after.timestamp() < self.now {
+                self.warnings.push(format!(
+                    "Warning: {} expired on {}, so nothing can be signed with it under the profile.",
+                    entry.location, not_after
+                ));
+            } else if not_after.timestamp() < profile.expires_at {
+                self.warnings.push(format!(
+                    "Warning: {} expires on {}, before the profile does on {}.",
+                    entry.location, not_after, profile.expires
+                ));
+            }
+            // Apple issues developer certificates with the team identifier as the OU
+            if let Some(team) = &profile.team_id {
+                let mut units = cert.subject().iter_organizational_unit();
+                if !units.any(|unit| unit.as_str() == Ok(team.as_str())) {
+                    self.warnings.push(format!(
+                        "Warning: {} was not issued to the team {} of the profile.",
+                        entry.location, team
+                    ));
+                }
+            }
+            drop(cert);
+            entries.push(entry);
+        }
+        if profile.expires_at < self.now {
+            self.warnings.push(format!(
+                "Warning: the provisioning profile {} expired on {}.",
+                path, profile.expires
+            ));
+        }
+        self.profiles.push(profile);
+        Ok(entries)
+    }
+
+    /// Decodes the signers of a JAR's signature files and of an APK's v2 and later
+    /// signature schemes, with the key rotation lineage of v3 signers.
+    fn decode_package(
+        &mut self,
+        path: &str,
+        bytes: &[u8],
+    ) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+        let signatures = apk::signatures(path, bytes)?;
+        let mut entries = Vec::new();
+        for signer in signatures.jar {
+            let source = format!("{}: {}", path, signer.name);
+            let signed = cms::read(&source, &signer.signature)?;
+            entries.append(&mut self.decode_signed(&source, signed, signer.signed.as_deref())?);
+        }
+
+        for signer in signatures.apk {
+            let location = match signer.sdk {
+                Some((min, max)) if max == i32::MAX as u32 => {
+                    format!("{}: {} signer (SDK {}+)", path, signer.scheme, min)
+                }
+                Some((min, max)) => {
+                    format!("{}: {} signer (SDK {}-{})", path, signer.scheme, min, max)
+                }
+                None => format!("{}: {} signer", path, signer.scheme),
+            };
+            if signer.certificates.is_empty() {
+                return Err(format!("Error: {} has no certificate.", location).into());
+            }
+            // The newest certificate in the lineage must be the one signing now
+            let newest = signer.lineage.last().map(|rotation| &rotation.der);
+            if newest.is_some_and(|newest| *newest != signer.certificates[0]) {
+                self.warnings.push(format!(
+                    "Warning: the lineage of {} does not end with its signing certificate.",
+                    location
+                ));
+            }
+            entries.append(&mut decode_chain(&location, signer.certificates)?);
+
+            let count = signer.lineage.len();
+            for (i, rotation) in signer.lineage.into_iter().enumerate() {
+                let name = format!("{}: lineage [{}/{}]", location, i + 1, count);
+                let mut entry = Entry::new(name, rotation.der)?;
+                entry.capabilities = Some(rotation.capabilities);
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Decodes the certificates and CRLs included in CMS SignedData, telling the
+    /// signers apart from the CA certificates included alongside them, and checking
+    /// their signatures if the content is embedded or was given with `--content`.
+    fn decode_signed(
+        &mut self,
+        source: &str,
+        signed: SignedData,
+        detached: Option<&[u8]>,
+    ) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+        let content = signed.content.as_deref().or(detached);
+        let mut timestamp = signed.timestamp;
+        // Countersigners sign the signature of the signer they belong to, and their
+        // certificates are included alongside the signers'
+        let mut signers = Vec::new();
+        let mut nested = Vec::new();
+        for mut signer in signed.signers {
+            let countersigners = std::mem::take(&mut signer.countersigners);
+            let signature = signer.signature().to_vec();
+            nested.extend(
+                signer
+                    .timestamps
+                    .drain(..)
+                    .map(|token| ("timestamp", token)),
+            );
+            nested.extend(
+                signer
+                    .nested
+                    .drain(..)
+                    .map(|inner| ("nested signature", inner)),
+            );
+            signers.push(Some(("signer", signer, content.map(<[u8]>::to_vec))));
+            for countersigner in countersigners {
+                signers.push(Some((
+                    "countersigner",
+                    countersigner,
+                    Some(signature.clone()),
+                )));
+            }
+        }
+
+        let mut entries = Vec::new();
+        let mut included = Vec::new();
+        for der in signed.certificates {
+            let (_, cert) = parse_x509_certificate(&der)?;
+            let signer = signers
+                .iter_mut()
+                .find(|signer| {
+                    signer
+                        .as_ref()
+                        .is_some_and(|(_, signer, _)| signer.is_signed_by(&cert))
+                })
+                .and_then(Option::take);
+            let (label, mut signer, content) = match signer {
+                Some(signer) => signer,
+                None => {
+                    included.push(der);
+                    continue;
+                }
+            };
+            let location = format!("{}: {}", source, label);
+            if label == "signer" && !signed.digest_algorithms.contains(&signer.digest_algorithm) {
+                self.warnings.push(format!(
+                    "Warning: {} uses {}, which is not among the digest algorithms of {}.",
+                    location, signer.digest_algorithm, source
+                ));
+            }
+            if let Some(content) = content {
+                signer.verify(&cert, &content);
+            }
+            if let Verification::Invalid(reason) = &signer.verification {
+                self.warnings.push(format!(
+                    "Warning: the signature of {} is invalid, {}.",
+                    location, reason
+                ));
+            }
+            let mut entry = Entry::new(location, der)?;
+            entry.signer = Some(signer);
+            entry.timestamp = timestamp.take();
+            entries.push(entry);
+        }
+        for (name, der) in keystore::chain_entries("included certificate", included.into_iter()) {
+            entries.push(Entry::new(format!("{}: {}", source, name), der)?);
+        }
+
+        let count = signed.crls.len();
+        for (i, der) in signed.crls.iter().enumerate() {
+            let location = match count {
+                1 => format!("{}: CRL", source),
+                _ => format!("{}: CRL [{}/{}]", source, i + 1, count),
+            };
+            let crl = crl::read(location, der, &self.find_serials)
+                .map_err(|error| format!("Error: a CRL in {} is not valid: {}", source, error))?;
+            self.crls.push(crl);
+        }
+
+        let missing = signers.iter().flatten().count();
+        if missing > 0 {
+            self.warnings.push(format!(
+                "Warning: {} signer(s) of {} did not include their certificate.",
+                missing, source
+            ));
+        }
+        if entries.is_empty() && count == 0 {
+            return Err(format!("Error: no certificates are included in {}.", source).into());
+        }
+
+        // A broken timestamp shouldn't hide the signature it was made over
+        for (label, inner) in nested {
+            let location = format!("{}: {}", source, label);
+            match self.decode_signed(&location, inner, None) {
+                Ok(mut inner) => entries.append(&mut inner),
+                Err(error) => self.add(&location, Err(error)),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Adds certificates labelled by where they were found within `source`.
+    pub fn add_named(
+        &mut self,
+        source: &str,
+        result: Result<Vec<NamedCertificate>, Box<dyn std::error::Error>>,
+    ) {
+        let result = result.and_then(|items| decode_named(source, None, items));
+        self.add(source, result);
+    }
+
+    fn add(&mut self, location: &str, result: Result<Vec<Entry>, Box<dyn std::error::Error>>) {
+        let result = match self.strictness {
+            Strictness::Strict => result.and_then(refuse_deviations),
+            _ => result,
+        };
+        match result {
+            Ok(mut entries) => {
+                if let Some(logs) = &self.ct_logs {
+                    check_scts(&mut entries, logs);
+                }
+                if self.anchors.is_some() && !entries.is_empty() {
+                    let start = self.entries.len();
+                    self.groups.push(start..start + entries.len());
+                }
+                for entry in &entries {
+                    let cert = entry.certificate();
+                    for sct in entry.scts.iter().filter(|sct| sct.invalid) {
+                        self.warnings.push(format!(
+                            "Warning: {} has an SCT from {} whose signature is invalid.",
+                            entry.location, sct.log
+                        ));
+                    }
+                    if precertificate::poison(&cert) == Some(false) {
+                        self.warnings.push(format!(
+                            "Warning: {} is a precertificate whose poison extension is not critical, so clients may accept it.",
+                            entry.location
+                        ));
+                    }
+                    for name in idn::names(&cert) {
+                        if let Some(homograph) = name.homograph {
+                            self.warnings.push(format!(
+                                "Warning: {} has the name {} ({}), which may be a spoof, {}.",
+                                entry.location, name.ascii, name.unicode, homograph
+                            ));
+                        }
+                    }
+                    if self.strictness == Strictness::Lenient {
+                        for deviation in conformance::deviations(&entry.der) {
+                            self.warnings.push(format!(
+                                "Warning: {} does not strictly follow DER and RFC 5280, {}.",
+                                entry.location, deviation
+                            ));
+                        }
+                    }
+                    for extension in critical::extensions(&cert) {
+                        if !extension.recognized {
+                            self.warnings.push(format!(
+                                "Warning: {} has the critical extension {}, which most clients don't recognize, so they will reject it.",
+                                entry.location, extension.name
+                            ));
+                        }
+                    }
+                    let issues = match self.lint {
+                        true => lint::lint(&cert),
+                        false => Vec::new(),
+                    };
+                    for issue in issues {
+                        self.warnings.push(format!(
+                            "Warning: {} does not follow the Baseline Requirements, {}.",
+                            entry.location, issue
+                        ));
+                    }
+                    if let Some(profile) = self.profile {
+                        let checks = compliance::check(&cert, profile);
+                        for problem in checks.into_iter().filter_map(|check| check.problem) {
+                            self.warnings.push(format!(
+                                "Warning: {} does not comply with {}, {}.",
+                                entry.location, profile, problem
+                            ));
+                        }
+                    }
+                    let ca = matches!(cert.basic_constraints(), Ok(Some(basic)) if basic.value.ca);
+                    if !ca {
+                        for coverage in covers::check(&cert, &self.covers, &self.suffixes) {
+                            if let Err(reason) = coverage.by {
+                                self.warnings.push(format!(
+                                    "Warning: {} does not cover {}, {}.",
+                                    entry.location, coverage.name, reason
+                                ));
+                            }
+                        }
+                    }
+                    if self.domains && !ca {
+                        let names = psl::dns_names(&cert);
+                        for problem in psl::problems(&self.suffixes, &names) {
+                            self.warnings
+                                .push(format!("Warning: {} {}.", entry.location, problem));
+                        }
+                    }
+                    let issuer = issuers::identify(&cert);
+                    if let Some(reason) = issuer.distrusted {
+                        self.warnings.push(format!(
+                            "Warning: {} was issued by {}, which browsers no longer trust, {}.",
+                            entry.location, issuer.name, reason
+                        ));
+                    }
+                    for issue in matter::lint(&cert) {
+                        self.warnings.push(format!(
+                            "Warning: {} does not follow the Matter certificate profile, {}.",
+                            entry.location, issue
+                        ));
+                    }
+                }
+                self.entries.append(&mut entries)
+            }
+            Err(error) => self.failures.push(Failure {
+                location: String::from(location),
+                error,
+            }),
+        }
+    }
+
+    /// Decodes the certificates a web server config refers to, and any written into it.
+    fn add_server_config(&mut self, processor: &impl FileProcessor, path: &str) {
+        let config = match server_config::read(processor, path) {
+            Ok(config) => config,
+            Err(error) => return self.add(path, Err(error)),
+        };
+        let (entries, failures) = (self.entries.len(), self.failures.len());
+
+        for (file, bytes) in config.inline {
+            let result = decode_pem(&file, &bytes);
+            self.add(&file, result);
+        }
+        for (location, error) in config.failures {
+            self.add(&location, Err(error));
+        }
+        for reference in config.references {
+            let location = format!("{} {}", reference.label, reference.path);
+            if reference.path.contains('$') {
+                let error = format!(
+                    "Error: {} is chosen at runtime from a variable, so it can't be read from the config.",
+                    reference.path
+                );
+                self.add(&location, Err(error.into()));
+            } else if processor.is_dir(&reference.path) {
+                self.add_dir(processor, &reference.path);
+            } else {
+                let result = processor
+                    .read(&reference.path)
+                    .map_err(|error| {
+                        format!("Error: could not read {}: {}", reference.path, error).into()
+                    })
+                    .and_then(|bytes| decode_pem(&location, &bytes));
+                self.add(&location, result);
+            }
+        }
+
+        if self.entries.len() == entries && self.failures.len() == failures {
+            let error = format!("Error: no certificates are referenced in {}.", path);
+            self.add(path, Err(error.into()));
+        }
+    }
+
+    /// Walks a directory (and its subdirectories) decoding anything that looks like a PEM certificate.
+    fn add_dir(&mut self, processor: &impl FileProcessor, dir: &str) {
+        let found = self.entries.len();
+        self.walk_dir(processor, dir);
+        if self.entries.len() == found {
+            self.add(
+                dir,
+                Err(format!("Error: no certificates found in directory {}.", dir).into()),
+            );
+        }
+    }
+
+    fn walk_dir(&mut self, processor: &impl FileProcessor, dir: &str) {
+        let paths = match processor.read_dir(dir) {
+            Ok(paths) => paths,
+            Err(error) => return self.add(dir, Err(error)),
+        };
+
+        for path in paths {
+            if processor.is_dir(&path) {
+                self.walk_dir(processor, &path);
+                continue;
+            }
+            match processor.read(&path) {
+                Ok(bytes) if contains_pem_certificate(&bytes) => {
+                    let result = decode_pem(&path, &bytes);
+                    self.add(&path, result);
+                }
+                Ok(_) => {}
+                Err(error) => self.add(&path, Err(error)),
+            }
+        }
+    }
+
+    /// Builds the paths from the first certificate found in each place to a trust
+    /// anchor, through the others found with it and any intermediates they point to.
+    fn build_trust_paths(&mut self, processor: &impl FileProcessor, connect: &ConnectOptions) {
+        let anchors = match &self.anchors {
+            Some(anchors) => anchors,
+            None => return,
+        };
+        for group in &self.groups {
+            let certificates: Vec<NamedCertificate> = self.entries[group.clone()]
+                .iter()
+                .map(|entry| (entry.location.clone(), entry.der.clone()))
+                .collect();
+            let mut paths = trust_path::build_fetching(
+                processor,
+                connect,
+                &certificates[0],
+                &certificates[1..],
+                anchors,
+                self.now,
+            );
+            for path in &mut paths {
+                path.require(&self.required_policies);
+            }
+            if let Some(path) = paths.first() {
+                if let Some(problem) = &path.problem {
+                    self.warnings.push(format!(
+                        "Warning: {} is not trusted, {}.",
+                        certificates[0].0, problem
+                    ));
+                }
+                for problem in &path.purposes.problems {
+                    self.warnings.push(format!(
+                        "Warning: {} is not valid for {}.",
+                        certificates[0].0, problem
+                    ));
+                }
+                for check in &path.policies {
+                    if let Some(problem) = &check.problem {
+                        self.warnings.push(format!(
+                            "Warning: policy {} is not valid for {}, {}.",
+                            check.policy, certificates[0].0, problem
+                        ));
+                    }
+                }
+            }
+            self.entries[group.start].trust = paths;
+        }
+    }
+
+    /// Warns about every certificate that is valid for longer than it may be.
+    pub fn check_validity_periods(&mut self, max: &MaxValidity, now: i64) {
+        for entry in &self.entries {
+            if let Some(issue) = lifetime::check(&entry.certificate(), max, now) {
+                self.warnings
+                    .push(format!("Warning: {} {}.", entry.location, issue));
+            }
+        }
+    }
+
+    /// Succeeds only if every target decoded.
+    pub fn status(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "Error: {} location(s) could not be decoded, see the report above.",
+            self.failures.len()
+        )
+        .into())
+    }
+}
+
+/// Fails what was found in one place when any of its certificates deviates from
+/// DER and RFC 5280, for `--strict`.
+fn refuse_deviations(entries: Vec<Entry>) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    for entry in &entries {
+        let deviations = conformance::deviations(&entry.der);
+        if !deviations.is_empty() {
+            let err_msg = format!(
+                "Error: {} does not strictly follow DER and RFC 5280, {}.",
+                entry.location,
+                deviations.join("; ")
+            );
+            return Err(err_msg.into());
+        }
+    }
+    Ok(entries)
+}
+
+/// Checks the SCTs of each certificate, with the key of its issuer if that
+/// was found alongside it.
+fn check_scts(entries: &mut [Entry], logs: &[Log]) {
+    let checks: Vec<Vec<SctCheck>> = entries
+        .iter()
+        .map(|entry| {
+            let cert = entry.certificate();
+            let issuer = entries.iter().map(Entry::certificate).find(|issuer| {
+                issuer.subject() == cert.issuer() && issuer.subject() != cert.subject()
+            });
+            sct::check(&entry.der, issuer.as_ref(), logs)
+        })
+        .collect();
+    for (entry, checks) in entries.iter_mut().zip(checks) {
+        entry.scts = checks;
+    }
+}
+
+/// The SSHFP records of an SSH host key, and how it compared with known_hosts and DNS.
+fn write_host_key_checks(
+    f: &mut fmt::Formatter,
+    sshfp: &[String],
+    checks: &[HostKeyCheck],
+) -> fmt::Result {
+    if !sshfp.is_empty() {
+        writeln!(f, "SSHFP records:")?;
+        for record in sshfp {
+            writeln!(f, "    {}", record)?;
+        }
+    }
+    for check in checks {
+        writeln!(f, "{}: {}", check.source, check.outcome)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Headers are only needed to tell several results apart
+        let headers = self.entries.len()
+            + self.crls.len()
+            + self.profiles.len()
+            + self.attribute_certificates.len()
+            + self.raw_keys.len()
+            + self.ocsp_requests.len()
+            + self.cmp_messages.len()
+            + self.ssh_certificates.len()
+            + self.ssh_keys.len()
+            + self.failures.len()
+            > 1;
+
+        if self.expiry_filter.is_set() && self.entries.is_empty() {
+            writeln!(f, "No certificates are {}.", self.expiry_filter)?;
+        }
+        let mut group = None;
+        for entry in &self.entries {
+            if let Some(group_by) = self.group_by {
+                let key = group_by.key(entry);
+                if group.as_ref() != Some(&key) {
+                    writeln!(f, "=== Issued by {} ===", key)?;
+                    group = Some(key);
+                }
+            }
+            if headers {
+                writeln!(f, "==> {} <==", entry.location)?;
+            }
+            if !entry.duplicates.is_empty() {
+                writeln!(f, "Also found at:")?;
+                for location in &entry.duplicates {
+                    writeln!(f, "    {}", location)?;
+                }
+            }
+            if let Some(session) = &entry.session {
+                writeln!(f, "TLS version: {}", session.version)?;
+                writeln!(f, "Cipher suite: {}", session.cipher_suite)?;
+                writeln!(
+                    f,
+                    "ALPN protocol: {}",
+                    session.alpn.as_deref().unwrap_or("none")
+                )?;
+                let requested = match (session.client_cert_requested, session.client_cert_sent) {
+                    (false, _) => "no",
+                    (true, false) => "yes, none sent",
+                    (true, true) => "yes, sent",
+                };
+                writeln!(f, "Client certificate requested: {}", requested)?;
+                if session.client_cert_requested {
+                    match session.client_ca_names.as_slice() {
+                        [] => writeln!(f, "Acceptable client CAs: any")?,
+                        names => {
+                            writeln!(f, "Acceptable client CAs:")?;
+                            for name in names {
+                                writeln!(f, "    {}", name)?;
+                            }
+                        }
+                    }
+                }
+            }
+            if let Some(signer) = &entry.signer {
+                writeln!(f, "Content type: {}", signer.content_type)?;
+                writeln!(f, "Digest algorithm: {}", signer.digest_algorithm)?;
+                writeln!(f, "Signature algorithm: {}", signer.signature_algorithm)?;
+                writeln!(
+                    f,
+                    "Signing time: {}",
+                    signer.signing_time.as_deref().unwrap_or("not given")
+                )?;
+                if !signer.attributes.is_empty() {
+                    writeln!(f, "Signed attributes:")?;
+                    for (name, value) in &signer.attributes {
+                        writeln!(f, "    {}: {}", name, value)?;
+                    }
+                }
+                match &signer.verification {
+                    Verification::Valid => writeln!(f, "Signature: valid")?,
+                    Verification::Invalid(reason) => writeln!(f, "Signature: invalid, {}", reason)?,
+                    Verification::NotChecked(reason) => {
+                        writeln!(f, "Signature: not checked, {}", reason)?
+                    }
+                    Verification::Unsupported(reason) => {
+                        writeln!(f, "Signature: unsupported, {}", reason)?
+                    }
+                }
+            }
+            if let Some(timestamp) = &entry.timestamp {
+                writeln!(f, "Timestamp: {}", timestamp.gen_time)?;
+                writeln!(f, "Timestamp policy: {}", timestamp.policy)?;
+                writeln!(
+                    f,
+                    "Message imprint: {} {}",
+                    timestamp.imprint_algorithm, timestamp.imprint
+                )?;
+                writeln!(f, "Timestamp serial number: {}", timestamp.serial)?;
+                writeln!(
+                    f,
+                    "Accuracy: {}",
+                    timestamp.accuracy.as_deref().unwrap_or("not given")
+                )?;
+                if let Some(nonce) = &timestamp.nonce {
+                    writeln!(f, "Nonce: {}", nonce)?;
+                }
+                if let Some(tsa) = &timestamp.tsa {
+                    writeln!(f, "TSA name: {}", tsa)?;
+                }
+            }
+            if let Some(capabilities) = &entry.capabilities {
+                match capabilities.as_slice() {
+                    [] => writeln!(f, "Rotation capabilities: none")?,
+                    capabilities => {
+                        writeln!(f, "Rotation capabilities: {}", capabilities.join(", "))?
+                    }
+                }
+            }
+            let cert = entry.certificate();
+            let precertificate = precertificate::poison(&cert).is_some();
+            if precertificate {
+                writeln!(f, "Precertificate: yes, for a Certificate Transparency log")?;
+            }
+            for decoded in extensions::decode(&cert) {
+                write!(f, "{}", decoded)?;
+            }
+            let names = idn::names(&cert);
+            if !names.is_empty() {
+                writeln!(f, "Internationalized names:")?;
+                for name in names {
+                    writeln!(f, "    {}: {}", name.ascii, name.unicode)?;
+                }
+            }
+            let critical: Vec<String> = critical::extensions(&cert)
+                .into_iter()
+                .map(|extension| match extension.recognized {
+                    true => extension.name,
+                    false => format!("{} (not recognized)", extension.name),
+                })
+                .collect();
+            match critical.as_slice() {
+                [] => writeln!(f, "Critical extensions: none")?,
+                critical => writeln!(f, "Critical extensions: {}", critical.join(", "))?,
+            }
+            let ca = matches!(cert.basic_constraints(), Ok(Some(basic)) if basic.value.ca);
+            if !self.covers.is_empty() && !ca {
+                writeln!(f, "Covers:")?;
+                for coverage in covers::check(&cert, &self.covers, &self.suffixes) {
+                    match coverage.by {
+                        Ok(san) => writeln!(f, "    {}: yes, by {}", coverage.name, san)?,
+                        Err(reason) => writeln!(f, "    {}: no, {}", coverage.name, reason)?,
+                    }
+                }
+            }
+            if self.domains && !ca {
+                let names = psl::dns_names(&cert);
+                let domains: Vec<String> = psl::registrable_domains(&self.suffixes, &names)
+                    .into_iter()
+                    .map(|(domain, count)| match count {
+                        1 => domain,
+                        count => format!("{} ({} names)", domain, count),
+                    })
+                    .collect();
+                match domains.as_slice() {
+                    [] => writeln!(f, "Registrable domains: none")?,
+                    domains => writeln!(f, "Registrable domains: {}", domains.join(", "))?,
+                }
+            }
+            if let Some(profile) = self.profile {
+                let checks = compliance::check(&cert, profile);
+                let outcome = match checks.iter().all(|check| check.problem.is_none()) {
+                    true => "compliant",
+                    false => "non-compliant",
+                };
+                writeln!(f, "{} compliance: {}", profile, outcome)?;
+                for check in checks {
+                    writeln!(f, "    {}", check)?;
+                }
+            }
+            if !entry.scts.is_empty() {
+                writeln!(f, "Signed certificate timestamps:")?;
+                for sct in &entry.scts {
+                    writeln!(f, "    {}", sct.log)?;
+                    writeln!(f, "        Timestamp: {}", sct.timestamp)?;
+                    writeln!(f, "        Signature: {}", sct.signature)?;
+                    writeln!(f, "        Log qualified at the time: {}", sct.qualified)?;
+                }
+            }
+            if let (Some(path), false) = (entry.trust.first(), self.explain_trust) {
+                match (path.anchor(), &path.problem) {
+                    (Some(anchor), None) => {
+                        writeln!(f, "Trust anchor: {}", anchor.subject)?;
+                        writeln!(f, "    From: {}", anchor.source)?;
+                        writeln!(f, "    SHA-256 fingerprint: {}", anchor.fingerprint)?;
+                        writeln!(f, "    Valid for: {}", path.purposes)?;
+                        for check in &path.policies {
+                            writeln!(f, "    {}", check)?;
+                        }
+                    }
+                    (_, problem) => writeln!(
+                        f,
+                        "Trust anchor: none, {}",
+                        problem.as_deref().unwrap_or("no path was found")
+                    )?,
+                }
+            }
+            if entry.trust.len() > 1 && self.explain_trust {
+                writeln!(f, "Trust paths found: {}", entry.trust.len())?;
+                for (i, path) in entry.trust.iter().enumerate() {
+                    writeln!(f, "    [{}] {}", i + 1, path.summary())?;
+                }
+            }
+            if let (Some(trust), true) = (entry.trust.first(), self.explain_trust) {
+                write!(f, "{}", trust)?;
+            }
+            writeln!(f, "Subject: {}", dn::to_string(cert.subject()))?;
+            writeln!(f, "Issuer: {}", dn::to_string(cert.issuer()))?;
+            writeln!(f, "Issued by: {}", issuers::identify(&cert))?;
+            writeln!(f, "{:#?}", cert.tbs_certificate)?;
+            if precertificate && self.reconstruct_tbs {
+                // Only the issuer tells whether a precertificate signing certificate signed it
+                let issuer = self.entries.iter().find(|candidate| {
+                    let issuer = candidate.certificate();
+                    issuer.subject().as_raw() == cert.issuer().as_raw()
+                        && cert.verify_signature(Some(issuer.public_key())).is_ok()
+                });
+                let heading = match issuer {
+                    Some(issuer) if precertificate::is_precertificate_signer(&issuer.certificate()) => {
+                        "without the poison extension and issued by the CA of the precertificate signing certificate"
+                    }
+                    Some(_) => "without the poison extension",
+                    None => "without the poison extension, if its issuer is not a precertificate signing certificate",
+                };
+                let final_tbs = precertificate::reconstruct_tbs(
+                    &entry.der,
+                    issuer.map(|issuer| issuer.der.as_slice()),
+                );
+                match final_tbs.as_deref().map(TbsCertificate::from_der) {
+                    Some(Ok((_, tbs))) => {
+                        writeln!(f, "Final TBS certificate, {}:", heading)?;
+                        writeln!(f, "{:#?}", tbs)?;
+                    }
+                    _ => writeln!(f, "Final TBS certificate: could not be reconstructed")?,
+                }
+            }
+        }
+        for crl in &self.crls {
+            if headers {
+                writeln!(f, "==> {} <==", crl.location)?;
+            }
+            writeln!(f, "CRL issuer: {}", crl.issuer)?;
+            writeln!(f, "Last update: {}", crl.last_update)?;
+            writeln!(
+                f,
+                "Next update: {}",
+                crl.next_update.as_deref().unwrap_or("not given")
+            )?;
+            writeln!(f, "Revoked certificates: {}", crl.count)?;
+            for serial in &crl.searched {
+                match crl.revoked.iter().find(|revoked| revoked.serial == *serial) {
+                    Some(revoked) => writeln!(
+                        f,
+                        "    {}: revoked on {}{}",
+                        serial,
+                        revoked.date,
+                        revoked
+                            .reason
+                            .as_ref()
+                            .map(|reason| format!(", {}", reason))
+                            .unwrap_or_default()
+                    )?,
+                    None => writeln!(f, "    {}: not revoked", serial)?,
+                }
+            }
+            if let Some(number) = &crl.number {
+                writeln!(f, "CRL number: {}", number)?;
+            }
+            if let Some(base) = &crl.base {
+                writeln!(f, "Delta CRL: changes since base CRL number {}", base)?;
+            }
+            if !crl.freshest.is_empty() {
+                writeln!(f, "Delta CRLs at: {}", crl.freshest.join(", "))?;
+            }
+            if !crl.scope.is_empty() {
+                writeln!(f, "Partition: {}", crl.scope.join(", "))?;
+            }
+        }
+        for effective in &self.effective_crls {
+            writeln!(f, "==> {} + {} <==", effective.base, effective.delta)?;
+            writeln!(f, "Revoked certificates: {}", effective.revoked.len())?;
+            for added in &effective.added {
+                match &added.reason {
+                    Some(reason) => {
+                        writeln!(f, "    Revoked by the delta: {} ({})", added.serial, reason)?
+                    }
+                    None => writeln!(f, "    Revoked by the delta: {}", added.serial)?,
+                }
+            }
+            for serial in &effective.removed {
+                writeln!(f, "    Taken off hold by the delta: {}", serial)?;
+            }
+        }
+        for profile in &self.profiles {
+            if headers {
+                writeln!(f, "==> {} <==", profile.location)?;
+            }
+            writeln!(f, "Profile name: {}", profile.name)?;
+            writeln!(f, "Profile type: {}", profile.kind)?;
+            if let Some(uuid) = &profile.uuid {
+                writeln!(f, "UUID: {}", uuid)?;
+            }
+            if let Some(app_id_name) = &profile.app_id_name {
+                writeln!(f, "App ID name: {}", app_id_name)?;
+            }
+            writeln!(
+                f,
+                "Team: {}",
+                profile.team.as_deref().unwrap_or("not given")
+            )?;
+            if !profile.platforms.is_empty() {
+                writeln!(f, "Platforms: {}", profile.platforms.join(", "))?;
+            }
+            writeln!(f, "Created: {}", profile.created)?;
+            writeln!(f, "Expires: {}", profile.expires)?;
+            writeln!(f, "Provisioned devices: {}", profile.devices)?;
+            writeln!(f, "Developer certificates: {}", profile.certificates.len())?;
+            writeln!(f, "Entitlements:")?;
+            for (name, value) in &profile.entitlements {
+                writeln!(f, "    {}: {}", name, value)?;
+            }
+        }
+        for certificate in &self.attribute_certificates {
+            if headers {
+                writeln!(f, "==> {} <==", certificate.location)?;
+            }
+            writeln!(
+                f,
+                "Attribute certificate holder: {}",
+                certificate.holder.join(", ")
+            )?;
+            writeln!(
+                f,
+                "Attribute certificate issuer: {}",
+                certificate.issuer.join(", ")
+            )?;
+            writeln!(f, "Serial number: {}", certificate.serial)?;
+            writeln!(f, "Not before: {}", certificate.not_before)?;
+            writeln!(f, "Not after: {}", certificate.not_after)?;
+            writeln!(
+                f,
+                "Signature algorithm: {}",
+                certificate.signature_algorithm
+            )?;
+            writeln!(f, "Attributes:")?;
+            for (name, value) in &certificate.attributes {
+                writeln!(f, "    {}: {}", name, value)?;
+            }
+            if !certificate.extensions.is_empty() {
+                writeln!(f, "Extensions: {}", certificate.extensions.join(", "))?;
+            }
+        }
+        for key in &self.raw_keys {
+            if headers {
+                writeln!(f, "==> {} <==", key.location)?;
+            }
+            writeln!(f, "Raw public key: {}", key.key)?;
+            writeln!(f, "Algorithm: {}", key.algorithm)?;
+            writeln!(f, "SPKI pin: pin-sha256=\"{}\"", key.pin)?;
+            writeln!(f, "SPKI SHA-256: {}", key.sha256)?;
+        }
+        for request in &self.ocsp_requests {
+            if headers {
+                writeln!(f, "==> {} <==", request.location)?;
+            }
+            writeln!(f, "OCSP request version: {}", request.version)?;
+            if let Some(requestor) = &request.requestor {
+                writeln!(f, "Requestor: {}", requestor)?;
+            }
+            writeln!(f, "Requested certificates: {}", request.certificates.len())?;
+            for id in &request.certificates {
+                for line in ocsp::describe(id, &self.entries) {
+                    writeln!(f, "    {}", line)?;
+                }
+            }
+            match &request.nonce {
+                Some(nonce) => writeln!(f, "Nonce: {} ({} bytes)", nonce, request.nonce_length)?,
+                None => writeln!(f, "Nonce: none, so responses can be replayed")?,
+            }
+            if !request.extensions.is_empty() {
+                writeln!(f, "Extensions: {}", request.extensions.join(", "))?;
+            }
+            match &request.signature_algorithm {
+                Some(algorithm) => writeln!(f, "Signed: yes, with {}", algorithm)?,
+                None => writeln!(f, "Signed: no")?,
+            }
+        }
+        for message in &self.cmp_messages {
+            if headers {
+                writeln!(f, "==> {} <==", message.location)?;
+            }
+            writeln!(f, "CMP message: {}, {}", message.body.0, message.body.1)?;
+            writeln!(f, "Version: {}", message.version)?;
+            writeln!(f, "Sender: {}", message.sender)?;
+            writeln!(f, "Recipient: {}", message.recipient)?;
+            if let Some(time) = &message.message_time {
+                writeln!(f, "Message time: {}", time)?;
+            }
+            if let Some(id) = &message.transaction_id {
+                writeln!(f, "Transaction ID: {}", id)?;
+            }
+            if let Some(kid) = &message.sender_kid {
+                writeln!(f, "Sender key ID: {}", kid)?;
+            }
+            match &message.protection {
+                Some(protection) => writeln!(f, "Protection: {}", protection)?,
+                None => writeln!(f, "Protection: none")?,
+            }
+            if !message.general_info.is_empty() {
+                writeln!(f, "General info: {}", message.general_info.join(", "))?;
+            }
+            for line in &message.details {
+                writeln!(f, "{}", line)?;
+            }
+            if !message.certificates.is_empty() {
+                writeln!(f, "Certificates: {}", message.certificates.len())?;
+            }
+        }
+        for certificate in &self.ssh_certificates {
+            if headers {
+                writeln!(f, "==> {} <==", certificate.location)?;
+            }
+            writeln!(
+                f,
+                "SSH certificate: {} {} certificate",
+                certificate.key_type, certificate.kind
+            )?;
+            writeln!(f, "Key ID: {}", certificate.key_id)?;
+            writeln!(f, "Serial number: {}", certificate.serial)?;
+            writeln!(f, "Public key: {}", certificate.public_key)?;
+            match certificate.principals.as_slice() {
+                [] => writeln!(f, "Principals: any")?,
+                principals => writeln!(f, "Principals: {}", principals.join(", "))?,
+            }
+            writeln!(f, "Valid after: {}", certificate.valid_after)?;
+            writeln!(f, "Valid before: {}", certificate.valid_before)?;
+            if certificate.critical_options.is_empty() {
+                writeln!(f, "Critical options: none")?;
+            } else {
+                writeln!(f, "Critical options:")?;
+                for (name, value) in &certificate.critical_options {
+                    match value.is_empty() {
+                        true => writeln!(f, "    {}", name)?,
+                        false => writeln!(f, "    {}: {}", name, value)?,
+                    }
+                }
+            }
+            match certificate.extensions.as_slice() {
+                [] => writeln!(f, "Extensions: none")?,
+                extensions => writeln!(f, "Extensions: {}", extensions.join(", "))?,
+            }
+            writeln!(
+                f,
+                "Signing CA: {} (using {})",
+                certificate.signing_ca, certificate.signature_algorithm
+            )?;
+            write_host_key_checks(f, &certificate.sshfp, &certificate.checks)?;
+        }
+        for key in &self.ssh_keys {
+            if headers {
+                writeln!(f, "==> {} <==", key.location)?;
+            }
+            writeln!(f, "SSH public key: {}", key.fingerprint)?;
+            if let Some(comment) = &key.comment {
+                writeln!(f, "Comment: {}", comment)?;
+            }
+            write_host_key_checks(f, &key.sshfp, &key.checks)?;
+        }
+        for failure in &self.failures {
+            writeln!(f, "==> {} <==", failure.location)?;
+            writeln!(f, "{}", failure.error)?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "{}", warning)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// With `--all-addresses` a host stands for one server per address,
+/// otherwise just for whichever address answers first.
+fn servers_for(
+    processor: &impl FileProcessor,
+    host: &str,
+    port: u16,
+    options: &Options,
+) -> Result<Vec<Server>, Box<dyn std::error::Error>> {
+    let server = |address| Server {
+        host: String::from(host),
+        port,
+        address,
+    };
+    if !options.scan.all_addresses {
+        return Ok(vec![server(None)]);
+    }
+
+    let addresses = processor.resolve(host, port, options.connect.family)?;
+    Ok(addresses
+        .into_iter()
+        .map(|address| server(Some(address)))
+        .collect())
+}
+
+fn contains_pem_certificate(bytes: &[u8]) -> bool {
+    let marker = b"-----BEGIN CERTIFICATE-----";
+    bytes.windows(marker.len()).any(|window| window == marker)
+}
+
+/// Decodes every PEM certificate in a file, wherever in it they are.
+fn decode_pem(source: &str, bytes: &[u8]) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    let bytes = pem::normalize(bytes);
+    let mut chain = Vec::new();
+    for block in pem::blocks(&bytes) {
+        if block.label == "CERTIFICATE" {
+            chain.push(
+                block
+                    .contents
+                    .ok_or_else(|| pem::diagnose(source, &bytes))?,
+            );
+        }
+    }
+
+    if chain.is_empty() {
+        return Err(pem::diagnose(source, &bytes).into());
+    }
+    decode_chain(source, chain)
+}
+
+/// Decodes the certificates of a system store, keeping only those
+/// whose subject matches the `--subject` filter if there is one.
+fn decode_store(
+    store: &StoreOptions,
+    certificates: Vec<Vec<u8>>,
+) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    let mut entries = decode_chain(&store.to_string(), certificates)?;
+    if let Some(subject) = &store.subject {
+        let subject = subject.to_lowercase();
+        entries.retain(|entry| {
+            let name = entry.certificate().subject().to_string();
+            name.to_lowercase().contains(&subject)
+        });
+    }
+
+    if entries.is_empty() {
+        return Err(match &store.subject {
+            Some(subject) => format!(
+                "Error: no certificates in {} have a subject matching {}.",
+                store, subject
+            ),
+            None => format!("Error: no certificates found in {}.", store),
+        }
+        .into());
+    }
+    Ok(entries)
+}
+
+/// Decodes certificates that have a label in the store they came from,
+/// such as a keychain or NSS database, keeping only those matching `label`.
+fn decode_named(
+    source: &str,
+    label: Option<&str>,
+    items: Vec<NamedCertificate>,
+) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    let filter = label.map(str::to_lowercase);
+    let entries = items
+        .into_iter()
+        .filter(|(name, _)| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| name.to_lowercase().contains(filter))
+        })
+        .map(|(name, der)| Entry::new(format!("{}: {}", source, name), der))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if entries.is_empty() {
+        return Err(match label {
+            Some(label) => format!(
+                "Error: no certificates in the {} have a label matching {}.",
+                source, label
+            ),
+            None => format!("Error: no certificates found in the {}.", source),
+        }
+        .into());
+    }
+    Ok(entries)
+}
+
+/// Turns a chain of DER certificates from one source into entries,
+/// numbering them when there is more than one.
+fn decode_chain(
+    source: &str,
+    chain: Vec<Vec<u8>>,
+) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    let count = chain.len();
+    chain
+        .into_iter()
+        .enumerate()
+        .map(|(i, der)| match count {
+            1 => Entry::new(String::from(source), der),
+            _ => Entry::new(format!("{} [{}/{}]", source, i + 1, count), der),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Write;
+    use std::net::IpAddr;
+    use std::time::Duration;
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use x509_parser::pem::{parse_x509_pem, Pem};
+
+    use super::Report;
+    use crate::cloud::Cloud;
+    use crate::conformance::Strictness;
+    use crate::expiry::ExpiryFilter;
+    use crate::http::Response;
+    use crate::lifetime::MaxValidity;
+    use crate::options::Options;
+    use crate::pkcs11::Pkcs11Options;
+    use crate::psl::SuffixList;
+    use crate::scan::ScanOptions;
+    use crate::sct;
+    use crate::sort::{GroupBy, SortBy};
+    use crate::store::StoreOptions;
+    use crate::target::Target;
+    use crate::test::{google_der, self_signed_der, FakeProcessor};
+    use crate::trust_path;
+
+    fn all_addresses() -> Options {
+        Options {
+            scan: ScanOptions {
+                all_addresses: true,
+                ..ScanOptions::default()
+            },
+            ..Options::default()
+        }
+    }
+
+    fn example() -> Vec<Target> {
+        vec![Target::Remote {
+            host: String::from("example.com"),
+            port: 443,
+        }]
+    }
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn should_report_each_address_separately() {
+        let mut processor = FakeProcessor::default();
+        processor.add_addresses("example.com", &[ip("192.0.2.1"), ip("2001:db8::1")]);
+        processor.add_remote("example.com:443 (192.0.2.1)", vec![google_der()]);
+        processor.add_remote("example.com:443 (2001:db8::1)", vec![google_der()]);
+
+        let report = Report::build(&processor, &example(), &all_addresses());
+
+        let locations: Vec<_> = report
+            .entries
+            .iter()
+            .map(|entry| entry.location.as_str())
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                "example.com:443 (192.0.2.1)",
+                "example.com:443 (2001:db8::1)"
+            ]
+        );
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn should_report_what_the_handshake_negotiated() {
+        let mut processor = FakeProcessor::default();
+        processor.add_remote("example.com:443", vec![google_der(), self_signed_der()]);
+
+        let report = Report::build(&processor, &example(), &Options::default());
+        let output = report.to_string();
+
+        assert!(report.entries[0].session.is_some());
+        assert!(report.entries[1].session.is_none());
+        assert!(output.starts_with(
+            "==> example.com:443 [1/2] <==\nTLS version: TLS 1.3\nCipher suite: TLS13_AES_128_GCM_SHA256\nALPN protocol: h2\nClient certificate requested: no\n"
+        ));
+    }
+
+    #[test]
+    fn should_list_the_client_cas_the_server_asked_for() {
+        let mut processor = FakeProcessor::default();
+        processor.add_remote("example.com:443", vec![google_der()]);
+        let handshake = processor.remotes.get_mut("example.com:443").unwrap();
+        handshake.session.client_cert_requested = true;
+        handshake.session.client_ca_names = vec![String::from("CN=Corp Client CA, O=Corp")];
+
+        let output = Report::build(&processor, &example(), &Options::default()).to_string();
+
+        assert!(output.contains(
+            "Client certificate requested: yes, none sent\nAcceptable client CAs:\n    CN=Corp Client CA, O=Corp\n"
+        ));
+    }
+
+    #[test]
+    fn should_warn_if_addresses_serve_different_certificates() {
+        let mut processor = FakeProcessor::default();
+        processor.add_addresses("example.com", &[ip("192.0.2.1"), ip("2001:db8::1")]);
+        processor.add_remote("example.com:443 (192.0.2.1)", vec![google_der()]);
+        processor.add_remote("example.com:443 (2001:db8::1)", vec![self_signed_der()]);
+
+        let report = Report::build(&processor, &example(), &all_addresses());
+
+        assert_eq!(
+            report.warnings,
+            vec![
+                "Warning: the addresses of example.com:443 did not all serve the same certificate."
+            ]
+        );
+    }
+
+    #[test]
+    fn should_only_use_addresses_of_the_requested_family() {
+        let mut processor = FakeProcessor::default();
+        processor.add_addresses("example.com", &[ip("192.0.2.1"), ip("2001:db8::1")]);
+        processor.add_remote("example.com:443 (2001:db8::1)", vec![google_der()]);
+        let mut options = all_addresses();
+        options.connect.family = Some(crate::remote::Family::V6);
+
+        let report = Report::build(&processor, &example(), &options);
+
+        assert_eq!(report.entries.len(), 1);
+        assert!(report.failures.is_empty());
+    }
+
+    fn my_store(subject: Option<&str>) -> Vec<Target> {
+        vec![Target::Store(StoreOptions {
+            subject: subject.map(String::from),
+            ..StoreOptions::default()
+        })]
+    }
+
+    #[test]
+    fn should_decode_every_certificate_in_a_store() {
+        let mut processor = FakeProcessor::default();
+        processor.stores.insert(
+            String::from("CurrentUser\\My"),
+            vec![google_der(), self_signed_der()],
+        );
+
+        let report = Report::build(&processor, &my_store(None), &Options::default());
+
+        let locations: Vec<_> = report.entries.iter().map(|e| e.location.as_str()).collect();
+        assert_eq!(
+            locations,
+            vec!["CurrentUser\\My [1/2]", "CurrentUser\\My [2/2]"]
+        );
+    }
+
+    #[test]
+    fn should_only_keep_store_certificates_matching_the_subject() {
+        let mut processor = FakeProcessor::default();
+        processor.stores.insert(
+            String::from("CurrentUser\\My"),
+            vec![google_der(), self_signed_der()],
+        );
+
+        let report = Report::build(
+            &processor,
+            &my_store(Some("SELF-SIGNED")),
+            &Options::default(),
+        );
+        let unmatched = Report::build(&processor, &my_store(Some("contoso")), &Options::default());
+
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(report.entries[0].location, "CurrentUser\\My [2/2]");
+        assert_eq!(
+            format!("{}", unmatched.failures[0].error),
+            "Error: no certificates in CurrentUser\\My have a subject matching contoso."
+        );
+    }
+
+    #[test]
+    fn should_label_keychain_certificates_and_filter_by_label() {
+        let mut processor = FakeProcessor::default();
+        processor.keychains.insert(
+            String::from("system"),
+            vec![
+                (String::from("*.google.com"), google_der()),
+                (String::from("Self Signed"), self_signed_der()),
+            ],
+        );
+        let targets = vec![Target::Keychain {
+            keychain: String::from("system"),
+            label: Some(String::from("google")),
+        }];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(report.entries[0].location, "system keychain: *.google.com");
+    }
+
+    #[test]
+    fn should_decode_the_certificates_on_a_pkcs11_token() {
+        let mut processor = FakeProcessor::default();
+        let token = Pkcs11Options {
+            module: String::from("/usr/lib/libykcs11.so"),
+            slot: Some(0),
+            pin_env: Some(String::from("YUBIKEY_PIN")),
+        };
+        processor.tokens.insert(
+            token.to_string(),
+            vec![(
+                String::from(
+                    "slot 0 (YubiKey PIV #1234): X.509 Certificate for PIV Authentication",
+                ),
+                google_der(),
+            )],
+        );
+        let targets = vec![Target::Pkcs11(token)];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        assert_eq!(
+            report.failures[0].error.to_string(),
+            "Error: the PIN environment variable YUBIKEY_PIN is not set."
+        );
+
+        processor
+            .vars
+            .insert(String::from("YUBIKEY_PIN"), String::from("123456"));
+        let report = Report::build(&processor, &targets, &Options::default());
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(
+            report.entries[0].location,
+            "PKCS#11 module /usr/lib/libykcs11.so slot 0: slot 0 (YubiKey PIV #1234): X.509 Certificate for PIV Authentication"
+        );
+    }
+
+    #[test]
+    fn should_report_on_several_clouds_at_once() {
+        let mut processor = FakeProcessor::default();
+        processor
+            .commands
+            .insert(String::from("az"), String::from("eyJ0\n"));
+        processor.responses.insert(
+            String::from("https://contoso.vault.azure.net/certificates?api-version=7.4"),
+            Response {
+                status: 200,
+                headers: Vec::new(),
+                body: br#"{"value":[{"id":"https://contoso.vault.azure.net/certificates/www"}]}"#
+                    .to_vec(),
+            },
+        );
+        processor.responses.insert(
+            String::from("https://contoso.vault.azure.net/certificates/www?api-version=7.4"),
+            Response {
+                status: 200,
+                headers: Vec::new(),
+                body: format!(r#"{{"cer":"{}"}}"#, STANDARD.encode(google_der())).into_bytes(),
+            },
+        );
+        let targets = vec![
+            Target::Cloud(Cloud::parse("azure:contoso").unwrap()),
+            Target::Cloud(Cloud::parse("gcp:shop").unwrap()),
+        ];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(report.entries[0].location, "Azure Key Vault contoso: www");
+        assert_eq!(
+            report.failures[0].location,
+            "GCP Certificate Manager in shop (global)"
+        );
+    }
+
+    #[test]
+    fn should_read_directories_holding_an_nss_database_as_one() {
+        let mut processor = FakeProcessor::default();
+        processor.add_dir("profile", &["profile/cert9.db", "profile/key4.db"]);
+        processor.add_file("profile/cert9.db", "");
+        processor.nss.insert(
+            String::from("profile"),
+            vec![(String::from("Corp Root"), self_signed_der())],
+        );
+        let targets = vec![Target::Path(String::from("profile"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(
+            report.entries[0].location,
+            "NSS database profile: Corp Root"
+        );
+    }
+
+    #[test]
+    fn should_decode_the_certificates_in_an_archive() {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, contents) in [
+            ("README", &b"Install the certificates below."[..]),
+            (
+                "certs/google.pem",
+                include_bytes!("../resources/google.com.crt"),
+            ),
+            ("certs/broken.der", b"not DER"),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("release.zip"),
+            zip.finish().unwrap().into_inner(),
+        );
+        let targets = vec![Target::Path(String::from("release.zip"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(report.entries[0].location, "release.zip: certs/google.pem");
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].location, "release.zip: certs/broken.der");
+    }
+
+    #[test]
+    fn should_tell_the_signer_of_a_message_from_its_ca_certificates() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("signed.eml", include_str!("../resources/signed.eml"));
+        let targets = vec![Target::Path(String::from("signed.eml"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        let output = report.to_string();
+
+        let locations: Vec<_> = report.entries.iter().map(|e| e.location.as_str()).collect();
+        assert_eq!(
+            locations,
+            vec![
+                "signed.eml: signer",
+                "signed.eml: included certificate [1/2]",
+                "signed.eml: included certificate [2/2]"
+            ]
+        );
+        assert!(output.contains(
+            "==> signed.eml: signer <==\nContent type: pkcs7-data\nDigest algorithm: sha256\nSignature algorithm: ecdsa-with-SHA256\nSigning time: "
+        ));
+    }
+
+    #[test]
+    fn should_check_a_detached_signature_against_the_given_content() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("release.txt.p7s"),
+            include_bytes!("../resources/release.txt.p7s").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("release.txt.p7s"))];
+        let mut options = Options {
+            detached_content: Some(include_bytes!("../resources/release.txt").to_vec()),
+            ..Options::default()
+        };
+
+        let valid = Report::build(&processor, &targets, &options);
+        options.detached_content = Some(b"tampered".to_vec());
+        let invalid = Report::build(&processor, &targets, &options);
+
+        assert!(valid.to_string().contains("\nSignature: valid\n"));
+        assert!(valid.warnings.is_empty());
+        assert_eq!(
+            invalid.warnings,
+            vec!["Warning: the signature of release.txt.p7s: signer is invalid, the content does not match its message digest."]
+        );
+    }
+
+    #[test]
+    fn should_report_the_crls_in_a_pkcs7_bundle() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("bundle.p7b", include_str!("../resources/bundle.p7b"));
+        let targets = vec![Target::Path(String::from("bundle.p7b"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert_eq!(report.entries.len(), 1);
+        assert!(report.to_string().contains(
+            "==> bundle.p7b: CRL <==\nCRL issuer: CN=self-signed.example\nLast update: Oct  1 00:00:00 2026 +00:00\nNext update: Oct  1 00:00:00 2126 +00:00\nRevoked certificates: 2\n"
+        ));
+    }
+
+    #[test]
+    fn should_combine_a_base_crl_with_its_delta() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("base.crl", include_str!("../resources/base.crl"));
+        processor.binaries.insert(
+            String::from("delta.crl"),
+            include_bytes!("../resources/delta.crl").to_vec(),
+        );
+        let targets = vec![
+            Target::Path(String::from("base.crl")),
+            Target::Path(String::from("delta.crl")),
+        ];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        let output = report.to_string();
+
+        assert!(report.failures.is_empty());
+        assert!(report.warnings.is_empty());
+        assert!(output.contains(
+            "Revoked certificates: 2\n\
+             CRL number: 10\n\
+             Delta CRLs at: http://crl.example/partition1-delta.crl\n\
+             Partition: distribution point http://crl.example/partition1.crl, only end-entity certificates\n"
+        ));
+        assert!(output.contains("Delta CRL: changes since base CRL number 10\n"));
+        assert!(output.ends_with(
+            "==> base.crl + delta.crl <==\n\
+             Revoked certificates: 2\n    \
+             Revoked by the delta: 10:03 (superseded)\n    \
+             Taken off hold by the delta: 10:02\n"
+        ));
+    }
+
+    #[test]
+    fn should_say_whether_the_serials_searched_for_are_revoked() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("base.crl", include_str!("../resources/base.crl"));
+        let targets = vec![Target::Path(String::from("base.crl"))];
+        let options = Options {
+            find_serials: vec![vec![0x10, 0x01], vec![0x20, 0x01]],
+            ..Options::default()
+        };
+
+        let report = Report::build(&processor, &targets, &options);
+
+        assert!(report.to_string().contains(
+            "Revoked certificates: 2\n    \
+             10:01: revoked on Sep 30 00:00:00 2026 +00:00, keyCompromise\n    \
+             20:01: not revoked\n"
+        ));
+    }
+
+    #[test]
+    fn should_summarize_registrable_domains_and_flag_broad_certificates() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        let targets = vec![Target::Path(String::from("google.crt"))];
+        let options = Options {
+            domains: true,
+            suffixes: Some(SuffixList::parse("com\nuk\nco.uk\n")),
+            ..Options::default()
+        };
+
+        let report = Report::build(&processor, &targets, &options);
+
+        assert!(report.to_string().contains(
+            "Registrable domains: google.com (7 names), android.com (2 names), bdn.dev, "
+        ));
+        assert!(report.to_string().contains(", google.co.uk, com.ar, "));
+        assert_eq!(
+            report.warnings,
+            vec!["Warning: google.crt covers names under 47 registrable domains, more than a site usually needs."]
+        );
+    }
+
+    #[test]
+    fn should_show_what_a_tsa_vouched_for() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("release.txt.tsr"),
+            include_bytes!("../resources/release.txt.tsr").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("release.txt.tsr"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        let output = report.to_string();
+
+        assert_eq!(report.entries.len(), 1);
+        assert!(output.contains("Content type: id-ct-TSTInfo\n"));
+        assert!(output.contains("Signature: valid\nTimestamp: Oct 15 02:18:51 2026 +00:00\nTimestamp policy: 1.3.6.1.4.1.32473.1.1\nMessage imprint: sha256 73:81:f9:8a"));
+        assert!(output.contains("Accuracy: 1s 500ms\n"));
+    }
+
+    #[test]
+    fn should_decode_the_signer_and_timestamp_of_a_signed_executable() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("signed.exe"),
+            include_bytes!("../resources/signed.exe").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("signed.exe"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        let output = report.to_string();
+
+        assert!(report.failures.is_empty());
+        assert!(output.contains("==> signed.exe: signer <=="));
+        assert!(output.contains("==> signed.exe: included certificate [1/2] <=="));
+        assert!(output.contains("==> signed.exe: timestamp: signer <=="));
+        assert!(output.contains("Timestamp: Oct 15 02:18:51 2026 +00:00\n"));
+    }
+
+    #[test]
+    fn should_decode_the_signers_and_key_rotation_lineage_of_an_apk() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("signed.apk"),
+            include_bytes!("../resources/signed.apk").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("signed.apk"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        let output = report.to_string();
+
+        assert!(report.failures.is_empty());
+        assert!(report.warnings.is_empty());
+        assert!(output.contains(
+            "==> signed.apk: META-INF/RELEASE.RSA: signer <==\nContent type: pkcs7-data\n"
+        ));
+        assert!(output.contains("==> signed.apk: APK v2 signer <=="));
+        assert!(output.contains("==> signed.apk: APK v3 signer (SDK 28+) <=="));
+        assert!(output.contains("==> signed.apk: APK v3 signer (SDK 28+): lineage [1/2] <==\nRotation capabilities: installed data, shared user ID, rollback, auth\n"));
+    }
+
+    #[test]
+    fn should_check_the_developer_certificates_of_a_provisioning_profile() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("example.mobileprovision"),
+            include_bytes!("../resources/example.mobileprovision").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("example.mobileprovision"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        let output = report.to_string();
+
+        assert_eq!(report.entries.len(), 2);
+        assert!(output.contains("==> example.mobileprovision: developer certificate <=="));
+        assert!(output.contains("Profile name: Example Development\nProfile type: development\n"));
+        assert!(output.contains("Team: Example Inc. (ABCDE12345)\n"));
+        assert!(output.contains("Provisioned devices: 2\n"));
+        assert!(output.contains("    keychain-access-groups: ABCDE12345.*, com.apple.token\n"));
+        assert_eq!(
+            report.warnings,
+            vec![String::from("Warning: example.mobileprovision: developer certificate expires on Oct  1 00:00:00 2027 +00:00, before the profile does on 2027-10-15T00:00:00Z.")]
+        );
+    }
+
+    #[test]
+    fn should_decode_android_key_attestation() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("attestation.crt"),
+            include_bytes!("../resources/android-attestation.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("attestation.crt"))];
+
+        let output = Report::build(&processor, &targets, &Options::default()).to_string();
+
+        assert!(output.contains("Android key attestation:\n    Attestation version: 300\n    Attestation security level: TrustedEnvironment\n    KeyMint version: 300\n"));
+        assert!(output.contains("    Attestation challenge: nonce-1234\n    Unique ID: none\n"));
+        assert!(output.contains("    Software enforced:\n        Creation time: Oct 12 02:18:51 2026 +00:00\n        Attestation application ID: com.example.app version 12, signing certificate digest 00:01:02"));
+        assert!(output.contains("        Purpose: sign, verify\n        Algorithm: EC\n        Key size: 256\n        Digest: SHA-2-256\n        EC curve: P-256\n        No authentication required: yes\n        Origin: generated\n"));
+        assert!(output.contains("        Root of trust: Verified, locked, verified boot key ab:ab"));
+        assert!(output
+            .contains("        OS patch level: 2026-09\n        Vendor patch level: 2026-09-05\n"));
+    }
+
+    #[test]
+    fn should_decode_the_tpm_of_an_endorsement_key_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("ek.crt"),
+            include_bytes!("../resources/tpm-ek.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("ek.crt"))];
+
+        let output = Report::build(&processor, &targets, &Options::default()).to_string();
+
+        assert!(output.starts_with("TPM endorsement key:\n    TPM manufacturer: id:4E544300 (Nuvoton)\n    TPM model: NPCT75x\n    TPM firmware version: id:00070002 (7.2)\n"));
+        assert!(output.contains("    Policy: 1.3.6.1.4.1.32473.1.2\n        CPS: https://tpm.example.com/cps\n    TPM specification: 2.0, level 0, revision 1.38\n"));
+    }
+
+    #[test]
+    fn should_decode_the_sgx_extensions_of_a_pck_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("pck.crt"),
+            include_bytes!("../resources/sgx-pck.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("pck.crt"))];
+
+        let output = Report::build(&processor, &targets, &Options::default()).to_string();
+
+        assert!(output.starts_with("SGX PCK extensions:\n    PPID: a1:b2:c3:d4"));
+        assert!(output.contains("    TCB:\n        Component SVNs: 4, 4, 3, 3, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0\n        PCESVN: 11\n        CPUSVN: 04:04:03:03:ff:ff"));
+        assert!(
+            output.contains("    PCE ID: 0000\n    FMSPC: 00906ED50000\n    SGX type: Standard\n")
+        );
+    }
+
+    #[test]
+    fn should_decode_the_device_identity_of_an_idevid() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("idevid.crt"),
+            include_bytes!("../resources/idevid.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("idevid.crt"))];
+
+        let output = Report::build(&processor, &targets, &Options::default()).to_string();
+
+        assert!(output.starts_with("IEEE 802.1AR device identity:\n    Hardware type: 1.3.6.1.4.1.32473.3.1\n    Hardware serial number: SN-00042\n    Device serial number: SN-00042\n"));
+        assert!(output.contains("    MASA URL: https://masa.example.com/.well-known/brski\n    Expires: never (99991231235959Z)\n"));
+    }
+
+    #[test]
+    fn should_decode_the_qualified_certificate_statements_of_a_qwac() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("qwac.crt"),
+            include_bytes!("../resources/psd2-qwac.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("qwac.crt"))];
+
+        let output = Report::build(&processor, &targets, &Options::default()).to_string();
+
+        assert!(output.starts_with("Qualified certificate statements:\n    Compliance: EU qualified certificate\n    Transaction limit: 50000 EUR\n    Retention period: 15 years\n    PKI disclosure statements:\n        en: https://qtsp.example.com/pds_en.pdf\n    Type: website authentication\n"));
+        assert!(output.contains("    PSD2:\n        Roles: PSP_AI (account information), PSP_PI (payment initiation)\n        NCA name: Federal Financial Supervisory Authority\n        NCA ID: DE-BAFIN\n    Semantics: legal person\n"));
+    }
+
+    #[test]
+    fn should_decode_the_tn_auth_list_of_a_shaken_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("shaken.crt"),
+            include_bytes!("../resources/shaken.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("shaken.crt"))];
+
+        let output = Report::build(&processor, &targets, &Options::default()).to_string();
+
+        assert!(output.starts_with("STIR/SHAKEN TNAuthList:\n    Service provider code: 709J\n    Telephone numbers: 12025550100 to 12025550149 (50 numbers)\n    Telephone number: 12025550123\n"));
+    }
+
+    #[test]
+    fn should_decode_the_ad_cs_extensions_of_an_enterprise_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("web.crt"),
+            include_bytes!("../resources/adcs-web.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("web.crt"))];
+
+        let output = Report::build(&processor, &targets, &Options::default()).to_string();
+
+        assert!(
+            output.starts_with("Microsoft certificate template name:\n    Template: WebServer\n")
+        );
+        assert!(output.contains("Microsoft application policies:\n    Policy: Server Authentication\n    Policy: Client Authentication\nMicrosoft NTDS CA security:\n    Object SID: S-1-5-21-3623811015-3361044348-30300820-1013\n"));
+    }
+
+    #[test]
+    fn should_decode_the_netscape_extensions_of_an_old_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("intranet.crt"),
+            include_bytes!("../resources/netscape.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("intranet.crt"))];
+
+        let output = Report::build(&processor, &targets, &Options::default()).to_string();
+
+        assert!(output.starts_with("Netscape extensions:\n    Certificate type: SSL client, SSL server\n    Comment: Generated by the intranet CA\n"));
+    }
+
+    #[test]
+    fn should_name_the_gost_algorithms_of_a_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("gost.crt"),
+            include_bytes!("../resources/gost.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("gost.crt"))];
+
+        let output = Report::build(&processor, &targets, &Options::default()).to_string();
+
+        assert!(output.starts_with("GOST R 34.10 key:\n    Algorithm: GOST R 34.10-2012 (256 bit)\n    Parameter set: TC26 256-bit parameter set A\n    Digest: GOST R 34.11-2012 (256 bit)\n    Key size: 256 bits\n    Signature algorithm: GOST R 34.11-2012 with GOST R 34.10-2012 (256 bit)\n"));
+    }
+
+    #[test]
+    fn should_name_the_sm2_curve_and_sm3_signature_of_a_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("sm2.crt"),
+            include_bytes!("../resources/sm2.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("sm2.crt"))];
+
+        let output = Report::build(&processor, &targets, &Options::default()).to_string();
+
+        assert!(output.starts_with("SM2 key:\n    Curve: SM2 (sm2p256v1)\n    Key size: 256 bits\n    Signature algorithm: SM3 with SM2\n"));
+    }
+
+    #[test]
+    fn should_label_post_quantum_and_hybrid_algorithms() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("composite.crt"),
+            include_bytes!("../resources/composite.crt").to_vec(),
+        );
+        processor.binaries.insert(
+            String::from("hybrid.crt"),
+            include_bytes!("../resources/hybrid.crt").to_vec(),
+        );
+        let report = |path: &str| {
+            let targets = vec![Target::Path(String::from(path))];
+            Report::build(&processor, &targets, &Options::default()).to_string()
+        };
+
+        let composite = report("composite.crt");
+        let hybrid = report("hybrid.crt");
+
+        assert!(composite.starts_with("Post-quantum key:\n    Algorithm: ML-DSA-44 with ECDSA P-256\n    Status: experimental, from draft-ietf-lamps-pq-composite-sigs\n    Key size: 1377 bytes\n    Signature algorithm: ML-DSA-44 with ECDSA P-256\n"));
+        assert!(hybrid.starts_with("Alternative public key:\n    Algorithm: ML-DSA-44\n    Status: standardised in FIPS 204\n    Key size: 1312 bytes\nAlternative signature algorithm:\n    Algorithm: ML-DSA-44\n    Status: standardised in FIPS 204\nAlternative signature:\n    Signature size: 2420 bytes\n"));
+    }
+
+    #[test]
+    fn should_decode_the_rsassa_pss_parameters_of_a_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("pss.crt"),
+            include_bytes!("../resources/rsa-pss.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("pss.crt"))];
+
+        let output = Report::build(&processor, &targets, &Options::default()).to_string();
+
+        assert!(output.starts_with("RSASSA-PSS parameters:\n    Signature:\n        Hash: sha256\n        Mask generation: MGF1 with sha256\n        Salt length: 64\n        Trailer field: 1\n    Public key:\n        Hash: sha256\n"));
+    }
+
+    #[test]
+    fn should_label_a_precertificate_and_reconstruct_its_final_tbs() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("precert.crt"),
+            include_bytes!("../resources/precert.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("precert.crt"))];
+        let options = Options {
+            reconstruct_tbs: true,
+            ..Options::default()
+        };
+
+        let plain = Report::build(&processor, &targets, &Options::default()).to_string();
+        let output = Report::build(&processor, &targets, &options).to_string();
+
+        assert!(plain.starts_with("Precertificate: yes, for a Certificate Transparency log\n"));
+        assert!(!plain.contains("Final TBS certificate"));
+        let (before, after) = output
+            .split_once("Final TBS certificate, without the poison extension, if its issuer is not a precertificate signing certificate:\n")
+            .unwrap();
+        assert!(before.contains("1.3.6.1.4.1.11129.2.4.3"));
+        assert!(!after.contains("1.3.6.1.4.1.11129.2.4.3"));
+        assert!(after.contains("www.example.com"));
+    }
+
+    #[test]
+    fn should_reconstruct_the_tbs_of_a_precertificate_from_a_signing_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("chain.pem"),
+            include_bytes!("../resources/precert-signer-chain.pem").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("chain.pem"))];
+        let options = Options {
+            reconstruct_tbs: true,
+            ..Options::default()
+        };
+
+        let output = Report::build(&processor, &targets, &options).to_string();
+
+        assert!(output.contains("Final TBS certificate, without the poison extension and issued by the CA of the precertificate signing certificate:\n"));
+    }
+
+    #[test]
+    fn should_lint_matter_attestation_certificates() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("dac.pem"),
+            include_bytes!("../resources/matter-dac-chain.pem").to_vec(),
+        );
+        processor.binaries.insert(
+            String::from("bad.crt"),
+            include_bytes!("../resources/matter-bad-dac.crt").to_vec(),
+        );
+        let targets = vec![
+            Target::Path(String::from("dac.pem")),
+            Target::Path(String::from("bad.crt")),
+        ];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        let output = report.to_string();
+
+        assert!(output.contains("Matter device attestation:\n    Certificate type: DAC\n    Vendor ID: 0xFFF1 (test vendor)\n    Product ID: 0x8000\n"));
+        assert!(output.contains("Matter device attestation:\n    Certificate type: PAI\n"));
+        assert_eq!(report.warnings.len(), 3);
+        assert_eq!(
+            report.warnings[0],
+            "Warning: bad.crt does not follow the Matter certificate profile, its basic constraints are not critical."
+        );
+    }
+
+    #[test]
+    fn should_decode_an_attribute_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("alice.ac.pem"),
+            include_bytes!("../resources/alice.ac.pem").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("alice.ac.pem"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert!(report.failures.is_empty());
+        assert_eq!(
+            report.to_string(),
+            "Attribute certificate holder: O=Example, CN=Alice\n\
+             Attribute certificate issuer: CN=self-signed.example\n\
+             Serial number: 2a\n\
+             Not before: Oct  1 00:00:00 2026 +00:00\n\
+             Not after: Oct  1 00:00:00 2027 +00:00\n\
+             Signature algorithm: ecdsa-with-SHA256\n\
+             Attributes:\n    \
+             Role: URI(urn:example:role:admin)\n    \
+             Group: engineering, ops\n    \
+             Clearance: secret under 1.3.6.1.4.1.32473.2\n\
+             Extensions: noRevAvail, targetInformation (critical)\n"
+        );
+    }
+
+    #[test]
+    fn should_decode_a_raw_public_key() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("raw-key.pem"),
+            include_bytes!("../resources/raw-key.pem").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("raw-key.pem"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert!(report.failures.is_empty());
+        assert_eq!(
+            report.to_string(),
+            "Raw public key: EC P-256\n\
+             Algorithm: id-ecPublicKey\n\
+             SPKI pin: pin-sha256=\"2ZUv5Vyy15HT+pAm2114/2XbUzS8i+a5KVJozgyP6Ds=\"\n\
+             SPKI SHA-256: d9952fe55cb2d791d3fa9026db5d78ff65db5334bc8be6b9295268ce0c8fe83b\n"
+        );
+    }
+
+    #[test]
+    fn should_decode_a_cmp_response_and_the_certificates_it_carries() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("ip.der"),
+            include_bytes!("../resources/cmp-ip.der").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("ip.der"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        let output = report.to_string();
+
+        assert!(report.failures.is_empty());
+        assert_eq!(report.entries.len(), 3);
+        assert!(output.contains("==> ip.der: certificate for request 0 <==\n"));
+        assert!(output.contains(
+            "==> ip.der <==\n\
+             CMP message: ip, initialization response\n\
+             Version: 2\n\
+             Sender: CN=self-signed.example\n\
+             Recipient: CN=device.example\n"
+        ));
+        assert!(output.contains(
+            "Protection: signed with ecdsa-with-SHA256 by CN=self-signed.example, verified\n\
+             Request 0: accepted, certificate issued\n\
+             Certificates: 3\n"
+        ));
+    }
+
+    #[test]
+    fn should_decode_an_ocsp_request_and_name_its_issuer() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("request.der"),
+            include_bytes!("../resources/ocsp-request.der").to_vec(),
+        );
+        processor.binaries.insert(
+            String::from("root.crt"),
+            include_bytes!("../resources/example-root.crt").to_vec(),
+        );
+        let targets = vec![
+            Target::Path(String::from("request.der")),
+            Target::Path(String::from("root.crt")),
+        ];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        let output = report.to_string();
+
+        assert!(report.failures.is_empty());
+        assert!(output.contains(
+            "==> request.der <==\n\
+             OCSP request version: 1\n\
+             Requested certificates: 2\n    \
+             Serial number: 1a:2b\n    \
+             Hash algorithm: sha256\n"
+        ));
+        assert!(output.contains("    Issuer: CN=Example Root CA, from root.crt\n"));
+        assert!(output.contains(
+            "Nonce: f7:d0:e4:71:0c:03:2b:07:ee:f7:4a:4f:ed:72:d2:b3 (16 bytes)\n\
+             Signed: no\n"
+        ));
+    }
+
+    #[test]
+    fn should_decode_ct_log_entries() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("entries.json"),
+            include_bytes!("../resources/ct-entries.json").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("entries.json"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+        let locations: Vec<&str> = report
+            .entries
+            .iter()
+            .map(|entry| entry.location.as_str())
+            .collect();
+
+        assert!(report.failures.is_empty());
+        assert!(report.warnings.is_empty());
+        assert_eq!(
+            locations,
+            vec![
+                "entries.json: entry 1 (certificate, logged Oct  1 00:05:00 2026 +00:00) [1/2]",
+                "entries.json: entry 1 (certificate, logged Oct  1 00:05:00 2026 +00:00) [2/2]",
+                "entries.json: entry 2 (precertificate, logged Oct  1 00:05:01 2026 +00:00) [1/2]",
+                "entries.json: entry 2 (precertificate, logged Oct  1 00:05:01 2026 +00:00) [2/2]",
+            ]
+        );
+        assert!(report
+            .to_string()
+            .contains("Precertificate: yes, for a Certificate Transparency log\n"));
+    }
+
+    #[test]
+    fn should_decode_an_openssh_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("host-cert.pub"),
+            include_bytes!("../resources/ssh-host-cert.pub").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("host-cert.pub"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert!(report.failures.is_empty());
+        assert_eq!(
+            report.to_string(),
+            "SSH certificate: ssh-ed25519-cert-v01@openssh.com host certificate\n\
+             Key ID: web01\n\
+             Serial number: 0\n\
+             Public key: ssh-ed25519 SHA256:BRMoE9WGUldUQxPmc96jnfde5ZQNph/EXIY35P2EOq0\n\
+             Principals: web01.example.com\n\
+             Valid after: always\n\
+             Valid before: forever\n\
+             Critical options: none\n\
+             Extensions: none\n\
+             Signing CA: ssh-ed25519 SHA256:1jkFcP3Or1t/8vlyP0ATLCSk5dWvl6KT2iircw4WqeQ (using ssh-ed25519)\n\
+             SSHFP records:\n    \
+             4 1 c40757890481de30468b17bf6517bf18654ddcc4\n    \
+             4 2 05132813d5865257544313e673dea39df75ee5940da61fc45c8637e4fd843aad\n"
+        );
+    }
+
+    #[test]
+    fn should_check_an_ssh_host_key_against_known_hosts_and_dns() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("host.pub"),
+            include_bytes!("../resources/ssh-host.pub").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("host.pub"))];
+        let mut options = Options::default();
+        options.host_keys.known_hosts = Some(String::from("other ssh-ed25519 AAAA\n"));
+        options.host_keys.sshfp = Some((
+            String::from("web01.example.com"),
+            vec![String::from(
+                "4 2 05132813d5865257544313e673dea39df75ee5940da61fc45c8637e4fd843aad",
+            )],
+            true,
+        ));
+
+        let report = Report::build(&processor, &targets, &options);
+
+        assert!(report.to_string().starts_with(
+            "SSH public key: ssh-ed25519 SHA256:BRMoE9WGUldUQxPmc96jnfde5ZQNph/EXIY35P2EOq0\n\
+             Comment: host\n\
+             SSHFP records:\n    \
+             4 1 c40757890481de30468b17bf6517bf18654ddcc4\n    \
+             4 2 05132813d5865257544313e673dea39df75ee5940da61fc45c8637e4fd843aad\n\
+             known_hosts: not listed\n\
+             DNS SSHFP for web01.example.com: matches 4 2\n"
+        ));
+        assert_eq!(
+            report.warnings,
+            vec!["Warning: the host key in host.pub could not be confirmed by known_hosts, not listed."]
+        );
+    }
+
+    #[test]
+    fn should_check_embedded_scts_against_the_log_list() {
+        let mut processor = FakeProcessor::default();
+        let mut chain = include_bytes!("../resources/sct.crt").to_vec();
+        chain.extend_from_slice(include_bytes!("../resources/self-signed.crt"));
+        processor.binaries.insert(String::from("chain.pem"), chain);
+        let targets = vec![Target::Path(String::from("chain.pem"))];
+        let options = Options {
+            ct_logs: Some(
+                sct::log_list(include_bytes!("../resources/ct-log-list-test.json")).unwrap(),
+            ),
+            ..Options::default()
+        };
+
+        let report = Report::build(&processor, &targets, &options);
+        let output = report.to_string();
+
+        assert!(output.contains(
+            "Signed certificate timestamps:\n    \
+             Example 'Test2026' log (zDG4c0LJw5O1elwMTqolzeCBBxMKvdeqry5lfgRJwvw=)\n        \
+             Timestamp: Oct  1 00:05:00 2026 +00:00\n        \
+             Signature: valid\n        \
+             Log qualified at the time: yes\n"
+        ));
+        assert_eq!(
+            report.warnings,
+            vec!["Warning: chain.pem [1/2] has an SCT from Example 'Test2026' log (zDG4c0LJw5O1elwMTqolzeCBBxMKvdeqry5lfgRJwvw=) whose signature is invalid."]
+        );
+    }
+
+    #[test]
+    fn should_explain_the_trust_path_of_each_leaf() {
+        let mut processor = FakeProcessor {
+            // Jun  1 2026, when the example chain is valid
+            now: 1_780_272_000,
+            ..FakeProcessor::default()
+        };
+        processor.binaries.insert(
+            String::from("chain.pem"),
+            include_bytes!("../resources/example-chain.pem").to_vec(),
+        );
+        processor.binaries.insert(
+            String::from("self-signed.crt"),
+            include_bytes!("../resources/self-signed.crt").to_vec(),
+        );
+        processor.binaries.insert(
+            String::from("roots.pem"),
+            include_bytes!("../resources/example-root.crt").to_vec(),
+        );
+        let targets = vec![
+            Target::Path(String::from("chain.pem")),
+            Target::Path(String::from("self-signed.crt")),
+        ];
+        let options = Options {
+            anchors: Some(trust_path::load(&processor, &[String::from("roots.pem")]).unwrap()),
+            explain_trust: true,
+            ..Options::default()
+        };
+
+        let report = Report::build(&processor, &targets, &options);
+        let output = report.to_string();
+
+        assert!(output.contains(
+            "Trust path: trusted, anchored by CN=Example Root CA from the trust anchor in roots.pem\n"
+        ));
+        assert!(output.contains(
+            "    CN=www.example.com\n        \
+             From: chain.pem [1/2]\n        \
+             SHA-256 fingerprint: 3C:D0:AD:BA:AD:F8:F1:E8:89:07:EA:1A:AA:6B:53:EB:99:62:C7:36:F0:68:38:06:D4:8A:EF:A5:EE:9D:27:AE\n        \
+             Signature: valid, from CN=Example Issuing CA with ecdsa-with-SHA256\n"
+        ));
+        assert!(report.entries[1].trust.is_empty());
+        assert_eq!(
+            report.warnings,
+            vec!["Warning: self-signed.crt is not trusted, CN=self-signed.example is self-signed and not a trust anchor."]
+        );
+    }
+
+    #[test]
+    fn should_list_every_path_of_a_cross_signed_chain() {
+        let mut processor = FakeProcessor {
+            now: 1_780_272_000,
+            ..FakeProcessor::default()
+        };
+        processor.binaries.insert(
+            String::from("chain.pem"),
+            include_bytes!("../resources/cross-chain.pem").to_vec(),
+        );
+        processor.binaries.insert(
+            String::from("roots.pem"),
+            include_bytes!("../resources/cross-roots.pem").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("chain.pem"))];
+        let options = Options {
+            anchors: Some(trust_path::load(&processor, &[String::from("roots.pem")]).unwrap()),
+            explain_trust: true,
+            ..Options::default()
+        };
+
+        let output = Report::build(&processor, &targets, &options).to_string();
+
+        assert!(output.contains(
+            "Trust paths found: 2\n    \
+             [1] 3 certificates to CN=Example New Root CA from the trust anchor in roots.pem, expiring Jan  1 00:00:00 2036 +00:00, trusted\n    \
+             [2] 4 certificates to CN=Example Old Root CA from the trust anchor in roots.pem, expiring Jan  1 00:00:00 2027 +00:00, trusted\n\
+             Trust path: trusted, anchored by CN=Example New Root CA from the trust anchor in roots.pem\n"
+        ));
+    }
+
+    #[test]
+    fn should_name_the_anchor_of_a_leaf_given_alone() {
+        let mut processor = FakeProcessor {
+            now: 1_780_272_000,
+            ..FakeProcessor::default()
+        };
+        let chain = include_bytes!("../resources/example-chain.pem");
+        let intermediate = Pem::iter_from_buffer(chain).nth(1).unwrap().unwrap();
+        processor.responses.insert(
+            String::from("http://ca.example.com/issuing.crt"),
+            Response {
+                status: 200,
+                headers: Vec::new(),
+                body: intermediate.contents,
+            },
+        );
+        processor.binaries.insert(
+            String::from("leaf.crt"),
+            include_bytes!("../resources/aia-leaf.crt").to_vec(),
+        );
+        processor.binaries.insert(
+            String::from("roots.pem"),
+            include_bytes!("../resources/example-root.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("leaf.crt"))];
+        let options = Options {
+            anchors: Some(trust_path::load(&processor, &[String::from("roots.pem")]).unwrap()),
+            find_anchor: true,
+            ..Options::default()
+        };
+
+        let report = Report::build(&processor, &targets, &options);
+
+        assert!(report.warnings.is_empty());
+        assert!(report.to_string().contains(
+            "Trust anchor: CN=Example Root CA\n    \
+             From: trust anchor in roots.pem\n    \
+             SHA-256 fingerprint: 49:43:51:23:A1:41:B7:8C:AE:F2:1C:9F:3D:B1:87:49:0C:A5:EF:AE:F9:BF:F0:4B:02:8F:0F:68:37:8E:BA:99\n"
+        ));
+    }
+
+    #[test]
+    fn should_warn_about_purposes_the_chain_does_not_allow() {
+        let mut processor = FakeProcessor {
+            now: 1_780_272_000,
+            ..FakeProcessor::default()
+        };
+        processor.binaries.insert(
+            String::from("chain.pem"),
+            include_bytes!("../resources/restricted-chain.pem").to_vec(),
+        );
+        processor.binaries.insert(
+            String::from("roots.pem"),
+            include_bytes!("../resources/example-root.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("chain.pem"))];
+        let options = Options {
+            anchors: Some(trust_path::load(&processor, &[String::from("roots.pem")]).unwrap()),
+            explain_trust: true,
+            ..Options::default()
+        };
+
+        let report = Report::build(&processor, &targets, &options);
+
+        assert!(report.to_string().contains(
+            "Valid for: serverAuth\n\
+             Not valid for:\n    \
+             clientAuth, CN=Example Restricted CA only allows serverAuth, codeSigning\n    \
+             codeSigning, it needs the digitalSignature key usage\n"
+        ));
+        assert_eq!(
+            report.warnings,
+            vec![
+                "Warning: chain.pem [1/2] is not valid for clientAuth, CN=Example Restricted CA only allows serverAuth, codeSigning.",
+                "Warning: chain.pem [1/2] is not valid for codeSigning, it needs the digitalSignature key usage.",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_say_whether_the_required_policies_are_valid() {
+        let mut processor = FakeProcessor {
+            now: 1_780_272_000,
+            ..FakeProcessor::default()
+        };
+        processor.binaries.insert(
+            String::from("chain.pem"),
+            include_bytes!("../resources/policy-chain.pem").to_vec(),
+        );
+        processor.binaries.insert(
+            String::from("roots.pem"),
+            include_bytes!("../resources/example-root.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("chain.pem"))];
+        let options = Options {
+            anchors: Some(trust_path::load(&processor, &[String::from("roots.pem")]).unwrap()),
+            required_policies: vec![
+                String::from("2.23.140.1.2.1"),
+                String::from("1.3.6.1.4.1.32473.1"),
+            ],
+            ..Options::default()
+        };
+
+        let report = Report::build(&processor, &targets, &options);
+
+        assert!(report.to_string().contains(
+            "    Valid for: any purpose\n    \
+             Required policy 2.23.140.1.2.1: valid\n    \
+             Required policy 1.3.6.1.4.1.32473.1: not valid, CN=policy.example.com does not assert it, only 2.23.140.1.2.1\n"
+        ));
+        assert_eq!(
+            report.warnings,
+            vec!["Warning: policy 1.3.6.1.4.1.32473.1 is not valid for chain.pem [1/2], CN=policy.example.com does not assert it, only 2.23.140.1.2.1."]
+        );
+    }
+
+    #[test]
+    fn should_warn_about_certificates_valid_for_too_long() {
+        let mut processor = FakeProcessor {
+            now: 1_780_272_000,
+            ..FakeProcessor::default()
+        };
+        processor.binaries.insert(
+            String::from("chain.pem"),
+            include_bytes!("../resources/example-chain.pem").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("chain.pem"))];
+        let options = Options {
+            max_validity: Some(MaxValidity::Cabf),
+            ..Options::default()
+        };
+
+        let report = Report::build(&processor, &targets, &options);
+
+        assert_eq!(
+            report.warnings,
+            vec!["Warning: chain.pem [1/2] is valid for 3652 days and 1 second, more than the 398 days the CA/B Forum allows for TLS server certificates issued from Sep  1 00:00:00 2020 +00:00."]
+        );
+    }
+
+    #[test]
+    fn should_lint_the_serial_numbers() {
+        let mut processor = FakeProcessor {
+            now: 1_780_272_000,
+            ..FakeProcessor::default()
+        };
+        processor.binaries.insert(
+            String::from("renewal.crt"),
+            include_bytes!("../resources/renewal.crt").to_vec(),
+        );
+        processor.binaries.insert(
+            String::from("aia-leaf.crt"),
+            include_bytes!("../resources/aia-leaf.crt").to_vec(),
+        );
+        let targets = vec![
+            Target::Path(String::from("renewal.crt")),
+            Target::Path(String::from("aia-leaf.crt")),
+        ];
+        let options = Options {
+            lint: true,
+            ..Options::default()
+        };
+
+        let report = Report::build(&processor, &targets, &options);
+
+        assert_eq!(
+            report.warnings,
+            vec![
+                "Warning: renewal.crt does not follow the Baseline Requirements, its serial number has only 5 bits, too few to hold the 64 random bits it should.",
+                "Warning: renewal.crt does not follow the Baseline Requirements, it has no certificatePolicies extension.",
+                "Warning: renewal.crt does not follow the Baseline Requirements, it has no authorityInfoAccess extension.",
+                "Warning: aia-leaf.crt does not follow the Baseline Requirements, its serial number has only 4 bits, too few to hold the 64 random bits it should.",
+                "Warning: renewal.crt is valid for 199 days and 1 second, more than the 100 days the CA/B Forum will allow for TLS server certificates issued from Mar 15 00:00:00 2027 +00:00.",
+                "Warning: aia-leaf.crt and renewal.crt, both issued by CN=Example Issuing CA, have serial numbers only 7 apart, as if counted rather than random.",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_warn_about_critical_extensions_clients_do_not_recognize() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("critical.crt"),
+            include_bytes!("../resources/critical.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("critical.crt"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert!(report.to_string().contains(
+            "Critical extensions: basicConstraints, keyUsage, 1.3.6.1.4.1.32473.2 (not recognized)\n"
+        ));
+        assert_eq!(
+            report.warnings,
+            vec!["Warning: critical.crt has the critical extension 1.3.6.1.4.1.32473.2, which most clients don't recognize, so they will reject it."]
+        );
+    }
+
+    #[test]
+    fn should_show_internationalized_names_in_unicode() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("idn.crt"),
+            include_bytes!("../resources/idn.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("idn.crt"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert!(report.to_string().contains(
+            "Internationalized names:\n    \
+             xn--mller-kva.example: müller.example\n    \
+             xn--80ak6aa92e.com: аррӏе.com\n"
+        ));
+        assert_eq!(
+            report.warnings,
+            vec![
+                "Warning: idn.crt has the name xn--80ak6aa92e.com (аррӏе.com), which may be a spoof, its label аррӏе is Cyrillic letters that pass for the Latin apple.",
+                "Warning: idn.crt has the name xn--pple-43d.com (аpple.com), which may be a spoof, its label аpple mixes Cyrillic and Latin letters.",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_decode_every_string_type_of_the_subject() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("dn.crt"),
+            include_bytes!("../resources/dn.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("dn.crt"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert!(report.to_string().contains(
+            "Subject: C=DE, jurisdictionC=DE, businessCategory=Private Organization, \
+             serialNumber=HRB 12345, organizationIdentifier=VATDE-123456789, O=Müller GmbH, \
+             OU=Zentrale – IT, L=Köln, DC=example, emailAddress=it@example.de, CN=dn.example.de\n\
+             Issuer: CN=Example Issuing CA\n"
+        ));
+    }
+
+    #[test]
+    fn should_decode_the_other_names_of_a_smart_card_certificate() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("smartcard.crt"),
+            include_bytes!("../resources/smartcard.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("smartcard.crt"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert!(report.to_string().starts_with(
+            "Other names:\n    \
+             User principal name: alice@corp.example\n    \
+             Kerberos principal: alice@CORP.EXAMPLE\n    \
+             FASC-N: d0:43:94:58:21:0c:2c:19:a0:84:6d:83:68:5a:10:82:10:8c:e7:39:84:10:8c:a3:fc\n        \
+             Agency code: 0032\n"
+        ));
+    }
+
+    #[test]
+    fn should_warn_about_or_refuse_deviations_by_strictness() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("sloppy.crt"),
+            include_bytes!("../resources/sloppy.crt").to_vec(),
+        );
+        let targets = vec![Target::Path(String::from("sloppy.crt"))];
+        let with = |strictness| Options {
+            strictness,
+            ..Options::default()
+        };
+
+        let default = Report::build(&processor, &targets, &with(Strictness::Default));
+        let lenient = Report::build(&processor, &targets, &with(Strictness::Lenient));
+        let strict = Report::build(&processor, &targets, &with(Strictness::Strict));
+
+        assert_eq!(default.entries.len(), 1);
+        assert!(default.warnings.is_empty());
+        assert_eq!(lenient.entries.len(), 1);
+        assert_eq!(
+            lenient.warnings,
+            vec![
+                "Warning: sloppy.crt does not strictly follow DER and RFC 5280, it is followed by 4 bytes of trailing data.",
+                "Warning: sloppy.crt does not strictly follow DER and RFC 5280, its serial number is negative, and is shown as 332448088244605016775547121450601670905 as if it were not.",
+                "Warning: sloppy.crt does not strictly follow DER and RFC 5280, the O of its subject, Smith & Sons_Ltd, has characters a PrintableString can't hold.",
+            ]
+        );
+        assert!(strict.entries.is_empty());
+        assert_eq!(
+            strict.failures[0].error.to_string(),
+            "Error: sloppy.crt does not strictly follow DER and RFC 5280, it is followed by 4 bytes of trailing data; \
+             its serial number is negative, and is shown as 332448088244605016775547121450601670905 as if it were not; \
+             the O of its subject, Smith & Sons_Ltd, has characters a PrintableString can't hold."
+        );
+    }
+
+    #[test]
+    fn should_explain_where_a_certificate_stopped_decoding() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/renewal.crt")).unwrap();
+        let mut processor = FakeProcessor::default();
+        processor
+            .binaries
+            .insert(String::from("renewal.der"), pem.contents[..100].to_vec());
+        let targets = vec![Target::Path(String::from("renewal.der"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert_eq!(
+            report.failures[0].error.to_string(),
+            "Error: could not decode renewal.der past byte 91 of 100, in the subject of the TBS certificate \
+             (a SEQUENCE of 30 bytes): its length is 30 bytes, but only 7 follow.\n\
+             Decoded before it:\n    \
+             Version: 3\n    \
+             Serial number: 22\n    \
+             Signature algorithm: ecdsa-with-SHA256\n    \
+             Issuer: CN=Example Issuing CA\n    \
+             Not before: Apr  1 00:00:00 2026 +00:00\n    \
+             Not after: Oct 17 00:00:00 2026 +00:00"
+        );
+    }
+
+    #[test]
+    fn should_decode_a_certificate_pasted_among_other_text() {
+        let quoted: Vec<String> = include_str!("../resources/renewal.crt")
+            .lines()
+            .map(|line| format!("> {}", line))
+            .collect();
+        let paste = format!(
+            "Alice: the cert from the load balancer is\n{}\nBob: looks expired to me\n",
+            quoted.join("\n")
+        );
+        let mut processor = FakeProcessor::default();
+        processor
+            .binaries
+            .insert(String::from("chat.txt"), paste.into_bytes());
+        let targets = vec![Target::Path(String::from("chat.txt"))];
+
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        assert!(report.failures.is_empty());
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(
+            report.entries[0].certificate().subject().to_string(),
+            "CN=renewal.example.com"
+        );
+    }
+
+    #[test]
+    fn should_keep_only_certificates_matching_the_expiry_filter() {
+        let (_, google) = parse_x509_pem(include_bytes!("../resources/google.com.crt")).unwrap();
+        let not_after = google
+            .parse_x509()
+            .unwrap()
+            .validity()
+            .not_after
+            .timestamp();
+        let mut processor = FakeProcessor {
+            now: not_after - 10 * 24 * 60 * 60,
+            ..FakeProcessor::default()
+        };
+        processor.add_file(
+            "certs/google.crt",
+            include_str!("../resources/google.com.crt"),
+        );
+        processor.add_file(
+            "certs/renewal.crt",
+            include_str!("../resources/renewal.crt"),
+        );
+        processor.add_dir("certs", &["certs/google.crt", "certs/renewal.crt"]);
+        let targets = vec![Target::Path(String::from("certs"))];
+        let with = |expiry_filter| Options {
+            expiry_filter,
+            ..Options::default()
+        };
+        let expiring = ExpiryFilter {
+            expiring_within: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            ..ExpiryFilter::default()
+        };
+        let expired = ExpiryFilter {
+            expired: true,
+            ..ExpiryFilter::default()
+        };
+
+        let expiring = Report::build(&processor, &targets, &with(expiring));
+        let expired = Report::build(&processor, &targets, &with(expired));
+
+        let locations: Vec<&str> = expiring
+            .entries
+            .iter()
+            .map(|entry| entry.location.as_str())
+            .collect();
+        assert_eq!(locations, vec!["certs/google.crt"]);
+        assert!(expired.entries.is_empty());
+        assert_eq!(expired.to_string(), "No certificates are expired.\n");
+    }
+
+    #[test]
+    fn should_show_a_certificate_found_in_several_places_once() {
+        let mut processor = FakeProcessor::default();
+        processor.add_remote("a.example.com:443", vec![google_der()]);
+        processor.add_remote("b.example.com:443", vec![google_der()]);
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        processor.add_file("renewal.crt", include_str!("../resources/renewal.crt"));
+        processor.add_addresses("a.example.com", &[ip("192.0.2.1")]);
+        processor.add_addresses("b.example.com", &[ip("192.0.2.2")]);
+        let targets = vec![
+            Target::Path(String::from("google.crt")),
+            Target::Remote {
+                host: String::from("a.example.com"),
+                port: 443,
+            },
+            Target::Path(String::from("renewal.crt")),
+            Target::Remote {
+                host: String::from("b.example.com"),
+                port: 443,
+            },
+        ];
+        let options = Options {
+            dedupe: true,
+            ..Options::default()
+        };
+
+        let report = Report::build(&processor, &targets, &options);
+
+        let locations: Vec<&str> = report
+            .entries
+            .iter()
+            .map(|entry| entry.location.as_str())
+            .collect();
+        assert_eq!(locations, vec!["google.crt", "renewal.crt"]);
+        assert_eq!(
+            report.entries[0].duplicates,
+            vec!["a.example.com:443", "b.example.com:443"]
+        );
+        assert!(report.to_string().starts_with(
+            "==> google.crt <==\nAlso found at:\n    a.example.com:443\n    b.example.com:443\n"
+        ));
+    }
+
+    #[test]
+    fn should_sort_and_group_certificates_by_issuer() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("google.crt", include_str!("../resources/google.com.crt"));
+        processor.add_file("renewal.crt", include_str!("../resources/renewal.crt"));
+        processor.add_file("dn.crt", include_str!("../resources/dn.crt"));
+        let targets = vec![
+            Target::Path(String::from("renewal.crt")),
+            Target::Path(String::from("google.crt")),
+            Target::Path(String::from("dn.crt")),
+        ];
+        let options = Options {
+            sort_by: Some(SortBy::Subject),
+            group_by: Some(GroupBy::Issuer),
+            ..Options::default()
+        };
+
+        let report = Report::build(&processor, &targets, &options);
+
+        let locations: Vec<&str> = report
+            .entries
+            .iter()
+            .map(|entry| entry.location.as_str())
+            .collect();
+        assert_eq!(locations, vec!["google.crt", "dn.crt", "renewal.crt"]);
+        let output = report.to_string();
+        assert!(output.starts_with(
+            "=== Issued by C=US, O=Google Trust Services, CN=GTS CA 1O1 ===\n==> google.crt <==\n"
+        ));
+        assert!(output.contains("=== Issued by CN=Example Issuing CA ===\n==> dn.crt <==\n"));
+        assert_eq!(output.matches("=== Issued by").count(), 2);
+    }
+}
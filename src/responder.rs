@@ -0,0 +1,541 @@
+use std::fmt;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::parse_x509_certificate;
+use x509_parser::time::ASN1Time;
+
+use crate::cms;
+use crate::dn;
+use crate::http::{Request, Response, Url};
+use crate::lifetime;
+use crate::ocsp::{
+    self, BasicResponse, CertId, CertStatus, OcspOptions, OcspResponse, SingleResponse,
+};
+use crate::remote::ConnectOptions;
+use crate::FileProcessor;
+
+/// How far a responder's clock may be ahead before its answers are from the future.
+const CLOCK_SKEW: i64 = 5 * 60;
+
+/// The longest the Baseline Requirements let an OCSP response be valid for.
+const MAX_VALIDITY: i64 = 10 * 24 * 60 * 60;
+
+/// The nonce length RFC 8954 recommends.
+const NONCE_LENGTH: usize = 32;
+
+/// How a responder fared in one check.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Pass(String),
+    /// Allowed, but worth knowing about.
+    Warn(String),
+    Fail(String),
+}
+
+/// One way of exercising a responder.
+#[derive(Debug, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.outcome {
+            Outcome::Pass(detail) => write!(f, "PASS {}: {}", self.name, detail),
+            Outcome::Warn(detail) => write!(f, "WARN {}: {}", self.name, detail),
+            Outcome::Fail(detail) => write!(f, "FAIL {}: {}", self.name, detail),
+        }
+    }
+}
+
+/// How a responder answered requests for a certificate.
+pub struct Conformance {
+    pub url: String,
+    /// How the responder identified itself, by name or key hash.
+    pub responder: Option<String>,
+    pub serial: String,
+    pub issuer: String,
+    pub checks: Vec<Check>,
+}
+
+/// Sends a responder a request for the certificate's status by POST and by
+/// GET, one for a serial number the issuer never used, one with a nonce and
+/// one that is malformed, and checks each answer against RFC 6960, RFC 5019
+/// and the Baseline Requirements.
+pub fn test(
+    processor: &impl FileProcessor,
+    options: &OcspOptions,
+    connect: &ConnectOptions,
+) -> Result<Conformance, Box<dyn std::error::Error>> {
+    let (cert_der, issuer_der) = ocsp::load(processor, options)?;
+    let (_, cert) = parse_x509_certificate(&cert_der)?;
+    let (_, issuer) = parse_x509_certificate(&issuer_der)?;
+    let url = match &options.url {
+        Some(url) => url.clone(),
+        None => ocsp::responders(&cert).into_iter().next().ok_or_else(|| {
+            format!(
+                "Error: {} names no OCSP responder, please give one with --url.",
+                options.cert
+            )
+        })?,
+    };
+    let parsed = Url::parse(&url)?;
+    let now = processor.now();
+    let post = |body: Vec<u8>| {
+        let request =
+            Request::post(parsed.clone(), body).header("Content-Type", "application/ocsp-request");
+        processor.http(&request, connect)
+    };
+    let mut checks = Vec::new();
+    let mut responder = None;
+
+    let request = ocsp::encode_request(&issuer, cert.raw_serial(), "sha1", None)?;
+    let id = asked(&request);
+    let answer = post(request.clone()).map_err(problem);
+    let answered = answer.as_ref().map_err(String::clone).and_then(decode);
+    checks.push(Check {
+        name: "POST request",
+        outcome: match single(&answered, &id) {
+            Ok((_, single)) if single.status == CertStatus::Unknown => Outcome::Fail(format!(
+                "unknown, though the certificate was issued by {}",
+                dn::to_string(issuer.subject())
+            )),
+            Ok((_, single)) => Outcome::Pass(single.status.to_string()),
+            Err(problem) => Outcome::Fail(problem),
+        },
+    });
+    if let Ok(Some(content_type)) = answer
+        .as_ref()
+        .map(|response| response.header("Content-Type"))
+    {
+        if !content_type.starts_with("application/ocsp-response") {
+            checks.push(Check {
+                name: "Content type",
+                outcome: Outcome::Warn(format!("{}, not application/ocsp-response", content_type)),
+            });
+        }
+    }
+    if let Ok((basic, single)) = single(&answered, &id) {
+        responder = Some(basic.responder.clone());
+        checks.push(Check {
+            name: "Signature",
+            outcome: signature(basic, &issuer),
+        });
+        checks.push(Check {
+            name: "Validity",
+            outcome: validity(basic.produced_at, single, now),
+        });
+    }
+
+    let unissued = unissued_serial()?;
+    let request = ocsp::encode_request(&issuer, &unissued, "sha1", None)?;
+    let id = asked(&request);
+    let answered = post(request)
+        .map_err(problem)
+        .and_then(|response| decode(&response));
+    checks.push(Check {
+        name: "Unissued serial",
+        outcome: match (&answered, single(&answered, &id)) {
+            (Ok(response), _) if response.status == "unauthorized" => {
+                Outcome::Pass(String::from("unauthorized"))
+            }
+            (_, Ok((_, single))) if single.status == CertStatus::Good => Outcome::Fail(
+                String::from("good for a serial number that was never issued, which the Baseline Requirements forbid"),
+            ),
+            (_, Ok((_, single))) => Outcome::Pass(single.status.to_string()),
+            (_, Err(problem)) => Outcome::Fail(problem),
+        },
+    });
+
+    let nonce = ocsp::random(NONCE_LENGTH)?;
+    let request = ocsp::encode_request(&issuer, cert.raw_serial(), "sha1", Some(&nonce))?;
+    let answered = post(request)
+        .map_err(problem)
+        .and_then(|response| decode(&response));
+    checks.push(Check {
+        name: "Nonce",
+        outcome: match answered.as_ref().map(|response| response.basic.as_ref()) {
+            Ok(Some(basic)) => match &basic.nonce {
+                Some(echoed) if *echoed == nonce => Outcome::Pass(String::from("echoed")),
+                Some(echoed) => Outcome::Fail(format!(
+                    "answered with the nonce {} instead of the one sent",
+                    cms::hex(echoed)
+                )),
+                None => Outcome::Warn(String::from(
+                    "ignored, so its responses can be replayed until they expire",
+                )),
+            },
+            Ok(None) => Outcome::Fail(format!(
+                "answered {}",
+                answered.as_ref().map_or("", |response| &response.status)
+            )),
+            Err(problem) => Outcome::Fail(problem.clone()),
+        },
+    });
+
+    // RFC 5019 has requests of up to 255 bytes sent by GET, so caches can help
+    let request = ocsp::encode_request(&issuer, cert.raw_serial(), "sha1", None)?;
+    let id = asked(&request);
+    let get = Url::parse(&get_url(&url, &request))?;
+    let answer = processor.http(&Request::get(get), connect).map_err(problem);
+    let answered = answer.as_ref().map_err(String::clone).and_then(decode);
+    checks.push(Check {
+        name: "GET request",
+        outcome: match single(&answered, &id) {
+            Ok((_, single)) => {
+                let max_age = answer.as_ref().ok().and_then(max_age);
+                match max_age {
+                    Some(max_age) => Outcome::Pass(format!(
+                        "{}, cacheable for {} seconds",
+                        single.status, max_age
+                    )),
+                    None => Outcome::Warn(format!(
+                        "{}, but without a Cache-Control max-age, so caches can't help",
+                        single.status
+                    )),
+                }
+            }
+            Err(problem) => Outcome::Fail(format!("{}, though RFC 5019 requires GET", problem)),
+        },
+    });
+
+    // A request that is a SEQUENCE holding only an INTEGER
+    let malformed = vec![0x30, 0x03, 0x02, 0x01, 0x00];
+    checks.push(Check {
+        name: "Malformed request",
+        outcome: match post(malformed) {
+            Ok(response) => match ocsp::response(&response.body) {
+                Ok(answered) if answered.status == "malformedRequest" => {
+                    Outcome::Pass(answered.status)
+                }
+                Ok(answered) => Outcome::Warn(format!(
+                    "answered {} instead of malformedRequest",
+                    answered.status
+                )),
+                Err(_) => Outcome::Warn(format!(
+                    "answered with HTTP status {} instead of an OCSP malformedRequest",
+                    response.status
+                )),
+            },
+            Err(error) => Outcome::Warn(problem(error)),
+        },
+    });
+
+    Ok(Conformance {
+        url,
+        responder,
+        serial: cms::hex(cert.raw_serial()),
+        issuer: dn::to_string(issuer.subject()),
+        checks,
+    })
+}
+
+/// The CertID of a request just encoded.
+fn asked(request: &[u8]) -> CertId {
+    let request = ocsp::read("", request).expect("requests are encoded valid");
+    request.certificates[0].clone()
+}
+
+/// The URL RFC 5019 has a request sent by GET to: the responder's with the
+/// percent-encoded base64 of the request appended.
+fn get_url(url: &str, request: &[u8]) -> String {
+    let encoded = STANDARD
+        .encode(request)
+        .replace('+', "%2B")
+        .replace('/', "%2F")
+        .replace('=', "%3D");
+    format!("{}/{}", url.trim_end_matches('/'), encoded)
+}
+
+/// A random serial number of the size CAs use, which will not have been issued.
+fn unissued_serial() -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut serial = ocsp::random(16)?;
+    // Positive, and without a leading zero byte
+    serial[0] = serial[0] & 0x7f | 0x40;
+    Ok(serial)
+}
+
+/// An error as a check's detail, which reads better without the prefix.
+fn problem(error: Box<dyn std::error::Error>) -> String {
+    let error = error.to_string();
+    String::from(error.strip_prefix("Error: ").unwrap_or(&error))
+}
+
+fn decode(response: &Response) -> Result<OcspResponse, String> {
+    if response.status != 200 {
+        return Err(format!("answered with HTTP status {}", response.status));
+    }
+    ocsp::response(&response.body).map_err(|problem| format!("answered, but {}", problem))
+}
+
+/// The answer for the certificate asked about in a successful response.
+fn single<'a>(
+    answered: &'a Result<OcspResponse, String>,
+    id: &CertId,
+) -> Result<(&'a BasicResponse, &'a SingleResponse), String> {
+    let response = answered.as_ref().map_err(String::clone)?;
+    let basic = response
+        .basic
+        .as_ref()
+        .ok_or_else(|| format!("answered {}", response.status))?;
+    let single = basic
+        .responses
+        .iter()
+        .find(|single| single.id.serial == id.serial)
+        .ok_or_else(|| String::from("answered for other certificates than the one asked about"))?;
+    Ok((basic, single))
+}
+
+/// Whether the issuer signed a response, or a responder it delegated to
+/// with a certificate that has the OCSP signing extended key usage.
+fn signature(basic: &BasicResponse, issuer: &X509Certificate) -> Outcome {
+    if basic.is_signed_by(issuer) {
+        return Outcome::Pass(String::from("signed by the issuer"));
+    }
+    for der in &basic.certificates {
+        let responder = match parse_x509_certificate(der) {
+            Ok((_, responder)) => responder,
+            Err(_) => continue,
+        };
+        if !basic.is_signed_by(&responder) {
+            continue;
+        }
+        let name = dn::to_string(responder.subject());
+        if responder
+            .verify_signature(Some(issuer.public_key()))
+            .is_err()
+        {
+            return Outcome::Fail(format!(
+                "signed by {}, which the issuer did not issue",
+                name
+            ));
+        }
+        let ocsp_signing = matches!(
+            responder.extended_key_usage(),
+            Ok(Some(extended)) if extended.value.ocsp_signing
+        );
+        return match ocsp_signing {
+            true => Outcome::Pass(format!("signed by the delegated responder {}", name)),
+            false => Outcome::Fail(format!(
+                "signed by {}, which lacks the OCSPSigning extended key usage",
+                name
+            )),
+        };
+    }
+    Outcome::Fail(String::from(
+        "not signed by the issuer or a responder it delegated to",
+    ))
+}
+
+/// Whether a response is current, and not valid for longer than allowed.
+fn validity(produced_at: i64, single: &SingleResponse, now: i64) -> Outcome {
+    let time = |at: i64| {
+        ASN1Time::from_timestamp(at)
+            .map(|at| at.to_string())
+            .unwrap_or_default()
+    };
+    if produced_at > now + CLOCK_SKEW {
+        return Outcome::Fail(format!(
+            "producedAt is in the future, {}",
+            time(produced_at)
+        ));
+    }
+    let this_update = single.this_update;
+    if this_update > now + CLOCK_SKEW {
+        return Outcome::Fail(format!(
+            "thisUpdate is in the future, {}",
+            time(this_update)
+        ));
+    }
+    let next_update = match single.next_update {
+        Some(next_update) => next_update,
+        None => {
+            return Outcome::Warn(String::from(
+                "no nextUpdate, so clients can't tell when to fetch a newer response",
+            ))
+        }
+    };
+    let period = next_update - this_update;
+    if next_update < now {
+        Outcome::Fail(format!("expired on {}", time(next_update)))
+    } else if period > MAX_VALIDITY {
+        Outcome::Warn(format!(
+            "valid for {}, more than the 10 days the Baseline Requirements allow",
+            lifetime::describe(period)
+        ))
+    } else {
+        Outcome::Pass(format!(
+            "valid for {}, until {}",
+            lifetime::describe(period),
+            time(next_update)
+        ))
+    }
+}
+
+/// The max-age a response's Cache-Control gives it.
+fn max_age(response: &Response) -> Option<u64> {
+    response
+        .header("Cache-Control")?
+        .split(',')
+        .filter_map(|directive| directive.trim().strip_prefix("max-age="))
+        .find_map(|age| age.parse().ok())
+}
+
+impl Conformance {
+    /// An error when the responder failed any check, so scripts can tell.
+    pub fn status(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let failed = self
+            .checks
+            .iter()
+            .filter(|check| matches!(check.outcome, Outcome::Fail(_)))
+            .count();
+        match failed {
+            0 => Ok(()),
+            failed => Err(format!(
+                "Error: the OCSP responder at {} failed {} check(s).",
+                self.url, failed
+            )
+            .into()),
+        }
+    }
+}
+
+impl fmt::Display for Conformance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "OCSP responder: {}", self.url)?;
+        writeln!(
+            f,
+            "Certificate: serial {}, issued by {}",
+            self.serial, self.issuer
+        )?;
+        if let Some(responder) = &self.responder {
+            writeln!(f, "Responder ID: {}", responder)?;
+        }
+        for check in &self.checks {
+            writeln!(f, "{}", check)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+
+    use super::{get_url, test};
+    use crate::http::Response;
+    use crate::ocsp::{encode_request, OcspCommand, OcspOptions};
+    use crate::remote::ConnectOptions;
+    use crate::test::FakeProcessor;
+
+    const URL: &str = "http://ocsp.example.com/";
+
+    fn options() -> OcspOptions {
+        OcspOptions {
+            command: OcspCommand::Test,
+            cert: String::from("cert.pem"),
+            issuer: Some(String::from("cert.pem")),
+            url: Some(String::from(URL)),
+            ..OcspOptions::default()
+        }
+    }
+
+    fn answer(headers: &[(&str, &str)]) -> Response {
+        Response {
+            status: 200,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (String::from(*name), String::from(*value)))
+                .collect(),
+            body: include_bytes!("../resources/ocsp-response.der").to_vec(),
+        }
+    }
+
+    #[test]
+    fn should_check_each_way_of_asking_a_responder() {
+        let cert = include_str!("../resources/self-signed.crt");
+        let mut processor = FakeProcessor {
+            // 2026-10-16
+            now: 1792108800,
+            ..FakeProcessor::default()
+        };
+        processor.add_file("cert.pem", cert);
+        let content_type = ("Content-Type", "application/ocsp-response");
+        processor
+            .responses
+            .insert(String::from(URL), answer(&[content_type]));
+        let der = x509_parser::pem::parse_x509_pem(cert.as_bytes()).unwrap().1;
+        let (_, issuer) = parse_x509_certificate(&der.contents).unwrap();
+        let request = encode_request(&issuer, issuer.raw_serial(), "sha1", None).unwrap();
+        processor.responses.insert(
+            get_url(URL, &request),
+            answer(&[content_type, ("Cache-Control", "max-age=3600, public")]),
+        );
+
+        let conformance = test(&processor, &options(), &ConnectOptions::default()).unwrap();
+        let output = conformance.to_string();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "OCSP responder: http://ocsp.example.com/");
+        assert!(lines[1].starts_with("Certificate: serial 12:b0:16:72"));
+        assert_eq!(
+            lines[2],
+            "Responder ID: key 27:ab:89:91:a0:dc:fb:5a:e4:18:d4:f0:d5:dd:18:cc:52:29:2c:f4"
+        );
+        assert_eq!(lines[3], "PASS POST request: good");
+        assert_eq!(lines[4], "PASS Signature: signed by the issuer");
+        assert!(lines[5].starts_with("PASS Validity: valid for 4 days"));
+        // Every POST gets the same answer, which is for another serial number
+        assert_eq!(
+            lines[6],
+            "FAIL Unissued serial: answered for other certificates than the one asked about"
+        );
+        assert_eq!(
+            lines[7],
+            "WARN Nonce: ignored, so its responses can be replayed until they expire"
+        );
+        assert_eq!(
+            lines[8],
+            "PASS GET request: good, cacheable for 3600 seconds"
+        );
+        assert_eq!(
+            lines[9],
+            "WARN Malformed request: answered successful instead of malformedRequest"
+        );
+        assert_eq!(
+            conformance.status().err().unwrap().to_string(),
+            "Error: the OCSP responder at http://ocsp.example.com/ failed 1 check(s)."
+        );
+    }
+
+    #[test]
+    fn should_fail_a_stale_response_and_an_unreachable_responder() {
+        let mut processor = FakeProcessor {
+            // 2026-10-20
+            now: 1792454400,
+            ..FakeProcessor::default()
+        };
+        processor.add_file("cert.pem", include_str!("../resources/self-signed.crt"));
+        processor.responses.insert(String::from(URL), answer(&[]));
+
+        let conformance = test(&processor, &options(), &ConnectOptions::default()).unwrap();
+        let output = conformance.to_string();
+        assert!(output.contains("FAIL Validity: expired on Oct 18 00:00:00 2026 +00:00\n"));
+        assert!(output.contains("FAIL GET request: could not connect to http://ocsp.example.com/"));
+
+        let options = OcspOptions {
+            url: None,
+            ..options()
+        };
+        assert_eq!(
+            test(&processor, &options, &ConnectOptions::default())
+                .err()
+                .unwrap()
+                .to_string(),
+            "Error: cert.pem names no OCSP responder, please give one with --url."
+        );
+    }
+}
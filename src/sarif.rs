@@ -0,0 +1,93 @@
+use crate::findings::{Finding, Level, RULES};
+use crate::json;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The findings as a SARIF 2.1.0 log, which GitHub code scanning and other
+/// security dashboards ingest, each at the file or server it was found in.
+pub fn to_string(findings: &[Finding]) -> String {
+    let rules: Vec<String> = RULES
+        .iter()
+        .map(|(id, description)| {
+            format!(
+                "            {{\"id\": {}, \"shortDescription\": {{\"text\": {}}}}}",
+                json::string(id),
+                json::string(description)
+            )
+        })
+        .collect();
+    let results: Vec<String> = findings
+        .iter()
+        .map(|finding| {
+            let level = match finding.level {
+                Level::Error => "error",
+                Level::Warning => "warning",
+            };
+            // SARIF locations are URIs, which only have forward slashes
+            let uri = finding.source().replace('\\', "/");
+            format!(
+                "        {{\n          \"ruleId\": {},\n          \"level\": \"{}\",\n          \
+                 \"message\": {{\"text\": {}}},\n          \"locations\": [{{\"physicalLocation\": \
+                 {{\"artifactLocation\": {{\"uri\": {}}}}}}}]\n        }}",
+                json::string(finding.rule),
+                level,
+                json::string(&finding.message),
+                json::string(&uri)
+            )
+        })
+        .collect();
+    format!(
+        "{{\n  \"$schema\": \"{}\",\n  \"version\": \"2.1.0\",\n  \"runs\": [\n    {{\n      \
+         \"tool\": {{\n        \"driver\": {{\n          \"name\": \"cert-decoder\",\n          \
+         \"version\": \"{}\",\n          \"rules\": [\n{}\n          ]\n        }}\n      }},\n      \
+         \"results\": [\n{}\n      ]\n    }}\n  ]\n}}\n",
+        SCHEMA,
+        env!("CARGO_PKG_VERSION"),
+        rules.join(",\n"),
+        results.join(",\n")
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use serde_yaml::Value;
+
+    use super::to_string;
+    use crate::findings::{Finding, Level};
+
+    #[test]
+    fn should_write_each_finding_as_a_result_at_its_file() {
+        let findings = vec![Finding {
+            rule: "expired",
+            level: Level::Error,
+            location: String::from("certs\\chain.pem [1/2]"),
+            message: String::from("CN=\"web\" expired on Sep  2 09:34:58 2020 +00:00."),
+        }];
+
+        let sarif: Value = serde_yaml::from_str(&to_string(&findings)).unwrap();
+
+        let run = &sarif["runs"][0];
+        assert_eq!(sarif["version"].as_str(), Some("2.1.0"));
+        assert_eq!(
+            run["tool"]["driver"]["rules"][1]["id"].as_str(),
+            Some("expired")
+        );
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"].as_str(), Some("expired"));
+        assert_eq!(result["level"].as_str(), Some("error"));
+        assert_eq!(
+            result["message"]["text"].as_str(),
+            Some("CN=\"web\" expired on Sep  2 09:34:58 2020 +00:00.")
+        );
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"].as_str(),
+            Some("certs/chain.pem")
+        );
+        let empty: Value = serde_yaml::from_str(&to_string(&[])).unwrap();
+        assert_eq!(
+            empty["runs"][0]["results"].as_sequence().map(Vec::len),
+            Some(0)
+        );
+    }
+}
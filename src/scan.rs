@@ -0,0 +1,195 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How hard to push when fetching chains from many servers.
+#[derive(Debug, PartialEq)]
+pub struct ScanOptions {
+    /// How many handshakes may be in flight at once.
+    pub jobs: usize,
+    /// The time between handshake starts across all jobs, from the most
+    /// handshakes to start per second.
+    pub rate_limit: Option<Duration>,
+    /// How many times to retry a failed handshake.
+    pub retries: u32,
+    /// The wait before the first retry, doubled for each one after.
+    pub backoff: Duration,
+    /// Fetch from every address a host resolves to, not just the first that answers.
+    pub all_addresses: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> ScanOptions {
+        ScanOptions {
+            jobs: 8,
+            rate_limit: None,
+            retries: 2,
+            backoff: Duration::from_millis(500),
+            all_addresses: false,
+        }
+    }
+}
+
+/// Spaces out handshake starts so they never exceed the configured rate.
+struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    fn new(interval: Duration) -> RateLimiter {
+        RateLimiter {
+            interval,
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Claims the next start slot, returning how long to wait for it.
+    fn claim(&self, now: Instant) -> Duration {
+        let mut next = self.next.lock().unwrap();
+        let slot = match *next {
+            Some(next) if next > now => next,
+            _ => now,
+        };
+        *next = Some(slot + self.interval);
+        slot - now
+    }
+
+    fn wait(&self) {
+        thread::sleep(self.claim(Instant::now()));
+    }
+}
+
+/// Runs `fetch` for every server concurrently, returning results in the same order as `servers`.
+/// Errors are returned as strings as they have to cross threads.
+pub fn fetch_all<S, T, F>(servers: &[S], options: &ScanOptions, fetch: F) -> Vec<Result<T, String>>
+where
+    S: Sync,
+    T: Send,
+    F: Fn(&S) -> Result<T, Box<dyn std::error::Error>> + Sync,
+{
+    let limiter = options.rate_limit.map(RateLimiter::new);
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::new());
+
+    thread::scope(|scope| {
+        for _ in 0..options.jobs.max(1).min(servers.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                let server = match servers.get(i) {
+                    Some(server) => server,
+                    None => break,
+                };
+                let result = fetch_with_retries(options, limiter.as_ref(), || fetch(server));
+                results.lock().unwrap().push((i, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+fn fetch_with_retries<T, F>(
+    options: &ScanOptions,
+    limiter: Option<&RateLimiter>,
+    fetch: F,
+) -> Result<T, String>
+where
+    F: Fn() -> Result<T, Box<dyn std::error::Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        if let Some(limiter) = limiter {
+            limiter.wait();
+        }
+        match fetch() {
+            Ok(fetched) => return Ok(fetched),
+            Err(error) if attempt >= options.retries => return Err(error.to_string()),
+            Err(_) => {
+                thread::sleep(options.backoff * 2u32.saturating_pow(attempt));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    use super::{fetch_all, RateLimiter, ScanOptions};
+
+    fn no_backoff() -> ScanOptions {
+        ScanOptions {
+            backoff: Duration::from_millis(0),
+            ..ScanOptions::default()
+        }
+    }
+
+    #[test]
+    fn should_return_results_in_server_order() {
+        let servers = [("a", 1), ("b", 2), ("c", 3), ("d", 4)];
+
+        let results = fetch_all(&servers, &no_backoff(), |(_, port)| {
+            Ok(vec![vec![*port as u8]])
+        });
+
+        let ports: Vec<_> = results
+            .into_iter()
+            .map(|result| result.unwrap()[0][0])
+            .collect();
+        assert_eq!(ports, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn should_retry_failed_handshakes() {
+        let attempts = AtomicUsize::new(0);
+
+        let results = fetch_all(&[("flaky", 443)], &no_backoff(), |_| {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err("Error: connection reset".into()),
+                _ => Ok(()),
+            }
+        });
+
+        assert!(results[0].is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn should_give_up_after_the_last_retry() {
+        let options = ScanOptions {
+            retries: 1,
+            ..no_backoff()
+        };
+        let attempts = AtomicUsize::new(0);
+
+        let results = fetch_all(&[("down", 443)], &options, |_| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>("Error: connection refused".into())
+        });
+
+        assert_eq!(results[0], Err(String::from("Error: connection refused")));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn should_space_out_handshakes_to_the_rate_limit() {
+        let limiter = RateLimiter::new(Duration::from_millis(250));
+        let now = Instant::now();
+
+        assert_eq!(limiter.claim(now), Duration::from_millis(0));
+        assert_eq!(limiter.claim(now), Duration::from_millis(250));
+        assert_eq!(limiter.claim(now), Duration::from_millis(500));
+        // Once the slots are in the past there is no need to wait at all
+        assert_eq!(
+            limiter.claim(now + Duration::from_secs(5)),
+            Duration::from_millis(0)
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use std::fmt;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::parse_x509_certificate;
+use x509_parser::pem::Pem;
+
+use crate::cms;
+use crate::dn;
+use crate::http::{Request, Response, Trust, Url};
+use crate::remote::ConnectOptions;
+use crate::report::Report;
+use crate::FileProcessor;
+
+/// What RFC 8894 has each GetCACaps capability mean.
+const CAPABILITIES: &[(&str, &str)] = &[
+    ("AES", "requests can be encrypted with AES"),
+    ("DES3", "requests can be encrypted with triple DES"),
+    (
+        "GetNextCACert",
+        "the next CA certificate can be fetched before a rollover",
+    ),
+    ("POSTPKIOperation", "requests can be sent by POST"),
+    (
+        "Renewal",
+        "certificates can be renewed by signing with the old one",
+    ),
+    ("SHA-1", "requests can be signed with SHA-1"),
+    ("SHA-256", "requests can be signed with SHA-256"),
+    ("SHA-512", "requests can be signed with SHA-512"),
+    (
+        "SCEPStandard",
+        "the server follows RFC 8894, so AES, SHA-256 and POST",
+    ),
+];
+
+/// The certificates a SCEP server hands out for enrolling, and what it supports.
+pub struct Scep {
+    pub url: String,
+    /// The GetCACaps capabilities, or why they could not be had.
+    pub capabilities: Result<Vec<String>, String>,
+    pub content_type: Option<String>,
+    /// Each certificate's name and what a client uses it for.
+    pub roles: Vec<(String, String)>,
+    pub report: Report,
+}
+
+/// The URL of a SCEP operation, added to whatever query the endpoint already has.
+fn operation(url: &Url, operation: &str) -> Url {
+    let separator = match url.path.contains('?') {
+        true => '&',
+        false => '?',
+    };
+    let mut url = url.clone();
+    url.path = format!("{}{}operation={}", url.path, separator, operation);
+    url
+}
+
+/// Asks a SCEP endpoint, such as an MDM's or NDES, for its capabilities and
+/// its CA and RA certificates, as a device does before enrolling. SCEP does
+/// not rely on TLS, so the server is not authenticated and the CA's
+/// fingerprint has to be checked out of band.
+pub fn get_ca_cert(
+    processor: &impl FileProcessor,
+    target: &str,
+    connect: &ConnectOptions,
+) -> Result<Scep, Box<dyn std::error::Error>> {
+    let endpoint = Url::parse(target)?;
+    let get = |url: Url| {
+        let mut request = Request::get(url);
+        request.trust = Trust::Insecure;
+        processor.http(&request, connect)
+    };
+
+    // Servers from before GetCACaps answer it with an error
+    let url = operation(&endpoint, "GetCACaps");
+    let capabilities = get(url.clone())
+        .and_then(|response| response.success(&url))
+        .map(|response| {
+            String::from_utf8_lossy(&response.body)
+                .lines()
+                .map(str::trim)
+                .filter(|capability| !capability.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .map_err(|error| error.to_string());
+
+    let url = operation(&endpoint, "GetCACert");
+    let response = get(url.clone())?.success(&url)?;
+    let content_type = response.header("Content-Type").map(String::from);
+    let certificates = certificates(&response)
+        .ok_or_else(|| format!("Error: {} did not answer with CA certificates.", url))?;
+
+    let mut report = Report::default();
+    report.warnings.extend(warnings(&capabilities));
+    let expected = match certificates.len() {
+        1 => "application/x-x509-ca-cert",
+        _ => "application/x-x509-ca-ra-cert",
+    };
+    if !content_type
+        .as_deref()
+        .is_some_and(|content_type| content_type.starts_with(expected))
+    {
+        report.warnings.push(format!(
+            "Warning: {} answered with Content-Type {}, clients expect {} for {} certificate(s).",
+            url,
+            content_type.as_deref().unwrap_or("none"),
+            expected,
+            certificates.len()
+        ));
+    }
+    let roles = certificates
+        .iter()
+        .filter_map(|der| parse_x509_certificate(der).ok())
+        .map(|(_, cert)| (dn::to_string(cert.subject()), role(&cert)))
+        .collect();
+
+    let count = certificates.len();
+    let items = certificates
+        .into_iter()
+        .enumerate()
+        .map(|(i, der)| (format!("GetCACert [{}/{}]", i + 1, count), der))
+        .collect();
+    report.add_named(&url.to_string(), Ok(items));
+    Ok(Scep {
+        url: endpoint.to_string(),
+        capabilities,
+        content_type,
+        roles,
+        report,
+    })
+}
+
+/// The certificates of a GetCACert answer: one CA certificate on its own,
+/// or the CA's and RA's in a degenerate PKCS#7. Servers don't always send the
+/// Content-Type that says which, so the body decides.
+fn certificates(response: &Response) -> Option<Vec<Vec<u8>>> {
+    if let Some(der) = cms::find(&response.body) {
+        return cms::read("", &der).ok().map(|signed| signed.certificates);
+    }
+    if parse_x509_certificate(&response.body).is_ok() {
+        return Some(vec![response.body.clone()]);
+    }
+    let pems: Vec<Vec<u8>> = Pem::iter_from_buffer(&response.body)
+        .filter_map(Result::ok)
+        .filter(|pem| pem.label == "CERTIFICATE")
+        .map(|pem| pem.contents)
+        .collect();
+    Some(pems).filter(|pems| !pems.is_empty())
+}
+
+/// What a client uses a certificate for: CA certificates issue, RA
+/// certificates are what requests are encrypted to and responses signed with.
+fn role(cert: &X509Certificate) -> String {
+    if cert.is_ca() {
+        return String::from("CA");
+    }
+    let usage = match cert.key_usage() {
+        Ok(Some(usage)) => usage.value,
+        _ => return String::from("RA, for encrypting requests and signing responses"),
+    };
+    match (usage.key_encipherment(), usage.digital_signature()) {
+        (true, true) => String::from("RA, for encrypting requests and signing responses"),
+        (true, false) => String::from("RA, for encrypting requests"),
+        (false, true) => String::from("RA, for signing responses"),
+        (false, false) => {
+            String::from("RA, with a key usage that allows neither encrypting nor signing")
+        }
+    }
+}
+
+/// The weak algorithms a client falls back to with the capabilities given.
+fn warnings(capabilities: &Result<Vec<String>, String>) -> Vec<String> {
+    let capabilities = match capabilities {
+        Ok(capabilities) => capabilities,
+        Err(_) => {
+            return vec![String::from(
+                "Warning: the SCEP server did not answer GetCACaps, so clients fall back to MD5 and DES.",
+            )]
+        }
+    };
+    let has = |capability: &str| {
+        capabilities
+            .iter()
+            .any(|offered| offered.eq_ignore_ascii_case(capability))
+    };
+    if has("SCEPStandard") {
+        return Vec::new();
+    }
+    let mut warnings = Vec::new();
+    if !has("SHA-256") && !has("SHA-512") {
+        warnings.push(String::from(
+            "Warning: the SCEP server offers no hash stronger than SHA-1, so requests are signed with SHA-1 or MD5.",
+        ));
+    }
+    if !has("AES") {
+        warnings.push(String::from(
+            "Warning: the SCEP server does not offer AES, so requests are encrypted with triple DES or DES.",
+        ));
+    }
+    warnings
+}
+
+impl fmt::Display for Scep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "SCEP server: {}", self.url)?;
+        writeln!(
+            f,
+            "Server authentication: none, SCEP does not rely on TLS, so check the CA's fingerprint out of band"
+        )?;
+        match &self.capabilities {
+            Ok(capabilities) => {
+                writeln!(f, "Capabilities:")?;
+                for capability in capabilities {
+                    let meaning = CAPABILITIES
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case(capability))
+                        .map_or("not a capability RFC 8894 defines", |(_, meaning)| meaning);
+                    writeln!(f, "    {}: {}", capability, meaning)?;
+                }
+            }
+            Err(error) => writeln!(f, "Capabilities: unknown, {}", error)?,
+        }
+        if let Some(content_type) = &self.content_type {
+            writeln!(f, "Content type: {}", content_type)?;
+        }
+        writeln!(f, "Certificates: {}", self.roles.len())?;
+        for (name, role) in &self.roles {
+            writeln!(f, "    {}: {}", name, role)?;
+        }
+        write!(f, "{}", self.report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{get_ca_cert, operation};
+    use crate::http::{Response, Url};
+    use crate::remote::ConnectOptions;
+    use crate::test::FakeProcessor;
+
+    const URL: &str = "http://mdm.example.com/scep";
+
+    fn response(content_type: &str, body: &[u8]) -> Response {
+        Response {
+            status: 200,
+            headers: vec![(String::from("Content-Type"), String::from(content_type))],
+            body: body.to_vec(),
+        }
+    }
+
+    #[test]
+    fn should_add_the_operation_to_the_query() {
+        let url = Url::parse(URL).unwrap();
+        assert_eq!(
+            operation(&url, "GetCACert").to_string(),
+            "http://mdm.example.com/scep?operation=GetCACert"
+        );
+        let url = Url::parse("http://ndes.example.com/certsrv/mscep/mscep.dll?tenant=1").unwrap();
+        assert_eq!(
+            operation(&url, "GetCACaps").to_string(),
+            "http://ndes.example.com/certsrv/mscep/mscep.dll?tenant=1&operation=GetCACaps"
+        );
+    }
+
+    #[test]
+    fn should_decode_the_ca_and_ra_certificates_and_capabilities() {
+        let mut processor = FakeProcessor::default();
+        processor.responses.insert(
+            format!("{}?operation=GetCACaps", URL),
+            response("text/plain", b"POSTPKIOperation\nSHA-256\nAES\nRenewal\n"),
+        );
+        processor.responses.insert(
+            format!("{}?operation=GetCACert", URL),
+            response(
+                "application/x-x509-ca-ra-cert",
+                include_bytes!("../resources/scep-ca-ra.p7b"),
+            ),
+        );
+
+        let scep = get_ca_cert(&processor, URL, &ConnectOptions::default()).unwrap();
+        assert!(scep.to_string().starts_with(
+            "SCEP server: http://mdm.example.com/scep\nServer authentication: none, SCEP does not rely on TLS, so check the CA's fingerprint out of band\nCapabilities:\n    POSTPKIOperation: requests can be sent by POST\n    SHA-256: requests can be signed with SHA-256\n    AES: requests can be encrypted with AES\n    Renewal: certificates can be renewed by signing with the old one\nContent type: application/x-x509-ca-ra-cert\nCertificates: 2\n    CN=self-signed.example: CA\n    CN=SCEP RA: RA, for encrypting requests and signing responses\n"
+        ));
+        assert!(scep.report.warnings.is_empty());
+    }
+
+    #[test]
+    fn should_warn_about_what_old_servers_fall_back_to() {
+        let mut processor = FakeProcessor::default();
+        let der = x509_parser::pem::parse_x509_pem(include_bytes!("../resources/self-signed.crt"))
+            .unwrap()
+            .1
+            .contents;
+        processor.responses.insert(
+            format!("{}?operation=GetCACert", URL),
+            response("application/x-x509-ca-ra-cert", &der),
+        );
+
+        let scep = get_ca_cert(&processor, URL, &ConnectOptions::default()).unwrap();
+        assert_eq!(
+            scep.roles,
+            vec![(String::from("CN=self-signed.example"), String::from("CA"))]
+        );
+        assert_eq!(
+            scep.report.warnings,
+            vec![
+                "Warning: the SCEP server did not answer GetCACaps, so clients fall back to MD5 and DES.",
+                "Warning: http://mdm.example.com/scep?operation=GetCACert answered with Content-Type application/x-x509-ca-ra-cert, clients expect application/x-x509-ca-cert for 1 certificate(s).",
+            ]
+        );
+
+        processor.responses.insert(
+            format!("{}?operation=GetCACert", URL),
+            response("text/html", b"<html>"),
+        );
+        assert_eq!(
+            get_ca_cert(&processor, URL, &ConnectOptions::default())
+                .err()
+                .unwrap()
+                .to_string(),
+            "Error: http://mdm.example.com/scep?operation=GetCACert did not answer with CA certificates."
+        );
+    }
+}
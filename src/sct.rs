@@ -0,0 +1,296 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+use serde_yaml::Value;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::ParsedExtension;
+use x509_parser::oid_registry::{OID_EC_P256, OID_KEY_TYPE_EC_PUBLIC_KEY, OID_NIST_EC_P384};
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+use crate::precertificate::tbs_without;
+use crate::provisioning::epoch_seconds;
+
+/// The extension RFC 6962 embeds SCTs in.
+const SCT_LIST: &str = "1.3.6.1.4.1.11129.2.4.2";
+
+/// The CT logs SCTs are checked against, in the format of Google's log list
+/// (https://www.gstatic.com/ct/log_list/v3/log_list.json). Give `--ct-log-list`
+/// a newer copy of that file to check against logs added since.
+const BUNDLED: &[u8] = include_bytes!("../resources/ct-log-list.json");
+
+/// A CT log, and what state it was last put in.
+#[derive(Clone, Debug)]
+pub struct Log {
+    pub description: String,
+    /// The SHA-256 of the log's key, which SCTs name it by.
+    pub id: Vec<u8>,
+    /// The log's SubjectPublicKeyInfo.
+    pub key: Vec<u8>,
+    /// The state, like `usable` or `retired`, and since when in seconds since the epoch.
+    pub state: Option<(String, i64)>,
+}
+
+/// An SCT embedded in a certificate, and whether it holds up.
+pub struct SctCheck {
+    /// The log's description, or its ID if the log is not in the list.
+    pub log: String,
+    pub timestamp: String,
+    pub signature: String,
+    /// Whether the signature was checked and found invalid.
+    pub invalid: bool,
+    /// Whether the log was qualified when it issued the SCT.
+    pub qualified: String,
+}
+
+/// The logs in the bundled log list.
+pub fn bundled() -> Vec<Log> {
+    log_list(BUNDLED).expect("the bundled log list is valid")
+}
+
+/// The logs in a log list, including those it has since retired or rejected.
+pub fn log_list(json: &[u8]) -> Result<Vec<Log>, Box<dyn std::error::Error>> {
+    let invalid = || String::from("Error: the CT log list is not valid.");
+    let list: Value = serde_yaml::from_slice(json).map_err(|_| invalid())?;
+    let mut logs = Vec::new();
+    for operator in list["operators"].as_sequence().ok_or_else(invalid)? {
+        let operator_logs = operator["logs"].as_sequence().into_iter().flatten();
+        // Static CT API logs are listed apart, but sign SCTs the same way
+        let tiled_logs = operator["tiled_logs"].as_sequence().into_iter().flatten();
+        for log in operator_logs.chain(tiled_logs) {
+            logs.push(read_log(log).ok_or_else(invalid)?);
+        }
+    }
+    Ok(logs)
+}
+
+fn read_log(log: &Value) -> Option<Log> {
+    let state = log["state"].as_mapping().and_then(|state| {
+        let (name, since) = state.iter().next()?;
+        let since = epoch_seconds(since["timestamp"].as_str()?)?;
+        Some((String::from(name.as_str()?), since))
+    });
+    Some(Log {
+        description: String::from(log["description"].as_str()?),
+        id: STANDARD.decode(log["log_id"].as_str()?).ok()?,
+        key: STANDARD.decode(log["key"].as_str()?).ok()?,
+        state,
+    })
+}
+
+/// Checks the signature of each SCT embedded in a certificate, which needs the
+/// key of the certificate's issuer, and whether its log was qualified at the time.
+pub fn check(der: &[u8], issuer: Option<&X509Certificate>, logs: &[Log]) -> Vec<SctCheck> {
+    let (_, cert) = match X509Certificate::from_der(der) {
+        Ok(parsed) => parsed,
+        Err(_) => return Vec::new(),
+    };
+    let scts = cert
+        .extensions()
+        .iter()
+        .find_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::SCT(scts) => Some(scts.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    if scts.is_empty() {
+        return Vec::new();
+    }
+    // What the log signed is the certificate as it was before the SCTs went in
+    let tbs = tbs_without(der, SCT_LIST);
+
+    let mut checks = Vec::new();
+    for sct in scts {
+        let log = logs.iter().find(|log| log.id == sct.id.key_id);
+        let seconds = (sct.timestamp / 1000) as i64;
+        let timestamp = ASN1Time::from_timestamp(seconds)
+            .map(|time| time.to_string())
+            .unwrap_or_else(|_| sct.timestamp.to_string());
+
+        let mut signed = vec![sct.version.0, 0];
+        signed.extend_from_slice(&sct.timestamp.to_be_bytes());
+        // A precert_entry, which embedded SCTs always are
+        signed.extend_from_slice(&[0, 1]);
+        let verification = match (log, issuer, &tbs) {
+            (None, _, _) => Err(String::from("not checked, the log is not in the log list")),
+            (_, None, _) => Err(String::from(
+                "not checked, the issuer certificate is needed",
+            )),
+            (_, _, None) => Err(String::from(
+                "not checked, the certificate could not be reconstructed",
+            )),
+            (Some(log), Some(issuer), Some(tbs)) => {
+                signed.extend_from_slice(digest(&SHA256, issuer.public_key().raw).as_ref());
+                signed.extend_from_slice(&(tbs.len() as u32).to_be_bytes()[1..]);
+                signed.extend_from_slice(tbs);
+                signed.extend_from_slice(&(sct.extensions.0.len() as u16).to_be_bytes());
+                signed.extend_from_slice(sct.extensions.0);
+                verify(
+                    log,
+                    (sct.signature.hash_alg_id, sct.signature.sign_alg_id),
+                    sct.signature.data,
+                    &signed,
+                )
+            }
+        };
+
+        checks.push(SctCheck {
+            log: match log {
+                Some(log) => format!("{} ({})", log.description, STANDARD.encode(&log.id)),
+                None => format!("unknown ({})", STANDARD.encode(sct.id.key_id)),
+            },
+            timestamp,
+            invalid: verification == Ok(false),
+            signature: match verification {
+                Ok(true) => String::from("valid"),
+                Ok(false) => String::from("invalid"),
+                Err(reason) => reason,
+            },
+            qualified: match log {
+                Some(log) => qualified(log, seconds),
+                None => String::from("not known"),
+            },
+        });
+    }
+    checks
+}
+
+/// Whether a log's signature over what it signed is valid, or why it can't be checked.
+fn verify(log: &Log, (hash, sign): (u8, u8), value: &[u8], signed: &[u8]) -> Result<bool, String> {
+    let (_, key) = SubjectPublicKeyInfo::from_der(&log.key)
+        .map_err(|_| String::from("not checked, the log's key is not valid"))?;
+    let curve = key
+        .algorithm
+        .parameters
+        .as_ref()
+        .and_then(|parameters| parameters.as_oid().ok());
+    // Logs sign with SHA-256, and either ECDSA (3) or RSA (1)
+    let algorithm: &dyn VerificationAlgorithm = match (hash, sign) {
+        (4, 3) if key.algorithm.algorithm == OID_KEY_TYPE_EC_PUBLIC_KEY => match curve {
+            Some(curve) if curve == OID_EC_P256 => &signature::ECDSA_P256_SHA256_ASN1,
+            Some(curve) if curve == OID_NIST_EC_P384 => &signature::ECDSA_P384_SHA256_ASN1,
+            _ => {
+                return Err(String::from(
+                    "not checked, the log's curve is not supported",
+                ))
+            }
+        },
+        (4, 1) => &signature::RSA_PKCS1_2048_8192_SHA256,
+        _ => {
+            return Err(format!(
+                "not checked, hash algorithm {} with signature algorithm {} is not supported",
+                hash, sign
+            ))
+        }
+    };
+    Ok(
+        UnparsedPublicKey::new(algorithm, &key.subject_public_key.data)
+            .verify(signed, value)
+            .is_ok(),
+    )
+}
+
+/// Whether a log was qualified at a time, going by the state it is in now.
+fn qualified(log: &Log, seconds: i64) -> String {
+    let (state, since) = match &log.state {
+        Some(state) => state,
+        None => return String::from("not known, the log list gives no state"),
+    };
+    let date = ASN1Time::from_timestamp(*since)
+        .map(|time| time.to_string())
+        .unwrap_or_default();
+    match state.as_str() {
+        "qualified" | "usable" | "readonly" if *since <= seconds => String::from("yes"),
+        "qualified" | "usable" | "readonly" => format!("no, not until {}", date),
+        "retired" if seconds < *since => format!("yes, though it was retired on {}", date),
+        "retired" => format!("no, it was retired on {}", date),
+        state => format!("no, the log is {}", state),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{bundled, check, log_list};
+    use crate::test::{google_der, self_signed_der};
+
+    const LOGS: &[u8] = include_bytes!("../resources/ct-log-list-test.json");
+
+    #[test]
+    fn should_verify_embedded_scts() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/sct.crt")).unwrap();
+        let issuer = self_signed_der();
+        let (_, issuer) = parse_x509_certificate(&issuer).unwrap();
+        let logs = log_list(LOGS).unwrap();
+
+        let checks = check(&pem.contents, Some(&issuer), &logs);
+
+        assert_eq!(checks.len(), 3);
+        assert_eq!(
+            checks[0].log,
+            "Example 'Test2026' log (zDG4c0LJw5O1elwMTqolzeCBBxMKvdeqry5lfgRJwvw=)"
+        );
+        assert_eq!(checks[0].timestamp, "Oct  1 00:05:00 2026 +00:00");
+        assert_eq!(checks[0].signature, "valid");
+        assert_eq!(checks[0].qualified, "yes");
+        assert_eq!(checks[1].signature, "invalid");
+        assert!(checks[1].invalid);
+        assert_eq!(
+            checks[2].log,
+            "unknown (iQItlrQc1a99V3qy/aZ60iIGjW204Uu1ltxxl0KgWOs=)"
+        );
+        assert_eq!(
+            checks[2].signature,
+            "not checked, the log is not in the log list"
+        );
+        assert!(!checks[2].invalid);
+    }
+
+    #[test]
+    fn should_need_the_issuer_to_verify() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/sct.crt")).unwrap();
+        let logs = log_list(LOGS).unwrap();
+
+        let checks = check(&pem.contents, None, &logs);
+
+        assert_eq!(
+            checks[0].signature,
+            "not checked, the issuer certificate is needed"
+        );
+    }
+
+    #[test]
+    fn should_tell_whether_the_log_was_qualified_at_the_time() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/sct.crt")).unwrap();
+        let list = String::from_utf8_lossy(LOGS);
+        let retired = list.replace("usable", "retired");
+        let pending = list
+            .replace("usable", "qualified")
+            .replace("2026-01-01", "2027-01-01");
+
+        let retired = check(&pem.contents, None, &log_list(retired.as_bytes()).unwrap());
+        let pending = check(&pem.contents, None, &log_list(pending.as_bytes()).unwrap());
+
+        assert_eq!(
+            retired[0].qualified,
+            "no, it was retired on Jan  1 00:00:00 2026 +00:00"
+        );
+        assert_eq!(
+            pending[0].qualified,
+            "no, not until Jan  1 00:00:00 2027 +00:00"
+        );
+    }
+
+    #[test]
+    fn should_read_the_bundled_log_list() {
+        bundled();
+
+        assert!(check(&google_der(), None, &[]).len() == 2);
+        assert!(log_list(b"{}").is_err());
+    }
+}
@@ -0,0 +1,446 @@
+use crate::FileProcessor;
+
+/// Includes are followed no deeper than this, in case a config includes itself.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// nginx directives that name a PEM file of certificates.
+const NGINX_DIRECTIVES: &[&str] = &[
+    "ssl_certificate",
+    "ssl_trusted_certificate",
+    "ssl_client_certificate",
+    "proxy_ssl_certificate",
+    "proxy_ssl_trusted_certificate",
+    "grpc_ssl_certificate",
+    "grpc_ssl_trusted_certificate",
+];
+
+/// Apache directives that name a file or directory of certificates, in lowercase
+/// as Apache doesn't mind the case.
+const APACHE_DIRECTIVES: &[&str] = &[
+    "sslcertificatefile",
+    "sslcertificatechainfile",
+    "sslcacertificatefile",
+    "sslcacertificatepath",
+    "sslproxycacertificatefile",
+    "sslproxycacertificatepath",
+    "sslproxymachinecertificatefile",
+];
+
+/// HAProxy `bind` and `server` options that name a file or directory of certificates.
+const HAPROXY_OPTIONS: &[&str] = &["crt", "ca-file", "ca-verify-file"];
+
+/// HAProxy section keywords, each starting a section that lasts until the next.
+const HAPROXY_SECTIONS: &[&str] = &["frontend", "backend", "listen", "defaults", "global"];
+
+/// A certificate file (or directory) a config refers to.
+#[derive(Debug, PartialEq)]
+pub struct Reference {
+    /// The vhost or section and directive it was found under,
+    /// e.g. `server example.com ssl_certificate`.
+    pub label: String,
+    pub path: String,
+}
+
+/// What was found by reading a web server config and everything it includes.
+#[derive(Debug, Default)]
+pub struct ServerConfig {
+    pub references: Vec<Reference>,
+    /// Config files that have PEM certificates written into them, with their contents.
+    pub inline: Vec<(String, Vec<u8>)>,
+    /// Includes that could not be read.
+    pub failures: Vec<(String, Box<dyn std::error::Error>)>,
+}
+
+/// Finds the certificates an nginx, Apache or HAProxy config refers to, following
+/// includes. Any other text file is searched for PEM certificates only.
+/// Relative paths are taken from the directory of the config unless it sets
+/// its own base with `ServerRoot`, `crt-base` or `ca-base`.
+pub fn read(
+    processor: &impl FileProcessor,
+    path: &str,
+) -> Result<ServerConfig, Box<dyn std::error::Error>> {
+    let base = match path.rsplit_once('/') {
+        Some((dir, _)) if !dir.is_empty() => String::from(dir),
+        Some(_) => String::from("/"),
+        None => String::from("."),
+    };
+    let mut parser = Parser {
+        processor,
+        config: ServerConfig::default(),
+        contexts: Vec::new(),
+        current: Vec::new(),
+        found: Vec::new(),
+        blocks: Vec::new(),
+        root: base,
+        crt_base: None,
+        ca_base: None,
+    };
+    parser.file(path, 0)?;
+    Ok(parser.finish())
+}
+
+/// A vhost or section, named once its `server_name` or `ServerName` is seen.
+struct Context {
+    kind: String,
+    name: Option<String>,
+    /// Where it starts, to tell apart those that never get a name.
+    at: String,
+}
+
+struct Parser<'a, P: FileProcessor> {
+    processor: &'a P,
+    config: ServerConfig,
+    contexts: Vec<Context>,
+    /// The indexes of the contexts currently open, innermost last.
+    current: Vec<usize>,
+    /// References with the index of their context, labelled once the contexts are named.
+    found: Vec<(Option<usize>, String, String)>,
+    /// For each open nginx block, whether it opened a context.
+    blocks: Vec<bool>,
+    root: String,
+    crt_base: Option<String>,
+    ca_base: Option<String>,
+}
+
+/// How a statement ended.
+#[derive(PartialEq)]
+enum End {
+    Semicolon,
+    Open,
+    Close,
+    Line,
+}
+
+impl<P: FileProcessor> Parser<'_, P> {
+    fn file(&mut self, path: &str, depth: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let text = self.processor.read_to_string(path)?;
+        if text.contains("-----BEGIN CERTIFICATE-----") {
+            self.config
+                .inline
+                .push((String::from(path), text.clone().into_bytes()));
+        }
+
+        for (number, line) in text.lines().enumerate() {
+            let at = format!("{}:{}", path, number + 1);
+            for (words, end) in statements(line) {
+                self.statement(&words, end, &at, depth);
+            }
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, words: &[String], end: End, at: &str, depth: usize) {
+        if end == End::Close {
+            if self.blocks.pop() == Some(true) {
+                self.current.pop();
+            }
+            if words.is_empty() {
+                return;
+            }
+        }
+        let first = match words.first() {
+            Some(first) => first.to_lowercase(),
+            None => {
+                if end == End::Open {
+                    self.blocks.push(false);
+                }
+                return;
+            }
+        };
+        let value = words.get(1).cloned();
+
+        if end == End::Open {
+            let server = first == "server";
+            if server {
+                self.open("server", at);
+            }
+            self.blocks.push(server);
+            return;
+        }
+
+        // Apache sections look like tags
+        if first.starts_with("<virtualhost") {
+            let address = value.unwrap_or_default();
+            self.open(
+                &format!("VirtualHost {}", address.trim_end_matches('>')),
+                at,
+            );
+            return;
+        }
+        if first.starts_with("</virtualhost") {
+            self.current.pop();
+            return;
+        }
+        // HAProxy sections run until the next one and statements have no semicolons.
+        // Its keywords are lowercase, unlike Apache's Listen.
+        if end == End::Line
+            && self.blocks.is_empty()
+            && HAPROXY_SECTIONS.contains(&words[0].as_str())
+        {
+            self.current.clear();
+            self.open(&first, at);
+            if let Some(context) = self.contexts.last_mut() {
+                context.name = value;
+            }
+            return;
+        }
+
+        let contexts = &mut self.contexts;
+        match first.as_str() {
+            "server_name" | "servername" => {
+                if let Some(context) = self.current.last().map(|i| &mut contexts[*i]) {
+                    context.name = context.name.take().or(value);
+                }
+            }
+            "serverroot" => {
+                if let Some(root) = value {
+                    self.root = root;
+                }
+            }
+            "crt-base" => self.crt_base = value,
+            "ca-base" => self.ca_base = value,
+            "include" | "includeoptional" => {
+                if let Some(pattern) = value {
+                    let optional = first == "includeoptional";
+                    self.include(&pattern, optional, at, depth);
+                }
+            }
+            directive
+                if NGINX_DIRECTIVES.contains(&directive)
+                    || APACHE_DIRECTIVES.contains(&directive) =>
+            {
+                if let Some(path) = value {
+                    let path = resolve(&self.root, &path);
+                    self.reference(&words[0], path);
+                }
+            }
+            _ => {
+                // HAProxy puts certificates in the options of bind and server lines
+                for pair in words.windows(2) {
+                    if HAPROXY_OPTIONS.contains(&pair[0].as_str()) {
+                        let base = match pair[0].as_str() {
+                            "crt" => self.crt_base.as_deref(),
+                            _ => self.ca_base.as_deref(),
+                        };
+                        let path = resolve(base.unwrap_or(&self.root), &pair[1]);
+                        self.reference(&pair[0], path);
+                    }
+                }
+            }
+        }
+    }
+
+    fn open(&mut self, kind: &str, at: &str) {
+        self.contexts.push(Context {
+            kind: String::from(kind),
+            name: None,
+            at: String::from(at),
+        });
+        self.current.push(self.contexts.len() - 1);
+    }
+
+    fn reference(&mut self, directive: &str, path: String) {
+        let context = self.current.last().copied();
+        self.found.push((context, String::from(directive), path));
+    }
+
+    /// Reads an included file, or every file matching a `*` in its last component.
+    fn include(&mut self, pattern: &str, optional: bool, at: &str, depth: usize) {
+        if depth >= MAX_INCLUDE_DEPTH {
+            let error = format!("Error: includes nest too deeply at {}.", at);
+            self.config.failures.push((String::from(at), error.into()));
+            return;
+        }
+        let pattern = resolve(&self.root, pattern);
+        let (dir, name) = pattern.rsplit_once('/').unwrap_or((".", &pattern));
+
+        let files = match name.split_once('*') {
+            Some((prefix, suffix)) => match self.processor.read_dir(dir) {
+                Ok(paths) => paths
+                    .into_iter()
+                    .filter(|path| {
+                        let file = path.rsplit('/').next().unwrap_or(path);
+                        file.len() >= prefix.len() + suffix.len()
+                            && file.starts_with(prefix)
+                            && file.ends_with(suffix)
+                    })
+                    .filter(|path| !self.processor.is_dir(path))
+                    .collect(),
+                Err(_) => Vec::new(),
+            },
+            None if optional && !self.processor.is_file(&pattern) => Vec::new(),
+            None => vec![pattern.clone()],
+        };
+        for file in files {
+            if let Err(error) = self.file(&file, depth + 1) {
+                let error = format!(
+                    "Error: could not read {}, included at {}: {}",
+                    file, at, error
+                );
+                self.config.failures.push((file, error.into()));
+            }
+        }
+    }
+
+    /// Labels each reference with the vhost or section it was in.
+    fn finish(mut self) -> ServerConfig {
+        let found = std::mem::take(&mut self.found);
+        for (context, directive, path) in found {
+            let label = match context.map(|i| &self.contexts[i]) {
+                Some(Context {
+                    kind,
+                    name: Some(name),
+                    ..
+                }) => format!("{} {} {}", kind, name, directive),
+                Some(Context { kind, at, .. }) => format!("{} at {} {}", kind, at, directive),
+                None => directive,
+            };
+            self.config.references.push(Reference { label, path });
+        }
+        self.config
+    }
+}
+
+/// Splits a line into statements, dropping comments and quotes.
+fn statements(line: &str) -> Vec<(Vec<String>, End)> {
+    let mut statements = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quote = None;
+
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '#') if word.is_empty() => break,
+            (None, c) if c.is_whitespace() || c == ';' || c == '{' || c == '}' => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                let end = match c {
+                    ';' => End::Semicolon,
+                    '{' => End::Open,
+                    '}' => End::Close,
+                    _ => continue,
+                };
+                statements.push((std::mem::take(&mut words), end));
+            }
+            (None, c) => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    if !words.is_empty() {
+        statements.push((words, End::Line));
+    }
+    statements
+}
+
+fn resolve(base: &str, path: &str) -> String {
+    if path.starts_with('/') {
+        return String::from(path);
+    }
+    format!("{}/{}", base.trim_end_matches('/'), path)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{read, Reference};
+    use crate::test::FakeProcessor;
+
+    fn references(processor: &FakeProcessor, path: &str) -> Vec<Reference> {
+        read(processor, path).unwrap().references
+    }
+
+    fn reference(label: &str, path: &str) -> Reference {
+        Reference {
+            label: String::from(label),
+            path: String::from(path),
+        }
+    }
+
+    #[test]
+    fn should_find_nginx_certificates_by_server_following_includes() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file(
+            "/etc/nginx/nginx.conf",
+            "http {\n  ssl_trusted_certificate /etc/ssl/ca.pem; # for stapling\n  include sites-enabled/*.conf;\n}\n",
+        );
+        processor.add_dir(
+            "/etc/nginx/sites-enabled",
+            &[
+                "/etc/nginx/sites-enabled/a.conf",
+                "/etc/nginx/sites-enabled/README",
+            ],
+        );
+        processor.add_file(
+            "/etc/nginx/sites-enabled/a.conf",
+            "server {\n  listen 443 ssl;\n  ssl_certificate \"certs/example.crt\";\n  server_name example.com www.example.com;\n  location / { root /srv; }\n}\nserver { ssl_certificate /etc/ssl/default.crt; }\n",
+        );
+
+        let found = references(&processor, "/etc/nginx/nginx.conf");
+
+        assert_eq!(
+            found,
+            vec![
+                reference("ssl_trusted_certificate", "/etc/ssl/ca.pem"),
+                reference(
+                    "server example.com ssl_certificate",
+                    "/etc/nginx/certs/example.crt"
+                ),
+                reference(
+                    "server at /etc/nginx/sites-enabled/a.conf:7 ssl_certificate",
+                    "/etc/ssl/default.crt"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_find_apache_certificates_by_virtual_host() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file(
+            "httpd.conf",
+            "ServerRoot /etc/httpd\n<VirtualHost *:443>\n    ServerName shop.example\n    SSLCertificateFile conf/shop.crt\n    SSLCACertificatePath /etc/pki/ca/\n</VirtualHost>\nIncludeOptional conf.d/missing.conf\n",
+        );
+
+        let found = references(&processor, "httpd.conf");
+
+        assert_eq!(
+            found,
+            vec![
+                reference(
+                    "VirtualHost *:443 shop.example SSLCertificateFile",
+                    "/etc/httpd/conf/shop.crt"
+                ),
+                reference(
+                    "VirtualHost *:443 shop.example SSLCACertificatePath",
+                    "/etc/pki/ca/"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_find_haproxy_certificates_by_section() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file(
+            "/etc/haproxy/haproxy.cfg",
+            "global\n    crt-base /etc/haproxy/certs\nfrontend web\n    bind :443 ssl crt site.pem crt /etc/ssl/other.pem\nbackend api\n    server api1 10.0.0.1:443 ssl ca-file ca.pem\n",
+        );
+
+        let found = references(&processor, "/etc/haproxy/haproxy.cfg");
+
+        assert_eq!(
+            found,
+            vec![
+                reference("frontend web crt", "/etc/haproxy/certs/site.pem"),
+                reference("frontend web crt", "/etc/ssl/other.pem"),
+                reference("backend api ca-file", "/etc/haproxy/ca.pem"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::borrow::Cow;
+
+use x509_parser::asn1_rs::Any;
+use x509_parser::der_parser::oid::Oid;
+
+use crate::cms::hex;
+use crate::extensions::{elements, unsigned, Field};
+
+/// The extension Intel puts in PCK certificates, and under which its fields are numbered.
+pub const SGX_EXTENSIONS: &str = "1.2.840.113741.1.13.1";
+
+const SGX_TYPES: &[&str] = &["Standard", "Scalable", "Scalable with integrity"];
+
+/// Decodes the SGX extensions of a PCK certificate, which DCAP quote verification
+/// matches against the TCB info of the platform's FMSPC.
+pub fn pck_extensions(value: &[u8]) -> Option<Vec<Field>> {
+    let extensions = elements(value)?;
+    let mut fields = Vec::new();
+    for (number, value) in numbered(extensions.first()?)? {
+        let field = match number {
+            1 => Field::new("PPID", hex(value.data)),
+            2 => Field::group("TCB", tcb(&value)?),
+            3 => Field::new("PCE ID", upper_hex(value.data)),
+            // Intel's PCS API takes the FMSPC as it is written here
+            4 => Field::new("FMSPC", upper_hex(value.data)),
+            5 => {
+                let sgx_type = unsigned(&value)? as usize;
+                Field::new("SGX type", SGX_TYPES.get(sgx_type)?.to_string())
+            }
+            6 => Field::new("Platform instance ID", hex(value.data)),
+            7 => Field::group("Configuration", configuration(&value)?),
+            _ => Field::new(&format!("{}.{}", SGX_EXTENSIONS, number), hex(value.data)),
+        };
+        fields.push(field);
+    }
+    Some(fields)
+}
+
+/// The SVN of each of the sixteen TCB components, the PCESVN and the CPUSVN.
+fn tcb(value: &Any) -> Option<Vec<Field>> {
+    let mut components = Vec::new();
+    let mut fields = Vec::new();
+    for (number, value) in numbered(value)? {
+        match number {
+            1..=16 => components.push(unsigned(&value)?.to_string()),
+            17 => fields.push(Field::new("PCESVN", unsigned(&value)?.to_string())),
+            18 => fields.push(Field::new("CPUSVN", hex(value.data))),
+            _ => {}
+        }
+    }
+    fields.insert(0, Field::new("Component SVNs", components.join(", ")));
+    Some(fields)
+}
+
+/// Whether a platform CA certificate's platform is multi-package, and how.
+fn configuration(value: &Any) -> Option<Vec<Field>> {
+    let mut fields = Vec::new();
+    for (number, value) in numbered(value)? {
+        let name = match number {
+            1 => "Dynamic platform",
+            2 => "Cached keys",
+            3 => "SMT enabled",
+            _ => continue,
+        };
+        let enabled = value.data.first().is_some_and(|byte| *byte != 0);
+        fields.push(Field::new(name, if enabled { "yes" } else { "no" }));
+    }
+    Some(fields)
+}
+
+/// Every field is a sequence of an OID and a value, and the OID is the
+/// parent's with a number added, which is all that tells them apart.
+fn numbered<'a>(sequence: &Any<'a>) -> Option<Vec<(u64, Any<'a>)>> {
+    let mut fields = Vec::new();
+    for field in elements(sequence.data)? {
+        let mut parts = elements(field.data)?.into_iter();
+        let oid = Oid::new(Cow::Borrowed(parts.next()?.data));
+        let number = oid.iter()?.last()?;
+        fields.push((number, parts.next()?));
+    }
+    Some(fields)
+}
+
+fn upper_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+
+    use super::{pck_extensions, SGX_EXTENSIONS};
+
+    const CERT: &[u8] = include_bytes!("../resources/sgx-pck.crt");
+
+    #[test]
+    fn should_decode_the_tcb_and_fmspc_of_a_pck_certificate() {
+        let (_, pem) = x509_parser::pem::parse_x509_pem(CERT).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+        let extension = cert
+            .extensions()
+            .iter()
+            .find(|extension| extension.oid.to_id_string() == SGX_EXTENSIONS)
+            .unwrap();
+
+        let fields = pck_extensions(extension.value).unwrap();
+
+        assert_eq!(fields[1].name, "TCB");
+        assert_eq!(
+            fields[1].fields[0].value,
+            "4, 4, 3, 3, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0"
+        );
+        assert_eq!(fields[1].fields[1].value, "11");
+        assert_eq!(fields[3].value, "00906ED50000");
+        assert_eq!(fields[4].value, "Standard");
+    }
+}
@@ -0,0 +1,106 @@
+use x509_parser::asn1_rs::Any;
+
+use crate::extensions::{context_tag, elements, unsigned, Field};
+
+/// The extension of RFC 8226 that says which telephone numbers a STIR/SHAKEN
+/// certificate may sign calls for.
+pub const TN_AUTH_LIST: &str = "1.3.6.1.5.5.7.1.26";
+
+/// Decodes a TNAuthList: the service provider codes, number ranges and single
+/// numbers the certificate holder is authorised for.
+pub fn tn_auth_list(value: &[u8]) -> Option<Vec<Field>> {
+    let list = elements(value)?;
+    let mut fields = Vec::new();
+    for entry in elements(list.first()?.data)? {
+        let field = match context_tag(&entry)? {
+            0 => Field::new("Service provider code", text(&entry)?),
+            1 => Field::new("Telephone numbers", ranges(&entry)?),
+            2 => Field::new("Telephone number", text(&entry)?),
+            _ => return None,
+        };
+        fields.push(field);
+    }
+    Some(fields)
+}
+
+/// The string of an entry, which RFC 8226 tags explicitly but some issuers tag implicitly.
+fn text(entry: &Any) -> Option<String> {
+    let value = match elements(entry.data) {
+        Some(inner) if inner.len() == 1 && inner[0].tag().0 == 22 => inner[0].data,
+        _ => entry.data,
+    };
+    Some(String::from_utf8_lossy(value).into_owned())
+}
+
+/// A range of numbers, or the list of them RFC 9448 allows, each as the first and last number.
+fn ranges(entry: &Any) -> Option<String> {
+    let range = elements(entry.data)?.into_iter().next()?;
+    let fields = elements(range.data)?;
+    let ranges = match fields.first()?.tag().0 {
+        16 => fields
+            .iter()
+            .map(|range| self::range(&elements(range.data)?))
+            .collect::<Option<Vec<_>>>()?,
+        _ => vec![self::range(&fields)?],
+    };
+    Some(ranges.join(", "))
+}
+
+fn range(fields: &[Any]) -> Option<String> {
+    let start = String::from_utf8_lossy(fields.first()?.data).into_owned();
+    let count = unsigned(fields.get(1)?)?;
+    // Numbers are counted up from the start, keeping its leading zeros
+    let last = start
+        .parse::<u64>()
+        .ok()
+        .and_then(|first| first.checked_add(count.checked_sub(1)?))
+        .map(|last| format!("{:0width$}", last, width = start.len()))?;
+    Some(format!("{} to {} ({} numbers)", start, last, count))
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::{tn_auth_list, TN_AUTH_LIST};
+
+    const CERT: &[u8] = include_bytes!("../resources/shaken.crt");
+
+    #[test]
+    fn should_decode_the_service_provider_code_and_numbers() {
+        let (_, pem) = parse_x509_pem(CERT).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+        let extension = cert
+            .extensions()
+            .iter()
+            .find(|extension| extension.oid.to_id_string() == TN_AUTH_LIST)
+            .unwrap();
+
+        let fields = tn_auth_list(extension.value).unwrap();
+
+        assert_eq!(fields[0].name, "Service provider code");
+        assert_eq!(fields[0].value, "709J");
+        assert_eq!(fields[1].value, "12025550100 to 12025550149 (50 numbers)");
+        assert_eq!(fields[2].value, "12025550123");
+    }
+
+    #[test]
+    fn should_decode_implicitly_tagged_codes_and_lists_of_ranges() {
+        // [0] IMPLICIT "123A", then [1] holding two ranges of two numbers, from 0100 and 0200
+        let value = [
+            0x30, 0x20, 0x80, 0x04, 0x31, 0x32, 0x33, 0x41, 0xa1, 0x18, 0x30, 0x16, 0x30, 0x09,
+            0x16, 0x04, 0x30, 0x31, 0x30, 0x30, 0x02, 0x01, 0x02, 0x30, 0x09, 0x16, 0x04, 0x30,
+            0x32, 0x30, 0x30, 0x02, 0x01, 0x02,
+        ];
+
+        let fields = tn_auth_list(&value).unwrap();
+
+        assert_eq!(fields[0].value, "123A");
+        assert_eq!(
+            fields[1].value,
+            "0100 to 0101 (2 numbers), 0200 to 0201 (2 numbers)"
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::convert::TryFrom;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::oid::Oid;
+
+use crate::extensions::Field;
+use crate::oid;
+
+/// The SM2 curve, which Chinese commercial cryptography certificates put
+/// their keys on, either as an EC key's curve or as the key algorithm itself.
+pub const SM2: &str = "1.2.156.10197.1.301";
+
+/// The algorithms of GM/T 0006, the Chinese commercial cryptography standards.
+const NAMES: &[(&str, &str)] = &[
+    ("1.2.156.10197.1.104", "SM4"),
+    ("1.2.156.10197.1.301", "SM2"),
+    ("1.2.156.10197.1.301.1", "SM2 signature"),
+    ("1.2.156.10197.1.301.2", "SM2 key exchange"),
+    ("1.2.156.10197.1.301.3", "SM2 encryption"),
+    ("1.2.156.10197.1.401", "SM3"),
+    ("1.2.156.10197.1.501", "SM3 with SM2"),
+    ("1.2.156.10197.1.502", "SHA-1 with SM2"),
+    ("1.2.156.10197.1.503", "SHA-256 with SM2"),
+];
+
+/// The name of an SM algorithm.
+pub fn name(oid: &Oid) -> Option<&'static str> {
+    let oid = oid.to_id_string();
+    NAMES
+        .iter()
+        .find(|(known, _)| *known == oid)
+        .map(|(_, name)| *name)
+}
+
+/// Decodes the SM2 key of a certificate and the algorithm it was signed with.
+/// Other certificates are left alone.
+pub fn key(cert: &X509Certificate) -> Option<Vec<Field>> {
+    let spki = cert.public_key();
+    let curve = spki
+        .algorithm
+        .parameters
+        .as_ref()
+        .and_then(|parameters| Oid::try_from(parameters).ok());
+    let on_sm2 = spki.algorithm.algorithm.to_id_string() == SM2
+        || curve.is_some_and(|curve| curve.to_id_string() == SM2);
+    if !on_sm2 {
+        return None;
+    }
+    // An uncompressed point, both coordinates after a leading 04
+    let bits = spki.subject_public_key.data.len().saturating_sub(1) * 8 / 2;
+    Some(vec![
+        Field::new("Curve", "SM2 (sm2p256v1)"),
+        Field::new("Key size", format!("{} bits", bits)),
+        Field::new(
+            "Signature algorithm",
+            oid::name(&cert.signature_algorithm.algorithm),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::key;
+    use crate::test::google_der;
+
+    #[test]
+    fn should_name_the_curve_and_signature_algorithm() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../resources/sm2.crt")).unwrap();
+        let (_, cert) = parse_x509_certificate(&pem.contents).unwrap();
+
+        let fields = key(&cert).unwrap();
+
+        assert_eq!(fields[0].value, "SM2 (sm2p256v1)");
+        assert_eq!(fields[1].value, "256 bits");
+        assert_eq!(fields[2].value, "SM3 with SM2");
+    }
+
+    #[test]
+    fn should_leave_other_certificates_alone() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        assert!(key(&cert).is_none());
+    }
+}
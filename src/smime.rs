@@ -0,0 +1,194 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::cms::{self, SignedData};
+
+/// Content types of the CMS part of a message, with or without the old `x-` prefix.
+const SIGNATURE_TYPES: &[&str] = &[
+    "application/pkcs7-signature",
+    "application/x-pkcs7-signature",
+    "application/pkcs7-mime",
+    "application/x-pkcs7-mime",
+];
+
+/// Whether the bytes are a MIME message, such as a `.eml` file, with an S/MIME signature.
+pub fn is_smime(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(signature)
+        .is_some()
+}
+
+/// Reads the CMS signature of an S/MIME message, whether it is detached in a
+/// `multipart/signed` part or wraps the whole message as `application/pkcs7-mime`.
+pub fn read(source: &str, bytes: &[u8]) -> Result<SignedData, Box<dyn std::error::Error>> {
+    let part = std::str::from_utf8(bytes)
+        .ok()
+        .and_then(signature)
+        .ok_or_else(|| format!("Error: {} is not an S/MIME message.", source))?;
+    let der = match part.header("content-transfer-encoding") {
+        Some(encoding) if encoding.eq_ignore_ascii_case("base64") => {
+            let encoded: String = part.body.split_whitespace().collect();
+            STANDARD.decode(encoded).map_err(|error| {
+                format!(
+                    "Error: the signature of {} is not valid base64: {}.",
+                    source, error
+                )
+            })?
+        }
+        _ => part.body.as_bytes().to_vec(),
+    };
+    cms::read(&format!("the signature of {}", source), &der)
+}
+
+/// A MIME entity, split into its headers and body.
+struct Part<'a> {
+    /// Names are lowercase, and folded values are joined back into one line.
+    headers: Vec<(String, String)>,
+    body: &'a str,
+}
+
+impl<'a> Part<'a> {
+    /// Splits an entity at the first blank line, if what comes before it is headers.
+    fn parse(text: &'a str) -> Option<Part<'a>> {
+        let (head, body) = match (text.find("\r\n\r\n"), text.find("\n\n")) {
+            (Some(crlf), Some(lf)) if lf < crlf => (&text[..lf], &text[lf + 2..]),
+            (Some(crlf), _) => (&text[..crlf], &text[crlf + 4..]),
+            (None, Some(lf)) => (&text[..lf], &text[lf + 2..]),
+            (None, None) => return None,
+        };
+
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in head.lines() {
+            if line.starts_with([' ', '\t']) {
+                let (_, value) = headers.last_mut()?;
+                value.push(' ');
+                value.push_str(line.trim());
+                continue;
+            }
+            let (name, value) = line.split_once(':')?;
+            headers.push((name.trim().to_lowercase(), String::from(value.trim())));
+        }
+        Some(Part { headers, body })
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The media type of the part, lowercase and without parameters.
+    fn media_type(&self) -> Option<String> {
+        let content_type = self.header("content-type")?;
+        let media_type = content_type.split(';').next()?;
+        Some(media_type.trim().to_lowercase())
+    }
+
+    /// A parameter of the `Content-Type` header, such as `boundary`.
+    fn parameter(&self, name: &str) -> Option<&str> {
+        self.header("content-type")?
+            .split(';')
+            .skip(1)
+            .filter_map(|parameter| parameter.split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().trim_matches('"'))
+    }
+}
+
+/// Finds the part holding the CMS signature, looking through nested multiparts.
+fn signature(text: &str) -> Option<Part<'_>> {
+    let part = Part::parse(text)?;
+    let media_type = part.media_type()?;
+    if SIGNATURE_TYPES.contains(&media_type.as_str()) {
+        return Some(part);
+    }
+    if !media_type.starts_with("multipart/") {
+        return None;
+    }
+    let boundary = part.parameter("boundary")?;
+    parts(part.body, boundary).into_iter().find_map(signature)
+}
+
+/// Splits the body of a multipart into the parts between its boundaries.
+fn parts<'a>(body: &'a str, boundary: &str) -> Vec<&'a str> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut start = None;
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let line = line.trim_end();
+        if !line.starts_with(&delimiter) {
+            continue;
+        }
+        let rest = &line[delimiter.len()..];
+        if !rest.is_empty() && rest != "--" {
+            continue;
+        }
+        if let Some(start) = start {
+            parts.push(&body[start..line_start]);
+        }
+        if rest == "--" {
+            break;
+        }
+        start = Some(offset);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+
+    use super::{is_smime, read};
+
+    const SIGNED: &str = include_str!("../resources/signed.eml");
+
+    #[test]
+    fn should_read_the_signer_and_certificates_of_a_signed_message() {
+        let signed = read("signed.eml", SIGNED.as_bytes()).unwrap();
+
+        assert!(is_smime(SIGNED.as_bytes()));
+        assert_eq!(signed.certificates.len(), 3);
+        assert_eq!(signed.signers.len(), 1);
+        let signer = &signed.signers[0];
+        assert_eq!(signer.digest_algorithm, "sha256");
+        assert_eq!(signer.signature_algorithm, "ecdsa-with-SHA256");
+        assert!(signer.signing_time.is_some());
+        let signed_by: Vec<_> = signed
+            .certificates
+            .iter()
+            .map(|der| signer.is_signed_by(&parse_x509_certificate(der).unwrap().1))
+            .collect();
+        assert_eq!(signed_by, vec![false, false, true]);
+    }
+
+    #[test]
+    fn should_read_a_message_wrapped_in_its_signature() {
+        let (_, signature) = SIGNED.split_once("filename=\"smime.p7s\"").unwrap();
+        let opaque = format!(
+            "From: alice@example.com\nContent-Type: application/pkcs7-mime;\n\tsmime-type=signed-data; name=\"smime.p7m\"\nContent-Transfer-Encoding: base64{}",
+            signature.split("------").next().unwrap()
+        );
+
+        let signed = read("opaque.eml", opaque.as_bytes()).unwrap();
+
+        assert_eq!(signed.certificates.len(), 3);
+    }
+
+    #[test]
+    fn should_not_mistake_other_files_for_messages() {
+        let unsigned = "From: alice@example.com\r\nContent-Type: text/plain\r\n\r\nHello\r\n";
+
+        assert!(!is_smime(unsigned.as_bytes()));
+        assert!(!is_smime(include_bytes!("../resources/google.com.crt")));
+        assert_eq!(
+            format!("{}", read("hello.eml", unsigned.as_bytes()).err().unwrap()),
+            "Error: hello.eml is not an S/MIME message."
+        );
+    }
+}
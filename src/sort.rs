@@ -0,0 +1,85 @@
+use crate::dn;
+use crate::report::Entry;
+
+/// The order to show certificates in, from `--sort-by`, rather than the order
+/// they were found in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortBy {
+    /// Soonest to expire first.
+    Expiry,
+    Subject,
+    Issuer,
+}
+
+impl SortBy {
+    pub fn parse(value: &str) -> Result<SortBy, Box<dyn std::error::Error>> {
+        match value.to_lowercase().as_str() {
+            "expiry" => Ok(SortBy::Expiry),
+            "subject" => Ok(SortBy::Subject),
+            "issuer" => Ok(SortBy::Issuer),
+            _ => Err(format!(
+                "Error: --sort-by expects expiry, subject or issuer, not {}.",
+                value
+            )
+            .into()),
+        }
+    }
+}
+
+/// What to gather certificates under a heading by, from `--group-by`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GroupBy {
+    Issuer,
+}
+
+impl GroupBy {
+    pub fn parse(value: &str) -> Result<GroupBy, Box<dyn std::error::Error>> {
+        match value.to_lowercase().as_str() {
+            "issuer" => Ok(GroupBy::Issuer),
+            _ => Err(format!("Error: --group-by expects issuer, not {}.", value).into()),
+        }
+    }
+
+    /// The heading a certificate goes under.
+    pub fn key(&self, entry: &Entry) -> String {
+        match self {
+            GroupBy::Issuer => dn::to_string(entry.certificate().issuer()),
+        }
+    }
+}
+
+/// Puts certificates in the order asked for, keeping those of a group together
+/// and in the order they were found where nothing tells them apart.
+pub fn arrange(entries: &mut [Entry], sort_by: Option<SortBy>, group_by: Option<GroupBy>) {
+    entries.sort_by_cached_key(|entry| {
+        let group = group_by.map(|group_by| group_by.key(entry));
+        let cert = entry.certificate();
+        let (expiry, name) = match sort_by {
+            Some(SortBy::Expiry) => (cert.validity().not_after.timestamp(), String::new()),
+            Some(SortBy::Subject) => (0, dn::to_string(cert.subject()).to_lowercase()),
+            Some(SortBy::Issuer) => (0, dn::to_string(cert.issuer()).to_lowercase()),
+            None => (0, String::new()),
+        };
+        (group, expiry, name)
+    });
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{GroupBy, SortBy};
+
+    #[test]
+    fn should_parse_what_to_sort_and_group_by() {
+        assert_eq!(SortBy::parse("Expiry").unwrap(), SortBy::Expiry);
+        assert_eq!(GroupBy::parse("issuer").unwrap(), GroupBy::Issuer);
+        assert_eq!(
+            format!("{}", SortBy::parse("serial").err().unwrap()),
+            "Error: --sort-by expects expiry, subject or issuer, not serial."
+        );
+        assert_eq!(
+            format!("{}", GroupBy::parse("subject").err().unwrap()),
+            "Error: --group-by expects issuer, not subject."
+        );
+    }
+}
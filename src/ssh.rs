@@ -0,0 +1,589 @@
+use std::convert::{TryFrom, TryInto};
+
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use base64::Engine;
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY, SHA256};
+use x509_parser::time::ASN1Time;
+
+/// What OpenSSH appends to a key type to name the certificate of that kind of key.
+const CERTIFICATE_SUFFIX: &str = "-cert-v01@openssh.com";
+
+/// An OpenSSH certificate, as described in PROTOCOL.certkeys.
+pub struct SshCertificate {
+    pub location: String,
+    /// The certificate type, like `ssh-ed25519-cert-v01@openssh.com`.
+    pub key_type: String,
+    /// Whether it certifies a user or a host.
+    pub kind: String,
+    pub key_id: String,
+    pub serial: u64,
+    /// The fingerprint of the key it certifies.
+    pub public_key: String,
+    /// The blob of the key it certifies, and of the CA key that signed it.
+    pub key: Vec<u8>,
+    pub ca_key: Vec<u8>,
+    /// The users or host names it is valid for, where none means any.
+    pub principals: Vec<String>,
+    pub valid_after: String,
+    pub valid_before: String,
+    pub critical_options: Vec<(String, String)>,
+    pub extensions: Vec<String>,
+    /// The fingerprint of the CA key that signed it.
+    pub signing_ca: String,
+    pub signature_algorithm: String,
+    /// For a host certificate, the SSHFP records of its key and how they compare.
+    pub sshfp: Vec<String>,
+    pub checks: Vec<HostKeyCheck>,
+}
+
+/// An OpenSSH public key, as written to a `.pub` file.
+pub struct SshKey {
+    pub location: String,
+    pub fingerprint: String,
+    pub comment: Option<String>,
+    pub sshfp: Vec<String>,
+    pub checks: Vec<HostKeyCheck>,
+}
+
+/// What a host key was compared with, from `--known-hosts` and `--sshfp`.
+#[derive(Debug, Default)]
+pub struct HostKeyReferences {
+    /// The contents of the known_hosts file.
+    pub known_hosts: Option<String>,
+    /// The host name, the SSHFP records DNS gave for it, and whether the
+    /// resolver validated them with DNSSEC.
+    pub sshfp: Option<(String, Vec<String>, bool)>,
+}
+
+/// How a host key compared with a known_hosts file or DNS.
+pub struct HostKeyCheck {
+    /// Where the key was looked for, like `known_hosts`.
+    pub source: String,
+    pub outcome: String,
+    /// Whether the key was found and trusted there.
+    pub confirmed: bool,
+}
+
+/// The SSH wire encoding: big-endian integers, and strings prefixed with their length.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_be_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn string(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn text(&mut self) -> Option<String> {
+        Some(String::from_utf8_lossy(self.string()?).into_owned())
+    }
+
+    /// The strings packed one after another into a string, as principals,
+    /// options and extensions are.
+    fn strings(&mut self) -> Option<Vec<&'a [u8]>> {
+        let mut packed = Reader {
+            bytes: self.string()?,
+        };
+        let mut strings = Vec::new();
+        while !packed.bytes.is_empty() {
+            strings.push(packed.string()?);
+        }
+        Some(strings)
+    }
+}
+
+/// The blobs of the OpenSSH certificates in a file, one to a line as `ssh-keygen`
+/// writes them to a `-cert.pub` file.
+pub fn find(bytes: &[u8]) -> Vec<Vec<u8>> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let key_type = words.next()?;
+            if !key_type.ends_with(CERTIFICATE_SUFFIX) {
+                return None;
+            }
+            STANDARD.decode(words.next()?).ok()
+        })
+        .collect()
+}
+
+/// The blobs and comments of the plain OpenSSH public keys in a file, one to a line.
+pub fn find_keys(bytes: &[u8]) -> Vec<(Vec<u8>, Option<String>)> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.splitn(3, char::is_whitespace);
+            let key_type = words.next()?;
+            key_fields(key_type)?;
+            let blob = STANDARD.decode(words.next()?).ok()?;
+            let comment = words
+                .next()
+                .map(str::trim)
+                .filter(|comment| !comment.is_empty());
+            Some((blob, comment.map(String::from)))
+        })
+        .collect()
+}
+
+pub fn read_key(
+    source: &str,
+    blob: &[u8],
+    comment: Option<String>,
+) -> Result<SshKey, Box<dyn std::error::Error>> {
+    let fingerprint = fingerprint(blob)
+        .ok_or_else(|| format!("Error: {} is not a valid OpenSSH public key.", source))?;
+    Ok(SshKey {
+        location: String::from(source),
+        fingerprint,
+        comment,
+        sshfp: sshfp(blob),
+        checks: Vec::new(),
+    })
+}
+
+pub fn read(source: &str, blob: &[u8]) -> Result<SshCertificate, Box<dyn std::error::Error>> {
+    certificate(source, blob)
+        .ok_or_else(|| format!("Error: {} is not a valid OpenSSH certificate.", source).into())
+}
+
+fn certificate(source: &str, blob: &[u8]) -> Option<SshCertificate> {
+    let mut reader = Reader { bytes: blob };
+    let key_type = String::from_utf8(reader.string()?.to_vec()).ok()?;
+    let plain_type = key_type.strip_suffix(CERTIFICATE_SUFFIX)?;
+    let _nonce = reader.string()?;
+
+    // The certified key is written inline, so its blob is rebuilt to fingerprint it
+    let key_start = reader.bytes;
+    for _ in 0..key_fields(plain_type)? {
+        reader.string()?;
+    }
+    let key_fields = &key_start[..key_start.len() - reader.bytes.len()];
+    let mut key = Vec::new();
+    key.extend_from_slice(&(plain_type.len() as u32).to_be_bytes());
+    key.extend_from_slice(plain_type.as_bytes());
+    key.extend_from_slice(key_fields);
+
+    let serial = reader.u64()?;
+    let kind = match reader.u32()? {
+        1 => String::from("user"),
+        2 => String::from("host"),
+        other => format!("unknown ({})", other),
+    };
+    let key_id = reader.text()?;
+    let principals = reader
+        .strings()?
+        .iter()
+        .map(|principal| String::from_utf8_lossy(principal).into_owned())
+        .collect();
+    let valid_after = reader.u64()?;
+    let valid_before = reader.u64()?;
+    let critical_options = options(reader.strings()?)?;
+    let extensions = options(reader.strings()?)?
+        .into_iter()
+        .map(|(name, value)| match value.is_empty() {
+            true => name,
+            false => format!("{} {}", name, value),
+        })
+        .collect();
+    let _reserved = reader.string()?;
+    let signature_key = reader.string()?;
+    let signature_algorithm = Reader {
+        bytes: reader.string()?,
+    }
+    .text()?;
+
+    Some(SshCertificate {
+        location: String::from(source),
+        key_type,
+        kind: kind.clone(),
+        key_id,
+        serial,
+        public_key: fingerprint(&key)?,
+        principals,
+        valid_after: match valid_after {
+            0 => String::from("always"),
+            time => timestamp(time)?,
+        },
+        valid_before: match valid_before {
+            u64::MAX => String::from("forever"),
+            time => timestamp(time)?,
+        },
+        critical_options,
+        extensions,
+        signing_ca: fingerprint(signature_key)?,
+        signature_algorithm,
+        sshfp: match kind.as_str() {
+            "host" => sshfp(&key),
+            _ => Vec::new(),
+        },
+        checks: Vec::new(),
+        key,
+        ca_key: signature_key.to_vec(),
+    })
+}
+
+/// How many strings and mpints make up each kind of key.
+fn key_fields(key_type: &str) -> Option<usize> {
+    match key_type {
+        "ssh-rsa" => Some(2),
+        "ssh-dss" => Some(4),
+        "ssh-ed25519" | "ssh-ed448" => Some(1),
+        "sk-ssh-ed25519@openssh.com" => Some(2),
+        "sk-ecdsa-sha2-nistp256@openssh.com" => Some(3),
+        key_type if key_type.starts_with("ecdsa-sha2-") => Some(2),
+        _ => None,
+    }
+}
+
+/// Critical options and extensions, each a name and its data. The data is
+/// empty for a flag, and otherwise holds a string.
+fn options(packed: Vec<&[u8]>) -> Option<Vec<(String, String)>> {
+    packed
+        .chunks(2)
+        .map(|option| {
+            let name = String::from_utf8_lossy(option.first()?).into_owned();
+            let data = option.get(1)?;
+            let value = match data.is_empty() {
+                true => String::new(),
+                false => Reader { bytes: data }.text()?,
+            };
+            Some((name, value))
+        })
+        .collect()
+}
+
+/// A key's type and fingerprint as `ssh-keygen -l` gives them, like
+/// `ssh-ed25519 SHA256:1jkFcP3Or1t/8vlyP0ATLCSk5dWvl6KT2iircw4WqeQ`.
+pub fn fingerprint(key: &[u8]) -> Option<String> {
+    let key_type = Reader { bytes: key }.text()?;
+    let hash = digest(&SHA256, key);
+    Some(format!(
+        "{} SHA256:{}",
+        key_type,
+        STANDARD_NO_PAD.encode(hash.as_ref())
+    ))
+}
+
+/// The SSHFP records of RFC 4255 for a host key, with SHA-1 and SHA-256
+/// fingerprints, as `ssh-keygen -r` gives them without the owner name.
+pub fn sshfp(key: &[u8]) -> Vec<String> {
+    let key_type = Reader { bytes: key }.text();
+    let algorithm = match key_type.as_deref() {
+        Some("ssh-rsa") => 1,
+        Some("ssh-dss") => 2,
+        Some(key_type) if key_type.starts_with("ecdsa-sha2-") => 3,
+        Some("ssh-ed25519") => 4,
+        Some("ssh-ed448") => 6,
+        _ => return Vec::new(),
+    };
+    [(1, &SHA1_FOR_LEGACY_USE_ONLY), (2, &SHA256)]
+        .iter()
+        .map(|(kind, hash)| {
+            let hex: String = digest(hash, key)
+                .as_ref()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            format!("{} {} {}", algorithm, kind, hex)
+        })
+        .collect()
+}
+
+/// Compares a host key, or the CA key of a host certificate, with a known_hosts
+/// file and with the SSHFP records DNS gave.
+pub fn check(
+    key: &[u8],
+    ca_key: Option<&[u8]>,
+    references: &HostKeyReferences,
+) -> Vec<HostKeyCheck> {
+    let mut checks = Vec::new();
+    if let Some(known_hosts) = &references.known_hosts {
+        let (outcome, confirmed) = match known_host(known_hosts, key, ca_key) {
+            Some(found) => found,
+            None => (String::from("not listed"), false),
+        };
+        checks.push(HostKeyCheck {
+            source: String::from("known_hosts"),
+            outcome,
+            confirmed,
+        });
+    }
+    if let Some((host, records, validated)) = &references.sshfp {
+        let expected = sshfp(key);
+        let matching: Vec<&String> = records
+            .iter()
+            .filter(|record| expected.contains(record))
+            .collect();
+        let outcome = match (records.len(), matching.as_slice()) {
+            (0, _) => String::from("no records"),
+            (1, []) => String::from("the record does not match"),
+            (_, []) => format!("none of the {} records match", records.len()),
+            (_, matching) => {
+                let types: Vec<String> = matching
+                    .iter()
+                    .map(|record| record.split(' ').take(2).collect::<Vec<_>>().join(" "))
+                    .collect();
+                // RFC 4255 has clients trust SSHFP records only once DNSSEC has validated them
+                match validated {
+                    true => format!("matches {}", types.join(", ")),
+                    false => format!("matches {} (not DNSSEC-validated)", types.join(", ")),
+                }
+            }
+        };
+        checks.push(HostKeyCheck {
+            source: format!("DNS SSHFP for {}", host),
+            outcome,
+            confirmed: *validated && !matching.is_empty(),
+        });
+    }
+    checks
+}
+
+/// The line of a known_hosts file that lists a key, or trusts the CA that
+/// signed it, and whether that line vouches for it or revokes it.
+fn known_host(known_hosts: &str, key: &[u8], ca_key: Option<&[u8]>) -> Option<(String, bool)> {
+    for (number, line) in known_hosts.lines().enumerate() {
+        let mut words = line.split_whitespace().peekable();
+        let marker = match words.peek() {
+            Some(word) if word.starts_with('@') => words.next(),
+            _ => None,
+        };
+        let (hosts, blob) = match (words.next(), words.next(), words.next()) {
+            (Some(hosts), Some(_), Some(blob)) => (hosts, blob),
+            _ => continue,
+        };
+        let blob = match STANDARD.decode(blob) {
+            Ok(blob) => blob,
+            Err(_) => continue,
+        };
+        // Hashed host names can't be read back, only matched against
+        let hosts = match hosts.starts_with("|1|") {
+            true => "a hashed host name",
+            false => hosts,
+        };
+        let line = number + 1;
+        match marker {
+            Some("@revoked") if blob == key || ca_key == Some(blob.as_slice()) => {
+                return Some((format!("revoked (line {})", line), false));
+            }
+            Some("@cert-authority") if ca_key == Some(blob.as_slice()) => {
+                return Some((
+                    format!("signed by a CA trusted for {} (line {})", hosts, line),
+                    true,
+                ));
+            }
+            None if blob == key => {
+                return Some((format!("listed for {} (line {})", hosts, line), true));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The SSHFP records in what `dig +short` printed, in the form `sshfp` gives
+/// them. Long fingerprints are split into several words and in upper case.
+pub fn parse_sshfp(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let algorithm: u8 = words.next()?.parse().ok()?;
+            let kind: u8 = words.next()?.parse().ok()?;
+            let fingerprint = words.collect::<String>().to_lowercase();
+            Some(format!("{} {} {}", algorithm, kind, fingerprint))
+        })
+        .collect()
+}
+
+fn timestamp(seconds: u64) -> Option<String> {
+    Some(
+        ASN1Time::from_timestamp(i64::try_from(seconds).ok()?)
+            .ok()?
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{check, find, find_keys, parse_sshfp, read, read_key, HostKeyReferences};
+
+    const HOST_KEY: &[u8] = include_bytes!("../resources/ssh-host.pub");
+    const CA_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIFts1pmNs3KCziVP44Lm6RPcaKPofKK8TQKel5N4jYvH";
+
+    #[test]
+    fn should_decode_a_user_certificate() {
+        let blobs = find(include_bytes!("../resources/ssh-user-cert.pub"));
+        let certificate = read("ssh-user-cert.pub", &blobs[0]).unwrap();
+
+        assert_eq!(
+            certificate.key_type,
+            "ecdsa-sha2-nistp256-cert-v01@openssh.com"
+        );
+        assert_eq!(certificate.kind, "user");
+        assert_eq!(certificate.key_id, "alice@example.com");
+        assert_eq!(certificate.serial, 42);
+        assert_eq!(
+            certificate.public_key,
+            "ecdsa-sha2-nistp256 SHA256:9xKyLjdv/6Bzj3SpY9friQBxOrHRnIRuFAJGkcDPxlw"
+        );
+        assert_eq!(certificate.principals, vec!["alice", "root"]);
+        assert_eq!(certificate.valid_after, "Jan  1 00:00:00 2026 +00:00");
+        assert_eq!(certificate.valid_before, "Jan  1 00:00:00 2027 +00:00");
+        assert_eq!(
+            certificate.critical_options,
+            vec![
+                (
+                    String::from("force-command"),
+                    String::from("/usr/bin/uptime")
+                ),
+                (String::from("source-address"), String::from("10.0.0.0/8")),
+            ]
+        );
+        assert_eq!(
+            certificate.extensions,
+            vec![
+                "permit-X11-forwarding",
+                "permit-agent-forwarding",
+                "permit-pty",
+                "permit-user-rc"
+            ]
+        );
+        assert_eq!(
+            certificate.signing_ca,
+            "ssh-ed25519 SHA256:1jkFcP3Or1t/8vlyP0ATLCSk5dWvl6KT2iircw4WqeQ"
+        );
+        assert_eq!(certificate.signature_algorithm, "ssh-ed25519");
+    }
+
+    #[test]
+    fn should_decode_a_host_certificate_valid_forever() {
+        let blobs = find(include_bytes!("../resources/ssh-host-cert.pub"));
+        let certificate = read("ssh-host-cert.pub", &blobs[0]).unwrap();
+
+        assert_eq!(certificate.kind, "host");
+        assert_eq!(certificate.principals, vec!["web01.example.com"]);
+        assert_eq!(certificate.valid_after, "always");
+        assert_eq!(certificate.valid_before, "forever");
+        assert!(certificate.critical_options.is_empty());
+    }
+
+    #[test]
+    fn should_reject_a_truncated_certificate() {
+        let blobs = find(include_bytes!("../resources/ssh-host-cert.pub"));
+
+        assert!(read("truncated", &blobs[0][..100]).is_err());
+        assert!(find(b"ssh-ed25519 AAAAC3NzaC1lZDI1NTE5 plain key").is_empty());
+    }
+
+    #[test]
+    fn should_give_the_sshfp_records_of_a_host_key() {
+        let keys = find_keys(HOST_KEY);
+        let (blob, comment) = keys[0].clone();
+        let key = read_key("ssh-host.pub", &blob, comment).unwrap();
+
+        assert_eq!(
+            key.fingerprint,
+            "ssh-ed25519 SHA256:BRMoE9WGUldUQxPmc96jnfde5ZQNph/EXIY35P2EOq0"
+        );
+        assert_eq!(key.comment.as_deref(), Some("host"));
+        assert_eq!(
+            key.sshfp,
+            vec![
+                "4 1 c40757890481de30468b17bf6517bf18654ddcc4",
+                "4 2 05132813d5865257544313e673dea39df75ee5940da61fc45c8637e4fd843aad"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_find_a_host_key_in_known_hosts() {
+        let (key, _) = find_keys(HOST_KEY).remove(0);
+        let listed = String::from_utf8_lossy(HOST_KEY).replacen(
+            "ssh-ed25519",
+            "web01,10.0.0.1 ssh-ed25519",
+            1,
+        );
+        let references = HostKeyReferences {
+            known_hosts: Some(format!("# hosts\nother ssh-ed25519 AAAA\n{}", listed)),
+            sshfp: None,
+        };
+
+        let checks = check(&key, None, &references);
+
+        assert_eq!(checks[0].source, "known_hosts");
+        assert_eq!(checks[0].outcome, "listed for web01,10.0.0.1 (line 3)");
+        assert!(checks[0].confirmed);
+    }
+
+    #[test]
+    fn should_trust_a_host_certificate_by_its_ca() {
+        let blobs = find(include_bytes!("../resources/ssh-host-cert.pub"));
+        let certificate = read("ssh-host-cert.pub", &blobs[0]).unwrap();
+        let trusted = HostKeyReferences {
+            known_hosts: Some(format!("@cert-authority *.example.com {}", CA_KEY)),
+            sshfp: None,
+        };
+        let revoked = HostKeyReferences {
+            known_hosts: Some(format!("@revoked * {}", CA_KEY)),
+            sshfp: None,
+        };
+
+        let trusted = check(&certificate.key, Some(&certificate.ca_key), &trusted);
+        let revoked = check(&certificate.key, Some(&certificate.ca_key), &revoked);
+
+        assert_eq!(
+            trusted[0].outcome,
+            "signed by a CA trusted for *.example.com (line 1)"
+        );
+        assert!(trusted[0].confirmed);
+        assert_eq!(revoked[0].outcome, "revoked (line 1)");
+        assert!(!revoked[0].confirmed);
+    }
+
+    #[test]
+    fn should_compare_a_host_key_with_dns() {
+        let (key, _) = find_keys(HOST_KEY).remove(0);
+        let records = parse_sshfp(
+            "4 2 05132813D5865257544313E673DEA39DF75EE5940DA61FC45C8637E4 FD843AAD\n1 2 00\n",
+        );
+        let host = String::from("web01.example.com");
+        let references = |records: Vec<String>, validated: bool| HostKeyReferences {
+            known_hosts: None,
+            sshfp: Some((host.clone(), records, validated)),
+        };
+
+        let matching = check(&key, None, &references(records.clone(), true));
+        let unvalidated = check(&key, None, &references(records.clone(), false));
+        let other = check(&key, None, &references(records[1..].to_vec(), true));
+
+        assert_eq!(matching[0].source, "DNS SSHFP for web01.example.com");
+        assert_eq!(matching[0].outcome, "matches 4 2");
+        assert!(matching[0].confirmed);
+        assert_eq!(unvalidated[0].outcome, "matches 4 2 (not DNSSEC-validated)");
+        assert!(!unvalidated[0].confirmed);
+        assert_eq!(other[0].outcome, "the record does not match");
+        assert!(!other[0].confirmed);
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+
+/// Which Windows system store to read, from `cert-decoder store`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoreOptions {
+    /// `CurrentUser` or `LocalMachine`, from `--location`.
+    pub location: String,
+    /// The store within the location such as `My` or `Root`, from `--store`.
+    pub name: String,
+    /// Only certificates whose subject contains this (ignoring case), from `--subject`.
+    pub subject: Option<String>,
+}
+
+impl Default for StoreOptions {
+    fn default() -> StoreOptions {
+        StoreOptions {
+            location: String::from("CurrentUser"),
+            name: String::from("My"),
+            subject: None,
+        }
+    }
+}
+
+impl StoreOptions {
+    /// Checks `--location` names a store location we know how to open.
+    pub fn parse_location(location: &str) -> Result<String, Box<dyn std::error::Error>> {
+        match location {
+            "CurrentUser" | "LocalMachine" => Ok(String::from(location)),
+            _ => Err(format!(
+                "Error: {} is not a store location, use CurrentUser or LocalMachine.",
+                location
+            )
+            .into()),
+        }
+    }
+}
+
+impl fmt::Display for StoreOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\\{}", self.location, self.name)
+    }
+}
+
+/// Reads the DER of every certificate in a system store, in store order.
+#[cfg(windows)]
+pub fn read(store: &StoreOptions) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+    use std::ptr;
+
+    use windows_sys::Win32::Security::Cryptography::{
+        CertCloseStore, CertEnumCertificatesInStore, CertOpenStore, CERT_STORE_OPEN_EXISTING_FLAG,
+        CERT_STORE_PROV_SYSTEM_W, CERT_STORE_READONLY_FLAG, CERT_SYSTEM_STORE_CURRENT_USER,
+        CERT_SYSTEM_STORE_LOCAL_MACHINE,
+    };
+
+    let location = match store.location.as_str() {
+        "LocalMachine" => CERT_SYSTEM_STORE_LOCAL_MACHINE,
+        _ => CERT_SYSTEM_STORE_CURRENT_USER,
+    };
+    let name: Vec<u16> = store.name.encode_utf16().chain(Some(0)).collect();
+
+    // SAFETY: the handle is checked before use and closed once every context has been copied out
+    unsafe {
+        let handle = CertOpenStore(
+            CERT_STORE_PROV_SYSTEM_W,
+            0,
+            0,
+            location | CERT_STORE_READONLY_FLAG | CERT_STORE_OPEN_EXISTING_FLAG,
+            name.as_ptr().cast(),
+        );
+        if handle.is_null() {
+            return Err(format!(
+                "Error: could not open the {} certificate store: {}",
+                store,
+                std::io::Error::last_os_error()
+            )
+            .into());
+        }
+
+        let mut certificates = Vec::new();
+        let mut context = CertEnumCertificatesInStore(handle, ptr::null());
+        while !context.is_null() {
+            let encoded = std::slice::from_raw_parts(
+                (*context).pbCertEncoded,
+                (*context).cbCertEncoded as usize,
+            );
+            certificates.push(encoded.to_vec());
+            // Passing the previous context frees it
+            context = CertEnumCertificatesInStore(handle, context);
+        }
+        CertCloseStore(handle, 0);
+
+        Ok(certificates)
+    }
+}
+
+#[cfg(not(windows))]
+pub fn read(_store: &StoreOptions) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+    Err(String::from("Error: the certificate store can only be read on Windows.").into())
+}
+
+/// Which macOS keychains to read, from `cert-decoder keychain`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeychainOptions {
+    /// `login`, `system`, `roots` or a path to a keychain file, from `--keychain`.
+    pub keychains: Vec<String>,
+    /// Only certificates whose label contains this (ignoring case), from `--label`.
+    pub label: Option<String>,
+}
+
+impl Default for KeychainOptions {
+    fn default() -> KeychainOptions {
+        KeychainOptions {
+            keychains: vec![String::from("system"), String::from("login")],
+            label: None,
+        }
+    }
+}
+
+/// A certificate's label in its keychain and its DER.
+pub type NamedCertificate = (String, Vec<u8>);
+
+/// Reads every certificate in a keychain.
+#[cfg(target_os = "macos")]
+pub fn read_keychain(keychain: &str) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    use security_framework::item::{ItemClass, ItemSearchOptions, Limit, Reference, SearchResult};
+    use security_framework::os::macos::keychain::SecKeychain;
+
+    let path = match keychain {
+        "login" => {
+            let home = std::env::var("HOME")?;
+            format!("{}/Library/Keychains/login.keychain-db", home)
+        }
+        "system" => String::from("/Library/Keychains/System.keychain"),
+        "roots" => String::from("/System/Library/Keychains/SystemRootCertificates.keychain"),
+        path => String::from(path),
+    };
+    let opened = SecKeychain::open(&path)
+        .map_err(|error| format!("Error: could not open the {} keychain: {}", keychain, error))?;
+
+    let results = ItemSearchOptions::new()
+        .class(ItemClass::certificate())
+        .keychains(&[opened])
+        .load_refs(true)
+        .limit(Limit::All)
+        .search();
+    let results = match results {
+        Ok(results) => results,
+        // An empty keychain is reported as nothing found rather than as no results
+        Err(error) if error.code() == -25300 => Vec::new(),
+        Err(error) => {
+            return Err(format!(
+                "Error: could not search the {} keychain: {}",
+                keychain, error
+            )
+            .into())
+        }
+    };
+
+    Ok(results
+        .into_iter()
+        .filter_map(|result| match result {
+            SearchResult::Ref(Reference::Certificate(cert)) => {
+                Some((cert.subject_summary(), cert.to_der()))
+            }
+            _ => None,
+        })
+        .collect())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn read_keychain(_keychain: &str) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    Err(String::from("Error: keychains can only be read on macOS.").into())
+}
@@ -0,0 +1,257 @@
+use std::fmt;
+
+use crate::cloud::Cloud;
+use crate::ldap::LdapOptions;
+use crate::options::Options;
+use crate::pkcs11::Pkcs11Options;
+use crate::store::StoreOptions;
+use crate::FileProcessor;
+
+/// Something certificates can be decoded from.
+#[derive(Debug, PartialEq)]
+pub enum Target {
+    /// A certificate file, or a directory to search for certificate files.
+    Path(String),
+    /// Standard input, given as `-`.
+    Stdin,
+    /// Whatever was copied to the system clipboard, from `--clipboard`.
+    Clipboard,
+    /// A TLS server whose presented chain should be decoded.
+    Remote { host: String, port: u16 },
+    /// A Windows system certificate store, from `cert-decoder store`.
+    Store(StoreOptions),
+    /// A macOS keychain, from `cert-decoder keychain`, with the `--label` filter.
+    Keychain {
+        keychain: String,
+        label: Option<String>,
+    },
+    /// A smart card or HSM, through the PKCS#11 module given with `--pkcs11`.
+    Pkcs11(Pkcs11Options),
+    /// A cloud's certificate service, from the `aws` commands or `--cloud`.
+    Cloud(Cloud),
+    /// The entries of an LDAP directory, from `cert-decoder ldap`.
+    Ldap(LdapOptions),
+}
+
+impl Target {
+    /// Works out what kind of target `s` is, if any.
+    /// Existing paths win over `host:port` so that oddly named files still work.
+    pub fn parse(processor: &impl FileProcessor, s: &str) -> Option<Target> {
+        if s == "-" {
+            return Some(Target::Stdin);
+        }
+        if processor.is_file(s) || processor.is_dir(s) {
+            return Some(Target::Path(String::from(s)));
+        }
+
+        let (host, port) = if let Some(rest) = s.strip_prefix('[') {
+            // Bracketed IPv6 address, e.g. [::1]:443
+            let (host, port) = rest.split_once("]:")?;
+            (host, port)
+        } else {
+            let (host, port) = s.rsplit_once(':')?;
+            if host.contains(':') {
+                return None;
+            }
+            (host, port)
+        };
+
+        if host.is_empty() {
+            return None;
+        }
+        let port = port.parse().ok()?;
+        Some(Target::Remote {
+            host: String::from(host),
+            port,
+        })
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Path(path) => write!(f, "{}", path),
+            Target::Stdin => write!(f, "stdin"),
+            Target::Clipboard => write!(f, "clipboard"),
+            Target::Remote { host, port } if host.contains(':') => write!(f, "[{}]:{}", host, port),
+            Target::Remote { host, port } => write!(f, "{}:{}", host, port),
+            Target::Store(store) => write!(f, "{}", store),
+            Target::Keychain { keychain, .. } => write!(f, "{} keychain", keychain),
+            Target::Pkcs11(token) => write!(f, "{}", token),
+            Target::Cloud(cloud) => write!(f, "{}", cloud),
+            Target::Ldap(ldap) => write!(f, "{}", ldap),
+        }
+    }
+}
+
+/// Gathers every target requested on the command line, in the order given.
+pub fn collect(
+    processor: &impl FileProcessor,
+    options: &Options,
+) -> Result<Vec<Target>, Box<dyn std::error::Error>> {
+    let mut targets = Vec::new();
+
+    if let Some(store) = &options.store {
+        targets.push(Target::Store(store.clone()));
+    }
+    if let Some(keychains) = &options.keychains {
+        for keychain in &keychains.keychains {
+            targets.push(Target::Keychain {
+                keychain: keychain.clone(),
+                label: keychains.label.clone(),
+            });
+        }
+    }
+
+    if let Some(token) = &options.pkcs11 {
+        targets.push(Target::Pkcs11(token.clone()));
+    }
+
+    if let Some(aws) = &options.aws {
+        targets.push(Target::Cloud(Cloud::Aws(aws.clone())));
+    }
+    for cloud in &options.clouds {
+        targets.push(Target::Cloud(cloud.clone()));
+    }
+    if let Some(ldap) = &options.ldap {
+        targets.push(Target::Ldap(ldap.clone()));
+    }
+
+    if options.clipboard {
+        targets.push(Target::Clipboard);
+    }
+
+    for arg in &options.targets {
+        match Target::parse(processor, arg) {
+            Some(target) => targets.push(target),
+            None => {
+                let err_msg = String::from("Error: path given as argument is not a regular file, it must be a path to a certificate!");
+                return Err(err_msg.into());
+            }
+        }
+    }
+
+    if let Some(targets_file) = &options.targets_file {
+        if !processor.is_file(targets_file) {
+            return Err(format!(
+                "Error: targets file {} is not a regular file.",
+                targets_file
+            )
+            .into());
+        }
+
+        let contents = processor.read_to_string(targets_file)?;
+        for (number, line) in contents.lines().enumerate() {
+            // Blank lines and comments are allowed so the list can be organised
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match Target::parse(processor, line) {
+                Some(target) => targets.push(target),
+                None => {
+                    return Err(format!(
+                        "Error: line {} of {} ({}) is not a file, directory, or host:port.",
+                        number + 1,
+                        targets_file,
+                        line
+                    )
+                    .into())
+                }
+            }
+        }
+    }
+
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{collect, Target};
+    use crate::options::Options;
+    use crate::test::FakeProcessor;
+
+    fn remote(host: &str, port: u16) -> Target {
+        Target::Remote {
+            host: String::from(host),
+            port,
+        }
+    }
+
+    #[test]
+    fn should_parse_host_and_port() {
+        let processor = FakeProcessor::default();
+
+        assert_eq!(
+            Target::parse(&processor, "example.com:443"),
+            Some(remote("example.com", 443))
+        );
+        assert_eq!(
+            Target::parse(&processor, "[::1]:8443"),
+            Some(remote("::1", 8443))
+        );
+    }
+
+    #[test]
+    fn should_not_parse_things_that_are_not_targets() {
+        let processor = FakeProcessor::default();
+
+        assert_eq!(Target::parse(&processor, "does-not-exist"), None);
+        assert_eq!(Target::parse(&processor, "example.com:https"), None);
+        assert_eq!(Target::parse(&processor, ":443"), None);
+        assert_eq!(Target::parse(&processor, "::1:443"), None);
+    }
+
+    #[test]
+    fn should_prefer_existing_paths_over_host_and_port() {
+        let processor = FakeProcessor {
+            is_file: true,
+            ..FakeProcessor::default()
+        };
+
+        assert_eq!(
+            Target::parse(&processor, "backup:1"),
+            Some(Target::Path(String::from("backup:1")))
+        );
+    }
+
+    #[test]
+    fn should_read_targets_file_skipping_blank_lines_and_comments() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("targets.txt", "# web\nexample.com:443\n\n  certs  \n");
+        processor.add_dir("certs", &[]);
+        let options = Options {
+            targets_file: Some(String::from("targets.txt")),
+            ..Options::default()
+        };
+
+        let targets = collect(&processor, &options).unwrap();
+
+        assert_eq!(
+            targets,
+            vec![
+                remote("example.com", 443),
+                Target::Path(String::from("certs"))
+            ]
+        );
+    }
+
+    #[test]
+    fn should_error_with_line_number_on_bad_target() {
+        let mut processor = FakeProcessor::default();
+        processor.add_file("targets.txt", "example.com:443\nnope\n");
+        let options = Options {
+            targets_file: Some(String::from("targets.txt")),
+            ..Options::default()
+        };
+
+        let result = collect(&processor, &options);
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: line 2 of targets.txt (nope) is not a file, directory, or host:port."
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+
+use crate::cms::{self, element, Tlv};
+use crate::oid;
+
+/// What a TSA vouched for in an RFC 3161 timestamp token.
+pub struct TstInfo {
+    pub gen_time: String,
+    pub policy: String,
+    /// The digest algorithm and the digest of what was timestamped.
+    pub imprint_algorithm: String,
+    pub imprint: String,
+    pub serial: String,
+    /// How far `gen_time` can be from the real time, which TSAs are free to leave out.
+    pub accuracy: Option<String>,
+    pub nonce: Option<String>,
+    /// The name the TSA gives itself.
+    pub tsa: Option<String>,
+}
+
+/// The statuses of a TimeStampResp, by value.
+const STATUSES: &[&str] = &[
+    "granted",
+    "granted with modifications",
+    "rejection",
+    "waiting",
+    "revocation warning",
+    "revocation notification",
+];
+
+/// Whether the bytes are a TSA response, as `.tsr` files hold: a status,
+/// and the token if the request was granted.
+pub fn is_response(bytes: &[u8]) -> bool {
+    let sniff = || {
+        let (response, _) = element(bytes)?;
+        let response = response.children()?;
+        let status = response.first()?.children()?;
+        // Unlike a certificate, which is three elements
+        Some(response.len() <= 2 && response.first()?.tag == 0x30 && status.first()?.tag == 0x02)
+    };
+    sniff().unwrap_or(false)
+}
+
+/// Takes the timestamp token out of a TSA response, erroring with the
+/// TSA's reason if it didn't grant the request.
+pub fn token(source: &str, bytes: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let invalid = || format!("Error: {} is not a valid timestamp response.", source);
+    let (response, _) = element(bytes).ok_or_else(invalid)?;
+    let response = response.children().ok_or_else(invalid)?;
+    let status_info = response
+        .first()
+        .and_then(Tlv::children)
+        .ok_or_else(invalid)?;
+    let status = status_info
+        .first()
+        .and_then(|status| unsigned(status.contents))
+        .ok_or_else(invalid)?;
+
+    if status > 1 {
+        let name = STATUSES.get(status as usize).unwrap_or(&"unknown status");
+        // The free text is a sequence of UTF8Strings, when the TSA gives one
+        let text: Vec<String> = status_info
+            .get(1)
+            .filter(|text| text.tag == 0x30)
+            .and_then(Tlv::children)
+            .unwrap_or_default()
+            .iter()
+            .map(|text| String::from_utf8_lossy(text.contents).into_owned())
+            .collect();
+        return Err(match text.is_empty() {
+            true => format!("Error: the TSA did not grant {}: {}.", source, name),
+            false => format!(
+                "Error: the TSA did not grant {}: {}, {}.",
+                source,
+                name,
+                text.join(" ")
+            ),
+        }
+        .into());
+    }
+    match response.get(1) {
+        Some(token) => Ok(token.raw.to_vec()),
+        None => Err(format!("Error: the TSA response {} has no token.", source).into()),
+    }
+}
+
+/// Decodes the TSTInfo a timestamp token signs.
+pub fn tst_info(content: &[u8]) -> Option<TstInfo> {
+    let (info, _) = element(content)?;
+    let fields = info.children()?;
+    let imprint = fields.get(2)?.children()?;
+    let mut info = TstInfo {
+        policy: oid::name(&fields.get(1)?.oid()?),
+        imprint_algorithm: cms::algorithm(imprint.first()?)?,
+        imprint: cms::hex(imprint.get(1)?.contents),
+        serial: cms::hex(fields.get(3)?.contents),
+        gen_time: ASN1Time::from_der(fields.get(4)?.raw).ok()?.1.to_string(),
+        accuracy: None,
+        nonce: None,
+        tsa: None,
+    };
+
+    for field in fields.iter().skip(5) {
+        match field.tag {
+            0x30 => info.accuracy = Some(accuracy(field)?),
+            0x02 => info.nonce = Some(cms::hex(field.contents)),
+            0xa0 => {
+                let name = field.children()?;
+                info.tsa = match GeneralName::from_der(name.first()?.raw).ok()? {
+                    (_, GeneralName::DirectoryName(name)) => Some(name.to_string()),
+                    (_, name) => Some(name.to_string()),
+                };
+            }
+            _ => {} // ordering and extensions
+        }
+    }
+    Some(info)
+}
+
+/// Seconds, milliseconds and microseconds, any of which can be left out.
+fn accuracy(accuracy: &Tlv) -> Option<String> {
+    let mut parts = Vec::new();
+    for part in accuracy.children()? {
+        let unit = match part.tag {
+            0x02 => "s",
+            0x80 => "ms",
+            0x81 => "µs",
+            _ => return None,
+        };
+        parts.push(format!("{}{}", unsigned(part.contents)?, unit));
+    }
+    Some(parts.join(" "))
+}
+
+fn unsigned(bytes: &[u8]) -> Option<u64> {
+    if bytes.is_empty() || bytes.len() > 8 {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .fold(0, |value, byte| value << 8 | *byte as u64),
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{is_response, token};
+    use crate::cms;
+
+    const RESPONSE: &[u8] = include_bytes!("../resources/release.txt.tsr");
+
+    #[test]
+    fn should_decode_the_token_of_a_response() {
+        let token = token("release.txt.tsr", RESPONSE).unwrap();
+
+        let signed = cms::read("release.txt.tsr", &token).unwrap();
+        let info = signed.timestamp.unwrap();
+
+        assert!(is_response(RESPONSE));
+        assert!(!is_response(&token));
+        assert_eq!(info.policy, "1.3.6.1.4.1.32473.1.1");
+        assert_eq!(info.imprint_algorithm, "sha256");
+        assert!(info.imprint.starts_with("73:81:f9:8a"));
+        assert_eq!(info.serial, "02");
+        assert_eq!(info.accuracy.as_deref(), Some("1s 500ms"));
+        assert_eq!(info.tsa.as_deref(), Some("CN=Example TSA"));
+        assert_eq!(signed.certificates.len(), 1);
+    }
+
+    #[test]
+    fn should_error_with_the_reason_a_request_was_rejected() {
+        // status rejection, with the text "unsupported policy"
+        let rejected = b"\x30\x1b\x30\x19\x02\x01\x02\x30\x14\x0c\x12unsupported policy";
+
+        let result = token("rejected.tsr", rejected);
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "Error: the TSA did not grant rejected.tsr: rejection, unsupported policy."
+        );
+    }
+}
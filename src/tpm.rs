@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::oid::Oid;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+
+use crate::extensions::{elements, unsigned, Field};
+use crate::oid;
+
+/// The extended key usage TCG gives endorsement key certificates.
+const EK_CERTIFICATE: &str = "2.23.133.8.1";
+
+/// The extension that holds the TPM specification an EK certificate was issued under.
+const SUBJECT_DIRECTORY_ATTRIBUTES: &str = "2.5.29.9";
+const TPM_SPECIFICATION: &str = "2.23.133.2.16";
+
+/// The attributes TCG puts in the directoryName of an EK certificate's SAN.
+const ATTRIBUTES: &[(&str, &str)] = &[
+    ("2.23.133.2.1", "TPM manufacturer"),
+    ("2.23.133.2.2", "TPM model"),
+    ("2.23.133.2.3", "TPM firmware version"),
+];
+
+/// The policy qualifiers of RFC 5280.
+const QUALIFIERS: &[(&str, &str)] = &[
+    ("1.3.6.1.5.5.7.2.1", "CPS"),
+    ("1.3.6.1.5.5.7.2.2", "User notice"),
+];
+
+/// Vendor IDs from the TCG vendor ID registry, as the four ASCII bytes they spell.
+const MANUFACTURERS: &[(&str, &str)] = &[
+    ("414D4400", "AMD"),
+    ("41544D4C", "Atmel"),
+    ("4252434D", "Broadcom"),
+    ("4353434F", "Cisco"),
+    ("464C5953", "Flyslice"),
+    ("524F4343", "Fuzhou Rockchip"),
+    ("474F4F47", "Google"),
+    ("48504900", "HPI"),
+    ("48504500", "HPE"),
+    ("48495349", "Huawei"),
+    ("49424D00", "IBM"),
+    ("49465800", "Infineon"),
+    ("494E5443", "Intel"),
+    ("4C454E00", "Lenovo"),
+    ("4D534654", "Microsoft"),
+    ("4E534D20", "National Semiconductor"),
+    ("4E545A00", "Nationz"),
+    ("4E544300", "Nuvoton"),
+    ("51434F4D", "Qualcomm"),
+    ("534D534E", "Samsung"),
+    ("534E5300", "Sinosun"),
+    ("534D5343", "SMSC"),
+    ("53544D20", "STMicroelectronics"),
+    ("54584E00", "Texas Instruments"),
+    ("57454300", "Winbond"),
+];
+
+/// Decodes what TCG puts in an endorsement key certificate: the TPM it was
+/// issued to, the specification it follows and the policies it was issued under.
+/// Other certificates are left alone.
+pub fn endorsement_key(cert: &X509Certificate) -> Option<Vec<Field>> {
+    let mut fields = Vec::new();
+    let mut is_ek = cert
+        .extended_key_usage()
+        .ok()
+        .flatten()
+        .is_some_and(|usage| {
+            usage
+                .value
+                .other
+                .iter()
+                .any(|oid| oid.to_id_string() == EK_CERTIFICATE)
+        });
+
+    let names = cert.subject_alternative_name().ok().flatten();
+    for name in names.iter().flat_map(|names| &names.value.general_names) {
+        let name = match name {
+            GeneralName::DirectoryName(name) => name,
+            _ => continue,
+        };
+        for attribute in name.iter_attributes() {
+            let oid = attribute.attr_type().to_id_string();
+            if let Some((_, label)) = ATTRIBUTES.iter().find(|(known, _)| *known == oid) {
+                let value = attribute.as_str().ok()?;
+                let value = match oid.as_str() {
+                    "2.23.133.2.1" => manufacturer(value),
+                    "2.23.133.2.3" => firmware_version(value),
+                    _ => String::from(value),
+                };
+                fields.push(Field::new(label, value));
+                is_ek = true;
+            }
+        }
+    }
+    if !is_ek {
+        return None;
+    }
+
+    for extension in cert.extensions() {
+        match extension.parsed_extension() {
+            ParsedExtension::CertificatePolicies(policies) => {
+                for policy in policies {
+                    let mut field = Field::new("Policy", oid::name(&policy.policy_id));
+                    for qualifier in policy.policy_qualifiers.iter().flatten() {
+                        let value = elements(qualifier.qualifier)
+                            .and_then(|value| value.first().map(|value| value.data.to_vec()))
+                            .unwrap_or_default();
+                        let id = qualifier.policy_qualifier_id.to_id_string();
+                        let name = match QUALIFIERS.iter().find(|(known, _)| *known == id) {
+                            Some((_, name)) => String::from(*name),
+                            None => id,
+                        };
+                        field
+                            .fields
+                            .push(Field::new(&name, String::from_utf8_lossy(&value)));
+                    }
+                    fields.push(field);
+                }
+            }
+            _ if extension.oid.to_id_string() == SUBJECT_DIRECTORY_ATTRIBUTES => {
+                fields.extend(specification(extension.value));
+            }
+            _ => {}
+        }
+    }
+    Some(fields)
+}
+
+/// The manufacturer ID with the vendor it stands for, like `id:4E544300 (Nuvoton)`.
+fn manufacturer(value: &str) -> String {
+    let id = value.trim_start_matches("id:").to_uppercase();
+    match MANUFACTURERS.iter().find(|(known, _)| *known == id) {
+        Some((_, vendor)) => format!("{} ({})", value, vendor),
+        None => String::from(value),
+    }
+}
+
+/// The firmware version, which is usually the major and minor version as 16 bits each.
+fn firmware_version(value: &str) -> String {
+    let hex = value.trim_start_matches("id:");
+    match (hex.len(), u32::from_str_radix(hex, 16)) {
+        (8, Ok(version)) => format!("{} ({}.{})", value, version >> 16, version & 0xffff),
+        _ => String::from(value),
+    }
+}
+
+/// The TPM specification family, level and revision from the subject directory attributes.
+fn specification(value: &[u8]) -> Option<Field> {
+    let attributes = elements(value)?;
+    for attribute in elements(attributes.first()?.data)? {
+        let attribute = elements(attribute.data)?;
+        let oid = Oid::new(Cow::Borrowed(attribute.first()?.data));
+        if oid.to_id_string() != TPM_SPECIFICATION {
+            continue;
+        }
+        let values = elements(attribute.get(1)?.data)?;
+        let specification = elements(values.first()?.data)?;
+        // The revision is given in hundredths, so 138 is revision 1.38
+        let revision = unsigned(specification.get(2)?)?;
+        return Some(Field::new(
+            "TPM specification",
+            format!(
+                "{}, level {}, revision {}.{:02}",
+                String::from_utf8_lossy(specification.first()?.data),
+                unsigned(specification.get(1)?)?,
+                revision / 100,
+                revision % 100
+            ),
+        ));
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+
+    use super::{endorsement_key, firmware_version, manufacturer};
+    use crate::test::google_der;
+
+    #[test]
+    fn should_name_the_manufacturer_and_split_the_firmware_version() {
+        assert_eq!(manufacturer("id:49465800"), "id:49465800 (Infineon)");
+        assert_eq!(manufacturer("id:12345678"), "id:12345678");
+        assert_eq!(firmware_version("id:00070055"), "id:00070055 (7.85)");
+        assert_eq!(firmware_version("7.2.3"), "7.2.3");
+    }
+
+    #[test]
+    fn should_leave_other_certificates_alone() {
+        let der = google_der();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+
+        assert!(endorsement_key(&cert).is_none());
+    }
+}
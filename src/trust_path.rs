@@ -0,0 +1,754 @@
+use std::fmt;
+
+use ring::digest::{digest, SHA256};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+use x509_parser::oid_registry::OID_PKIX_ACCESS_DESCRIPTOR_CA_ISSUERS;
+use x509_parser::parse_x509_certificate;
+use x509_parser::pem::Pem;
+
+use crate::cms;
+use crate::http::{Request, Url};
+use crate::name_constraints;
+use crate::oid;
+use crate::policy::{self, PolicyCheck};
+use crate::remote::ConnectOptions;
+use crate::store::NamedCertificate;
+use crate::trust_store::BUNDLES;
+use crate::usage::{self, Purposes};
+use crate::FileProcessor;
+
+/// How many certificates a path may have, so a tangle of cross-signatures can't
+/// make the search run away.
+const MAX_LENGTH: usize = 10;
+
+/// A certificate trusted to end a path, and the trust store it came from.
+#[derive(Clone, Debug)]
+pub struct Anchor {
+    pub store: String,
+    pub der: Vec<u8>,
+}
+
+/// A certificate in a path, and why it was or wasn't accepted there.
+pub struct Step {
+    pub subject: String,
+    pub der: Vec<u8>,
+    /// The SHA-256 fingerprint, as colon separated hex.
+    pub fingerprint: String,
+    /// The trust store of an anchor, or where any other certificate was found.
+    pub source: String,
+    pub signature: String,
+    pub validity: String,
+    pub constraints: String,
+    pub accepted: bool,
+}
+
+/// A path built from a certificate to a trust anchor, root first.
+pub struct TrustPath {
+    pub steps: Vec<Step>,
+    /// Whether the path reaches a trust anchor, even if it isn't accepted.
+    pub anchored: bool,
+    /// When the first certificate on the path expires, which is when the path does.
+    pub expires: String,
+    /// Why the certificate is not trusted, if it isn't.
+    pub problem: Option<String>,
+    /// What the leaf can be used for at the end of the path.
+    pub purposes: Purposes,
+    /// Whether each policy required with `--require-policy` is valid for the leaf.
+    pub policies: Vec<PolicyCheck>,
+}
+
+/// Reads the trust anchors from the stores given with `--trust-anchors`, or from
+/// the system CA bundle if none were.
+pub fn load(
+    processor: &impl FileProcessor,
+    stores: &[String],
+) -> Result<Vec<Anchor>, Box<dyn std::error::Error>> {
+    let stores = match stores {
+        [] => vec![BUNDLES
+            .iter()
+            .find(|bundle| processor.is_file(bundle))
+            .map(|bundle| String::from(*bundle))
+            .ok_or_else(|| {
+                String::from(
+                    "Error: could not find the system CA bundle, give trust anchors with --trust-anchors.",
+                )
+            })?],
+        stores => stores.to_vec(),
+    };
+    let mut anchors = Vec::new();
+    for store in stores {
+        let bytes = processor.read(&store)?;
+        let found: Vec<Vec<u8>> = match parse_x509_certificate(&bytes) {
+            Ok(_) => vec![bytes],
+            Err(_) => Pem::iter_from_buffer(&bytes)
+                .filter_map(Result::ok)
+                .filter(|pem| pem.label == "CERTIFICATE" || pem.label == "TRUSTED CERTIFICATE")
+                .map(|pem| pem.contents)
+                .filter(|der| parse_x509_certificate(der).is_ok())
+                .collect(),
+        };
+        if found.is_empty() {
+            return Err(format!("Error: no trust anchors found in {}.", store).into());
+        }
+        anchors.extend(found.into_iter().map(|der| Anchor {
+            store: store.clone(),
+            der,
+        }));
+    }
+    Ok(anchors)
+}
+
+/// A certificate that can go in a path.
+struct Node<'a> {
+    source: String,
+    der: &'a [u8],
+    anchor: bool,
+}
+
+/// Builds every path from a leaf to a trust anchor, through the certificates found
+/// alongside it, and checks each certificate on them. A cross-signed CA gives
+/// several paths, those whose certificates are all accepted come first. When no
+/// path reaches an anchor, there is only as much of one as could be built.
+pub fn build(
+    leaf: &NamedCertificate,
+    intermediates: &[NamedCertificate],
+    anchors: &[Anchor],
+    now: i64,
+) -> Vec<TrustPath> {
+    let anchor_nodes = anchors.iter().map(|anchor| Node {
+        source: format!("trust anchor in {}", anchor.store),
+        der: &anchor.der,
+        anchor: true,
+    });
+    // Anchors come first, so a root that is also in the chain is taken as the anchor
+    let nodes: Vec<Node> = anchor_nodes
+        .chain(intermediates.iter().map(|(source, der)| Node {
+            source: source.clone(),
+            der,
+            anchor: false,
+        }))
+        .collect();
+    let leaf = Node {
+        source: leaf.0.clone(),
+        der: &leaf.1,
+        anchor: nodes
+            .iter()
+            .any(|node| node.anchor && node.der == &leaf.1[..]),
+    };
+
+    let mut complete = Vec::new();
+    let mut longest = Vec::new();
+    search(&nodes, &leaf, &mut Vec::new(), &mut complete, &mut longest);
+    let mut paths: Vec<TrustPath> = complete
+        .iter()
+        .map(|path| explain(&nodes, &leaf, path, now))
+        .collect();
+    if !paths.is_empty() {
+        paths.sort_by_key(|path| path.problem.is_some());
+        return paths;
+    }
+
+    // Explain as much of the path as there is, and where it stops
+    let mut path = explain(&nodes, &leaf, &longest, now);
+    let last = match longest.last() {
+        Some(index) => parse(nodes[*index].der),
+        None => parse(leaf.der),
+    };
+    path.problem = Some(match last.subject() == last.issuer() {
+        true => format!("{} is self-signed and not a trust anchor", last.subject()),
+        false => format!(
+            "no issuer of {} was found among the certificates given or the trust anchors",
+            last.subject()
+        ),
+    });
+    vec![path]
+}
+
+/// Builds the paths like `build`, but when none reaches an anchor fetches the
+/// issuer of the certificate it stops at from the CA Issuers URL in its Authority
+/// Information Access, as browsers do for servers that leave out intermediates.
+pub fn build_fetching(
+    processor: &impl FileProcessor,
+    connect: &ConnectOptions,
+    leaf: &NamedCertificate,
+    intermediates: &[NamedCertificate],
+    anchors: &[Anchor],
+    now: i64,
+) -> Vec<TrustPath> {
+    let mut intermediates = intermediates.to_vec();
+    let mut fetched = Vec::new();
+    let mut failures = Vec::new();
+    loop {
+        let mut paths = build(leaf, &intermediates, anchors, now);
+        let top = match paths.as_slice() {
+            [path] if !path.anchored => parse(&path.steps[0].der),
+            _ => return paths,
+        };
+        let urls: Vec<String> = ca_issuers(&top)
+            .into_iter()
+            .filter(|url| !fetched.contains(url))
+            .collect();
+        if urls.is_empty() || fetched.len() >= MAX_LENGTH {
+            if let (Some(path), false) = (paths.first_mut(), failures.is_empty()) {
+                let problem = path.problem.take().unwrap_or_default();
+                path.problem = Some(format!("{}, {}", problem, failures.join(", ")));
+            }
+            return paths;
+        }
+        for url in urls {
+            match fetch(processor, connect, &url) {
+                Ok(certificates) => intermediates.extend(
+                    certificates
+                        .into_iter()
+                        .map(|der| (format!("CA issuers {}", url), der)),
+                ),
+                Err(error) => failures.push(format!(
+                    "fetching its issuer from {} failed: {}",
+                    url,
+                    error.to_string().trim_start_matches("Error: ")
+                )),
+            }
+            fetched.push(url);
+        }
+    }
+}
+
+/// The CA Issuers URLs in a certificate's Authority Information Access.
+fn ca_issuers(cert: &X509Certificate) -> Vec<String> {
+    let mut urls = Vec::new();
+    for extension in cert.extensions() {
+        if let ParsedExtension::AuthorityInfoAccess(access) = extension.parsed_extension() {
+            for description in &access.accessdescs {
+                match &description.access_location {
+                    GeneralName::URI(url)
+                        if description.access_method == OID_PKIX_ACCESS_DESCRIPTOR_CA_ISSUERS =>
+                    {
+                        urls.push(String::from(*url))
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    urls
+}
+
+/// The certificates at a CA Issuers URL, which is a DER certificate or a
+/// PKCS#7 bundle of them, though some CAs serve PEM.
+fn fetch(
+    processor: &impl FileProcessor,
+    connect: &ConnectOptions,
+    url: &str,
+) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+    let url = Url::parse(url)?;
+    let body = processor
+        .http(&Request::get(url.clone()), connect)?
+        .success(&url)?
+        .body;
+    if parse_x509_certificate(&body).is_ok() {
+        return Ok(vec![body]);
+    }
+    if let Some(der) = cms::find(&body) {
+        return Ok(cms::read(&url.to_string(), &der)?.certificates);
+    }
+    let certificates: Vec<Vec<u8>> = Pem::iter_from_buffer(&body)
+        .filter_map(Result::ok)
+        .filter(|pem| pem.label == "CERTIFICATE")
+        .map(|pem| pem.contents)
+        .filter(|der| parse_x509_certificate(der).is_ok())
+        .collect();
+    if certificates.is_empty() {
+        return Err(format!("Error: {} did not answer with a certificate.", url).into());
+    }
+    Ok(certificates)
+}
+
+/// Finds every path from the certificate at the end of `path` to an anchor, as
+/// indexes into `nodes` going up from the leaf, keeping the longest dead end too.
+fn search(
+    nodes: &[Node],
+    leaf: &Node,
+    path: &mut Vec<usize>,
+    complete: &mut Vec<Vec<usize>>,
+    longest: &mut Vec<usize>,
+) {
+    let current = match path.last() {
+        Some(index) => &nodes[*index],
+        None => leaf,
+    };
+    if current.anchor {
+        complete.push(path.clone());
+        return;
+    }
+    if path.len() > longest.len() {
+        *longest = path.clone();
+    }
+    if path.len() + 1 >= MAX_LENGTH {
+        return;
+    }
+    let cert = parse(current.der);
+    for (index, node) in nodes.iter().enumerate() {
+        let issuer = parse(node.der);
+        let used = path.iter().any(|used| nodes[*used].der == node.der) || node.der == leaf.der;
+        if used || issuer.subject() != cert.issuer() {
+            continue;
+        }
+        if cert.verify_signature(Some(issuer.public_key())).is_err() {
+            continue;
+        }
+        path.push(index);
+        search(nodes, leaf, path, complete, longest);
+        path.pop();
+    }
+}
+
+/// Checks each certificate on a path, root first.
+fn explain(nodes: &[Node], leaf: &Node, path: &[usize], now: i64) -> TrustPath {
+    let chain: Vec<&Node> = std::iter::once(leaf)
+        .chain(path.iter().map(|index| &nodes[*index]))
+        .rev()
+        .collect();
+    let certificates: Vec<X509Certificate> = chain.iter().map(|node| parse(node.der)).collect();
+
+    let mut steps = Vec::new();
+    let mut problem = None;
+    for (i, (node, cert)) in chain.iter().zip(&certificates).enumerate() {
+        let signature = match i {
+            0 if node.anchor => Ok(String::from("not checked, it is a trust anchor")),
+            0 => Err(String::from("not checked, no issuer was found")),
+            _ => {
+                let issuer = &certificates[i - 1];
+                match cert.verify_signature(Some(issuer.public_key())) {
+                    Ok(()) => Ok(format!(
+                        "valid, from {} with {}",
+                        issuer.subject(),
+                        oid::name(&cert.signature_algorithm.algorithm)
+                    )),
+                    Err(_) => Err(format!("invalid, it was not made by {}", issuer.subject())),
+                }
+            }
+        };
+        let validity = validity(cert, now);
+        // The leaf only has to be valid, every certificate above it has to be able to issue
+        let below = &certificates[i + 1..];
+        let constraints = match below.is_empty() {
+            true => leaf_constraints(&certificates),
+            false => constraints(cert, below),
+        };
+
+        let accepted = signature.is_ok() && validity.is_ok() && constraints.is_ok();
+        if problem.is_none() {
+            problem = [&signature, &validity, &constraints]
+                .iter()
+                .find_map(|check| check.as_ref().err())
+                .map(|reason| format!("{} was not accepted, {}", cert.subject(), reason));
+        }
+        let text = |check: Result<String, String>| check.unwrap_or_else(|reason| reason);
+        steps.push(Step {
+            subject: cert.subject().to_string(),
+            der: node.der.to_vec(),
+            fingerprint: fingerprint(node.der),
+            source: node.source.clone(),
+            signature: text(signature),
+            validity: text(validity),
+            constraints: text(constraints),
+            accepted,
+        });
+    }
+    let expires = certificates
+        .iter()
+        .map(|cert| cert.validity().not_after)
+        .min()
+        .map(|time| time.to_string())
+        .unwrap_or_default();
+    TrustPath {
+        steps,
+        anchored: chain[0].anchor,
+        expires,
+        problem,
+        purposes: usage::purposes(&certificates),
+        policies: Vec::new(),
+    }
+}
+
+/// Whether a certificate is valid at a time.
+fn validity(cert: &X509Certificate, now: i64) -> Result<String, String> {
+    let validity = cert.validity();
+    if now < validity.not_before.timestamp() {
+        return Err(format!("not valid until {}", validity.not_before));
+    }
+    if validity.not_after.timestamp() < now {
+        return Err(format!("expired on {}", validity.not_after));
+    }
+    Ok(format!("valid until {}", validity.not_after))
+}
+
+/// Whether the names of the leaf of a path are within the name constraints of the
+/// CAs above it.
+fn leaf_constraints(path: &[X509Certificate]) -> Result<String, String> {
+    let constrained = path[..path.len() - 1]
+        .iter()
+        .any(|ca| matches!(ca.name_constraints(), Ok(Some(_))));
+    let problems = name_constraints::check(path);
+    match (problems.is_empty(), constrained) {
+        (false, _) => Err(problems.join("; ")),
+        (true, true) => Ok(String::from(
+            "its names are within the name constraints above it",
+        )),
+        (true, false) => Ok(String::from("none, it is the leaf")),
+    }
+}
+
+/// Whether a certificate may issue the certificates below it on a path.
+fn constraints(cert: &X509Certificate, below: &[X509Certificate]) -> Result<String, String> {
+    let basic = match cert.basic_constraints() {
+        Ok(Some(basic)) if basic.value.ca => basic.value,
+        _ => return Err(String::from("it is not a CA, so cannot issue certificates")),
+    };
+    if let Ok(Some(usage)) = cert.key_usage() {
+        if !usage.value.key_cert_sign() {
+            return Err(String::from(
+                "its key usage does not allow signing certificates",
+            ));
+        }
+    }
+    let length = match basic.path_len_constraint {
+        Some(length) => length as usize,
+        None => return Ok(String::from("CA, with no limit on the path below it")),
+    };
+    // Self-issued certificates, such as key rollovers, don't count towards the limit
+    let cas = below[..below.len() - 1]
+        .iter()
+        .filter(|ca| ca.subject() != ca.issuer())
+        .count();
+    match cas <= length {
+        true => Ok(format!(
+            "CA, with {} of at most {} CAs below it",
+            cas, length
+        )),
+        false => Err(format!(
+            "its path length constraint allows {} CAs below it, but there are {}",
+            length, cas
+        )),
+    }
+}
+
+/// The SHA-256 fingerprint of a certificate, as colon separated hex.
+pub fn fingerprint(der: &[u8]) -> String {
+    let hex: Vec<String> = digest(&SHA256, der)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect();
+    hex.join(":")
+}
+
+fn parse(der: &[u8]) -> X509Certificate<'_> {
+    let (_, cert) = parse_x509_certificate(der).expect("certificates are parsed before use");
+    cert
+}
+
+impl TrustPath {
+    /// The trust anchor the path reaches, if it reaches one.
+    pub fn anchor(&self) -> Option<&Step> {
+        self.steps.first().filter(|_| self.anchored)
+    }
+
+    /// Checks whether the policies are valid for the leaf, if the path reaches an
+    /// anchor for them to be valid from.
+    pub fn require(&mut self, policies: &[String]) {
+        if !self.anchored {
+            return;
+        }
+        let certificates: Vec<X509Certificate> =
+            self.steps.iter().map(|step| parse(&step.der)).collect();
+        self.policies = policy::check(&certificates, policies);
+    }
+
+    /// The path in a line, to tell it apart from the others.
+    pub fn summary(&self) -> String {
+        let anchor = match self.steps.first() {
+            Some(root) => format!("{} from the {}", root.subject, root.source),
+            None => String::from("nothing"),
+        };
+        let trusted = match &self.problem {
+            Some(problem) => format!("not trusted, {}", problem),
+            None => String::from("trusted"),
+        };
+        format!(
+            "{} certificates to {}, expiring {}, {}",
+            self.steps.len(),
+            anchor,
+            self.expires,
+            trusted
+        )
+    }
+}
+
+impl fmt::Display for TrustPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.problem, self.steps.first()) {
+            (None, Some(root)) => writeln!(
+                f,
+                "Trust path: trusted, anchored by {} from the {}",
+                root.subject, root.source
+            )?,
+            (Some(problem), _) => writeln!(f, "Trust path: not trusted, {}", problem)?,
+            (None, None) => writeln!(f, "Trust path: none")?,
+        }
+        writeln!(f, "Valid for: {}", self.purposes)?;
+        if !self.purposes.problems.is_empty() {
+            writeln!(f, "Not valid for:")?;
+            for problem in &self.purposes.problems {
+                writeln!(f, "    {}", problem)?;
+            }
+        }
+        for check in &self.policies {
+            writeln!(f, "{}", check)?;
+        }
+        for step in &self.steps {
+            let accepted = if step.accepted { "" } else { " (not accepted)" };
+            writeln!(f, "    {}{}", step.subject, accepted)?;
+            writeln!(f, "        From: {}", step.source)?;
+            writeln!(f, "        SHA-256 fingerprint: {}", step.fingerprint)?;
+            writeln!(f, "        Signature: {}", step.signature)?;
+            writeln!(f, "        Validity: {}", step.validity)?;
+            writeln!(f, "        Constraints: {}", step.constraints)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::pem::Pem;
+
+    use super::{build, build_fetching, fingerprint, load, Anchor};
+    use crate::http::Response;
+    use crate::remote::ConnectOptions;
+    use crate::store::NamedCertificate;
+    use crate::test::FakeProcessor;
+
+    // Jun  1 2026, when the example chain is valid
+    const NOW: i64 = 1_780_272_000;
+
+    fn pem(bytes: &[u8], name: &str) -> Vec<NamedCertificate> {
+        Pem::iter_from_buffer(bytes)
+            .enumerate()
+            .map(|(i, pem)| (format!("{} [{}]", name, i + 1), pem.unwrap().contents))
+            .collect()
+    }
+
+    fn root() -> Vec<Anchor> {
+        pem(include_bytes!("../resources/example-root.crt"), "root")
+            .into_iter()
+            .map(|(_, der)| Anchor {
+                store: String::from("roots.pem"),
+                der,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_explain_the_path_to_the_anchor() {
+        let chain = pem(
+            include_bytes!("../resources/example-chain.pem"),
+            "chain.pem",
+        );
+
+        let paths = build(&chain[0], &chain[1..], &root(), NOW);
+        let path = &paths[0];
+
+        assert_eq!(paths.len(), 1);
+        assert!(path.problem.is_none());
+        assert_eq!(
+            path.to_string(),
+            "Trust path: trusted, anchored by CN=Example Root CA from the trust anchor in roots.pem\n\
+             Valid for: serverAuth\n    \
+             CN=Example Root CA\n        \
+             From: trust anchor in roots.pem\n        \
+             SHA-256 fingerprint: 49:43:51:23:A1:41:B7:8C:AE:F2:1C:9F:3D:B1:87:49:0C:A5:EF:AE:F9:BF:F0:4B:02:8F:0F:68:37:8E:BA:99\n        \
+             Signature: not checked, it is a trust anchor\n        \
+             Validity: valid until Jan  1 00:00:00 2036 +00:00\n        \
+             Constraints: CA, with no limit on the path below it\n    \
+             CN=Example Issuing CA\n        \
+             From: chain.pem [2]\n        \
+             SHA-256 fingerprint: 19:DE:4E:ED:3E:A8:B3:18:E7:65:94:12:B2:B1:E4:F2:4E:9A:D3:19:14:DC:3E:CE:65:12:A5:41:63:5F:9D:46\n        \
+             Signature: valid, from CN=Example Root CA with ecdsa-with-SHA256\n        \
+             Validity: valid until Jan  1 00:00:00 2036 +00:00\n        \
+             Constraints: CA, with 0 of at most 0 CAs below it\n    \
+             CN=www.example.com\n        \
+             From: chain.pem [1]\n        \
+             SHA-256 fingerprint: 3C:D0:AD:BA:AD:F8:F1:E8:89:07:EA:1A:AA:6B:53:EB:99:62:C7:36:F0:68:38:06:D4:8A:EF:A5:EE:9D:27:AE\n        \
+             Signature: valid, from CN=Example Issuing CA with ecdsa-with-SHA256\n        \
+             Validity: valid until Jan  1 00:00:00 2036 +00:00\n        \
+             Constraints: none, it is the leaf\n"
+        );
+    }
+
+    #[test]
+    fn should_say_where_the_path_stops() {
+        let chain = pem(
+            include_bytes!("../resources/example-chain.pem"),
+            "chain.pem",
+        );
+
+        let missing = build(&chain[0], &[], &root(), NOW).remove(0);
+        let untrusted = build(&chain[0], &chain[1..], &[], NOW).remove(0);
+
+        assert_eq!(
+            missing.problem.as_deref(),
+            Some("no issuer of CN=www.example.com was found among the certificates given or the trust anchors")
+        );
+        assert_eq!(missing.steps.len(), 1);
+        assert_eq!(
+            untrusted.problem.as_deref(),
+            Some("no issuer of CN=Example Issuing CA was found among the certificates given or the trust anchors")
+        );
+        assert_eq!(
+            untrusted.steps[0].signature,
+            "not checked, no issuer was found"
+        );
+    }
+
+    #[test]
+    fn should_not_accept_an_expired_path() {
+        let chain = pem(
+            include_bytes!("../resources/example-chain.pem"),
+            "chain.pem",
+        );
+
+        // Jan  1 2037, after the whole chain expired
+        let path = build(&chain[0], &chain[1..], &root(), 2_114_380_800).remove(0);
+
+        assert_eq!(
+            path.problem.as_deref(),
+            Some("CN=Example Root CA was not accepted, expired on Jan  1 00:00:00 2036 +00:00")
+        );
+        assert!(path.steps.iter().all(|step| !step.accepted));
+    }
+
+    #[test]
+    fn should_stop_at_a_self_signed_certificate() {
+        let self_signed = pem(
+            include_bytes!("../resources/self-signed.crt"),
+            "self-signed.crt",
+        );
+
+        let path = build(&self_signed[0], &[], &root(), NOW).remove(0);
+
+        assert_eq!(
+            path.problem.as_deref(),
+            Some("CN=self-signed.example is self-signed and not a trust anchor")
+        );
+    }
+
+    #[test]
+    fn should_put_the_paths_still_trusted_first() {
+        let chain = pem(include_bytes!("../resources/cross-chain.pem"), "chain.pem");
+        let roots: Vec<Anchor> = pem(include_bytes!("../resources/cross-roots.pem"), "roots")
+            .into_iter()
+            .map(|(_, der)| Anchor {
+                store: String::from("roots.pem"),
+                der,
+            })
+            .collect();
+
+        // Jun  1 2027, after the old root and its cross-signature expired
+        let paths = build(&chain[0], &chain[1..], &roots, 1_811_808_000);
+
+        assert_eq!(paths.len(), 2);
+        assert!(paths[0].problem.is_none());
+        assert_eq!(paths[0].expires, "Jan  1 00:00:00 2036 +00:00");
+        assert_eq!(paths[1].steps.len(), 4);
+        assert_eq!(
+            paths[1].problem.as_deref(),
+            Some("CN=Example Old Root CA was not accepted, expired on Jan  1 00:00:00 2027 +00:00")
+        );
+    }
+
+    #[test]
+    fn should_fetch_an_intermediate_left_out() {
+        let leaf = pem(include_bytes!("../resources/aia-leaf.crt"), "aia-leaf.crt");
+        let chain = pem(
+            include_bytes!("../resources/example-chain.pem"),
+            "chain.pem",
+        );
+        let mut processor = FakeProcessor::default();
+        processor.responses.insert(
+            String::from("http://ca.example.com/issuing.crt"),
+            Response {
+                status: 200,
+                headers: Vec::new(),
+                body: chain[1].1.clone(),
+            },
+        );
+        let connect = ConnectOptions::default();
+
+        let fetched = build_fetching(&processor, &connect, &leaf[0], &[], &root(), NOW);
+        let failed = build_fetching(
+            &FakeProcessor::default(),
+            &connect,
+            &leaf[0],
+            &[],
+            &root(),
+            NOW,
+        );
+
+        assert!(fetched[0].problem.is_none());
+        assert_eq!(
+            fetched[0].steps[1].source,
+            "CA issuers http://ca.example.com/issuing.crt"
+        );
+        assert_eq!(
+            fetched[0].anchor().unwrap().fingerprint,
+            fingerprint(&root()[0].der)
+        );
+        assert_eq!(
+            failed[0].problem.as_deref(),
+            Some("no issuer of CN=aia.example.com was found among the certificates given or the trust anchors, \
+                  fetching its issuer from http://ca.example.com/issuing.crt failed: could not connect to http://ca.example.com/issuing.crt")
+        );
+        assert!(failed[0].anchor().is_none());
+    }
+
+    #[test]
+    fn should_not_accept_a_leaf_outside_the_name_constraints() {
+        let chain = pem(
+            include_bytes!("../resources/constrained-chain.pem"),
+            "chain.pem",
+        );
+
+        let path = build(&chain[0], &chain[1..], &root(), NOW).remove(0);
+
+        assert_eq!(
+            path.problem.as_deref(),
+            Some("CN=constrained.example.com was not accepted, \
+                  DNS:admin.example.com is in the excluded subtree DNS:admin.example.com of CN=Example Constrained CA; \
+                  DNS:www.example.net is not in the permitted subtrees of CN=Example Constrained CA (DNS:example.com); \
+                  IP:192.168.1.1 is not in the permitted subtrees of CN=Example Constrained CA (IP:10.0.0.0/8)")
+        );
+        assert!(path.steps[1].accepted && !path.steps[2].accepted);
+    }
+
+    #[test]
+    fn should_load_anchors_from_the_system_bundle() {
+        let mut processor = FakeProcessor::default();
+        processor.binaries.insert(
+            String::from("/etc/ssl/cert.pem"),
+            include_bytes!("../resources/example-root.crt").to_vec(),
+        );
+
+        let anchors = load(&processor, &[]).unwrap();
+        let missing = load(&FakeProcessor::default(), &[]).err().unwrap();
+
+        assert_eq!(anchors.len(), 1);
+        assert_eq!(anchors[0].store, "/etc/ssl/cert.pem");
+        assert_eq!(
+            missing.to_string(),
+            "Error: could not find the system CA bundle, give trust anchors with --trust-anchors."
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::Duration;
+
+use x509_parser::der_parser::oid::Oid;
+use x509_parser::oid_registry::{
+    OID_EC_P256, OID_NIST_EC_P384, OID_NIST_EC_P521, OID_SIG_ED25519, OID_SIG_ED448,
+};
+use x509_parser::public_key::PublicKey;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+use crate::oid;
+use crate::report::Report;
+use crate::sm2;
+use crate::FileProcessor;
+
+/// Where distributions keep their combined CA bundle, most common first.
+pub const BUNDLES: &[&str] = &[
+    // Debian, Ubuntu, Arch, Gentoo
+    "/etc/ssl/certs/ca-certificates.crt",
+    // Fedora, RHEL, CentOS
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/pki/ca-trust/extracted/pem/tls-ca-bundle.pem",
+    // openSUSE
+    "/etc/ssl/ca-bundle.pem",
+    // Alpine, BSDs
+    "/etc/ssl/cert.pem",
+];
+
+/// Signature algorithms that can no longer be relied on: MD2, MD5 and SHA-1 based.
+pub const WEAK_SIGNATURES: &[&str] = &[
+    "1.2.840.113549.1.1.2",
+    "1.2.840.113549.1.1.4",
+    "1.2.840.113549.1.1.5",
+    "1.2.840.10045.4.1",
+    "1.2.840.10040.4.3",
+    "1.3.14.3.2.25",
+    "1.3.14.3.2.29",
+];
+
+/// What `cert-decoder trust-store` should audit.
+#[derive(Debug, PartialEq)]
+pub struct TrustStoreOptions {
+    /// The CA bundle to audit instead of the one found on the system, from `--bundle`.
+    pub bundle: Option<String>,
+    /// Roots expiring within this long are called out, from `--expiring-within`.
+    pub expiring_within: Duration,
+}
+
+impl Default for TrustStoreOptions {
+    fn default() -> TrustStoreOptions {
+        TrustStoreOptions {
+            bundle: None,
+            expiring_within: Duration::from_secs(365 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// Finds the CA bundle to audit.
+pub fn locate(
+    processor: &impl FileProcessor,
+    options: &TrustStoreOptions,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if let Some(bundle) = &options.bundle {
+        return Ok(bundle.clone());
+    }
+    BUNDLES
+        .iter()
+        .find(|bundle| processor.is_file(bundle))
+        .map(|bundle| String::from(*bundle))
+        .ok_or_else(|| {
+            String::from("Error: could not find the system CA bundle, give its path with --bundle.")
+                .into()
+        })
+}
+
+/// A summary of the roots in a trust store, printed instead of every decoded root.
+pub struct Summary<'a> {
+    report: &'a Report,
+    now: i64,
+    expiring_within: Duration,
+}
+
+impl<'a> Summary<'a> {
+    pub fn new(report: &'a Report, now: i64, options: &TrustStoreOptions) -> Summary<'a> {
+        Summary {
+            report,
+            now,
+            expiring_within: options.expiring_within,
+        }
+    }
+}
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let certificates: Vec<_> = self
+            .report
+            .entries
+            .iter()
+            .map(|entry| entry.certificate())
+            .collect();
+        let horizon = self.now + self.expiring_within.as_secs() as i64;
+
+        let mut key_types = BTreeMap::new();
+        let mut expired = Vec::new();
+        let mut expiring = Vec::new();
+        let mut weak = Vec::new();
+        for cert in &certificates {
+            *key_types.entry(key_type(cert.public_key())).or_insert(0) += 1;
+
+            let not_after = cert.validity().not_after;
+            let line = format!("{} ({})", cert.subject(), not_after);
+            if not_after.timestamp() < self.now {
+                expired.push(line);
+            } else if not_after.timestamp() < horizon {
+                expiring.push(line);
+            }
+
+            let algorithm = &cert.signature_algorithm.algorithm;
+            if WEAK_SIGNATURES.contains(&algorithm.to_id_string().as_str()) {
+                weak.push(format!("{} ({})", cert.subject(), oid::name(algorithm)));
+            }
+        }
+
+        writeln!(f, "Roots: {}", certificates.len())?;
+        writeln!(f, "Key types:")?;
+        for (key_type, count) in &key_types {
+            writeln!(f, "    {}: {}", key_type, count)?;
+        }
+        let days = self.expiring_within.as_secs() / (24 * 60 * 60);
+        list(f, "Expired", &expired)?;
+        list(f, &format!("Expiring within {} days", days), &expiring)?;
+        list(f, "Weak signatures", &weak)?;
+
+        for failure in &self.report.failures {
+            writeln!(f, "==> {} <==", failure.location)?;
+            writeln!(f, "{}", failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a heading and its indented lines, or `none` if there aren't any.
+pub fn list(f: &mut fmt::Formatter, heading: &str, lines: &[String]) -> fmt::Result {
+    if lines.is_empty() {
+        return writeln!(f, "{}: none", heading);
+    }
+    writeln!(f, "{}:", heading)?;
+    for line in lines {
+        writeln!(f, "    {}", line)?;
+    }
+    Ok(())
+}
+
+/// What a public key is, for checks that judge keys rather than show them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyInfo {
+    pub kind: KeyKind,
+    /// The size of the modulus, for RSA keys.
+    pub bits: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyKind {
+    Rsa,
+    /// An elliptic curve key, on its named curve if it has one.
+    Ec(Option<Curve>),
+    Dsa,
+    Ed25519,
+    Ed448,
+    /// Any other algorithm, by name.
+    Other(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Curve {
+    P256,
+    P384,
+    P521,
+    Sm2,
+    /// Any other curve, by OID.
+    Other(String),
+}
+
+impl fmt::Display for KeyInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(bits) = self.bits {
+            write!(f, " {}", bits)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyKind::Rsa => write!(f, "RSA"),
+            KeyKind::Ec(Some(curve)) => write!(f, "EC {}", curve),
+            KeyKind::Ec(None) => write!(f, "EC"),
+            KeyKind::Dsa => write!(f, "DSA"),
+            KeyKind::Ed25519 => write!(f, "Ed25519"),
+            KeyKind::Ed448 => write!(f, "Ed448"),
+            KeyKind::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Curve::P256 => write!(f, "P-256"),
+            Curve::P384 => write!(f, "P-384"),
+            Curve::P521 => write!(f, "P-521"),
+            Curve::Sm2 => write!(f, "SM2"),
+            Curve::Other(oid) => write!(f, "{}", oid),
+        }
+    }
+}
+
+/// Describes a key, e.g. `RSA 2048` or `EC P-256`.
+pub fn key_type(spki: &SubjectPublicKeyInfo) -> String {
+    key_info(spki).to_string()
+}
+
+pub fn key_info(spki: &SubjectPublicKeyInfo) -> KeyInfo {
+    let mut bits = None;
+    let kind = match spki.parsed() {
+        Ok(PublicKey::RSA(rsa)) => {
+            // Leading zero bytes only keep the modulus positive, they aren't part of the key
+            let modulus = rsa.modulus;
+            let start = modulus
+                .iter()
+                .position(|b| *b != 0)
+                .unwrap_or(modulus.len());
+            bits = Some(match modulus.get(start) {
+                Some(first) => (modulus.len() - start) * 8 - first.leading_zeros() as usize,
+                None => 0,
+            });
+            KeyKind::Rsa
+        }
+        Ok(PublicKey::EC(_)) => {
+            let curve = spki
+                .algorithm
+                .parameters
+                .as_ref()
+                .and_then(|parameters| Oid::try_from(parameters).ok());
+            KeyKind::Ec(curve.map(|curve| match curve {
+                curve if curve == OID_EC_P256 => Curve::P256,
+                curve if curve == OID_NIST_EC_P384 => Curve::P384,
+                curve if curve == OID_NIST_EC_P521 => Curve::P521,
+                curve if curve.to_id_string() == sm2::SM2 => Curve::Sm2,
+                curve => Curve::Other(curve.to_string()),
+            }))
+        }
+        Ok(PublicKey::DSA(_)) => KeyKind::Dsa,
+        _ if spki.algorithm.algorithm == OID_SIG_ED25519 => KeyKind::Ed25519,
+        _ if spki.algorithm.algorithm == OID_SIG_ED448 => KeyKind::Ed448,
+        _ => KeyKind::Other(oid::name(&spki.algorithm.algorithm)),
+    };
+    KeyInfo { kind, bits }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{locate, Summary, TrustStoreOptions};
+    use crate::options::Options;
+    use crate::report::Report;
+    use crate::target::Target;
+    use crate::test::FakeProcessor;
+
+    // 2020-01-01, a year before the google.com certificate expired
+    const NOW: i64 = 1_577_836_800;
+
+    fn bundle() -> FakeProcessor {
+        let mut processor = FakeProcessor::default();
+        let roots = [
+            include_str!("../resources/google.com.crt"),
+            include_str!("../resources/self-signed.crt"),
+            include_str!("../resources/sha1-root.crt"),
+        ];
+        processor.add_file("/etc/ssl/cert.pem", &roots.concat());
+        processor
+    }
+
+    #[test]
+    fn should_find_the_first_bundle_that_exists() {
+        let processor = bundle();
+
+        let found = locate(&processor, &TrustStoreOptions::default()).unwrap();
+        let missing = locate(&FakeProcessor::default(), &TrustStoreOptions::default());
+
+        assert_eq!(found, "/etc/ssl/cert.pem");
+        assert_eq!(
+            format!("{}", missing.err().unwrap()),
+            "Error: could not find the system CA bundle, give its path with --bundle."
+        );
+    }
+
+    #[test]
+    fn should_summarise_key_types_expiry_and_weak_signatures() {
+        let processor = bundle();
+        let targets = vec![Target::Path(String::from("/etc/ssl/cert.pem"))];
+        let report = Report::build(&processor, &targets, &Options::default());
+
+        let summary = Summary::new(&report, NOW, &TrustStoreOptions::default()).to_string();
+
+        assert_eq!(
+            summary,
+            "Roots: 3\n\
+             Key types:\n    EC P-256: 2\n    RSA 2048: 1\n\
+             Expired: none\n\
+             Expiring within 365 days:\n    C=US, ST=California, L=Mountain View, O=Google LLC, CN=*.google.com (Sep  2 09:34:58 2020 +00:00)\n\
+             Weak signatures:\n    CN=SHA-1 Root (sha1WithRSAEncryption)\n"
+        );
+    }
+}
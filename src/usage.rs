@@ -0,0 +1,210 @@
+use std::fmt;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{ExtendedKeyUsage, KeyUsage};
+use x509_parser::objects::{oid2sn, oid_registry};
+
+/// The key usages RFC 5280 says are consistent with each extended key usage.
+const CONSISTENT: &[(&str, &[&str])] = &[
+    (
+        "serverAuth",
+        &["digitalSignature", "keyEncipherment", "keyAgreement"],
+    ),
+    ("clientAuth", &["digitalSignature", "keyAgreement"]),
+    ("codeSigning", &["digitalSignature"]),
+    (
+        "emailProtection",
+        &[
+            "digitalSignature",
+            "nonRepudiation",
+            "keyEncipherment",
+            "keyAgreement",
+        ],
+    ),
+    ("timeStamping", &["digitalSignature", "nonRepudiation"]),
+    ("OCSPSigning", &["digitalSignature", "nonRepudiation"]),
+];
+
+/// What the leaf of a path can be used for, once every CA above it has had its say.
+#[derive(Debug, PartialEq)]
+pub struct Purposes {
+    /// The extended key usages the leaf is valid for, or `None` for any.
+    pub valid: Option<Vec<String>>,
+    /// The purposes the leaf claims but isn't valid for, and why.
+    pub problems: Vec<String>,
+}
+
+/// Works out the purposes the leaf of a path, given root first, is valid for. A CA
+/// with an extended key usage extension limits every certificate below it to those
+/// purposes, as browsers and Windows enforce, and the leaf's key usage has to allow
+/// what each purpose needs the key to do.
+pub fn purposes(path: &[X509Certificate]) -> Purposes {
+    let (leaf, cas) = match path.split_last() {
+        Some(split) => split,
+        None => {
+            return Purposes {
+                valid: None,
+                problems: Vec::new(),
+            }
+        }
+    };
+
+    let mut problems = Vec::new();
+    let mut valid = extended_key_usages(leaf);
+    // The CA closest to the leaf is the one to name when several exclude a purpose
+    for ca in cas.iter().rev() {
+        let allowed = match extended_key_usages(ca) {
+            Some(allowed) => allowed,
+            None => continue,
+        };
+        valid = Some(match valid {
+            None => allowed.clone(),
+            Some(purposes) => {
+                let (kept, excluded): (Vec<String>, Vec<String>) = purposes
+                    .into_iter()
+                    .partition(|purpose| allowed.contains(purpose));
+                problems.extend(excluded.iter().map(|purpose| {
+                    format!(
+                        "{}, {} only allows {}",
+                        purpose,
+                        ca.subject(),
+                        allowed.join(", ")
+                    )
+                }));
+                kept
+            }
+        });
+    }
+
+    if let (Some(purposes), Ok(Some(usage))) = (valid.as_mut(), leaf.key_usage()) {
+        let usages = key_usages(usage.value);
+        purposes.retain(|purpose| {
+            let needed = match CONSISTENT.iter().find(|(name, _)| name == purpose) {
+                Some((_, needed)) => needed,
+                None => return true,
+            };
+            if needed.iter().any(|usage| usages.contains(usage)) {
+                return true;
+            }
+            problems.push(format!(
+                "{}, it needs the {} key usage",
+                purpose,
+                needed.join(" or ")
+            ));
+            false
+        });
+    }
+    Purposes { valid, problems }
+}
+
+/// The extended key usages of a certificate, or `None` if it has no such
+/// extension or allows any purpose.
+fn extended_key_usages(cert: &X509Certificate) -> Option<Vec<String>> {
+    let extended = match cert.extended_key_usage() {
+        Ok(Some(extended)) if !extended.value.any => extended.value,
+        _ => return None,
+    };
+    Some(names(extended))
+}
+
+pub fn names(extended: &ExtendedKeyUsage) -> Vec<String> {
+    let known = [
+        (extended.server_auth, "serverAuth"),
+        (extended.client_auth, "clientAuth"),
+        (extended.code_signing, "codeSigning"),
+        (extended.email_protection, "emailProtection"),
+        (extended.time_stamping, "timeStamping"),
+        (extended.ocsp_signing, "OCSPSigning"),
+    ];
+    let mut names: Vec<String> = known
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| String::from(*name))
+        .collect();
+    names.extend(extended.other.iter().map(|oid| {
+        oid2sn(oid, oid_registry())
+            .map(String::from)
+            .unwrap_or_else(|_| oid.to_id_string())
+    }));
+    names
+}
+
+fn key_usages(usage: &KeyUsage) -> Vec<&'static str> {
+    let flags = [
+        (usage.digital_signature(), "digitalSignature"),
+        (usage.non_repudiation(), "nonRepudiation"),
+        (usage.key_encipherment(), "keyEncipherment"),
+        (usage.data_encipherment(), "dataEncipherment"),
+        (usage.key_agreement(), "keyAgreement"),
+        (usage.key_cert_sign(), "keyCertSign"),
+        (usage.crl_sign(), "cRLSign"),
+    ];
+    flags
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+impl fmt::Display for Purposes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.valid {
+            None => write!(f, "any purpose"),
+            Some(purposes) if purposes.is_empty() => write!(f, "nothing"),
+            Some(purposes) => write!(f, "{}", purposes.join(", ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use x509_parser::parse_x509_certificate;
+    use x509_parser::pem::Pem;
+
+    use super::{purposes, Purposes};
+
+    fn ders(bytes: &[u8]) -> Vec<Vec<u8>> {
+        Pem::iter_from_buffer(bytes)
+            .map(|pem| pem.unwrap().contents)
+            .collect()
+    }
+
+    #[test]
+    fn should_narrow_the_purposes_to_what_the_cas_allow() {
+        let mut chain = ders(include_bytes!("../resources/restricted-chain.pem"));
+        chain.extend(ders(include_bytes!("../resources/example-root.crt")));
+        chain.reverse();
+        let path: Vec<_> = chain
+            .iter()
+            .map(|der| parse_x509_certificate(der).unwrap().1)
+            .collect();
+
+        assert_eq!(
+            purposes(&path),
+            Purposes {
+                valid: Some(vec![String::from("serverAuth")]),
+                problems: vec![
+                    String::from(
+                        "clientAuth, CN=Example Restricted CA only allows serverAuth, codeSigning"
+                    ),
+                    String::from("codeSigning, it needs the digitalSignature key usage"),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn should_allow_any_purpose_without_extended_key_usages() {
+        let root = ders(include_bytes!("../resources/example-root.crt"));
+        let (_, root) = parse_x509_certificate(&root[0]).unwrap();
+        let leaf = ders(include_bytes!("../resources/example-chain.pem"));
+        let (_, leaf) = parse_x509_certificate(&leaf[0]).unwrap();
+
+        assert_eq!(purposes(&[root]).valid, None);
+        assert_eq!(
+            purposes(&[leaf]).valid,
+            Some(vec![String::from("serverAuth")])
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use std::fmt;
+
+use serde_yaml::Value;
+use x509_parser::pem::Pem;
+use x509_parser::time::ASN1Time;
+
+use crate::crl;
+use crate::http::{Request, Response, Trust, Url};
+use crate::remote::ConnectOptions;
+use crate::report::Report;
+use crate::store::NamedCertificate;
+use crate::FileProcessor;
+
+/// Where `vault secrets enable pki` mounts the secrets engine unless told otherwise.
+const DEFAULT_MOUNT: &str = "pki";
+
+/// What to fetch from a Vault PKI secrets engine.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum VaultCommand {
+    #[default]
+    Ca,
+    Crl,
+    Cert,
+}
+
+/// What `cert-decoder vault ca`, `vault crl` or `vault cert` should fetch.
+#[derive(Debug, Default, PartialEq)]
+pub struct VaultOptions {
+    pub command: VaultCommand,
+    /// Where the PKI secrets engine is mounted, from `--mount`.
+    pub mount: Option<String>,
+    /// The serial numbers of the certificates to fetch, for `vault cert`.
+    pub serials: Vec<String>,
+}
+
+/// What was fetched from a PKI mount.
+pub struct Vault {
+    /// The mount's API URL.
+    pub url: String,
+    /// Each certificate fetched by serial number, and when it was revoked if it was.
+    pub revocations: Vec<(String, Option<i64>)>,
+    pub report: Report,
+}
+
+/// Talks to Vault as its CLI does, configured by the same environment variables.
+struct Client<'a, P: FileProcessor> {
+    processor: &'a P,
+    connect: &'a ConnectOptions,
+    mount: Url,
+    token: Option<String>,
+    namespace: Option<String>,
+    trust: Trust,
+}
+
+impl<P: FileProcessor> Client<'_, P> {
+    fn get(&self, path: &str) -> Result<Response, Box<dyn std::error::Error>> {
+        let mut url = self.mount.clone();
+        url.path = format!("{}{}", self.mount.path, path);
+        let mut request = Request::get(url.clone());
+        if let Some(token) = &self.token {
+            request = request.header("X-Vault-Token", token);
+        }
+        if let Some(namespace) = &self.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        request.trust = self.trust.clone();
+        self.processor.http(&request, self.connect)?.success(&url)
+    }
+}
+
+/// Fetches a PKI mount's CA chain, its CRL, or certificates it issued by
+/// serial number, from the Vault server in VAULT_ADDR. VAULT_TOKEN,
+/// VAULT_NAMESPACE, VAULT_CACERT and VAULT_SKIP_VERIFY are honoured as the
+/// vault CLI does, though the CA chain and CRL need no token.
+pub fn fetch(
+    processor: &impl FileProcessor,
+    options: &VaultOptions,
+    connect: &ConnectOptions,
+) -> Result<Vault, Box<dyn std::error::Error>> {
+    let address = processor.var("VAULT_ADDR").ok_or(
+        "Error: VAULT_ADDR is not set, it should be the Vault server's address, such as https://vault.example.com:8200.",
+    )?;
+    let mut mount = Url::parse(&address)?;
+    let name = options.mount.as_deref().unwrap_or(DEFAULT_MOUNT);
+    mount.path = format!(
+        "{}/v1/{}",
+        mount.path.trim_end_matches('/'),
+        name.trim_matches('/')
+    );
+    let skip_verify = processor
+        .var("VAULT_SKIP_VERIFY")
+        .is_some_and(|skip| matches!(skip.to_lowercase().as_str(), "1" | "true"));
+    let trust = match processor.var("VAULT_CACERT") {
+        _ if skip_verify => Trust::Insecure,
+        Some(path) => Trust::Roots(certificates(&processor.read(&path)?)),
+        None => Trust::System,
+    };
+    let client = Client {
+        processor,
+        connect,
+        mount,
+        token: processor.var("VAULT_TOKEN"),
+        namespace: processor.var("VAULT_NAMESPACE"),
+        trust,
+    };
+
+    let url = client.mount.to_string();
+    let mut report = Report::default();
+    let mut revocations = Vec::new();
+    match options.command {
+        VaultCommand::Ca => report.add_named(&url, ca_chain(&client)),
+        VaultCommand::Crl => {
+            // The CRL is DER, unless the mount was set up to hand out PEM
+            let body = client.get("/crl")?.body;
+            let der = crl::find(&body)
+                .into_iter()
+                .next()
+                .ok_or_else(|| format!("Error: {}/crl did not answer with a CRL.", url))?;
+            report
+                .crls
+                .push(crl::read(format!("{}/crl", url), &der, &[])?);
+        }
+        VaultCommand::Cert => {
+            for serial in &options.serials {
+                match cert(&client, serial) {
+                    Ok(issued) => {
+                        let items = vec![(format!("cert/{}", issued.serial), issued.der)];
+                        report.add_named(&url, Ok(items));
+                        revocations.push((issued.serial, issued.revoked));
+                    }
+                    Err(error) => {
+                        report.add_named(&format!("{} serial {}", url, serial), Err(error))
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(Vault {
+        url,
+        revocations,
+        report,
+    })
+}
+
+fn certificates(pem: &[u8]) -> Vec<Vec<u8>> {
+    Pem::iter_from_buffer(pem)
+        .filter_map(Result::ok)
+        .filter(|block| block.label == "CERTIFICATE")
+        .map(|block| block.contents)
+        .collect()
+}
+
+/// The mount's issuer and the CAs above it. A mount whose issuer was
+/// imported without its parents, or set up before Vault kept chains, only
+/// has the issuer itself to give.
+fn ca_chain<P: FileProcessor>(
+    client: &Client<P>,
+) -> Result<Vec<NamedCertificate>, Box<dyn std::error::Error>> {
+    let mut chain = certificates(&client.get("/ca_chain")?.body);
+    let mut name = "ca_chain";
+    if chain.is_empty() {
+        chain = certificates(&client.get("/ca/pem")?.body);
+        name = "ca";
+    }
+    if chain.is_empty() {
+        return Err(format!(
+            "Error: {} has no CA certificate, the mount has no root or intermediate yet.",
+            client.mount
+        )
+        .into());
+    }
+    let count = chain.len();
+    Ok(chain
+        .into_iter()
+        .enumerate()
+        .map(|(i, der)| (format!("{} [{}/{}]", name, i + 1, count), der))
+        .collect())
+}
+
+/// A certificate the mount issued.
+struct Issued {
+    /// Its serial number, in the form Vault writes it.
+    serial: String,
+    der: Vec<u8>,
+    /// When it was revoked, if it was.
+    revoked: Option<i64>,
+}
+
+/// Fetches a certificate the mount issued by its serial number, given in any
+/// of the usual forms and asked for as the hyphenated hex Vault writes.
+fn cert<P: FileProcessor>(
+    client: &Client<P>,
+    serial: &str,
+) -> Result<Issued, Box<dyn std::error::Error>> {
+    let bytes = crl::parse_serial(serial)
+        .ok_or_else(|| format!("Error: {} is not a serial number.", serial))?;
+    let serial: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let serial = serial.join("-");
+
+    let path = format!("/cert/{}", serial);
+    let response: Value = serde_yaml::from_slice(&client.get(&path)?.body).map_err(|error| {
+        format!(
+            "Error: {}{} did not answer with JSON: {}",
+            client.mount, path, error
+        )
+    })?;
+    let data = &response["data"];
+    let der = data["certificate"]
+        .as_str()
+        .map(|pem| certificates(pem.as_bytes()))
+        .and_then(|certificates| certificates.into_iter().next())
+        .ok_or_else(|| {
+            format!(
+                "Error: Vault has no certificate with serial number {}.",
+                serial
+            )
+        })?;
+    let revoked = data["revocation_time"].as_i64().filter(|time| *time > 0);
+    Ok(Issued {
+        serial,
+        der,
+        revoked,
+    })
+}
+
+impl fmt::Display for Vault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Vault PKI mount: {}", self.url)?;
+        for (serial, revoked) in &self.revocations {
+            match revoked {
+                Some(at) => {
+                    let at = ASN1Time::from_timestamp(*at)
+                        .map(|at| at.to_string())
+                        .unwrap_or_default();
+                    writeln!(f, "Serial {}: revoked {}", serial, at)?
+                }
+                None => writeln!(f, "Serial {}: not revoked", serial)?,
+            }
+        }
+        write!(f, "{}", self.report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::collections::HashMap;
+
+    use super::{fetch, VaultCommand, VaultOptions};
+    use crate::http::Response;
+    use crate::remote::ConnectOptions;
+    use crate::test::FakeProcessor;
+
+    const MOUNT: &str = "https://vault.example.com:8200/v1/pki_int";
+
+    fn ok(body: &[u8]) -> Response {
+        Response {
+            status: 200,
+            headers: Vec::new(),
+            body: body.to_vec(),
+        }
+    }
+
+    fn vault() -> FakeProcessor {
+        FakeProcessor {
+            vars: HashMap::from([
+                (
+                    String::from("VAULT_ADDR"),
+                    String::from("https://vault.example.com:8200"),
+                ),
+                (String::from("VAULT_TOKEN"), String::from("hvs.token")),
+            ]),
+            ..FakeProcessor::default()
+        }
+    }
+
+    fn options(command: VaultCommand, serials: &[&str]) -> VaultOptions {
+        VaultOptions {
+            command,
+            mount: Some(String::from("pki_int")),
+            serials: serials.iter().map(|serial| String::from(*serial)).collect(),
+        }
+    }
+
+    #[test]
+    fn should_decode_the_ca_chain_falling_back_to_the_issuer() {
+        let mut processor = vault();
+        processor
+            .responses
+            .insert(format!("{}/ca_chain", MOUNT), ok(b""));
+        processor.responses.insert(
+            format!("{}/ca/pem", MOUNT),
+            ok(include_bytes!("../resources/self-signed.crt")),
+        );
+
+        let vault = fetch(
+            &processor,
+            &options(VaultCommand::Ca, &[]),
+            &ConnectOptions::default(),
+        )
+        .unwrap();
+        let output = vault.to_string();
+        assert!(output.starts_with("Vault PKI mount: https://vault.example.com:8200/v1/pki_int\n"));
+        assert!(output.contains("Subject: CN=self-signed.example"));
+        assert_eq!(
+            vault.report.entries[0].location,
+            "https://vault.example.com:8200/v1/pki_int: ca [1/1]"
+        );
+    }
+
+    #[test]
+    fn should_decode_the_crl() {
+        let mut processor = vault();
+        processor.responses.insert(
+            format!("{}/crl", MOUNT),
+            ok(include_bytes!("../resources/base.crl")),
+        );
+
+        let vault = fetch(
+            &processor,
+            &options(VaultCommand::Crl, &[]),
+            &ConnectOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(vault.report.crls.len(), 1);
+        assert_eq!(
+            vault.report.crls[0].location,
+            "https://vault.example.com:8200/v1/pki_int/crl"
+        );
+    }
+
+    #[test]
+    fn should_fetch_certificates_by_serial_and_say_if_they_were_revoked() {
+        let mut processor = vault();
+        let pem = String::from_utf8(include_bytes!("../resources/self-signed.crt").to_vec())
+            .unwrap()
+            .replace('\n', "\\n");
+        processor.responses.insert(
+            format!("{}/cert/0a-1b-2c", MOUNT),
+            ok(format!(
+                "{{\"data\":{{\"certificate\":\"{}\",\"revocation_time\":1791936000}}}}",
+                pem
+            )
+            .as_bytes()),
+        );
+
+        processor.responses.insert(
+            format!("{}/cert/ff-ff", MOUNT),
+            Response {
+                status: 404,
+                headers: Vec::new(),
+                body: b"{\"errors\":[]}".to_vec(),
+            },
+        );
+
+        let vault = fetch(
+            &processor,
+            &options(VaultCommand::Cert, &["0A:1B:2C", "ff-ff"]),
+            &ConnectOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            vault.revocations,
+            vec![(String::from("0a-1b-2c"), Some(1791936000))]
+        );
+        let output = vault.to_string();
+        assert!(output.contains("Serial 0a-1b-2c: revoked Oct 14 00:00:00 2026 +00:00\n"));
+        assert!(output.contains("==> https://vault.example.com:8200/v1/pki_int: cert/0a-1b-2c <=="));
+        assert_eq!(
+            vault.report.failures[0].location,
+            "https://vault.example.com:8200/v1/pki_int serial ff-ff"
+        );
+        assert_eq!(
+            vault.report.failures[0].error.to_string(),
+            "Error: https://vault.example.com:8200/v1/pki_int/cert/ff-ff answered with HTTP status 404"
+        );
+    }
+
+    #[test]
+    fn should_need_vault_addr() {
+        let processor = FakeProcessor::default();
+        assert_eq!(
+            fetch(
+                &processor,
+                &options(VaultCommand::Ca, &[]),
+                &ConnectOptions::default()
+            )
+            .err()
+            .unwrap()
+            .to_string(),
+            "Error: VAULT_ADDR is not set, it should be the Vault server's address, such as https://vault.example.com:8200."
+        );
+    }
+}